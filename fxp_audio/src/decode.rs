@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::Path;
+use std::process::Command as StdCommand;
+use tracing::info_span;
+
+use fxp_output::ToolError;
+
+use crate::error::AudioError;
+
+/// Samples per second the audio is decoded at, plenty for loudness and beats.
+pub const SAMPLE_RATE: u32 = 8000;
+
+/// Decodes audio into mono 16-bit samples at `SAMPLE_RATE` with ffmpeg.
+///
+/// # Parameters
+/// - `audio`: Any audio, or video with sound, that ffmpeg reads.
+///
+/// # Returns
+/// - `Result<Vec<i16>>`: The samples, or an error if ffmpeg fails to decode the audio.
+pub fn decode_samples(audio: &Path) -> Result<Vec<i16>> {
+    let _span = info_span!("decode audio", audio = %audio.display()).entered();
+    let output = StdCommand::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(audio)
+        .args([
+            "-ac",
+            "1",
+            "-ar",
+            &SAMPLE_RATE.to_string(),
+            "-f",
+            "s16le",
+            "-",
+        ])
+        .output()
+        .map_err(|e| AudioError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to decode the audio")?;
+    if !output.status.success() {
+        return Err(AudioError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+        .with_context(|| format!("Failed to decode {}", audio.display()));
+    }
+    let samples: Vec<i16> = output
+        .stdout
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    debug!("Decoded {} samples of {}", samples.len(), audio.display());
+    Ok(samples)
+}
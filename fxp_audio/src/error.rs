@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of decoding audio that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum AudioError {
+    /// A tool is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+}
+
+impl AudioError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            AudioError::ToolMissing(_) => FailureKind::ToolMissing,
+            AudioError::ToolFailed { .. } => FailureKind::ToolFailed,
+        }
+    }
+}
+
+impl ToolError for AudioError {
+    fn missing(tool: &str) -> Self {
+        AudioError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        AudioError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
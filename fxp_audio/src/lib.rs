@@ -0,0 +1,11 @@
+mod decode;
+mod error;
+mod loudness;
+mod onset;
+mod silence;
+
+pub use decode::{decode_samples, SAMPLE_RATE};
+pub use error::AudioError;
+pub use loudness::{FrameLoudness, Loudness};
+pub use onset::find_onset;
+pub use silence::leading_silence;
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Serialize, Serializer};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fxp_output::FrameRate;
+
+use crate::decode::{decode_samples, SAMPLE_RATE};
+
+/// How far above the energy of the second before a frame must be to count as a beat.
+const BEAT_SENSITIVITY: f64 = 1.4;
+
+/// RMS below which a frame is too quiet to be a beat, however sudden.
+const BEAT_FLOOR: f32 = 0.02;
+
+/// Loudness of the audio a frame spans.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FrameLoudness {
+    /// Frame number, from 1 as the Exporter numbers frames.
+    pub frame: u32,
+    /// Start of the frame in the audio, in milliseconds.
+    pub time_ms: u64,
+    /// Root mean square of the samples, from 0 for silence to 1 for full scale.
+    pub rms: f32,
+    /// Largest absolute sample, from 0 to 1 of full scale.
+    pub peak: f32,
+    /// Whether a beat starts on the frame.
+    pub beat: bool,
+}
+
+/// Per-frame loudness of an audio track, written as JSON for audio-driven effects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Loudness {
+    /// The audio analyzed.
+    pub audio: PathBuf,
+    /// Frame rate the audio was divided at.
+    #[serde(serialize_with = "serialize_display")]
+    pub fps: FrameRate,
+    /// One entry per frame, up to the end of the audio.
+    pub frames: Vec<FrameLoudness>,
+}
+
+impl Loudness {
+    /// Decodes `audio` and measures the loudness of each frame at `fps`.
+    ///
+    /// # Parameters
+    /// - `audio`: Any audio, or video with sound, that ffmpeg reads.
+    /// - `fps`: Frame rate the frames play at.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The loudness of every frame the audio spans, or an error if ffmpeg
+    ///   cannot decode the audio.
+    ///
+    /// # Notes
+    /// - A frame is a beat when its energy rises above `BEAT_SENSITIVITY` times the mean
+    ///   energy of the second before it and its RMS is above `BEAT_FLOOR`; the frame right
+    ///   after a beat never is one, so a single onset is not counted twice.
+    pub fn analyze(audio: &Path, fps: FrameRate) -> Result<Self> {
+        let samples = decode_samples(audio)?;
+        let loudness = Self::from_samples(audio, &samples, fps);
+        debug!(
+            "Measured {} frames of {} with {} beats",
+            loudness.frames.len(),
+            audio.display(),
+            loudness.beats()
+        );
+        Ok(loudness)
+    }
+
+    fn from_samples(audio: &Path, samples: &[i16], fps: FrameRate) -> Self {
+        let numerator = fps.numerator() as u64;
+        let denominator = fps.denominator() as u64;
+        let rate = SAMPLE_RATE as u64;
+        // First sample of frame `index`, rounded to the nearest one.
+        let start = |index: u64| -> usize {
+            ((index * rate * denominator + numerator / 2) / numerator) as usize
+        };
+        // Every frame that starts before the last sample, the last one possibly partial.
+        let count = (samples.len() as u64 * numerator).div_ceil(rate * denominator);
+
+        let mut frames: Vec<FrameLoudness> = (0..count)
+            .map(|index| {
+                let from = start(index).min(samples.len());
+                let to = start(index + 1).min(samples.len());
+                let window = &samples[from..to];
+                let (rms, peak) = if window.is_empty() {
+                    (0.0, 0.0)
+                } else {
+                    let power: f64 = window
+                        .iter()
+                        .map(|&sample| (sample as f64 / i16::MAX as f64).powi(2))
+                        .sum::<f64>()
+                        / window.len() as f64;
+                    let peak = window
+                        .iter()
+                        .map(|&sample| sample.unsigned_abs())
+                        .max()
+                        .unwrap_or(0);
+                    (
+                        power.sqrt() as f32,
+                        (peak as f32 / i16::MAX as f32).min(1.0),
+                    )
+                };
+                FrameLoudness {
+                    frame: index as u32 + 1,
+                    time_ms: from as u64 * 1000 / rate,
+                    rms,
+                    peak,
+                    beat: false,
+                }
+            })
+            .collect();
+
+        let history = ((numerator as f64 / denominator as f64).round() as usize).max(1);
+        for index in 1..frames.len() {
+            let window = &frames[index.saturating_sub(history)..index];
+            let average = window
+                .iter()
+                .map(|frame| (frame.rms as f64).powi(2))
+                .sum::<f64>()
+                / window.len() as f64;
+            let energy = (frames[index].rms as f64).powi(2);
+            frames[index].beat = frames[index].rms > BEAT_FLOOR
+                && energy > BEAT_SENSITIVITY * average
+                && !frames[index - 1].beat;
+        }
+
+        Self {
+            audio: audio.to_path_buf(),
+            fps,
+            frames,
+        }
+    }
+
+    /// Returns the number of frames a beat starts on.
+    pub fn beats(&self) -> usize {
+        self.frames.iter().filter(|frame| frame.beat).count()
+    }
+
+    /// Returns the RMS of each of the first `frames` frames relative to the loudest frame.
+    ///
+    /// # Returns
+    /// - `Vec<f32>`: One level from 0 to 1 per frame; frames past the end of the audio, and
+    ///   all frames of silent audio, are 0.
+    pub fn levels(&self, frames: usize) -> Vec<f32> {
+        let loudest = self
+            .frames
+            .iter()
+            .map(|frame| frame.rms)
+            .fold(0.0, f32::max);
+        (0..frames)
+            .map(|index| match self.frames.get(index) {
+                Some(frame) if loudest > 0.0 => frame.rms / loudest,
+                _ => 0.0,
+            })
+            .collect()
+    }
+
+    /// Writes the loudness as pretty-printed JSON to `path`.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Returns the loudness as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize the loudness")
+    }
+}
+
+/// Serializes a value by its `Display` form, as frame rates read on the command line.
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use log::debug;
+use std::path::Path;
+
+use crate::decode::{decode_samples, SAMPLE_RATE};
+
+/// Length of the windows the audio is measured in, in milliseconds.
+const WINDOW_MS: u64 = 10;
+
+/// Share of the loudest window's RMS a window must reach to be the onset.
+const ONSET_SHARE: f32 = 0.25;
+
+/// RMS below which a window is silence, however quiet the rest of the audio is.
+const ONSET_FLOOR: f32 = 0.02;
+
+/// Finds where the music starts in an audio track: its first strong onset.
+///
+/// # Parameters
+/// - `audio`: Any audio, or video with sound, that ffmpeg reads.
+///
+/// # Returns
+/// - `Result<Option<u64>>`: The start of the onset in milliseconds, `None` if the audio
+///   is silent throughout, or an error if ffmpeg cannot decode the audio.
+///
+/// # Notes
+/// - The audio is measured in windows of `WINDOW_MS`; the onset is the first window whose
+///   RMS reaches `ONSET_SHARE` of the loudest window's and is above `ONSET_FLOOR`, so
+///   room noise or a quiet count-in before the music is skipped.
+pub fn find_onset(audio: &Path) -> Result<Option<u64>> {
+    let samples = decode_samples(audio)?;
+    let onset = onset_in(&samples);
+    debug!("Audio onset of {}: {:?} ms", audio.display(), onset);
+    Ok(onset)
+}
+
+fn onset_in(samples: &[i16]) -> Option<u64> {
+    let window = (SAMPLE_RATE as u64 * WINDOW_MS / 1000) as usize;
+    let levels: Vec<f32> = samples
+        .chunks(window)
+        .map(|chunk| {
+            let energy: f64 = chunk
+                .iter()
+                .map(|&sample| {
+                    let sample = sample as f64 / i16::MAX as f64;
+                    sample * sample
+                })
+                .sum();
+            (energy / chunk.len() as f64).sqrt() as f32
+        })
+        .collect();
+    let loudest = levels.iter().copied().fold(0.0, f32::max);
+    let threshold = (loudest * ONSET_SHARE).max(ONSET_FLOOR);
+    levels
+        .iter()
+        .position(|&level| level >= threshold)
+        .map(|index| index as u64 * WINDOW_MS)
+}
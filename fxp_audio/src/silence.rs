@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::Path;
+use std::process::Command as StdCommand;
+use tracing::info_span;
+
+use fxp_output::ToolError;
+
+use crate::error::AudioError;
+
+/// Level below which ffmpeg's `silencedetect` counts the audio as silent.
+const SILENCE_NOISE: &str = "-50dB";
+
+/// Shortest silence `silencedetect` reports, in seconds.
+const SILENCE_MIN_SECONDS: f64 = 0.05;
+
+/// Latest start, in seconds, of a silence that still counts as leading the audio, as
+/// the first silence of a track may be reported a few samples in.
+const LEADING_TOLERANCE_SECONDS: f64 = 0.01;
+
+/// Measures the silence an audio track begins with, using ffmpeg's `silencedetect`.
+///
+/// # Parameters
+/// - `audio`: Any audio, or video with sound, that ffmpeg reads.
+///
+/// # Returns
+/// - `Result<u64>`: The length of the leading silence in milliseconds, 0 if the audio
+///   starts with sound, or an error if ffmpeg fails to read the audio.
+///
+/// # Notes
+/// - Audio below `SILENCE_NOISE` for at least `SILENCE_MIN_SECONDS` counts as silence,
+///   so encoder padding and tape hiss are skipped along with digital silence.
+/// - Audio that is silent throughout gives its whole length.
+pub fn leading_silence(audio: &Path) -> Result<u64> {
+    let _span = info_span!("detect silence", audio = %audio.display()).entered();
+    let output = StdCommand::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(audio)
+        .args([
+            "-af",
+            &format!(
+                "silencedetect=noise={}:d={}",
+                SILENCE_NOISE, SILENCE_MIN_SECONDS
+            ),
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .map_err(|e| AudioError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to detect silence")?;
+    if !output.status.success() {
+        return Err(AudioError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+        .with_context(|| format!("Failed to detect the silence of {}", audio.display()));
+    }
+    let silence = leading_silence_in(&String::from_utf8_lossy(&output.stderr));
+    debug!("Leading silence of {}: {} ms", audio.display(), silence);
+    Ok(silence)
+}
+
+/// Reads the leading silence off the log of `silencedetect`, which reports each silence
+/// as a `silence_start: <s>` line and, unless it lasts to the end, a
+/// `silence_end: <s> | silence_duration: <s>` line.
+fn leading_silence_in(log: &str) -> u64 {
+    let value = |line: &str, key: &str| -> Option<f64> {
+        let rest = &line[line.find(key)? + key.len()..];
+        rest.split_whitespace().next()?.parse().ok()
+    };
+    let mut lines = log.lines();
+    let Some(start) = lines
+        .by_ref()
+        .find_map(|line| value(line, "silence_start:"))
+    else {
+        return 0;
+    };
+    if start > LEADING_TOLERANCE_SECONDS {
+        return 0;
+    }
+    let end = lines
+        .find_map(|line| value(line, "silence_end:"))
+        // Silent to the end: the silence lasts as long as the input.
+        .or_else(|| log.lines().find_map(duration_of))
+        .unwrap_or(0.0);
+    (end * 1000.0).round() as u64
+}
+
+/// Reads the `Duration: HH:MM:SS.ss` of an input from ffmpeg's log line.
+fn duration_of(line: &str) -> Option<f64> {
+    let rest = &line[line.find("Duration:")? + "Duration:".len()..];
+    let time = rest.split(',').next()?.trim();
+    let mut seconds = 0.0;
+    for part in time.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
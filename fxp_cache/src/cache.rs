@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fxp_modes::Modes;
+
+/// Name of the cache manifest kept in every output directory.
+pub const CACHE_FILE_NAME: &str = ".fxp_cache";
+
+/// Bumped whenever the manifest layout or the key derivation changes.
+const CACHE_VERSION: u32 = 1;
+
+/// The manifest as stored on disk.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    mode: String,
+    /// Cache entries keyed by output path relative to the output directory, which is the
+    /// filename of an output directly inside it.
+    entries: BTreeMap<String, Entry>,
+}
+
+/// What produced one output file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Hash over the mode, the parameters and the contents of every input.
+    key: String,
+    /// Size of the output when it was written, to notice outputs changed since.
+    size: u64,
+}
+
+/// Checksum cache of the outputs a mode wrote into its output directory.
+///
+/// Every output file is recorded with a key hashed from its inputs' contents and the
+/// parameters that produced it. A rerun with identical inputs and parameters finds the
+/// same key and skips the frame; changing a parameter or an input only reprocesses the
+/// frames whose key changed.
+///
+/// A new run only sees the manifest when it writes into the same output directory again,
+/// which takes `--append`; otherwise an existing output is never reused.
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    manifest: Manifest,
+    /// Content hashes of the inputs seen in this run, so shared inputs are read once.
+    file_hashes: HashMap<PathBuf, String>,
+}
+
+impl Cache {
+    /// Opens the cache manifest of an output directory.
+    ///
+    /// # Parameters
+    /// - `output_directory`: Directory the mode writes its outputs to.
+    /// - `mode`: The mode writing the outputs.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The cache, empty if there is no manifest yet.
+    ///
+    /// # Notes
+    /// - A manifest that cannot be parsed, was written by another mode or by another
+    ///   cache version is discarded, so every frame is processed again.
+    pub fn open(output_directory: &Path, mode: Modes) -> Result<Self> {
+        let path = output_directory.join(CACHE_FILE_NAME);
+        let mode = format!("{:?}", mode);
+        let fresh = Manifest {
+            version: CACHE_VERSION,
+            mode: mode.clone(),
+            entries: BTreeMap::new(),
+        };
+
+        let manifest = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<Manifest>(&contents) {
+                Ok(manifest) if manifest.version == CACHE_VERSION && manifest.mode == mode => {
+                    debug!(
+                        "Loaded cache {:?} with {} entries",
+                        path,
+                        manifest.entries.len()
+                    );
+                    manifest
+                }
+                Ok(_) => {
+                    debug!(
+                        "Cache {:?} belongs to another mode or version, starting over",
+                        path
+                    );
+                    fresh
+                }
+                Err(e) => {
+                    warn!("Ignoring unreadable cache {:?}: {}", path, e);
+                    fresh
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!("No cache at {:?}, starting empty", path);
+                fresh
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read cache {:?}", path));
+            }
+        };
+
+        Ok(Self {
+            path,
+            manifest,
+            file_hashes: HashMap::new(),
+        })
+    }
+
+    /// Derives the cache key of one output from its inputs and parameters.
+    ///
+    /// # Parameters
+    /// - `inputs`: Every file the output is computed from.
+    /// - `parameters`: Every setting that affects the output, in a fixed order.
+    ///
+    /// # Returns
+    /// - `Result<String>`: The hex-encoded key, or an error if an input cannot be read.
+    pub fn key(&mut self, inputs: &[&Path], parameters: &[String]) -> Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.manifest.mode.as_bytes());
+        for parameter in parameters {
+            // Length-prefix every field so that ["ab", "c"] and ["a", "bc"] differ.
+            hasher.update(&(parameter.len() as u64).to_le_bytes());
+            hasher.update(parameter.as_bytes());
+        }
+        for input in inputs {
+            let hash = self.file_hash(input)?;
+            hasher.update(hash.as_bytes());
+        }
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    /// Returns `true` if `output` exists and was produced from the inputs behind `key`.
+    pub fn is_fresh(&self, output: &Path, key: &str) -> bool {
+        let Some(entry) = self
+            .entry_name(output)
+            .and_then(|name| self.manifest.entries.get(&name))
+        else {
+            return false;
+        };
+        let fresh = entry.key == key
+            && fs::metadata(output).is_ok_and(|m| m.is_file() && m.len() == entry.size);
+        debug!("Cache entry for {:?} is fresh: {}", output, fresh);
+        fresh
+    }
+
+    /// Records that `output` was just produced from the inputs behind `key`.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the output cannot be inspected.
+    pub fn record(&mut self, output: &Path, key: String) -> Result<()> {
+        let name = self
+            .entry_name(output)
+            .with_context(|| format!("Output {:?} has no valid filename", output))?;
+        let size = fs::metadata(output)
+            .with_context(|| format!("Failed to inspect output {:?}", output))?
+            .len();
+        self.manifest.entries.insert(name, Entry { key, size });
+        Ok(())
+    }
+
+    /// Writes the manifest back to the output directory.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the manifest cannot be written.
+    ///
+    /// # Notes
+    /// - The manifest is written to a temporary file and renamed into place, so an
+    ///   interrupted save never leaves a truncated manifest behind.
+    pub fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create cache {:?}", tmp_path))?;
+        serde_json::to_writer_pretty(file, &self.manifest)
+            .with_context(|| format!("Failed to write cache {:?}", tmp_path))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to move cache into place at {:?}", self.path))?;
+        debug!(
+            "Saved cache {:?} with {} entries",
+            self.path,
+            self.manifest.entries.len()
+        );
+        Ok(())
+    }
+
+    /// Returns the manifest entry name of an output: its path relative to the output
+    /// directory with `/` separators, so outputs of the same name in different
+    /// subdirectories are told apart.
+    fn entry_name(&self, output: &Path) -> Option<String> {
+        let relative = self
+            .path
+            .parent()
+            .and_then(|directory| output.strip_prefix(directory).ok())
+            .filter(|relative| !relative.as_os_str().is_empty());
+        match relative {
+            Some(relative) => relative
+                .components()
+                .map(|component| component.as_os_str().to_str())
+                .collect::<Option<Vec<&str>>>()
+                .map(|parts| parts.join("/")),
+            None => output.file_name()?.to_str().map(String::from),
+        }
+    }
+
+    /// Returns the content hash of an input, reading each file at most once per run.
+    fn file_hash(&mut self, path: &Path) -> Result<String> {
+        if let Some(hash) = self.file_hashes.get(path) {
+            return Ok(hash.clone());
+        }
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {:?} for hashing", path))?;
+        let mut hasher = blake3::Hasher::new();
+        io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to hash {:?}", path))?;
+        let hash = hasher.finalize().to_hex().to_string();
+        self.file_hashes.insert(path.to_path_buf(), hash.clone());
+        Ok(hash)
+    }
+}
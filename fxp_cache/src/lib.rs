@@ -0,0 +1,3 @@
+mod cache;
+
+pub use cache::{Cache, CACHE_FILE_NAME};
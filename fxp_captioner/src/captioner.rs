@@ -0,0 +1,361 @@
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use indicatif::ProgressStyle;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tracing::info_span;
+
+use fxp_cache::Cache;
+use fxp_modes::Modes;
+use fxp_output::progress_bar;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::FileOperations;
+use fxp_stream::{FrameEncoder, IccProfile};
+
+use crate::captions::Captions;
+use crate::error::CaptionerError;
+use crate::render::{default_font, Typeface};
+use crate::style::CaptionStyle;
+
+/// Struct responsible for drawing captions onto a directory of frames natively.
+pub struct Captioner {
+    input_directory: PathBuf,
+    input_files: BTreeMap<u32, PathBuf>,
+    output_directory: PathBuf,
+    captions_path: PathBuf,
+    captions: Captions,
+    /// How the text is drawn; `new` sets white 48 px text with a 2 px black outline,
+    /// centered near the bottom, see `CaptionStyle::default`.
+    pub style: CaptionStyle,
+    /// The font file to draw with; `new` sets `None`, which takes the first font found by
+    /// `default_font`.
+    pub font: Option<PathBuf>,
+    /// The format the frames are written in; `new` keeps the format of the input frames.
+    pub encoder: FrameEncoder,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+}
+
+impl Captioner {
+    /// Creates a new `Captioner` instance for a directory of frames.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `captions`: Path to the captions file, see `Captions`.
+    /// - `output_directory`: Optional path for the captioned frames; defaults to
+    ///   `<input>_captioned`.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: New `Captioner` instance on success, or an error if validation
+    ///   fails or the captions file is malformed.
+    ///
+    /// # Notes
+    /// - Creates the output directory if it does not exist.
+    /// - The frames are numbered by their filenames, see `FileOperations::load_files`;
+    ///   the captions refer to these numbers.
+    pub fn new(
+        input_directory: String,
+        captions: String,
+        output_directory: Option<String>,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        debug!("Initializing new Captioner instance with:");
+        debug!("- Input directory: {}", input_directory);
+        debug!("- Captions: {}", captions);
+        debug!("- Output directory: {:?}", output_directory);
+
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+        debug!("Found {} input files for processing", input_files.len());
+        let captions_path = PathBuf::from(captions);
+        let captions = Captions::load(&captions_path)?;
+        debug!("Loaded {} captions", captions.len());
+
+        let mode: Modes = Modes::Captioner;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Captioner(captioner_output) => captioner_output
+                .create_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Captioner mode"),
+        };
+        debug!("Output directory created at: {:?}", output_directory_path);
+
+        Ok(Self {
+            input_directory: input_directory_path,
+            input_files,
+            output_directory: output_directory_path,
+            captions_path,
+            captions,
+            style: CaptionStyle::default(),
+            font: None,
+            encoder: FrameEncoder::default(),
+            in_place: false,
+        })
+    }
+
+    /// Resolves what `new` and `caption` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `captions`: Path to the captions file.
+    /// - `output_directory`: Optional path for the captioned frames.
+    /// - `style`: How the text is drawn.
+    /// - `font`: The font file, or `None` for the first font found by `default_font`.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails, the captions
+    ///   file is malformed or no usable font is found.
+    pub fn plan(
+        input_directory: String,
+        captions: String,
+        output_directory: Option<String>,
+        style: &CaptionStyle,
+        font: Option<&Path>,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        style.validate().map_err(CaptionerError::InvalidInput)?;
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+        let captions_path = PathBuf::from(captions);
+        let loaded = Captions::load(&captions_path)?;
+        let typeface = load_typeface(font)?;
+        let captioned = input_files
+            .keys()
+            .filter(|number| loaded.at(**number).is_some())
+            .count();
+
+        let mode: Modes = Modes::Captioner;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Captioner(captioner_output) => captioner_output
+                .plan_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Captioner mode"),
+        };
+
+        Ok(Plan::new(Modes::Captioner)
+            .entry("input directory", input_directory_path.display())
+            .entry("frames", input_files.len())
+            .entry("captions file", captions_path.display())
+            .entry("captions", loaded.len())
+            .entry("captioned frames", captioned)
+            .entry("font", typeface.path().display())
+            .entry("text", style)
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
+}
+
+/// Checks that the input is a directory and canonicalizes it.
+fn canonical_input_directory(input_directory: &str) -> Result<PathBuf> {
+    let input_directory_path = PathBuf::from(input_directory);
+    if !input_directory_path.is_dir() {
+        return Err(CaptionerError::InvalidInput(format!(
+            "Input directory '{}' does not exist or is not a directory",
+            input_directory_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_directory_path).with_context(|| {
+        format!(
+            "Failed to resolve input directory '{}'",
+            input_directory_path.display()
+        )
+    })
+}
+
+/// Maps the frames of the input directory by their frame number.
+fn load_frames(input_directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let input_images: Vec<PathBuf> = fs::read_dir(input_directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    Ok(Modes::Captioner.load_files(&input_images)?)
+}
+
+/// Loads the given font, or the first one `default_font` finds.
+fn load_typeface(font: Option<&Path>) -> Result<Typeface> {
+    match font.map(Path::to_path_buf).or_else(default_font) {
+        Some(path) => Typeface::load(&path),
+        None => Err(CaptionerError::InvalidInput(
+            "No font was given and none was found in the usual places".to_string(),
+        )
+        .into()),
+    }
+}
+
+impl Captioner {
+    /// Draws the captions onto every frame and writes it under its own name into the
+    /// output.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of frames, or an error if no usable font is found, a
+    ///   frame cannot be decoded or written, or processing was interrupted.
+    ///
+    /// # Notes
+    /// - Frames without a caption are copied unchanged, unless `encoder` has a format, in
+    ///   which they are written as all frames are, named with its extension.
+    /// - Frames whose input, text and style are unchanged since the last run into the
+    ///   same output directory are skipped, see `fxp_cache::Cache`.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are written, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Writes a `manifest.json` recording the captions file, font and style and the
+    ///   input hashes.
+    pub fn caption(&self) -> Result<usize> {
+        self.style
+            .validate()
+            .map_err(CaptionerError::InvalidInput)?;
+        self.encoder
+            .validate()
+            .map_err(CaptionerError::InvalidInput)?;
+        let typeface = load_typeface(self.font.as_deref())?;
+        let _span = info_span!(
+            "captioner",
+            input = %self.input_directory.display(),
+            output = %self.output_directory.display()
+        )
+        .entered();
+
+        let manifest = self.manifest(&typeface);
+
+        let running = running_flag()?;
+
+        let pb = progress_bar(self.input_files.len() as u64);
+        pb.set_style(ProgressStyle::default_bar().template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+        )?);
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let mut cache = Cache::open(staged.path(), Modes::Captioner)?;
+
+        for (number, frame) in &self.input_files {
+            if !running.load(Ordering::SeqCst) {
+                pb.abandon();
+                cache.save()?;
+                return Err(CaptionerError::Interrupted.into());
+            }
+
+            let file_name = frame
+                .file_name()
+                .with_context(|| format!("Frame {:?} has no filename", frame))?;
+            let target = self.encoder.output_path(&staged.path().join(file_name));
+            let text = self.captions.at(*number);
+            let mut parameters = vec![
+                text.clone().unwrap_or_default(),
+                typeface.path().display().to_string(),
+                self.style.to_string(),
+            ];
+            parameters.extend(self.encoder.cache_parameter());
+            let key = cache.key(&[frame.as_path()], &parameters)?;
+            if cache.is_fresh(&target, &key) {
+                debug!("Frame {} is unchanged, skipping", number);
+                pb.inc(1);
+                continue;
+            }
+
+            let _frame_span = info_span!("caption", frame = number).entered();
+            match text {
+                Some(text) => {
+                    let original = image::open(frame)
+                        .with_context(|| format!("Failed to decode frame {}", frame.display()))?;
+                    let mut image = original.to_rgba8();
+                    typeface.draw(&mut image, &text, &self.style);
+                    // Keep frames without alpha opaque, as JPEG has no alpha to write.
+                    let captioned = if original.color().has_alpha() {
+                        DynamicImage::ImageRgba8(image)
+                    } else {
+                        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8())
+                    };
+                    self.encoder
+                        .save(&captioned, IccProfile::read(frame).as_ref(), &target)
+                        .with_context(|| {
+                            format!("Failed to write captioned frame {}", target.display())
+                        })?;
+                }
+                None if !self.encoder.is_default() => {
+                    let image = image::open(frame)
+                        .with_context(|| format!("Failed to decode frame {}", frame.display()))?;
+                    self.encoder
+                        .save(&image, IccProfile::read(frame).as_ref(), &target)
+                        .with_context(|| format!("Failed to write frame {}", target.display()))?;
+                }
+                None => {
+                    fs::copy(frame, &target).with_context(|| {
+                        format!(
+                            "Failed to copy frame {} to {}",
+                            frame.display(),
+                            target.display()
+                        )
+                    })?;
+                }
+            }
+            cache.record(&target, key)?;
+            pb.inc(1);
+        }
+        pb.finish_with_message("Done");
+        cache.save()?;
+        debug!("Captioned {} frames", self.input_files.len());
+
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Captioner, self.input_files.len())?;
+
+        Ok(self.input_files.len())
+    }
+
+    /// Builds the manifest, recording the style only where it differs from the default.
+    fn manifest(&self, typeface: &Typeface) -> Manifest {
+        let default = CaptionStyle::default();
+        let mut manifest = Manifest::new(Modes::Captioner)
+            .parameter("input", self.input_directory.display())
+            .parameter("captions", self.captions_path.display());
+        if let Some(font) = &self.font {
+            manifest = manifest.parameter("font", font.display());
+        }
+        if self.style.font_size != default.font_size {
+            manifest = manifest.parameter("font size", self.style.font_size);
+        }
+        if self.style.color != default.color {
+            let [r, g, b, a] = self.style.color.0;
+            manifest = manifest.parameter(
+                "text color",
+                format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            );
+        }
+        if self.style.outline != default.outline {
+            let outline = self
+                .style
+                .outline
+                .map_or_else(|| "0".to_string(), |outline| outline.to_string());
+            manifest = manifest.parameter("outline", outline);
+        }
+        if self.style.position != default.position {
+            manifest = manifest.parameter("text position", self.style.position);
+        }
+        if self.style.margin != default.margin {
+            manifest = manifest.parameter("text margin", self.style.margin);
+        }
+        if let Some(format) = self.encoder.format {
+            manifest = manifest
+                .parameter("output format", format)
+                .parameter("quality", self.encoder.quality)
+                .parameter("lossless", self.encoder.lossless);
+        }
+        manifest
+            .inputs(self.input_files.values())
+            .inputs([self.captions_path.as_path(), typeface.path()])
+    }
+}
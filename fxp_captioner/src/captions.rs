@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::error::CaptionerError;
+
+/// A line of text shown on a range of frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caption {
+    /// The first frame showing the text.
+    pub first: u32,
+    /// The last frame showing the text, inclusive.
+    pub last: u32,
+    /// The text; may span several lines.
+    pub text: String,
+}
+
+/// The captions of a frame sequence, as read from a captions file.
+///
+/// Each line of the file gives a frame or an inclusive range of frames and the text
+/// shown on them, e.g. `1-120 First line of the song` or `121 A single frame`. Blank
+/// lines and lines starting with `#` are skipped, and `\n` in a text breaks the line.
+/// Frames are numbered by their filenames, as in `frame_0001.png`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Captions {
+    pub captions: Vec<Caption>,
+}
+
+impl Captions {
+    /// Reads a captions file.
+    ///
+    /// # Parameters
+    /// - `path`: Path to the captions file.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The captions in file order, or an error naming the first
+    ///   malformed line.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            CaptionerError::InvalidInput(format!(
+                "Failed to read captions file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::parse(&contents).map_err(|e| {
+            CaptionerError::InvalidInput(format!("Captions file '{}': {}", path.display(), e))
+                .into()
+        })
+    }
+
+    /// Parses the contents of a captions file, see `Captions`.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut captions = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (range, text) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            let (Ok(first), Ok(last)) = (first.parse::<u32>(), last.parse::<u32>()) else {
+                return Err(format!(
+                    "line {}: expected FRAME or FIRST-LAST followed by the text, got '{}'",
+                    index + 1,
+                    line
+                ));
+            };
+            if first > last {
+                return Err(format!(
+                    "line {}: the range {}-{} ends before it starts",
+                    index + 1,
+                    first,
+                    last
+                ));
+            }
+            captions.push(Caption {
+                first,
+                last,
+                text: text.trim().replace("\\n", "\n"),
+            });
+        }
+        Ok(Self { captions })
+    }
+
+    /// Returns the text shown on a frame.
+    ///
+    /// # Returns
+    /// - `Option<String>`: The text of every caption whose range holds the frame, one
+    ///   below the other in file order; `None` if no caption does.
+    pub fn at(&self, frame: u32) -> Option<String> {
+        let texts: Vec<&str> = self
+            .captions
+            .iter()
+            .filter(|caption| (caption.first..=caption.last).contains(&frame))
+            .map(|caption| caption.text.as_str())
+            .filter(|text| !text.is_empty())
+            .collect();
+        (!texts.is_empty()).then(|| texts.join("\n"))
+    }
+
+    /// Returns how many captions there are.
+    pub fn len(&self) -> usize {
+        self.captions.len()
+    }
+
+    /// Returns whether there are no captions.
+    pub fn is_empty(&self) -> bool {
+        self.captions.is_empty()
+    }
+}
@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+use fxp_output::FailureKind;
+
+/// Failures of the Captioner that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum CaptionerError {
+    /// The input, the captions file or the font does not exist or is not what the
+    /// Captioner takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Captioning interrupted by user")]
+    Interrupted,
+}
+
+impl CaptionerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            CaptionerError::InvalidInput(_) => FailureKind::InvalidInput,
+            CaptionerError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
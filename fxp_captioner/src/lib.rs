@@ -0,0 +1,12 @@
+mod captioner;
+mod captions;
+mod error;
+mod render;
+mod style;
+
+pub use captioner::Captioner;
+pub use captions::{Caption, Captions};
+pub use error::CaptionerError;
+pub use render::{default_font, Typeface};
+pub use style::{parse_color, CaptionStyle, Outline, TextPosition};
+pub use style::{DEFAULT_FONT_SIZE, DEFAULT_TEXT_MARGIN};
@@ -0,0 +1,207 @@
+use ab_glyph::{Font, FontArc, Glyph, PxScale, ScaleFont};
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::CaptionerError;
+use crate::style::{CaptionStyle, TextPosition};
+
+/// Fonts tried, in order, when no font is given.
+const SYSTEM_FONTS: [&str; 8] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Bold.ttf",
+    "/Library/Fonts/Arial Bold.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+    "C:\\Windows\\Fonts\\arialbd.ttf",
+];
+
+/// Returns the first bold sans serif font installed in a usual place, if any.
+pub fn default_font() -> Option<PathBuf> {
+    SYSTEM_FONTS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+/// A TrueType or OpenType font the captions are drawn with.
+pub struct Typeface {
+    font: FontArc,
+    path: PathBuf,
+}
+
+impl Typeface {
+    /// Loads a font file.
+    ///
+    /// # Parameters
+    /// - `path`: Path to a `.ttf` or `.otf` file.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The font, or an error if the file cannot be read or is not a font.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| {
+            CaptionerError::InvalidInput(format!("Font '{}' {}", path.display(), reason))
+        };
+        let bytes = fs::read(path).map_err(|e| invalid(format!("cannot be read: {}", e)))?;
+        let font = FontArc::try_from_vec(bytes)
+            .map_err(|_| invalid("is not a TrueType or OpenType font".to_string()))?;
+        Ok(Self {
+            font,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Returns the path the font was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Draws text onto a frame.
+    ///
+    /// # Parameters
+    /// - `frame`: The frame, drawn onto in place.
+    /// - `text`: The text; `\n` starts a new line.
+    /// - `style`: Size, colors and position of the text.
+    ///
+    /// # Notes
+    /// - Lines are centered on the frame, unless the style places the text at a point,
+    ///   where they are aligned left.
+    /// - Text running off the frame is cut off; it is not wrapped.
+    pub fn draw(&self, frame: &mut RgbaImage, text: &str, style: &CaptionStyle) {
+        let font = self.font.as_scaled(PxScale::from(style.font_size));
+        let outline = style.outline_width();
+        let line_height = font.height() + font.line_gap();
+        let lines: Vec<(Vec<Glyph>, f32)> =
+            text.lines().map(|line| layout_line(&font, line)).collect();
+        let text_width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+        let text_height = line_height * lines.len() as f32;
+
+        // The coverage of the letters over the text block, with room for the outline.
+        let pad = outline as f32;
+        let mask_width = (text_width + 2.0 * pad).ceil() as u32 + 1;
+        let mask_height = (text_height + 2.0 * pad).ceil() as u32 + 1;
+        let mut coverage = vec![0.0f32; (mask_width * mask_height) as usize];
+        for (row, (glyphs, width)) in lines.into_iter().enumerate() {
+            let indent = match style.position {
+                TextPosition::At(..) => 0.0,
+                _ => (text_width - width) / 2.0,
+            };
+            let baseline = pad + line_height * row as f32 + font.ascent();
+            for mut glyph in glyphs {
+                glyph.position.x += pad + indent;
+                glyph.position.y = baseline;
+                let Some(outlined) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, c| {
+                    let x = bounds.min.x as i64 + x as i64;
+                    let y = bounds.min.y as i64 + y as i64;
+                    if (0..mask_width as i64).contains(&x) && (0..mask_height as i64).contains(&y) {
+                        let cell = &mut coverage[(y as u32 * mask_width + x as u32) as usize];
+                        *cell = (*cell + c).min(1.0);
+                    }
+                });
+            }
+        }
+
+        let (x, y) = origin(
+            style,
+            (frame.width(), frame.height()),
+            (mask_width, mask_height),
+        );
+        if let Some(outline_style) = style.outline.filter(|outline| outline.width > 0) {
+            let spread = dilate(&coverage, (mask_width, mask_height), outline);
+            paint(frame, (x, y), (mask_width, &spread), outline_style.color);
+        }
+        paint(frame, (x, y), (mask_width, &coverage), style.color);
+    }
+}
+
+/// Lays out a line of text from x 0, kerning between letters.
+///
+/// # Returns
+/// - `(Vec<Glyph>, f32)`: The glyphs, positioned along the line, and the line's width.
+fn layout_line<F: Font>(font: &impl ScaleFont<F>, line: &str) -> (Vec<Glyph>, f32) {
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0;
+    let mut previous = None;
+    for character in line.chars() {
+        let id = font.glyph_id(character);
+        if let Some(previous) = previous {
+            caret += font.kern(previous, id);
+        }
+        glyphs.push(id.with_scale_and_position(font.scale(), ab_glyph::point(caret, 0.0)));
+        caret += font.h_advance(id);
+        previous = Some(id);
+    }
+    (glyphs, caret)
+}
+
+/// Returns where the top left corner of the text block lands on the frame.
+fn origin(style: &CaptionStyle, frame: (u32, u32), block: (u32, u32)) -> (i64, i64) {
+    let pad = style.outline_width() as i64;
+    let centered = (frame.0 as i64 - block.0 as i64) / 2;
+    match style.position {
+        TextPosition::Top => (centered, style.margin as i64),
+        TextPosition::Center => (centered, (frame.1 as i64 - block.1 as i64) / 2),
+        TextPosition::Bottom => (
+            centered,
+            frame.1 as i64 - block.1 as i64 - style.margin as i64,
+        ),
+        TextPosition::At(x, y) => (x as i64 - pad, y as i64 - pad),
+    }
+}
+
+/// Spreads the coverage by a radius, the strongest coverage within a circle winning,
+/// which gives the outline's shape.
+fn dilate(coverage: &[f32], (width, height): (u32, u32), radius: u32) -> Vec<f32> {
+    let radius = radius as i64;
+    let offsets: Vec<(i64, i64)> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| dx * dx + dy * dy <= radius * radius)
+        .collect();
+    let (width, height) = (width as i64, height as i64);
+    let mut spread = vec![0.0f32; coverage.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            if coverage[index] <= 0.0 {
+                continue;
+            }
+            for (dx, dy) in &offsets {
+                let (nx, ny) = (x + dx, y + dy);
+                if (0..width).contains(&nx) && (0..height).contains(&ny) {
+                    let cell = &mut spread[(ny * width + nx) as usize];
+                    *cell = cell.max(coverage[index]);
+                }
+            }
+        }
+    }
+    spread
+}
+
+/// Blends a color over the frame, weighted by a coverage mask placed at `origin`.
+fn paint(frame: &mut RgbaImage, origin: (i64, i64), (width, mask): (u32, &[f32]), color: Rgba<u8>) {
+    for (index, &coverage) in mask.iter().enumerate() {
+        if coverage <= 0.0 {
+            continue;
+        }
+        let x = origin.0 + (index as u32 % width) as i64;
+        let y = origin.1 + (index as u32 / width) as i64;
+        if !(0..frame.width() as i64).contains(&x) || !(0..frame.height() as i64).contains(&y) {
+            continue;
+        }
+        let alpha = coverage * color[3] as f32 / 255.0;
+        let pixel = frame.get_pixel_mut(x as u32, y as u32);
+        for channel in 0..3 {
+            let below = pixel[channel] as f32;
+            pixel[channel] = (below + (color[channel] as f32 - below) * alpha).round() as u8;
+        }
+        let below = pixel[3] as f32;
+        pixel[3] = (below + (255.0 - below) * alpha).round() as u8;
+    }
+}
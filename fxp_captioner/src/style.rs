@@ -0,0 +1,196 @@
+use image::Rgba;
+use std::fmt;
+use std::str::FromStr;
+
+/// Height of the text in pixels, see `CaptionStyle::font_size`.
+pub const DEFAULT_FONT_SIZE: f32 = 48.0;
+
+/// Distance in pixels between text at the top or bottom and the edge of the frame.
+pub const DEFAULT_TEXT_MARGIN: u32 = 40;
+
+/// Parses `white`, `black` or a hex color, `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(s: &str) -> Result<Rgba<u8>, String> {
+    let invalid = || {
+        format!(
+            "Invalid color '{}', expected white, black or a color such as #ffcc00 or #ffcc0080",
+            s
+        )
+    };
+    match s.trim().to_ascii_lowercase().as_str() {
+        "white" => return Ok(Rgba([255, 255, 255, 255])),
+        "black" => return Ok(Rgba([0, 0, 0, 255])),
+        _ => {}
+    }
+    let hex = s.trim().trim_start_matches('#');
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return Err(invalid());
+    }
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+    };
+    let alpha = if hex.len() == 8 {
+        channel(3)
+    } else {
+        Some(255)
+    };
+    match (channel(0), channel(1), channel(2), alpha) {
+        (Some(r), Some(g), Some(b), Some(a)) => Ok(Rgba([r, g, b, a])),
+        _ => Err(invalid()),
+    }
+}
+
+/// Writes a color as `parse_color` reads it.
+fn write_color(f: &mut fmt::Formatter<'_>, color: Rgba<u8>) -> fmt::Result {
+    match color {
+        Rgba([r, g, b, 255]) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        Rgba([r, g, b, a]) => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+    }
+}
+
+/// An outline drawn around the letters, keeping them readable on any frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outline {
+    /// Width in pixels.
+    pub width: u32,
+    pub color: Rgba<u8>,
+}
+
+impl FromStr for Outline {
+    type Err = String;
+
+    /// Parses a width, optionally followed by a color, e.g. `2` or `3,#202020`; the color
+    /// is black if left out.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, color) = s.split_once(',').unwrap_or((s, "black"));
+        let width = width
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid outline '{}', expected WIDTH[,COLOR]", s))?;
+        Ok(Outline {
+            width,
+            color: parse_color(color)?,
+        })
+    }
+}
+
+impl fmt::Display for Outline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},", self.width)?;
+        write_color(f, self.color)
+    }
+}
+
+/// Where the text sits on the frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextPosition {
+    Top,
+    Center,
+    #[default]
+    Bottom,
+    /// The top left corner of the text, in pixels of the frame; lines are aligned left
+    /// and the margin is ignored.
+    At(u32, u32),
+}
+
+impl FromStr for TextPosition {
+    type Err = String;
+
+    /// Parses `top`, `center`, `bottom` or the top left corner of the text as `X,Y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "top" => return Ok(TextPosition::Top),
+            "center" => return Ok(TextPosition::Center),
+            "bottom" => return Ok(TextPosition::Bottom),
+            _ => {}
+        }
+        let coordinates = s
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+        match coordinates {
+            Some((x, y)) => Ok(TextPosition::At(x, y)),
+            None => Err(format!(
+                "Unknown position '{}', expected top, center, bottom or X,Y",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextPosition::Top => write!(f, "top"),
+            TextPosition::Center => write!(f, "center"),
+            TextPosition::Bottom => write!(f, "bottom"),
+            TextPosition::At(x, y) => write!(f, "{},{}", x, y),
+        }
+    }
+}
+
+/// How the captions are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptionStyle {
+    /// Height of the text in pixels.
+    pub font_size: f32,
+    pub color: Rgba<u8>,
+    pub outline: Option<Outline>,
+    pub position: TextPosition,
+    /// Distance in pixels between text at the top or bottom, with its outline, and the
+    /// edge of the frame.
+    pub margin: u32,
+}
+
+impl Default for CaptionStyle {
+    /// White 48 px text with a 2 px black outline, centered near the bottom.
+    fn default() -> Self {
+        Self {
+            font_size: DEFAULT_FONT_SIZE,
+            color: Rgba([255, 255, 255, 255]),
+            outline: Some(Outline {
+                width: 2,
+                color: Rgba([0, 0, 0, 255]),
+            }),
+            position: TextPosition::default(),
+            margin: DEFAULT_TEXT_MARGIN,
+        }
+    }
+}
+
+impl fmt::Display for CaptionStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} px ", self.font_size)?;
+        write_color(f, self.color)?;
+        match self.outline {
+            Some(outline) if outline.width > 0 => {
+                write!(f, ", {} px ", outline.width)?;
+                write_color(f, outline.color)?;
+                write!(f, " outline")?;
+            }
+            _ => write!(f, ", no outline")?,
+        }
+        write!(f, " at {}", self.position)?;
+        if !matches!(self.position, TextPosition::At(..) | TextPosition::Center) {
+            write!(f, ", {} px margin", self.margin)?;
+        }
+        Ok(())
+    }
+}
+
+impl CaptionStyle {
+    /// Returns the width of the outline in pixels, 0 without one.
+    pub fn outline_width(&self) -> u32 {
+        self.outline.map_or(0, |outline| outline.width)
+    }
+
+    /// Checks that the text has a size.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.font_size.is_finite() && self.font_size > 0.0) {
+            return Err(format!(
+                "The font size {} must be a positive number of pixels",
+                self.font_size
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use fxp_output::{kill_requested, ToolError};
+
+use crate::clip::{part_file_path, EncodeSettings};
+use crate::error::ClipperError;
+use crate::format::ClipFormat;
+
+/// Encodes the staged frames into a looping GIF or APNG.
+///
+/// # Parameters
+/// - `frame_pattern`: ffmpeg input pattern of the staged frames, e.g. `dir/frame_%04d.png`.
+/// - `output_path`: Path where the animation will be saved.
+/// - `format`: `ClipFormat::Gif` or `ClipFormat::Apng`.
+/// - `encode`: Frame rate, size limits and play count of the animation.
+/// - `duration`: Optional duration in milliseconds to cut the animation at.
+/// - `running`: Cleared by a stop signal to end the encoding.
+/// - `tmp_dir_path`: Temporary directory for the GIF palette.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The animation, or an error if ffmpeg fails or was interrupted.
+///
+/// # Notes
+/// - A GIF is encoded in two passes: `palettegen` computes the 256 colors best suited to
+///   the frames, then `paletteuse` maps the frames onto them, which avoids the banding of
+///   ffmpeg's default palette.
+/// - Like the video, the animation is written to `<output>.part` and renamed into place.
+pub fn make_animation(
+    frame_pattern: &Path,
+    output_path: &Path,
+    format: ClipFormat,
+    encode: &EncodeSettings,
+    duration: Option<u64>,
+    running: Arc<AtomicBool>,
+    tmp_dir_path: &Path,
+) -> Result<PathBuf> {
+    let _span = info_span!(
+        "animate",
+        frames = %frame_pattern.display(),
+        format = %format,
+        fps = %encode.fps
+    )
+    .entered();
+    let input: Vec<OsString> = vec![
+        "-framerate".into(),
+        encode.fps.ffmpeg_arg().into(),
+        "-start_number".into(),
+        "1".into(),
+        "-i".into(),
+        frame_pattern.into(),
+    ];
+    // An output option, so it must follow every input.
+    let cut: Vec<String> = match duration {
+        Some(duration) => vec!["-t".into(), format!("{:.3}", duration as f64 / 1000.0)],
+        None => Vec::new(),
+    };
+    let scale = encode.scale_filter(false);
+    let part_path = part_file_path(output_path);
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y").args(&input);
+    match format {
+        ClipFormat::Gif => {
+            let palette = tmp_dir_path.join("palette.png");
+            let mut palette_command = Command::new("ffmpeg");
+            palette_command
+                .arg("-y")
+                .args(&input)
+                .args(["-vf", &format!("{},palettegen=stats_mode=diff", scale)])
+                .args(&cut)
+                .arg(&palette);
+            run_ffmpeg(palette_command, "compute the GIF palette", running.clone())?;
+
+            command
+                .arg("-i")
+                .arg(&palette)
+                .args([
+                    "-lavfi",
+                    &format!(
+                        "[0:v]{}[frames];[frames][1:v]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle",
+                        scale
+                    ),
+                    "-loop",
+                    &gif_loop(encode.loop_count).to_string(),
+                    "-f",
+                    "gif",
+                ]);
+        }
+        ClipFormat::Apng => {
+            command.args([
+                "-vf",
+                &scale,
+                "-plays",
+                &encode.loop_count.to_string(),
+                "-f",
+                "apng",
+            ]);
+        }
+        ClipFormat::Mp4 | ClipFormat::Hls | ClipFormat::Dash => {
+            unreachable!("Videos are made by make_clip")
+        }
+    }
+    command.args(&cut).arg(&part_path);
+
+    if let Err(e) = run_ffmpeg(command, "encode the animation", running) {
+        fs::remove_file(&part_path).ok();
+        return Err(e);
+    }
+    fs::rename(&part_path, output_path).with_context(|| {
+        format!(
+            "Failed to move {} into place at {}",
+            part_path.display(),
+            output_path.display()
+        )
+    })?;
+    debug!("Animation saved at: {:?}", output_path);
+
+    Ok(output_path.to_path_buf())
+}
+
+/// Converts the number of plays, 0 for forever, into the GIF muxer's `-loop`, which
+/// counts the repeats after the first play and uses -1 for none.
+fn gif_loop(loop_count: u32) -> i64 {
+    match loop_count {
+        0 => 0,
+        1 => -1,
+        plays => plays as i64 - 1,
+    }
+}
+
+/// Runs an ffmpeg command to completion, unless the run is stopped before it starts;
+/// a stop while it runs kills it, see `fxp_output::kill_requested`.
+fn run_ffmpeg(mut command: Command, action: &str, running: Arc<AtomicBool>) -> Result<()> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
+    }
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
+        .with_context(|| format!("Failed to start ffmpeg to {}", action))?;
+
+    loop {
+        if kill_requested(&running) {
+            debug!("Interruption requested; terminating ffmpeg process.");
+            child.kill().ok();
+            return Err(ClipperError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => {
+                debug!("FFmpeg command failed with status: {:?}", status);
+                return Err(ClipperError::ToolFailed {
+                    tool: "ffmpeg".to_string(),
+                    reason: status.to_string(),
+                })
+                .with_context(|| format!("ffmpeg failed to {}", action));
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::{thread, time::Duration};
+use tracing::info_span;
+
+use fxp_output::FrameRate;
+use fxp_output::{kill_requested, ToolError};
+
+use crate::clip::part_file_path;
+use crate::error::ClipperError;
+
+/// A label starting at a frame of the input, as read from a markers file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    /// Frame number of the input the chapter starts at.
+    pub frame: u32,
+    /// Title of the chapter.
+    pub title: String,
+}
+
+/// A chapter placed on the timeline of the encoded clip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub title: String,
+}
+
+/// Reads a markers file: one frame number and title per line, e.g. `120 Second verse`.
+///
+/// # Parameters
+/// - `path`: The markers file.
+///
+/// # Returns
+/// - `Result<Vec<Marker>>`: The markers by frame number, or an error naming the line that
+///   lacks a frame number or a title, or repeats a frame.
+///
+/// # Notes
+/// - Blank lines and lines starting with `#` are skipped.
+/// - The frame number may be followed by a comma or a tab instead of a space.
+pub fn read_markers(path: &Path) -> Result<Vec<Marker>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read markers file {}", path.display()))?;
+    let mut markers: BTreeMap<u32, String> = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || {
+            anyhow!(
+                "Line {} of {} is not a frame number followed by a title: {}",
+                index + 1,
+                path.display(),
+                line
+            )
+        };
+        let (frame, title) = line
+            .split_once(|c: char| c.is_whitespace() || c == ',')
+            .ok_or_else(invalid)?;
+        let frame: u32 = frame.parse().map_err(|_| invalid())?;
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(invalid());
+        }
+        if markers.insert(frame, title.to_string()).is_some() {
+            bail!("Frame {} is marked twice in {}", frame, path.display());
+        }
+    }
+    if markers.is_empty() {
+        bail!("Markers file {} marks no frames", path.display());
+    }
+    Ok(markers
+        .into_iter()
+        .map(|(frame, title)| Marker { frame, title })
+        .collect())
+}
+
+/// Places the markers on the timeline of the encoded frames.
+///
+/// # Parameters
+/// - `markers`: The markers, by frame number of the input.
+/// - `frames`: The input frames mapped by frame number.
+/// - `sequence`: One source file per encoded frame, in playback order.
+/// - `fps`: Frame rate of the clip.
+/// - `end_ms`: Where the clip ends, which closes the last chapter.
+///
+/// # Returns
+/// - `Vec<Chapter>`: The chapters in playback order, each lasting until the next starts.
+///
+/// # Notes
+/// - A chapter starts where its frame is first shown, so after a reversal the chapters
+///   follow the playback order rather than the markers file.
+/// - Markers on frames that are not encoded, or shown only after the clip ends, are
+///   skipped with a message.
+pub fn place_chapters(
+    markers: &[Marker],
+    frames: &BTreeMap<u32, PathBuf>,
+    sequence: &[PathBuf],
+    fps: FrameRate,
+    end_ms: u64,
+) -> Vec<Chapter> {
+    let mut starts: Vec<(u64, &str)> = Vec::new();
+    for marker in markers {
+        let start_ms = frames
+            .get(&marker.frame)
+            .and_then(|path| sequence.iter().position(|frame| frame == path))
+            .map(|position| fps.timestamp_ms(position as u64))
+            .filter(|&start_ms| start_ms < end_ms);
+        match start_ms {
+            Some(start_ms) => starts.push((start_ms, &marker.title)),
+            None => println!(
+                "Frame {} is not in the clip, skipping the chapter \"{}\"",
+                marker.frame, marker.title
+            ),
+        }
+    }
+    starts.sort_by_key(|&(start_ms, _)| start_ms);
+    // Two markers shown at once, e.g. on a frame and the gap it fills, keep the first.
+    starts.dedup_by_key(|&mut (start_ms, _)| start_ms);
+
+    let ends: Vec<u64> = starts
+        .iter()
+        .skip(1)
+        .map(|&(start_ms, _)| start_ms)
+        .chain([end_ms])
+        .collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|((start_ms, title), end_ms)| Chapter {
+            start_ms,
+            end_ms,
+            title: title.to_string(),
+        })
+        .collect()
+}
+
+/// Returns the chapters as an ffmpeg metadata file, with millisecond timestamps.
+fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        // `=`, `;`, `#`, `\` and line breaks would otherwise end the value.
+        let mut title = String::new();
+        for c in chapter.title.chars() {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                title.push('\\');
+            }
+            title.push(c);
+        }
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_ms, chapter.end_ms, title
+        ));
+    }
+    metadata
+}
+
+/// Returns the chapters as a WebVTT chapters track.
+pub fn webvtt(chapters: &[Chapter]) -> String {
+    let timestamp = |ms: u64| {
+        format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    };
+    let mut vtt = String::from("WEBVTT\n");
+    for (index, chapter) in chapters.iter().enumerate() {
+        vtt.push_str(&format!(
+            "\n{}\n{} --> {}\n{}\n",
+            index + 1,
+            timestamp(chapter.start_ms),
+            timestamp(chapter.end_ms),
+            chapter.title
+        ));
+    }
+    vtt
+}
+
+/// Writes the chapters into the metadata of an MP4 video, in place.
+///
+/// # Parameters
+/// - `video`: The finished video.
+/// - `chapters`: The chapters to embed.
+/// - `tmp_dir`: Directory receiving the metadata file.
+/// - `running`: Cleared to interrupt the process.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or is interrupted; the video is then left as
+///   it was.
+///
+/// # Notes
+/// - The streams are copied into `<video>.part`, which then replaces the video.
+pub fn embed_chapters(
+    video: &Path,
+    chapters: &[Chapter],
+    tmp_dir: &Path,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span =
+        info_span!("chapters", video = %video.display(), chapters = chapters.len()).entered();
+    let metadata_path = tmp_dir.join("chapters.txt");
+    fs::write(&metadata_path, ffmetadata(chapters))
+        .context("Failed to write the chapter metadata")?;
+    let part_path = part_file_path(video);
+
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
+    }
+    let mut child = Command::new("ffmpeg")
+        .args(["-y", "-i"])
+        .arg(video)
+        .arg("-i")
+        .arg(&metadata_path)
+        .args([
+            "-map",
+            "0",
+            "-map_metadata",
+            "1",
+            "-map_chapters",
+            "1",
+            "-c",
+            "copy",
+            "-f",
+            "mp4",
+        ])
+        .arg(&part_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
+        .context("Failed to start ffmpeg for the chapters")?;
+
+    loop {
+        if kill_requested(&running) {
+            log::debug!("Interruption requested; terminating ffmpeg process.");
+            child.kill().ok();
+            fs::remove_file(&part_path).ok();
+            return Err(ClipperError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) => {
+                if !status.success() {
+                    log::debug!("FFmpeg command failed with status: {:?}", status);
+                    fs::remove_file(&part_path).ok();
+                    return Err(ClipperError::ToolFailed {
+                        tool: "ffmpeg".to_string(),
+                        reason: status.to_string(),
+                    })
+                    .with_context(|| {
+                        format!("Failed to write the chapters into {}", video.display())
+                    });
+                }
+                break;
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+
+    fs::rename(&part_path, video).with_context(|| {
+        format!(
+            "Failed to move {} into place at {}",
+            part_path.display(),
+            video.display()
+        )
+    })?;
+    debug!("Embedded {} chapters into {:?}", chapters.len(), video);
+    Ok(())
+}
@@ -1,11 +1,9 @@
-use anyhow::{Context, Result};
-use indicatif::ProgressBar;
+use anyhow::{anyhow, Context, Result};
 use indicatif::ProgressStyle;
 use log::debug;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::exit;
 use std::process::Command;
 use std::process::Stdio;
 use std::sync::{
@@ -13,6 +11,186 @@ use std::sync::{
     Arc,
 };
 use std::{fs, thread, time::Duration};
+use tracing::info_span;
+
+use fxp_filenames::FramePadding;
+use fxp_output::kill_requested;
+use fxp_output::{progress_bar, FrameRate, ToolError};
+use fxp_stream::ColorPrimaries;
+
+use crate::error::ClipperError;
+use crate::hdr::HdrMetadata;
+use crate::quality::VideoQuality;
+use crate::sizes::{resize_frame, SizeMismatch};
+
+/// Encoder settings of the frames-to-video step.
+#[derive(Debug, Clone)]
+pub struct EncodeSettings {
+    /// Frames per second for the generated video.
+    pub fps: FrameRate,
+    /// Largest allowed width or height; frames are scaled down to fit, keeping the aspect ratio.
+    pub pixel_upper_limit: Option<u32>,
+    /// Largest allowed width; frames are scaled down to it, keeping the aspect ratio.
+    pub max_width: Option<u32>,
+    /// How many times a GIF or APNG plays, 0 for forever; a video ignores it.
+    pub loop_count: u32,
+    /// Codec and rate control of the video; animations and previews ignore it.
+    pub quality: VideoQuality,
+    /// Milliseconds each frame is shown, in order, instead of `1 / fps`; only the video
+    /// of `create_video_without_audio` follows them.
+    pub frame_durations: Option<Vec<u64>>,
+    /// Primaries of the frames' colors; the video is converted to YUV and tagged for
+    /// them. Animations and previews ignore it.
+    pub primaries: ColorPrimaries,
+    /// Tags and mastering metadata of the HDR video the frames came from, written into
+    /// the video instead of those of `primaries`; needs 10-bit H.265 `quality`.
+    pub hdr: Option<HdrMetadata>,
+    /// Seconds between forced keyframes, so the video can be cut into segments of that
+    /// length without encoding again; `None` leaves them to the encoder.
+    pub keyframe_seconds: Option<u32>,
+}
+
+impl EncodeSettings {
+    /// Returns the ffmpeg filter scaling the frames down to the limits, `null` without any.
+    ///
+    /// # Parameters
+    /// - `even`: Keep both dimensions even, as yuv420p video needs.
+    pub fn scale_filter(&self, even: bool) -> String {
+        let mut filters = Vec::new();
+        if let Some(limit) = self.pixel_upper_limit {
+            filters.push(format!(
+                "scale=w='min({0},iw)':h='min({0},ih)':force_original_aspect_ratio=decrease{1}",
+                limit,
+                if even { ":force_divisible_by=2" } else { "" }
+            ));
+        }
+        if let Some(width) = self.max_width {
+            filters.push(format!(
+                "scale=w='min({},iw)':h={}",
+                width,
+                if even { -2 } else { -1 }
+            ));
+        }
+        if filters.is_empty() {
+            "null".to_string()
+        } else {
+            filters.join(",")
+        }
+    }
+
+    /// Returns the ffmpeg filter of a video: `scale_filter`, then the conversion to
+    /// limited range YUV, in the pixel format of `quality`, with the matrix of the
+    /// primaries or of `hdr`.
+    ///
+    /// # Notes
+    /// - Left to itself, ffmpeg converts RGB frames with the BT.601 matrix and tags
+    ///   nothing, so players shift the colors.
+    pub fn video_filter(&self) -> String {
+        let matrix = match (&self.hdr, self.primaries) {
+            (Some(hdr), _) => hdr.matrix(),
+            (None, ColorPrimaries::Bt709 | ColorPrimaries::DisplayP3) => "bt709",
+            (None, ColorPrimaries::Bt2020) => "bt2020",
+        };
+        let conversion = format!(
+            "scale=out_color_matrix={}:out_range=tv,format={}",
+            matrix,
+            self.quality.pixel_format()
+        );
+        match self.scale_filter(true).as_str() {
+            "null" => conversion,
+            scale => format!("{},{}", scale, conversion),
+        }
+    }
+
+    /// Returns the ffmpeg output options forcing the keyframes of `keyframe_seconds`.
+    pub fn keyframe_args(&self) -> Vec<String> {
+        match self.keyframe_seconds {
+            Some(seconds) => vec![
+                "-force_key_frames".to_string(),
+                format!("expr:gte(t,n_forced*{})", seconds),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the ffmpeg output options tagging a video with the color of its frames.
+    ///
+    /// # Notes
+    /// - The frames are taken to follow the sRGB transfer curve, as images do, unless
+    ///   `hdr` is set: frames exported from an HDR video keep its transfer curve, so the
+    ///   video is tagged with the tags of that video.
+    pub fn color_args(&self) -> Vec<String> {
+        if let Some(hdr) = &self.hdr {
+            return hdr.color_args();
+        }
+        let (primaries, colorspace) = match self.primaries {
+            ColorPrimaries::Bt709 => ("bt709", "bt709"),
+            ColorPrimaries::DisplayP3 => ("smpte432", "bt709"),
+            ColorPrimaries::Bt2020 => ("bt2020", "bt2020nc"),
+        };
+        [
+            "-color_primaries",
+            primaries,
+            "-color_trc",
+            "iec61966-2-1",
+            "-colorspace",
+            colorspace,
+            "-color_range",
+            "tv",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+}
+
+/// The audio laid under a clip.
+#[derive(Debug, Clone, Copy)]
+pub struct AudioTrack<'a> {
+    /// The audio file.
+    pub path: &'a Path,
+    /// Duration in milliseconds to trim the final video to.
+    pub duration_ms: u64,
+    /// Milliseconds to delay the audio by relative to the frames; negative to advance it.
+    pub offset_ms: i64,
+    /// Integrated loudness in LUFS to normalize the audio to; `None` keeps it as is.
+    pub loudness: Option<i32>,
+}
+
+/// Returns the ffmpeg audio filter normalizing the loudness to `target` LUFS.
+///
+/// # Notes
+/// - Uses the single-pass EBU R128 `loudnorm` filter, with a true peak of -1.5 dBTP and
+///   a loudness range of 11 LU.
+/// - `loudnorm` resamples to 192 kHz internally, so the output is set back to 48 kHz.
+pub fn loudnorm_args(target: i32) -> Vec<String> {
+    vec![
+        "-af".into(),
+        format!("loudnorm=I={}:TP=-1.5:LRA=11", target),
+        "-ar".into(),
+        "48000".into(),
+    ]
+}
+
+/// Returns the ffmpeg input options shifting the audio input that follows them.
+///
+/// # Parameters
+/// - `offset_ms`: Milliseconds to delay the audio by; negative to advance it.
+///
+/// # Returns
+/// - `Vec<String>`: `-itsoffset` to delay the audio, `-ss` to advance it, or nothing.
+///
+/// # Notes
+/// - Advancing seeks into the audio instead of using a negative `-itsoffset`, which the
+///   MP4 muxer would undo by shifting the video along with it.
+pub fn audio_offset_args(offset_ms: i64) -> Vec<String> {
+    let seconds = format!("{:.3}", offset_ms.unsigned_abs() as f64 / 1000.0);
+    match offset_ms {
+        0 => Vec::new(),
+        ms if ms > 0 => vec!["-itsoffset".into(), seconds],
+        _ => vec!["-ss".into(), seconds],
+    }
+}
 
 /// Creates a video clip from images, optionally merges audio, and trims the result.
 ///
@@ -20,11 +198,12 @@ use std::{fs, thread, time::Duration};
 /// merging it with an optional audio file, and trimming the final video to a specified duration.
 ///
 /// # Parameters
-/// - `input_dir`: Directory containing image files to process.
+/// - `frame_pattern`: ffmpeg input pattern of the staged frames, e.g. `dir/frame_%04d.png`.
 /// - `output_path`: Path where the final video file will be saved.
-/// - `mp3_path`: Optional path to an MP3 audio file for merging.
-/// - `fps`: Frames per second for the generated video.
-/// - `duration`: Optional duration to trim the final video (required if MP3 is provided).
+/// - `audio`: Optional audio file for merging, with the duration to trim the final
+///   video to and the offset of the audio.
+/// - `encode`: Frame rate and optional size limit of the generated video.
+/// - `append_to`: An existing video the new frames are appended to, if any.
 /// - `running`: A handle to check if the process should continue running.
 /// - `tmp_dir_path`: Temporary directory for intermediate files.
 ///
@@ -32,70 +211,236 @@ use std::{fs, thread, time::Duration};
 /// - `Result<PathBuf>`: Path to the created video file, or an error if something fails.
 ///
 /// # Notes
+/// - When appending, the video without audio is joined to the end of `append_to` before
+///   the audio is merged, so the audio covers the whole video; the audio `append_to`
+///   already had is dropped.
 /// - If an MP3 path is provided, the function will:
 ///   1. Create a video without audio.
 ///   2. Merge the video with the audio.
 ///   3. Trim the merged video to the specified duration.
 /// - If no MP3 is provided, the function will only create and copy the video without audio.
 /// - The progress bar tracks the three main processing steps.
+/// - The video is written to `<output>.part` and renamed to `output_path` only once it is
+///   complete, so a failed or interrupted run leaves no file behind.
 pub fn make_clip(
-    input_dir: &Path,
+    frame_pattern: &Path,
     output_path: &Path,
-    mp3_path: Option<&Path>,
-    fps: u32,
-    duration: Option<u64>,
+    audio: Option<AudioTrack>,
+    encode: &EncodeSettings,
+    append_to: Option<&Path>,
     running: Arc<AtomicBool>,
     tmp_dir_path: &Path,
 ) -> Result<PathBuf> {
     // Create one progress bar with 3 steps.
-    let pb = ProgressBar::new(3);
+    let pb = progress_bar(3);
     let style = ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
+        .template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
+        )
         .context("Failed to set progress bar template")?;
     pb.set_style(style);
 
     // Step 1: Create video without audio.
     pb.set_message("Creating video without audio...");
-    let video_path_no_audio =
-        create_video_without_audio(input_dir, fps, tmp_dir_path, output_path, running.clone());
+    let video_path_no_audio = create_video_without_audio(
+        frame_pattern,
+        encode,
+        tmp_dir_path,
+        output_path,
+        running.clone(),
+    )?;
     debug!("Video without audio created at: {:?}", video_path_no_audio);
+    let video_path_no_audio = match append_to {
+        Some(existing) => {
+            pb.set_message("Appending to the existing video...");
+            append_video(
+                existing,
+                &video_path_no_audio,
+                tmp_dir_path,
+                running.clone(),
+            )?
+        }
+        None => video_path_no_audio,
+    };
     pb.inc(1);
     pb.set_message("Video without audio created.");
 
-    // Check if we have an MP3 file for audio merging.
-    if let Some(mp3) = mp3_path {
-        // Step 2: Merge video and audio.
-        pb.set_message("Merging video and audio...");
-        let merged_video_path = merge_video_audio(&video_path_no_audio, mp3, running.clone());
-        debug!("Video and audio merged at: {:?}", merged_video_path);
-        pb.inc(1);
-        pb.set_message("Audio merged with video.");
-
-        // Step 3: Trim the merged video.
-        let duration = duration.expect("duration must be provided");
-        let trimmed_video_path = trim_merged_video(
-            merged_video_path,
-            duration,
-            output_path.to_path_buf(),
-            running.clone(),
-        )?;
-        debug!("Trimmed video saved at: {:?}", trimmed_video_path);
-        pb.inc(1);
-        pb.finish();
-        Ok(output_path.to_path_buf())
-    } else {
-        // When no MP3 is provided, we simulate the remaining two steps.
-        pb.set_message("No MP3 provided. Copying video without audio to output...");
-        fs::copy(&video_path_no_audio, output_path)
-            .context("Failed to copy video without audio to output directory")?;
-        debug!("Video without audio copied to output path: {:?}", output_path);
-        // We still want to complete the progress bar (steps 2 and 3).
-        pb.inc(2);
-        pb.finish();
-        Ok(output_path.to_path_buf())
+    // Write the final video next to the output and move it into place once complete.
+    let part_path = part_file_path(output_path);
+    let written = match audio {
+        // Check if we have an MP3 file for audio merging.
+        Some(audio) => {
+            // Step 2: Merge video and audio.
+            pb.set_message("Merging video and audio...");
+            merge_video_audio(&video_path_no_audio, &audio, running.clone()).and_then(
+                |merged_video_path| {
+                    debug!("Video and audio merged at: {:?}", merged_video_path);
+                    pb.inc(1);
+                    pb.set_message("Audio merged with video.");
+
+                    // Step 3: Trim the merged video.
+                    trim_merged_video(
+                        merged_video_path,
+                        audio.duration_ms,
+                        part_path.clone(),
+                        running.clone(),
+                    )
+                    .map(|_| pb.inc(1))
+                },
+            )
+        }
+        None => {
+            // When no MP3 is provided, we simulate the remaining two steps.
+            pb.set_message("No MP3 provided. Copying video without audio to output...");
+            fs::copy(&video_path_no_audio, &part_path)
+                .context("Failed to copy video without audio to output directory")
+                // We still want to complete the progress bar (steps 2 and 3).
+                .map(|_| pb.inc(2))
+        }
+    };
+
+    if let Err(e) = written {
+        discard_part_file(&part_path);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&part_path, output_path) {
+        discard_part_file(&part_path);
+        return Err(e).with_context(|| {
+            format!(
+                "Failed to move {} into place at {}",
+                part_path.display(),
+                output_path.display()
+            )
+        });
+    }
+    pb.finish();
+    debug!("Final video saved at: {:?}", output_path);
+
+    Ok(output_path.to_path_buf())
+}
+
+/// Returns the in-progress path of an output file: `<output>.part` in the same directory.
+///
+/// Keeping it in the output's directory makes the final rename atomic.
+pub(crate) fn part_file_path(output_path: &Path) -> PathBuf {
+    let mut file_name = output_path
+        .file_name()
+        .unwrap_or_else(|| OsStr::new("output.mp4"))
+        .to_os_string();
+    file_name.push(".part");
+    output_path.with_file_name(file_name)
+}
+
+/// Removes a leftover `.part` file after a failure, ignoring one that was never written.
+fn discard_part_file(part_path: &Path) {
+    if part_path.exists() {
+        debug!("Removing incomplete output: {:?}", part_path);
+        if let Err(e) = fs::remove_file(part_path) {
+            debug!("Failed to remove incomplete output {:?}: {}", part_path, e);
+        }
     }
 }
 
+/// Stages frames under the consecutive `frame_0001.<ext>` names ffmpeg reads.
+///
+/// Each frame is linked (or copied where links are unavailable) into `staging_dir`
+/// as `frame_<number>.{ext}`, so the user's input directory is never renamed.
+///
+/// # Parameters
+/// - `frames`: Source files in playback order.
+/// - `staging_dir`: Empty directory receiving the staged frames.
+/// - `resize`: Frames to resize to the size most frames share, see `find_size_mismatch`.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The ffmpeg input pattern of the staged frames.
+///
+/// # Notes
+/// - Staged frames are numbered consecutively from 1, matching ffmpeg's `-start_number 1`,
+///   padded as wide as the input frames are or the number of frames needs, at least to
+///   four digits; see `FramePadding`.
+/// - A source may appear more than once, e.g. when gaps are filled.
+/// - When all frames share one extension (png, jpg, webp, tiff, ...) they are staged as-is.
+///   Mixed extensions cannot share one ffmpeg pattern, so every frame is converted to PNG.
+/// - A frame to resize is decoded and written resized instead of linked.
+pub fn stage_frames(
+    frames: &[PathBuf],
+    staging_dir: &Path,
+    resize: Option<&SizeMismatch>,
+) -> Result<PathBuf> {
+    debug!(
+        "Staging {} frames into {}",
+        frames.len(),
+        staging_dir.display()
+    );
+
+    let extension = common_extension(frames);
+    debug!("Common frame extension: {:?}", extension);
+    let padding = FramePadding::detect(frames.iter().map(PathBuf::as_path))
+        .max(FramePadding::for_count(frames.len() as u64));
+    debug!("Staging with {} digit frame numbers", padding.width());
+
+    for (index, source) in frames.iter().enumerate() {
+        let number = index as u64 + 1;
+        if let Some(resize) = resize.filter(|resize| resize.needs_resize(source)) {
+            let extension = extension.as_deref().unwrap_or("png");
+            let staged = staging_dir.join(padding.file_name("frame", number, extension));
+            resize_frame(source, &staged, resize.size)?;
+            continue;
+        }
+        let source = fs::canonicalize(source)
+            .with_context(|| format!("Failed to resolve frame {}", source.display()))?;
+        match extension.as_deref() {
+            Some(extension) => {
+                let staged = staging_dir.join(padding.file_name("frame", number, extension));
+                link_or_copy(&source, &staged).with_context(|| {
+                    format!(
+                        "Failed to stage frame {} as {}",
+                        source.display(),
+                        staged.display()
+                    )
+                })?;
+            }
+            None => {
+                let staged = staging_dir.join(padding.file_name("frame", number, "png"));
+                image::open(&source)
+                    .with_context(|| format!("Failed to decode frame {}", source.display()))?
+                    .save(&staged)
+                    .with_context(|| format!("Failed to convert frame {}", source.display()))?;
+            }
+        }
+    }
+
+    let pattern_extension = extension.unwrap_or_else(|| "png".to_string());
+    Ok(staging_dir.join(padding.pattern("frame", &pattern_extension)))
+}
+
+/// Returns the lowercase extension shared by all frames, or `None` if they differ.
+fn common_extension(frames: &[PathBuf]) -> Option<String> {
+    let mut extensions = frames.iter().map(|frame| {
+        frame
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+    });
+    let first = extensions.next()??;
+    extensions
+        .all(|extension| extension.as_deref() == Some(first.as_str()))
+        .then_some(first)
+}
+
+/// Links `source` to `destination`, falling back to a copy.
+#[cfg(unix)]
+fn link_or_copy(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+        .or_else(|_| fs::copy(source, destination).map(|_| ()))
+}
+
+/// Links `source` to `destination`, falling back to a copy.
+#[cfg(not(unix))]
+fn link_or_copy(source: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::hard_link(source, destination).or_else(|_| fs::copy(source, destination).map(|_| ()))
+}
+
 /// Creates a video from image frames without audio using ffmpeg.
 ///
 /// This function takes a directory of image frames, processes them into a video
@@ -103,39 +448,49 @@ pub fn make_clip(
 /// includes progress tracking and supports cancellation.
 ///
 /// # Parameters
-/// - `input_dir`: Directory containing the image frames to process.
-/// - `fps`: Frame rate for the output video.
+/// - `frame_pattern`: ffmpeg input pattern of the image frames, e.g. `dir/frame_%04d.jpg`.
+/// - `encode`: Frame rate, optional size limits and quality of the output video.
 /// - `tmp_dir`: Temporary directory to store the output video.
 /// - `output_path`: Desired output filename for the video.
 /// - `running`: Flag to check if the process should continue running.
 ///
 /// # Returns
-/// - `PathBuf`: Path to the created video file.
+/// - `Result<PathBuf>`: Path to the created video file, or an error if ffmpeg is missing,
+///   fails or the run is interrupted.
 ///
 /// # Notes
 /// - The function assumes image frames follow a zero-padded numbering format.
 /// - Supports cancellation via the `running` flag.
 /// - The output filename will have a `_no_audio` suffix.
+/// - With `encode.quality.two_pass`, a first pass writes the rate statistics into
+///   `tmp_dir` and the second encodes with them.
+/// - With `encode.frame_durations`, the frames are read through a concat list giving
+///   each its duration, and their timestamps are kept as they are.
+/// - The frames are converted to YUV with the matrix of `encode.primaries`, and the
+///   video is tagged with their primaries, transfer curve and matrix; with
+///   `encode.hdr`, with the HDR video's tags, and its mastering metadata is written
+///   into the stream.
 pub fn create_video_without_audio(
-    input_dir: &Path,
-    fps: u32,
+    frame_pattern: &Path,
+    encode: &EncodeSettings,
     tmp_dir: &Path,
     output_path: &Path,
     running: Arc<AtomicBool>,
-) -> PathBuf {
+) -> Result<PathBuf> {
     debug!("Starting video creation process without audio...");
-
-    // Ensure correct frame pattern with zero-padded four-digit numbering
-    let frame_pattern = input_dir
-        .join("frame_%04d.png")
-        .to_string_lossy()
-        .to_string();
-    debug!("Input frame pattern: {}", frame_pattern);
+    let _span =
+        info_span!("encode", frames = %frame_pattern.display(), fps = %encode.fps).entered();
+    debug!("Input frame pattern: {}", frame_pattern.display());
 
     // Convert fps to a string for ffmpeg.
-    let fps_str = fps.to_string();
+    let fps_str = encode.fps.ffmpeg_arg();
     debug!("Using FPS: {}", fps_str);
 
+    // Scale down to the limits when any is set, keeping even dimensions for yuv420p, and
+    // convert to YUV for the primaries of the frames.
+    let video_filter = encode.video_filter();
+    debug!("Using video filter: {}", video_filter);
+
     // Extract the file stem from output_path and create a new filename with _no_audio suffix.
     let file_stem = output_path
         .file_stem()
@@ -143,62 +498,183 @@ pub fn create_video_without_audio(
         .to_string_lossy();
     let new_filename = format!("{}_no_audio.mp4", file_stem);
     let output_file = tmp_dir.join(new_filename);
-    let output_filename = output_file.to_string_lossy().to_string();
-    debug!("Output video file: {}", output_filename);
+    debug!("Output video file: {:?}", output_file);
+
+    let input: Vec<OsString> = match &encode.frame_durations {
+        Some(durations) => vec![
+            "-f".into(),
+            "concat".into(),
+            "-safe".into(),
+            "0".into(),
+            "-i".into(),
+            timed_frames_list(frame_pattern, durations, tmp_dir)?.into(),
+            "-vf".into(),
+            video_filter.into(),
+            "-fps_mode".into(),
+            "passthrough".into(),
+        ],
+        None => vec![
+            "-framerate".into(),
+            fps_str.into(),
+            "-start_number".into(),
+            "1".into(),
+            "-i".into(),
+            frame_pattern.into(),
+            "-vf".into(),
+            video_filter.into(),
+        ],
+    };
+    let pass_log = tmp_dir.join("encode_pass");
+    let x265_params = encode
+        .hdr
+        .as_ref()
+        .map(HdrMetadata::x265_params)
+        .unwrap_or_default();
+    if encode.quality.two_pass {
+        // The first pass only measures the frames; its video is thrown away.
+        debug!("Spawning ffmpeg process for the first pass...");
+        let mut first_pass = Command::new("ffmpeg");
+        first_pass
+            .args(&input)
+            .args(
+                encode
+                    .quality
+                    .encoder_args(Some(1), &pass_log, &x265_params),
+            )
+            .args(["-pix_fmt", encode.quality.pixel_format()])
+            .args(encode.color_args())
+            .args(encode.keyframe_args())
+            .args(["-an", "-f", "null", "-"]);
+        run_encode(first_pass, &running)?;
+    }
 
     // Spawn the ffmpeg process.
     debug!("Spawning ffmpeg process to create video...");
-    let mut child = Command::new("ffmpeg")
-        .args(&[
-            "-framerate",
-            &fps_str,
-            "-start_number",
-            "1",
-            "-i",
-            &frame_pattern,
-            "-c:v",
-            "libx264",
-            "-pix_fmt",
-            "yuv420p",
-            &output_filename,
-        ])
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(input)
+        .args(encode.quality.encoder_args(
+            encode.quality.two_pass.then_some(2),
+            &pass_log,
+            &x265_params,
+        ))
+        .args(["-pix_fmt", encode.quality.pixel_format()])
+        .args(encode.color_args())
+        .args(encode.keyframe_args())
+        .arg(&output_file);
+    run_encode(command, &running)?;
+
+    debug!("Audio-free video saved as {:?}", output_file);
+    Ok(output_file)
+}
+
+/// Writes the concat list showing each staged frame for its duration.
+///
+/// # Parameters
+/// - `frame_pattern`: ffmpeg input pattern of the staged frames, see `stage_frames`.
+/// - `durations`: Milliseconds each frame is shown, one per staged frame.
+/// - `tmp_dir`: Directory receiving the list.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The path of the list.
+///
+/// # Notes
+/// - The concat demuxer ignores the duration of the last entry, so the last frame is
+///   listed a second time.
+fn timed_frames_list(frame_pattern: &Path, durations: &[u64], tmp_dir: &Path) -> Result<PathBuf> {
+    // The staged frames are numbered with one padding, so their names sort in order.
+    let staging_dir = frame_pattern
+        .parent()
+        .context("The frame pattern has no directory")?;
+    let mut frames: Vec<PathBuf> = fs::read_dir(staging_dir)
+        .with_context(|| format!("Failed to read staged frames in {}", staging_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    frames.sort();
+    if frames.len() != durations.len() {
+        return Err(anyhow!(
+            "{} frames are staged but {} have durations",
+            frames.len(),
+            durations.len()
+        ));
+    }
+
+    let mut list = String::from("ffconcat version 1.0\n");
+    let mut last = String::new();
+    for (frame, duration) in frames.iter().zip(durations) {
+        last = concat_entry(frame)?;
+        list.push_str(&last);
+        list.push_str(&format!("duration {:.3}\n", *duration as f64 / 1000.0));
+    }
+    list.push_str(&last);
+
+    let list_path = tmp_dir.join("timed_frames.txt");
+    fs::write(&list_path, list).context("Failed to write the list of timed frames")?;
+    debug!(
+        "Listed {} frames with their durations in {:?}",
+        durations.len(),
+        list_path
+    );
+    Ok(list_path)
+}
+
+/// Returns the `file` line of a concat list naming `path`.
+///
+/// # Notes
+/// - Paths in a concat list are quoted, with quotes escaped as `'\''`.
+fn concat_entry(path: &Path) -> Result<String> {
+    let path = fs::canonicalize(path)
+        .map(without_verbatim_prefix)
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+    Ok(format!(
+        "file '{}'\n",
+        path.to_string_lossy().replace('\'', "'\\''")
+    ))
+}
+
+/// Runs an encoding ffmpeg command, failing if it fails or is interrupted.
+pub(crate) fn run_encode(mut command: Command, running: &AtomicBool) -> Result<()> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
+    }
+    let mut child = command
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .expect("Failed to spawn ffmpeg process");
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
+        .context("Failed to spawn ffmpeg to encode the video")?;
 
     // Poll the process periodically, checking for interruption.
     loop {
-        if running.load(Ordering::Relaxed) {
+        if kill_requested(running) {
             // Attempt to kill the ffmpeg process.
             if let Err(e) = child.kill() {
                 debug!("Failed to kill ffmpeg process: {}", e);
             }
-            eprintln!("Video creation interrupted by user.");
-            exit(1);
+            return Err(ClipperError::Interrupted.into());
         }
-        match child.try_wait() {
-            Ok(Some(status)) => {
+        match child
+            .try_wait()
+            .context("Error while checking ffmpeg process")?
+        {
+            Some(status) => {
                 debug!("ffmpeg command finished with status: {}", status);
                 if !status.success() {
-                    eprintln!("ffmpeg command failed");
-                    exit(1);
+                    return Err(ClipperError::ToolFailed {
+                        tool: "ffmpeg".to_string(),
+                        reason: status.to_string(),
+                    })
+                    .context("Failed to encode the video");
                 }
                 break;
             }
-            Ok(None) => {
+            None => {
                 // Process still running. Sleep a little before polling again.
                 std::thread::sleep(Duration::from_millis(100));
             }
-            Err(e) => {
-                eprintln!("Error while checking ffmpeg process: {}", e);
-                exit(1);
-            }
         }
     }
-
-    debug!("Audio-free video saved as {}", output_filename);
-    output_file
+    Ok(())
 }
 
 /// Merges a video file with an audio file using FFmpeg.
@@ -208,26 +684,30 @@ pub fn create_video_without_audio(
 ///
 /// # Parameters
 /// - `video_path`: The path to the video file to process.
-/// - `mp3_path`: The path to the audio file to merge.
+/// - `audio`: The audio file to merge, with its offset and loudness target.
 /// - `running`: A flag indicating whether the operation should continue.
 ///
 /// # Returns
-/// - `PathBuf`: The path to the merged output file.
+/// - `Result<PathBuf>`: The path to the merged output file, or an error if ffmpeg is
+///   missing, fails or the run is interrupted.
 ///
 /// # Notes
 /// - The output file is placed in the same directory as the video file, named with "_videoclipped" appended.
 /// - If an output file already exists at the target path, it will be deleted before creating a new one.
 /// - FFmpeg is used with standard settings for video copying and audio re-encoding.
-/// - The process can be interrupted by setting the `running` flag.
+/// - The offset is applied to the audio input, see `audio_offset_args`.
+/// - With a loudness target, the audio is normalized while it is encoded, see `loudnorm_args`.
+/// - The process can be interrupted by clearing the `running` flag.
 pub fn merge_video_audio(
-    video_path: &PathBuf,
-    mp3_path: &Path,
+    video_path: &Path,
+    audio: &AudioTrack,
     running: Arc<AtomicBool>,
-) -> PathBuf {
-    log::debug!(
+) -> Result<PathBuf> {
+    let _span =
+        info_span!("mux", video = %video_path.display(), audio = %audio.path.display()).entered();
+    debug!(
         "Starting merge of video: {:?} and audio: {:?}",
-        video_path,
-        mp3_path
+        video_path, audio.path
     );
 
     // Determine the parent directory (defaulting to the current directory if unavailable)
@@ -253,7 +733,7 @@ pub fn merge_video_audio(
             "Output file already exists at {:?}, deleting it...",
             output_path
         );
-        fs::remove_file(&output_path).expect("Failed to remove existing merged video file");
+        fs::remove_file(&output_path).context("Failed to remove existing merged video file")?;
         log::debug!("Existing output file deleted successfully.");
     }
 
@@ -262,55 +742,59 @@ pub fn merge_video_audio(
         output_path
     );
 
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
+    }
     // Start the ffmpeg command as a child process so that we can monitor it
     let mut child = Command::new("ffmpeg")
-        .args(&[
-            "-y",
-            "-i",
-            video_path.to_str().expect("Invalid video path"),
-            "-i",
-            mp3_path.to_str().expect("Invalid mp3 path"),
-            "-c:v",
-            "copy",
-            "-c:a",
-            "aac",
-            output_path.to_str().expect("Invalid output path"),
-        ])
+        .args(["-y", "-i"])
+        .arg(video_path)
+        .args(audio_offset_args(audio.offset_ms))
+        .arg("-i")
+        .arg(audio.path)
+        .args(["-c:v", "copy", "-c:a", "aac"])
+        .args(audio.loudness.map(loudnorm_args).unwrap_or_default())
+        .arg(&output_path)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .expect("Failed to spawn ffmpeg process");
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
+        .context("Failed to spawn ffmpeg to merge video and audio")?;
 
     // Periodically poll the child process while also checking for interruption
     loop {
         // Check if the process has finished
-        match child.try_wait() {
-            Ok(Some(status)) => {
+        match child
+            .try_wait()
+            .context("Error attempting to wait for ffmpeg process")?
+        {
+            Some(status) => {
                 if !status.success() {
                     log::debug!("FFmpeg command failed with status: {:?}", status);
-                    panic!("Failed to merge video and audio");
+                    return Err(ClipperError::ToolFailed {
+                        tool: "ffmpeg".to_string(),
+                        reason: status.to_string(),
+                    })
+                    .context("Failed to merge video and audio");
                 }
                 break;
             }
-            Ok(None) => {
+            None => {
                 // Check for interruption
-                if running.load(Ordering::Relaxed) {
+                if kill_requested(&running) {
                     log::debug!("Interrupt flag detected. Terminating ffmpeg process.");
-                    child.kill().expect("Failed to kill ffmpeg process");
-                    panic!("Merge operation interrupted by user");
+                    child.kill().ok();
+                    return Err(ClipperError::Interrupted.into());
                 }
                 // Sleep for a short duration before checking again
                 thread::sleep(Duration::from_millis(100));
             }
-            Err(e) => {
-                panic!("Error attempting to wait for ffmpeg process: {}", e);
-            }
         }
     }
 
     debug!("Merged audio and video saved as {:?}", output_path);
 
-    output_path
+    Ok(output_path)
 }
 
 /// Trims a merged video using ffmpeg to a specified duration.
@@ -328,7 +812,7 @@ pub fn merge_video_audio(
 /// - `Result<PathBuf>`: Path to the trimmed video file on success.
 ///
 /// # Notes
-/// - The function uses a temporary file to ensure proper formatting.
+/// - The output is always written as MP4, whatever its extension, so it can be a `.part` file.
 /// - Interrupts the process if the `running` flag is set to false.
 pub fn trim_merged_video(
     video_path: std::path::PathBuf,
@@ -336,52 +820,47 @@ pub fn trim_merged_video(
     output_path: std::path::PathBuf,
     running: Arc<AtomicBool>,
 ) -> anyhow::Result<std::path::PathBuf> {
-    // Preserve the original output path.
-    let original_output = output_path.clone();
-
-    // Determine the temporary output path that guarantees a .mp4 extension.
-    let tmp_output = get_tmp_output_path(&output_path);
-
     // Convert the duration from milliseconds to seconds (ffmpeg expects seconds).
     let duration_secs = (duration_ms as f64) / 1000.0;
 
-    log::debug!(
+    let _span = info_span!(
+        "cut",
+        input = %video_path.display(),
+        output = %output_path.display(),
+        seconds = %duration_secs
+    )
+    .entered();
+    debug!(
         "Trimming video at {} to {} seconds ({} ms)",
         video_path.display(),
         duration_secs,
         duration_ms
     );
-    log::debug!("Output path for trimmed video: {}", tmp_output.display());
+    debug!("Output path for trimmed video: {}", output_path.display());
 
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
+    }
     // Build the ffmpeg command
     let mut child = Command::new("ffmpeg")
-        .args(&[
-            "-y",
-            "-i",
-            video_path
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("Invalid video path"))?,
-            "-t",
-            &duration_secs.to_string(),
-            "-c",
-            "copy",
-            tmp_output
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("Invalid temporary output path"))?,
-        ])
+        .args(["-y", "-i"])
+        .arg(video_path)
+        .args(["-t", &duration_secs.to_string(), "-c", "copy", "-f", "mp4"])
+        .arg(&output_path)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
         .context("Failed to start ffmpeg for trimming")?;
 
     // Periodically check for an interruption.
     loop {
         // Check if the running flag was triggered.
-        if running.load(Ordering::Relaxed) {
+        if kill_requested(&running) {
             log::debug!("Interruption requested; terminating ffmpeg process.");
             // Kill the ffmpeg process.
             child.kill().ok();
-            return Err(anyhow::anyhow!("Operation interrupted by user"));
+            return Err(ClipperError::Interrupted.into());
         }
 
         // Check if the child process has exited.
@@ -389,7 +868,11 @@ pub fn trim_merged_video(
             Some(status) => {
                 if !status.success() {
                     log::debug!("FFmpeg command failed with status: {:?}", status);
-                    return Err(anyhow::anyhow!("Failed to trim merged video"));
+                    return Err(ClipperError::ToolFailed {
+                        tool: "ffmpeg".to_string(),
+                        reason: status.to_string(),
+                    })
+                    .context("Failed to trim merged video");
                 }
                 break;
             }
@@ -400,66 +883,88 @@ pub fn trim_merged_video(
         }
     }
 
-    // If a temporary file was used, rename it to the original output path.
-    rename_output_file_if_needed(&tmp_output, &original_output)?;
+    log::debug!("Final video trimmed and saved to {}", output_path.display());
 
-    log::debug!(
-        "Final video trimmed and saved to {}",
-        original_output.display()
-    );
-
-    Ok(original_output)
+    Ok(output_path)
 }
 
-/// Ensures the output path ends with .mp4 extension.
-///
-/// This function validates and adjusts the output path to ensure it has a .mp4 extension.
+/// Joins a video to the end of another one with ffmpeg's concat demuxer.
 ///
 /// # Parameters
-/// - `output_path`: The input path to be validated and potentially modified.
+/// - `existing`: The video to append to.
+/// - `video_path`: The video appended to its end; it must have the same codec, frame
+///   rate and size, as the videos of one Clipper configuration do.
+/// - `tmp_dir`: Directory receiving the concat list and the joined video.
+/// - `running`: Cleared to interrupt the process.
 ///
 /// # Returns
-/// - `PathBuf`: The normalized path with a .mp4 extension.
+/// - `Result<PathBuf>`: The joined video, without audio.
 ///
 /// # Notes
-/// - If the original path already has a .mp4 extension, it is returned unchanged.
-fn get_tmp_output_path(output_path: &Path) -> PathBuf {
-    if output_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_lowercase())
-        != Some("mp4".to_string())
-    {
-        // Replace or append with ".mp4".
-        output_path.with_extension("mp4")
-    } else {
-        output_path.to_owned()
+/// - The streams are copied, not re-encoded, so appending costs no quality.
+pub fn append_video(
+    existing: &Path,
+    video_path: &Path,
+    tmp_dir: &Path,
+    running: Arc<AtomicBool>,
+) -> Result<PathBuf> {
+    let _span = info_span!("append", existing = %existing.display(), video = %video_path.display())
+        .entered();
+
+    let list_path = tmp_dir.join("append_list.txt");
+    fs::write(
+        &list_path,
+        concat_entry(existing)? + &concat_entry(video_path)?,
+    )
+    .context("Failed to write the concat list")?;
+    let output_path = tmp_dir.join("appended.mp4");
+
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClipperError::Interrupted.into());
     }
+    let mut child = Command::new("ffmpeg")
+        .args(["-y", "-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-map", "0:v", "-c", "copy"])
+        .arg(&output_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ClipperError::spawn("ffmpeg", e))
+        .context("Failed to start ffmpeg for appending")?;
+
+    loop {
+        if kill_requested(&running) {
+            log::debug!("Interruption requested; terminating ffmpeg process.");
+            child.kill().ok();
+            return Err(ClipperError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) => {
+                if !status.success() {
+                    log::debug!("FFmpeg command failed with status: {:?}", status);
+                    return Err(ClipperError::ToolFailed {
+                        tool: "ffmpeg".to_string(),
+                        reason: status.to_string(),
+                    })
+                    .with_context(|| format!("Failed to append to {}", existing.display()));
+                }
+                break;
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+
+    debug!("Appended video saved as {:?}", output_path);
+    Ok(output_path)
 }
 
-/// Renames a temporary output file to its original name if necessary.
-///
-/// This function checks if the temporary output path differs from the original
-/// output path, and if so, renames the file accordingly.
-///
-/// # Parameters
-/// - `tmp_output`: The temporary output file path.
-/// - `original_output`: The original output file path.
-///
-/// # Returns
-/// - `Result<()>`: Indicates success or failure of the rename operation.
-///
-/// # Notes
-/// - No action is taken if `tmp_output` and `original_output` are the same.
-fn rename_output_file_if_needed(tmp_output: &Path, original_output: &Path) -> Result<()> {
-    if tmp_output != original_output {
-        fs::rename(&tmp_output, &original_output).with_context(|| {
-            format!(
-                "Failed to rename {} to {}",
-                tmp_output.display(),
-                original_output.display()
-            )
-        })?;
+/// Strips the `\\?\` prefix `fs::canonicalize` gives Windows paths, which ffmpeg does not
+/// read in a concat list; other paths are returned as they are.
+fn without_verbatim_prefix(path: PathBuf) -> PathBuf {
+    match path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+        // Only drive paths; a verbatim UNC path needs its prefix.
+        Some(stripped) if stripped.as_bytes().get(1) == Some(&b':') => PathBuf::from(stripped),
+        _ => path,
     }
-    Ok(())
 }
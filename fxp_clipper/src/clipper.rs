@@ -1,25 +1,465 @@
 use anyhow::{anyhow, Context, Result};
-use ctrlc;
-use log::debug;
-use std::collections::BTreeMap;
+use log::{debug, warn};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
-use tempfile;
+use tracing::info_span;
 
 use fxp_modes::Modes;
+use fxp_output::keep_temp_files;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::FrameRate;
+use fxp_output::FrameTimes;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_stream::ColorPrimaries;
 
-use crate::clip::make_clip;
+use crate::animation::make_animation;
+use crate::chapters::{embed_chapters, place_chapters, read_markers, webvtt, Marker};
+use crate::clip::{make_clip, stage_frames, AudioTrack, EncodeSettings};
+use crate::error::ClipperError;
+use crate::fit::{fit_frames, frames_for_duration, speed_factor, DurationMismatch};
+use crate::format::ClipFormat;
+use crate::gaps::{describe_missing, missing_frames, sequence_frames, GapPolicy};
+use crate::hdr::HdrMetadata;
+use crate::preview::{stream_preview, PreviewTarget};
+use crate::quality::{VideoCodec, VideoQuality};
+use crate::segments::{segment_frames, Segment};
+use crate::sizes::{find_size_mismatch, SizeMismatch};
+use crate::streaming::{segment_video, SEGMENT_SECONDS};
 
 use fxp_filenames::FileOperations;
+use fxp_filenames::FrameSelection;
 use fxp_filenames::ImageMappingError;
 
+/// Optional settings of the Clipper, all of which have sensible defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ClipOptions {
+    /// How to treat missing frame numbers in the input sequence.
+    pub gap_policy: GapPolicy,
+
+    /// Stream a preview here instead of writing the final video.
+    pub preview: Option<PreviewTarget>,
+
+    /// Encode only the first N seconds, for a quick low-resolution render.
+    pub preview_seconds: Option<u32>,
+
+    /// Largest width or height of the encoded video; `None` keeps the frames' size.
+    pub pixel_upper_limit: Option<u32>,
+
+    /// Append the encoded frames to the end of an existing output video instead of
+    /// replacing it; the audio, if any, is then laid over the whole video.
+    pub append: bool,
+
+    /// Play the frames from the last to the first.
+    pub reverse: bool,
+
+    /// Follow the frames with the same frames backwards, for a boomerang clip.
+    pub pingpong: bool,
+
+    /// Encode only these frames, to try a section before the full run.
+    pub selection: FrameSelection,
+
+    /// Repeat or drop frames evenly so the frames last exactly as long as the audio,
+    /// instead of cutting the clip at the end of the audio.
+    pub fit_audio: bool,
+
+    /// End the clip with the frames when they end before the audio, cutting the audio.
+    pub trim_audio: bool,
+
+    /// Hold the last frame until the audio ends when the frames end before it.
+    pub pad_frames: bool,
+
+    /// Milliseconds to delay the audio by relative to the frames; negative to advance it,
+    /// e.g. when the frames were exported starting mid-song.
+    pub audio_offset_ms: i64,
+
+    /// Normalize the audio to this integrated loudness in LUFS (EBU R128) while merging
+    /// it; `None` keeps the audio's own volume.
+    pub loudness: Option<i32>,
+
+    /// Write a video, or a silent looping GIF or APNG for the web.
+    pub format: ClipFormat,
+
+    /// Largest width of the encoded clip; `None` keeps the frames' width.
+    pub max_width: Option<u32>,
+
+    /// How many times a GIF or APNG plays, 0 for forever.
+    pub loop_count: u32,
+
+    /// Codec and rate control of an MP4 video.
+    pub quality: VideoQuality,
+
+    /// Resize frames whose size differs from the size most frames share, instead of
+    /// refusing to encode them.
+    pub auto_fix: bool,
+    /// Copy the intermediate files, such as the silent video, here before they are removed.
+    pub keep_temp: Option<PathBuf>,
+
+    /// Directories whose frames are clipped one after the other instead of the input
+    /// directory alone; when set, the first is the input directory.
+    pub segments: Vec<Segment>,
+
+    /// Markers file naming the frames chapters start at, embedded into the MP4 so
+    /// players can jump between them.
+    pub chapters: Option<PathBuf>,
+
+    /// Also write the chapters as a WebVTT track next to the video.
+    pub webvtt: bool,
+
+    /// `frames.json` written by the Exporter with `--frames-json`; each frame is then
+    /// shown for as long as it lasted in the source video instead of `1 / fps`.
+    pub source_timing: Option<PathBuf>,
+
+    /// HDR video the frames were exported from, with `--high-bit-depth`; its color tags
+    /// and mastering metadata are copied into the clip, which needs 10-bit `quality`.
+    pub hdr_source: Option<PathBuf>,
+}
+
+impl ClipOptions {
+    /// Orders the selected frames for encoding.
+    ///
+    /// # Parameters
+    /// - `frames`: Frames mapped by frame number.
+    ///
+    /// # Returns
+    /// - `Result<Vec<PathBuf>>`: One source file per output frame, in playback order, or
+    ///   an error if the selected range has gaps and the gap policy is `Error`.
+    ///
+    /// # Notes
+    /// - The range of `selection` is applied before the gap policy and its stride after,
+    ///   so thinning out the frames does not count as gaps.
+    fn sequence(&self, frames: &BTreeMap<u32, PathBuf>) -> Result<Vec<PathBuf>> {
+        let frames = self.selection.in_range(frames);
+        let sequence = sequence_frames(&frames, self.gap_policy)?;
+        let sequence = self.selection.stride(sequence);
+        Ok(self.playback_order(sequence))
+    }
+
+    /// Reorders the frame sequence for reverse or ping-pong playback, if asked for.
+    ///
+    /// # Parameters
+    /// - `sequence`: One source file per output frame, in frame number order.
+    ///
+    /// # Returns
+    /// - `Vec<PathBuf>`: The frames in playback order.
+    ///
+    /// # Notes
+    /// - The reversal is applied first, so both together play backwards, then forwards.
+    /// - The mirrored half leaves out the turnaround frame, which would otherwise be shown
+    ///   twice in a row; the clip ends on the frame it started with.
+    /// - Only the staged copies are ordered; the input files keep their names.
+    fn playback_order(&self, mut sequence: Vec<PathBuf>) -> Vec<PathBuf> {
+        if self.reverse {
+            sequence.reverse();
+        }
+        if self.pingpong && sequence.len() > 1 {
+            let mirrored: Vec<PathBuf> = sequence.iter().rev().skip(1).cloned().collect();
+            sequence.extend(mirrored);
+        }
+        sequence
+    }
+
+    /// Returns when the audio ends relative to the first frame, once `audio_offset_ms` is applied.
+    ///
+    /// # Parameters
+    /// - `duration`: Duration of the audio in milliseconds.
+    ///
+    /// # Returns
+    /// - `Result<Option<u64>>`: The duration to fit and trim the clip to, `None` without
+    ///   audio; an error if the audio is advanced past its end.
+    fn audio_end(&self, duration: Option<u64>) -> Result<Option<u64>> {
+        let Some(duration) = duration else {
+            return Ok(None);
+        };
+        match duration.checked_add_signed(self.audio_offset_ms) {
+            Some(end) if end > 0 => Ok(Some(end)),
+            _ => Err(anyhow!(
+                "An audio offset of {} ms leaves nothing of the {} ms of audio",
+                self.audio_offset_ms,
+                duration
+            )),
+        }
+    }
+
+    /// Stretches the frame sequence to the audio duration, if `fit_audio` is set.
+    ///
+    /// # Parameters
+    /// - `sequence`: One source file per output frame, in playback order.
+    /// - `fps`: Frame rate of the output video.
+    /// - `duration`: Duration of the audio in milliseconds.
+    ///
+    /// # Returns
+    /// - `Result<(Vec<PathBuf>, Option<f64>)>`: The frames to encode and, when fitted,
+    ///   the speed factor; an error if fitting is asked for without audio.
+    ///
+    /// # Notes
+    /// - The frame rate is kept; the number of frames changes instead, see `fit_frames`.
+    fn fit_to_audio(
+        &self,
+        sequence: Vec<PathBuf>,
+        fps: FrameRate,
+        duration: Option<u64>,
+    ) -> Result<(Vec<PathBuf>, Option<f64>)> {
+        if !self.fit_audio {
+            return Ok((sequence, None));
+        }
+        let duration = duration
+            .ok_or_else(|| anyhow!("--fit-audio needs an audio file to fit the frames to"))?;
+        let target_frames = frames_for_duration(duration, fps);
+        let speed = speed_factor(sequence.len(), target_frames);
+        Ok((fit_frames(&sequence, target_frames), Some(speed)))
+    }
+
+    /// Compares the frames with the audio, ending the clip with the frames or holding the
+    /// last frame as `trim_audio` and `pad_frames` ask.
+    ///
+    /// # Parameters
+    /// - `sequence`: One source file per output frame, in playback order.
+    /// - `fps`: Frame rate of the output video.
+    /// - `duration`: When the audio ends, in milliseconds.
+    ///
+    /// # Returns
+    /// - `(Vec<PathBuf>, Option<u64>, Option<DurationMismatch>)`: The frames to encode,
+    ///   the duration to trim the clip to, and how far the frames missed the audio if by
+    ///   more than a few percent.
+    ///
+    /// # Notes
+    /// - `trim_audio` and `pad_frames` only act when the frames end first; frames lasting
+    ///   longer are cut at the end of the audio either way, unless fitted to it.
+    /// - Frames fitted with `fit_audio` last as long as the audio already.
+    fn match_audio(
+        &self,
+        mut sequence: Vec<PathBuf>,
+        fps: FrameRate,
+        duration: Option<u64>,
+    ) -> (Vec<PathBuf>, Option<u64>, Option<DurationMismatch>) {
+        let Some(audio_ms) = duration.filter(|_| !self.fit_audio) else {
+            return (sequence, duration, None);
+        };
+        let mismatch = DurationMismatch::find(sequence.len(), fps, audio_ms);
+        let frames_ms = fps.duration_ms(sequence.len() as u64);
+        if frames_ms > 0 && frames_ms < audio_ms {
+            if self.trim_audio {
+                return (sequence, Some(frames_ms), mismatch);
+            }
+            if self.pad_frames {
+                if let Some(last) = sequence.last().cloned() {
+                    sequence.resize(frames_for_duration(audio_ms, fps), last);
+                }
+            }
+        }
+        (sequence, duration, mismatch)
+    }
+
+    /// Checks that all frames to encode share one size.
+    ///
+    /// # Parameters
+    /// - `sequence`: One source file per output frame, in playback order.
+    ///
+    /// # Returns
+    /// - `Result<Option<SizeMismatch>>`: The frames to resize when `auto_fix` is set, `None`
+    ///   if all frames share one size; an error listing the offending frames otherwise.
+    ///
+    /// # Notes
+    /// - ffmpeg cannot encode frames of differing sizes into one video, which happens
+    ///   easily after mixing the outputs of different G'MIC filters.
+    fn check_sizes(&self, sequence: &[PathBuf]) -> Result<Option<SizeMismatch>> {
+        match find_size_mismatch(sequence)? {
+            Some(mismatch) if !self.auto_fix => Err(mismatch.to_error(sequence.len())),
+            mismatch => Ok(mismatch),
+        }
+    }
+
+    /// Describes the playback order for the plan.
+    fn describe_order(&self) -> &'static str {
+        match (self.reverse, self.pingpong) {
+            (false, false) => "forward",
+            (true, false) => "reversed",
+            (false, true) => "forward, then backward",
+            (true, true) => "backward, then forward",
+        }
+    }
+
+    /// Cuts the frame sequence and duration down to the preview length, if one is set.
+    ///
+    /// # Parameters
+    /// - `sequence`: One source file per output frame, in playback order.
+    /// - `fps`: Frame rate of the output video.
+    /// - `duration`: Optional duration in milliseconds.
+    ///
+    /// # Returns
+    /// - `(Vec<PathBuf>, Option<u64>)`: The frames and duration to encode.
+    fn limit_to_preview(
+        &self,
+        mut sequence: Vec<PathBuf>,
+        fps: FrameRate,
+        duration: Option<u64>,
+    ) -> (Vec<PathBuf>, Option<u64>) {
+        match self.preview_seconds {
+            Some(seconds) => {
+                let max_frames = fps.frames_in(seconds as u64 * 1000) as usize;
+                let max_duration = seconds as u64 * 1000;
+                debug!(
+                    "Limiting to the first {} seconds: {} frames",
+                    seconds, max_frames
+                );
+                sequence.truncate(max_frames);
+                let duration = Some(duration.map_or(max_duration, |d| d.min(max_duration)));
+                (sequence, duration)
+            }
+            None => (sequence, duration),
+        }
+    }
+
+    /// Reads the markers file, if one is set.
+    ///
+    /// # Returns
+    /// - `Result<Vec<Marker>>`: The markers, none without a file; an error if the file
+    ///   cannot be read, or the clip is an animation or appended to, neither of which can
+    ///   hold chapters.
+    fn markers(&self) -> Result<Vec<Marker>> {
+        let Some(path) = &self.chapters else {
+            return Ok(Vec::new());
+        };
+        if self.format != ClipFormat::Mp4 {
+            return Err(anyhow!(
+                "Chapters work with MP4 videos only, not with {}",
+                self.format
+            ));
+        }
+        if self.append {
+            return Err(anyhow!(
+                "Chapters cannot be added while appending, as they would cover only the new frames"
+            ));
+        }
+        read_markers(path)
+    }
+
+    /// Returns how long each frame is shown, from the source times in `source_timing`.
+    ///
+    /// # Parameters
+    /// - `frames`: Frames mapped by frame number.
+    /// - `sequence`: One source file per output frame, in playback order.
+    ///
+    /// # Returns
+    /// - `Result<Option<Vec<u64>>>`: The milliseconds of each frame, `None` without
+    ///   `source_timing`; an error if the file cannot be read or lacks a frame, or if the
+    ///   clip is an animation or made of segments.
+    ///
+    /// # Notes
+    /// - A frame lasts until the source time of the next one, so frames left out by the
+    ///   selection are covered by the frame before them and the video keeps the pace of
+    ///   the source; the frames add up to the span of their source times, without the
+    ///   rounding drift of a fixed rate against the audio.
+    /// - The last frame, and a frame whose successor does not come later in the source,
+    ///   as after a filled gap or at the start of a following video, lasts one frame at
+    ///   the rate of `frames.json`.
+    /// - Frames are matched by number, so frames processed by the Gmicer or Clutter keep
+    ///   the times of the exported frames they were made from.
+    fn frame_durations(
+        &self,
+        frames: &BTreeMap<u32, PathBuf>,
+        sequence: &[PathBuf],
+    ) -> Result<Option<Vec<u64>>> {
+        let Some(path) = &self.source_timing else {
+            return Ok(None);
+        };
+        if self.format.is_animation() {
+            return Err(anyhow!(
+                "Source timing works with MP4 videos only, not with {}",
+                self.format
+            ));
+        }
+        if !self.segments.is_empty() {
+            return Err(anyhow!(
+                "Source timing works with a single input directory at the clip's frame rate"
+            ));
+        }
+        let times = FrameTimes::load(path)?;
+        let numbers: HashMap<&Path, u32> = frames
+            .iter()
+            .map(|(number, frame)| (frame.as_path(), *number))
+            .collect();
+        let source_ms = sequence
+            .iter()
+            .map(|frame| {
+                numbers
+                    .get(frame.as_path())
+                    .and_then(|number| times.frame(*number))
+                    .map(|time| time.source_ms)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Frame {} has no source time in {}",
+                            frame.display(),
+                            path.display()
+                        )
+                    })
+            })
+            .collect::<Result<Vec<u64>>>()?;
+
+        let one_frame = times.fps.duration_ms(1).max(1);
+        let mut durations: Vec<u64> = source_ms
+            .windows(2)
+            .map(|pair| match pair[1].checked_sub(pair[0]) {
+                Some(duration) if duration > 0 => duration,
+                _ => one_frame,
+            })
+            .collect();
+        if !source_ms.is_empty() {
+            durations.push(one_frame);
+        }
+        Ok(Some(durations))
+    }
+
+    /// Checks the 10-bit settings and reads the metadata of `hdr_source`, if set.
+    ///
+    /// # Returns
+    /// - `Result<Option<HdrMetadata>>`: The metadata to encode with, `None` without
+    ///   `hdr_source`; an error if 10-bit video is asked of another codec than H.265,
+    ///   HDR without 10-bit video or for an animation, or the source is not HDR.
+    fn hdr_metadata(&self) -> Result<Option<HdrMetadata>> {
+        if self.quality.ten_bit && self.quality.codec != VideoCodec::H265 {
+            return Err(ClipperError::InvalidInput(format!(
+                "10-bit video needs the h265 codec, not {}",
+                self.quality.codec
+            ))
+            .into());
+        }
+        let Some(source) = &self.hdr_source else {
+            return Ok(None);
+        };
+        if !self.quality.ten_bit || self.format.is_animation() {
+            return Err(ClipperError::InvalidInput(format!(
+                "HDR from {} needs a 10-bit MP4 video",
+                source.display()
+            ))
+            .into());
+        }
+        HdrMetadata::probe(source).map(Some)
+    }
+
+    /// Returns the encoder settings for the given frame rate.
+    fn encode_settings(&self, fps: FrameRate) -> EncodeSettings {
+        EncodeSettings {
+            fps,
+            pixel_upper_limit: self.pixel_upper_limit,
+            max_width: self.max_width,
+            loop_count: self.loop_count,
+            quality: self.quality.clone(),
+            frame_durations: None,
+            primaries: ColorPrimaries::default(),
+            hdr: None,
+            keyframe_seconds: self.format.is_segmented().then_some(SEGMENT_SECONDS),
+        }
+    }
+}
+
 /// Struct for handling video processing operations.
 #[derive(Debug)]
 pub struct Clipper {
@@ -33,58 +473,340 @@ pub struct Clipper {
     pub mp3_path: Option<PathBuf>,
 
     /// Frames per second (FPS) value for the output video.
-    pub fps: u32,
+    pub fps: FrameRate,
 
     /// Duration in milliseconds to use for video processing.
     pub duration: Option<u64>,
+
+    /// Input frames mapped by frame number, pointing at the untouched source files.
+    pub frames: BTreeMap<u32, PathBuf>,
+
+    /// Optional settings; `new` starts from `ClipOptions::default()`.
+    pub options: ClipOptions,
 }
 
 impl Clipper {
+    /// Returns the frames to clip: those of the input directory, or of all the segments.
+    fn all_frames(&self) -> Result<Cow<'_, BTreeMap<u32, PathBuf>>> {
+        if self.options.segments.is_empty() {
+            Ok(Cow::Borrowed(&self.frames))
+        } else {
+            Ok(Cow::Owned(segment_frames(
+                &self.options.segments,
+                self.fps,
+            )?))
+        }
+    }
+
     /// Clips and processes a video file, handling interruptions gracefully.
-///
-/// This function manages the video clipping process, including temporary file handling
-/// and cleanup. It also supports Ctrl-C interruption and debug logging.
-///
-/// # Parameters
-/// - `images`: A slice of `PathBuf` objects representing the image files to process.
-///
-/// # Returns
-/// - `Result<PathBuf>`: The path to the final clipped video file on success.
-///
-/// # Notes
-/// - Creates a temporary directory for processing.
-/// - Handles Ctrl-C interruptions by setting a running flag.
-/// - Copies temporary directory contents to a debug directory in debug builds.
+    ///
+    /// This function manages the video clipping process, including temporary file handling
+    /// and cleanup. It also supports Ctrl-C interruption and debug logging.
+    ///
+    /// # Parameters
+    /// - `images`: A slice of `PathBuf` objects representing the image files to process.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The path to the final clipped video file on success.
+    ///
+    /// # Notes
+    /// - Creates a temporary directory for processing.
+    /// - Stages the mapped frames into a second temporary directory; the input directory is never modified.
+    /// - With `options.segments`, the frames of several directories are clipped in order,
+    ///   each at its own frame rate if it has one; see `segment_frames`.
+    /// - With `options.reverse` or `options.pingpong`, the staged frames are reordered;
+    ///   see `ClipOptions::playback_order`.
+    /// - With `options.selection`, only a subset of the frames is encoded; see
+    ///   `ClipOptions::sequence`.
+    /// - With `options.fit_audio`, frames are repeated or dropped to last as long as the
+    ///   audio, and the resulting speed factor is printed.
+    /// - Frames lasting more than a few percent longer or shorter than the audio are
+    ///   reported with the options resolving it; `options.trim_audio` and
+    ///   `options.pad_frames` resolve frames ending first, see `ClipOptions::match_audio`.
+    /// - With `options.audio_offset_ms`, the audio is shifted against the frames and the
+    ///   clip is trimmed where the shifted audio ends.
+    /// - With `options.loudness`, the audio is normalized with EBU R128 `loudnorm` as it
+    ///   is merged, without a separate ffmpeg pass.
+    /// - Frames of differing sizes are refused, or resized to the size most frames share
+    ///   with `options.auto_fix`; see `ClipOptions::check_sizes`.
+    /// - With a GIF or APNG `options.format`, a silent looping animation is written
+    ///   instead; the audio only sets its duration, and appending is refused.
+    /// - With an HLS or DASH `options.format`, the video is cut into segments listed by
+    ///   the playlist at the output path, see `streaming::segment_video`; appending and
+    ///   chapters are refused.
+    /// - Stops on Ctrl-C or SIGTERM, see `fxp_output::running_flag`.
+    /// - With `options.source_timing`, each frame lasts as long as it did in the source
+    ///   video, see `ClipOptions::frame_durations`.
+    /// - With `options.hdr_source`, the 10-bit video is tagged and encoded with the
+    ///   metadata of that HDR video, see `ClipOptions::hdr_metadata`.
+    /// - With `options.chapters`, the marked frames start chapters embedded into the
+    ///   video, see `place_chapters`; with `options.webvtt` they are also written to
+    ///   `<video stem>.vtt`.
+    /// - Writes `<video>.manifest.json` next to the video, see `fxp_output::Manifest`.
+    /// - With `options.append`, an existing output video is extended by the new frames;
+    ///   the manifest then describes only the frames of this run.
+    /// - Copies temporary directory contents to a debug directory in debug builds.
     pub fn clip(&self) -> Result<PathBuf> {
         debug!("Starting video clipping process...");
+        let _span = info_span!(
+            "clipper",
+            input = %self.input_dir.display(),
+            output = %self.output_path.display(),
+            fps = %self.fps
+        )
+        .entered();
+        let all_frames = self.all_frames()?;
+        let mut manifest = Manifest::new(Modes::Clipper)
+            .parameter("input", self.input_dir.display())
+            .parameter("fps", self.fps)
+            .parameter("gap policy", self.options.gap_policy)
+            .inputs(self.options.selection.in_range(&all_frames).values());
+        if !self.options.segments.is_empty() {
+            let segments: Vec<String> = self
+                .options
+                .segments
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect();
+            manifest = manifest.parameter_list("segments", &segments);
+        }
+        if let Some(range) = self.options.selection.range {
+            manifest = manifest.parameter("frames", range);
+        }
+        if self.options.selection.every > 1 {
+            manifest = manifest.parameter("every", self.options.selection.every);
+        }
+        if let Some(duration) = self.duration {
+            manifest = manifest.parameter("duration", duration);
+        }
+        if let Some(mp3) = &self.mp3_path {
+            manifest = manifest.parameter("mp3", mp3.display()).inputs([mp3]);
+        }
+        if let Some(limit) = self.options.pixel_upper_limit {
+            manifest = manifest.parameter("pixel upper limit", limit);
+        }
+        if let Some(seconds) = self.options.preview_seconds {
+            manifest = manifest.parameter("preview seconds", seconds);
+        }
+        if self.options.reverse {
+            manifest = manifest.parameter("reverse", true);
+        }
+        if self.options.pingpong {
+            manifest = manifest.parameter("pingpong", true);
+        }
+        if self.options.audio_offset_ms != 0 {
+            manifest = manifest.parameter("audio offset", self.options.audio_offset_ms);
+        }
+        if let Some(loudness) = self.options.loudness {
+            manifest = manifest.parameter("loudness", loudness);
+        }
+        if let Some(width) = self.options.max_width {
+            manifest = manifest.parameter("max width", width);
+        }
+        if self.options.quality != VideoQuality::default() {
+            let quality = &self.options.quality;
+            manifest = manifest.parameter("codec", quality.codec);
+            if let Some(crf) = quality.crf {
+                manifest = manifest.parameter("crf", crf);
+            }
+            if let Some(preset) = &quality.preset {
+                manifest = manifest.parameter("preset", preset);
+            }
+            if let Some(bitrate) = &quality.bitrate {
+                manifest = manifest.parameter("bitrate", bitrate);
+            }
+            if quality.two_pass {
+                manifest = manifest.parameter("two pass", true);
+            }
+            if quality.ten_bit {
+                manifest = manifest.parameter("ten bit", true);
+            }
+        }
+        let hdr = self.options.hdr_metadata()?;
+        if let Some(source) = &self.options.hdr_source {
+            manifest = manifest
+                .parameter("hdr source", source.display())
+                .inputs([source]);
+        }
+        if self.options.format.is_segmented() {
+            manifest = manifest.parameter("segment", self.options.format);
+            if self.options.append {
+                return Err(anyhow!(
+                    "Appending works with MP4 videos only, not with {}",
+                    self.options.format
+                ));
+            }
+        }
+        if self.options.format.is_animation() {
+            manifest = manifest
+                .parameter("format", self.options.format)
+                .parameter("loop count", self.options.loop_count);
+            if self.options.append {
+                return Err(anyhow!(
+                    "Appending works with MP4 videos only, not with {}",
+                    self.options.format
+                ));
+            }
+            if self.mp3_path.is_some() {
+                println!(
+                    "A {} has no sound; the audio only sets how long it lasts",
+                    self.options.format
+                );
+            }
+        }
+        // Only an existing video is appended to; otherwise the clip is written as usual.
+        let append_to = (self.options.append && self.output_path.is_file())
+            .then_some(self.output_path.as_path());
+        if append_to.is_some() {
+            manifest = manifest.parameter("appended", true);
+        }
+        let markers = self.options.markers()?;
+        if let Some(chapters) = &self.options.chapters {
+            manifest = manifest
+                .parameter("chapters", chapters.display())
+                .inputs([chapters]);
+            if self.options.webvtt {
+                manifest = manifest.parameter("webvtt", true);
+            }
+        }
 
         // Create a temporary directory using the tempfile crate.
         let tmp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
         let tmp_dir_path = tmp_dir.path().to_path_buf();
 
-        // Set up the running flag and register a Ctrl-C handler.
-        let running = Arc::new(AtomicBool::new(false));
-        let running_clone = running.clone();
-        ctrlc::set_handler(move || {
-            running_clone.store(true, Ordering::Relaxed);
-        })
-        .expect("Error setting Ctrl-C handler");
+        // Stage the frames under the names ffmpeg expects, leaving the input directory untouched.
+        let audio_end = self.options.audio_end(self.duration)?;
+        let sequence = self.options.sequence(&all_frames)?;
+        let frames = sequence.len();
+        let (sequence, speed) = self.options.fit_to_audio(sequence, self.fps, audio_end)?;
+        if let Some(speed) = speed {
+            println!(
+                "Fitting {} frames to the audio as {} frames: speed x{:.3}",
+                frames,
+                sequence.len(),
+                speed
+            );
+            manifest = manifest.parameter("fit audio", true);
+        }
+        let (sequence, audio_end, mismatch) =
+            self.options.match_audio(sequence, self.fps, audio_end);
+        if let Some(mismatch) = mismatch {
+            match (
+                mismatch.frames_shorter(),
+                self.options.trim_audio,
+                self.options.pad_frames,
+            ) {
+                (true, true, _) => println!("{}; ending the clip with the frames", mismatch),
+                (true, _, true) => {
+                    println!("{}; holding the last frame until the audio ends", mismatch)
+                }
+                _ => warn!("{}", mismatch.advice()),
+            }
+        }
+        if self.options.trim_audio {
+            manifest = manifest.parameter("trim audio", true);
+        }
+        if self.options.pad_frames {
+            manifest = manifest.parameter("pad frames", true);
+        }
+        let (sequence, duration) = self.options.limit_to_preview(sequence, self.fps, audio_end);
+        let resize = self.options.check_sizes(&sequence)?;
+        if let Some(resize) = &resize {
+            println!(
+                "Resizing {} frames to the {}x{} most frames have",
+                resize.mismatched.len(),
+                resize.size.0,
+                resize.size.1
+            );
+            manifest = manifest.parameter("auto fix", true);
+        }
+        let chapters = place_chapters(
+            &markers,
+            &all_frames,
+            &sequence,
+            self.fps,
+            duration.unwrap_or_else(|| self.fps.duration_ms(sequence.len() as u64)),
+        );
+        let mut encode = self.options.encode_settings(self.fps);
+        encode.frame_durations = self.options.frame_durations(&all_frames, &sequence)?;
+        if let Some(first) = sequence.first() {
+            encode.primaries = ColorPrimaries::of_image(first);
+            debug!("Tagging the video with {} primaries", encode.primaries);
+        }
+        encode.hdr = hdr;
+        if let Some(source_timing) = &self.options.source_timing {
+            manifest = manifest
+                .parameter("source timing", source_timing.display())
+                .inputs([source_timing]);
+        }
+        let frames_dir = tempfile::tempdir().context("Failed to create frame staging directory")?;
+        let frame_pattern = stage_frames(&sequence, frames_dir.path(), resize.as_ref())?;
+
+        let running = running_flag()?;
+
+        let audio = self.mp3_path.as_deref().map(|mp3| AudioTrack {
+            path: mp3,
+            duration_ms: duration.expect("duration must be provided"),
+            offset_ms: self.options.audio_offset_ms,
+            loudness: self.options.loudness,
+        });
 
         // Process video using the extracted function.
-        let final_video_path = make_clip(
-            &self.input_dir,
-            &self.output_path,
-            self.mp3_path.as_deref(), // converts Option<PathBuf> to Option<&Path>
-            self.fps,
-            self.duration,
-            running.clone(),
-            &tmp_dir_path,
-        )?;
+        let final_video_path = if self.options.format.is_animation() {
+            make_animation(
+                &frame_pattern,
+                &self.output_path,
+                self.options.format,
+                &encode,
+                duration,
+                running.clone(),
+                &tmp_dir_path,
+            )?
+        } else if self.options.format.is_segmented() {
+            // The stream is cut from a whole video, which is only an intermediate file.
+            let video = tmp_dir_path.join("stream.mp4");
+            make_clip(
+                &frame_pattern,
+                &video,
+                audio,
+                &encode,
+                None,
+                running.clone(),
+                &tmp_dir_path,
+            )?;
+            segment_video(
+                &video,
+                &self.output_path,
+                self.options.format,
+                self.options.quality.codec,
+                running.clone(),
+            )?
+        } else {
+            make_clip(
+                &frame_pattern,
+                &self.output_path,
+                audio,
+                &encode,
+                append_to,
+                running.clone(),
+                &tmp_dir_path,
+            )?
+        };
 
-        #[cfg(debug_assertions)]
-        {
-            let debug_dir = PathBuf::from("/tmp/fxp_videoclipper");
-            copy_tmp_dir_contents(tmp_dir.path(), &debug_dir)?;
+        if !chapters.is_empty() {
+            embed_chapters(&final_video_path, &chapters, &tmp_dir_path, running.clone())?;
+            if self.options.webvtt {
+                let vtt_path = final_video_path.with_extension("vtt");
+                fs::write(&vtt_path, webvtt(&chapters))
+                    .with_context(|| format!("Failed to write {}", vtt_path.display()))?;
+                debug!("WebVTT chapters written to {:?}", vtt_path);
+            }
+        }
+
+        manifest.write(&final_video_path)?;
+
+        if let Some(keep_dir) = &self.options.keep_temp {
+            keep_temp_files(tmp_dir.path(), keep_dir)?;
         }
 
         debug!(
@@ -94,6 +816,46 @@ impl Clipper {
 
         Ok(final_video_path)
     }
+
+    /// Streams the clip to a preview target instead of writing the final video.
+    ///
+    /// Stages the frames exactly like `clip`, then encodes them with a fast preset and
+    /// streams the result to ffplay or over HTTP, so sync and pacing can be checked
+    /// before committing to a full encode.
+    ///
+    /// # Parameters
+    /// - `target`: Where to stream the preview.
+    ///
+    /// # Returns
+    /// - `Result<()>`: Indicates whether the preview ran to completion.
+    pub fn preview(&self, target: &PreviewTarget) -> Result<()> {
+        debug!("Starting preview to {}", target);
+
+        let audio_end = self.options.audio_end(self.duration)?;
+        let sequence = self.options.sequence(&*self.all_frames()?)?;
+        let (sequence, _) = self.options.fit_to_audio(sequence, self.fps, audio_end)?;
+        let (sequence, audio_end, _) = self.options.match_audio(sequence, self.fps, audio_end);
+        let (sequence, duration) = self.options.limit_to_preview(sequence, self.fps, audio_end);
+        let resize = self.options.check_sizes(&sequence)?;
+        let frames_dir = tempfile::tempdir().context("Failed to create frame staging directory")?;
+        let frame_pattern = stage_frames(&sequence, frames_dir.path(), resize.as_ref())?;
+
+        let running = running_flag()?;
+
+        stream_preview(
+            &frame_pattern,
+            self.mp3_path.as_deref().map(|mp3| AudioTrack {
+                path: mp3,
+                duration_ms: audio_end.unwrap_or_default(),
+                offset_ms: self.options.audio_offset_ms,
+                loudness: self.options.loudness,
+            }),
+            &self.options.encode_settings(self.fps),
+            duration,
+            target,
+            running,
+        )
+    }
 }
 
 impl Clipper {
@@ -109,6 +871,8 @@ impl Clipper {
     ///   will be created inside the input directory.
     /// - `fps`: Frames per second for the output video (must be > 0).
     /// - `duration`: Optional duration in milliseconds for the video.
+    /// - `format`: The container written, which sets the extension of a generated name.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: A new Clipper instance on success, or an error if validation fails.
@@ -122,13 +886,15 @@ impl Clipper {
         input_dir: String,
         mp3_path: Option<String>,
         output_path: Option<String>,
-        fps: u32,
+        fps: FrameRate,
         duration: Option<u64>,
+        format: ClipFormat,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
         debug!("Initializing Clipper instance...");
 
         // Validate fps.
-        if fps == 0 {
+        if fps.numerator() == 0 {
             debug!("FPS validation failed: FPS must be greater than zero");
             return Err(anyhow!("FPS must be greater than zero"));
         }
@@ -142,10 +908,11 @@ impl Clipper {
                 "Input directory validation failed: {} does not exist or is not a directory",
                 input_dir.display()
             );
-            return Err(anyhow!(
+            return Err(ClipperError::InvalidInput(format!(
                 "Input directory does not exist or is not a directory: {}",
                 input_dir.display()
-            ));
+            ))
+            .into());
         }
         debug!("Input directory validated successfully.");
 
@@ -171,15 +938,21 @@ impl Clipper {
         let mode: Modes = Modes::Clipper;
         let output: Output = mode.into();
         let output_directory_path = match output {
-            Output::Clipper(clipper_output) => {
-                clipper_output.create_output((input_dir.clone(), mp3_path.clone(), output_path))?
-            }
+            Output::Clipper(clipper_output) => clipper_output.create_output(
+                (
+                    input_dir.clone(),
+                    mp3_path.clone(),
+                    output_path,
+                    format.extension(),
+                ),
+                collision,
+            )?,
             _ => unreachable!("Expected Clipper mode"),
         };
         debug!("Generated output directory: {:?}", output_directory_path);
 
         // (Optional) Log additional details from the setup.
-        let (final_out_dir, _frames, total_frames) =
+        let (final_out_dir, frames, total_frames) =
             setup_clipper_processing(&input_dir, &output_directory_path)?;
         debug!("Clipper setup complete: {} frames found", total_frames);
 
@@ -190,8 +963,259 @@ impl Clipper {
             output_path: final_out_dir,
             fps,
             duration,
+            frames,
+            options: ClipOptions {
+                format,
+                ..ClipOptions::default()
+            },
         })
     }
+
+    /// Resolves what `new` and `clip` would do, without touching the filesystem.
+    ///
+    /// Performs the same validation as `new`, but only resolves the output path
+    /// instead of creating it.
+    ///
+    /// # Parameters
+    /// - `input_dir`: Path to the input directory containing image files.
+    /// - `mp3_path`: Optional path to an MP3 audio file for video creation.
+    /// - `output_path`: Optional custom output path.
+    /// - `fps`: Frames per second for the output video (must be > 0).
+    /// - `duration`: Optional duration in milliseconds for the video.
+    /// - `options`: Optional settings the Clipper would run with.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails.
+    pub fn plan(
+        input_dir: String,
+        mp3_path: Option<String>,
+        output_path: Option<String>,
+        fps: FrameRate,
+        duration: Option<u64>,
+        options: &ClipOptions,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        if fps.numerator() == 0 {
+            return Err(anyhow!("FPS must be greater than zero"));
+        }
+
+        let input_dir = PathBuf::from(input_dir);
+        if !input_dir.is_dir() {
+            return Err(ClipperError::InvalidInput(format!(
+                "Input directory does not exist or is not a directory: {}",
+                input_dir.display()
+            ))
+            .into());
+        }
+
+        let mp3_path = mp3_path.map(PathBuf::from);
+        let mode: Modes = Modes::Clipper;
+        let output: Output = mode.into();
+        let output_path = match output {
+            Output::Clipper(clipper_output) => clipper_output.plan_output(
+                (
+                    input_dir.clone(),
+                    mp3_path.clone(),
+                    output_path,
+                    options.format.extension(),
+                ),
+                collision,
+            )?,
+            _ => unreachable!("Expected Clipper mode"),
+        };
+        let (_, frames, total_frames) = setup_clipper_processing(&input_dir, &output_path)?;
+        let (frames, total_frames) = if options.segments.is_empty() {
+            (frames, total_frames)
+        } else {
+            let frames = segment_frames(&options.segments, fps)?;
+            let total_frames = frames.len();
+            (frames, total_frames)
+        };
+        let missing = missing_frames(&options.selection.in_range(&frames));
+        // Fail the plan exactly where the run would fail.
+        let duration = options.audio_end(duration)?;
+        let sequence = options.sequence(&frames)?;
+        let (sequence, speed) = options.fit_to_audio(sequence, fps, duration)?;
+        let (sequence, duration, mismatch) = options.match_audio(sequence, fps, duration);
+        let (sequence, duration) = options.limit_to_preview(sequence, fps, duration);
+        let resize = options.check_sizes(&sequence)?;
+        let durations = options.frame_durations(&frames, &sequence)?;
+        let hdr = options.hdr_metadata()?;
+        let chapters = place_chapters(
+            &options.markers()?,
+            &frames,
+            &sequence,
+            fps,
+            duration.unwrap_or_else(|| fps.duration_ms(sequence.len() as u64)),
+        );
+
+        let mut plan = Plan::new(Modes::Clipper).entry("input directory", input_dir.display());
+        if !options.segments.is_empty() {
+            let segments: Vec<String> = options.segments.iter().map(|s| s.to_string()).collect();
+            plan = plan.entry("segments", segments.join(", "));
+        }
+        Ok(plan
+            .entry("frames", total_frames)
+            .entry(
+                "missing frames",
+                if missing.is_empty() {
+                    "none".to_string()
+                } else {
+                    describe_missing(&missing)
+                },
+            )
+            .entry("gap policy", options.gap_policy)
+            .entry("selection", options.selection)
+            .entry("playback order", options.describe_order())
+            .entry("encoded frames", sequence.len())
+            .entry(
+                "frame sizes",
+                resize.map_or("all the same".to_string(), |resize| {
+                    format!(
+                        "{} resized to {}x{} (--auto-fix)",
+                        resize.mismatched.len(),
+                        resize.size.0,
+                        resize.size.1
+                    )
+                }),
+            )
+            .entry(
+                "speed",
+                speed.map_or("as exported".to_string(), |speed| {
+                    format!("x{:.3}, fitted to the audio", speed)
+                }),
+            )
+            .entry(
+                "frame timing",
+                match (&options.source_timing, durations) {
+                    (Some(path), Some(durations)) => format!(
+                        "source times from {}, {} ms in all",
+                        path.display(),
+                        durations.iter().sum::<u64>()
+                    ),
+                    _ => "one frame per 1 / fps".to_string(),
+                },
+            )
+            .entry(
+                "audio",
+                mp3_path
+                    .as_ref()
+                    .map_or("none".to_string(), |p| p.display().to_string()),
+            )
+            .entry(
+                "frames against audio",
+                match mismatch {
+                    None if mp3_path.is_none() => "no audio".to_string(),
+                    None => "about as long".to_string(),
+                    Some(mismatch) if mismatch.frames_shorter() && options.trim_audio => {
+                        format!("{}, ending the clip with the frames", mismatch)
+                    }
+                    Some(mismatch) if mismatch.frames_shorter() && options.pad_frames => {
+                        format!("{}, holding the last frame", mismatch)
+                    }
+                    Some(mismatch) => mismatch.to_string(),
+                },
+            )
+            .entry("audio offset", format!("{} ms", options.audio_offset_ms))
+            .entry(
+                "loudness",
+                options.loudness.map_or("as recorded".to_string(), |lufs| {
+                    format!("normalized to {} LUFS", lufs)
+                }),
+            )
+            .entry("fps", fps)
+            .entry(
+                "chapters",
+                match (&options.chapters, options.webvtt) {
+                    (None, _) => "none".to_string(),
+                    (Some(_), false) => format!("{} embedded", chapters.len()),
+                    (Some(_), true) => format!(
+                        "{} embedded and written to {}",
+                        chapters.len(),
+                        output_path.with_extension("vtt").display()
+                    ),
+                },
+            )
+            .entry(
+                "format",
+                if options.format.is_animation() {
+                    format!(
+                        "{}, {}",
+                        options.format,
+                        match options.loop_count {
+                            0 => "looping forever".to_string(),
+                            1 => "playing once".to_string(),
+                            plays => format!("playing {} times", plays),
+                        }
+                    )
+                } else if options.format.is_segmented() {
+                    format!(
+                        "{}, {} s segments next to the playlist",
+                        options.format, SEGMENT_SECONDS
+                    )
+                } else {
+                    options.format.to_string()
+                },
+            )
+            .entry(
+                "quality",
+                if options.format.is_animation() {
+                    "palette and frames as is".to_string()
+                } else {
+                    options.quality.to_string()
+                },
+            )
+            .entry(
+                "hdr",
+                match (&options.hdr_source, hdr) {
+                    (Some(source), Some(hdr)) => {
+                        format!("{} from {}", hdr.transfer, source.display())
+                    }
+                    _ => "none".to_string(),
+                },
+            )
+            .entry(
+                "max width",
+                options
+                    .max_width
+                    .map_or("none".to_string(), |w| w.to_string()),
+            )
+            .entry(
+                "pixel limit",
+                options
+                    .pixel_upper_limit
+                    .map_or("none".to_string(), |l| l.to_string()),
+            )
+            .entry(
+                "duration",
+                duration.map_or("all frames".to_string(), |d| format!("{} ms", d)),
+            )
+            .entry(
+                "on existing output",
+                if options.append {
+                    "append the frames to the video".to_string()
+                } else {
+                    collision.to_string()
+                },
+            )
+            .entry(
+                "intermediate files",
+                options
+                    .keep_temp
+                    .as_ref()
+                    .map_or("removed".to_string(), |dir| {
+                        format!("kept in {}", dir.display())
+                    }),
+            )
+            .entry(
+                "output file",
+                match &options.preview {
+                    Some(target) => format!("none, streaming a preview to {}", target),
+                    None => output_path.display().to_string(),
+                },
+            ))
+    }
 }
 
 /// Sets up and prepares image files for Clipper processing.
@@ -232,53 +1256,9 @@ fn setup_clipper_processing(
     let total_frames = frames.len();
     if total_frames == 0 {
         debug!("No valid image frames found in input directory after validation");
-        return Err(anyhow!(
-            "No valid image frames found in input directory: {}",
-            input_directory.display()
-        ));
+        return Err(ClipperError::NoFrames(input_directory.to_path_buf()).into());
     }
     debug!("Found {} image frames for processing", total_frames);
 
     Ok((output_directory.to_path_buf(), frames, total_frames))
 }
-
-/// Copies the contents of a temporary directory to a debug directory for inspection.
-///
-/// This function is used to duplicate files from a temporary directory into a debug directory,
-/// which is useful for debugging purposes.
-///
-/// # Parameters
-/// - `tmp_dir`: The path to the temporary directory containing files to copy.
-/// - `debug_dir`: The path where the files will be copied for debugging.
-///
-/// # Returns
-/// - `Result<()>`: Returns `Ok(())` if successful. Returns an error if the source directory
-///   cannot be read or if there's an issue during copying.
-///
-/// # Notes
-/// - This function is only available when `debug_assertions` are enabled.
-/// - If the destination directory does not exist, it will be created before copying files.
-/// - Each file copy operation will return an error if it fails, preventing further copies.
-#[cfg(debug_assertions)]
-fn copy_tmp_dir_contents(tmp_dir: &Path, debug_dir: &Path) -> Result<()> {
-    // Create the debug directory if it doesn't exist.
-    if !debug_dir.exists() {
-        fs::create_dir_all(debug_dir).context(format!(
-            "Failed to create debug directory: {}",
-            debug_dir.display()
-        ))?;
-    }
-
-    // Iterate and copy each file from tmp_dir to debug_dir.
-    for entry in fs::read_dir(tmp_dir).context("Failed to read temporary directory")? {
-        let entry = entry?;
-        let src_path = entry.path();
-        if let Some(file_name) = src_path.file_name() {
-            let dest_path = debug_dir.join(file_name);
-            fs::copy(&src_path, &dest_path)
-                .context(format!("Failed to copy {:?} to {:?}", src_path, dest_path))?;
-        }
-    }
-    debug!("Copied temporary files to {}", debug_dir.display());
-    Ok(())
-}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Clipper that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum ClipperError {
+    /// A tool the Clipper runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Clipper runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The input holds no frames.
+    #[error("No frames found in {}", .0.display())]
+    NoFrames(PathBuf),
+    /// The input does not exist or is not what the Clipper takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Clip interrupted by user")]
+    Interrupted,
+}
+
+impl ClipperError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ClipperError::ToolMissing(_) => FailureKind::ToolMissing,
+            ClipperError::ToolFailed { .. } => FailureKind::ToolFailed,
+            ClipperError::NoFrames(_) => FailureKind::NoFrames,
+            ClipperError::InvalidInput(_) => FailureKind::InvalidInput,
+            ClipperError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for ClipperError {
+    fn missing(tool: &str) -> Self {
+        ClipperError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        ClipperError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use log::debug;
+use std::fmt;
+use std::path::PathBuf;
+
+use fxp_output::FrameRate;
+
+/// Repeats or drops frames evenly so the sequence holds exactly `target_frames` frames.
+///
+/// # Parameters
+/// - `sequence`: One source file per output frame, in playback order.
+/// - `target_frames`: The number of frames the sequence must hold.
+///
+/// # Returns
+/// - `Vec<PathBuf>`: The resampled sequence; each output frame shows the source frame
+///   playing at that moment, so the motion keeps its order and spreads evenly.
+pub fn fit_frames(sequence: &[PathBuf], target_frames: usize) -> Vec<PathBuf> {
+    if sequence.is_empty() {
+        return Vec::new();
+    }
+    debug!(
+        "Fitting {} frames into {} frames",
+        sequence.len(),
+        target_frames
+    );
+    (0..target_frames)
+        .map(|index| sequence[index * sequence.len() / target_frames].clone())
+        .collect()
+}
+
+/// Returns the number of frames lasting `duration_ms` at `fps`, at least one.
+pub fn frames_for_duration(duration_ms: u64, fps: FrameRate) -> usize {
+    (fps.frames_in_rounded(duration_ms) as usize).max(1)
+}
+
+/// Returns the speed the frames play at once fitted: above 1 when frames are dropped,
+/// below 1 when they are repeated.
+pub fn speed_factor(frames: usize, target_frames: usize) -> f64 {
+    frames as f64 / target_frames as f64
+}
+
+/// How far the frames may last longer or shorter than the audio, as a fraction of the
+/// audio, before the Clipper reports it.
+const MISMATCH_TOLERANCE: f64 = 0.05;
+
+/// Frames lasting noticeably longer or shorter than the audio they are clipped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurationMismatch {
+    /// The frames to encode.
+    pub frames: usize,
+    /// How long the frames last at the clip's frame rate, in milliseconds.
+    pub frames_ms: u64,
+    /// How long the audio lasts, in milliseconds.
+    pub audio_ms: u64,
+}
+
+impl DurationMismatch {
+    /// Compares the frames to the audio.
+    ///
+    /// # Parameters
+    /// - `frames`: The number of frames to encode.
+    /// - `fps`: Frame rate of the clip.
+    /// - `audio_ms`: Duration of the audio in milliseconds.
+    ///
+    /// # Returns
+    /// - `Option<Self>`: The mismatch, or `None` if the frames last within 5% of the audio.
+    pub fn find(frames: usize, fps: FrameRate, audio_ms: u64) -> Option<Self> {
+        let frames_ms = fps.duration_ms(frames as u64);
+        let mismatch = Self {
+            frames,
+            frames_ms,
+            audio_ms,
+        };
+        (mismatch.fraction().abs() > MISMATCH_TOLERANCE).then_some(mismatch)
+    }
+
+    /// Returns whether the frames end before the audio.
+    pub fn frames_shorter(&self) -> bool {
+        self.frames_ms < self.audio_ms
+    }
+
+    /// Returns how much longer the frames last than the audio, negative when shorter, as
+    /// a fraction of the audio.
+    fn fraction(&self) -> f64 {
+        (self.frames_ms as f64 - self.audio_ms as f64) / self.audio_ms.max(1) as f64
+    }
+
+    /// Describes the mismatch with the options that resolve it, for the warning printed
+    /// before encoding.
+    pub fn advice(&self) -> String {
+        if self.frames_shorter() {
+            format!(
+                "{}; the audio plays on past the last frame unless --fit-audio stretches the frames to it, --trim-audio ends the clip with the frames, or --pad-frames holds the last frame until the audio ends",
+                self
+            )
+        } else {
+            format!(
+                "{}; the frames past the end of the audio are cut unless --fit-audio squeezes them into it",
+                self
+            )
+        }
+    }
+}
+
+impl fmt::Display for DurationMismatch {
+    /// Formats as `120 frames last 4.000 s, 6.000 s (60.0%) shorter than the 10.000 s of audio`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames last {:.3} s, {:.3} s ({:.1}%) {} than the {:.3} s of audio",
+            self.frames,
+            self.frames_ms as f64 / 1000.0,
+            self.frames_ms.abs_diff(self.audio_ms) as f64 / 1000.0,
+            self.fraction().abs() * 100.0,
+            if self.frames_shorter() {
+                "shorter"
+            } else {
+                "longer"
+            },
+            self.audio_ms as f64 / 1000.0
+        )
+    }
+}
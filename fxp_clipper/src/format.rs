@@ -0,0 +1,69 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Container the Clipper writes the frames into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClipFormat {
+    /// An H.264 video, with the audio if any.
+    #[default]
+    Mp4,
+    /// An animated GIF with a palette computed from the frames.
+    Gif,
+    /// An animated PNG, lossless and with full color.
+    Apng,
+    /// The video cut into HLS segments listed by an `.m3u8` playlist, for streaming.
+    Hls,
+    /// The video cut into DASH segments listed by an `.mpd` manifest, for streaming.
+    Dash,
+}
+
+impl ClipFormat {
+    /// Returns the extension of the output file, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ClipFormat::Mp4 => "mp4",
+            ClipFormat::Gif => "gif",
+            ClipFormat::Apng => "apng",
+            ClipFormat::Hls => "m3u8",
+            ClipFormat::Dash => "mpd",
+        }
+    }
+
+    /// Whether the format is a silent looping animation rather than a video.
+    pub fn is_animation(self) -> bool {
+        matches!(self, ClipFormat::Gif | ClipFormat::Apng)
+    }
+
+    /// Whether the video is cut into segments for streaming rather than written whole.
+    pub fn is_segmented(self) -> bool {
+        matches!(self, ClipFormat::Hls | ClipFormat::Dash)
+    }
+}
+
+impl FromStr for ClipFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mp4" => Ok(ClipFormat::Mp4),
+            "gif" => Ok(ClipFormat::Gif),
+            "apng" => Ok(ClipFormat::Apng),
+            "hls" => Ok(ClipFormat::Hls),
+            "dash" => Ok(ClipFormat::Dash),
+            _ => Err(format!(
+                "Unknown format '{}', expected mp4, gif, apng, hls or dash",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClipFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipFormat::Hls => write!(f, "hls"),
+            ClipFormat::Dash => write!(f, "dash"),
+            format => write!(f, "{}", format.extension()),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// How the Clipper treats missing frame numbers in the input sequence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// Number the existing frames consecutively, dropping the gaps.
+    Resequence,
+    /// Repeat the previous frame for every missing number, keeping the timing.
+    Fill,
+    /// Refuse to clip a sequence with gaps.
+    #[default]
+    Error,
+}
+
+impl FromStr for GapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "resequence" => Ok(GapPolicy::Resequence),
+            "fill" => Ok(GapPolicy::Fill),
+            "error" => Ok(GapPolicy::Error),
+            other => Err(format!(
+                "Unknown gap policy '{}', expected resequence, fill or error",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GapPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GapPolicy::Resequence => "resequence",
+            GapPolicy::Fill => "fill",
+            GapPolicy::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the frame numbers missing between the first and the last mapped frame.
+pub fn missing_frames(frames: &BTreeMap<u32, PathBuf>) -> Vec<u32> {
+    let mut missing = Vec::new();
+    let mut previous: Option<u32> = None;
+    for &number in frames.keys() {
+        if let Some(previous) = previous {
+            missing.extend(previous + 1..number);
+        }
+        previous = Some(number);
+    }
+    missing
+}
+
+/// Orders the frames for encoding according to the gap policy.
+///
+/// # Parameters
+/// - `frames`: Frames mapped by frame number.
+/// - `policy`: How to treat missing frame numbers.
+///
+/// # Returns
+/// - `Result<Vec<PathBuf>>`: One source file per output frame, in playback order,
+///   or an error if the sequence has gaps and the policy is `Error`.
+///
+/// # Notes
+/// - A sequence that merely starts above 1 has no gaps; it is always renumbered from 1.
+pub fn sequence_frames(frames: &BTreeMap<u32, PathBuf>, policy: GapPolicy) -> Result<Vec<PathBuf>> {
+    let missing = missing_frames(frames);
+    if missing.is_empty() {
+        return Ok(frames.values().cloned().collect());
+    }
+    debug!(
+        "Found {} missing frames, applying gap policy: {}",
+        missing.len(),
+        policy
+    );
+
+    match policy {
+        GapPolicy::Resequence => Ok(frames.values().cloned().collect()),
+        GapPolicy::Fill => {
+            let mut sequence = Vec::with_capacity(frames.len() + missing.len());
+            let mut previous: Option<(u32, &PathBuf)> = None;
+            for (&number, path) in frames {
+                if let Some((previous_number, previous_path)) = previous {
+                    for _ in previous_number + 1..number {
+                        sequence.push(previous_path.clone());
+                    }
+                }
+                sequence.push(path.clone());
+                previous = Some((number, path));
+            }
+            Ok(sequence)
+        }
+        GapPolicy::Error => Err(anyhow!(
+            "Frame sequence has {} missing frames ({}); use --gaps resequence or --gaps fill to clip it anyway",
+            missing.len(),
+            describe_missing(&missing)
+        )),
+    }
+}
+
+/// Formats missing frame numbers as compact ranges, e.g. `3, 7-9`.
+pub fn describe_missing(missing: &[u32]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = missing.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        if start == end {
+            ranges.push(start.to_string());
+        } else {
+            ranges.push(format!("{}-{}", start, end));
+        }
+    }
+    ranges.join(", ")
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use fxp_output::ToolError;
+
+use crate::error::ClipperError;
+
+/// Transfer curves of HDR video: PQ (HDR10) and HLG.
+const HDR_TRANSFERS: [&str; 2] = ["smpte2084", "arib-std-b67"];
+
+/// The color tags and mastering metadata of an HDR video, copied into a 10-bit clip of
+/// its frames so it plays as HDR again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HdrMetadata {
+    /// Color primaries as ffmpeg names them, e.g. `bt2020`.
+    pub primaries: String,
+    /// Transfer curve, `smpte2084` or `arib-std-b67`.
+    pub transfer: String,
+    /// YUV matrix, e.g. `bt2020nc`.
+    pub colorspace: String,
+    /// The mastering display as x265's `master-display` takes it, e.g.
+    /// `G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,50)`.
+    pub master_display: Option<String>,
+    /// The content light levels as x265's `max-cll` takes them, `MaxCLL,MaxFALL`.
+    pub max_cll: Option<String>,
+}
+
+impl HdrMetadata {
+    /// Reads the HDR metadata of a video with ffprobe.
+    ///
+    /// # Parameters
+    /// - `video`: The HDR video, usually the one the frames were exported from.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The metadata, or an error if ffprobe fails or the video has no
+    ///   HDR transfer curve.
+    ///
+    /// # Notes
+    /// - The mastering display and content light levels are read from the side data of
+    ///   the first frame; videos without them get the color tags only.
+    pub fn probe(video: &Path) -> Result<Self> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-read_intervals",
+                "%+#1",
+                "-show_entries",
+                "stream=color_primaries,color_transfer,color_space:frame=side_data_list",
+                "-of",
+                "default=noprint_wrappers=1",
+            ])
+            .arg(video)
+            .output()
+            .map_err(|e| ClipperError::spawn("ffprobe", e))
+            .context("Failed to read the HDR metadata")?;
+        if !output.status.success() {
+            return Err(ClipperError::ToolFailed {
+                tool: "ffprobe".to_string(),
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+            .with_context(|| format!("Failed to read the HDR metadata of {}", video.display()));
+        }
+        let metadata = Self::parse(&String::from_utf8_lossy(&output.stdout))
+            .map_err(|e| ClipperError::InvalidInput(format!("{}: {}", video.display(), e)))?;
+        debug!("HDR metadata of {}: {:?}", video.display(), metadata);
+        Ok(metadata)
+    }
+
+    /// Parses the `key=value` lines ffprobe prints, see `probe`.
+    fn parse(output: &str) -> Result<Self, String> {
+        // The first value of a key wins, as the stream's tags come before the frame's.
+        let mut values: HashMap<&str, &str> = HashMap::new();
+        for line in output.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                values.entry(key.trim()).or_insert(value.trim());
+            }
+        }
+        let transfer = values.get("color_transfer").copied().unwrap_or("unknown");
+        if !HDR_TRANSFERS.contains(&transfer) {
+            return Err(format!(
+                "not an HDR video, its transfer curve is {} instead of smpte2084 or arib-std-b67",
+                transfer
+            ));
+        }
+        let tag = |key: &str, default: &str| {
+            values
+                .get(key)
+                .filter(|value| **value != "unknown")
+                .map_or(default, |value| value)
+                .to_string()
+        };
+        let rational = |key: &str| {
+            let value = values.get(key)?;
+            let (numerator, denominator) = value.split_once('/').unwrap_or((value, "1"));
+            let denominator: f64 = denominator.parse().ok()?;
+            (denominator != 0.0).then_some(numerator.parse::<f64>().ok()? / denominator)
+        };
+        // x265 counts chromaticities in 0.00002 and luminance in 0.0001 cd/m².
+        let chromaticity = |x: &str, y: &str| {
+            Some(format!(
+                "({},{})",
+                (rational(x)? * 50000.0).round(),
+                (rational(y)? * 50000.0).round()
+            ))
+        };
+        let master_display = (|| {
+            Some(format!(
+                "G{}B{}R{}WP{}L({},{})",
+                chromaticity("green_x", "green_y")?,
+                chromaticity("blue_x", "blue_y")?,
+                chromaticity("red_x", "red_y")?,
+                chromaticity("white_point_x", "white_point_y")?,
+                (rational("max_luminance")? * 10000.0).round(),
+                (rational("min_luminance")? * 10000.0).round()
+            ))
+        })();
+        let max_cll = match (values.get("max_content"), values.get("max_average")) {
+            (Some(content), Some(average)) => Some(format!("{},{}", content, average)),
+            _ => None,
+        };
+        Ok(Self {
+            primaries: tag("color_primaries", "bt2020"),
+            transfer: transfer.to_string(),
+            colorspace: tag("color_space", "bt2020nc"),
+            master_display,
+            max_cll,
+        })
+    }
+
+    /// Returns the matrix of `colorspace` as ffmpeg's `scale` filter names it.
+    pub fn matrix(&self) -> &'static str {
+        match self.colorspace.as_str() {
+            "bt709" => "bt709",
+            _ => "bt2020",
+        }
+    }
+
+    /// Returns the ffmpeg output options tagging the clip with the video's colors.
+    pub fn color_args(&self) -> Vec<String> {
+        [
+            "-color_primaries",
+            &self.primaries,
+            "-color_trc",
+            &self.transfer,
+            "-colorspace",
+            &self.colorspace,
+            "-color_range",
+            "tv",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+
+    /// Returns the `-x265-params` options writing the mastering metadata into the stream.
+    pub fn x265_params(&self) -> Vec<String> {
+        let mut params = Vec::new();
+        if self.transfer == "smpte2084" {
+            params.push("hdr10=1".to_string());
+        }
+        if let Some(master_display) = &self.master_display {
+            params.push(format!("master-display={}", master_display));
+        }
+        if let Some(max_cll) = &self.max_cll {
+            params.push(format!("max-cll={}", max_cll));
+        }
+        params
+    }
+}
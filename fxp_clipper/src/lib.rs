@@ -1,4 +1,25 @@
+mod animation;
+mod chapters;
 mod clip;
 mod clipper;
+mod error;
+mod fit;
+mod format;
+mod gaps;
+mod hdr;
+mod preview;
+mod quality;
+mod segments;
+mod sizes;
+mod still;
+mod streaming;
 
-pub use clipper::Clipper;
+pub use clipper::{ClipOptions, Clipper};
+pub use error::ClipperError;
+pub use format::ClipFormat;
+pub use gaps::GapPolicy;
+pub use hdr::HdrMetadata;
+pub use preview::PreviewTarget;
+pub use quality::{VideoCodec, VideoQuality, PRESETS};
+pub use segments::Segment;
+pub use still::{is_still_image, still_frames, synthesize_frames, StillMotion};
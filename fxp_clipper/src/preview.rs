@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::{atomic::AtomicBool, Arc};
+use std::{thread, time::Duration};
+
+use crate::clip::{audio_offset_args, loudnorm_args, AudioTrack, EncodeSettings};
+use crate::error::ClipperError;
+use fxp_output::{kill_requested, ToolError};
+
+/// Default port of the HTTP preview when none is given.
+const DEFAULT_PREVIEW_PORT: u16 = 8080;
+
+/// Where the Clipper streams a preview instead of writing the final video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreviewTarget {
+    /// Pipe an MPEG-TS stream into a local ffplay window.
+    Ffplay,
+    /// Serve an MPEG-TS stream on `http://127.0.0.1:<port>` for any player to open.
+    Http(u16),
+}
+
+impl FromStr for PreviewTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        match lower.split_once(':') {
+            None if lower == "ffplay" => Ok(PreviewTarget::Ffplay),
+            None if lower == "http" => Ok(PreviewTarget::Http(DEFAULT_PREVIEW_PORT)),
+            Some(("http", port)) => port
+                .parse::<u16>()
+                .map(PreviewTarget::Http)
+                .map_err(|_| format!("Invalid preview port '{}'", port)),
+            _ => Err(format!(
+                "Unknown preview target '{}', expected ffplay, http or http:PORT",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PreviewTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewTarget::Ffplay => write!(f, "ffplay"),
+            PreviewTarget::Http(port) => write!(f, "http://127.0.0.1:{}", port),
+        }
+    }
+}
+
+/// Streams the staged frames, with optional audio, to a preview target.
+///
+/// The frames are encoded with a fast, low-latency preset into MPEG-TS and either piped
+/// into ffplay or served over HTTP. Nothing is written to disk.
+///
+/// # Parameters
+/// - `frame_pattern`: ffmpeg input pattern of the staged frames.
+/// - `audio`: Optional audio to play along, with its offset and loudness target.
+/// - `encode`: Frame rate and optional size limit of the preview.
+/// - `duration`: Optional duration in milliseconds to cut the preview at.
+/// - `target`: Where to stream the preview.
+/// - `running`: Cleared by a stop signal to end the preview.
+///
+/// # Returns
+/// - `Result<()>`: Indicates whether the preview ran to completion.
+///
+/// # Notes
+/// - The HTTP target waits for a single client to connect before encoding starts.
+pub fn stream_preview(
+    frame_pattern: &Path,
+    audio: Option<AudioTrack>,
+    encode: &EncodeSettings,
+    duration: Option<u64>,
+    target: &PreviewTarget,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let fps_str = encode.fps.ffmpeg_arg();
+    // Paths are passed as they are, so that names that are not valid UTF-8 survive.
+    let mut args: Vec<OsString> = vec![
+        "-hide_banner".into(),
+        "-loglevel".into(),
+        "error".into(),
+        "-framerate".into(),
+        fps_str.into(),
+        "-start_number".into(),
+        "1".into(),
+        "-i".into(),
+        frame_pattern.into(),
+    ];
+    if let Some(audio) = audio {
+        args.extend(
+            audio_offset_args(audio.offset_ms)
+                .into_iter()
+                .map(OsString::from),
+        );
+        args.extend([
+            "-i".into(),
+            audio.path.into(),
+            "-map".into(),
+            "0:v".into(),
+            "-map".into(),
+            "1:a".into(),
+            "-c:a".into(),
+            "aac".into(),
+        ]);
+        args.extend(
+            audio
+                .loudness
+                .map(loudnorm_args)
+                .unwrap_or_default()
+                .into_iter()
+                .map(OsString::from),
+        );
+    }
+    if let Some(duration) = duration {
+        args.extend([
+            "-t".into(),
+            format!("{:.3}", duration as f64 / 1000.0).into(),
+        ]);
+    }
+    if encode.pixel_upper_limit.is_some() || encode.max_width.is_some() {
+        args.extend(["-vf".into(), encode.scale_filter(true).into()]);
+    }
+    args.extend([
+        "-c:v".into(),
+        "libx264".into(),
+        "-preset".into(),
+        "ultrafast".into(),
+        "-tune".into(),
+        "zerolatency".into(),
+        "-pix_fmt".into(),
+        "yuv420p".into(),
+        "-f".into(),
+        "mpegts".into(),
+    ]);
+
+    let mut children: Vec<Child> = Vec::new();
+    match target {
+        PreviewTarget::Ffplay => {
+            args.push("pipe:1".into());
+            debug!("Streaming preview to ffplay: ffmpeg {:?}", args);
+            let mut encoder = Command::new("ffmpeg")
+                .args(&args)
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| ClipperError::spawn("ffmpeg", e))
+                .context("Failed to spawn ffmpeg for the preview")?;
+            let stream = encoder
+                .stdout
+                .take()
+                .ok_or_else(|| anyhow!("Failed to capture the ffmpeg preview stream"))?;
+            let player = Command::new("ffplay")
+                .args(["-autoexit", "-loglevel", "error", "-i", "-"])
+                .stdin(stream)
+                .spawn()
+                .map_err(|e| ClipperError::spawn("ffplay", e))
+                .context("Failed to spawn ffplay for the preview")?;
+            children.push(encoder);
+            children.push(player);
+        }
+        PreviewTarget::Http(port) => {
+            args.extend([
+                "-listen".into(),
+                "1".into(),
+                format!("http://127.0.0.1:{}", port).into(),
+            ]);
+            debug!("Serving preview over HTTP: ffmpeg {:?}", args);
+            println!("Serving preview at {} (MPEG-TS)", target);
+            let encoder = Command::new("ffmpeg")
+                .args(&args)
+                .spawn()
+                .map_err(|e| ClipperError::spawn("ffmpeg", e))
+                .context("Failed to spawn ffmpeg for the preview")?;
+            children.push(encoder);
+        }
+    }
+
+    // Poll until every process exits, or stop them all on Ctrl-C.
+    loop {
+        if kill_requested(&running) {
+            for child in children.iter_mut() {
+                let _ = child.kill();
+            }
+            return Err(ClipperError::Interrupted.into());
+        }
+
+        let mut all_done = true;
+        for child in children.iter_mut() {
+            match child
+                .try_wait()
+                .context("Failed to check preview process")?
+            {
+                Some(status) if !status.success() => {
+                    debug!("Preview process exited with status: {}", status);
+                }
+                Some(_) => {}
+                None => all_done = false,
+            }
+        }
+        if all_done {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    debug!("Preview finished");
+    Ok(())
+}
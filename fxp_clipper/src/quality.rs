@@ -0,0 +1,162 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The x264 and x265 speed presets, fastest first.
+pub const PRESETS: [&str; 10] = [
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+
+/// Video codec of the final clip.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoCodec {
+    /// H.264 with libx264, playable everywhere.
+    #[default]
+    H264,
+    /// H.265 with libx265, about half the size at the same quality.
+    H265,
+}
+
+impl VideoCodec {
+    /// Returns the ffmpeg encoder of the codec.
+    pub fn encoder(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::H265 => "libx265",
+        }
+    }
+}
+
+impl FromStr for VideoCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "h264" => Ok(VideoCodec::H264),
+            "h265" => Ok(VideoCodec::H265),
+            _ => Err(format!("Unknown codec '{}', expected h264 or h265", s)),
+        }
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoCodec::H264 => write!(f, "h264"),
+            VideoCodec::H265 => write!(f, "h265"),
+        }
+    }
+}
+
+/// Rate control of the final clip; the default is the encoder's own, CRF 23 for x264.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VideoQuality {
+    pub codec: VideoCodec,
+    /// Constant rate factor, from 0 (lossless) to 51; lower is better and larger.
+    pub crf: Option<u8>,
+    /// Speed preset, one of `PRESETS`; slower presets compress better.
+    pub preset: Option<String>,
+    /// Average bitrate, as ffmpeg reads it, e.g. `4M` or `2500k`; replaces the CRF.
+    pub bitrate: Option<String>,
+    /// Encode twice, the first pass measuring the frames, to meet `bitrate` precisely.
+    pub two_pass: bool,
+    /// Encode 10 bits per channel, `yuv420p10le`, keeping the gradients of 16-bit frames;
+    /// needs H.265.
+    pub ten_bit: bool,
+}
+
+impl VideoQuality {
+    /// Returns the pixel format of the encoded video.
+    pub fn pixel_format(&self) -> &'static str {
+        if self.ten_bit {
+            "yuv420p10le"
+        } else {
+            "yuv420p"
+        }
+    }
+
+    /// Returns the ffmpeg output options encoding with these settings.
+    ///
+    /// # Parameters
+    /// - `pass`: The pass of a two-pass encode, 1 or 2; `None` for a single pass.
+    /// - `pass_log`: Prefix of the statistics files the two passes share.
+    /// - `x265_params`: Further `-x265-params` options, such as HDR metadata; ignored by
+    ///   x264.
+    ///
+    /// # Notes
+    /// - x264 takes the pass with `-pass`, x265 through `-x265-params`, together with
+    ///   `x265_params`.
+    /// - The x265 statistics path is quoted, since `-x265-params` separates its options
+    ///   with `:`, which a Windows drive letter holds too.
+    /// - H.265 is tagged `hvc1`, which QuickTime and Apple devices require to play it.
+    pub fn encoder_args(
+        &self,
+        pass: Option<u8>,
+        pass_log: &Path,
+        x265_params: &[String],
+    ) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["-c:v".into(), self.codec.encoder().into()];
+        if let Some(preset) = &self.preset {
+            args.extend(["-preset".into(), preset.into()]);
+        }
+        match (&self.bitrate, self.crf) {
+            (Some(bitrate), _) => args.extend(["-b:v".into(), bitrate.into()]),
+            (None, Some(crf)) => args.extend(["-crf".into(), crf.to_string().into()]),
+            (None, None) => {}
+        }
+        let mut params = Vec::new();
+        if let Some(pass) = pass {
+            match self.codec {
+                VideoCodec::H264 => args.extend([
+                    "-pass".into(),
+                    pass.to_string().into(),
+                    "-passlogfile".into(),
+                    pass_log.into(),
+                ]),
+                VideoCodec::H265 => params.push(format!(
+                    "pass={}:stats='{}.log'",
+                    pass,
+                    pass_log.to_string_lossy().replace('\'', "'\\''")
+                )),
+            }
+        }
+        if self.codec == VideoCodec::H265 {
+            params.extend(x265_params.iter().cloned());
+            if !params.is_empty() {
+                args.extend(["-x265-params".into(), params.join(":").into()]);
+            }
+            args.extend(["-tag:v".into(), "hvc1".into()]);
+        }
+        args
+    }
+}
+
+impl fmt::Display for VideoQuality {
+    /// Formats the settings as `h264, crf 20, preset slow` or `h265, crf 20, 10-bit`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.codec)?;
+        match (&self.bitrate, self.crf) {
+            (Some(bitrate), _) if self.two_pass => write!(f, ", {} in two passes", bitrate)?,
+            (Some(bitrate), _) => write!(f, ", {}", bitrate)?,
+            (None, Some(crf)) => write!(f, ", crf {}", crf)?,
+            (None, None) => write!(f, ", default crf")?,
+        }
+        if let Some(preset) = &self.preset {
+            write!(f, ", preset {}", preset)?;
+        }
+        if self.ten_bit {
+            write!(f, ", 10-bit")?;
+        }
+        Ok(())
+    }
+}
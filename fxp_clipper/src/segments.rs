@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use fxp_filenames::FileOperations;
+use fxp_modes::Modes;
+use fxp_output::FrameRate;
+
+use crate::error::ClipperError;
+
+/// A directory of frames clipped as one part of a longer sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub directory: PathBuf,
+    /// Frame rate the segment plays at; `None` plays it at the clip's.
+    pub fps: Option<FrameRate>,
+}
+
+impl FromStr for Segment {
+    type Err = String;
+
+    /// Parses a directory, optionally followed by `@` and its frame rate, e.g. `intro@12`.
+    ///
+    /// # Notes
+    /// - An existing directory whose name contains `@` is read as a directory as a whole.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((directory, fps)) = s.rsplit_once('@') {
+            if !Path::new(s).is_dir() {
+                let fps: FrameRate = fps.parse()?;
+                return Ok(Self {
+                    directory: PathBuf::from(directory),
+                    fps: Some(fps),
+                });
+            }
+        }
+        Ok(Self {
+            directory: PathBuf::from(s),
+            fps: None,
+        })
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fps {
+            Some(fps) => write!(f, "{}@{}", self.directory.display(), fps),
+            None => write!(f, "{}", self.directory.display()),
+        }
+    }
+}
+
+/// Maps the frames of several directories by frame number, as if they were one sequence.
+///
+/// # Parameters
+/// - `segments`: The directories, in playback order.
+/// - `fps`: Frame rate of the clip.
+///
+/// # Returns
+/// - `Result<BTreeMap<u32, PathBuf>>`: The frames of all segments, those of each segment
+///   numbered on after the last of the segment before; an error if a directory cannot be
+///   read or holds no frames.
+///
+/// # Notes
+/// - Gaps within a segment are kept, so the gap policy treats them as in a single
+///   directory; the segments themselves follow each other without gaps.
+/// - A segment with a frame rate of its own is retimed to the clip's: at half the clip's
+///   frame rate each frame is numbered twice, at double it every other frame is left out.
+pub fn segment_frames(segments: &[Segment], fps: FrameRate) -> Result<BTreeMap<u32, PathBuf>> {
+    let mut frames = BTreeMap::new();
+    let mut offset = 0u32;
+    for segment in segments {
+        let files: Vec<PathBuf> = fs::read_dir(&segment.directory)
+            .with_context(|| {
+                format!(
+                    "Failed to read segment directory {}",
+                    segment.directory.display()
+                )
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        let segment_frames = Modes::Clipper.load_files(&files).with_context(|| {
+            format!(
+                "Failed to number the frames in {}",
+                segment.directory.display()
+            )
+        })?;
+        let first = *segment_frames
+            .keys()
+            .next()
+            .ok_or_else(|| ClipperError::NoFrames(segment.directory.clone()))?;
+        let last = *segment_frames
+            .keys()
+            .next_back()
+            .expect("the segment has frames");
+
+        // Position in the clip of the start of the segment's frame `number`.
+        let position = |number: u32| -> u32 {
+            let elapsed = (number - first) as u64;
+            let retimed = match segment.fps {
+                Some(segment_fps) => {
+                    let scale_numerator = fps.numerator() as u64 * segment_fps.denominator() as u64;
+                    let scale_denominator =
+                        fps.denominator() as u64 * segment_fps.numerator() as u64;
+                    (elapsed * scale_numerator + scale_denominator / 2) / scale_denominator
+                }
+                None => elapsed,
+            };
+            offset + retimed as u32
+        };
+        for (&number, path) in &segment_frames {
+            for clip_number in position(number)..position(number + 1) {
+                frames.insert(clip_number + 1, path.clone());
+            }
+        }
+        debug!(
+            "Segment {} numbered as frames {} to {}",
+            segment,
+            offset + 1,
+            position(last + 1)
+        );
+        offset = position(last + 1);
+    }
+    Ok(frames)
+}
@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Context, Result};
+use image::imageops::FilterType;
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Frames listed in a mismatch error before the rest are only counted.
+const LISTED_FRAMES: usize = 20;
+
+/// Frames whose size differs from the size most frames share.
+#[derive(Debug, Clone)]
+pub struct SizeMismatch {
+    /// The width and height most frames have.
+    pub size: (u32, u32),
+    /// The other frames, with their own width and height.
+    pub mismatched: BTreeMap<PathBuf, (u32, u32)>,
+}
+
+impl SizeMismatch {
+    /// Returns whether `frame` has to be resized to `size`.
+    pub fn needs_resize(&self, frame: &Path) -> bool {
+        self.mismatched.contains_key(frame)
+    }
+
+    /// Builds the error listing the offending frames.
+    pub fn to_error(&self, frames: usize) -> anyhow::Error {
+        let mut listing: Vec<String> = self
+            .mismatched
+            .iter()
+            .take(LISTED_FRAMES)
+            .map(|(path, (width, height))| format!("  {} ({}x{})", path.display(), width, height))
+            .collect();
+        if self.mismatched.len() > LISTED_FRAMES {
+            listing.push(format!(
+                "  and {} more",
+                self.mismatched.len() - LISTED_FRAMES
+            ));
+        }
+        anyhow!(
+            "{} of {} frames differ from the {}x{} most frames have:\n{}\nUse --auto-fix to resize them to {}x{}",
+            self.mismatched.len(),
+            frames,
+            self.size.0,
+            self.size.1,
+            listing.join("\n"),
+            self.size.0,
+            self.size.1
+        )
+    }
+}
+
+/// Reads the size of every frame and finds those differing from the most common size.
+///
+/// # Parameters
+/// - `frames`: The frames to encode; a frame listed more than once is read once.
+///
+/// # Returns
+/// - `Result<Option<SizeMismatch>>`: `None` if all frames share one size, or an error if
+///   the size of a frame cannot be read.
+///
+/// # Notes
+/// - Only the image headers are read, not the pixels.
+/// - On a tie, the size of the earliest frame wins.
+pub fn find_size_mismatch(frames: &[PathBuf]) -> Result<Option<SizeMismatch>> {
+    let mut sizes: BTreeMap<&Path, (u32, u32)> = BTreeMap::new();
+    // Count and first position of each size, to break ties by the earliest frame.
+    let mut counts: BTreeMap<(u32, u32), (usize, usize)> = BTreeMap::new();
+    for (position, frame) in frames.iter().enumerate() {
+        if sizes.contains_key(frame.as_path()) {
+            continue;
+        }
+        let size = image::image_dimensions(frame)
+            .with_context(|| format!("Failed to read the size of frame {}", frame.display()))?;
+        sizes.insert(frame, size);
+        counts.entry(size).or_insert((0, position)).0 += 1;
+    }
+    if counts.len() <= 1 {
+        return Ok(None);
+    }
+
+    let (&size, _) = counts
+        .iter()
+        .max_by(|(_, (count_a, first_a)), (_, (count_b, first_b))| {
+            count_a.cmp(count_b).then(first_b.cmp(first_a))
+        })
+        .expect("at least two sizes were counted");
+    let mismatched: BTreeMap<PathBuf, (u32, u32)> = sizes
+        .into_iter()
+        .filter(|(_, frame_size)| *frame_size != size)
+        .map(|(path, frame_size)| (path.to_path_buf(), frame_size))
+        .collect();
+    debug!(
+        "{} frames differ from the common size {}x{}",
+        mismatched.len(),
+        size.0,
+        size.1
+    );
+    Ok(Some(SizeMismatch { size, mismatched }))
+}
+
+/// Writes `source` resized to `size` at `destination`, in the format of its extension.
+pub fn resize_frame(source: &Path, destination: &Path, size: (u32, u32)) -> Result<()> {
+    image::open(source)
+        .with_context(|| format!("Failed to decode frame {}", source.display()))?
+        .resize_exact(size.0, size.1, FilterType::Lanczos3)
+        .save(destination)
+        .with_context(|| format!("Failed to write resized frame {}", destination.display()))
+}
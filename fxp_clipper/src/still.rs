@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Context, Result};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat};
+use indicatif::ProgressStyle;
+use log::debug;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use fxp_filenames::FramePadding;
+use fxp_output::{progress_bar, FrameRate};
+
+/// How far a Ken Burns clip zooms in by its last frame.
+const KEN_BURNS_ZOOM: f64 = 1.2;
+
+/// How far the center of a Ken Burns clip drifts, as a share of the image's size.
+const KEN_BURNS_DRIFT: f64 = 0.05;
+
+/// How the frames built from a still image move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StillMotion {
+    /// Every frame is the image itself.
+    #[default]
+    Static,
+    /// A slow zoom into the image, drifting towards its lower right.
+    KenBurns,
+}
+
+impl FromStr for StillMotion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "static" => Ok(StillMotion::Static),
+            "ken-burns" | "kenburns" => Ok(StillMotion::KenBurns),
+            other => Err(format!(
+                "Unknown still motion '{}', expected static or ken-burns",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StillMotion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StillMotion::Static => "static",
+            StillMotion::KenBurns => "ken-burns",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Whether the Clipper input is a single image file rather than a directory of frames.
+pub fn is_still_image(path: &Path) -> bool {
+    path.is_file() && ImageFormat::from_path(path).is_ok()
+}
+
+/// Returns the number of frames covering `duration_ms` at `fps`, at least one.
+pub fn still_frames(duration_ms: Option<u64>, fps: FrameRate) -> Result<u32> {
+    let duration_ms = duration_ms
+        .ok_or_else(|| anyhow!("A clip of a still image needs audio to know how long to last"))?;
+    Ok((fps.frames_in(duration_ms) as u32).max(1))
+}
+
+/// Builds a sequence of frames from a still image, for a clip of a single image.
+///
+/// # Parameters
+/// - `image`: The still image.
+/// - `frames`: Number of frames to build, e.g. enough to cover the audio.
+/// - `motion`: How the frames move.
+/// - `output_dir`: Directory the frames are written to as `frame_0001.png` and on.
+///
+/// # Returns
+/// - `Result<()>`: `Ok(())` once every frame is written, or an error if the image cannot
+///   be decoded or a frame cannot be written.
+///
+/// # Notes
+/// - Static frames are written once and hard-linked for the rest, falling back to copies
+///   where the filesystem has no hard links, so a long clip takes the space of one frame.
+/// - Ken Burns frames crop an ever smaller part of the image and scale it back to the
+///   image's size, on all cores.
+pub fn synthesize_frames(
+    image: &Path,
+    frames: u32,
+    motion: StillMotion,
+    output_dir: &Path,
+) -> Result<()> {
+    let still = image::open(image)
+        .with_context(|| format!("Failed to decode still image {}", image.display()))?;
+    debug!(
+        "Building {} {} frames from {}",
+        frames,
+        motion,
+        image.display()
+    );
+
+    let pb = progress_bar(frames as u64);
+    pb.set_style(ProgressStyle::default_bar().template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+    )?);
+    let padding = FramePadding::for_count(u64::from(frames));
+    let frame_path =
+        |number: u32| output_dir.join(padding.file_name("frame", u64::from(number), "png"));
+
+    match motion {
+        StillMotion::Static => {
+            let first = frame_path(1);
+            still
+                .save(&first)
+                .with_context(|| format!("Failed to write frame {}", first.display()))?;
+            pb.inc(1);
+            for number in 2..=frames {
+                let path = frame_path(number);
+                if fs::hard_link(&first, &path).is_err() {
+                    fs::copy(&first, &path)
+                        .with_context(|| format!("Failed to write frame {}", path.display()))?;
+                }
+                pb.inc(1);
+            }
+        }
+        StillMotion::KenBurns => {
+            let workers = thread::available_parallelism().map_or(1, |cores| cores.get());
+            let next = AtomicU32::new(1);
+            let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(|| loop {
+                        let number = next.fetch_add(1, Ordering::SeqCst);
+                        if number > frames || failure.lock().expect("a worker panicked").is_some() {
+                            break;
+                        }
+                        let progress = (number - 1) as f64 / (frames.max(2) - 1) as f64;
+                        let path = frame_path(number);
+                        let result = ken_burns_frame(&still, progress)
+                            .save(&path)
+                            .with_context(|| format!("Failed to write frame {}", path.display()));
+                        if let Err(e) = result {
+                            failure.lock().expect("a worker panicked").get_or_insert(e);
+                            break;
+                        }
+                        pb.inc(1);
+                    });
+                }
+            });
+            if let Some(e) = failure.into_inner().expect("a worker panicked") {
+                return Err(e);
+            }
+        }
+    }
+    pb.finish();
+    Ok(())
+}
+
+/// Crops and scales the still for the frame `progress` of the way through the clip.
+fn ken_burns_frame(still: &DynamicImage, progress: f64) -> DynamicImage {
+    let (width, height) = (still.width() as f64, still.height() as f64);
+    let zoom = 1.0 + (KEN_BURNS_ZOOM - 1.0) * progress;
+    let (crop_width, crop_height) = (width / zoom, height / zoom);
+    let center_x = width * (0.5 + KEN_BURNS_DRIFT * progress);
+    let center_y = height * (0.5 + KEN_BURNS_DRIFT * progress);
+    let x = (center_x - crop_width / 2.0).clamp(0.0, width - crop_width);
+    let y = (center_y - crop_height / 2.0).clamp(0.0, height - crop_height);
+    let cropped = imageops::crop_imm(
+        still,
+        x.round() as u32,
+        y.round() as u32,
+        (crop_width.round() as u32).max(1),
+        (crop_height.round() as u32).max(1),
+    )
+    .to_image();
+    DynamicImage::ImageRgba8(imageops::resize(
+        &cropped,
+        still.width(),
+        still.height(),
+        FilterType::Triangle,
+    ))
+}
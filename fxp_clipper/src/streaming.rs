@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{atomic::AtomicBool, Arc};
+use tracing::info_span;
+
+use crate::clip::run_encode;
+use crate::format::ClipFormat;
+use crate::quality::VideoCodec;
+
+/// Length of a segment in seconds; the video gets a keyframe this often so the segments
+/// can be cut without encoding again.
+pub const SEGMENT_SECONDS: u32 = 4;
+
+/// Cuts a finished video into the segments of an HLS or DASH stream.
+///
+/// # Parameters
+/// - `video`: The video, with its audio, as `make_clip` writes it.
+/// - `output_path`: The playlist, `.m3u8` for HLS or `.mpd` for DASH; the segments are
+///   written next to it, named after it.
+/// - `format`: `ClipFormat::Hls` or `ClipFormat::Dash`.
+/// - `codec`: The codec of the video, which decides the segments HLS uses.
+/// - `running`: Cleared by a stop signal to end the segmenting.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The playlist, or an error if ffmpeg fails or was interrupted.
+///
+/// # Notes
+/// - The streams are copied, not encoded again.
+/// - HLS uses MPEG-TS segments for H.264 and fragmented MP4 ones for H.265, which Apple
+///   players need for it.
+/// - The HLS playlist is written to a temporary file and renamed, so a web server never
+///   serves half of it.
+pub fn segment_video(
+    video: &Path,
+    output_path: &Path,
+    format: ClipFormat,
+    codec: VideoCodec,
+    running: Arc<AtomicBool>,
+) -> Result<PathBuf> {
+    let _span = info_span!("segment", video = %video.display(), format = %format).entered();
+    let stem = output_path
+        .file_stem()
+        .map_or_else(|| "stream".into(), |stem| stem.to_string_lossy());
+    let seconds = SEGMENT_SECONDS.to_string();
+    let args: Vec<OsString> = match format {
+        ClipFormat::Hls => {
+            let (segment_type, extension) = match codec {
+                VideoCodec::H264 => ("mpegts", "ts"),
+                VideoCodec::H265 => ("fmp4", "m4s"),
+            };
+            vec![
+                "-f".into(),
+                "hls".into(),
+                "-hls_time".into(),
+                seconds.into(),
+                "-hls_playlist_type".into(),
+                "vod".into(),
+                "-hls_flags".into(),
+                "temp_file".into(),
+                "-hls_segment_type".into(),
+                segment_type.into(),
+                "-hls_fmp4_init_filename".into(),
+                format!("{}_init.mp4", stem).into(),
+                "-hls_segment_filename".into(),
+                output_path
+                    .with_file_name(format!("{}_%04d.{}", stem, extension))
+                    .into(),
+            ]
+        }
+        ClipFormat::Dash => vec![
+            "-f".into(),
+            "dash".into(),
+            "-seg_duration".into(),
+            seconds.into(),
+            "-use_template".into(),
+            "1".into(),
+            "-use_timeline".into(),
+            "1".into(),
+            "-init_seg_name".into(),
+            format!("{}_init_$RepresentationID$.m4s", stem).into(),
+            "-media_seg_name".into(),
+            format!("{}_$RepresentationID$_$Number%05d$.m4s", stem).into(),
+        ],
+        _ => unreachable!("Only HLS and DASH are segmented"),
+    };
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-i"])
+        .arg(video)
+        .args(["-c", "copy"])
+        .args(args)
+        .arg(output_path);
+    run_encode(command, &running)
+        .with_context(|| format!("Failed to write the {} stream", format))?;
+    debug!("{} stream written to {:?}", format, output_path);
+    Ok(output_path.to_path_buf())
+}
@@ -1,16 +1,52 @@
-use anyhow::Result;
-use ctrlc;
-use indicatif::{ProgressBar, ProgressStyle};
+use anyhow::{Context, Result};
+use indicatif::ProgressStyle;
 use log::debug;
 use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command as StdCommand;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
 };
+use std::thread;
 use std::time::SystemTime;
+use tracing::info_span;
+
+use fxp_cache::Cache;
+use fxp_merger::{blend_image, Blending};
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::{progress_bar, ToolError};
+use fxp_stream::{decoded_size, has_deep_color, FrameEncoder, IccProfile, MemoryLimit};
+
+use crate::error::ClutterError;
+use crate::transfer::ColorTransfer;
+
+/// What the colors of each image are mapped with.
+#[derive(Debug, Clone, Copy)]
+pub enum Lookup<'a> {
+    /// A CLUT image, applied by ImageMagick.
+    Clut(&'a Path),
+    /// A color transfer from the reference image at the path, applied natively.
+    Transfer(&'a Path, &'a ColorTransfer),
+}
+
+impl Lookup<'_> {
+    /// Returns the CLUT or reference image.
+    fn path(&self) -> &Path {
+        match self {
+            Lookup::Clut(path) | Lookup::Transfer(path, _) => path,
+        }
+    }
+}
+
+/// Where and how the processed images are written.
+#[derive(Clone, Copy)]
+struct Destination<'a> {
+    directory: &'a Path,
+    encoder: &'a FrameEncoder,
+}
 
 /// Applies a Color Lookup Table (CLUT) to multiple images and saves the results.
 ///
@@ -18,23 +54,43 @@ use std::time::SystemTime;
 /// while providing progress tracking and supporting graceful interruption.
 ///
 /// # Parameters
-/// - `clut_path`: Path to the CLUT file to apply.
+/// - `lookup`: The CLUT file, or the color transfer from a reference image, to apply.
 /// - `images`: A `BTreeMap` containing image IDs mapped to their file paths.
 /// - `output_dir`: Directory where processed images will be saved.
+/// - `opacity`: Blend each clutted image over its input with this opacity, or `None`
+///   to save the clutted images as they are.
+/// - `encoder`: The format the images are written in.
+/// - `jobs`: Number of images processed at the same time.
+/// - `memory`: Limit on the memory the images processed at the same time take.
 ///
 /// # Returns
-/// - `Result<()>`: Indicates success or failure of the operation.
+/// - `Result<usize>`: The number of images in the output directory, or an error if any
+///   image failed or processing was interrupted.
 ///
 /// # Notes
 /// - The function displays a progress bar showing processing status.
 /// - Processing can be interrupted with `Ctrl+C`, gracefully terminating the operation.
 /// - Debug messages and timing information are logged during execution.
+/// - Images whose input and CLUT are unchanged since the last run into the same output
+///   directory are skipped, see `fxp_cache::Cache`; for a color transfer, the mapping
+///   has to be unchanged too, as it depends on the colors of all frames.
+/// - With an opacity, the clutted image is blended in memory and only the blend is
+///   written, see `clut_and_blend_image`.
+/// - With an output format, every image is written in it, its extension replaced by the
+///   format's; a CLUT is then applied in memory too, as ImageMagick knows nothing of the
+///   encoder settings.
+/// - Each of the `jobs` workers takes the next image as it finishes one, and waits before
+///   decoding it while the other workers hold too much of `memory`, see `working_size`.
 pub fn clut_all_images(
-    clut_path: &PathBuf,
+    lookup: Lookup,
     images: &BTreeMap<u32, PathBuf>,
     output_dir: &Path,
-) -> Result<()> {
-    let pb = ProgressBar::new(images.len() as u64);
+    opacity: Option<f32>,
+    encoder: &FrameEncoder,
+    jobs: usize,
+    memory: &MemoryLimit,
+) -> Result<usize> {
+    let pb = progress_bar(images.len() as u64);
     pb.set_style(ProgressStyle::default_bar().template(
         "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
     )?);
@@ -42,33 +98,164 @@ pub fn clut_all_images(
     debug!("Starting to process images...");
     let start_time = SystemTime::now();
 
-    let is_terminated = Arc::new(AtomicBool::new(false));
-    let is_terminated_clone = Arc::clone(&is_terminated);
+    let running = running_flag()?;
 
-    ctrlc::set_handler(move || {
-        is_terminated_clone.store(true, Ordering::SeqCst);
-    })
-    .expect("Error setting Ctrl+C handler");
+    let cache = Mutex::new(Cache::open(output_dir, Modes::Clutter)?);
+    let destination = Destination {
+        directory: output_dir,
+        encoder,
+    };
+    let failed = AtomicUsize::new(0);
+    let error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    let inputs: Vec<&PathBuf> = images.values().collect();
+    let next = AtomicUsize::new(0);
+    let workers = jobs.clamp(1, inputs.len().max(1));
+    debug!("Processing with {} workers", workers);
 
-    for (index, input_image) in images.values().enumerate() {
-        if is_terminated.load(Ordering::SeqCst) {
-            debug!("Process interrupted by user. Exiting...");
-            break;
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if !running.load(Ordering::SeqCst) {
+                    debug!("Process interrupted by user. Exiting...");
+                    break;
+                }
+                if error.lock().expect("a worker panicked").is_some() {
+                    break;
+                }
+                let Some(input_image) = inputs.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                    break;
+                };
+                let result = clut_one(
+                    lookup,
+                    input_image,
+                    destination,
+                    opacity,
+                    &cache,
+                    memory,
+                    &running,
+                );
+                match result {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        if running.load(Ordering::SeqCst) {
+                            failed.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                    Err(e) => {
+                        error.lock().expect("a worker panicked").get_or_insert(e);
+                        break;
+                    }
+                }
+                pb.inc(1);
+            });
         }
-
-        debug!("Processing image {}: {:?}", index + 1, input_image);
-        clut_image(input_image, clut_path, output_dir, &is_terminated);
-        pb.inc(1);
-        debug!("Image {} processed successfully.", index + 1);
-    }
+    });
 
     pb.finish_with_message("Processing complete!");
+    cache.into_inner().expect("a worker panicked").save()?;
+    if let Some(e) = error.into_inner().expect("a worker panicked") {
+        return Err(e);
+    }
+    if !running.load(Ordering::SeqCst) {
+        return Err(ClutterError::Interrupted.into());
+    }
+    let failed = failed.into_inner();
+    if failed > 0 {
+        anyhow::bail!("{} of {} images failed to process", failed, images.len());
+    }
     debug!(
         "All images processed successfully in {:?}.",
         start_time.elapsed()?
     );
 
-    Ok(())
+    Ok(images.len())
+}
+
+/// Processes an image unless its output is fresh.
+///
+/// # Returns
+/// - `Result<bool>`: Whether the output is there, or an error if the cache could not
+///   be read or updated.
+fn clut_one(
+    lookup: Lookup,
+    input_image: &Path,
+    destination: Destination,
+    opacity: Option<f32>,
+    cache: &Mutex<Cache>,
+    memory: &MemoryLimit,
+    running: &Arc<AtomicBool>,
+) -> Result<bool> {
+    let file_name = input_image
+        .file_name()
+        .with_context(|| format!("Input image {:?} has no filename", input_image))?;
+    let encoder = destination.encoder;
+    let output_path = encoder.output_path(&destination.directory.join(file_name));
+    let mut parameters: Vec<String> = opacity.iter().map(|o| o.to_string()).collect();
+    if let Lookup::Transfer(_, transfer) = lookup {
+        parameters.push(transfer.digest());
+    }
+    parameters.extend(encoder.cache_parameter());
+    let key = {
+        let mut cache = cache.lock().expect("a worker panicked");
+        let key = cache.key(&[input_image, lookup.path()], &parameters)?;
+        if cache.is_fresh(&output_path, &key) {
+            debug!("{:?} is unchanged, skipping", input_image);
+            return Ok(true);
+        }
+        key
+    };
+
+    let _permit = memory.acquire(working_size(lookup, opacity, input_image));
+    debug!("Processing image {:?}", input_image);
+    // convert would give the output the CLUT's color profile, so an image with its own
+    // goes through memory, where the profile is kept.
+    let profile = IccProfile::read(input_image);
+    let written = match (lookup, opacity) {
+        (Lookup::Clut(clut_path), None) if encoder.is_default() && profile.is_none() => {
+            clut_image(input_image, clut_path, &output_path, running)?
+        }
+        (Lookup::Clut(clut_path), opacity) => clut_and_blend_image(
+            input_image,
+            clut_path,
+            &output_path,
+            opacity,
+            encoder,
+            profile.as_ref(),
+            running,
+        )?,
+        (Lookup::Transfer(_, transfer), opacity) => transfer_image(
+            input_image,
+            transfer,
+            &output_path,
+            opacity,
+            encoder,
+            profile.as_ref(),
+            running,
+        ),
+    };
+    if written {
+        cache
+            .lock()
+            .expect("a worker panicked")
+            .record(&output_path, key)?;
+        debug!("Image {:?} processed successfully.", input_image);
+    }
+    Ok(written)
+}
+
+/// Returns the memory processing an image takes, as a multiple of its decoded size.
+///
+/// # Notes
+/// - ImageMagick holds the image and its clutted copy; blending adds the source and the
+///   blend, and a color transfer holds the source and the transferred copy.
+fn working_size(lookup: Lookup, opacity: Option<f32>, input_image: &Path) -> usize {
+    let copies = match (lookup, opacity) {
+        (Lookup::Clut(_), None) => 2,
+        (Lookup::Clut(_), Some(_)) => 4,
+        (Lookup::Transfer(..), None) => 2,
+        (Lookup::Transfer(..), Some(_)) => 3,
+    };
+    copies * decoded_size(input_image)
 }
 
 /// Applies a Color Lookup Table (CLUT) to an image and saves the result.
@@ -80,11 +267,12 @@ pub fn clut_all_images(
 /// # Parameters
 /// - `input_image`: Path to the source image file to process.
 /// - `clut_path`: Path to the CLUT file to apply.
-/// - `output_dir`: Directory where the processed image will be saved.
-/// - `is_terminated`: Flag to check if processing should be stopped.
+/// - `output_path`: Path where the processed image will be saved.
+/// - `running`: Flag cleared when processing should stop.
 ///
 /// # Returns
-/// - `Result<()>`: Returns `Ok(())` on success or an error if processing fails.
+/// - `Result<bool>`: `true` if the CLUT was applied and the output written, or an error
+///   if `convert` is not installed.
 ///
 /// # Notes
 /// - The function checks for a termination signal before proceeding with processing.
@@ -93,31 +281,220 @@ pub fn clut_all_images(
 fn clut_image(
     input_image: &Path,
     clut_path: &Path,
-    output_dir: &Path,
-    is_terminated: &Arc<AtomicBool>,
-) {
-    let file_name = input_image.file_name().unwrap();
-    let output_path = output_dir.join(file_name);
-
+    output_path: &Path,
+    running: &Arc<AtomicBool>,
+) -> Result<bool> {
     // If termination was requested, stop processing
-    if is_terminated.load(Ordering::SeqCst) {
+    if !running.load(Ordering::SeqCst) {
         debug!(
             "Skipping {} due to termination request.",
-            file_name.to_string_lossy()
+            input_image.display()
         );
-        return;
+        return Ok(false);
     }
 
     // Apply the CLUT to the source image
+    let _span = info_span!("clut", input = %input_image.display(), output = %output_path.display())
+        .entered();
     let status = StdCommand::new("convert")
         .arg(clut_path)
         .arg(input_image)
         .arg("-clut")
-        .arg(&output_path)
+        .args(depth_args(input_image))
+        .arg(output_path)
         .status()
-        .expect("Failed to run convert command");
+        .map_err(|e| ClutterError::spawn("convert", e))
+        .context("Failed to run convert command")?;
 
     if !status.success() {
         eprintln!("Failed to apply CLUT: {:?}", input_image);
     }
+    Ok(status.success())
+}
+
+/// Returns the `convert` options keeping 16 bits per channel for an image with more
+/// than 8, which would otherwise get the depth of the CLUT.
+fn depth_args(input_image: &Path) -> Vec<&'static str> {
+    if has_deep_color(input_image) {
+        vec!["-depth", "16"]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Applies a CLUT to an image and blends the result over it, saving only the blend.
+///
+/// # Parameters
+/// - `input_image`: Path to the source image file to process.
+/// - `clut_path`: Path to the CLUT file to apply.
+/// - `output_path`: Path where the blended image will be saved.
+/// - `opacity`: The opacity of the clutted image over the source (between `0.0` and `1.0`),
+///   or `None` to save the clutted image as it is.
+/// - `encoder`: The format the image is written in.
+/// - `profile`: The color profile of the source image, embedded in the output.
+/// - `running`: Flag cleared when processing should stop.
+///
+/// # Returns
+/// - `Result<bool>`: `true` if the blend was written, or an error if `convert` is not
+///   installed.
+///
+/// # Notes
+/// - ImageMagick's `convert` writes the clutted image as PNG to its standard output, so
+///   it is never written to disk and read back by the Merger.
+/// - The blend is the one of `fxp_merger`; a clutted image of another size than the
+///   source is resized to it first.
+/// - If any step fails, an error message is printed to stderr.
+fn clut_and_blend_image(
+    input_image: &Path,
+    clut_path: &Path,
+    output_path: &Path,
+    opacity: Option<f32>,
+    encoder: &FrameEncoder,
+    profile: Option<&IccProfile>,
+    running: &Arc<AtomicBool>,
+) -> Result<bool> {
+    if !running.load(Ordering::SeqCst) {
+        debug!(
+            "Skipping {} due to termination request.",
+            input_image.display()
+        );
+        return Ok(false);
+    }
+
+    let _span = info_span!(
+        "clut",
+        input = %input_image.display(),
+        output = %output_path.display(),
+        opacity = %opacity.unwrap_or(1.0)
+    )
+    .entered();
+    match clut_and_blend(
+        input_image,
+        clut_path,
+        output_path,
+        opacity,
+        encoder,
+        profile,
+    ) {
+        Ok(()) => Ok(true),
+        // Without convert every image fails the same way, so stop at the first.
+        Err(e) if matches!(e.downcast_ref(), Some(ClutterError::ToolMissing(_))) => Err(e),
+        Err(e) => {
+            eprintln!("Failed to apply CLUT: {:?}: {:#}", input_image, e);
+            Ok(false)
+        }
+    }
+}
+
+/// Runs the CLUT into memory, blends it over the source image if there is an opacity and
+/// saves the result.
+fn clut_and_blend(
+    input_image: &Path,
+    clut_path: &Path,
+    output_path: &Path,
+    opacity: Option<f32>,
+    encoder: &FrameEncoder,
+    profile: Option<&IccProfile>,
+) -> Result<()> {
+    let output = StdCommand::new("convert")
+        .arg(clut_path)
+        .arg(input_image)
+        .arg("-clut")
+        .args(depth_args(input_image))
+        .arg("png:-")
+        .output()
+        .map_err(|e| ClutterError::spawn("convert", e))
+        .context("Failed to run convert command")?;
+    if !output.status.success() {
+        return Err(ClutterError::ToolFailed {
+            tool: "convert".to_string(),
+            reason: format!(
+                "{}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+        .into());
+    }
+    let clutted =
+        image::load_from_memory(&output.stdout).context("Failed to decode the clutted image")?;
+    let Some(opacity) = opacity else {
+        return encoder
+            .save(&clutted, profile, output_path)
+            .with_context(|| format!("Failed to save clutted image {:?}", output_path));
+    };
+    let original = image::open(input_image)
+        .with_context(|| format!("Failed to open image {}", input_image.display()))?;
+    let clutted = if (clutted.width(), clutted.height()) == (original.width(), original.height()) {
+        clutted
+    } else {
+        clutted.resize_exact(
+            original.width(),
+            original.height(),
+            image::imageops::FilterType::Lanczos3,
+        )
+    };
+    let blended = blend_image(&original, &clutted, opacity, Blending::default());
+    encoder
+        .save(&blended, profile, output_path)
+        .with_context(|| format!("Failed to save blended image {:?}", output_path))
+}
+
+/// Transfers the colors of a reference image to an image and saves the result.
+///
+/// # Parameters
+/// - `input_image`: Path to the source image file to process.
+/// - `transfer`: The color transfer to apply.
+/// - `output_path`: Path where the processed image will be saved.
+/// - `opacity`: Blend the result over the source with this opacity, or `None` to save it
+///   as it is.
+/// - `encoder`: The format the image is written in.
+/// - `profile`: The color profile of the source image, embedded in the output.
+/// - `running`: Flag cleared when processing should stop.
+///
+/// # Returns
+/// - `bool`: `true` if the output was written.
+///
+/// # Notes
+/// - The transfer runs in memory, without ImageMagick.
+/// - If any step fails, an error message is printed to stderr.
+fn transfer_image(
+    input_image: &Path,
+    transfer: &ColorTransfer,
+    output_path: &Path,
+    opacity: Option<f32>,
+    encoder: &FrameEncoder,
+    profile: Option<&IccProfile>,
+    running: &Arc<AtomicBool>,
+) -> bool {
+    if !running.load(Ordering::SeqCst) {
+        debug!(
+            "Skipping {} due to termination request.",
+            input_image.display()
+        );
+        return false;
+    }
+
+    let _span =
+        info_span!("transfer", input = %input_image.display(), output = %output_path.display())
+            .entered();
+    let result = image::open(input_image)
+        .with_context(|| format!("Failed to open image {}", input_image.display()))
+        .and_then(|original| {
+            let transferred = transfer.apply(&original);
+            let output = match opacity {
+                Some(opacity) => blend_image(&original, &transferred, opacity, Blending::default()),
+                None => transferred,
+            };
+            encoder
+                .save(&output, profile, output_path)
+                .with_context(|| format!("Failed to save image {:?}", output_path))
+        });
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to transfer colors: {:?}: {:#}", input_image, e);
+            false
+        }
+    }
 }
@@ -4,21 +4,95 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use tracing::info_span;
 
 use fxp_modes::Modes;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
 
-use crate::clut::clut_all_images;
+use crate::clut::{clut_all_images, Lookup};
+use crate::error::ClutterError;
+use crate::transfer::ColorTransfer;
 
 use fxp_filenames::FileOperations;
+use fxp_stream::{default_max_memory, FrameEncoder, MemoryLimit};
+
+/// Where the Clutter takes the colors of the images from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClutSource {
+    /// A prepared CLUT image, applied with ImageMagick.
+    Clut(PathBuf),
+    /// A reference image, whose colors are transferred to the images by matching their
+    /// histograms; see `ColorTransfer`.
+    Reference(PathBuf),
+}
+
+impl ClutSource {
+    /// Returns the CLUT or reference image.
+    pub fn path(&self) -> &Path {
+        match self {
+            ClutSource::Clut(path) | ClutSource::Reference(path) => path,
+        }
+    }
+
+    /// Returns the name of the image, as recorded in the plan and manifest.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClutSource::Clut(_) => "clut image",
+            ClutSource::Reference(_) => "reference image",
+        }
+    }
+
+    /// Checks that the image is a file and resolves its path.
+    fn canonicalize(&self) -> Result<Self> {
+        let path = self.path();
+        if !path.is_file() {
+            return Err(ClutterError::InvalidInput(format!(
+                "{} '{}' does not exist or is not a file",
+                self.label(),
+                path.display()
+            ))
+            .into());
+        }
+        let path = fs::canonicalize(path)
+            .with_context(|| format!("Failed to resolve {} '{}'", self.label(), path.display()))?;
+        Ok(match self {
+            ClutSource::Clut(_) => ClutSource::Clut(path),
+            ClutSource::Reference(_) => ClutSource::Reference(path),
+        })
+    }
+
+    /// Returns the name of the image for messages, e.g. `CLUT image`.
+    fn label(&self) -> &'static str {
+        match self {
+            ClutSource::Clut(_) => "CLUT image",
+            ClutSource::Reference(_) => "Reference image",
+        }
+    }
+}
 
 /// Struct responsible for applying CLUT (Color Look-Up Table) to images in a directory.
 pub struct Clutter {
     input_directory: PathBuf,
-    clut_image: PathBuf,
+    source: ClutSource,
     input_files: BTreeMap<u32, PathBuf>,
     output_directory: PathBuf,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Blend each clutted image over its input with this opacity in the same pass;
+    /// `new` sets `None`, which saves the clutted images as they are.
+    pub opacity: Option<f32>,
+    /// The format the images are written in; `new` keeps the format of the input images.
+    pub encoder: FrameEncoder,
+    /// Number of images processed at the same time; `new` sets 1.
+    pub jobs: usize,
+    /// Memory the images processed at the same time may take, in bytes; `new` sets
+    /// `fxp_stream::default_max_memory()`, half of the system memory.
+    pub max_memory: usize,
 }
 
 impl Clutter {
@@ -28,8 +102,9 @@ impl Clutter {
     ///
     /// # Parameters
     /// - `input_directory`: Path to the directory containing input image files.
-    /// - `clut_image`: Path to the CLUT image file.
+    /// - `source`: The CLUT image, or the reference image to transfer the colors of.
     /// - `output_directory`: Optional path for output files; defaults to input directory if not provided.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: New `Clutter` instance on success, or an error if validation fails.
@@ -40,21 +115,23 @@ impl Clutter {
     /// - Sets up initial processing files from input directory.
     pub fn new(
         input_directory: String,
-        clut_image: String,
+        source: ClutSource,
         output_directory: Option<String>,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
         debug!("Initializing new Clutter instance with:");
         debug!("- Input directory: {}", input_directory);
-        debug!("- CLUT image: {}", clut_image);
+        debug!("- {}: {}", source.label(), source.path().display());
         debug!("- Output directory: {:?}", output_directory);
 
         // Process input directory: convert, check and canonicalize.
         let input_directory_path = PathBuf::from(&input_directory);
         if !input_directory_path.is_dir() {
-            anyhow::bail!(
+            return Err(ClutterError::InvalidInput(format!(
                 "Input directory '{}' does not exist or is not a directory",
                 input_directory_path.display()
-            );
+            ))
+            .into());
         }
         let input_directory_path = fs::canonicalize(&input_directory_path).with_context(|| {
             format!(
@@ -64,21 +141,9 @@ impl Clutter {
         })?;
         debug!("Canonicalized input directory: {:?}", input_directory_path);
 
-        // Process CLUT image: convert, check and canonicalize.
-        let clut_image_path = PathBuf::from(&clut_image);
-        if !clut_image_path.is_file() {
-            anyhow::bail!(
-                "CLUT image '{}' does not exist or is not a file",
-                clut_image_path.display()
-            );
-        }
-        let clut_image_path = fs::canonicalize(&clut_image_path).with_context(|| {
-            format!(
-                "Failed to resolve CLUT image '{}'",
-                clut_image_path.display()
-            )
-        })?;
-        debug!("Canonicalized CLUT image: {:?}", clut_image_path);
+        // Process CLUT or reference image: check and canonicalize.
+        let source = source.canonicalize()?;
+        debug!("Canonicalized {}: {:?}", source.name(), source.path());
 
         // Create output directory using the appropriate handler.
         debug!("Creating output directory...");
@@ -89,7 +154,7 @@ impl Clutter {
             Output::Clutter(clutter_output) => {
                 debug!("Using Clutter output handler to create directory");
                 let path = clutter_output
-                    .create_output((input_directory_path.clone(), output_directory))?;
+                    .create_output((input_directory_path.clone(), output_directory), collision)?;
                 debug!("Output directory created at: {:?}", path);
                 path
             }
@@ -111,16 +176,88 @@ impl Clutter {
 
         debug!("Successfully initialized Clutter instance:");
         debug!("- Final input directory: {:?}", input_directory_path);
-        debug!("- Final CLUT image path: {:?}", clut_image_path);
+        debug!("- Final {} path: {:?}", source.name(), source.path());
         debug!("- Output directory: {:?}", output_directory_path);
 
         Ok(Self {
             input_directory: input_directory_path,
-            clut_image: clut_image_path,
+            source,
             input_files,
             output_directory: output_directory_path,
+            in_place: false,
+            opacity: None,
+            encoder: FrameEncoder::default(),
+            jobs: 1,
+            max_memory: default_max_memory(),
         })
     }
+
+    /// Resolves what `new` and `create_clut_images` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing input image files.
+    /// - `source`: The CLUT image, or the reference image to transfer the colors of.
+    /// - `output_directory`: Optional path for output files.
+    /// - `opacity`: The opacity the clutted images are blended with, if any.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails.
+    pub fn plan(
+        input_directory: String,
+        source: ClutSource,
+        output_directory: Option<String>,
+        opacity: Option<f32>,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_directory_path = PathBuf::from(&input_directory);
+        if !input_directory_path.is_dir() {
+            return Err(ClutterError::InvalidInput(format!(
+                "Input directory '{}' does not exist or is not a directory",
+                input_directory_path.display()
+            ))
+            .into());
+        }
+        let input_directory_path = fs::canonicalize(&input_directory_path).with_context(|| {
+            format!(
+                "Failed to resolve input directory '{}'",
+                input_directory_path.display()
+            )
+        })?;
+
+        if !source.path().is_file() {
+            return Err(ClutterError::InvalidInput(format!(
+                "{} '{}' does not exist or is not a file",
+                source.label(),
+                source.path().display()
+            ))
+            .into());
+        }
+
+        let input_files = setup_clut_processing(&input_directory)?;
+
+        let mode: Modes = Modes::Clutter;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Clutter(clutter_output) => clutter_output
+                .plan_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Clutter mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Clutter)
+            .entry("input directory", input_directory_path.display())
+            .entry("images", input_files.len())
+            .entry(source.name(), source.path().display());
+        if let ClutSource::Reference(_) = source {
+            plan = plan.entry("color transfer", "histogram matching, applied natively");
+        }
+        if let Some(opacity) = opacity {
+            plan = plan.entry("opacity", opacity);
+        }
+        Ok(plan
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
 }
 
 /// Sets up CLUT (Color LookUp Table) processing by preparing input images and directories.
@@ -170,16 +307,78 @@ impl Clutter {
     /// # Notes
     /// - Creates a new directory for CLUT-processed images if it doesn't exist.
     /// - Processes all images in the input directory using the specified CLUT.
+    /// - Images are staged and moved into the output directory only once all of them
+    ///   succeeded, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - With an `opacity`, each clutted image is blended over its input in memory and
+    ///   only the blend is written, instead of running the Merger on a clutted directory.
+    /// - With a reference image, the colors of the input are matched to it natively
+    ///   instead; see `ColorTransfer`.
+    /// - With a format in `encoder`, the images are written in it, named with its
+    ///   extension.
+    /// - Writes a `manifest.json` recording the CLUT or reference image and input hashes.
+    /// - `jobs` images are processed at the same time, as long as they fit in
+    ///   `max_memory`; workers wait for each other otherwise.
     /// - Returns an error if image processing fails.
     pub fn create_clut_images(&self) -> Result<String> {
+        self.encoder
+            .validate()
+            .map_err(ClutterError::InvalidInput)?;
         debug!(
             "Applying CLUT from source image '{}' to images in directory '{}'",
-            self.clut_image.display(),
+            self.source.path().display(),
             self.input_directory.display()
         );
+        let _span = info_span!(
+            "clutter",
+            input = %self.input_directory.display(),
+            source = %self.source.path().display(),
+            output = %self.output_directory.display()
+        )
+        .entered();
 
         // Now that `input_files` has been populated in `new()`, simply use it.
-        clut_all_images(&self.clut_image, &self.input_files, &self.output_directory)?;
+        let mut manifest = Manifest::new(Modes::Clutter)
+            .parameter("input", self.input_directory.display())
+            .parameter(self.source.name(), self.source.path().display());
+        if let Some(opacity) = self.opacity {
+            manifest = manifest.parameter("opacity", opacity);
+        }
+        if let Some(format) = self.encoder.format {
+            manifest = manifest
+                .parameter("output format", format)
+                .parameter("quality", self.encoder.quality)
+                .parameter("lossless", self.encoder.lossless);
+        }
+        let manifest = manifest
+            .inputs(self.input_files.values())
+            .inputs([self.source.path()]);
+
+        let transfer = match &self.source {
+            ClutSource::Reference(reference) => Some(
+                ColorTransfer::new(reference, &self.input_files)
+                    .context("Failed to measure the colors for the transfer")?,
+            ),
+            ClutSource::Clut(_) => None,
+        };
+        let lookup = match &transfer {
+            Some(transfer) => Lookup::Transfer(self.source.path(), transfer),
+            None => Lookup::Clut(self.source.path()),
+        };
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let processed = clut_all_images(
+            lookup,
+            &self.input_files,
+            staged.path(),
+            self.opacity,
+            &self.encoder,
+            self.jobs,
+            &MemoryLimit::new(self.max_memory),
+        )?;
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Clutter, processed)?;
 
         Ok(self.output_directory.to_string_lossy().into_owned())
     }
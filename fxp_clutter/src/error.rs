@@ -0,0 +1,44 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Clutter that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum ClutterError {
+    /// A tool the Clutter runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Clutter runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The input does not exist or is not what the Clutter takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Processing interrupted by user")]
+    Interrupted,
+}
+
+impl ClutterError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ClutterError::ToolMissing(_) => FailureKind::ToolMissing,
+            ClutterError::ToolFailed { .. } => FailureKind::ToolFailed,
+            ClutterError::InvalidInput(_) => FailureKind::InvalidInput,
+            ClutterError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for ClutterError {
+    fn missing(tool: &str) -> Self {
+        ClutterError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        ClutterError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
@@ -1,4 +1,8 @@
 mod clut;
 mod clutter;
+mod error;
+mod transfer;
 
-pub use clutter::Clutter;
+pub use clutter::{ClutSource, Clutter};
+pub use error::ClutterError;
+pub use transfer::ColorTransfer;
@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, RgbImage, RgbaImage};
+use log::debug;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use fxp_stream::is_deep_color;
+
+/// Frames read to measure the colors of the input; more are sampled evenly.
+const SAMPLED_FRAMES: usize = 32;
+
+/// Counts of each of the 256 levels of the red, green and blue channels.
+type Histogram = [[u64; 256]; 3];
+
+/// A color transfer matching the colors of the frames to those of a reference image.
+///
+/// Each channel is mapped through its own lookup table, built by matching the histogram
+/// of the frames to the histogram of the reference; the alpha channel is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorTransfer {
+    tables: [[u8; 256]; 3],
+}
+
+impl ColorTransfer {
+    /// Builds the transfer from the colors of `frames` to the colors of `reference`.
+    ///
+    /// # Parameters
+    /// - `reference`: The image whose colors the frames should take.
+    /// - `frames`: The frames to transfer the colors to, mapped by number.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The transfer, or an error if an image cannot be decoded.
+    ///
+    /// # Notes
+    /// - The colors of the frames are measured over all of them together, so every frame
+    ///   is mapped alike and the sequence does not flicker.
+    /// - Of many frames, 32 evenly spaced ones are measured.
+    pub fn new(reference: &Path, frames: &BTreeMap<u32, PathBuf>) -> Result<Self> {
+        let mut reference_histogram = [[0; 256]; 3];
+        add_to_histogram(&open(reference)?, &mut reference_histogram);
+
+        let step = frames.len().div_ceil(SAMPLED_FRAMES).max(1);
+        let mut frames_histogram = [[0; 256]; 3];
+        for frame in frames.values().step_by(step) {
+            add_to_histogram(&open(frame)?, &mut frames_histogram);
+        }
+        debug!(
+            "Measured the colors of {} of {} frames",
+            frames.len().div_ceil(step),
+            frames.len()
+        );
+
+        let mut tables = [[0; 256]; 3];
+        for (channel, table) in tables.iter_mut().enumerate() {
+            *table = match_histograms(&frames_histogram[channel], &reference_histogram[channel]);
+        }
+        Ok(Self { tables })
+    }
+
+    /// Returns the image with its colors transferred, with 8 bits per channel, or 16 if
+    /// the image has more than 8.
+    ///
+    /// # Notes
+    /// - An image without alpha channel stays without one, so it can still be saved as JPEG.
+    /// - The levels of a 16-bit image are mapped between the entries of the tables, so
+    ///   its gradients keep their precision.
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        if is_deep_color(image) {
+            return if image.color().has_alpha() {
+                let mut pixels = image.to_rgba16();
+                for pixel in pixels.pixels_mut() {
+                    self.map_deep(&mut pixel.0);
+                }
+                DynamicImage::ImageRgba16(pixels)
+            } else {
+                let mut pixels = image.to_rgb16();
+                for pixel in pixels.pixels_mut() {
+                    self.map_deep(&mut pixel.0);
+                }
+                DynamicImage::ImageRgb16(pixels)
+            };
+        }
+        if image.color().has_alpha() {
+            let mut pixels: RgbaImage = image.to_rgba8();
+            for pixel in pixels.pixels_mut() {
+                self.map(&mut pixel.0);
+            }
+            DynamicImage::ImageRgba8(pixels)
+        } else {
+            let mut pixels: RgbImage = image.to_rgb8();
+            for pixel in pixels.pixels_mut() {
+                self.map(&mut pixel.0);
+            }
+            DynamicImage::ImageRgb8(pixels)
+        }
+    }
+
+    /// Maps the red, green and blue levels of one pixel, leaving any alpha as it is.
+    fn map(&self, pixel: &mut [u8]) {
+        for (level, table) in pixel.iter_mut().zip(&self.tables) {
+            *level = table[*level as usize];
+        }
+    }
+
+    /// Maps the 16-bit red, green and blue levels of one pixel, interpolating between
+    /// the entries of the tables; any alpha is kept.
+    fn map_deep(&self, pixel: &mut [u16]) {
+        for (level, table) in pixel.iter_mut().zip(&self.tables) {
+            let position = *level as f32 / 257.0;
+            let index = (position as usize).min(254);
+            let fraction = position - index as f32;
+            let (low, high) = (table[index] as f32, table[index + 1] as f32);
+            *level = ((low + (high - low) * fraction) * 257.0).round() as u16;
+        }
+    }
+
+    /// Returns a digest of the lookup tables, which changes whenever the mapping does.
+    pub fn digest(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.tables.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Opens an image, naming it on failure.
+fn open(path: &Path) -> Result<DynamicImage> {
+    image::open(path).with_context(|| format!("Failed to decode image {}", path.display()))
+}
+
+/// Adds the levels of every pixel of `image` to `counts`.
+fn add_to_histogram(image: &DynamicImage, counts: &mut Histogram) {
+    for pixel in image.to_rgb8().pixels() {
+        for (channel, level) in pixel.0.iter().enumerate() {
+            counts[channel][*level as usize] += 1;
+        }
+    }
+}
+
+/// Maps every level of `source` to the level of `target` at the same cumulative share.
+fn match_histograms(source: &[u64; 256], target: &[u64; 256]) -> [u8; 256] {
+    let source = cumulative(source);
+    let target = cumulative(target);
+    let mut table = [0; 256];
+    let mut level = 0;
+    for (entry, share) in table.iter_mut().zip(source) {
+        while level < 255 && target[level] < share {
+            level += 1;
+        }
+        *entry = level as u8;
+    }
+    table
+}
+
+/// Returns the share of pixels at or below each level, from 0 to 1.
+fn cumulative(counts: &[u64; 256]) -> [f64; 256] {
+    let total = counts.iter().sum::<u64>().max(1) as f64;
+    let mut shares = [0.0; 256];
+    let mut sum = 0;
+    for (share, count) in shares.iter_mut().zip(counts) {
+        sum += count;
+        *share = sum as f64 / total;
+    }
+    shares
+}
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Context, Result};
+use ffmpeg_next::codec::context::Context as CodecContext;
+use ffmpeg_next::format::{context::Input, Pixel};
+use ffmpeg_next::media::Type;
+use ffmpeg_next::software::scaling::{context::Context as Scaler, flag::Flags};
+use ffmpeg_next::{frame, Rational, Rescale};
+use image::RgbImage;
+use log::debug;
+use std::path::Path;
+use std::sync::Once;
+
+static INIT: Once = Once::new();
+
+/// A decoded frame of a video.
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    /// Time of the frame from the start of the video, in milliseconds.
+    pub timestamp_ms: u64,
+    /// The frame, at the output size of the decoder.
+    pub image: RgbImage,
+}
+
+/// Decodes the video stream of a file in-process, through the ffmpeg libraries.
+///
+/// Frames come out in presentation order as RGB buffers, optionally scaled, without
+/// spawning ffmpeg or writing anything to disk.
+pub struct VideoDecoder {
+    input: Input,
+    decoder: ffmpeg_next::decoder::Video,
+    stream_index: usize,
+    time_base: Rational,
+    start_pts: i64,
+    scaler: Option<Scaler>,
+    output_size: (u32, u32),
+    /// Frames before this time are dropped, after a seek lands on an earlier keyframe.
+    skip_before_ms: u64,
+    flushed: bool,
+    /// The frame `frame_at` returned last and the one after it, still unconverted.
+    current: Option<(u64, frame::Video)>,
+    lookahead: Option<(u64, frame::Video)>,
+}
+
+impl VideoDecoder {
+    /// Opens the best video stream of a file.
+    ///
+    /// # Parameters
+    /// - `video`: Any video the ffmpeg libraries read.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The decoder, with an output size equal to the video's, or an
+    ///   error if the file cannot be read or has no video stream.
+    pub fn open(video: &Path) -> Result<Self> {
+        INIT.call_once(|| {
+            if let Err(e) = ffmpeg_next::init() {
+                debug!("Failed to initialize the ffmpeg libraries: {}", e);
+            }
+            ffmpeg_next::log::set_level(ffmpeg_next::log::Level::Error);
+        });
+
+        let input = ffmpeg_next::format::input(video)
+            .with_context(|| format!("Failed to open {}", video.display()))?;
+        let stream = input
+            .streams()
+            .best(Type::Video)
+            .ok_or_else(|| anyhow!("{} has no video stream", video.display()))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+        let start_pts = stream.start_time().max(0);
+        let decoder = CodecContext::from_parameters(stream.parameters())?
+            .decoder()
+            .video()
+            .with_context(|| format!("Failed to open the video decoder of {}", video.display()))?;
+        let output_size = (decoder.width(), decoder.height());
+        debug!(
+            "Decoding {} at {}x{}, time base {}",
+            video.display(),
+            output_size.0,
+            output_size.1,
+            time_base
+        );
+
+        Ok(Self {
+            input,
+            decoder,
+            stream_index,
+            time_base,
+            start_pts,
+            scaler: None,
+            output_size,
+            skip_before_ms: 0,
+            flushed: false,
+            current: None,
+            lookahead: None,
+        })
+    }
+
+    /// Returns the width and height of the video stream.
+    pub fn source_size(&self) -> (u32, u32) {
+        (self.decoder.width(), self.decoder.height())
+    }
+
+    /// Returns the duration of the file in milliseconds, or 0 if it is unknown.
+    pub fn duration_ms(&self) -> u64 {
+        (self.input.duration().max(0) / 1000) as u64
+    }
+
+    /// Scales the frames to `width`x`height` from now on.
+    pub fn set_output_size(&mut self, width: u32, height: u32) {
+        self.output_size = (width, height);
+        self.scaler = None;
+    }
+
+    /// Moves to a time of the video, so that the next frame is the first one shown at or
+    /// after it.
+    ///
+    /// # Parameters
+    /// - `timestamp_ms`: Time from the start of the video, in milliseconds.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the file cannot be seeked.
+    ///
+    /// # Notes
+    /// - The file is seeked to the keyframe before the time and the frames in between are
+    ///   decoded and dropped, so the position is exact to the frame.
+    pub fn seek(&mut self, timestamp_ms: u64) -> Result<()> {
+        let target = (timestamp_ms as i64).rescale((1, 1000), ffmpeg_next::rescale::TIME_BASE);
+        self.input
+            .seek(target, ..target)
+            .with_context(|| format!("Failed to seek to {} ms", timestamp_ms))?;
+        self.decoder.flush();
+        self.flushed = false;
+        self.skip_before_ms = timestamp_ms;
+        self.current = None;
+        self.lookahead = None;
+        Ok(())
+    }
+
+    /// Decodes the next frame.
+    ///
+    /// # Returns
+    /// - `Result<Option<DecodedFrame>>`: The frame, `None` at the end of the video, or an
+    ///   error if decoding fails.
+    pub fn next_frame(&mut self) -> Result<Option<DecodedFrame>> {
+        let next = match self.lookahead.take() {
+            Some(next) => Some(next),
+            None => self.decode()?,
+        };
+        match next {
+            Some((timestamp_ms, frame)) => Ok(Some(DecodedFrame {
+                timestamp_ms,
+                image: self.convert(&frame)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the frame shown at a time, for sampling the video at a fixed frame rate.
+    ///
+    /// # Parameters
+    /// - `timestamp_ms`: Time from the start of the video, in milliseconds; the times
+    ///   must not decrease between calls, other than through `seek`.
+    ///
+    /// # Returns
+    /// - `Result<Option<RgbImage>>`: The last frame shown at or before the time, the
+    ///   first frame if the time is before it, or `None` if there is no frame from the
+    ///   start or last seek on.
+    ///
+    /// # Notes
+    /// - Like ffmpeg's `fps` filter, frames are repeated when the video has fewer of
+    ///   them than asked for and dropped when it has more.
+    pub fn frame_at(&mut self, timestamp_ms: u64) -> Result<Option<RgbImage>> {
+        loop {
+            if self.lookahead.is_none() {
+                self.lookahead = self.decode()?;
+            }
+            let advance = match &self.lookahead {
+                Some((next_ms, _)) => *next_ms <= timestamp_ms || self.current.is_none(),
+                None => false,
+            };
+            if !advance {
+                break;
+            }
+            self.current = self.lookahead.take();
+            if self
+                .current
+                .as_ref()
+                .is_some_and(|(ms, _)| *ms >= timestamp_ms)
+            {
+                break;
+            }
+        }
+        // The frame stays current, as the next time may show it again.
+        let Some((shown_ms, frame)) = self.current.take() else {
+            return Ok(None);
+        };
+        let image = self.convert(&frame);
+        self.current = Some((shown_ms, frame));
+        image.map(Some)
+    }
+
+    /// Decodes frames until one is at or after `skip_before_ms`, feeding packets as needed.
+    fn decode(&mut self) -> Result<Option<(u64, frame::Video)>> {
+        loop {
+            let mut decoded = frame::Video::empty();
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                let timestamp_ms = self.timestamp_ms(&decoded);
+                if timestamp_ms >= self.skip_before_ms {
+                    return Ok(Some((timestamp_ms, decoded)));
+                }
+                continue;
+            }
+            if self.flushed {
+                return Ok(None);
+            }
+            let stream_index = self.stream_index;
+            let packet = self
+                .input
+                .packets()
+                .find_map(|(stream, packet)| (stream.index() == stream_index).then_some(packet));
+            match packet {
+                Some(packet) => self
+                    .decoder
+                    .send_packet(&packet)
+                    .context("Failed to decode a video packet")?,
+                None => {
+                    self.decoder.send_eof()?;
+                    self.flushed = true;
+                }
+            }
+        }
+    }
+
+    /// Returns the time of a decoded frame from the start of the video.
+    fn timestamp_ms(&self, decoded: &frame::Video) -> u64 {
+        let pts = decoded
+            .timestamp()
+            .or(decoded.pts())
+            .unwrap_or(self.start_pts);
+        (pts - self.start_pts)
+            .max(0)
+            .rescale(self.time_base, (1, 1000)) as u64
+    }
+
+    /// Converts a decoded frame to RGB at the output size.
+    fn convert(&mut self, decoded: &frame::Video) -> Result<RgbImage> {
+        let (width, height) = self.output_size;
+        if self.scaler.is_none() {
+            self.scaler = Some(Scaler::get(
+                decoded.format(),
+                decoded.width(),
+                decoded.height(),
+                Pixel::RGB24,
+                width,
+                height,
+                Flags::BICUBIC,
+            )?);
+        }
+        let scaler = self.scaler.as_mut().expect("the scaler was just created");
+        let mut rgb = frame::Video::empty();
+        scaler
+            .run(decoded, &mut rgb)
+            .context("Failed to convert a frame to RGB")?;
+
+        // Rows are padded to the stride of the frame; the image wants them packed.
+        let stride = rgb.stride(0);
+        let row = width as usize * 3;
+        let data = rgb.data(0);
+        let mut pixels = Vec::with_capacity(row * height as usize);
+        for y in 0..height as usize {
+            pixels.extend_from_slice(&data[y * stride..y * stride + row]);
+        }
+        RgbImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("Decoded frame does not match {}x{}", width, height))
+    }
+}
@@ -0,0 +1,7 @@
+// The decoder links the ffmpeg libraries, so it is only built with the `ffmpeg` feature;
+// without it the stages spawn the ffmpeg binary instead.
+#[cfg(feature = "ffmpeg")]
+mod decoder;
+
+#[cfg(feature = "ffmpeg")]
+pub use decoder::{DecodedFrame, VideoDecoder};
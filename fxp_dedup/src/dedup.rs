@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use indicatif::ProgressStyle;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::progress_bar;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::FileOperations;
+use fxp_filenames::FramePadding;
+
+use crate::error::DedupError;
+use crate::hash::{dhash, distance};
+
+/// Hash distance below which a frame is a duplicate of the last kept one, see `Dedup::threshold`.
+pub const DEFAULT_THRESHOLD: u32 = 5;
+
+/// Struct responsible for dropping near-duplicate frames from a directory of images.
+pub struct Dedup {
+    input_directory: PathBuf,
+    input_files: BTreeMap<u32, PathBuf>,
+    output_directory: PathBuf,
+    /// A frame whose hash differs from the last kept frame in fewer bits than this is
+    /// dropped; `new` sets `DEFAULT_THRESHOLD`. 0 keeps every frame.
+    pub threshold: u32,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Copy the dropped frames into this directory under their original names;
+    /// `new` sets `None`, which leaves them out.
+    pub duplicates_directory: Option<PathBuf>,
+}
+
+impl Dedup {
+    /// Creates a new `Dedup` instance for a directory of frames.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the kept frames; defaults to `<input>_dedup`.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: New `Dedup` instance on success, or an error if validation fails.
+    ///
+    /// # Notes
+    /// - Creates the output directory if it does not exist.
+    /// - The frames are ordered by the numbers in their filenames, see
+    ///   `FileOperations::load_files`.
+    pub fn new(
+        input_directory: String,
+        output_directory: Option<String>,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        debug!("Initializing new Dedup instance with:");
+        debug!("- Input directory: {}", input_directory);
+        debug!("- Output directory: {:?}", output_directory);
+
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+        debug!("Found {} input files for processing", input_files.len());
+
+        let mode: Modes = Modes::Dedup;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Dedup(dedup_output) => dedup_output
+                .create_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Dedup mode"),
+        };
+        debug!("Output directory created at: {:?}", output_directory_path);
+
+        Ok(Self {
+            input_directory: input_directory_path,
+            input_files,
+            output_directory: output_directory_path,
+            threshold: DEFAULT_THRESHOLD,
+            in_place: false,
+            duplicates_directory: None,
+        })
+    }
+
+    /// Resolves what `new` and `remove_duplicates` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the kept frames.
+    /// - `threshold`: The hash distance below which a frame is dropped.
+    /// - `duplicates_directory`: Where the dropped frames would be copied, if anywhere.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails.
+    ///
+    /// # Notes
+    /// - How many frames are dropped is only known once they are hashed, so the plan
+    ///   reports the number of input frames.
+    pub fn plan(
+        input_directory: String,
+        output_directory: Option<String>,
+        threshold: u32,
+        duplicates_directory: Option<&Path>,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+
+        let mode: Modes = Modes::Dedup;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Dedup(dedup_output) => dedup_output
+                .plan_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Dedup mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Dedup)
+            .entry("input directory", input_directory_path.display())
+            .entry("frames", input_files.len())
+            .entry("threshold", format!("{} of 64 bits", threshold));
+        if let Some(duplicates_directory) = duplicates_directory {
+            plan = plan.entry("duplicates directory", duplicates_directory.display());
+        }
+        Ok(plan
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
+}
+
+/// Checks that the input is a directory and canonicalizes it.
+fn canonical_input_directory(input_directory: &str) -> Result<PathBuf> {
+    let input_directory_path = PathBuf::from(input_directory);
+    if !input_directory_path.is_dir() {
+        return Err(DedupError::InvalidInput(format!(
+            "Input directory '{}' does not exist or is not a directory",
+            input_directory_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_directory_path).with_context(|| {
+        format!(
+            "Failed to resolve input directory '{}'",
+            input_directory_path.display()
+        )
+    })
+}
+
+/// Maps the frames of the input directory by their frame number.
+fn load_frames(input_directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let input_images: Vec<PathBuf> = fs::read_dir(input_directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    Ok(Modes::Dedup.load_files(&input_images)?)
+}
+
+impl Dedup {
+    /// Copies the frames that differ from their predecessors, renumbered, into the output.
+    ///
+    /// Each frame is compared with the last kept frame by the distance between their
+    /// perceptual hashes; frames closer than `threshold` are dropped.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of kept frames, or an error if a frame cannot be
+    ///   decoded or copied, or processing was interrupted.
+    ///
+    /// # Notes
+    /// - The first frame is always kept. Comparing with the last kept frame rather than
+    ///   the previous one keeps a slow fade from being dropped in its entirety.
+    /// - The kept frames are written as `frame_0001.<ext>`, `frame_0002.<ext>`, ..., without
+    ///   gaps, so the Clipper can read them with the default gap policy.
+    /// - The input directory is never modified; with `duplicates_directory`, the dropped
+    ///   frames are copied there so the choice can be reviewed.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are processed, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Writes a `manifest.json` recording the threshold and the input hashes.
+    pub fn remove_duplicates(&self) -> Result<usize> {
+        let _span = info_span!(
+            "dedup",
+            input = %self.input_directory.display(),
+            output = %self.output_directory.display(),
+            threshold = %self.threshold
+        )
+        .entered();
+
+        let mut manifest = Manifest::new(Modes::Dedup)
+            .parameter("input", self.input_directory.display())
+            .parameter("threshold", self.threshold);
+        if let Some(duplicates_directory) = &self.duplicates_directory {
+            manifest = manifest.parameter("duplicates", duplicates_directory.display());
+            fs::create_dir_all(duplicates_directory).with_context(|| {
+                format!(
+                    "Failed to create duplicates directory {}",
+                    duplicates_directory.display()
+                )
+            })?;
+        }
+        let manifest = manifest.inputs(self.input_files.values());
+
+        let running = running_flag()?;
+
+        let pb = progress_bar(self.input_files.len() as u64);
+        pb.set_style(ProgressStyle::default_bar().template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+        )?);
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let mut last_kept: Option<u64> = None;
+        let mut kept = 0;
+        // At most every frame is kept, so the count of input frames sets the padding.
+        let padding = FramePadding::for_count(self.input_files.len() as u64);
+
+        for (number, frame) in &self.input_files {
+            if !running.load(Ordering::SeqCst) {
+                pb.abandon();
+                return Err(DedupError::Interrupted.into());
+            }
+            let _frame_span = info_span!("hash", frame = number).entered();
+
+            let hash = dhash(frame)?;
+            let is_duplicate =
+                last_kept.is_some_and(|kept_hash| distance(kept_hash, hash) < self.threshold);
+            if is_duplicate {
+                debug!("Dropping near-duplicate frame {:?}", frame);
+                if let Some(duplicates_directory) = &self.duplicates_directory {
+                    let file_name = frame
+                        .file_name()
+                        .with_context(|| format!("Frame {:?} has no filename", frame))?;
+                    let target = duplicates_directory.join(file_name);
+                    fs::copy(frame, &target)
+                        .with_context(|| format!("Failed to copy {:?} to {:?}", frame, target))?;
+                }
+            } else {
+                kept += 1;
+                last_kept = Some(hash);
+                let extension = frame
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "png".to_string());
+                let target =
+                    staged
+                        .path()
+                        .join(padding.file_name("frame", kept as u64, &extension));
+                fs::copy(frame, &target)
+                    .with_context(|| format!("Failed to copy {:?} to {:?}", frame, target))?;
+            }
+            pb.inc(1);
+        }
+        pb.finish_with_message("Done");
+        debug!("Kept {} of {} frames", kept, self.input_files.len());
+
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Dedup, kept)?;
+
+        Ok(kept)
+    }
+}
@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+use fxp_output::FailureKind;
+
+/// Failures of the Dedup that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum DedupError {
+    /// The input does not exist or is not what the Dedup takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Dedup interrupted by user")]
+    Interrupted,
+}
+
+impl DedupError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            DedupError::InvalidInput(_) => FailureKind::InvalidInput,
+            DedupError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
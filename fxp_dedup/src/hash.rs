@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use std::path::Path;
+
+/// Computes the 64-bit difference hash (dHash) of an image.
+///
+/// # Parameters
+/// - `path`: The image to hash.
+///
+/// # Returns
+/// - `Result<u64>`: The hash, or an error if the image cannot be decoded.
+///
+/// # Notes
+/// - The image is reduced to 9x8 grayscale pixels; each bit records whether a pixel is
+///   brighter than its right neighbour. Frames that look alike get hashes a few bits apart,
+///   regardless of small changes in noise, compression or exposure.
+pub(crate) fn dhash(path: &Path) -> Result<u64> {
+    let image =
+        image::open(path).with_context(|| format!("Failed to open image {}", path.display()))?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    Ok(hash)
+}
+
+/// Returns the number of bits that differ between two hashes.
+pub(crate) fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
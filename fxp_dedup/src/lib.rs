@@ -0,0 +1,6 @@
+mod dedup;
+mod error;
+mod hash;
+
+pub use dedup::Dedup;
+pub use error::DedupError;
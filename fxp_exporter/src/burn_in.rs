@@ -0,0 +1,93 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::process::Command as StdCommand;
+use std::str::FromStr;
+
+use fxp_output::ToolError;
+
+use crate::error::ExporterError;
+
+/// What the Exporter draws into the bottom-left corner of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnIn {
+    /// The frame number, as in the file name: 1 for `frame_0001.png`.
+    FrameNumber,
+    /// The time of the frame in the source video, as `HH:MM:SS.mmm`.
+    Timecode,
+}
+
+impl BurnIn {
+    /// Returns the text drawn into the frame.
+    ///
+    /// # Parameters
+    /// - `index`: Zero-based position of the frame in the export.
+    /// - `timestamp_ms`: Time of the frame in the source video, in milliseconds.
+    pub fn text(&self, index: u64, timestamp_ms: u64) -> String {
+        match self {
+            BurnIn::FrameNumber => (index + 1).to_string(),
+            BurnIn::Timecode => format!(
+                "{:02}:{:02}:{:02}.{:03}",
+                timestamp_ms / 3_600_000,
+                timestamp_ms / 60_000 % 60,
+                timestamp_ms / 1000 % 60,
+                timestamp_ms % 1000
+            ),
+        }
+    }
+
+    /// Returns the ffmpeg `drawtext` filter drawing `text`, white on a translucent box.
+    ///
+    /// # Notes
+    /// - Colons are escaped, as they would otherwise end the `text` option.
+    pub fn drawtext_filter(text: &str) -> String {
+        format!(
+            "drawtext=text='{}':x=10:y=h-th-10:fontsize=max(16\\,h/20):fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=6",
+            text.replace(':', "\\:")
+        )
+    }
+}
+
+impl FromStr for BurnIn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "framenumber" => Ok(BurnIn::FrameNumber),
+            "timecode" => Ok(BurnIn::Timecode),
+            other => Err(format!(
+                "Unknown burn-in '{}', expected framenumber or timecode",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BurnIn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BurnIn::FrameNumber => "framenumber",
+            BurnIn::Timecode => "timecode",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Checks that ffmpeg has the `drawtext` filter, which needs it built with libfreetype.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg cannot be run or lacks the filter.
+pub fn check_drawtext() -> Result<()> {
+    let output = StdCommand::new("ffmpeg")
+        .args(["-hide_banner", "-filters"])
+        .output()
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to list its filters")?;
+    let filters = String::from_utf8_lossy(&output.stdout);
+    if !filters
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some("drawtext"))
+    {
+        bail!("--burn-in needs the drawtext filter, which this ffmpeg is built without (it needs libfreetype)");
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Exporter that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum ExporterError {
+    /// A tool the Exporter runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Exporter runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The exported frames would not fit on the disk.
+    #[error(
+        "{0}; free up space, lower --pixel-limit or --duration, or pass --force to export anyway"
+    )]
+    InsufficientSpace(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Export interrupted by user")]
+    Interrupted,
+}
+
+impl ExporterError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ExporterError::ToolMissing(_) => FailureKind::ToolMissing,
+            ExporterError::ToolFailed { .. } => FailureKind::ToolFailed,
+            ExporterError::InsufficientSpace(_) => FailureKind::InsufficientSpace,
+            ExporterError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+
+    /// Returns whether an error would recur on every attempt, so it is not retried, see
+    /// `fxp_output::RetryPolicy`.
+    pub(crate) fn is_permanent(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<ExporterError>(),
+            Some(ExporterError::ToolMissing(_) | ExporterError::Interrupted)
+        )
+    }
+}
+
+impl ToolError for ExporterError {
+    fn missing(tool: &str) -> Self {
+        ExporterError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        ExporterError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
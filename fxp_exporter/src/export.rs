@@ -1,11 +1,18 @@
-use anyhow::{anyhow, bail, Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressStyle;
 use log::debug;
+use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::Command as StdCommand;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tracing::info_span;
+
+use fxp_output::{progress_bar, FrameRate, RetryPolicy, ToolError};
+
+use crate::error::ExporterError;
 
 /// Extracts all frames from a video file with progress indication.
 ///
@@ -14,28 +21,35 @@ use std::sync::Arc;
 ///
 /// # Parameters
 /// - `video`: Input video file path.
-/// - `output_dir`: Directory to save the extracted frames.
-/// - `duration`: Video duration in seconds.
-/// - `fps`: Frames per second to determine the number of frames.
+/// - `frame_path`: Returns the path to write the frame of a zero-based index to.
+/// - `frames`: Zero-based indices the video's frames are written as, in order; the video
+///   has one frame per index.
 /// - `running`: Flag to control the extraction process continuation.
+/// - `on_frame`: Called with the zero-based index and path of each frame once it is written.
+/// - `filters`: Returns the ffmpeg filters applied to the frame of a zero-based index
+///   once it is selected, such as burned-in text; none for most exports.
+/// - `retry`: How often a failed frame is extracted again before the export fails.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates if the extraction completed successfully or encountered an error.
 ///
 /// # Notes
-/// - The extracted frames are named in the format `frame_0001.png`, `frame_0002.png`, etc.
+/// - The frames are numbered from `frames.start`, so the frames of several videos
+///   continue one another; `frame_path` names them, e.g. `frame_0001.png`.
 /// - If the process is interrupted, returns an error message.
+/// - The filters follow the `select` filter picking the frame out of the video.
 pub fn extract_all_frames_with_progress(
-    video: &str,
-    output_dir: PathBuf,
-    duration: f64,
-    fps: u32,
+    video: &Path,
+    frame_path: impl Fn(u64) -> PathBuf,
+    frames: Range<u64>,
     running: Arc<AtomicBool>,
+    mut on_frame: impl FnMut(u64, PathBuf),
+    filters: impl Fn(u64) -> Vec<String>,
+    retry: &RetryPolicy,
 ) -> Result<()> {
-    let total_frames = (duration * fps as f64) as u64;
-    debug!("Total frames to extract: {}", total_frames);
+    debug!("Frames to extract: {:?}", frames);
 
-    let pb = ProgressBar::new(total_frames);
+    let pb = progress_bar(frames.end - frames.start);
     let style = ProgressStyle::default_bar()
         .template(
             "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
@@ -43,39 +57,31 @@ pub fn extract_all_frames_with_progress(
         .context("Failed to set progress bar template")?;
     pb.set_style(style);
 
-    for i in 0..total_frames {
+    for i in frames.clone() {
         if !running.load(Ordering::SeqCst) {
             pb.finish_with_message("");
             debug!("Frame extraction interrupted by user.");
-            return Err(anyhow!("Frame extraction interrupted by user."));
+            return Err(ExporterError::Interrupted.into());
+        }
+
+        let output_file = frame_path(i);
+        let _span = info_span!("export", frame_index = i, path = %output_file.display()).entered();
+
+        let mut filter = format!("select=eq(n\\,{})", i - frames.start);
+        for extra in filters(i) {
+            filter = format!("{},{}", filter, extra);
         }
 
-        let output_file = output_dir.join(format!("frame_{:04}.png", i + 1));
-
-        StdCommand::new("ffmpeg")
-            .args(&[
-                "-y",
-                "-i",
-                video,
-                "-vf",
-                &format!("select=eq(n\\,{})", i),
-                "-fps_mode",
-                "vfr",
-                output_file
-                    .to_str()
-                    .expect("Output file path contains invalid UTF-8"),
-            ])
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .output()
-            .with_context(|| {
-                format!(
-                    "Failed to execute ffmpeg for frame extraction at frame {}",
-                    i + 1
-                )
-            })?;
+        retry
+            .run(
+                &format!("Extracting frame {}", i + 1),
+                || extract_frame(video, &filter, &output_file),
+                ExporterError::is_permanent,
+            )
+            .with_context(|| format!("Failed to extract frame {}", i + 1))?;
 
         pb.inc(1);
+        on_frame(i, output_file);
     }
 
     pb.finish();
@@ -83,6 +89,28 @@ pub fn extract_all_frames_with_progress(
     Ok(())
 }
 
+/// Writes the single frame `filter` selects from `video` to `output_file` with ffmpeg.
+fn extract_frame(video: &Path, filter: &str, output_file: &Path) -> Result<()> {
+    let output = StdCommand::new("ffmpeg")
+        .args(["-y", "-i"])
+        .arg(video)
+        .args(["-vf", filter, "-fps_mode", "vfr"])
+        .arg(output_file)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg for frame extraction")?;
+    if !output.status.success() {
+        return Err(ExporterError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: output.status.to_string(),
+        })
+        .context("ffmpeg failed to extract the frame");
+    }
+    Ok(())
+}
+
 /// Processes a video by cutting it to a specified duration, adjusting FPS,
 /// and resizing based on a pixel limit.
 ///
@@ -95,6 +123,7 @@ pub fn extract_all_frames_with_progress(
 ///
 /// # Parameters
 /// - `video_path`: Path to the input video file
+/// - `start`: Milliseconds of the input to skip before the cut starts
 /// - `duration`: Desired duration of the output video in milliseconds
 /// - `pixel_upper_limit`: Maximum allowed pixels for resizing
 /// - `fps`: Target frames per second for the output video
@@ -102,7 +131,7 @@ pub fn extract_all_frames_with_progress(
 /// - `running`: Flag to check if processing should continue
 ///
 /// # Returns
-/// - `Result<(String, f64)>`: Tuple containing:
+/// - `Result<(PathBuf, f64)>`: Tuple containing:
 ///   - Path to the processed video file
 ///   - Duration of the output video in seconds
 ///
@@ -113,14 +142,16 @@ pub fn extract_all_frames_with_progress(
 /// - Returns an error if video cutting or resizing fails
 /// - If the requested duration is longer than the source video, it returns the original video
 pub fn cut_duration_adjust_fps_resize(
-    video_path: &str,
+    video_path: &Path,
+    start: u64,
     duration: u64,
     pixel_upper_limit: u32,
-    fps: u32,
+    fps: FrameRate,
     tmp_dir_path: PathBuf,
     running: Arc<AtomicBool>,
-) -> Result<(String, f64)> {
-    debug!("Processing video cut for: {}", video_path);
+) -> Result<(PathBuf, f64)> {
+    debug!("Processing video cut for: {}", video_path.display());
+    debug!("Requested start (milliseconds): {} ms", start);
     debug!("Requested duration (milliseconds): {} ms", duration);
 
     // Convert duration from milliseconds to seconds.
@@ -137,6 +168,7 @@ pub fn cut_duration_adjust_fps_resize(
     debug!("Attempting to cut the video...");
     let cut_video_path = cut_video(
         video_path,
+        start as f64 / 1000.0,
         cut_duration,
         pixel_upper_limit,
         fps,
@@ -159,6 +191,7 @@ pub fn cut_duration_adjust_fps_resize(
 ///
 /// # Parameters
 /// - `video_path`: Path to the input video file
+/// - `start`: Seconds of the input to skip before the cut starts
 /// - `duration`: Desired duration of the output video
 /// - `pixel_upper_limit`: Maximum allowed pixel size for resizing
 /// - `fps`: Frames per second for the output video
@@ -166,102 +199,158 @@ pub fn cut_duration_adjust_fps_resize(
 /// - `running`: Atomic boolean to track if process should continue
 ///
 /// # Returns
-/// - `Result<String>`: Path to the processed video file or error
+/// - `Result<PathBuf>`: Path to the processed video file or error
+///
+/// # Notes
+/// - The processed video is named after the input's file stem inside `tmp_dir_path`.
 fn cut_video(
-    video_path: &str,
+    video_path: &Path,
+    start: f64,
     duration: f64,
     pixel_upper_limit: u32,
-    fps: u32, // new fps parameter added here
+    fps: FrameRate,
     tmp_dir_path: PathBuf,
     running: Arc<AtomicBool>,
-) -> Result<String> {
+) -> Result<PathBuf> {
     // Create the temporary directory if it doesn't exist.
     fs::create_dir_all(&tmp_dir_path).context("Failed to create temporary directory")?;
 
     let temp_cut_path = tmp_dir_path.join("video_cut.mp4");
     let temp_resized_path = tmp_dir_path.join("video_resized.mp4");
 
-    let output_path_buf = tmp_dir_path.join(format!("{}.mp4", video_path.trim_end_matches(".mp4")));
-
-    // Convert the output path (PathBuf) to a String.
-    let output_path = output_path_buf
-        .to_str()
-        .expect("Output path contains invalid UTF-8")
-        .to_string();
+    // Only the file stem is kept: joining a full input path would replace the temporary
+    // directory and write next to, or over, the input.
+    let mut file_name = video_path
+        .file_stem()
+        .unwrap_or_else(|| OsStr::new("video"))
+        .to_os_string();
+    file_name.push(".mp4");
+    let output_path = tmp_dir_path.join(file_name);
 
-    debug!("Starting video processing for: {}", video_path);
+    debug!("Starting video processing for: {}", video_path.display());
     debug!("Temporary cut path: {:?}", temp_cut_path);
     debug!("Temporary resized path: {:?}", temp_resized_path);
-    debug!("Output path: {}", output_path);
+    debug!("Output path: {:?}", output_path);
 
     // Step 1: Cut the video to the desired duration.
-    cut_video_to_duration(
-        video_path,
-        temp_cut_path
-            .to_str()
-            .expect("Temporary cut path contains invalid UTF-8"),
-        duration,
-        running.clone(),
-    )?;
+    cut_video_to_duration(video_path, &temp_cut_path, start, duration, running.clone())?;
 
     // Check if the process is still running.
     if !running.load(Ordering::SeqCst) {
-        bail!("Process interrupted by user");
+        return Err(ExporterError::Interrupted.into());
     }
 
     // Step 2: Resize the video.
     resize_video(
-        temp_cut_path
-            .to_str()
-            .expect("Temporary cut path contains invalid UTF-8"),
-        temp_resized_path
-            .to_str()
-            .expect("Temporary resized path contains invalid UTF-8"),
+        &temp_cut_path,
+        &temp_resized_path,
         pixel_upper_limit,
         running.clone(),
     )?;
 
     // Step 3: Adjust the framerate using the provided fps value.
-    adjust_framerate(
-        temp_resized_path
-            .to_str()
-            .expect("Temporary resized path contains invalid UTF-8"),
-        &output_path,
-        fps,
-        running.clone(),
-    )?;
+    adjust_framerate(&temp_resized_path, &output_path, fps, running.clone())?;
 
     debug!("Video processing completed successfully");
     Ok(output_path)
 }
 
+/// Fetches the duration of a video file in milliseconds using ffprobe.
+///
+/// # Parameters
+/// - `input_path`: Path to the video file.
+///
+/// # Returns
+/// - `Result<u64>`: The duration in milliseconds, or an error if ffprobe fails or reports
+///   no duration.
+pub fn get_video_duration(input_path: &Path) -> Result<u64> {
+    debug!("Fetching video duration for input: {:?}", input_path);
+    let output = StdCommand::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(input_path)
+        .output()
+        .map_err(|e| ExporterError::spawn("ffprobe", e))
+        .context("Failed to execute ffprobe to get video duration")?;
+
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(ExporterError::ToolFailed {
+            tool: "ffprobe".to_string(),
+            reason,
+        })
+        .context("Failed to get video duration");
+    }
+
+    let seconds: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse the duration of {}", input_path.display()))?;
+    Ok((seconds * 1000.0).round() as u64)
+}
+
+/// Fetches the frame rate of a video file using ffprobe.
+///
+/// # Parameters
+/// - `input_path`: Path to the video file.
+///
+/// # Returns
+/// - `Option<FrameRate>`: The rate of the first video stream, or `None` if ffprobe fails
+///   or reports none, such as `0/0` for a still image.
+pub fn get_video_frame_rate(input_path: &Path) -> Option<FrameRate> {
+    let output = StdCommand::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=r_frame_rate",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(input_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let rate = String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+    debug!("Frame rate of {:?}: {:?}", input_path, rate);
+    rate
+}
+
 /// Fetches the dimensions (width and height) of a video file using ffprobe.
 ///
 /// This function executes an ffprobe command to extract video stream information
 /// and parse the dimensions from the output.
 ///
 /// # Parameters
-/// - `input_path`: Path to the video file as a string.
+/// - `input_path`: Path to the video file.
 /// - `running`: A flag to check if the process should continue running.
 ///
 /// # Returns
 /// - `Result<(u32, u32)>`: A tuple containing the video width and height in pixels.
-///                            Returns an error if dimensions cannot be parsed.
+///   Returns an error if dimensions cannot be parsed.
 ///
 /// # Notes
 /// - The function will bail if the process has been interrupted by the user.
 /// - Relies on ffprobe being available in the system PATH.
-fn get_video_dimensions(input_path: &str, running: Arc<AtomicBool>) -> Result<(u32, u32)> {
+fn get_video_dimensions(input_path: &Path, running: Arc<AtomicBool>) -> Result<(u32, u32)> {
     if !running.load(Ordering::SeqCst) {
-        bail!("Process interrupted by user");
+        return Err(ExporterError::Interrupted.into());
     }
 
-    debug!("Fetching video dimensions for input: {}", input_path);
+    debug!("Fetching video dimensions for input: {:?}", input_path);
 
     // Execute ffprobe to get video dimensions
     debug!("Executing ffprobe command to retrieve video dimensions...");
     let output = StdCommand::new("ffprobe")
-        .args(&[
+        .args([
             "-v",
             "error",
             "-select_streams",
@@ -270,15 +359,20 @@ fn get_video_dimensions(input_path: &str, running: Arc<AtomicBool>) -> Result<(u
             "stream=width,height",
             "-of",
             "csv=s=x:p=0",
-            input_path,
         ])
+        .arg(input_path)
         .output()
+        .map_err(|e| ExporterError::spawn("ffprobe", e))
         .context("Failed to execute ffprobe to get video dimensions")?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        debug!("FFprobe command failed with error: {}", stderr);
-        bail!("Failed to get video dimensions: {}", stderr);
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        debug!("FFprobe command failed with error: {}", reason);
+        return Err(ExporterError::ToolFailed {
+            tool: "ffprobe".to_string(),
+            reason,
+        })
+        .context("Failed to get video dimensions");
     }
 
     // Parse the output to extract dimensions
@@ -321,16 +415,22 @@ fn get_video_dimensions(input_path: &str, running: Arc<AtomicBool>) -> Result<(u
 /// - The =pixel_upper_limit= specifies the maximum number of pixels allowed in the resized video (width × height).
 /// - If =running= is set to =false=, the process will be interrupted.
 fn resize_video(
-    input_path: &str,
-    output_path: &str,
+    input_path: &Path,
+    output_path: &Path,
     pixel_upper_limit: u32,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
     if !running.load(Ordering::SeqCst) {
-        bail!("Process interrupted by user");
+        return Err(ExporterError::Interrupted.into());
     }
 
-    debug!("Starting video resizing process for input: {}", input_path);
+    let _span =
+        info_span!("resize", input = %input_path.display(), pixel_upper_limit = %pixel_upper_limit)
+            .entered();
+    debug!(
+        "Starting video resizing process for input: {}",
+        input_path.display()
+    );
 
     let (width, height) = get_video_dimensions(input_path, running.clone())?;
     debug!("Original video dimensions: {}x{}", width, height);
@@ -344,20 +444,27 @@ fn resize_video(
 
     debug!("Executing ffmpeg command to resize video...");
     let output = StdCommand::new("ffmpeg")
-        .args(&["-y", "-i", input_path, "-vf", &vf_arg, output_path])
+        .args(["-y", "-i"])
+        .arg(input_path)
+        .args(["-vf", &vf_arg])
+        .arg(output_path)
         .stderr(std::process::Stdio::null())
         .output()
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
         .context("Failed to execute ffmpeg for resizing video")?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        debug!("FFmpeg command failed with error: {}", stderr);
-        bail!("Failed to resize video: {}", stderr);
+        debug!("FFmpeg command failed with status: {}", output.status);
+        return Err(ExporterError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: output.status.to_string(),
+        })
+        .context("Failed to resize video");
     }
 
     debug!(
         "Video resizing completed successfully. Output saved to: {}",
-        output_path
+        output_path.display()
     );
     Ok(())
 }
@@ -369,6 +476,7 @@ fn resize_video(
 /// # Parameters
 /// - `input_path`: Path to the input video file
 /// - `output_path`: Path where the trimmed video will be saved
+/// - `start`: Seconds of the input to skip before the trimmed part
 /// - `duration`: Desired duration of the output video in seconds
 /// - `running`: Flag to check if the process should continue running
 ///
@@ -379,40 +487,58 @@ fn resize_video(
 /// - The function will stop execution if `running` flag becomes false
 /// - Requires FFmpeg to be installed and available in system PATH
 /// - Any existing file at `output_path` will be overwritten
+/// - The streams are copied when cutting from the start; a later start re-encodes them,
+///   since a copy can only start at a keyframe
 fn cut_video_to_duration(
-    input_path: &str,
-    output_path: &str,
+    input_path: &Path,
+    output_path: &Path,
+    start: f64,
     duration: f64,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
     let new_duration = duration + 1.0;
+    let _span =
+        info_span!("cut", input = %input_path.display(), start = %start, seconds = %new_duration)
+            .entered();
     debug!("Cutting video to {} seconds", new_duration);
 
     // Check if the process is still running
     if !running.load(Ordering::SeqCst) {
-        bail!("Process interrupted by user");
+        return Err(ExporterError::Interrupted.into());
     }
 
-    StdCommand::new("ffmpeg")
-        .args(&[
-            "-y", // Automatically overwrite existing files
-            "-i",
-            input_path,
-            "-t",
-            &new_duration.to_string(),
-            "-c",
-            "copy",
-            output_path,
-        ])
+    let seek = start > 0.0;
+    let start = start.to_string();
+    let new_duration = new_duration.to_string();
+    let mut command = StdCommand::new("ffmpeg");
+    command.arg("-y"); // Automatically overwrite existing files
+    if seek {
+        command.args(["-ss", &start]);
+    }
+    command
+        .arg("-i")
+        .arg(input_path)
+        .args(["-t", &new_duration]);
+    if !seek {
+        command.args(["-c", "copy"]);
+    }
+    command.arg(output_path);
+
+    let status = command
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .status()
-        .context("Failed to execute ffmpeg for cutting video")?
-        .success()
-        .then_some(())
-        .context("Failed to cut video")?;
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg for cutting video")?;
+    if !status.success() {
+        return Err(ExporterError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: status.to_string(),
+        })
+        .context("Failed to cut video");
+    }
 
-    debug!("Temporary cut video created at {}", output_path);
+    debug!("Temporary cut video created at {}", output_path.display());
     Ok(())
 }
 
@@ -434,47 +560,54 @@ fn cut_video_to_duration(
 /// - The audio stream is copied without re-encoding.
 /// - If the `running` flag is set to false, the process will be interrupted.
 fn adjust_framerate(
-    input_path: &str,
-    output_path: &str,
-    framerate: u32,
+    input_path: &Path,
+    output_path: &Path,
+    framerate: FrameRate,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
+    let _span = info_span!("fps", input = %input_path.display(), fps = %framerate).entered();
     debug!(
         "Adjusting framerate of video at {} to {}fps, saving to {}",
-        input_path, framerate, output_path
+        input_path.display(),
+        framerate,
+        output_path.display()
     );
 
     // Check if the process is still running
     if !running.load(Ordering::SeqCst) {
         debug!("Process interrupted by user. Exiting framerate adjustment.");
-        bail!("Process interrupted by user");
+        return Err(ExporterError::Interrupted.into());
     }
 
     debug!("Executing ffmpeg command to adjust framerate...");
     let status = StdCommand::new("ffmpeg")
-        .args(&[
-            "-y", // Automatically overwrite existing files
-            "-i",
-            input_path,
+        .args(["-y", "-i"]) // Automatically overwrite existing files
+        .arg(input_path)
+        .args([
             "-filter:v",
-            &format!("fps=fps={}", framerate),
+            &format!("fps=fps={}", framerate.ffmpeg_arg()),
             "-c:a",
             "copy", // Copy audio without re-encoding
-            output_path,
         ])
+        .arg(output_path)
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .status()
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
         .context("Failed to execute ffmpeg for changing framerate")?;
 
     if !status.success() {
         debug!("FFmpeg command failed to adjust framerate.");
-        bail!("Failed to change framerate");
+        return Err(ExporterError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: status.to_string(),
+        })
+        .context("Failed to change framerate");
     }
 
     debug!(
         "Framerate adjustment completed successfully: {}",
-        output_path
+        output_path.display()
     );
     Ok(())
 }
@@ -489,7 +622,7 @@ fn adjust_framerate(
 /// # Returns
 /// - `u32`: The even pixel limit.
 fn ensure_even(pixel_limit: u32) -> u32 {
-    if pixel_limit % 2 != 0 {
+    if !pixel_limit.is_multiple_of(2) {
         pixel_limit + 1 // Round up to the nearest even number
     } else {
         pixel_limit
@@ -512,7 +645,7 @@ fn ensure_even(pixel_limit: u32) -> u32 {
 /// # Notes
 /// - Maintains the original aspect ratio while scaling
 /// - Ensures both dimensions are even numbers
-fn calculate_aspect_ratio_dimensions(
+pub(crate) fn calculate_aspect_ratio_dimensions(
     width: u32,
     height: u32,
     pixel_upper_limit: u32,
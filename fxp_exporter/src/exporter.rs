@@ -1,27 +1,70 @@
 use anyhow::{Context, Result};
-use ctrlc;
 use log::debug;
 use std::fs;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
-use tempfile;
+use std::sync::{atomic::AtomicBool, Arc};
+use tracing::info_span;
 
+use fxp_filenames::FramePadding;
 use fxp_modes::Modes;
+use fxp_output::keep_temp_files;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::FrameRate;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::RetryPolicy;
+use fxp_output::StagedDirectory;
+use fxp_output::{FrameTime, FrameTimes};
+
+use crate::burn_in::{check_drawtext, BurnIn};
+use crate::export::{
+    cut_duration_adjust_fps_resize, extract_all_frames_with_progress, get_video_duration,
+    get_video_frame_rate,
+};
+use crate::frames::{Frame, Frames};
+#[cfg(feature = "native-decoding")]
+use crate::native;
+use crate::space::{available_space, check_disk_space, estimate_frames_size, format_bytes};
 
-use crate::export::{cut_duration_adjust_fps_resize, extract_all_frames_with_progress};
+/// Optional settings of the Exporter, all of which have sensible defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Export even if the frames are projected to exceed the free disk space.
+    pub force: bool,
+    /// Write the frames straight into the output directory instead of staging them.
+    pub in_place: bool,
+    /// Milliseconds of the video to skip before the exported part starts.
+    pub start_ms: u64,
+    /// Copy the intermediate cut and resized videos here before they are removed.
+    pub keep_temp: Option<PathBuf>,
+    /// Draw the frame number or the timecode into each frame.
+    pub burn_in: Option<BurnIn>,
+    /// Videos whose frames continue the sequence after the Exporter's video, in order.
+    pub more_videos: Vec<PathBuf>,
+    /// Where the music starts in the audio, in milliseconds; recorded so the Clipper
+    /// advances the audio by it and frame 1 plays on the musical start.
+    pub audio_onset_ms: Option<u64>,
+    /// How often the extraction of a frame is retried after a failure.
+    pub retry: RetryPolicy,
+    /// Write `frames.json`, mapping each frame to its time and index in the source video.
+    pub frames_json: bool,
+    /// Write 16-bit PNG frames, keeping the precision of 10-bit and HDR videos.
+    pub high_bit_depth: bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct Exporter {
     pub video_path: PathBuf,
     pub output_dir: PathBuf,
     pub duration: u64,
-    pub fps: u32,
+    pub fps: FrameRate,
     pub pixel_upper_limit: u32,
+    /// Optional settings; `new` starts from `ExportOptions::default()`.
+    pub options: ExportOptions,
 }
 
 impl Exporter {
@@ -37,6 +80,7 @@ impl Exporter {
     /// - `duration`: The duration of the video in seconds.
     /// - `fps`: The frames per second for processing.
     /// - `pixel_upper_limit`: The maximum allowed number of pixels.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: Returns the configured `Exporter` instance or an error.
@@ -50,8 +94,9 @@ impl Exporter {
         video_path: String,
         output: Option<String>,
         duration: u64,
-        fps: u32,
+        fps: FrameRate,
         pixel_upper_limit: u32,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
         let video_path = PathBuf::from(video_path);
 
@@ -62,7 +107,7 @@ impl Exporter {
         // Use the trait implementation for ExporterOutput to create the output directory.
         let output_directory = match output_enum {
             Output::Exporter(exporter_output) => {
-                exporter_output.create_output((video_path.clone(), output))?
+                exporter_output.create_output((video_path.clone(), output), collision)?
             }
             _ => unreachable!("Expected Exporter mode"),
         };
@@ -73,8 +118,122 @@ impl Exporter {
             duration,
             fps,
             pixel_upper_limit,
+            options: ExportOptions::default(),
         })
     }
+
+    /// Resolves what `new` and `export_images` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `video_path`: The file path to the input video.
+    /// - `output`: An optional path for the output directory.
+    /// - `duration`: The duration to export in milliseconds.
+    /// - `fps`: The frames per second for processing.
+    /// - `pixel_upper_limit`: The maximum allowed number of pixels.
+    /// - `options`: Optional settings the Exporter would run with.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the output cannot be resolved.
+    ///
+    /// # Notes
+    /// - The projected size of the frames needs a sample frame from ffmpeg, so the plan
+    ///   only reports the space available at the output directory.
+    pub fn plan(
+        video_path: String,
+        output: Option<String>,
+        duration: u64,
+        fps: FrameRate,
+        pixel_upper_limit: u32,
+        options: &ExportOptions,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let video_path = PathBuf::from(video_path);
+
+        let mode: Modes = Modes::Exporter;
+        let output_enum: Output = mode.into();
+        let output_directory = match output_enum {
+            Output::Exporter(exporter_output) => {
+                exporter_output.plan_output((video_path.clone(), output), collision)?
+            }
+            _ => unreachable!("Expected Exporter mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Exporter).entry("input video", video_path.display());
+        for video in &options.more_videos {
+            plan = plan.entry("then video", video.display());
+        }
+        if options.start_ms > 0 {
+            plan = plan.entry("start", format!("{} ms", options.start_ms));
+        }
+        if let Some(onset) = options.audio_onset_ms {
+            plan = plan.entry(
+                "audio onset",
+                format!("{} ms, the Clipper advances the audio by it", onset),
+            );
+        }
+        Ok(plan
+            .entry("duration", format!("{} ms", duration))
+            .entry("fps", fps)
+            .entry("pixel upper limit", pixel_upper_limit)
+            .entry("frames", fps.frames_in(duration))
+            .entry(
+                "burn-in",
+                options
+                    .burn_in
+                    .map_or("none".to_string(), |burn_in| burn_in.to_string()),
+            )
+            .entry(
+                "bit depth",
+                if options.high_bit_depth {
+                    "16 bits per channel"
+                } else {
+                    "8 bits per channel"
+                },
+            )
+            .entry(
+                "decoding",
+                if cfg!(feature = "native-decoding")
+                    && options.burn_in.is_none()
+                    && !options.high_bit_depth
+                {
+                    "ffmpeg libraries, in-process"
+                } else {
+                    "ffmpeg binary"
+                },
+            )
+            .entry(
+                "available space",
+                format_bytes(available_space(&output_directory)?),
+            )
+            .entry(
+                "disk space check",
+                if options.force {
+                    "warn only (--force)"
+                } else {
+                    "abort if the frames would not fit"
+                },
+            )
+            .entry("on existing output", collision)
+            .entry(
+                "frame times",
+                if options.frames_json {
+                    "written to frames.json"
+                } else {
+                    "not written"
+                },
+            )
+            .entry(
+                "intermediate files",
+                options
+                    .keep_temp
+                    .as_ref()
+                    .map_or("removed".to_string(), |dir| {
+                        format!("kept in {}", dir.display())
+                    }),
+            )
+            .entry("output directory", output_directory.display()))
+    }
 }
 
 impl Exporter {
@@ -94,90 +253,378 @@ impl Exporter {
     /// - Handles Ctrl+C interruptions gracefully.
     /// - Creates and manages a temporary directory for processing.
     /// - Provides progress tracking during frame extraction.
+    /// - Checks the projected size of the frames against the free disk space before
+    ///   extracting them, unless `options.force` is set.
+    /// - With `options.start_ms`, the export starts that far into the video; the frames
+    ///   are still numbered from `frame_0001`.
+    /// - Frame numbers are padded to four digits, or five and more for exports of over
+    ///   9999 frames, so that their names sort in order; see `FramePadding`.
+    /// - With `options.audio_onset_ms`, it is recorded in the manifest as `audio onset`;
+    ///   the duration is expected to be shortened by it already.
+    /// - With `options.more_videos`, their frames follow the video's, numbered on from
+    ///   it, until the duration is reached; `options.start_ms` only applies to the first.
+    /// - With `options.burn_in`, the frame number or the timecode in the source video is
+    ///   drawn into the bottom-left corner of each frame; ffmpeg must have `drawtext`.
+    /// - Frames are staged and moved into the output directory only once all of them are
+    ///   extracted, unless `options.in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - With `options.high_bit_depth`, the frames are written as 16-bit PNG, converted
+    ///   from the video without rounding to 8 bits first; HDR videos keep their transfer
+    ///   curve, so the Clipper's `--ten-bit` can encode them as HDR again.
+    /// - With the `native-decoding` feature, the videos are decoded in-process without
+    ///   temporary files, unless `options.burn_in` or `options.high_bit_depth` is set.
+    /// - A frame ffmpeg fails to extract is extracted again as `options.retry` allows.
+    /// - With `options.frames_json`, writes a `frames.json` next to the frames recording
+    ///   the source video, time and frame index of each; see `fxp_output::FrameTimes`.
+    /// - Writes a `manifest.json` recording the export parameters and the video hash.
     /// - Retains temporary files in debug mode for inspection.
     pub fn export_images(&self) -> Result<()> {
-        debug!("Starting export processing with arguments: {:?}", self);
+        // Cleared by Ctrl+C or SIGTERM.
+        let running = running_flag()?;
+
+        self.export(running, self.options.in_place, |_, _| {})
+    }
 
-        // Create the running variable and set up Ctrl+C handler.
-        let running = Arc::new(AtomicBool::new(true));
-        {
-            let r = running.clone();
-            ctrlc::set_handler(move || {
-                eprintln!("\nReceived Ctrl+C, terminating...");
-                r.store(false, Ordering::SeqCst);
+    /// Starts exporting the frames in the background and returns them as they are written.
+    ///
+    /// # Returns
+    /// - `Frames`: An iterator over the frames, yielding each one as soon as it is on disk,
+    ///   followed by the error that ended the export, if any.
+    ///
+    /// # Notes
+    /// - The frames are written straight into the output directory, as with
+    ///   `options.in_place`, so their paths stay valid once the export has finished.
+    /// - Unlike `export_images`, no Ctrl+C handler is installed; dropping the iterator
+    ///   stops the export after the frame being extracted and waits for it.
+    /// - The disk space check and the manifest are the same as for `export_images`.
+    pub fn frames(&self) -> Frames {
+        let exporter = self.clone();
+        let fps = self.fps;
+        Frames::spawn(move |running, sender| {
+            exporter.export(running, true, |index, path| {
+                let frame = Frame {
+                    index,
+                    path,
+                    timestamp_ms: fps.timestamp_ms(index),
+                };
+                // The receiver is gone only when the iterator was dropped, which stops us.
+                let _ = sender.send(Ok(frame));
             })
-            .context("Error setting Ctrl+C handler")?;
+        })
+    }
+
+    /// Returns the videos exported, in order: `video_path`, then `options.more_videos`.
+    pub fn videos(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.video_path).chain(&self.options.more_videos)
+    }
+
+    /// Cuts the videos, checks the disk space and extracts the frames into the output.
+    ///
+    /// # Parameters
+    /// - `running`: Cleared to interrupt the export.
+    /// - `in_place`: Write straight into the output directory instead of staging it.
+    /// - `on_frame`: Called with the index and path of each extracted frame.
+    ///
+    /// # Notes
+    /// - Each video is cut to what remains of the duration once the videos before it
+    ///   are exported, so the frames are numbered continuously across them; videos past
+    ///   the duration are left out.
+    fn export(
+        &self,
+        running: Arc<AtomicBool>,
+        in_place: bool,
+        mut on_frame: impl FnMut(u64, PathBuf),
+    ) -> Result<()> {
+        debug!("Starting export processing with arguments: {:?}", self);
+        let _span = info_span!(
+            "exporter",
+            video = %self.video_path.display(),
+            output = %self.output_dir.display(),
+            fps = %self.fps
+        )
+        .entered();
+        let mut manifest = Manifest::new(Modes::Exporter)
+            .parameter("video", self.video_path.display())
+            .parameter("duration", self.duration)
+            .parameter("fps", self.fps)
+            .parameter("pixel upper limit", self.pixel_upper_limit)
+            .inputs(self.videos());
+        if !self.options.more_videos.is_empty() {
+            let more_videos: Vec<String> = self
+                .options
+                .more_videos
+                .iter()
+                .map(|video| video.display().to_string())
+                .collect();
+            manifest = manifest.parameter_list("more videos", &more_videos);
+        }
+        if self.options.start_ms > 0 {
+            manifest = manifest.parameter("start", self.options.start_ms);
+        }
+        if let Some(onset) = self.options.audio_onset_ms {
+            manifest = manifest.parameter("audio onset", onset);
+        }
+        if let Some(burn_in) = self.options.burn_in {
+            manifest = manifest.parameter("burn-in", burn_in);
+            check_drawtext()?;
+        }
+        if self.options.frames_json {
+            manifest = manifest.parameter("frames json", true);
+        }
+        if self.options.high_bit_depth {
+            manifest = manifest.parameter("high bit depth", true);
+        }
+        // Burned-in text needs ffmpeg's drawtext filter, which only the binary runs, and
+        // the decoder writes 8-bit frames.
+        #[cfg(feature = "native-decoding")]
+        if self.options.burn_in.is_none() && !self.options.high_bit_depth {
+            return self.export_native(manifest, &running, in_place, on_frame);
         }
 
         // Create a temporary directory using the tempfile crate.
         let tmp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
         let tmp_dir_path = tmp_dir.path().to_path_buf();
 
-        let (cut_video_path, cut_duration) = cut_duration_adjust_fps_resize(
-            &self.video_path.to_str().unwrap(),
-            self.duration,
-            self.pixel_upper_limit,
-            self.fps,
-            tmp_dir_path.clone(),
-            running.clone(),
-        )
-        .context("An error occurred during video cutting")?;
-
-        extract_all_frames_with_progress(
-            &cut_video_path,
-            self.output_dir.clone(),
-            cut_duration,
-            self.fps,
-            running.clone(),
-        )
-        .context("An error occurred during frame extraction")?;
+        // Cut every video to its share of the duration, numbering its frames after the
+        // frames of the videos before it.
+        let mut sources: Vec<(PathBuf, Range<u64>, u64)> = Vec::new();
+        let mut origins: Vec<(&Path, Range<u64>, u64)> = Vec::new();
+        let mut remaining = self.duration;
+        let mut next_frame = 0;
+        let last = self.options.more_videos.len();
+        for (position, video) in self.videos().enumerate() {
+            if remaining == 0 {
+                debug!("Duration reached, leaving out {}", video.display());
+                break;
+            }
+            let start_ms = if position == 0 {
+                self.options.start_ms
+            } else {
+                0
+            };
+            // The last video takes whatever remains, as the duration was resolved against
+            // the videos' combined duration; the others contribute up to their end.
+            let share = if position == last {
+                remaining
+            } else {
+                let available = get_video_duration(video)?.saturating_sub(start_ms);
+                remaining.min(available)
+            };
+            if share == 0 {
+                debug!("Nothing to export from {}", video.display());
+                continue;
+            }
+            // Each video gets a directory of its own, as the cut files have fixed names.
+            let video_tmp_dir = if position == 0 {
+                tmp_dir_path.clone()
+            } else {
+                let dir = tmp_dir_path.join(format!("video_{}", position + 1));
+                fs::create_dir(&dir).context("Failed to create temporary directory")?;
+                dir
+            };
+            let (cut_video_path, cut_duration) = cut_duration_adjust_fps_resize(
+                video,
+                start_ms,
+                share,
+                self.pixel_upper_limit,
+                self.fps,
+                video_tmp_dir,
+                running.clone(),
+            )
+            .with_context(|| {
+                format!(
+                    "An error occurred during video cutting of {}",
+                    video.display()
+                )
+            })?;
+            let cut_ms = ((cut_duration * 1000.0).round() as u64).min(share);
+            let frames = next_frame..next_frame + self.fps.frames_in(cut_ms);
+            debug!("Frames {:?} come from {}", frames, video.display());
+            next_frame = frames.end;
+            remaining -= cut_ms;
+            origins.push((video, frames.clone(), start_ms));
+            sources.push((cut_video_path, frames, start_ms));
+        }
+
+        // Make sure the frames fit on the disk before extracting them.
+        let total_frames = next_frame;
+        let mut estimate = 0;
+        for (cut_video_path, frames, _) in &sources {
+            estimate += estimate_frames_size(
+                cut_video_path,
+                frames.end - frames.start,
+                self.options.high_bit_depth,
+                &tmp_dir_path,
+                running.clone(),
+            )
+            .context("An error occurred during the disk space estimate")?;
+        }
+        check_disk_space(&self.output_dir, estimate, self.options.force)?;
+
+        let mut staged = StagedDirectory::begin(&self.output_dir, in_place)?;
+        let padding = FramePadding::for_count(total_frames);
+        let frames_dir = staged.path().to_path_buf();
+        let frame_path = |index: u64| frames_dir.join(padding.file_name("frame", index + 1, "png"));
+
+        staged.keep_partial(&manifest);
+        for (cut_video_path, frames, start_ms) in sources {
+            let first_frame = frames.start;
+            extract_all_frames_with_progress(
+                &cut_video_path,
+                frame_path,
+                frames,
+                running.clone(),
+                &mut on_frame,
+                |index| {
+                    let mut filters = Vec::new();
+                    if let Some(burn_in) = self.options.burn_in {
+                        // The timecode is the time in the video the frame comes from.
+                        let timestamp_ms = start_ms + self.fps.timestamp_ms(index - first_frame);
+                        filters.push(BurnIn::drawtext_filter(&burn_in.text(index, timestamp_ms)));
+                    }
+                    if self.options.high_bit_depth {
+                        filters.push("format=rgb48be".to_string());
+                    }
+                    filters
+                },
+                &self.options.retry,
+            )
+            .context("An error occurred during frame extraction")?;
+        }
+        if self.options.frames_json {
+            self.frame_times(&origins, padding).write(staged.path())?;
+        }
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Exporter, total_frames as usize)?;
 
-        // In debug mode, copy the temporary directory contents to /tmp/fxp_videoclipper.
-        #[cfg(debug_assertions)]
-        {
-            let debug_dir = PathBuf::from("/tmp/fxp_videoclipper");
-            copy_tmp_dir_contents(tmp_dir.path(), &debug_dir)?;
+        if let Some(keep_dir) = &self.options.keep_temp {
+            keep_temp_files(tmp_dir.path(), keep_dir)?;
+            for position in 1..self.videos().count() {
+                let name = format!("video_{}", position + 1);
+                let video_tmp_dir = tmp_dir.path().join(&name);
+                if video_tmp_dir.is_dir() {
+                    keep_temp_files(&video_tmp_dir, &keep_dir.join(name))?;
+                }
+            }
         }
 
         Ok(())
     }
-}
 
-/// Copies contents from a temporary directory to a debug directory for debugging purposes.
-///
-/// This function transfers all files from a temporary directory to a specified debug directory.
-/// It creates the debug directory if it doesn't exist.
-///
-/// # Parameters
-/// - `tmp_dir`: The path to the temporary directory containing files to copy.
-/// - `debug_dir`: The path to the debug directory where files will be copied.
-///
-/// # Returns
-/// - `Result<()>`: Returns `Ok(())` on success or an error if any operation fails.
-///
-/// # Notes
-/// - This function is only included in debug builds.
-/// - Intended for use during debugging to preserve temporary files for inspection.
-#[cfg(debug_assertions)]
-fn copy_tmp_dir_contents(tmp_dir: &Path, debug_dir: &Path) -> Result<()> {
-    // Create the debug directory if it doesn't exist.
-    if !debug_dir.exists() {
-        fs::create_dir_all(debug_dir).context(format!(
-            "Failed to create debug directory: {}",
-            debug_dir.display()
-        ))?;
+    /// Exports the frames like `export`, decoding the videos in-process.
+    ///
+    /// # Parameters
+    /// - `manifest`: The manifest `export` prepared.
+    /// - `running`: Cleared to interrupt the export.
+    /// - `in_place`: Write straight into the output directory instead of staging it.
+    /// - `on_frame`: Called with the index and path of each extracted frame.
+    ///
+    /// # Notes
+    /// - The videos are not cut, resized or re-timed into temporary files first: the
+    ///   decoder seeks to the start, scales each frame and samples the video at `fps`.
+    #[cfg(feature = "native-decoding")]
+    fn export_native(
+        &self,
+        manifest: Manifest,
+        running: &AtomicBool,
+        in_place: bool,
+        mut on_frame: impl FnMut(u64, PathBuf),
+    ) -> Result<()> {
+        let mut sources = Vec::new();
+        let mut origins: Vec<(&Path, Range<u64>, u64)> = Vec::new();
+        let mut remaining = self.duration;
+        let mut next_frame = 0;
+        let mut estimate = 0;
+        for (position, video) in self.videos().enumerate() {
+            if remaining == 0 {
+                debug!("Duration reached, leaving out {}", video.display());
+                break;
+            }
+            let start_ms = if position == 0 {
+                self.options.start_ms
+            } else {
+                0
+            };
+            let mut decoder = native::open(video, self.pixel_upper_limit)
+                .with_context(|| format!("Failed to decode {}", video.display()))?;
+            let share = remaining.min(decoder.duration_ms().saturating_sub(start_ms));
+            if share == 0 {
+                debug!("Nothing to export from {}", video.display());
+                continue;
+            }
+            let frames = next_frame..next_frame + self.fps.frames_in(share);
+            debug!("Frames {:?} come from {}", frames, video.display());
+            estimate += native::estimate_frames_size(
+                &mut decoder,
+                start_ms,
+                share,
+                frames.end - frames.start,
+            )
+            .context("An error occurred during the disk space estimate")?;
+            next_frame = frames.end;
+            remaining -= share;
+            origins.push((video, frames.clone(), start_ms));
+            sources.push((decoder, frames, start_ms));
+        }
+        check_disk_space(&self.output_dir, estimate, self.options.force)?;
+
+        let total_frames = next_frame;
+        let mut staged = StagedDirectory::begin(&self.output_dir, in_place)?;
+        let padding = FramePadding::for_count(total_frames);
+        let frames_dir = staged.path().to_path_buf();
+        let frame_path = |index: u64| frames_dir.join(padding.file_name("frame", index + 1, "png"));
+        staged.keep_partial(&manifest);
+        for (mut decoder, frames, start_ms) in sources {
+            native::extract_frames(
+                &mut decoder,
+                start_ms,
+                frames,
+                self.fps,
+                frame_path,
+                running,
+                &mut on_frame,
+            )
+            .context("An error occurred during frame extraction")?;
+        }
+        if self.options.frames_json {
+            self.frame_times(&origins, padding).write(staged.path())?;
+        }
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Exporter, total_frames as usize)?;
+
+        Ok(())
     }
 
-    // Iterate and copy each file from tmp_dir to debug_dir.
-    for entry in fs::read_dir(tmp_dir).context("Failed to read temporary directory")? {
-        let entry = entry?;
-        let src_path = entry.path();
-        if let Some(file_name) = src_path.file_name() {
-            let dest_path = debug_dir.join(file_name);
-            fs::copy(&src_path, &dest_path)
-                .context(format!("Failed to copy {:?} to {:?}", src_path, dest_path))?;
+    /// Returns where each exported frame comes from, for `frames.json`.
+    ///
+    /// # Parameters
+    /// - `origins`: Each video exported, with the indices its frames are written as and
+    ///   how far into it the export starts, in milliseconds.
+    /// - `padding`: How the frames are numbered.
+    ///
+    /// # Notes
+    /// - The time of a frame is the same as its burned-in timecode; its index in the
+    ///   source is counted at the rate ffprobe reports for the video.
+    fn frame_times(
+        &self,
+        origins: &[(&Path, Range<u64>, u64)],
+        padding: FramePadding,
+    ) -> FrameTimes {
+        let mut frames = Vec::new();
+        for (video, indices, start_ms) in origins {
+            let source_rate = get_video_frame_rate(video);
+            for index in indices.clone() {
+                let source_ms = start_ms + self.fps.timestamp_ms(index - indices.start);
+                frames.push(FrameTime {
+                    file: padding.file_name("frame", index + 1, "png"),
+                    video: video.display().to_string(),
+                    source_ms,
+                    source_frame: source_rate.map(|rate| rate.frames_in(source_ms)),
+                });
+            }
+        }
+        FrameTimes {
+            fps: self.fps,
+            frames,
         }
     }
-    debug!("Copied temporary files to {}", debug_dir.display());
-    Ok(())
 }
@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// A frame written by the Exporter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Zero-based position of the frame in the export.
+    pub index: u64,
+    /// Where the frame was written, e.g. `frame_0001.png` for index 0.
+    pub path: PathBuf,
+    /// Time of the frame from the start of the export, in milliseconds.
+    pub timestamp_ms: u64,
+}
+
+/// The frames of a running export, in order, as returned by `Exporter::frames`.
+///
+/// Yields `Ok` for each frame as soon as it is on disk; if the export fails, the error
+/// is yielded last. Dropping the iterator interrupts the export and waits for it to stop.
+pub struct Frames {
+    receiver: Receiver<Result<Frame>>,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Frames {
+    /// Runs `export` on a background thread, handing it the running flag and the sender
+    /// its frames go to.
+    pub(crate) fn spawn<F>(export: F) -> Self
+    where
+        F: FnOnce(Arc<AtomicBool>, Sender<Result<Frame>>) -> Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let worker_running = running.clone();
+        let worker = thread::spawn(move || {
+            if let Err(e) = export(worker_running, sender.clone()) {
+                let _ = sender.send(Err(e));
+            }
+        });
+        Self {
+            receiver,
+            running,
+            worker: Some(worker),
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(item) => Some(item),
+            Err(_) => {
+                // The worker is done; report it if it panicked instead of returning.
+                let worker = self.worker.take()?;
+                worker
+                    .join()
+                    .err()
+                    .map(|_| Err(anyhow!("The export thread panicked")))
+            }
+        }
+    }
+}
+
+impl Drop for Frames {
+    /// Interrupts the export if it is still running, and waits for it to stop.
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
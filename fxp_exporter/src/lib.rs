@@ -1,4 +1,15 @@
+mod burn_in;
+mod error;
 mod export;
 mod exporter;
+mod frames;
+#[cfg(feature = "native-decoding")]
+mod native;
+mod playlist;
+mod space;
 
-pub use exporter::Exporter;
+pub use burn_in::BurnIn;
+pub use error::ExporterError;
+pub use exporter::{ExportOptions, Exporter};
+pub use frames::{Frame, Frames};
+pub use playlist::{is_playlist, read_playlist};
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Context, Result};
+use image::ImageFormat;
+use indicatif::ProgressStyle;
+use log::debug;
+use std::io::Cursor;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info_span;
+
+use fxp_decoder::VideoDecoder;
+use fxp_output::{progress_bar, FrameRate};
+
+use crate::error::ExporterError;
+
+use crate::export::calculate_aspect_ratio_dimensions;
+use crate::space::ESTIMATE_MARGIN;
+
+/// Opens a video for in-process decoding, scaled like `cut_duration_adjust_fps_resize`
+/// scales it.
+///
+/// # Parameters
+/// - `video`: The input video.
+/// - `pixel_upper_limit`: Size of the larger side of the frames.
+///
+/// # Returns
+/// - `Result<VideoDecoder>`: The decoder, or an error if the video cannot be decoded.
+pub fn open(video: &Path, pixel_upper_limit: u32) -> Result<VideoDecoder> {
+    let mut decoder = VideoDecoder::open(video)?;
+    let (width, height) = decoder.source_size();
+    let (width, height) = calculate_aspect_ratio_dimensions(width, height, pixel_upper_limit);
+    decoder.set_output_size(width, height);
+    Ok(decoder)
+}
+
+/// Projects the disk space the frames of a decoded video will take.
+///
+/// # Parameters
+/// - `decoder`: The video, opened with `open`.
+/// - `start_ms`: Where the exported part of the video starts.
+/// - `duration_ms`: Length of the exported part.
+/// - `total_frames`: The number of frames that will be extracted.
+///
+/// # Returns
+/// - `Result<u64>`: The projected size in bytes, including a safety margin.
+///
+/// # Notes
+/// - The frame in the middle of the exported part is encoded in memory, as with
+///   `estimate_frames_size`, so nothing is written to disk.
+pub fn estimate_frames_size(
+    decoder: &mut VideoDecoder,
+    start_ms: u64,
+    duration_ms: u64,
+    total_frames: u64,
+) -> Result<u64> {
+    if total_frames == 0 {
+        return Ok(0);
+    }
+    decoder.seek(start_ms + duration_ms / 2)?;
+    let sample = decoder
+        .next_frame()?
+        .ok_or_else(|| anyhow!("Failed to decode a sample frame for the disk space estimate"))?;
+    let mut encoded = Vec::new();
+    sample
+        .image
+        .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+        .context("Failed to encode a sample frame for the disk space estimate")?;
+
+    let estimate = (encoded.len() as f64 * total_frames as f64 * ESTIMATE_MARGIN) as u64;
+    debug!(
+        "Sample frame is {} bytes, projecting {} bytes for {} frames",
+        encoded.len(),
+        estimate,
+        total_frames
+    );
+    Ok(estimate)
+}
+
+/// Extracts frames by decoding the video in-process, without cutting it first.
+///
+/// # Parameters
+/// - `decoder`: The video, opened with `open`.
+/// - `start_ms`: Where the exported part of the video starts.
+/// - `frames`: Zero-based indices the frames are written as, one every frame of `fps`
+///   from `start_ms` on.
+/// - `fps`: Frame rate the video is sampled at.
+/// - `frame_path`: Returns the path to write the frame of a zero-based index to.
+/// - `running`: Cleared to interrupt the extraction.
+/// - `on_frame`: Called with the zero-based index and path of each frame once it is written.
+///
+/// # Returns
+/// - `Result<()>`: An error if decoding or writing a frame fails, or on interruption.
+///
+/// # Notes
+/// - The seek is exact to the frame, and frames are repeated or dropped to match `fps`.
+pub fn extract_frames(
+    decoder: &mut VideoDecoder,
+    start_ms: u64,
+    frames: Range<u64>,
+    fps: FrameRate,
+    frame_path: impl Fn(u64) -> PathBuf,
+    running: &AtomicBool,
+    mut on_frame: impl FnMut(u64, PathBuf),
+) -> Result<()> {
+    debug!("Frames to decode: {:?} from {} ms", frames, start_ms);
+    let pb = progress_bar(frames.end - frames.start);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
+            )
+            .context("Failed to set progress bar template")?,
+    );
+
+    decoder.seek(start_ms)?;
+    for i in frames.clone() {
+        if !running.load(Ordering::SeqCst) {
+            pb.finish_with_message("");
+            return Err(ExporterError::Interrupted.into());
+        }
+
+        let output_file = frame_path(i);
+        let _span = info_span!("decode", frame_index = i, path = %output_file.display()).entered();
+        let timestamp_ms = start_ms + fps.timestamp_ms(i - frames.start);
+        let image = decoder
+            .frame_at(timestamp_ms)?
+            .ok_or_else(|| anyhow!("The video has no frame at {} ms", timestamp_ms))?;
+        image
+            .save(&output_file)
+            .with_context(|| format!("Failed to write frame {}", output_file.display()))?;
+
+        pb.inc(1);
+        on_frame(i, output_file);
+    }
+
+    pb.finish();
+    debug!("Frame decoding completed!");
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files read as a list of videos rather than as a video.
+const PLAYLIST_EXTENSIONS: [&str; 3] = ["txt", "m3u", "m3u8"];
+
+/// Returns whether the Exporter reads `path` as a playlist.
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            PLAYLIST_EXTENSIONS
+                .iter()
+                .any(|playlist| extension.eq_ignore_ascii_case(playlist))
+        })
+}
+
+/// Reads the videos listed in a playlist, one per line.
+///
+/// # Parameters
+/// - `path`: A `.txt`, `.m3u` or `.m3u8` file.
+///
+/// # Returns
+/// - `Result<Vec<PathBuf>>`: The videos in the order listed, or an error if the file cannot
+///   be read or lists none.
+///
+/// # Notes
+/// - Blank lines and lines starting with `#`, such as `#EXTINF` entries, are skipped.
+/// - Relative paths are resolved against the directory of the playlist.
+pub fn read_playlist(path: &Path) -> Result<Vec<PathBuf>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read playlist {}", path.display()))?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let videos: Vec<PathBuf> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| directory.join(line))
+        .collect();
+    if videos.is_empty() {
+        bail!("Playlist {} lists no videos", path.display());
+    }
+    Ok(videos)
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use fxp_output::ToolError;
+
+use crate::error::ExporterError;
+
+/// Headroom added to the projected size, since frames vary in how well they compress.
+pub(crate) const ESTIMATE_MARGIN: f64 = 1.2;
+
+/// Projects the disk space the extracted frames will take.
+///
+/// Extracts a single frame from the middle of the prepared video and multiplies its
+/// size by the number of frames to extract.
+///
+/// # Parameters
+/// - `video`: The cut, resized and framerate-adjusted video the frames are extracted from.
+/// - `total_frames`: The number of frames that will be extracted.
+/// - `high_bit_depth`: Sample a 16-bit frame, as `ExportOptions::high_bit_depth` writes.
+/// - `tmp_dir`: Temporary directory to write the sample frame to.
+/// - `running`: Flag to check if the process should continue.
+///
+/// # Returns
+/// - `Result<u64>`: The projected size in bytes, including a safety margin.
+///
+/// # Notes
+/// - The middle frame is sampled because leading frames are often black and compress
+///   far better than the rest.
+pub fn estimate_frames_size(
+    video: &Path,
+    total_frames: u64,
+    high_bit_depth: bool,
+    tmp_dir: &Path,
+    running: Arc<AtomicBool>,
+) -> Result<u64> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(ExporterError::Interrupted.into());
+    }
+    if total_frames == 0 {
+        return Ok(0);
+    }
+
+    let sample_path = tmp_dir.join("sample_frame.png");
+    let sample_index = total_frames / 2;
+    debug!(
+        "Extracting sample frame {} of {} to {:?}",
+        sample_index, total_frames, sample_path
+    );
+
+    let mut filter = format!("select=eq(n\\,{})", sample_index);
+    if high_bit_depth {
+        filter.push_str(",format=rgb48be");
+    }
+    let status = StdCommand::new("ffmpeg")
+        .args(["-y", "-i"])
+        .arg(video)
+        .args(["-vf", &filter, "-fps_mode", "vfr", "-frames:v", "1"])
+        .arg(&sample_path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map_err(|e| ExporterError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to extract a sample frame")?;
+    if !status.success() {
+        return Err(ExporterError::ToolFailed {
+            tool: "ffmpeg".to_string(),
+            reason: status.to_string(),
+        })
+        .context("Failed to extract a sample frame for the disk space estimate");
+    }
+
+    let sample_size = fs::metadata(&sample_path)
+        .with_context(|| format!("Failed to read sample frame {:?}", sample_path))?
+        .len();
+    let estimate = (sample_size as f64 * total_frames as f64 * ESTIMATE_MARGIN) as u64;
+    debug!(
+        "Sample frame is {} bytes, projecting {} bytes for {} frames",
+        sample_size, estimate, total_frames
+    );
+
+    Ok(estimate)
+}
+
+/// Returns the space available on the filesystem holding `path`.
+///
+/// # Parameters
+/// - `path`: A file or directory; if it does not exist yet, its nearest existing
+///   ancestor is used.
+///
+/// # Returns
+/// - `Result<u64>`: The available space in bytes.
+pub fn available_space(path: &Path) -> Result<u64> {
+    let existing: PathBuf = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let available = fs2::available_space(&existing)
+        .with_context(|| format!("Failed to query free disk space at {:?}", existing))?;
+    debug!("Available space at {:?}: {} bytes", existing, available);
+    Ok(available)
+}
+
+/// Compares the projected output size with the space available at the output directory.
+///
+/// # Parameters
+/// - `output_dir`: Directory the frames will be written to.
+/// - `estimate`: Projected size of the frames in bytes.
+/// - `force`: Continue with a warning instead of failing when space runs short.
+///
+/// # Returns
+/// - `Result<()>`: An error if the frames would not fit and `force` is not set.
+pub fn check_disk_space(output_dir: &Path, estimate: u64, force: bool) -> Result<()> {
+    let available = available_space(output_dir)?;
+    if estimate <= available {
+        debug!(
+            "Disk space check passed: {} needed, {} available",
+            format_bytes(estimate),
+            format_bytes(available)
+        );
+        return Ok(());
+    }
+
+    let message = format!(
+        "The exported frames need about {} but only {} is available at {}",
+        format_bytes(estimate),
+        format_bytes(available),
+        output_dir.display()
+    );
+    if force {
+        eprintln!("Warning: {}; continuing because of --force", message);
+        Ok(())
+    } else {
+        Err(ExporterError::InsufficientSpace(message).into())
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
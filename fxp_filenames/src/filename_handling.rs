@@ -1,13 +1,12 @@
 use anyhow::Result;
 use log::debug;
-use regex::Regex;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use fxp_modes::Modes;
+use fxp_modes::{Capabilities, Modes};
 
-use crate::filename_parts::FilenameParts;
 use crate::filename_parts::ImageMappingError as OtherImageMappingError;
+use crate::numbering::{default_schemes, natural_cmp, NumberingScheme};
 
 pub trait FileOperations {
     fn load_files(
@@ -17,10 +16,11 @@ pub trait FileOperations {
 }
 
 impl FileOperations for Modes {
-    /// Loads and processes image files based on the specified mode.
+    /// Loads and maps image files by frame number based on the specified mode.
     ///
-    /// This function validates and processes a collection of image files. It ensures
-    /// all filenames follow a consistent structure and format.
+    /// This function tries each numbering scheme in turn and maps the images by the
+    /// numbers the first fitting scheme reads from their filenames. The files themselves
+    /// are never touched.
     ///
     /// # Parameters
     /// - `images`: A slice of `PathBuf` objects representing image files to process.
@@ -28,187 +28,141 @@ impl FileOperations for Modes {
     ///
     /// # Returns
     /// - `Result<BTreeMap<u32, PathBuf>, OtherImageMappingError>`:
-    ///   - `Ok(BTreeMap<u32, PathBuf>)`: Successfully loaded and mapped images.
+    ///   - `Ok(BTreeMap<u32, PathBuf>)`: Successfully mapped images, pointing at the original files.
     ///   - `Err(OtherImageMappingError)`: If an error occurs during processing.
     ///
     /// # Notes
-    /// - Supports modes: `Merger`, `Clutter`, `Clipper`, `Gmicer`.
-    /// - Uses the first image's prefix as a common prefix for all images.
-    /// - Validates filename structure and ensures consistent formatting.
-    /// - Returns an error if the mode is `Exporter` or `Sampler`.
+    /// - Supports the modes that read a directory of images, see
+    ///   `Capabilities::supports_directory_input`.
+    /// - A scheme fits when it reads a unique number from every filename; see `default_schemes`.
+    /// - If no scheme fits, the images are numbered from 1 in natural sort order.
+    /// - Hidden files (names starting with `.`) and JSON files, such as the run
+    ///   `manifest.json`, are skipped.
+    /// - Returns an error for the video modes, `Exporter` and `Sampler`.
     fn load_files(
         &self,
         images: &[PathBuf],
     ) -> Result<BTreeMap<u32, PathBuf>, OtherImageMappingError> {
-        match self {
-            Modes::Exporter | Modes::Sampler => {
-                debug!("Unsupported mode: {:?}. Cannot load files.", self);
-                Err(OtherImageMappingError::UnsupportedMode)
-            }
-            Modes::Merger | Modes::Clutter | Modes::Clipper | Modes::Gmicer => {
-                debug!("Loading files for mode: {:?}", self);
-
-                // Process the first image: create a FilenameParts and check its suffix.
-                debug!("Processing first image: {:?}", images[0]);
-                let first_parts = FilenameParts::new(&images[0])?;
-                debug!("First image parts: {:?}", first_parts);
-
-                // Use the first image's prefix as the common prefix for all subsequent images.
-                let common_prefix = first_parts.prefix.clone();
-                debug!("Common prefix extracted: {}", common_prefix);
-
-                // Create a new vector to store the updated PathBufs.
-                let mut new_image_paths: Vec<PathBuf> = Vec::with_capacity(images.len());
-                // Use the first image's (potentially modified) path.
-                new_image_paths.push(first_parts.path.clone());
-
-                // Process remaining images.
-                for image in &images[1..] {
-                    debug!("Processing image: {:?}", image);
-                    let mut parts = FilenameParts::new(image)?;
-                    debug!("Image parts: {:?}", parts);
-
-                    // Check the prefix against the common prefix.
-                    debug!("Checking prefix for image: {:?}", image);
-                    parts.check_prefix(&common_prefix)?;
-                    debug!("Prefix check completed for image: {:?}", image);
-
-                    // Check the suffix for each image.
-                    debug!("Checking suffix for image: {:?}", image);
-                    parts.check_suffix()?;
-                    debug!("Suffix check completed for image: {:?}", image);
-
-                    // If the file was modified, save it.
-                    if parts.is_modified() {
-                        debug!("Image was modified. Saving changes for: {:?}", image);
-                        parts.save_file()?;
-                        debug!("Changes saved for: {:?}", image);
-                    } else {
-                        debug!("No modifications needed for: {:?}", image);
-                    }
+        if !self.supports_directory_input() {
+            debug!("Unsupported mode: {:?}. Cannot load files.", self);
+            return Err(OtherImageMappingError::UnsupportedMode);
+        }
+        debug!("Loading files for mode: {:?}", self);
+
+        // Hidden files, such as the `.fxp_cache` manifest, and the JSON run
+        // manifest are never frames.
+        let images: Vec<PathBuf> = images
+            .iter()
+            .filter(|image| {
+                let hidden = image
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                let json = image
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+                !hidden && !json
+            })
+            .cloned()
+            .collect();
+        let images = images.as_slice();
+
+        if images.is_empty() {
+            debug!("No files to load.");
+            return Ok(BTreeMap::new());
+        }
 
-                    // Append the updated PathBuf from the parts.
-                    new_image_paths.push(parts.path.clone());
+        for scheme in default_schemes() {
+            debug!("Trying numbering scheme: {}", scheme.name());
+            match map_files_by_number(images, scheme.as_ref()) {
+                Ok(map) => {
+                    debug!(
+                        "Mapped {} files with the {} scheme.",
+                        map.len(),
+                        scheme.name()
+                    );
+                    return Ok(map);
                 }
-
-                // Map the new files by number.
-                debug!("Mapping files by number...");
-                let result = map_files_by_number(new_image_paths);
-                debug!("Files mapped successfully.");
-                result
+                Err(e) => debug!("Scheme {} does not fit: {}", scheme.name(), e),
             }
         }
+
+        debug!("No numbering scheme fits. Falling back to natural sort order.");
+        map_files_by_natural_order(images)
     }
 }
 
-/// Maps image files to a structured format based on extracted numbers.
-///
-/// This function processes image files to create a mapping of numeric identifiers
-/// to their corresponding file paths. It corrects filenames to a standard format and
-/// handles duplicates.
+/// Maps image files to the numbers a scheme reads from their filenames.
 ///
 /// # Parameters
-/// - `files`: A vector of `PathBuf` objects representing image file paths.
+/// - `files`: Image file paths.
+/// - `scheme`: The numbering scheme to apply to every filename.
 ///
 /// # Returns
 /// - `Result<BTreeMap<u32, PathBuf>, OtherImageMappingError>`: A sorted map of numeric IDs to
-///   corrected file paths, or an error if duplicates are found.
+///   original file paths, or an error if a file does not match or a number repeats.
 ///
 /// # Notes
-/// - Filenames are corrected to the format `frame_{number}.{extension}`.
-/// - If duplicate numeric identifiers are detected, an error is returned.
+/// - The scheme must match every file; partial matches are rejected so that a
+///   later scheme or the natural sort fallback can take over.
 fn map_files_by_number(
-    files: Vec<PathBuf>,
+    files: &[PathBuf],
+    scheme: &dyn NumberingScheme,
 ) -> Result<BTreeMap<u32, PathBuf>, OtherImageMappingError> {
-    debug!("Starting map_files_by_number function");
-
     let mut map: BTreeMap<u32, PathBuf> = BTreeMap::new();
-    debug!("Created an empty BTreeMap to store file mappings");
 
     for file in files {
-        debug!("Processing file: {:?}", file);
-
-        if let Some(filename) = file.file_stem().and_then(|f| f.to_str()) {
-            debug!("Found filename: {}", filename);
-
-            if let Some(number) = extract_correct_number(filename) {
-                debug!("Successfully extracted number from filename: {}", number);
-
-                let corrected_filename = format!(
-                    "frame_{:04}.{}",
-                    number,
-                    file.extension().and_then(|ext| ext.to_str()).unwrap_or("")
-                );
-                let corrected_path = file.with_file_name(corrected_filename);
-
-                // Strict duplicate check
-                if let Some(existing_file) = map.get(&number) {
-                    return Err(OtherImageMappingError::DuplicateIdentifier(
-                        number,
-                        existing_file.clone(), // original file path
-                        file.clone(),          // current original file path
-                    ));
-                }
-
-                debug!(
-                    "Mapped number {} to corrected file path: {:?}",
-                    number, corrected_path
-                );
-                map.insert(number, file.clone()); // store the original file path
-            } else {
-                debug!("Failed to extract number from filename: {}", filename);
-            }
-        } else {
-            debug!("Failed to convert file name to string for file: {:?}", file);
+        let number = scheme.frame_number(file).ok_or_else(|| {
+            OtherImageMappingError::InvalidFilename(
+                file.clone(),
+                format!("No frame number for the {} scheme", scheme.name()),
+            )
+        })?;
+
+        // Strict duplicate check
+        if let Some(existing_file) = map.get(&number) {
+            return Err(OtherImageMappingError::DuplicateIdentifier(
+                number,
+                existing_file.clone(),
+                file.clone(),
+            ));
         }
+
+        debug!("Mapped number {} to file path: {:?}", number, file);
+        map.insert(number, file.clone());
     }
 
-    debug!(
-        "Finished processing files. Total files mapped: {}",
-        map.len()
-    );
     Ok(map)
 }
 
-/// Extracts a number from a filename if it matches the expected pattern.
-///
-/// This function attempts to find and parse a number in the given filename.
+/// Numbers image files from 1 in natural sort order of their filenames.
 ///
 /// # Parameters
-/// - `filename`: The input filename string to extract the number from.
+/// - `files`: Image file paths.
 ///
 /// # Returns
-/// - `Option<u32>`: Contains the extracted number if successful, otherwise `None`.
-///
-/// # Notes
-/// - The function looks for digits preceded by an underscore (`_`).
-/// - Only the first occurrence of such a pattern is considered.
-fn extract_correct_number(filename: &str) -> Option<u32> {
-    debug!("Attempting to extract number from filename: {}", filename);
-
-    let re = Regex::new(r"_(\d+)").ok()?;
-    debug!("Regex compiled successfully.");
-
-    let number = re
-        .captures(filename)
-        .and_then(|caps| {
-            debug!("Captures found: {:?}", caps);
-            caps.get(1)
-        })
-        .and_then(|m| {
-            let matched_str = m.as_str();
-            debug!("Matched number string: {}", matched_str);
-            matched_str.parse::<u32>().ok()
-        });
-
-    match number {
-        Some(num) => {
-            debug!("Successfully extracted number: {}", num);
-            Some(num)
-        }
-        None => {
-            debug!("No number found in filename.");
-            None
-        }
+/// - `Result<BTreeMap<u32, PathBuf>, OtherImageMappingError>`: A sorted map of sequential
+///   numbers to original file paths, or an error if a filename is not valid UTF-8.
+fn map_files_by_natural_order(
+    files: &[PathBuf],
+) -> Result<BTreeMap<u32, PathBuf>, OtherImageMappingError> {
+    let mut named: Vec<(&str, &PathBuf)> = Vec::with_capacity(files.len());
+    for file in files {
+        let name = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                OtherImageMappingError::InvalidFilename(
+                    file.clone(),
+                    "Filename is not valid UTF-8".into(),
+                )
+            })?;
+        named.push((name, file));
     }
+    named.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+
+    Ok(named
+        .into_iter()
+        .enumerate()
+        .map(|(index, (_, file))| (index as u32 + 1, file.clone()))
+        .collect())
 }
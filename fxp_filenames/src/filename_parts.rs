@@ -1,15 +1,15 @@
 use anyhow::Result;
-use log::{debug, error};
-use std::fs;
+use log::debug;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-/// Holds the parts of a filename: a prefix, a suffix, the file path, the file extension, and a modified flag.
+use crate::padding::MIN_PADDING;
+
+/// Holds the parts of a filename: a prefix, a suffix, the file extension, and a modified flag.
 #[derive(Debug)]
 pub struct FilenameParts {
     pub prefix: String,
     pub suffix: String,
-    pub path: PathBuf,
     pub file_extension: String,
     pub modified: bool, // New field added
 }
@@ -61,111 +61,30 @@ impl FilenameParts {
             debug!("Suffix updated to digits only: {}", self.suffix);
         }
 
-        // Finally, ensure the suffix is padded to a length that is a multiple of 4.
+        // Finally, ensure the suffix is padded to at least four digits; longer suffixes,
+        // as of sequences over 9999 frames, keep their width.
         let len = self.suffix.len();
         debug!("Current suffix length: {}", len);
 
-        let remainder = len % 4;
-        debug!("Remainder when divided by 4: {}", remainder);
-
-        if remainder != 0 {
-            // Calculate how many zeros to add.
-            let padding = 4 - remainder;
-            debug!("Padding required: {}", padding);
-
+        if len < MIN_PADDING {
             // Left-pad the suffix with zeros.
-            let padded = format!("{:0>width$}", self.suffix, width = len + padding);
+            let padded = format!("{:0>width$}", self.suffix, width = MIN_PADDING);
             debug!("Padded suffix: {}", padded);
 
-            if padded != self.suffix {
-                self.suffix = padded;
-                self.modified = true;
-                debug!("Suffix updated after padding: {}", self.suffix);
-            }
+            self.suffix = padded;
+            self.modified = true;
+            debug!("Suffix updated after padding: {}", self.suffix);
         }
 
         Ok(())
     }
 
-    /// Updates the image's prefix if it differs from the current prefix.
-    ///
-    /// Compares the current prefix with the provided `new_prefix` and updates
-    /// the prefix field if they differ. Sets the `modified` flag to indicate
-    /// a change.
+    /// Returns the filename this file is mapped as, e.g. `frame_0012.png`.
     ///
-    /// # Parameters
-    /// - `new_prefix`: The target prefix to compare and potentially update to.
-    ///
-    /// # Returns
-    /// - `Result<()>`: Returns `Ok(())` on success, or an `ImageMappingError` if
-    ///   extraction fails.
-    ///
-    /// # Notes
-    /// - The function only modifies the prefix field if the provided `new_prefix`
-    ///   differs from the current one.
-    pub fn check_prefix(&mut self, new_prefix: &str) -> Result<(), ImageMappingError> {
-        // Extract the current prefix from the file's path.
-        let current_prefix = extract_prefix(&self.path)?;
-        // If the prefixes differ, update the prefix field and mark as modified.
-        if current_prefix != new_prefix {
-            self.prefix = new_prefix.to_string();
-            self.modified = true;
-        }
-        Ok(())
-    }
-
-    /// Returns whether the filename has been modified (i.e. its prefix changed).
-    pub fn is_modified(&self) -> bool {
-        self.modified
-    }
-
-    /// Saves the file by renaming it if the filename has been modified.
-    ///
-    /// This function checks if the filename has been modified and renames the file
-    /// accordingly. If no modifications are detected, the function will not perform
-    /// any actions.
-    ///
-    /// # Parameters
-    /// - `self`: Reference to the struct containing the file information.
-    ///
-    /// # Returns
-    /// - `Result<()>`: Indicates success or failure of the save operation.
-    ///
-    /// # Notes
-    /// - The file is only renamed if there are modifications.
-    /// - The `modified` flag is reset to `false` after a successful save.
-    pub fn save_file(&mut self) -> Result<(), ImageMappingError> {
-        if self.modified {
-            debug!("Filename is marked as modified. Proceeding to rename the file.");
-
-            // Construct the new filename using the current prefix, suffix, and file extension.
-            let new_filename = self.construct_new_filename(&self.prefix);
-            debug!("New filename constructed: {}", new_filename);
-
-            let new_path = self.path.with_file_name(new_filename);
-            debug!("New file path: {:?}", new_path);
-
-            if new_path != self.path {
-                debug!("Renaming file from {:?} to {:?}", self.path, new_path);
-                fs::rename(&self.path, &new_path).map_err(|e| {
-                    debug!("Error renaming file: {}", e);
-                    ImageMappingError::RenameError(e.to_string())
-                })?;
-
-                self.path = new_path;
-                debug!("File renamed successfully. Updated path: {:?}", self.path);
-            } else {
-                debug!("New path is the same as the current path. No renaming needed.");
-            }
-
-            // Reset the modified flag after saving.
-            self.modified = false;
-            debug!("Modified flag reset to false.");
-        } else {
-            debug!("Filename is not modified. No action taken.");
-        }
-
-        Ok(())
+    /// The file on disk keeps its original name; the normalized name only drives
+    /// the numeric mapping.
+    pub fn normalized_filename(&self) -> String {
+        self.construct_new_filename(&self.prefix)
     }
 
     /// Constructs a new filename by combining a prefix, suffix, and extension.
@@ -253,7 +172,6 @@ impl FilenameParts {
             Ok(Self {
                 prefix,
                 suffix,
-                path: file.to_path_buf(),
                 file_extension: extension.to_string(),
                 modified: false, // Initialize as false
             })
@@ -285,45 +203,3 @@ pub enum ImageMappingError {
     #[error("No images found on target folder {0}")]
     FileNotFound(String),
 }
-
-/// Extracts the prefix from a filename before the first underscore.
-///
-/// This function takes a file path and extracts the portion of the filename before the first underscore.
-/// If no underscore is present, the entire filename is returned.
-///
-/// # Parameters
-/// - `path`: A reference to a `PathBuf` representing the file path to process.
-///
-/// # Returns
-/// - `Result<String, ImageMappingError>`: The extracted prefix as a `String`, or an error if extraction fails.
-///
-/// # Notes
-/// - If the filename does not contain an underscore, the entire filename is used as the prefix.
-fn extract_prefix(path: &PathBuf) -> Result<String, ImageMappingError> {
-    debug!("Attempting to extract prefix from path: {:?}", path);
-
-    let filename = path.file_stem().and_then(|s| s.to_str()).ok_or_else(|| {
-        let error = ImageMappingError::InvalidFilename(
-            path.clone(),
-            "Could not extract valid file stem".to_string(),
-        );
-        debug!("Error extracting file stem: {:?}", error);
-        error
-    })?;
-    debug!("Extracted file stem: {}", filename);
-
-    // Split at the first underscore and return the prefix.
-    match filename.split_once('_') {
-        Some((prefix, _)) => {
-            debug!("Found underscore in filename. Extracted prefix: {}", prefix);
-            Ok(prefix.to_string())
-        }
-        None => {
-            debug!(
-                "No underscore found in filename. Using entire stem as prefix: {}",
-                filename
-            );
-            Ok(filename.to_string())
-        }
-    }
-}
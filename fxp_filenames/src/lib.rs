@@ -1,5 +1,15 @@
 mod filename_handling;
 mod filename_parts;
+mod numbering;
+mod padding;
+mod selection;
+mod sources;
 
 pub use filename_handling::FileOperations;
 pub use filename_parts::ImageMappingError;
+pub use numbering::{
+    default_schemes, natural_cmp, DotCounter, NumberingScheme, TrailingDigits, UnderscoreNumber,
+};
+pub use padding::{FramePadding, MIN_PADDING};
+pub use selection::{FrameRange, FrameSelection};
+pub use sources::{FrameGroup, FrameSource};
@@ -0,0 +1,180 @@
+use log::debug;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::filename_parts::FilenameParts;
+
+/// A strategy for reading a frame number out of a filename.
+///
+/// Schemes are tried in order by `FileOperations::load_files`; the first scheme that
+/// assigns a unique number to every file wins.
+pub trait NumberingScheme {
+    /// Short name of the scheme, used in log messages.
+    fn name(&self) -> &'static str;
+
+    /// Returns the frame number of `path`, or `None` if the filename does not match the scheme.
+    fn frame_number(&self, path: &Path) -> Option<u32>;
+}
+
+/// The original `prefix_0001.png` scheme: digits following the first underscore.
+pub struct UnderscoreNumber;
+
+/// Digits at the end of the file stem, as in `shot12.png` or `IMG-0042.jpg`.
+pub struct TrailingDigits;
+
+/// A dot-separated counter, as in `frame.0001.png` or `render.0001.beauty.exr`.
+pub struct DotCounter;
+
+impl NumberingScheme for UnderscoreNumber {
+    fn name(&self) -> &'static str {
+        "underscore"
+    }
+
+    /// Normalizes the filename to `frame_{suffix}.{extension}` and reads its number.
+    fn frame_number(&self, path: &Path) -> Option<u32> {
+        let mut parts = FilenameParts::new(path).ok()?;
+        parts.check_suffix().ok()?;
+        let normalized = parts.normalized_filename();
+        let stem = Path::new(&normalized).file_stem()?.to_str()?;
+        extract_correct_number(stem)
+    }
+}
+
+impl NumberingScheme for TrailingDigits {
+    fn name(&self) -> &'static str {
+        "trailing digits"
+    }
+
+    fn frame_number(&self, path: &Path) -> Option<u32> {
+        let stem = path.file_stem()?.to_str()?;
+        let digits_start = stem
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(0, |index| index + 1);
+        stem[digits_start..].parse::<u32>().ok()
+    }
+}
+
+impl NumberingScheme for DotCounter {
+    fn name(&self) -> &'static str {
+        "dot counter"
+    }
+
+    fn frame_number(&self, path: &Path) -> Option<u32> {
+        let stem = path.file_stem()?.to_str()?;
+        // The last all-digit segment after the first dot, so `render.0001.beauty` reads as 1.
+        stem.split('.')
+            .skip(1)
+            .filter(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+            .last()?
+            .parse::<u32>()
+            .ok()
+    }
+}
+
+/// Returns the schemes tried by `load_files`, in order of preference.
+///
+/// # Notes
+/// - The underscore scheme comes first so existing `frame_0001.png` sequences map exactly as before.
+/// - Natural sort is not part of this list; it is the fallback when no scheme applies.
+pub fn default_schemes() -> Vec<Box<dyn NumberingScheme>> {
+    vec![
+        Box::new(UnderscoreNumber),
+        Box::new(DotCounter),
+        Box::new(TrailingDigits),
+    ]
+}
+
+/// Compares two strings in natural order, so that `shot2` sorts before `shot10`.
+///
+/// Runs of ASCII digits are compared by numeric value, everything else character by character.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_run = take_digits(&mut a_chars);
+                let b_run = take_digits(&mut b_chars);
+                let a_trimmed = a_run.trim_start_matches('0');
+                let b_trimmed = b_run.trim_start_matches('0');
+                // Longer runs without leading zeros are larger numbers.
+                let ordering = a_trimmed
+                    .len()
+                    .cmp(&b_trimmed.len())
+                    .then_with(|| a_trimmed.cmp(b_trimmed))
+                    .then_with(|| a_run.len().cmp(&b_run.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Consumes a run of ASCII digits from the iterator.
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        run.push(c);
+        chars.next();
+    }
+    run
+}
+
+/// Extracts a number from a filename if it matches the expected pattern.
+///
+/// This function attempts to find and parse a number in the given filename.
+///
+/// # Parameters
+/// - `filename`: The input filename string to extract the number from.
+///
+/// # Returns
+/// - `Option<u32>`: Contains the extracted number if successful, otherwise `None`.
+///
+/// # Notes
+/// - The function looks for digits preceded by an underscore (`_`).
+/// - Only the first occurrence of such a pattern is considered.
+fn extract_correct_number(filename: &str) -> Option<u32> {
+    debug!("Attempting to extract number from filename: {}", filename);
+
+    let re = Regex::new(r"_(\d+)").ok()?;
+    debug!("Regex compiled successfully.");
+
+    let number = re
+        .captures(filename)
+        .and_then(|caps| {
+            debug!("Captures found: {:?}", caps);
+            caps.get(1)
+        })
+        .and_then(|m| {
+            let matched_str = m.as_str();
+            debug!("Matched number string: {}", matched_str);
+            matched_str.parse::<u32>().ok()
+        });
+
+    match number {
+        Some(num) => {
+            debug!("Successfully extracted number: {}", num);
+            Some(num)
+        }
+        None => {
+            debug!("No number found in filename.");
+            None
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+
+/// The narrowest padding of frame numbers, as in `frame_0001.png`.
+pub const MIN_PADDING: usize = 4;
+
+/// The width frame numbers are zero-padded to, so that names sort in frame order.
+///
+/// Four digits until a sequence needs more, as one of over 9999 frames or one whose
+/// files are padded to five digits does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FramePadding(usize);
+
+impl Default for FramePadding {
+    fn default() -> Self {
+        Self(MIN_PADDING)
+    }
+}
+
+impl FramePadding {
+    /// Returns the padding numbering `count` frames from 1 needs.
+    pub fn for_count(count: u64) -> Self {
+        Self(count.max(1).to_string().len().max(MIN_PADDING))
+    }
+
+    /// Detects how wide the numbers of a sequence of files are padded.
+    ///
+    /// # Parameters
+    /// - `files`: The files of the sequence, as mapped by `FileOperations::load_files`.
+    ///
+    /// # Returns
+    /// - `Self`: The widest run of digits ending a file stem's last number, at least
+    ///   `MIN_PADDING`; `frame_00001.png` is padded to 5 digits, `shot12.png` to 4.
+    pub fn detect<'a>(files: impl IntoIterator<Item = &'a Path>) -> Self {
+        let widest = files
+            .into_iter()
+            .filter_map(|file| file.file_stem()?.to_str().map(last_digit_run))
+            .max()
+            .unwrap_or(0);
+        Self(widest.max(MIN_PADDING))
+    }
+
+    /// Returns the number of digits.
+    pub fn width(self) -> usize {
+        self.0
+    }
+
+    /// Returns the name of a frame, e.g. `frame_00012.png` for 12 padded to 5 digits.
+    pub fn file_name(self, prefix: &str, number: u64, extension: &str) -> String {
+        format!(
+            "{}_{:0width$}.{}",
+            prefix,
+            number,
+            extension,
+            width = self.0
+        )
+    }
+
+    /// Returns the image2 pattern ffmpeg reads and writes such frames by, e.g.
+    /// `frame_%05d.png`.
+    pub fn pattern(self, prefix: &str, extension: &str) -> String {
+        format!("{}_%0{}d.{}", prefix, self.0, extension)
+    }
+}
+
+/// Returns the length of the last run of digits in a file stem, 0 if it has none.
+fn last_digit_run(stem: &str) -> usize {
+    stem.rsplit(|c: char| !c.is_ascii_digit())
+        .find(|run| !run.is_empty())
+        .map_or(0, str::len)
+}
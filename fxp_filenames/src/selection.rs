@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// An inclusive range of frame numbers, either end of which may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameRange {
+    pub first: Option<u32>,
+    pub last: Option<u32>,
+}
+
+impl FrameRange {
+    /// Whether the frame number lies within the range.
+    pub fn contains(&self, number: u32) -> bool {
+        self.first.is_none_or(|first| number >= first)
+            && self.last.is_none_or(|last| number <= last)
+    }
+}
+
+impl FromStr for FrameRange {
+    type Err = String;
+
+    /// Parses `A..B`, `A..=B`, `A..` or `..B`; both ends are included.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid frame range '{}', expected FIRST..LAST, FIRST.. or ..LAST",
+                s
+            )
+        };
+        let (first, last) = s.split_once("..").ok_or_else(invalid)?;
+        let last = last.strip_prefix('=').unwrap_or(last);
+        let bound = |value: &str| -> Result<Option<u32>, String> {
+            if value.is_empty() {
+                Ok(None)
+            } else {
+                value.trim().parse().map(Some).map_err(|_| invalid())
+            }
+        };
+        let range = FrameRange {
+            first: bound(first)?,
+            last: bound(last)?,
+        };
+        if let (Some(first), Some(last)) = (range.first, range.last) {
+            if first > last {
+                return Err(format!(
+                    "Invalid frame range '{}', the first frame comes after the last",
+                    s
+                ));
+            }
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for FrameRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(first) = self.first {
+            write!(f, "{}", first)?;
+        }
+        write!(f, "..")?;
+        if let Some(last) = self.last {
+            write!(f, "{}", last)?;
+        }
+        Ok(())
+    }
+}
+
+/// A subset of the mapped frames: a range of frame numbers and a stride through it.
+///
+/// Lets a filter chain be tried on a short or sparse section before the full run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSelection {
+    /// Frames outside this range are left out; `None` keeps them all.
+    pub range: Option<FrameRange>,
+    /// Keep every Nth frame of the range, starting with its first; 1 keeps them all.
+    pub every: usize,
+}
+
+impl Default for FrameSelection {
+    fn default() -> Self {
+        Self {
+            range: None,
+            every: 1,
+        }
+    }
+}
+
+impl FrameSelection {
+    /// Whether the selection keeps every frame.
+    pub fn is_all(&self) -> bool {
+        self.range.is_none() && self.every <= 1
+    }
+
+    /// Returns the selected frames, keeping their frame numbers.
+    ///
+    /// # Parameters
+    /// - `frames`: Frames mapped by frame number.
+    ///
+    /// # Returns
+    /// - `BTreeMap<u32, PathBuf>`: The frames within the range, thinned out by the stride.
+    pub fn select(&self, frames: &BTreeMap<u32, PathBuf>) -> BTreeMap<u32, PathBuf> {
+        let in_range = self.in_range(frames);
+        self.stride(in_range.into_iter().collect())
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the frames within the range, without applying the stride.
+    pub fn in_range(&self, frames: &BTreeMap<u32, PathBuf>) -> BTreeMap<u32, PathBuf> {
+        match self.range {
+            Some(range) => frames
+                .iter()
+                .filter(|(number, _)| range.contains(**number))
+                .map(|(number, path)| (*number, path.clone()))
+                .collect(),
+            None => frames.clone(),
+        }
+    }
+
+    /// Keeps every Nth item, starting with the first.
+    pub fn stride<T>(&self, items: Vec<T>) -> Vec<T> {
+        if self.every <= 1 {
+            return items;
+        }
+        items.into_iter().step_by(self.every).collect()
+    }
+
+    /// Returns the selected items of a sequence whose positions count as frame numbers.
+    ///
+    /// # Notes
+    /// - The first item is frame 1, as with the Merger's pairs.
+    pub fn select_positions<T>(&self, items: Vec<T>) -> Vec<T> {
+        let in_range = match self.range {
+            Some(range) => items
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| range.contains(*index as u32 + 1))
+                .map(|(_, item)| item)
+                .collect(),
+            None => items,
+        };
+        self.stride(in_range)
+    }
+}
+
+impl fmt::Display for FrameSelection {
+    /// Formats the selection as `all frames`, `frames 100..500`, `every 2nd frame` or both.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.range, self.every) {
+            (None, 0 | 1) => write!(f, "all frames"),
+            (Some(range), 0 | 1) => write!(f, "frames {}", range),
+            (None, every) => write!(f, "every {} frame", ordinal(every)),
+            (Some(range), every) => write!(f, "every {} frame of {}", ordinal(every), range),
+        }
+    }
+}
+
+/// Formats 2 as `2nd`, 3 as `3rd`, 11 as `11th` and so on.
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
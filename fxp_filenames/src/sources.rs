@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use glob::MatchOptions;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use fxp_modes::Modes;
+
+use crate::filename_handling::FileOperations;
+
+/// Characters that make an input a glob pattern rather than a directory.
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// Where a mode reads its images from: a directory, a directory and all of its
+/// subdirectories, or a glob pattern such as `shots/**/frame_*.png`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSource {
+    input: String,
+    /// Read the subdirectories of a directory input too; a glob pattern already names
+    /// the directories it reads, so this has no effect on it.
+    pub recursive: bool,
+}
+
+/// The images of one directory of a `FrameSource`, numbered on their own.
+#[derive(Debug, Clone)]
+pub struct FrameGroup {
+    /// The directory relative to the root of the source; empty for the root itself.
+    pub relative: PathBuf,
+    /// The images mapped by frame number, see `FileOperations::load_files`.
+    pub frames: BTreeMap<u32, PathBuf>,
+}
+
+impl FrameSource {
+    /// Creates a source reading `input`, a directory or a glob pattern.
+    ///
+    /// # Parameters
+    /// - `input`: A directory, or a pattern containing `*`, `?` or `[`.
+    /// - `recursive`: Whether to read the subdirectories of a directory too.
+    pub fn new(input: impl Into<String>, recursive: bool) -> Self {
+        Self {
+            input: input.into(),
+            recursive,
+        }
+    }
+
+    /// Returns the input as given.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns whether the input is a glob pattern rather than a directory.
+    pub fn is_glob(&self) -> bool {
+        self.input.contains(GLOB_CHARACTERS)
+    }
+
+    /// Returns whether the images may come from more than one directory.
+    pub fn is_nested(&self) -> bool {
+        self.recursive || self.is_glob()
+    }
+
+    /// Returns the directory the relative paths of the groups start from.
+    ///
+    /// # Notes
+    /// - For a glob pattern, this is the part before the first component holding a
+    ///   wildcard, e.g. `shots` for `shots/**/frame_*.png`, or `.` if there is none.
+    pub fn root(&self) -> PathBuf {
+        let root = self.glob_root();
+        if root.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            root
+        }
+    }
+
+    /// Returns the literal leading components of the input.
+    fn glob_root(&self) -> PathBuf {
+        if !self.is_glob() {
+            return PathBuf::from(&self.input);
+        }
+        Path::new(&self.input)
+            .components()
+            .take_while(|component| match component {
+                Component::Normal(name) => !name.to_string_lossy().contains(GLOB_CHARACTERS),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Finds the images of the source and numbers those of each directory.
+    ///
+    /// # Parameters
+    /// - `mode`: The mode reading the images, which decides how they are numbered.
+    ///
+    /// # Returns
+    /// - `Result<Vec<FrameGroup>>`: One group per directory holding images, ordered by
+    ///   relative path, or an error if a directory cannot be read or the pattern is invalid.
+    ///
+    /// # Notes
+    /// - A plain directory always gives exactly one group, even when it is empty.
+    /// - Recursion skips hidden directories, such as the staging directory of a run.
+    /// - Wildcards do not match names starting with `.`; `**` matches any number of
+    ///   directories.
+    pub fn load_groups(&self, mode: Modes) -> Result<Vec<FrameGroup>> {
+        let files = if self.is_glob() {
+            self.glob_files()?
+        } else if self.recursive {
+            let mut files = BTreeMap::new();
+            walk_directory(Path::new(&self.input), Path::new(""), &mut files)?;
+            files
+        } else {
+            BTreeMap::from([(PathBuf::new(), list_files(Path::new(&self.input))?)])
+        };
+
+        let mut groups = Vec::with_capacity(files.len());
+        for (relative, files) in files {
+            let frames = mode.load_files(&files).with_context(|| {
+                format!(
+                    "Failed to number the images in {}",
+                    self.root().join(&relative).display()
+                )
+            })?;
+            debug!("Loaded {} images from {:?}", frames.len(), relative);
+            groups.push(FrameGroup { relative, frames });
+        }
+        Ok(groups)
+    }
+
+    /// Returns the files matching the pattern, grouped by their directory.
+    fn glob_files(&self) -> Result<BTreeMap<PathBuf, Vec<PathBuf>>> {
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            ..MatchOptions::new()
+        };
+        let root = self.glob_root();
+        let mut files: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for entry in glob::glob_with(&self.input, options)
+            .with_context(|| format!("Invalid glob pattern {}", self.input))?
+        {
+            let path = entry.context("Failed to read a path matching the pattern")?;
+            if !path.is_file() {
+                continue;
+            }
+            let relative = path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&root).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            files.entry(relative).or_default().push(path);
+        }
+        debug!(
+            "Pattern {} matched files in {} directories",
+            self.input,
+            files.len()
+        );
+        Ok(files)
+    }
+}
+
+impl From<String> for FrameSource {
+    fn from(input: String) -> Self {
+        Self::new(input, false)
+    }
+}
+
+impl From<&str> for FrameSource {
+    fn from(input: &str) -> Self {
+        Self::new(input, false)
+    }
+}
+
+impl fmt::Display for FrameSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.input)
+    }
+}
+
+/// Returns the files directly inside a directory.
+fn list_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+/// Collects the files of `directory` and its subdirectories, keyed by their directory
+/// relative to the root.
+fn walk_directory(
+    directory: &Path,
+    relative: &Path,
+    files: &mut BTreeMap<PathBuf, Vec<PathBuf>>,
+) -> Result<()> {
+    let here = list_files(directory)?;
+    if !here.is_empty() {
+        files.insert(relative.to_path_buf(), here);
+    }
+    let mut subdirectories: Vec<PathBuf> = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_dir()
+                && !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    subdirectories.sort();
+    for subdirectory in subdirectories {
+        let name = subdirectory.file_name().unwrap_or_default().to_owned();
+        walk_directory(&subdirectory, &relative.join(name), files)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use anyhow::Result;
+use log::debug;
+#[cfg(not(feature = "libgmic"))]
+use std::path::Path;
+use std::path::PathBuf;
+use tracing::info_span;
+
+/// One image for G'MIC to process.
+#[derive(Debug, Clone)]
+pub struct GmicJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// The G'MIC arguments, with the placeholders already resolved for this image.
+    pub args: Vec<String>,
+}
+
+/// Number of images handed to G'MIC at once: the library loads its command definitions
+/// once per call, so images are batched; the binary runs once per image.
+pub const BATCH_SIZE: usize = if cfg!(feature = "libgmic") { 16 } else { 1 };
+
+/// Describes how G'MIC is run, for the plan.
+pub fn engine() -> &'static str {
+    if cfg!(feature = "libgmic") {
+        "libgmic, in-process"
+    } else {
+        "gmic binary, one process per image"
+    }
+}
+
+/// Processes a batch of images, at most `BATCH_SIZE` of them.
+///
+/// # Parameters
+/// - `jobs`: The images to process.
+///
+/// # Returns
+/// - `Vec<Result<()>>`: One result per job, in order, with G'MIC's own error message
+///   where it gives one.
+///
+/// # Notes
+/// - With the `libgmic` feature, the whole batch is run in one call; if it fails, each
+///   image is run on its own so the error is reported for the image that caused it.
+pub fn process_batch(jobs: &[GmicJob]) -> Vec<Result<()>> {
+    let _span = info_span!("gmic", images = jobs.len()).entered();
+    #[cfg(feature = "libgmic")]
+    {
+        if jobs.len() > 1 {
+            let command: Vec<String> = jobs.iter().flat_map(library::commands).collect();
+            match library::run(&command) {
+                Ok(()) => return jobs.iter().map(|_| Ok(())).collect(),
+                Err(e) => debug!(
+                    "Batch of {} images failed, retrying one by one: {}",
+                    jobs.len(),
+                    e
+                ),
+            }
+        }
+        jobs.iter()
+            .map(|job| library::run(&library::commands(job)))
+            .collect()
+    }
+    #[cfg(not(feature = "libgmic"))]
+    {
+        jobs.iter()
+            .map(|job| process_image(&job.input, &job.output, &job.args))
+            .collect()
+    }
+}
+
+/// Runs GMIC command on a single image file, suppressing output.
+///
+/// This function executes a GMIC command with specified arguments on a given image file.
+///
+/// # Parameters
+/// - `input`: Input image path as a `Path` reference.
+/// - `output`: Output image path as a `Path` reference.
+/// - `gmic_args`: Slice of GMIC command arguments as strings.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` on successful processing, or an error if processing fails.
+///
+/// # Notes
+/// - Suppresses `stdout`; the last line G'MIC writes to `stderr` is the error reported.
+/// - Does not handle GMIC installation or setup; assumes GMIC is already available in the system PATH.
+#[cfg(not(feature = "libgmic"))]
+fn process_image(input: &Path, output: &Path, gmic_args: &[String]) -> Result<()> {
+    use anyhow::Context;
+    use std::process::Command as StdCommand;
+
+    use fxp_output::ToolError;
+
+    use crate::error::GmicerError;
+
+    // Debug: Print the input and output paths
+    debug!(
+        "Processing image: input = {:?}, output = {:?}",
+        input, output
+    );
+    // Debug: Print the GMIC arguments being used
+    debug!("GMIC arguments: {:?}", gmic_args);
+
+    // Run the GMIC command
+    let result = StdCommand::new("gmic")
+        .arg(input)
+        .args(gmic_args)
+        .arg("-output")
+        .arg(output)
+        .stdout(std::process::Stdio::null()) // Suppress stdout
+        .output()
+        .map_err(|e| GmicerError::spawn("gmic", e))
+        .with_context(|| format!("Failed to execute GMIC command for input: {:?}", input))?;
+
+    // Debug: Print the status of the GMIC command
+    debug!("GMIC command executed with status: {}", result.status);
+
+    if !result.status.success() {
+        // Return an error if the GMIC command failed
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let reason = match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(message) => message.trim().to_string(),
+            None => result.status.to_string(),
+        };
+        return Err(GmicerError::ToolFailed {
+            tool: "gmic".to_string(),
+            reason,
+        })
+        .with_context(|| format!("GMIC command failed for input: {:?}", input));
+    } else {
+        // Debug: Print a success message if the GMIC command succeeded
+        debug!("Successfully processed image: {:?}", input);
+    }
+
+    Ok(())
+}
+
+/// G'MIC linked in through the C interface of libcgmic, `gmic_libc.h`.
+#[cfg(feature = "libgmic")]
+mod library {
+    use anyhow::{anyhow, Result};
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_float, c_int, c_uint, c_void};
+    use std::path::Path;
+
+    use super::GmicJob;
+
+    /// Size of the buffer G'MIC copies its error message into.
+    const ERROR_BUFFER_SIZE: usize = 4096;
+
+    /// `gmic_interface_options` of `gmic_libc.h`.
+    #[repr(C)]
+    struct GmicInterfaceOptions {
+        ignore_stdlib: bool,
+        p_is_abort: *mut bool,
+        p_progress: *mut c_float,
+        error_message_buffer: *const c_char,
+        no_inplace_processing: bool,
+        output_format: c_int,
+        interleave_output: bool,
+    }
+
+    #[link(name = "cgmic")]
+    extern "C" {
+        fn gmic_call(
+            cmd: *const c_char,
+            nof_images: *mut c_uint,
+            images: *mut c_void,
+            options: *mut GmicInterfaceOptions,
+        ) -> c_int;
+    }
+
+    /// Returns the G'MIC commands loading, processing, saving and dropping one image.
+    pub fn commands(job: &GmicJob) -> Vec<String> {
+        let mut commands = vec!["input".to_string(), quote_path(&job.input)];
+        commands.extend(job.args.iter().map(|arg| quote(arg)));
+        commands.extend([
+            "output".to_string(),
+            quote_path(&job.output),
+            "remove".into(),
+        ]);
+        commands
+    }
+
+    /// Runs a G'MIC command line in-process.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error with G'MIC's message if the command fails.
+    ///
+    /// # Notes
+    /// - The command must leave no image behind, as no image list is passed in to
+    ///   receive it; `commands` ends with `remove` for this.
+    pub fn run(commands: &[String]) -> Result<()> {
+        let command = CString::new(commands.join(" "))
+            .map_err(|_| anyhow!("G'MIC command contains a NUL byte"))?;
+        let mut error = vec![0 as c_char; ERROR_BUFFER_SIZE];
+        let mut options = GmicInterfaceOptions {
+            ignore_stdlib: false,
+            p_is_abort: std::ptr::null_mut(),
+            p_progress: std::ptr::null_mut(),
+            error_message_buffer: error.as_mut_ptr(),
+            no_inplace_processing: false,
+            output_format: 0,
+            interleave_output: false,
+        };
+        let mut nof_images: c_uint = 0;
+        // SAFETY: the command is NUL-terminated, no images are passed in or left behind,
+        // and the error buffer outlives the call.
+        let status = unsafe {
+            gmic_call(
+                command.as_ptr(),
+                &mut nof_images,
+                std::ptr::null_mut(),
+                &mut options,
+            )
+        };
+        if status != 0 {
+            // SAFETY: G'MIC writes a NUL-terminated message into the zeroed buffer.
+            let message = unsafe { CStr::from_ptr(error.as_ptr()) }.to_string_lossy();
+            return Err(anyhow!("G'MIC failed: {}", message.trim()));
+        }
+        Ok(())
+    }
+
+    /// Quotes a path for the G'MIC command line.
+    fn quote_path(path: &Path) -> String {
+        format!("\"{}\"", path.to_string_lossy().replace('"', "\\\""))
+    }
+
+    /// Quotes an argument holding whitespace, which would otherwise split it.
+    fn quote(arg: &str) -> String {
+        if arg.chars().any(char::is_whitespace) {
+            format!("\"{}\"", arg.replace('"', "\\\""))
+        } else {
+            arg.to_string()
+        }
+    }
+}
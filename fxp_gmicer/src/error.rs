@@ -0,0 +1,49 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Gmicer that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum GmicerError {
+    /// A tool the Gmicer runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Gmicer runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The run was stopped by Ctrl+C.
+    #[error("Processing interrupted by user")]
+    Interrupted,
+}
+
+impl GmicerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            GmicerError::ToolMissing(_) => FailureKind::ToolMissing,
+            GmicerError::ToolFailed { .. } => FailureKind::ToolFailed,
+            GmicerError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+
+    /// Returns whether an error would recur on every attempt, so it is not retried, see
+    /// `fxp_output::RetryPolicy`.
+    pub(crate) fn is_permanent(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<GmicerError>(),
+            Some(GmicerError::ToolMissing(_) | GmicerError::Interrupted)
+        )
+    }
+}
+
+impl ToolError for GmicerError {
+    fn missing(tool: &str) -> Self {
+        GmicerError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        GmicerError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
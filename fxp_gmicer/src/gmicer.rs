@@ -1,25 +1,43 @@
 use anyhow::{Context, Result};
 use console::style;
 use log::{debug, error};
-use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use tracing::info_span;
 
 use fxp_modes::Modes;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::RetryPolicy;
+use fxp_output::StagedDirectory;
 
+use crate::engine;
 use crate::image::image_processing;
-use fxp_filenames::FileOperations;
-use fxp_filenames::ImageMappingError;
+use crate::template::{self, Sequence};
+use fxp_filenames::FrameGroup;
+use fxp_filenames::FrameSelection;
+use fxp_filenames::FrameSource;
 
 pub struct Gmicer {
-    input_path: PathBuf,
+    input: FrameSource,
     gmic_args: Vec<String>,
     output_path: PathBuf,
-    images: BTreeMap<u32, PathBuf>,
+    /// The images of every input directory, written to the same relative directory of
+    /// the output.
+    groups: Vec<FrameGroup>,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Process only these frames; `new` selects all of them.
+    pub selection: FrameSelection,
+    /// The preset the GMIC arguments start with, recorded in the manifest; `new` sets `None`.
+    pub preset: Option<String>,
+    /// How often an image GMIC fails to process is retried; `new` sets no retries.
+    pub retry: RetryPolicy,
 }
 
 impl Gmicer {
@@ -29,9 +47,11 @@ impl Gmicer {
     /// including input and output directories, and GMIC arguments.
     ///
     /// # Parameters
-    /// - `input_directory`: The path to the directory containing input images.
+    /// - `input`: The directory containing input images, optionally with its
+    ///   subdirectories, or a glob pattern matching them.
     /// - `output_directory`: Optional path for output images; defaults to input directory if not provided.
     /// - `gmic_args`: Vector of GMIC arguments to apply during processing.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: Returns a new `Gmicer` instance on success, or an error if initialization fails.
@@ -39,17 +59,20 @@ impl Gmicer {
     /// # Notes
     /// - If `output_directory` is not provided, output files will be placed in the input directory.
     /// - The function validates the input directory and GMIC arguments before initializing.
+    /// - Images of a subdirectory of the input are numbered on their own and written to the
+    ///   same subdirectory of the output; see `FrameSource::load_groups`.
     pub fn new(
-        input_directory: &str,
+        input: FrameSource,
         output_directory: Option<&str>,
         gmic_args: Vec<String>,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
         debug!("Initializing new Gmicer instance");
-        debug!("Input directory: {}", input_directory);
+        debug!("Input: {}", input);
         debug!("Output directory: {:?}", output_directory);
         debug!("GMIC arguments: {:?}", gmic_args);
 
-        let input_path = PathBuf::from(input_directory);
+        let input_path = input.root();
         debug!("Created input PathBuf: {:?}", input_path);
 
         // Create the output directory via the ModeOutput trait:
@@ -60,11 +83,14 @@ impl Gmicer {
         let output_path_buf = match output {
             Output::Gmicer(gmicer_output) => {
                 debug!("Creating GMICer output directory");
-                let path = gmicer_output.create_output((
-                    input_path.clone(),
-                    gmic_args.clone(),
-                    output_directory.map(String::from),
-                ))?;
+                let path = gmicer_output.create_output(
+                    (
+                        input_path.clone(),
+                        gmic_args.clone(),
+                        output_directory.map(String::from),
+                    ),
+                    collision,
+                )?;
                 debug!("Output directory created at: {:?}", path);
                 path
             }
@@ -74,67 +100,151 @@ impl Gmicer {
             }
         };
 
+        debug!("Setting up GMIC processing for input: {}", input);
+        let (groups, total_images) = setup_gmic_processing(&input)?;
         debug!(
-            "Setting up GMIC processing for directory: {}",
-            input_directory
+            "Found {} images in {} directories",
+            total_images,
+            groups.len()
         );
-        let (images, padding) = setup_gmic_processing(input_directory)?;
-        debug!("Found {} images with padding: {}", images.len(), padding);
 
         let gmicer = Self {
-            input_path: input_path.clone(),
+            input,
             gmic_args: gmic_args.clone(),
             output_path: output_path_buf.clone(),
-            images: images.clone(),
+            groups,
+            in_place: false,
+            selection: FrameSelection::default(),
+            preset: None,
+            retry: RetryPolicy::default(),
         };
 
         debug!("Successfully created Gmicer instance:");
-        debug!("- Input path: {:?}", gmicer.input_path);
+        debug!("- Input: {}", gmicer.input);
         debug!("- Output path: {:?}", gmicer.output_path);
-        debug!("- Number of images: {}", gmicer.images.len());
+        debug!("- Number of directories: {}", gmicer.groups.len());
         debug!("- GMIC arguments: {:?}", gmicer.gmic_args);
 
         Ok(gmicer)
     }
+
+    /// Resolves what `new` and `gmic_images` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input`: The directory, optionally with its subdirectories, or glob pattern of
+    ///   the input images.
+    /// - `output_directory`: Optional path for output images.
+    /// - `gmic_args`: Vector of GMIC arguments to apply during processing, including those
+    ///   of `preset`.
+    /// - `preset`: The preset the GMIC arguments start with, if any.
+    /// - `selection`: The frames to process.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the input cannot be read.
+    pub fn plan(
+        input: &FrameSource,
+        output_directory: Option<&str>,
+        gmic_args: Vec<String>,
+        preset: Option<&str>,
+        selection: &FrameSelection,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_path = input.root();
+        let (groups, total_images) = setup_gmic_processing(input)?;
+
+        let mode: Modes = Modes::Gmicer;
+        let output: Output = mode.into();
+        let output_path_buf = match output {
+            Output::Gmicer(gmicer_output) => gmicer_output.plan_output(
+                (
+                    input_path.clone(),
+                    gmic_args.clone(),
+                    output_directory.map(String::from),
+                ),
+                collision,
+            )?,
+            _ => unreachable!("Expected Gmicer mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Gmicer);
+        plan = if input.is_glob() {
+            plan.entry("input pattern", input)
+        } else {
+            plan.entry("input directory", input_path.display())
+        };
+        if input.is_nested() {
+            plan = plan.entry("directories", groups.len());
+        }
+        plan = plan.entry("images", total_images);
+        if !selection.is_all() {
+            let selected: usize = groups
+                .iter()
+                .map(|group| selection.select(&group.frames).len())
+                .sum();
+            plan = plan
+                .entry("selection", selection)
+                .entry("selected images", selected);
+        }
+        if let Some(preset) = preset {
+            plan = plan.entry("preset", preset);
+        }
+        plan = plan
+            .entry("gmic arguments", gmic_args.join(" "))
+            .entry("gmic engine", engine::engine());
+        if template::has_placeholders(&gmic_args) {
+            if let Some((group, sequence)) = groups
+                .iter()
+                .find_map(|group| Some((group, Sequence::of(&group.frames)?)))
+            {
+                let selected = selection.select(&group.frames);
+                if let (Some(first), Some(last)) =
+                    (selected.keys().next(), selected.keys().next_back())
+                {
+                    plan = plan
+                        .entry(
+                            "first image arguments",
+                            template::substitute(&gmic_args, *first, sequence).join(" "),
+                        )
+                        .entry(
+                            "last image arguments",
+                            template::substitute(&gmic_args, *last, sequence).join(" "),
+                        );
+                }
+            }
+        }
+        Ok(plan
+            .entry("on existing output", collision)
+            .entry("output directory", output_path_buf.display()))
+    }
 }
 
-/// Sets up and processes G'MIC image files from a specified directory.
+/// Sets up and processes G'MIC image files from a specified input.
 ///
-/// This function reads image files from the input directory, processes them,
+/// This function reads image files from the input, processes them,
 /// and returns a mapped collection of the images along with their count.
 ///
 /// # Parameters
-/// - `input_directory`: The file path to the directory containing G'MIC images to process.
+/// - `input`: The directory or glob pattern of the G'MIC images to process.
 ///
 /// # Returns
-/// - `Result<(BTreeMap<u32, PathBuf>, usize)>`: A tuple containing:
-///   - A `BTreeMap` mapping image IDs to their file paths.
+/// - `Result<(Vec<FrameGroup>, usize)>`: A tuple containing:
+///   - The images of every input directory, mapped by their numbers.
 ///   - The total number of images processed.
 ///
 /// # Notes
-/// - The function reads all image files from the specified directory.
+/// - The function reads all image files from the input directories.
 /// - Uses `FileOperations` for processing images in "Gmicer" mode.
-fn setup_gmic_processing(input_directory: &str) -> Result<(BTreeMap<u32, PathBuf>, usize)> {
+fn setup_gmic_processing(input: &FrameSource) -> Result<(Vec<FrameGroup>, usize)> {
     debug!("Starting setup_gmic_processing function");
 
-    let dir_path = Path::new(input_directory);
-    debug!("Input directory path: {:?}", dir_path);
+    let groups = input
+        .load_groups(Modes::Gmicer)
+        .context("Failed to read input directory")?;
+    let total_images = groups.iter().map(|group| group.frames.len()).sum();
+    debug!("Total images after processing: {}", total_images);
 
-    // Read all image paths from the input directory.
-    let images: Vec<PathBuf> = fs::read_dir(dir_path)
-        .context("Failed to read input directory")?
-        .filter_map(|entry| entry.ok().map(|e| e.path()))
-        .collect();
-    debug!("Found {} images in input directory", images.len());
-
-    // Use FileOperations implemented for Modes::Clipper to process images.
-    debug!("Loading files using FileOperations for Clipper mode");
-    let image_map = Modes::Gmicer
-        .load_files(&images)
-        .map_err(|e| ImageMappingError::RenameError(e.to_string()))?;
-    debug!("Total images after processing: {}", image_map.len());
-
-    Ok((image_map.clone(), image_map.len()))
+    Ok((groups, total_images))
 }
 
 impl Gmicer {
@@ -152,24 +262,74 @@ impl Gmicer {
     /// # Notes
     /// - Logs debug and error messages for visibility into processing flow
     /// - Processes images with GMIC arguments and handles output directory warnings
+    /// - Images are staged and moved into the output directory only once all of them
+    ///   succeeded, unless `in_place` is set; see `fxp_output::StagedDirectory`
+    /// - Writes a `manifest.json` recording the GMIC arguments and input hashes
+    /// - Only the frames of `selection` are processed
+    /// - `{frame}`, `{t}` and `{total}` in the GMIC arguments are resolved per image; see
+    ///   `template::substitute`
     /// - Returns early with success if no images are found
     pub fn gmic_images(&self) -> Result<()> {
         debug!(
             "Processing images from '{}' with GMIC arguments: {:?}",
-            self.input_path.display(),
-            self.gmic_args
+            self.input, self.gmic_args
         );
+        let _span = info_span!(
+            "gmicer",
+            input = %self.input,
+            output = %self.output_path.display(),
+            gmic_args = %self.gmic_args.join(" ")
+        )
+        .entered();
 
-        if self.images.is_empty() {
+        // The placeholders resolve against the whole directory, so a selection of frames
+        // gets the same arguments as a full run.
+        let groups: Vec<(FrameGroup, Sequence)> = self
+            .groups
+            .iter()
+            .filter_map(|group| {
+                let sequence = Sequence::of(&group.frames)?;
+                let selected = FrameGroup {
+                    relative: group.relative.clone(),
+                    frames: self.selection.select(&group.frames),
+                };
+                (!selected.frames.is_empty()).then_some((selected, sequence))
+            })
+            .collect();
+        if groups.is_empty() {
             error!("No images found in the input directory.");
             return Ok(());
         }
 
-        image_processing(&self.images, &self.gmic_args, &self.output_path)
+        let mut manifest = Manifest::new(Modes::Gmicer)
+            .parameter("input", &self.input)
+            .parameter_list("gmic arguments", &self.gmic_args);
+        if self.input.recursive {
+            manifest = manifest.parameter("recursive", true);
+        }
+        if let Some(preset) = &self.preset {
+            manifest = manifest.parameter("preset", preset);
+        }
+        if let Some(range) = self.selection.range {
+            manifest = manifest.parameter("frames", range);
+        }
+        if self.selection.every > 1 {
+            manifest = manifest.parameter("every", self.selection.every);
+        }
+        let manifest = manifest.inputs(groups.iter().flat_map(|(group, _)| group.frames.values()));
+
+        let mut staged = StagedDirectory::begin(&self.output_path, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let processed = image_processing(&groups, &self.gmic_args, staged.path(), &self.retry)
             .context("Failed to process images")?;
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Gmicer, processed)?;
 
-        warn_on_multiple_image_output(&self.output_path)
-            .context("Failed to warn on multiple image output")?;
+        for (group, _) in &groups {
+            warn_on_multiple_image_output(&self.output_path.join(&group.relative))
+                .context("Failed to warn on multiple image output")?;
+        }
 
         Ok(())
     }
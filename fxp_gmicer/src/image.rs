@@ -1,13 +1,19 @@
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 use log::{debug, warn};
-use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command as StdCommand;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
+use std::sync::atomic::Ordering;
+
+use fxp_cache::Cache;
+use fxp_filenames::{FrameGroup, FramePadding};
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::{progress_bar, seed, RetryPolicy};
+
+use crate::engine::{process_batch, GmicJob, BATCH_SIZE};
+use crate::error::GmicerError;
+use crate::template::{self, Sequence};
 
 /// Processes images using GMIC with specified arguments and outputs to a directory.
 ///
@@ -15,40 +21,44 @@ use std::sync::{
 /// GMIC operations on the provided images.
 ///
 /// # Parameters
-/// - `images`: Collection of images to process, mapped by unique identifiers.
+/// - `groups`: The images to process, mapped by unique identifiers, per input directory,
+///   with the sequence of that directory the placeholders resolve against.
 /// - `gmic_args`: Command-line arguments for GMIC processing.
 /// - `output_directory`: Path to the directory where processed images will be saved.
+/// - `retry`: How often a failed image is processed again.
 ///
 /// # Returns
-/// - `Result<()>`: Indicates successful execution or returns an error if any issues occur.
+/// - `Result<usize>`: The number of images in the output directory, or an error if any
+///   image failed or processing was interrupted.
 ///
 /// # Notes
 /// - The function logs debug information about the processing steps.
 /// - Validates that the output directory exists and that images are provided.
 pub fn image_processing(
-    images: &BTreeMap<u32, PathBuf>,
+    groups: &[(FrameGroup, Sequence)],
     gmic_args: &[String],
-    output_directory: &PathBuf,
-) -> Result<()> {
+    output_directory: &Path,
+    retry: &RetryPolicy,
+) -> Result<usize> {
     if !output_directory.exists() {
         anyhow::bail!("Error: The specified output directory does not exist.");
     }
 
-    if images.is_empty() {
+    let total: usize = groups.iter().map(|(group, _)| group.frames.len()).sum();
+    if total == 0 {
         anyhow::bail!("No valid images provided.");
     }
 
-    debug!("Found {} images. Starting processing...", images.len());
+    debug!("Found {} images. Starting processing...", total);
     debug!("GMIC arguments: {:?}", gmic_args);
     debug!("Output directory: {:?}", output_directory);
 
-    let gmic_args_ref: Vec<&str> = gmic_args.iter().map(String::as_str).collect();
-    process_all_images(images, output_directory, &gmic_args_ref)
+    let processed = process_all_images(groups, output_directory, gmic_args, retry)
         .context("Failed to process all images")?;
 
     debug!("All images processed successfully!");
 
-    Ok(())
+    Ok(processed)
 }
 
 /// Processes a collection of images with specified GMIC arguments and outputs them to a target directory.
@@ -57,41 +67,54 @@ pub fn image_processing(
 /// and saves the results to the output directory while tracking progress and handling interruptions.
 ///
 /// # Parameters
-/// - `images`: A map of image numbers to their respective file paths.
+/// - `groups`: Maps of image numbers to their respective file paths, per input directory,
+///   with the sequence of that directory.
 /// - `output_dir`: The directory where processed images will be saved.
-/// - `gmic_args`: Command-line arguments to be used for GMIC processing.
+/// - `gmic_args`: Command-line arguments to be used for GMIC processing, possibly with
+///   placeholders.
+/// - `retry`: How often a failed image is processed again, on its own.
 ///
 /// # Returns
-/// - `Result<()>`: Indicates success or failure of the entire processing operation.
+/// - `Result<usize>`: The number of images in the output directory, or an error if any
+///   image failed or processing was interrupted.
 ///
 /// # Notes
 /// - The function supports handling of interrupts (Ctrl+C) to stop processing prematurely.
+/// - Every image is attempted even if one fails; the failures are reported together.
 /// - A progress bar tracks the processing of each image.
-/// - Each image is processed using the provided GMIC tool arguments.
-/// - Output filenames follow the format: `image_{number}{extension}`.
+/// - Each image is processed using the provided GMIC tool arguments, with `{frame}`, `{t}`
+///   and `{total}` replaced for that image; see `template::substitute`.
+/// - Output filenames follow the format: `image_{number}{extension}`, in the subdirectory
+///   of `output_dir` matching the input directory of the image; the number is padded to
+///   four digits, or as many as the input names or the largest number have.
 /// - If an error occurs during image processing, it is logged and processing continues with the next image.
+/// - The images are handed to G'MIC `BATCH_SIZE` at a time, see `engine::process_batch`.
+/// - With a seed set, see `fxp_output::set_seed`, each image's arguments start with
+///   `srand` and a seed of its own, so G'MIC's random commands such as `noise` give the
+///   same image on every run.
+/// - Images whose input and resolved GMIC arguments are unchanged since the last run into
+///   the same output directory are skipped, see `fxp_cache::Cache`.
 fn process_all_images(
-    images: &BTreeMap<u32, PathBuf>,
+    groups: &[(FrameGroup, Sequence)],
     output_dir: &Path,
-    gmic_args: &[&str],
-) -> Result<()> {
+    gmic_args: &[String],
+    retry: &RetryPolicy,
+) -> Result<usize> {
+    let total: usize = groups.iter().map(|(group, _)| group.frames.len()).sum();
     debug!(
         "Processing {} images to output directory: {:?}",
-        images.len(),
-        output_dir
+        total, output_dir
     );
     debug!("GMIC arguments: {:?}", gmic_args);
 
-    let running = Arc::new(AtomicBool::new(true));
-    let r = Arc::clone(&running);
+    let running = running_flag()?;
 
-    ctrlc::set_handler(move || {
-        warn!("Interrupt signal received. Stopping image processing...");
-        r.store(false, Ordering::SeqCst);
-    })
-    .context("Error setting Ctrl+C handler")?;
+    let mut cache = Cache::open(output_dir, Modes::Gmicer)?;
+    let mut cached = 0;
+    let mut failed = 0;
+    let mut interrupted = false;
 
-    let pb = ProgressBar::new(images.len() as u64);
+    let pb = progress_bar(total as u64);
     pb.set_style(
         ProgressStyle::default_bar()
             .template(
@@ -100,16 +123,20 @@ fn process_all_images(
             .unwrap(),
     );
 
-    for (index, (image_number, image_path)) in images.iter().enumerate() {
-        if !running.load(Ordering::SeqCst) {
-            warn!(
-                "Processing interrupted by user at image {}. Exiting...",
-                index + 1
-            );
-            break;
-        }
-
-        debug!("Processing image {}: {:?}", image_number, image_path);
+    let images = groups.iter().flat_map(|(group, sequence)| {
+        let directory = output_dir.join(&group.relative);
+        // Numbers are written as wide as the input pads them, or as the largest needs.
+        let padding = FramePadding::detect(group.frames.values().map(PathBuf::as_path)).max(
+            FramePadding::for_count(group.frames.keys().max().copied().unwrap_or(0) as u64),
+        );
+        group
+            .frames
+            .iter()
+            .map(move |(number, path)| (number, path, directory.clone(), *sequence, padding))
+    });
+    let mut pending: Vec<(u32, GmicJob, String)> = Vec::new();
+    for (image_number, image_path, directory, sequence, padding) in images {
+        debug!("Preparing image {}: {:?}", image_number, image_path);
 
         let extension = image_path
             .extension()
@@ -118,73 +145,107 @@ fn process_all_images(
 
         debug!("File extension for image {}: {}", image_number, extension);
 
-        let output_file = output_dir.join(format!("image_{:04}.{}", image_number, extension));
+        if !directory.exists() {
+            fs::create_dir_all(&directory)
+                .with_context(|| format!("Failed to create output directory {:?}", directory))?;
+        }
+        let output_file =
+            directory.join(padding.file_name("image", *image_number as u64, extension));
 
         debug!(
             "Output file path for image {}: {:?}",
             image_number, output_file
         );
 
-        if let Err(e) = process_image(image_path, &output_file, gmic_args) {
-            warn!("Error processing image {}: {:?}", image_number, e);
+        let mut image_args = template::substitute(gmic_args, *image_number, sequence);
+        if let Some(seed) = seed() {
+            // G'MIC keeps integers exact up to 2^53, so its seed is cut to 32 bits.
+            let image_seed = seed.derive(*image_number as u64).value() >> 32;
+            image_args.splice(0..0, ["srand".to_string(), image_seed.to_string()]);
+        }
+        let key = cache.key(&[image_path.as_path()], &image_args)?;
+        if cache.is_fresh(&output_file, &key) {
+            debug!("Image {} is unchanged, skipping", image_number);
+            cached += 1;
+            pb.inc(1);
+            continue;
         }
 
-        pb.inc(1);
-        debug!("Finished processing image {}", image_number);
+        pending.push((
+            *image_number,
+            GmicJob {
+                input: image_path.clone(),
+                output: output_file,
+                args: image_args,
+            },
+            key,
+        ));
     }
 
-    pb.finish_with_message("Processing complete!");
-    debug!("All images processed successfully!");
+    let mut done = cached;
+    for batch in pending.chunks(BATCH_SIZE) {
+        if !running.load(Ordering::SeqCst) {
+            warn!(
+                "Processing interrupted by user at image {}. Exiting...",
+                done + 1
+            );
+            interrupted = true;
+            break;
+        }
 
-    Ok(())
-}
+        let jobs: Vec<GmicJob> = batch.iter().map(|(_, job, _)| job.clone()).collect();
+        for ((image_number, job, key), result) in batch.iter().zip(process_batch(&jobs)) {
+            let result = retry.resume(
+                &format!("Processing image {}", image_number),
+                result,
+                || process_one(job),
+                GmicerError::is_permanent,
+            );
+            match result {
+                Ok(()) => cache.record(&job.output, key.clone())?,
+                Err(e) => {
+                    // Without gmic every image fails the same way, so stop at the first.
+                    if matches!(
+                        e.downcast_ref::<GmicerError>(),
+                        Some(GmicerError::ToolMissing(_))
+                    ) {
+                        cache.save()?;
+                        return Err(e);
+                    }
+                    warn!("Error processing image {}: {:?}", image_number, e);
+                    failed += 1;
+                }
+            }
+            debug!("Finished processing image {}", image_number);
+        }
 
-/// Runs GMIC command on a single image file, suppressing output.
-///
-/// This function executes a GMIC command with specified arguments on a given image file.
-///
-/// # Parameters
-/// - `input`: Input image path as a `Path` reference.
-/// - `output`: Output image path as a `Path` reference.
-/// - `gmic_args`: Slice of GMIC command arguments as strings.
-///
-/// # Returns
-/// - `Result<()>`: Returns `Ok(())` on successful processing, or an error if processing fails.
-///
-/// # Notes
-/// - Suppresses both `stdout` and `stderr` during command execution.
-/// - Does not handle GMIC installation or setup; assumes GMIC is already available in the system PATH.
-fn process_image(input: &Path, output: &Path, gmic_args: &[&str]) -> Result<()> {
-    // Debug: Print the input and output paths
+        done += batch.len();
+        pb.inc(batch.len() as u64);
+    }
+
+    pb.finish_with_message("Processing complete!");
+    cache.save()?;
+    if interrupted {
+        return Err(GmicerError::Interrupted.into());
+    }
+    if failed > 0 {
+        return Err(GmicerError::ToolFailed {
+            tool: "gmic".to_string(),
+            reason: format!("{} of {} images failed to process", failed, total),
+        }
+        .into());
+    }
     debug!(
-        "Processing image: input = {:?}, output = {:?}",
-        input, output
+        "All images processed successfully! {} unchanged images were skipped.",
+        cached
     );
 
-    // Debug: Print the GMIC arguments being used
-    debug!("GMIC arguments: {:?}", gmic_args);
-
-    // Run the GMIC command
-    let status = StdCommand::new("gmic")
-        .arg(input)
-        .args(gmic_args)
-        .arg("-output")
-        .arg(output)
-        .stdout(std::process::Stdio::null()) // Suppress stdout
-        .stderr(std::process::Stdio::null()) // Suppress stderr
-        .status()
-        .with_context(|| format!("Failed to execute GMIC command for input: {:?}", input))?;
-
-    // Debug: Print the status of the GMIC command
-    debug!("GMIC command executed with status: {}", status);
-
-    if !status.success() {
-        // Return an error if the GMIC command failed
-        anyhow::bail!("GMIC command failed for input: {:?}", input);
-    } else {
-        // Debug: Print a success message if the GMIC command succeeded
-        debug!("Successfully processed image: {:?}", input);
-    }
+    Ok(total)
+}
 
-    Ok(())
+/// Processes a single image on its own, as when it is retried.
+fn process_one(job: &GmicJob) -> Result<()> {
+    process_batch(std::slice::from_ref(job))
+        .pop()
+        .unwrap_or(Ok(()))
 }
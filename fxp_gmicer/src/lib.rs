@@ -1,4 +1,10 @@
+mod engine;
+mod error;
 mod gmicer;
 mod image;
+mod preset;
+mod template;
 
+pub use error::GmicerError;
 pub use gmicer::Gmicer;
+pub use preset::{find_preset, presets, Preset};
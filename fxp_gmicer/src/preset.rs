@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The presets shipped with the crate: name, description and GMIC arguments.
+const BUILTIN_PRESETS: [(&str, &str, &str); 8] = [
+    (
+        "cartoon",
+        "flat colors with dark outlines",
+        "cartoon 3,200,20,0.25,1.5,8",
+    ),
+    ("glow", "soft bloom around highlights", "glow 1.5%"),
+    (
+        "noir",
+        "high contrast black and white",
+        "luminance adjust_colors 0,40,0,0,0 normalize 0,255",
+    ),
+    (
+        "oldfilm",
+        "faded sepia print with grain and dark corners",
+        "old_photo noise 4 vignette 70,70,95",
+    ),
+    ("sepia", "warm brown monochrome", "sepia"),
+    (
+        "sketch",
+        "black and white pencil drawing",
+        "pencilbw 0.3,60",
+    ),
+    (
+        "vhs",
+        "smeared, noisy and washed-out tape look",
+        "blur_x 2 noise 6 adjust_colors 0,-10,0,0,-25 sharpen 40",
+    ),
+    ("vignette", "darkened corners", "vignette 80,60,100"),
+];
+
+/// A named bundle of GMIC arguments applying a common look.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<String>,
+}
+
+impl Preset {
+    /// Creates a preset from its arguments written as one string, e.g. `blur 3 sharpen 40`.
+    ///
+    /// # Notes
+    /// - The arguments are split on whitespace; an argument holding spaces cannot be given.
+    pub fn new(name: &str, description: &str, args: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            args: args.split_whitespace().map(String::from).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<10} {} ({})",
+            self.name,
+            self.description,
+            self.args.join(" ")
+        )
+    }
+}
+
+/// Returns every preset available, built in or from the configuration.
+///
+/// # Parameters
+/// - `custom`: The presets of the configuration, mapping a name to its GMIC arguments.
+///
+/// # Returns
+/// - `Vec<Preset>`: The presets ordered by name.
+///
+/// # Notes
+/// - A configured preset with the name of a built-in one replaces it.
+pub fn presets(custom: &BTreeMap<String, String>) -> Vec<Preset> {
+    let mut presets: BTreeMap<String, Preset> = BUILTIN_PRESETS
+        .iter()
+        .map(|(name, description, args)| (name.to_string(), Preset::new(name, description, args)))
+        .collect();
+    for (name, args) in custom {
+        presets.insert(
+            name.clone(),
+            Preset::new(name, "from the configuration", args),
+        );
+    }
+    presets.into_values().collect()
+}
+
+/// Finds a preset by name.
+///
+/// # Parameters
+/// - `name`: The name of the preset, e.g. `oldfilm`.
+/// - `custom`: The presets of the configuration, see `presets`.
+///
+/// # Returns
+/// - `Result<Preset>`: The preset, or an error listing every available preset.
+pub fn find_preset(name: &str, custom: &BTreeMap<String, String>) -> Result<Preset> {
+    let presets = presets(custom);
+    presets
+        .iter()
+        .find(|preset| preset.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let listing: Vec<String> = presets
+                .iter()
+                .map(|preset| format!("  {}", preset))
+                .collect();
+            anyhow!(
+                "Unknown preset '{}'; the available presets are:\n{}",
+                name,
+                listing.join("\n")
+            )
+        })
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Replaced by the number of the image being processed.
+pub const FRAME_PLACEHOLDER: &str = "{frame}";
+/// Replaced by the position of the image in its sequence, from 0 at the first to 1 at the last.
+pub const TIME_PLACEHOLDER: &str = "{t}";
+/// Replaced by the number of images in the sequence.
+pub const TOTAL_PLACEHOLDER: &str = "{total}";
+
+const PLACEHOLDERS: [&str; 3] = [FRAME_PLACEHOLDER, TIME_PLACEHOLDER, TOTAL_PLACEHOLDER];
+
+/// The frame numbers of one input directory, which the placeholders are resolved against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sequence {
+    pub first: u32,
+    pub last: u32,
+    pub total: usize,
+}
+
+impl Sequence {
+    /// Returns the sequence of the mapped images, or `None` if there are none.
+    pub fn of(frames: &BTreeMap<u32, PathBuf>) -> Option<Self> {
+        Some(Self {
+            first: *frames.keys().next()?,
+            last: *frames.keys().next_back()?,
+            total: frames.len(),
+        })
+    }
+
+    /// Returns where `frame` lies between the first and last frame, from 0 to 1.
+    ///
+    /// # Notes
+    /// - The position follows the frame numbers, so a gap in the numbering is a gap in
+    ///   time too, and a selection of frames resolves to the same values as a full run.
+    pub fn position(&self, frame: u32) -> f64 {
+        if self.last <= self.first {
+            return 0.0;
+        }
+        (frame.saturating_sub(self.first) as f64 / (self.last - self.first) as f64).min(1.0)
+    }
+}
+
+/// Returns whether any argument holds a placeholder, so the arguments differ per image.
+pub fn has_placeholders(gmic_args: &[String]) -> bool {
+    gmic_args.iter().any(|arg| {
+        PLACEHOLDERS
+            .iter()
+            .any(|placeholder| arg.contains(placeholder))
+    })
+}
+
+/// Resolves the placeholders of the GMIC arguments for one image.
+///
+/// # Parameters
+/// - `gmic_args`: The arguments as given, with `{frame}`, `{t}` and `{total}` placeholders.
+/// - `frame`: The number of the image.
+/// - `sequence`: The sequence the image belongs to.
+///
+/// # Returns
+/// - `Vec<String>`: The arguments with every placeholder replaced.
+///
+/// # Notes
+/// - Other braces are left to GMIC, which evaluates `{expression}` itself; so
+///   `blur {10*{t}}` blurs from 0 at the first image to 10 at the last.
+/// - `{t}` is written with up to six decimals.
+pub fn substitute(gmic_args: &[String], frame: u32, sequence: Sequence) -> Vec<String> {
+    let position = format!("{:.6}", sequence.position(frame));
+    let position = position.trim_end_matches('0').trim_end_matches('.');
+    gmic_args
+        .iter()
+        .map(|arg| {
+            arg.replace(FRAME_PLACEHOLDER, &frame.to_string())
+                .replace(TIME_PLACEHOLDER, position)
+                .replace(TOTAL_PLACEHOLDER, &sequence.total.to_string())
+        })
+        .collect()
+}
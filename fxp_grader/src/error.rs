@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+use fxp_output::FailureKind;
+
+/// Failures of the Grader that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum GraderError {
+    /// The input does not exist or is not what the Grader takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Grading interrupted by user")]
+    Interrupted,
+}
+
+impl GraderError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            GraderError::InvalidInput(_) => FailureKind::InvalidInput,
+            GraderError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use image::{DynamicImage, RgbImage, RgbaImage};
+
+use crate::keyframes::Keyframes;
+
+/// Rec. 709 weights of red, green and blue in the luma.
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Share by which a temperature of 1 raises red and lowers blue.
+const TEMPERATURE_SHIFT: f32 = 0.2;
+
+/// The color controls of one frame; 0 leaves the frame unchanged for each of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Grade {
+    /// Brightness change in stops; 1 doubles the light.
+    pub exposure: f32,
+    /// Contrast change around middle grey, from -1 (flat) to 1 (twice as steep).
+    pub contrast: f32,
+    /// Saturation change, from -1 (black and white) to 1 (twice as saturated).
+    pub saturation: f32,
+    /// Color temperature change, from -1 (cooler, bluer) to 1 (warmer, redder).
+    pub temperature: f32,
+}
+
+impl Grade {
+    /// Returns whether the grade leaves every frame unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Grade::default()
+    }
+
+    /// Returns the image graded, with 8 bits per channel.
+    ///
+    /// # Notes
+    /// - Exposure scales the light in linear space; temperature, contrast and saturation
+    ///   then act on the encoded values, in that order.
+    /// - The alpha channel is kept, and an image without one stays without one.
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let tables = self.tables();
+        if image.color().has_alpha() {
+            let mut pixels: RgbaImage = image.to_rgba8();
+            for pixel in pixels.pixels_mut() {
+                self.grade_pixel(&tables, &mut pixel.0);
+            }
+            DynamicImage::ImageRgba8(pixels)
+        } else {
+            let mut pixels: RgbImage = image.to_rgb8();
+            for pixel in pixels.pixels_mut() {
+                self.grade_pixel(&tables, &mut pixel.0);
+            }
+            DynamicImage::ImageRgb8(pixels)
+        }
+    }
+
+    /// Builds the per-channel tables of exposure, temperature and contrast, from 0 to 1.
+    fn tables(&self) -> [[f32; 256]; 3] {
+        let gain = 2f32.powf(self.exposure);
+        let channel_gain = [
+            1.0 + TEMPERATURE_SHIFT * self.temperature,
+            1.0,
+            1.0 - TEMPERATURE_SHIFT * self.temperature,
+        ];
+        let mut tables = [[0.0; 256]; 3];
+        for (table, channel_gain) in tables.iter_mut().zip(channel_gain) {
+            for (level, entry) in table.iter_mut().enumerate() {
+                let linear = to_linear(level as f32 / 255.0) * gain;
+                let encoded = from_linear(linear.clamp(0.0, 1.0)) * channel_gain;
+                *entry = (encoded - 0.5) * (1.0 + self.contrast) + 0.5;
+            }
+        }
+        tables
+    }
+
+    /// Grades the red, green and blue levels of one pixel, leaving any alpha as it is.
+    fn grade_pixel(&self, tables: &[[f32; 256]; 3], pixel: &mut [u8]) {
+        let mut rgb = [0.0; 3];
+        for (channel, value) in rgb.iter_mut().enumerate() {
+            *value = tables[channel][pixel[channel] as usize];
+        }
+        let luma: f32 = rgb
+            .iter()
+            .zip(LUMA)
+            .map(|(value, weight)| value * weight)
+            .sum();
+        for (level, value) in pixel.iter_mut().zip(rgb) {
+            let saturated = luma + (value - luma) * (1.0 + self.saturation);
+            *level = (saturated.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+}
+
+/// Decodes an sRGB value to linear light.
+fn to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes linear light as an sRGB value.
+fn from_linear(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The color controls of a sequence, each constant or keyframed across the frames.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grading {
+    pub exposure: Keyframes,
+    pub contrast: Keyframes,
+    pub saturation: Keyframes,
+    pub temperature: Keyframes,
+}
+
+impl Grading {
+    /// Returns the grade of `frame`, interpolating the keyframed controls.
+    pub fn at(&self, frame: u32) -> Grade {
+        Grade {
+            exposure: self.exposure.value_at(frame),
+            contrast: self.contrast.value_at(frame),
+            saturation: self.saturation.value_at(frame),
+            temperature: self.temperature.value_at(frame),
+        }
+    }
+
+    /// Returns the controls by name, as recorded in the plan and manifest.
+    pub fn controls(&self) -> [(&'static str, &Keyframes); 4] {
+        [
+            ("exposure", &self.exposure),
+            ("contrast", &self.contrast),
+            ("saturation", &self.saturation),
+            ("temperature", &self.temperature),
+        ]
+    }
+
+    /// Checks that every value of every control lies within its range.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error naming the first control out of range: exposure must lie
+    ///   within -10 to 10 stops, the others within -1 to 1.
+    pub fn validate(&self) -> Result<()> {
+        for (name, keyframes) in self.controls() {
+            let limit = if name == "exposure" { 10.0 } else { 1.0 };
+            if let Some(value) = keyframes.values().find(|value| value.abs() > limit) {
+                anyhow::bail!(
+                    "The {} {} is out of range, expected -{} to {}",
+                    name,
+                    value,
+                    limit,
+                    limit
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use indicatif::ProgressStyle;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tracing::info_span;
+
+use fxp_cache::Cache;
+use fxp_modes::Modes;
+use fxp_output::progress_bar;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::FileOperations;
+use fxp_stream::{FrameEncoder, IccProfile};
+
+use crate::error::GraderError;
+use crate::grade::Grading;
+
+/// Struct responsible for color grading a directory of frames natively.
+pub struct Grader {
+    input_directory: PathBuf,
+    input_files: BTreeMap<u32, PathBuf>,
+    output_directory: PathBuf,
+    /// The color controls, constant or keyframed by frame number; `new` leaves every
+    /// control at 0, which copies the frames unchanged.
+    pub grading: Grading,
+    /// The format the graded frames are written in; `new` keeps the format of the input
+    /// frames.
+    pub encoder: FrameEncoder,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+}
+
+impl Grader {
+    /// Creates a new `Grader` instance for a directory of frames.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the graded frames; defaults to `<input>_graded`.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: New `Grader` instance on success, or an error if validation fails.
+    ///
+    /// # Notes
+    /// - Creates the output directory if it does not exist.
+    /// - The frames are numbered by their filenames, see `FileOperations::load_files`;
+    ///   keyframes refer to these numbers.
+    pub fn new(
+        input_directory: String,
+        output_directory: Option<String>,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        debug!("Initializing new Grader instance with:");
+        debug!("- Input directory: {}", input_directory);
+        debug!("- Output directory: {:?}", output_directory);
+
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+        debug!("Found {} input files for processing", input_files.len());
+
+        let mode: Modes = Modes::Grader;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Grader(grader_output) => grader_output
+                .create_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Grader mode"),
+        };
+        debug!("Output directory created at: {:?}", output_directory_path);
+
+        Ok(Self {
+            input_directory: input_directory_path,
+            input_files,
+            output_directory: output_directory_path,
+            grading: Grading::default(),
+            encoder: FrameEncoder::default(),
+            in_place: false,
+        })
+    }
+
+    /// Resolves what `new` and `grade` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the graded frames.
+    /// - `grading`: The color controls to apply.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails or a control
+    ///   is out of range.
+    pub fn plan(
+        input_directory: String,
+        output_directory: Option<String>,
+        grading: &Grading,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        grading.validate()?;
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+
+        let mode: Modes = Modes::Grader;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Grader(grader_output) => grader_output
+                .plan_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Grader mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Grader)
+            .entry("input directory", input_directory_path.display())
+            .entry("frames", input_files.len());
+        for (name, keyframes) in grading.controls() {
+            plan = plan.entry(name, keyframes);
+        }
+        Ok(plan
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
+}
+
+/// Checks that the input is a directory and canonicalizes it.
+fn canonical_input_directory(input_directory: &str) -> Result<PathBuf> {
+    let input_directory_path = PathBuf::from(input_directory);
+    if !input_directory_path.is_dir() {
+        return Err(GraderError::InvalidInput(format!(
+            "Input directory '{}' does not exist or is not a directory",
+            input_directory_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_directory_path).with_context(|| {
+        format!(
+            "Failed to resolve input directory '{}'",
+            input_directory_path.display()
+        )
+    })
+}
+
+/// Maps the frames of the input directory by their frame number.
+fn load_frames(input_directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let input_images: Vec<PathBuf> = fs::read_dir(input_directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    Ok(Modes::Grader.load_files(&input_images)?)
+}
+
+impl Grader {
+    /// Grades every frame and writes it under its own name into the output.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of graded frames, or an error if a control is out of
+    ///   range, a frame cannot be decoded or written, or processing was interrupted.
+    ///
+    /// # Notes
+    /// - Each frame gets the grade of its frame number, see `Grading::at`.
+    /// - Frames whose input and grade are unchanged since the last run into the same
+    ///   output directory are skipped, see `fxp_cache::Cache`.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are graded, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - With a format in `encoder`, the frames are written in it, named with its
+    ///   extension.
+    /// - Writes a `manifest.json` recording the controls and the input hashes.
+    pub fn grade(&self) -> Result<usize> {
+        self.grading.validate()?;
+        self.encoder.validate().map_err(GraderError::InvalidInput)?;
+        let _span = info_span!(
+            "grader",
+            input = %self.input_directory.display(),
+            output = %self.output_directory.display()
+        )
+        .entered();
+
+        let mut manifest =
+            Manifest::new(Modes::Grader).parameter("input", self.input_directory.display());
+        for (name, keyframes) in self.grading.controls() {
+            if *keyframes != Default::default() {
+                manifest = manifest.parameter(name, keyframes);
+            }
+        }
+        if let Some(format) = self.encoder.format {
+            manifest = manifest
+                .parameter("output format", format)
+                .parameter("quality", self.encoder.quality)
+                .parameter("lossless", self.encoder.lossless);
+        }
+        let manifest = manifest.inputs(self.input_files.values());
+
+        let running = running_flag()?;
+
+        let pb = progress_bar(self.input_files.len() as u64);
+        pb.set_style(ProgressStyle::default_bar().template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+        )?);
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let mut cache = Cache::open(staged.path(), Modes::Grader)?;
+
+        for (number, frame) in &self.input_files {
+            if !running.load(Ordering::SeqCst) {
+                pb.abandon();
+                cache.save()?;
+                return Err(GraderError::Interrupted.into());
+            }
+
+            let file_name = frame
+                .file_name()
+                .with_context(|| format!("Frame {:?} has no filename", frame))?;
+            let target = self.encoder.output_path(&staged.path().join(file_name));
+            let grade = self.grading.at(*number);
+            let mut parameters = vec![
+                grade.exposure.to_string(),
+                grade.contrast.to_string(),
+                grade.saturation.to_string(),
+                grade.temperature.to_string(),
+            ];
+            parameters.extend(self.encoder.cache_parameter());
+            let key = cache.key(&[frame.as_path()], &parameters)?;
+            if cache.is_fresh(&target, &key) {
+                debug!("Frame {} is unchanged, skipping", number);
+                pb.inc(1);
+                continue;
+            }
+
+            let _frame_span = info_span!("grade", frame = number).entered();
+            let image = image::open(frame)
+                .with_context(|| format!("Failed to decode frame {}", frame.display()))?;
+            let graded = if grade.is_identity() {
+                image
+            } else {
+                grade.apply(&image)
+            };
+            self.encoder
+                .save(&graded, IccProfile::read(frame).as_ref(), &target)
+                .with_context(|| format!("Failed to write graded frame {}", target.display()))?;
+            cache.record(&target, key)?;
+            pb.inc(1);
+        }
+        pb.finish_with_message("Done");
+        cache.save()?;
+        debug!("Graded {} frames", self.input_files.len());
+
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Grader, self.input_files.len())?;
+
+        Ok(self.input_files.len())
+    }
+}
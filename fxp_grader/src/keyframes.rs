@@ -0,0 +1,105 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A value that is constant, or moves between values set at given frame numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    /// The values by frame number, ordered and without duplicate frames; a single
+    /// keyframe is a constant.
+    points: Vec<(u32, f32)>,
+}
+
+impl Keyframes {
+    /// Returns a value that is the same on every frame.
+    pub fn constant(value: f32) -> Self {
+        Self {
+            points: vec![(0, value)],
+        }
+    }
+
+    /// Returns the value on `frame`.
+    ///
+    /// # Notes
+    /// - Between two keyframes, the value is interpolated linearly.
+    /// - Before the first and after the last keyframe, it holds their value.
+    pub fn value_at(&self, frame: u32) -> f32 {
+        let next = self.points.partition_point(|(number, _)| *number <= frame);
+        match (
+            next.checked_sub(1).map(|i| self.points[i]),
+            self.points.get(next),
+        ) {
+            (Some((from, start)), Some(&(to, end))) => {
+                let t = (frame - from) as f32 / (to - from) as f32;
+                start + (end - start) * t
+            }
+            (Some((_, value)), None) | (None, Some(&(_, value))) => value,
+            (None, None) => 0.0,
+        }
+    }
+
+    /// Returns the values of the keyframes.
+    pub fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.points.iter().map(|(_, value)| *value)
+    }
+}
+
+impl Default for Keyframes {
+    fn default() -> Self {
+        Self::constant(0.0)
+    }
+}
+
+impl FromStr for Keyframes {
+    type Err = String;
+
+    /// Parses a constant such as `0.5`, or keyframes such as `1:0,120:1.5` giving the
+    /// value at each frame number.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |part: &str| {
+            format!(
+                "Invalid value '{}', expected a number or keyframes such as 1:0,120:1.5",
+                part
+            )
+        };
+        if !s.contains(':') {
+            let value: f32 = s.trim().parse().map_err(|_| invalid(s))?;
+            if !value.is_finite() {
+                return Err(invalid(s));
+            }
+            return Ok(Self::constant(value));
+        }
+
+        let mut points = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (frame, value) = part.split_once(':').ok_or_else(|| invalid(part))?;
+            let frame: u32 = frame.trim().parse().map_err(|_| invalid(part))?;
+            let value: f32 = value.trim().parse().map_err(|_| invalid(part))?;
+            if !value.is_finite() {
+                return Err(invalid(part));
+            }
+            points.push((frame, value));
+        }
+        points.sort_by_key(|(frame, _)| *frame);
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("Frame {} has more than one keyframe", pair[0].0));
+        }
+        if points.is_empty() {
+            return Err(invalid(s));
+        }
+        Ok(Self { points })
+    }
+}
+
+impl fmt::Display for Keyframes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [(_, value)] = self.points.as_slice() {
+            return write!(f, "{}", value);
+        }
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(frame, value)| format!("{}:{}", frame, value))
+            .collect();
+        write!(f, "{}", points.join(","))
+    }
+}
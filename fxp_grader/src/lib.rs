@@ -0,0 +1,9 @@
+mod error;
+mod grade;
+mod grader;
+mod keyframes;
+
+pub use error::GraderError;
+pub use grade::{Grade, Grading};
+pub use grader::Grader;
+pub use keyframes::Keyframes;
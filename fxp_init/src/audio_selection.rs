@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use console::Term;
+use dialoguer::Select;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::media_duration::media_duration;
+
+/// The file chosen per directory, so a directory is only chosen from once per run.
+static CHOSEN: Mutex<Option<HashMap<PathBuf, PathBuf>>> = Mutex::new(None);
+
+/// Which audio file to use when a directory holds several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioSelection {
+    /// The first by file name.
+    #[default]
+    Alphabetical,
+    /// The most recently modified.
+    Newest,
+    /// The longest, as ffprobe reports it.
+    Longest,
+    /// Ask which one, on a terminal; alphabetical otherwise.
+    Prompt,
+}
+
+impl FromStr for AudioSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "alphabetical" => Ok(AudioSelection::Alphabetical),
+            "newest" => Ok(AudioSelection::Newest),
+            "longest" => Ok(AudioSelection::Longest),
+            "prompt" => Ok(AudioSelection::Prompt),
+            other => Err(format!(
+                "Unknown audio selection '{}', expected alphabetical, newest, longest or prompt",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AudioSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AudioSelection::Alphabetical => "alphabetical",
+            AudioSelection::Newest => "newest",
+            AudioSelection::Longest => "longest",
+            AudioSelection::Prompt => "prompt",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl AudioSelection {
+    /// Chooses one of the audio files found in a directory.
+    ///
+    /// # Parameters
+    /// - `directory`: The directory the files were found in.
+    /// - `candidates`: The audio files in it, at least one.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The chosen file, or an error if the prompt fails.
+    ///
+    /// # Notes
+    /// - The choice is logged at info level when there was one to make, and remembered
+    ///   for the rest of the run, so the audio path and its duration, resolved one after
+    ///   the other, always name the same file and the prompt is shown only once.
+    /// - Ties, and files whose date or duration cannot be read, fall back to the
+    ///   alphabetical order.
+    pub(crate) fn choose(self, directory: &Path, mut candidates: Vec<PathBuf>) -> Result<PathBuf> {
+        let mut chosen = CHOSEN.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(path) = chosen.get_or_insert_with(HashMap::new).get(directory) {
+            return Ok(path.clone());
+        }
+
+        candidates.sort();
+        let path = match candidates.len() {
+            0 => unreachable!("choose is only called with candidates"),
+            1 => candidates.remove(0),
+            _ => {
+                let path = self.choose_among(candidates)?;
+                info!(
+                    "Chose audio file {} in {} ({})",
+                    path.display(),
+                    directory.display(),
+                    self
+                );
+                path
+            }
+        };
+        chosen
+            .get_or_insert_with(HashMap::new)
+            .insert(directory.to_path_buf(), path.clone());
+        Ok(path)
+    }
+
+    /// Picks one of several candidates, sorted by name.
+    fn choose_among(self, mut candidates: Vec<PathBuf>) -> Result<PathBuf> {
+        match self {
+            AudioSelection::Alphabetical => {}
+            AudioSelection::Newest => {
+                // A stable sort keeps the alphabetical order among equal dates.
+                candidates.sort_by_key(|path| {
+                    Reverse(fs::metadata(path).and_then(|m| m.modified()).ok())
+                });
+            }
+            AudioSelection::Longest => {
+                candidates.sort_by_key(|path| {
+                    let duration = media_duration(&path.to_string_lossy());
+                    if let Err(e) = &duration {
+                        debug!("Could not read the duration of {:?}: {:#}", path, e);
+                    }
+                    Reverse(duration.ok())
+                });
+            }
+            AudioSelection::Prompt if Term::stderr().is_term() => {
+                let names: Vec<String> = candidates
+                    .iter()
+                    .map(|path| {
+                        path.file_name()
+                            .unwrap_or(path.as_os_str())
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                    .collect();
+                let index = Select::new()
+                    .with_prompt("Several audio files found, which one should be used?")
+                    .items(&names)
+                    .default(0)
+                    .interact()
+                    .context("Failed to ask which audio file to use")?;
+                return Ok(candidates.swap_remove(index));
+            }
+            AudioSelection::Prompt => {
+                warn!("Not on a terminal, choosing the audio file alphabetically");
+            }
+        }
+        Ok(candidates.remove(0))
+    }
+}
@@ -3,19 +3,117 @@ use dialoguer::Input;
 use log::debug;
 use log::warn;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use fxp_output::FrameRate;
+
+use crate::audio_selection::AudioSelection;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "ConfigFile")]
 pub struct Config {
     /// Optional AUDIO path
     pub audio_path: Option<String>,
-    /// Frames per second
-    pub fps: u32,
+    /// Frames per second, e.g. `30`, or `"29.97"` and `"30000/1001"` for NTSC rates
+    pub fps: FrameRate,
     /// Upper limit for pixels
     pub pixel_upper_limit: u32,
     /// Number of frames to sample
     pub sampling_number: usize,
     /// Overall opacity value for merging images (0.0 - 1.0)
     pub opacity: f32,
+    /// Opacities merged or clutted in one run with `merger --multiple` and
+    /// `clutter --clut-multiple`, each into its own directory (0.0 - 1.0)
+    pub multiple_opacities: Vec<f32>,
+    /// Presets for `gmicer --preset`, mapping a name to its GMIC arguments, e.g.
+    /// `warm = "adjust_colors 0,10,0,10,20"`; a name of a built-in preset replaces it
+    pub gmic_presets: BTreeMap<String, String>,
+    /// Commands for `process --processor`, mapping a name to a command run once per frame,
+    /// e.g. `negate = "magick {input} -negate {output}"`
+    pub processors: BTreeMap<String, String>,
+    /// Frames processed at the same time by `process`, `merger` and `clutter` without
+    /// `--jobs`; `bench --save` stores the number it recommends
+    pub jobs: usize,
+    /// Times a failed per-frame call of ffmpeg or gmic is retried by `exporter`, `sampler`
+    /// and `gmicer` without `--retries`; 0 fails the run at the first failure
+    pub retries: u32,
+    /// Milliseconds waited before the first retry without `--retry-backoff`, doubled
+    /// before each further one
+    pub retry_backoff_ms: u64,
+    /// Which audio file to use when the audio path is a directory holding several:
+    /// `alphabetical`, `newest`, `longest` or `prompt`; `--audio-select` overrides it
+    pub audio_selection: AudioSelection,
+    /// Command run by the shell after a mode succeeds, e.g. an upload script; it gets the
+    /// outputs in `FXP_OUTPUTS` and the JSON summary of the run on stdin. `--on-success`
+    /// overrides it
+    pub on_success: Option<String>,
+    /// URL the JSON summary of every run is posted to once it succeeds or fails;
+    /// `--webhook` overrides it
+    pub webhook: Option<String>,
+    /// Seconds a run must take before `--notify` announces its end on the desktop;
+    /// `--notify-after` overrides it
+    pub notify_after_seconds: u64,
+}
+
+/// The configuration file as stored, including fields of older versions.
+///
+/// Older files hold the multiple opacities as `multiple_opacities_1` to `_3`; they are
+/// read into `Config::multiple_opacities` and written back as a list when saved.
+#[derive(Deserialize)]
+struct ConfigFile {
+    audio_path: Option<String>,
+    fps: FrameRate,
+    pixel_upper_limit: u32,
+    sampling_number: usize,
+    opacity: f32,
+    multiple_opacities: Option<Vec<f32>>,
+    multiple_opacities_1: Option<f32>,
+    multiple_opacities_2: Option<f32>,
+    multiple_opacities_3: Option<f32>,
+    gmic_presets: Option<BTreeMap<String, String>>,
+    processors: Option<BTreeMap<String, String>>,
+    jobs: Option<usize>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    audio_selection: Option<AudioSelection>,
+    on_success: Option<String>,
+    webhook: Option<String>,
+    notify_after_seconds: Option<u64>,
+}
+
+impl From<ConfigFile> for Config {
+    fn from(file: ConfigFile) -> Self {
+        let legacy: Vec<f32> = [
+            file.multiple_opacities_1,
+            file.multiple_opacities_2,
+            file.multiple_opacities_3,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let multiple_opacities = match file.multiple_opacities {
+            Some(opacities) => opacities,
+            None if !legacy.is_empty() => legacy,
+            None => Config::default().multiple_opacities,
+        };
+        Config {
+            audio_path: file.audio_path,
+            fps: file.fps,
+            pixel_upper_limit: file.pixel_upper_limit,
+            sampling_number: file.sampling_number,
+            opacity: file.opacity,
+            multiple_opacities,
+            gmic_presets: file.gmic_presets.unwrap_or_default(),
+            processors: file.processors.unwrap_or_default(),
+            jobs: file.jobs.unwrap_or(1),
+            retries: file.retries.unwrap_or(0),
+            retry_backoff_ms: file.retry_backoff_ms.unwrap_or(500),
+            audio_selection: file.audio_selection.unwrap_or_default(),
+            on_success: file.on_success,
+            webhook: file.webhook,
+            notify_after_seconds: file.notify_after_seconds.unwrap_or(60),
+        }
+    }
 }
 
 // Manually implement Default to set custom default values
@@ -23,10 +121,20 @@ impl Default for Config {
     fn default() -> Self {
         Config {
             audio_path: None,
-            fps: 60,                    // Adjust default FPS if needed
-            pixel_upper_limit: 480,     // Adjust default pixel limit if needed
-            sampling_number: 10,        // Adjust default sample count if needed
-            opacity: 0.5,               // Default overall opacity
+            fps: FrameRate::fps(60), // Adjust default FPS if needed
+            pixel_upper_limit: 480,  // Adjust default pixel limit if needed
+            sampling_number: 10,     // Adjust default sample count if needed
+            opacity: 0.5,            // Default overall opacity
+            multiple_opacities: vec![0.25, 0.5, 0.75],
+            gmic_presets: BTreeMap::new(),
+            processors: BTreeMap::new(),
+            jobs: 1,
+            retries: 0,
+            retry_backoff_ms: 500,
+            audio_selection: AudioSelection::default(),
+            on_success: None,
+            webhook: None,
+            notify_after_seconds: 60,
         }
     }
 }
@@ -47,7 +155,8 @@ impl Default for Config {
 ///   opacity, and multiple opacity values.
 /// - Handles user input gracefully, allowing empty values for the AUDIO path and validating
 ///   numerical inputs where necessary.
-/// - Saves the updated configuration to disk upon successful user interaction.
+/// - Saves the updated configuration to disk upon successful user interaction, unless
+///   `Config::validate` rejects a value.
 /// - Logs debug information throughout the process.
 pub fn initialize_configuration() -> Result<()> {
     debug!("Initializing configuration process started.");
@@ -72,14 +181,19 @@ pub fn initialize_configuration() -> Result<()> {
         .ok();
 
     // Prompt the user to update FPS
-    config.fps = Input::new()
+    let current_fps = config.fps.to_string();
+    let fps: String = Input::new()
         .with_prompt(format!(
-            "Enter the default FPS value (current: {})",
-            config.fps
+            "Enter the default FPS value, e.g. 30 or 29.97 (current: {})",
+            current_fps
         ))
-        .default(config.fps)
+        .default(current_fps.clone())
         .interact()
-        .unwrap_or(config.fps);
+        .unwrap_or(current_fps);
+    match fps.parse::<FrameRate>() {
+        Ok(parsed) => config.fps = parsed,
+        Err(e) => warn!("Keeping the FPS value: {}", e),
+    }
 
     // Prompt the user to update Pixel Upper Limit
     config.pixel_upper_limit = Input::new()
@@ -111,16 +225,65 @@ pub fn initialize_configuration() -> Result<()> {
         .interact()
         .unwrap_or(config.opacity);
 
-    debug!("User input received for configuration.");
+    // Prompt the user to update the multiple opacity values
+    let current_opacities = format_opacities(&config.multiple_opacities);
+    let opacities: String = Input::new()
+        .with_prompt(format!(
+            "Enter the multiple opacity values, comma separated (current: {})",
+            current_opacities
+        ))
+        .default(current_opacities.clone())
+        .interact()
+        .unwrap_or(current_opacities);
+    match parse_opacities(&opacities) {
+        Ok(parsed) => config.multiple_opacities = parsed,
+        Err(e) => warn!("Keeping the multiple opacity values: {}", e),
+    }
 
-    // Save the updated configuration using confy
-    confy::store("fxp_videoclipper", "config", &config).context("Failed to save configuration")?;
+    debug!("User input received for configuration.");
+    save_configuration(&config)
+}
 
+/// Saves a configuration, replacing the stored one.
+///
+/// # Parameters
+/// - `config`: The configuration to save, e.g. the loaded one with a value changed.
+///
+/// # Returns
+/// - `Result<()>`: An error if a value is invalid, see `Config::validate`, or the file
+///   cannot be written.
+pub fn save_configuration(config: &Config) -> Result<()> {
+    config.validate().context("Configuration not saved")?;
+    confy::store("fxp_videoclipper", "config", config).context("Failed to save configuration")?;
     debug!("Configuration saved successfully.");
-
     Ok(())
 }
 
+/// Formats opacities as a comma separated list, e.g. `0.25,0.5,0.75`.
+fn format_opacities(opacities: &[f32]) -> String {
+    opacities
+        .iter()
+        .map(|opacity| opacity.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses a comma separated list of opacities, e.g. `0.25,0.5,0.75`.
+///
+/// # Returns
+/// - `Result<Vec<f32>>`: The opacities, or an error naming the first invalid value.
+pub(crate) fn parse_opacities(list: &str) -> Result<Vec<f32>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .with_context(|| format!("Invalid opacity value '{}'", value))
+        })
+        .collect()
+}
+
 /// Loads and provides default configuration settings for the application.
 ///
 /// This function attempts to load existing configuration settings and falls
@@ -133,13 +296,23 @@ pub fn initialize_configuration() -> Result<()> {
 ///
 /// # Notes
 /// - If configuration loading fails, default values will be used.
+/// - A configuration that loads but holds invalid values is an error, listing every
+///   invalid field with its range and line; see `Config::validate`.
 pub fn load_default_configuration() -> Result<Config> {
     debug!("Default configuration loading using confy...");
 
     // Attempt to load the configuration using confy
-    match confy::load("fxp_videoclipper", "config") {
+    match confy::load::<Config>("fxp_videoclipper", "config") {
         Ok(config) => {
             debug!("Configuration successfully loaded.");
+            if let Err(invalid) = config.validate() {
+                let invalid = match confy::get_configuration_file_path("fxp_videoclipper", "config")
+                {
+                    Ok(path) => invalid.locate(&path),
+                    Err(_) => invalid,
+                };
+                return Err(invalid.into());
+            }
             Ok(config)
         }
         Err(err) => {
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+use fxp_output::ToolError;
+
+use crate::error::InitError;
+use crate::media_info::media_info;
+
+/// Returns whether an input names a video to download rather than a local path.
+pub fn is_url(input: &str) -> bool {
+    let lower = input.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Returns the directory downloaded videos are cached in.
+///
+/// # Notes
+/// - The platform's cache directory, e.g. `~/.cache/fxp_videoclipper/downloads` on
+///   Linux, or `downloads` in the system temporary directory where there is none.
+pub fn default_download_dir() -> PathBuf {
+    directories::ProjectDirs::from("", "", "fxp_videoclipper")
+        .map(|dirs| dirs.cache_dir().join("downloads"))
+        .unwrap_or_else(|| {
+            std::env::temp_dir()
+                .join("fxp_videoclipper")
+                .join("downloads")
+        })
+}
+
+/// Resolves a video input that may be a URL to a local file.
+///
+/// # Parameters
+/// - `input`: The input as given, a local path or an `http://` or `https://` URL.
+/// - `download`: Whether a URL that is not cached yet may be downloaded.
+///
+/// # Returns
+/// - `Result<String>`: `input` itself if it is not a URL, otherwise the path of the
+///   downloaded video; or an error if downloading is not allowed, fails, or does not
+///   yield a video.
+///
+/// # Notes
+/// - A URL is downloaded once into `default_download_dir`, under a name derived from the
+///   URL, and reused by later runs.
+/// - yt-dlp is used if it is installed, which handles the pages of video sites; otherwise
+///   the URL is fetched as a plain file with curl.
+/// - The download is written under a temporary name and only kept once ffprobe finds a
+///   video stream in it.
+pub fn resolve_video_url(input: &str, download: bool) -> Result<String> {
+    if !is_url(input) {
+        return Ok(input.to_string());
+    }
+
+    let key = blake3::hash(input.as_bytes()).to_hex();
+    let cache_dir = default_download_dir().join(key.as_str());
+    if let Some(cached) = cached_video(&cache_dir)? {
+        info!(
+            "Using {} downloaded earlier from {}",
+            cached.display(),
+            input
+        );
+        return Ok(cached.display().to_string());
+    }
+    if !download {
+        return Err(InitError::UrlInput(format!(
+            "{} is not downloaded yet; run without --no-download or --dry-run to download it",
+            input
+        ))
+        .into());
+    }
+
+    fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("Failed to create download directory {:?}", cache_dir))?;
+    let part_dir = cache_dir.join("part");
+    if part_dir.exists() {
+        fs::remove_dir_all(&part_dir)
+            .with_context(|| format!("Failed to remove partial download {:?}", part_dir))?;
+    }
+    fs::create_dir_all(&part_dir)
+        .with_context(|| format!("Failed to create download directory {:?}", part_dir))?;
+
+    eprintln!("Downloading {}...", input);
+    let downloaded = match download_with_yt_dlp(input, &part_dir) {
+        Err(e) if is_missing(&e) => {
+            debug!("yt-dlp is not installed, downloading {} with curl", input);
+            download_with_curl(input, &part_dir)?
+        }
+        result => result?,
+    };
+
+    let info = media_info(&downloaded.to_string_lossy())
+        .with_context(|| format!("Failed to probe the download of {}", input))?;
+    if info.video.is_none() {
+        let _ = fs::remove_dir_all(&part_dir);
+        return Err(InitError::UrlInput(format!("{} did not download to a video", input)).into());
+    }
+
+    let file_name = downloaded
+        .file_name()
+        .context("Downloaded file has no name")?;
+    let video = cache_dir.join(file_name);
+    fs::rename(&downloaded, &video)
+        .with_context(|| format!("Failed to move the download into place at {:?}", video))?;
+    let _ = fs::remove_dir_all(&part_dir);
+    info!("Downloaded {} to {}", input, video.display());
+    Ok(video.display().to_string())
+}
+
+/// Returns the video cached in a download directory, if it holds a complete one.
+fn cached_video(cache_dir: &Path) -> Result<Option<PathBuf>> {
+    if !cache_dir.is_dir() {
+        return Ok(None);
+    }
+    let video = fs::read_dir(cache_dir)
+        .with_context(|| format!("Failed to read download directory {:?}", cache_dir))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.is_file());
+    Ok(video)
+}
+
+/// Downloads with yt-dlp into `part_dir`, returning the file it wrote.
+fn download_with_yt_dlp(url: &str, part_dir: &Path) -> Result<PathBuf> {
+    let status = StdCommand::new("yt-dlp")
+        .args(["--no-playlist", "--quiet", "--no-warnings", "-o"])
+        .arg(part_dir.join("video.%(ext)s"))
+        .arg(url)
+        .status()
+        .map_err(|e| InitError::spawn("yt-dlp", e))?;
+    if !status.success() {
+        return Err(InitError::ToolFailed {
+            tool: "yt-dlp".to_string(),
+            reason: format!("{} while downloading {}", status, url),
+        }
+        .into());
+    }
+    single_file(part_dir)
+}
+
+/// Downloads with curl into `part_dir`, naming the file after the URL's last segment.
+fn download_with_curl(url: &str, part_dir: &Path) -> Result<PathBuf> {
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("video");
+    let file = part_dir.join(name);
+    let status = StdCommand::new("curl")
+        .args(["--fail", "--location", "--silent", "--show-error", "-o"])
+        .arg(&file)
+        .arg(url)
+        .status()
+        .map_err(|e| InitError::spawn("curl", e))?;
+    if !status.success() {
+        return Err(InitError::ToolFailed {
+            tool: "curl".to_string(),
+            reason: format!("{} while downloading {}", status, url),
+        }
+        .into());
+    }
+    single_file(part_dir)
+}
+
+/// Returns the one file a downloader left in `part_dir`.
+fn single_file(part_dir: &Path) -> Result<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(part_dir)
+        .with_context(|| format!("Failed to read download directory {:?}", part_dir))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    if files.len() > 1 {
+        warn!("The download left {} files, using the first", files.len());
+        files.sort();
+    }
+    files.into_iter().next().ok_or_else(|| {
+        InitError::UrlInput(format!("Nothing was downloaded to {:?}", part_dir)).into()
+    })
+}
+
+fn is_missing(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<InitError>(),
+        Some(InitError::ToolMissing(_))
+    )
+}
@@ -32,9 +32,32 @@ pub fn get_duration(
     mp3_path: Option<String>,
     duration_arg: Option<String>,
     config: &Config,
+) -> Result<u64> {
+    get_sequence_duration(&[video_path.to_string()], mp3_path, duration_arg, config)
+}
+
+/// Determines the duration to export from videos played one after the other.
+///
+/// # Parameters
+/// - `video_paths`: File paths to the videos, in the order they are played.
+/// - `mp3_path`: Optional path to the corresponding MP3 audio file.
+/// - `duration_arg`: Optional manually specified duration.
+/// - `config`: Configuration containing necessary settings.
+///
+/// # Returns
+/// - `Result<u64>`: The duration in milliseconds.
+///
+/// # Notes
+/// - Resolves the duration as `get_duration` does, with the videos' combined duration
+///   in place of a single video's.
+pub fn get_sequence_duration(
+    video_paths: &[String],
+    mp3_path: Option<String>,
+    duration_arg: Option<String>,
+    config: &Config,
 ) -> Result<u64> {
     debug!("Getting duration with parameters:");
-    debug!("  video_path: {}", video_path);
+    debug!("  video_paths: {:?}", video_paths);
     debug!("  mp3_path: {:?}", mp3_path);
     debug!("  duration_arg: {:?}", duration_arg);
 
@@ -72,18 +95,26 @@ pub fn get_duration(
                 duration
             } else {
                 debug!("MP3 duration not found. Falling back to video duration.");
-                let duration =
-                    media_duration(video_path).context("Error determining video duration")?;
+                let duration = sequence_duration(video_paths)?;
                 debug!("Video duration: {:?}", duration);
                 duration
             }
         }
     };
 
-    let final_duration = minimum_duration(calculated_duration, video_path)?;
+    let final_duration = minimum_duration(calculated_duration, sequence_duration(video_paths)?);
     Ok(final_duration)
 }
 
+/// Returns the combined duration of the videos in milliseconds.
+fn sequence_duration(video_paths: &[String]) -> Result<u64> {
+    video_paths.iter().try_fold(0, |total, video_path| {
+        let duration = media_duration(video_path)
+            .with_context(|| format!("Error determining video duration of {}", video_path))?;
+        Ok(total + duration)
+    })
+}
+
 /// Ensures the calculated duration does not exceed the actual video duration.
 ///
 /// This function compares the calculated duration with the video's actual duration
@@ -91,29 +122,26 @@ pub fn get_duration(
 ///
 /// # Parameters
 /// - `calculated_duration`: The calculated duration to validate
-/// - `video_path`: File path to the video for duration measurement
+/// - `video_duration`: The measured duration of the video
 ///
 /// # Returns
-/// - `Result<u64>`: The minimum duration value or an error
+/// - `u64`: The minimum duration value
 ///
 /// # Notes
 /// - The function returns the calculated duration if it's less than or equal to
 ///   the video duration, otherwise returns the video duration.
-pub fn minimum_duration(calculated_duration: u64, video_path: &str) -> Result<u64> {
-    let video_duration = media_duration(video_path)
-        .context("Error determining video duration in minimum_duration")?;
-
+pub fn minimum_duration(calculated_duration: u64, video_duration: u64) -> u64 {
     if calculated_duration > video_duration {
         debug!(
             "Calculated duration ({}) is greater than video duration ({}). Using video duration.",
             calculated_duration, video_duration
         );
-        Ok(video_duration)
+        video_duration
     } else {
         debug!(
             "Calculated duration ({}) is within video duration ({}).",
             calculated_duration, video_duration
         );
-        Ok(calculated_duration)
+        calculated_duration
     }
 }
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of probing media that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum InitError {
+    /// A tool is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// A URL input cannot be downloaded, or did not download to a video.
+    #[error("{0}")]
+    UrlInput(String),
+}
+
+impl InitError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            InitError::ToolMissing(_) => FailureKind::ToolMissing,
+            InitError::ToolFailed { .. } => FailureKind::ToolFailed,
+            InitError::UrlInput(_) => FailureKind::InvalidInput,
+        }
+    }
+}
+
+impl ToolError for InitError {
+    fn missing(tool: &str) -> Self {
+        InitError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        InitError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
@@ -3,13 +3,15 @@ use anyhow::{anyhow, Context, Result};
 use log::{debug, warn};
 use std::env;
 
+use fxp_output::FrameRate;
+
 use crate::literals::FXP_VIDEOCLIPPER_FPS;
 
 /// Enum to represent the source of the FPS value
 enum FpsSource {
-    CliArgument(u32),
+    CliArgument(FrameRate),
     EnvironmentVariable,
-    FromConfigFile(u32),
+    FromConfigFile(FrameRate),
 }
 
 /// Retrieves the Frames Per Second (FPS) value from multiple sources.
@@ -24,11 +26,13 @@ enum FpsSource {
 /// - `config`: Configuration struct containing the FPS value if not set elsewhere.
 ///
 /// # Returns
-/// - `Result<u32>`: The determined FPS value or an error if no sources are available.
+/// - `Result<FrameRate>`: The determined FPS value or an error if no sources are available.
 ///
 /// # Notes
 /// - If no FPS sources are provided, the function will return an error.
-pub fn get_fps(cli_fps: Option<u32>, config: &Config) -> Result<u32> {
+/// - The environment variable takes the forms `FrameRate` parses: `30`, `29.97` or
+///   `30000/1001`.
+pub fn get_fps(cli_fps: Option<FrameRate>, config: &Config) -> Result<FrameRate> {
     // Log the start of the function
     debug!("Starting to resolve FPS...");
 
@@ -39,7 +43,7 @@ pub fn get_fps(cli_fps: Option<u32>, config: &Config) -> Result<u32> {
     } else if env::var(FXP_VIDEOCLIPPER_FPS).is_ok() {
         debug!("Using FPS from FXP_VIDEOCLIPPER_FPS environment variable.");
         FpsSource::EnvironmentVariable
-    } else if config.fps > 0 {
+    } else if config.fps.numerator() > 0 {
         debug!("Using FPS from configuration file: {}", config.fps);
         FpsSource::FromConfigFile(config.fps)
     } else {
@@ -63,13 +67,12 @@ pub fn get_fps(cli_fps: Option<u32>, config: &Config) -> Result<u32> {
 /// - `fps_source`: The source from which to resolve the FPS value.
 ///
 /// # Returns
-/// - `Result<u32>`: The resolved FPS value as an unsigned 32-bit integer,
-///                    or an error if resolution fails.
+/// - `Result<FrameRate>`: The resolved FPS value, or an error if resolution fails.
 ///
 /// # Notes
 /// - Prioritizes sources in the order: CLI argument > Environment variable > Config file.
-/// - Validates and parses the FPS value to ensure it is a valid unsigned integer.
-fn resolve_fps(fps_source: FpsSource) -> Result<u32> {
+/// - Validates and parses the FPS value to ensure it is a valid frame rate.
+fn resolve_fps(fps_source: FpsSource) -> Result<FrameRate> {
     debug!("Resolving FPS value based on the provided source...");
 
     match fps_source {
@@ -81,10 +84,13 @@ fn resolve_fps(fps_source: FpsSource) -> Result<u32> {
             debug!("Searching for FPS in FXP_VIDEOCLIPPER_FPS environment variable...");
             let fps_str = env::var(FXP_VIDEOCLIPPER_FPS)
                 .context("Failed to read FXP_VIDEOCLIPPER_FPS environment variable")?;
-            let fps = fps_str.parse::<u32>().context(format!(
-                "Invalid FPS value in FXP_VIDEOCLIPPER_FPS: '{}",
-                fps_str
-            ))?;
+            let fps = fps_str
+                .parse::<FrameRate>()
+                .map_err(|e| anyhow!(e))
+                .context(format!(
+                    "Invalid FPS value in FXP_VIDEOCLIPPER_FPS: '{}'",
+                    fps_str
+                ))?;
             Ok(fps)
         }
         FpsSource::FromConfigFile(fps) => {
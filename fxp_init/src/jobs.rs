@@ -0,0 +1,36 @@
+use crate::config::Config;
+use log::debug;
+use std::env;
+
+use crate::literals::FXP_VIDEOCLIPPER_JOBS;
+
+/// Determines how many frames are processed at the same time.
+///
+/// # Parameters
+/// - `cli_jobs`: The `--jobs` given on the command line, if any.
+/// - `config`: The configuration holding the stored number.
+///
+/// # Returns
+/// - `usize`: The resolved number of jobs, at least 1.
+///
+/// # Notes
+/// - The command line takes priority, then the FXP_VIDEOCLIPPER_JOBS environment
+///   variable, then `jobs` of the configuration, as stored by `bench --save`.
+pub fn get_jobs(cli_jobs: Option<u16>, config: &Config) -> usize {
+    if let Some(jobs) = cli_jobs {
+        return usize::from(jobs).max(1);
+    }
+    match env::var(FXP_VIDEOCLIPPER_JOBS).map(|value| value.parse::<usize>()) {
+        Ok(Ok(jobs)) if jobs > 0 => {
+            debug!(
+                "Using jobs from FXP_VIDEOCLIPPER_JOBS environment variable: {}",
+                jobs
+            );
+            jobs
+        }
+        _ => {
+            debug!("Using jobs from configuration file: {}", config.jobs);
+            config.jobs.max(1)
+        }
+    }
+}
@@ -1,24 +1,39 @@
 mod audio_dir;
+mod audio_selection;
 mod config;
+mod download;
 mod duration;
+mod error;
 mod fps;
+mod jobs;
 mod literals;
 mod log_config;
 mod media_duration;
+mod media_info;
 mod mp3;
 mod opacity;
 mod pixel;
+mod retry;
 mod sampling;
+mod validate;
 
 pub use audio_dir::get_audio_dir;
+pub use audio_selection::AudioSelection;
 pub use config::initialize_configuration;
 pub use config::load_default_configuration;
+pub use config::save_configuration;
 pub use config::Config;
-pub use duration::get_duration;
+pub use download::{default_download_dir, is_url, resolve_video_url};
+pub use duration::{get_duration, get_sequence_duration};
+pub use error::InitError;
 pub use fps::get_fps;
-pub use log_config::initialize_logger;
+pub use jobs::get_jobs;
+pub use log_config::{default_log_dir, initialize_logger, LogFile, LogFormat};
 pub use media_duration::media_duration;
+pub use media_info::{media_info, AudioStream, MediaInfo, VideoStream};
 pub use mp3::{get_audio_duration, get_audio_file};
-pub use opacity::get_opacity;
-pub use pixel::get_pixel_upper_limit;
+pub use opacity::{get_multiple_opacities, get_opacity};
+pub use pixel::{get_pixel_upper_limit, get_preview_pixel_limit};
+pub use retry::get_retry_policy;
 pub use sampling::get_sampling_number;
+pub use validate::{InvalidConfig, InvalidField};
@@ -2,57 +2,120 @@ use anyhow::{Context, Result};
 use env_logger::Builder;
 use log::{debug, warn, LevelFilter};
 use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use std::fmt;
 use std::fs::{create_dir_all, read_dir, remove_file};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Mutex;
 
+/// Where the log file is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogFile {
+    /// `app.log` in the default log directory, see `default_log_dir`.
+    Default,
+    /// A file chosen by the user.
+    Path(PathBuf),
+    /// Log to the console only.
+    Disabled,
+}
+
+/// How log records are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines, colored on the console.
+    Plain,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "plain" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "Unknown log format '{}', expected one of: plain, json",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogFormat::Plain => "plain",
+            LogFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Initializes a logger with specified log level and configuration.
 ///
 /// This function sets up a logging system that includes a rolling file appender
-/// and console output. It creates a log directory, manages log file sizes, and sets
+/// and console output. It creates the log directory, manages log file sizes, and sets
 /// a global log level for the application.
 ///
 /// # Parameters
 /// - `log_level`: The level of logging to be displayed (e.g., debug, info, warn, error)
+/// - `log_file`: Where the log file is written, if anywhere
+/// - `format`: Whether records are written as plain lines or as JSON objects
 ///
 /// # Returns
 /// - `Result<()>`: Indicates successful initialization of the logger
 ///
 /// # Notes
-/// - Creates a "frames_exporter_logs" directory in the user's document directory (or "logs" in the current directory if the document directory isn't accessible)
+/// - The default log file is `app.log` in the directory returned by `default_log_dir`
 /// - Implements rolling file logging with a maximum of 2 log files
 /// - Sets a maximum file size of 5MB before rolling over to a new file
-/// - Logs are formatted with timestamp, log level, and message
+/// - Plain records hold a timestamp, log level, and message; JSON records also the target
 /// - Creates the log directory if it doesn't exist
-/// - Deletes older log files if the maximum number of files is exceeded
+/// - Deletes older log files of the default directory if the maximum number of files is exceeded
 /// - Initializes the global logger with the specified log level
 /// - Logs errors when writing to the log file fails
-pub fn initialize_logger(log_level: LevelFilter) -> Result<()> {
+pub fn initialize_logger(
+    log_level: LevelFilter,
+    log_file: LogFile,
+    format: LogFormat,
+) -> Result<()> {
     let max_log_files = 2;
-    let log_dir = directories::UserDirs::new()
-        .and_then(|dirs| dirs.document_dir().map(|d| d.join("frames_exporter_logs")))
-        .unwrap_or_else(|| PathBuf::from("logs"));
-
-    debug!("Initializing logger with log directory: {:?}", log_dir);
-
-    create_dir_all(&log_dir).context("Failed to create log directory")?;
+    let log_file_path = match log_file {
+        LogFile::Default => {
+            let log_dir = default_log_dir();
+            create_dir_all(&log_dir).context("Failed to create log directory")?;
+            manage_log_files(&log_dir, max_log_files).context("Failed to manage log files")?;
+            Some(log_dir.join("app.log"))
+        }
+        LogFile::Path(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                create_dir_all(parent).with_context(|| {
+                    format!("Failed to create log directory {}", parent.display())
+                })?;
+            }
+            Some(path)
+        }
+        LogFile::Disabled => None,
+    };
 
-    let log_file_path = log_dir.join("app.log");
     let size_limit = 5 * 1024 * 1024; // 5 MB
-
-    let rolling_condition = RollingConditionBasic::new().max_size(size_limit);
-    let rolling_appender =
-        BasicRollingFileAppender::new(log_file_path.clone(), rolling_condition, max_log_files)
-            .context("Failed to create rolling file appender")?;
-
-    debug!(
-        "Rolling file appender created successfully with max size: {} bytes",
-        size_limit
-    );
-
-    let rolling_appender = Mutex::new(rolling_appender);
-
-    manage_log_files(&log_dir, max_log_files).context("Failed to manage log files")?;
+    let rolling_appender = match &log_file_path {
+        Some(path) => {
+            let rolling_condition = RollingConditionBasic::new().max_size(size_limit);
+            let appender =
+                BasicRollingFileAppender::new(path.clone(), rolling_condition, max_log_files)
+                    .with_context(|| {
+                        format!(
+                            "Failed to create rolling file appender for {}",
+                            path.display()
+                        )
+                    })?;
+            Some(Mutex::new(appender))
+        }
+        None => None,
+    };
 
     let mut builder = Builder::new();
     builder.filter(None, log_level);
@@ -65,26 +128,43 @@ pub fn initialize_logger(log_level: LevelFilter) -> Result<()> {
         let level = record.level();
         let msg = record.args();
 
-        // Determine the color based on the log level.
-        let color = match record.level() {
-            log::Level::Error => console::Color::Red,
-            log::Level::Warn => console::Color::Yellow,
-            log::Level::Info => console::Color::Green,
-            log::Level::Debug => console::Color::Blue,
-            log::Level::Trace => console::Color::Cyan,
-        };
+        let log_entry = match format {
+            LogFormat::Plain => {
+                // Determine the color based on the log level.
+                let color = match record.level() {
+                    log::Level::Error => console::Color::Red,
+                    log::Level::Warn => console::Color::Yellow,
+                    log::Level::Info => console::Color::Green,
+                    log::Level::Debug => console::Color::Blue,
+                    log::Level::Trace => console::Color::Cyan,
+                };
 
-        // Style the log level.
-        let styled_level = style(record.level()).fg(color);
+                // Style the log level.
+                let styled_level = style(record.level()).fg(color);
 
-        // Write the styled log message to the console.
-        writeln!(buf, "[{:<5}] {} - {}", styled_level, ts, msg)?;
+                // Write the styled log message to the console.
+                writeln!(buf, "[{:<5}] {} - {}", styled_level, ts, msg)?;
+
+                format!("{} - {} - {}\n", ts, level, msg)
+            }
+            LogFormat::Json => {
+                let entry = serde_json::json!({
+                    "timestamp": ts.to_string(),
+                    "level": level.as_str(),
+                    "target": record.target(),
+                    "message": msg.to_string(),
+                });
+                writeln!(buf, "{}", entry)?;
+                format!("{}\n", entry)
+            }
+        };
 
-        // Also write a plain-text log entry to the rolling file.
-        let log_entry = format!("{} - {} - {}\n", ts, level, msg);
-        if let Ok(mut appender) = rolling_appender.lock() {
-            if let Err(e) = appender.write(log_entry.as_bytes()) {
-                warn!("Failed to write log entry to file: {:?}", e);
+        // Also write the entry to the rolling file.
+        if let Some(appender) = &rolling_appender {
+            if let Ok(mut appender) = appender.lock() {
+                if let Err(e) = appender.write(log_entry.as_bytes()) {
+                    warn!("Failed to write log entry to file: {:?}", e);
+                }
             }
         }
 
@@ -101,6 +181,25 @@ pub fn initialize_logger(log_level: LevelFilter) -> Result<()> {
     Ok(())
 }
 
+/// Returns the directory the log file is written to by default.
+///
+/// # Returns
+/// - `PathBuf`: The first available of the XDG state directory (`~/.local/state/fxp_videoclipper`),
+///   the former `frames_exporter_logs` in the user's document directory, and `logs` in the
+///   current directory.
+///
+/// # Notes
+/// - The XDG state directory only exists on Linux; other systems use the fallbacks.
+pub fn default_log_dir() -> PathBuf {
+    directories::ProjectDirs::from("", "", "fxp_videoclipper")
+        .and_then(|dirs| dirs.state_dir().map(Path::to_path_buf))
+        .or_else(|| {
+            directories::UserDirs::new()
+                .and_then(|dirs| dirs.document_dir().map(|d| d.join("frames_exporter_logs")))
+        })
+        .unwrap_or_else(|| PathBuf::from("logs"))
+}
+
 /// Manages log files in a directory, ensuring the number of files does not exceed a specified limit.
 ///
 /// This function handles log file management by reading the directory, collecting and filtering log files,
@@ -134,7 +233,7 @@ fn manage_log_files(log_dir: &Path, max_log_files: usize) -> Result<()> {
             path
         })
         .filter(|path| {
-            let is_log = path.is_file() && path.extension().map_or(false, |ext| ext == "log");
+            let is_log = path.is_file() && path.extension().is_some_and(|ext| ext == "log");
             if is_log {
                 debug!("Identified as log file: {:?}", path);
             }
@@ -150,7 +249,7 @@ fn manage_log_files(log_dir: &Path, max_log_files: usize) -> Result<()> {
 
     // Delete old log files if the number exceeds the limit
     while log_files.len() > max_log_files {
-        if let Some(old_file) = log_files.get(0).cloned() {
+        if let Some(old_file) = log_files.first().cloned() {
             debug!("Attempting to delete old log file: {:?}", old_file);
             if let Err(e) = remove_file(&old_file) {
                 warn!("Failed to delete old log file {:?}: {}", old_file, e);
@@ -2,6 +2,10 @@ use anyhow::{Context, Result};
 use log::debug;
 use std::process::Command as StdCommand;
 
+use fxp_output::ToolError;
+
+use crate::error::InitError;
+
 /// Retrieves the duration of a media file in milliseconds.
 ///
 /// This function executes an `ffprobe` command to extract the duration
@@ -16,34 +20,26 @@ use std::process::Command as StdCommand;
 /// # Notes
 /// - The function relies on the `ffprobe` command-line tool.
 /// - The duration is converted from seconds to milliseconds before being returned.
+/// - Falls back to the duration of the first audio stream when the container has none.
 pub fn media_duration(file_path: &str) -> Result<u64> {
     debug!("Attempting to get media duration for file: {}", file_path);
 
-    let child = StdCommand::new("ffprobe")
-        .args(&[
-            "-v",
-            "error",
-            "-show_entries",
-            "format=duration",
-            "-of",
-            "default=noprint_wrappers=1:nokey=1",
+    let duration_str = probe_duration(file_path, &["-show_entries", "format=duration"])?;
+    // Raw streams such as ADTS AAC may leave the container duration as N/A.
+    let duration_str = if duration_str.parse::<f64>().is_ok() {
+        duration_str
+    } else {
+        debug!(
+            "No container duration for {}, reading the audio stream's",
+            file_path
+        );
+        probe_duration(
             file_path,
-        ])
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .with_context(|| format!("Failed to spawn ffprobe command for file: {}", file_path))?;
-
-    let output = child
-        .wait_with_output()
-        .context("Failed to capture ffprobe output")?;
-
-    debug!("ffprobe command output: {:?}", output.stdout);
-
-    let duration_str =
-        String::from_utf8(output.stdout).context("Failed to parse ffprobe output as UTF-8")?;
+            &["-select_streams", "a:0", "-show_entries", "stream=duration"],
+        )?
+    };
 
-    let duration_in_seconds = duration_str.trim().parse::<f64>().with_context(|| {
+    let duration_in_seconds = duration_str.parse::<f64>().with_context(|| {
         format!(
             "Failed to parse duration as f64 from string: '{}'",
             duration_str
@@ -58,3 +54,26 @@ pub fn media_duration(file_path: &str) -> Result<u64> {
 
     Ok(duration_in_milliseconds)
 }
+
+/// Runs ffprobe for a single duration entry and returns its trimmed output.
+fn probe_duration(file_path: &str, entries: &[&str]) -> Result<String> {
+    let child = StdCommand::new("ffprobe")
+        .args(["-v", "error"])
+        .args(entries)
+        .args(["-of", "default=noprint_wrappers=1:nokey=1", file_path])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| InitError::spawn("ffprobe", e))
+        .with_context(|| format!("Failed to spawn ffprobe command for file: {}", file_path))?;
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to capture ffprobe output")?;
+
+    debug!("ffprobe command output: {:?}", output.stdout);
+
+    let duration_str =
+        String::from_utf8(output.stdout).context("Failed to parse ffprobe output as UTF-8")?;
+    Ok(duration_str.trim().to_string())
+}
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use fxp_output::{FrameRate, ToolError};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::process::Command as StdCommand;
+
+use crate::error::InitError;
+
+/// What ffprobe reports about a media file.
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaInfo {
+    /// Container format, e.g. `mov,mp4,m4a,3gp,3g2,mj2` or `mp3`.
+    pub format: Option<String>,
+    pub duration_ms: Option<u64>,
+    /// The first video stream, if any.
+    pub video: Option<VideoStream>,
+    /// The first audio stream, if any.
+    pub audio: Option<AudioStream>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoStream {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: Option<FrameRate>,
+    /// The frame count from the container, or estimated from the duration and rate.
+    pub frames: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioStream {
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+/// The parts of `ffprobe -of json` output that `media_info` reads.
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    r_frame_rate: Option<String>,
+    nb_frames: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+}
+
+/// Retrieves the duration, streams, resolution and frame rate of a media file.
+///
+/// # Parameters
+/// - `file_path`: The path to the media file as a string.
+///
+/// # Returns
+/// - `Result<MediaInfo>`: What ffprobe reports, or an error if ffprobe cannot be run or
+///   does not recognize the file.
+///
+/// # Notes
+/// - Like `media_duration`, this relies on the `ffprobe` command-line tool.
+/// - Only the first video and the first audio stream are reported.
+/// - Containers that do not store a frame count get one estimated from the duration and
+///   frame rate.
+pub fn media_info(file_path: &str) -> Result<MediaInfo> {
+    debug!("Probing media file: {}", file_path);
+
+    let output = StdCommand::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=format_name,duration:stream=codec_type,codec_name,width,height,r_frame_rate,nb_frames,sample_rate,channels",
+            "-of",
+            "json",
+            file_path,
+        ])
+        .output()
+        .map_err(|e| InitError::spawn("ffprobe", e))
+        .with_context(|| format!("Failed to run ffprobe for file: {}", file_path))?;
+
+    if !output.status.success() {
+        return Err(InitError::ToolFailed {
+            tool: "ffprobe".to_string(),
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+        .with_context(|| format!("ffprobe could not read {}", file_path));
+    }
+
+    let probe: Probe = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Failed to parse ffprobe output for file: {}", file_path))?;
+
+    let duration_ms = probe
+        .format
+        .as_ref()
+        .and_then(|format| format.duration.as_deref())
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .map(|seconds| (seconds * 1000.0).round() as u64);
+
+    let of_type = |kind: &str| {
+        probe
+            .streams
+            .iter()
+            .find(|stream| stream.codec_type.as_deref() == Some(kind))
+    };
+
+    let video = of_type("video").map(|stream| {
+        // ffprobe reports 0/0 for streams without a rate, such as still images.
+        let fps = stream
+            .r_frame_rate
+            .as_deref()
+            .and_then(|rate| rate.parse::<FrameRate>().ok());
+        let frames = stream
+            .nb_frames
+            .as_deref()
+            .and_then(|frames| frames.parse::<u64>().ok())
+            .or_else(|| Some(fps?.frames_in(duration_ms?)));
+        VideoStream {
+            codec: stream.codec_name.clone().unwrap_or_default(),
+            width: stream.width.unwrap_or(0),
+            height: stream.height.unwrap_or(0),
+            fps,
+            frames,
+        }
+    });
+
+    let audio = of_type("audio").map(|stream| AudioStream {
+        codec: stream.codec_name.clone().unwrap_or_default(),
+        sample_rate: stream
+            .sample_rate
+            .as_deref()
+            .and_then(|rate| rate.parse().ok()),
+        channels: stream.channels,
+    });
+
+    Ok(MediaInfo {
+        format: probe.format.and_then(|format| format.format_name),
+        duration_ms,
+        video,
+        audio,
+    })
+}
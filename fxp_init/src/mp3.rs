@@ -1,3 +1,4 @@
+use crate::audio_selection::AudioSelection;
 use crate::config::Config;
 
 use anyhow::{anyhow, Context, Result};
@@ -7,8 +8,10 @@ use std::{env, fs};
 
 use crate::literals::FXP_VIDEOCLIPPER_AUDIO;
 use crate::media_duration::media_duration;
+use crate::media_info::media_info;
 
-const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "wav", "flac"];
+/// Extensions a directory is searched for, compared without regard to case.
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "wav", "flac", "ogg", "oga", "m4a", "aac", "opus"];
 
 /// Enum to represent the source of the audio file
 enum AudioSource {
@@ -68,8 +71,8 @@ pub fn get_audio_duration(cli_audio: Option<String>, config: &Config) -> Result<
 
     // Resolve the audio path, passing the config_path as an argument
     debug!("Considering all audio sources...");
-    let audio_path =
-        resolve_audio_path(audio_source, config_path).context("Failed to resolve audio path")?;
+    let audio_path = resolve_audio_path(audio_source, config_path, config.audio_selection)
+        .context("Failed to resolve audio path")?;
 
     // Calculate the duration of the audio file
     if let Some(ref audio_path_str) = audio_path {
@@ -132,8 +135,8 @@ pub fn get_audio_file(cli_audio: Option<String>, config: &Config) -> Result<Opti
 
     // Resolve the audio path, passing the config_path as an argument
     debug!("Considering all audio sources...");
-    let audio_path =
-        resolve_audio_path(audio_source, config_path).context("Failed to resolve audio path")?;
+    let audio_path = resolve_audio_path(audio_source, config_path, config.audio_selection)
+        .context("Failed to resolve audio path")?;
 
     if let Some(ref audio_path_str) = audio_path {
         let path_buf = PathBuf::from(audio_path_str);
@@ -158,6 +161,7 @@ pub fn get_audio_file(cli_audio: Option<String>, config: &Config) -> Result<Opti
 /// # Parameters
 /// - `audio_source`: Specifies where to look for the audio file.
 /// - `config_path`: Optional path to a configuration file for fallback.
+/// - `selection`: Which file to use when a directory holds several.
 ///
 /// # Returns
 /// - `Result<Option<String>>`: Path to the found audio file if successful, `None` if not found.
@@ -167,6 +171,7 @@ pub fn get_audio_file(cli_audio: Option<String>, config: &Config) -> Result<Opti
 fn resolve_audio_path(
     audio_source: AudioSource,
     config_path: Option<String>,
+    selection: AudioSelection,
 ) -> Result<Option<String>> {
     debug!("Resolving audio path based on the provided source...");
 
@@ -175,13 +180,13 @@ fn resolve_audio_path(
     let result = match audio_source {
         AudioSource::CliArgument(path) => {
             debug!("Using audio path provided via CLI argument: {}", path);
-            find_audio_file(&path)
+            find_audio_file(&path, selection)
                 .map(Some)
                 .context("Failed to find audio file from CLI argument")
         }
         AudioSource::SearchInExportPath(dir) => {
             debug!("Searching for audio file in $PATH directory: {}", dir);
-            match find_audio_file(&dir) {
+            match find_audio_file(&dir, selection) {
                 Ok(audio_path) => Ok(Some(audio_path)),
                 Err(err) => {
                     debug!("No audio file found in $EXPORT_PATH directory: {}", err);
@@ -194,7 +199,7 @@ fn resolve_audio_path(
                                 config_path
                             );
                             config_audio_result = Some(
-                                find_audio_file(config_path)
+                                find_audio_file(config_path, selection)
                                     .context("Failed to find audio file in configuration path"),
                             );
                         }
@@ -217,7 +222,7 @@ fn resolve_audio_path(
             debug!("Using audio path from configuration file: {}", config_path);
             if config_audio_result.is_none() {
                 config_audio_result = Some(
-                    find_audio_file(&config_path)
+                    find_audio_file(&config_path, selection)
                         .context("Failed to find audio file in configuration file"),
                 );
             }
@@ -274,70 +279,78 @@ impl PathType {
 
     /// Discovers and returns the path of a supported audio file.
     ///
-    /// This function identifies and validates audio files based on their extensions.
+    /// This function identifies audio files by the streams ffprobe finds in them, and
+    /// by their extensions where ffprobe cannot tell.
     ///
     /// # Parameters
     /// - `self`: The current path to evaluate for an audio file or directory.
+    /// - `selection`: Which file to use when a directory holds several.
     ///
     /// # Returns
     /// - `Result<String>`: The path to a supported audio file on success, or an error if no valid audio file is found.
     ///
     /// # Notes
-    /// - If the input is a file, it checks if the file extension matches supported audio types.
-    /// - If the input is a directory, it searches for the first supported audio file within the directory.
+    /// - If the input is a file, any file ffprobe finds an audio stream in is taken, whatever
+    ///   its extension; only without ffprobe does the extension decide.
+    /// - If the input is a directory, it takes the files with an audio extension (mp3,
+    ///   wav, flac, ogg, oga, m4a, aac or opus) that ffprobe does not find to be without
+    ///   audio, and lets `selection` choose if there are several.
     /// - Returns an error if the input path does not resolve to a valid audio file or directory.
-    fn find_audio(self) -> Result<String> {
+    fn find_audio(self, selection: AudioSelection) -> Result<String> {
         match self {
             PathType::File(path) => {
-                if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
-                    if AUDIO_EXTENSIONS.contains(&ext) {
-                        let audio_path = path.to_string_lossy().to_string();
+                let audio_path = path.to_string_lossy().to_string();
+                match probe_audio(&path) {
+                    Some(true) => {
                         debug!("Input is a valid audio file: {}", audio_path);
-                        return Ok(audio_path);
+                        Ok(audio_path)
                     }
+                    Some(false) => Err(anyhow!(
+                        "The specified file has no audio stream: {}",
+                        audio_path
+                    )),
+                    // Without ffprobe, the extension is all there is to go by.
+                    None if has_audio_extension(&path) => {
+                        debug!("Input has an audio extension: {}", audio_path);
+                        Ok(audio_path)
+                    }
+                    None => Err(anyhow!(
+                        "The specified file is not a supported audio file: {}",
+                        audio_path
+                    )),
                 }
-                debug!(
-                    "Input path is a file but not a supported audio file: {}",
-                    path.to_string_lossy()
-                );
-                Err(anyhow!(
-                    "The specified file is not a supported audio file: {}",
-                    path.to_string_lossy()
-                ))
             }
             PathType::Directory(path) => {
                 debug!(
                     "Searching for audio file in directory: {}",
                     path.to_string_lossy()
                 );
-                let audio_entry = fs::read_dir(&path)
+                let candidates: Vec<PathBuf> = fs::read_dir(&path)
                     .context(format!(
                         "Failed to read directory: {}",
                         path.to_string_lossy()
                     ))?
                     .filter_map(Result::ok)
-                    .find(|entry| {
-                        entry
-                            .path()
-                            .extension()
-                            .and_then(|ext| ext.to_str())
-                            .map_or(false, |ext| AUDIO_EXTENSIONS.contains(&ext))
-                    });
+                    .map(|entry| entry.path())
+                    .filter(|entry| entry.is_file() && has_audio_extension(entry))
+                    // A file named like audio that ffprobe finds no audio in is skipped.
+                    .filter(|entry| probe_audio(entry) != Some(false))
+                    .collect();
 
-                if let Some(entry) = audio_entry {
-                    let audio_path = entry.path().to_string_lossy().to_string();
-                    debug!("Found audio file: {}", audio_path);
-                    Ok(audio_path)
-                } else {
+                if candidates.is_empty() {
                     debug!(
                         "No audio file found in directory: {}",
                         path.to_string_lossy()
                     );
-                    Err(anyhow!(
+                    return Err(anyhow!(
                         "No supported audio file found in directory: {}",
                         path.to_string_lossy()
-                    ))
+                    ));
                 }
+                let audio_path = selection.choose(&path, candidates)?;
+                let audio_path = audio_path.to_string_lossy().to_string();
+                debug!("Found audio file: {}", audio_path);
+                Ok(audio_path)
             }
         }
     }
@@ -350,11 +363,35 @@ impl PathType {
 ///
 /// # Parameters
 /// - `path_or_dir`: A string representing the directory or file path to search for the audio file.
+/// - `selection`: Which file to use when a directory holds several.
 ///
 /// # Returns
 /// - `Result<String>`: A `Result` containing the path to the found audio file as a `String` on success, or an error on failure.
-fn find_audio_file(path_or_dir: &str) -> Result<String> {
+fn find_audio_file(path_or_dir: &str, selection: AudioSelection) -> Result<String> {
     let path = Path::new(path_or_dir);
     let path_type = PathType::from_path(path)?;
-    path_type.find_audio()
+    path_type.find_audio(selection)
+}
+
+/// Returns whether a file has an extension of `AUDIO_EXTENSIONS`, in any case.
+fn has_audio_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|audio| audio.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Returns whether ffprobe finds an audio stream in a file, or `None` if ffprobe cannot
+/// be run or does not recognize the file.
+fn probe_audio(path: &Path) -> Option<bool> {
+    match media_info(&path.to_string_lossy()) {
+        Ok(info) => Some(info.audio.is_some()),
+        Err(e) => {
+            debug!("Could not probe {:?} for audio: {:#}", path, e);
+            None
+        }
+    }
 }
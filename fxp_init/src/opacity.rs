@@ -3,7 +3,9 @@ use anyhow::{Context, Result};
 use log::{debug, warn};
 use std::env;
 
-use crate::literals::FXP_VIDEOCLIPPER_OPACITY;
+use crate::config::parse_opacities;
+use crate::literals::{FXP_VIDEOCLIPPER_MULTIPLE_OPACITIES, FXP_VIDEOCLIPPER_OPACITY};
+use anyhow::anyhow;
 
 /// Enum to represent the source of the Opacity value
 enum OpacitySource {
@@ -106,3 +108,52 @@ fn resolve_opacity(opacity_source: OpacitySource) -> Result<f32> {
         }
     }
 }
+
+/// Retrieves and validates the list of opacities merged or clutted in one run.
+///
+/// The list is taken from, in order:
+/// 1. CLI argument
+/// 2. `FXP_VIDEOCLIPPER_MULTIPLE_OPACITIES`, comma separated
+/// 3. Configuration file
+///
+/// # Parameters
+/// - `cli_opacities`: The opacities given on the command line, if any.
+/// - `config`: The configuration holding `multiple_opacities`.
+///
+/// # Returns
+/// - `Result<Vec<f32>>`: The opacities, or an error if the list is empty or any value
+///   lies outside 0.0 to 1.0.
+pub fn get_multiple_opacities(
+    cli_opacities: Option<Vec<f32>>,
+    config: &Config,
+) -> Result<Vec<f32>> {
+    let opacities = if let Some(opacities) = cli_opacities.filter(|list| !list.is_empty()) {
+        debug!("Using opacities provided via CLI argument: {:?}", opacities);
+        opacities
+    } else if let Ok(env_opacities) = env::var(FXP_VIDEOCLIPPER_MULTIPLE_OPACITIES) {
+        let opacities = parse_opacities(&env_opacities).with_context(|| {
+            format!(
+                "Invalid opacities in {} environment variable: '{}'",
+                FXP_VIDEOCLIPPER_MULTIPLE_OPACITIES, env_opacities
+            )
+        })?;
+        debug!("Using opacities from environment variable: {:?}", opacities);
+        opacities
+    } else {
+        debug!(
+            "Using opacities from configuration file: {:?}",
+            config.multiple_opacities
+        );
+        config.multiple_opacities.clone()
+    };
+
+    if opacities.is_empty() {
+        return Err(anyhow!(
+            "No opacities to merge; set multiple_opacities in the configuration"
+        ));
+    }
+    if let Some(invalid) = opacities.iter().find(|o| !(0.0..=1.0).contains(*o)) {
+        return Err(anyhow!("Opacity {} is outside 0.0 to 1.0", invalid));
+    }
+    Ok(opacities)
+}
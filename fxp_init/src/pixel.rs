@@ -75,3 +75,19 @@ pub fn get_pixel_upper_limit(cli_pixel_limit: Option<u32>, config: &Config) -> R
 
     Ok(pixel_limit)
 }
+
+/// Returns the reduced pixel upper limit used for quick preview renders.
+///
+/// # Parameters
+/// - `pixel_upper_limit`: The pixel upper limit of a full render.
+///
+/// # Returns
+/// - `u32`: Half of the given limit, kept even and at least 2 so it is valid for yuv420p.
+pub fn get_preview_pixel_limit(pixel_upper_limit: u32) -> u32 {
+    let limit = (pixel_upper_limit / 2).max(2) & !1;
+    debug!(
+        "Using preview pixel limit {} (full limit {})",
+        limit, pixel_upper_limit
+    );
+    limit
+}
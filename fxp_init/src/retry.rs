@@ -0,0 +1,31 @@
+use crate::config::Config;
+use log::debug;
+
+use fxp_output::RetryPolicy;
+
+/// Determines how often a failed per-frame call of ffmpeg or gmic is retried.
+///
+/// # Parameters
+/// - `cli_retries`: The `--retries` given on the command line, if any.
+/// - `cli_backoff_ms`: The `--retry-backoff` given on the command line, if any.
+/// - `config`: The configuration holding the defaults.
+///
+/// # Returns
+/// - `RetryPolicy`: The resolved policy.
+///
+/// # Notes
+/// - Each setting of the command line takes priority over `retries` and
+///   `retry_backoff_ms` of the configuration.
+pub fn get_retry_policy(
+    cli_retries: Option<u32>,
+    cli_backoff_ms: Option<u64>,
+    config: &Config,
+) -> RetryPolicy {
+    let retries = cli_retries.unwrap_or(config.retries);
+    let backoff_ms = cli_backoff_ms.unwrap_or(config.retry_backoff_ms);
+    debug!(
+        "Retrying failed frames {} times, backing off from {} ms",
+        retries, backoff_ms
+    );
+    RetryPolicy::new(retries, backoff_ms)
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// A configuration value outside the range the modes accept.
+#[derive(Debug, Clone)]
+pub struct InvalidField {
+    /// The key of the value in the configuration file.
+    pub field: &'static str,
+    /// The value as read.
+    pub value: String,
+    /// The accepted range, e.g. `0.0 to 1.0`.
+    pub expected: &'static str,
+    /// The line of the configuration file the value came from, if known.
+    pub line: Option<usize>,
+}
+
+/// Every invalid value of a configuration, reported at once.
+#[derive(Debug, Clone)]
+pub struct InvalidConfig {
+    /// The configuration file the values came from, if known.
+    pub file: Option<PathBuf>,
+    pub fields: Vec<InvalidField>,
+}
+
+impl Config {
+    /// Checks every value of the configuration against the range the modes accept.
+    ///
+    /// # Returns
+    /// - `Result<(), InvalidConfig>`: `Ok(())` if all values are valid, otherwise every
+    ///   invalid field with its accepted range.
+    ///
+    /// # Notes
+    /// - The file and lines are left empty; `InvalidConfig::locate` fills them in from
+    ///   the configuration file.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        let mut fields = Vec::new();
+        let mut check = |valid: bool, field: &'static str, value: String, expected| {
+            if !valid {
+                fields.push(InvalidField {
+                    field,
+                    value,
+                    expected,
+                    line: None,
+                });
+            }
+        };
+
+        check(
+            self.pixel_upper_limit > 0,
+            "pixel_upper_limit",
+            self.pixel_upper_limit.to_string(),
+            "at least 1",
+        );
+        check(
+            self.sampling_number > 0,
+            "sampling_number",
+            self.sampling_number.to_string(),
+            "at least 1",
+        );
+        check(
+            (0.0..=1.0).contains(&self.opacity),
+            "opacity",
+            self.opacity.to_string(),
+            "0.0 to 1.0",
+        );
+        check(
+            !self.multiple_opacities.is_empty()
+                && self
+                    .multiple_opacities
+                    .iter()
+                    .all(|opacity| (0.0..=1.0).contains(opacity)),
+            "multiple_opacities",
+            format!("{:?}", self.multiple_opacities),
+            "a non-empty list of 0.0 to 1.0",
+        );
+        check(self.jobs > 0, "jobs", self.jobs.to_string(), "at least 1");
+        for (name, args) in &self.gmic_presets {
+            check(
+                !args.trim().is_empty(),
+                "gmic_presets",
+                format!("{{ {} = {:?} }}", name, args),
+                "at least one GMIC argument per preset",
+            );
+        }
+        for (name, command) in &self.processors {
+            check(
+                command.contains("{output}"),
+                "processors",
+                format!("{{ {} = {:?} }}", name, command),
+                "a command writing to {output}",
+            );
+        }
+
+        if let Some(webhook) = &self.webhook {
+            check(
+                webhook.starts_with("http://") || webhook.starts_with("https://"),
+                "webhook",
+                webhook.clone(),
+                "an http:// or https:// URL",
+            );
+        }
+
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidConfig { file: None, fields })
+        }
+    }
+}
+
+impl InvalidConfig {
+    /// Records the configuration file and the line each invalid value is on.
+    ///
+    /// # Parameters
+    /// - `path`: The configuration file the values were loaded from.
+    ///
+    /// # Returns
+    /// - `Self`: The report with the file set, and the lines found in it.
+    ///
+    /// # Notes
+    /// - A value missing from the file, and so taken from the defaults, has no line.
+    /// - The legacy `multiple_opacities_1` to `_3` keys are located as `multiple_opacities`.
+    pub fn locate(mut self, path: &Path) -> Self {
+        if let Ok(contents) = fs::read_to_string(path) {
+            for field in &mut self.fields {
+                field.line = find_key(&contents, field.field);
+            }
+        }
+        self.file = Some(path.to_path_buf());
+        self
+    }
+}
+
+/// Returns the 1-based line on which `key` is assigned, one of its numbered variants, or
+/// the table of that name starts.
+fn find_key(contents: &str, key: &str) -> Option<usize> {
+    let assigned = |line: &str, key: &str| {
+        line.trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    };
+    let numbered = |line: &str| {
+        line.trim_start()
+            .strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    };
+    let table = format!("[{}]", key);
+    contents
+        .lines()
+        .position(|line| assigned(line, key))
+        .or_else(|| contents.lines().position(numbered))
+        .or_else(|| contents.lines().position(|line| line.trim() == table))
+        .map(|index| index + 1)
+}
+
+impl fmt::Display for InvalidConfig {
+    /// Formats the report as one line per invalid field, e.g.
+    /// `line 3: pixel_upper_limit = 0, expected at least 1`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => writeln!(f, "Invalid configuration in {}:", file.display())?,
+            None => writeln!(f, "Invalid configuration:")?,
+        }
+        for field in &self.fields {
+            match field.line {
+                Some(line) => write!(f, "  line {}: ", line)?,
+                None => write!(f, "  ")?,
+            }
+            writeln!(
+                f,
+                "{} = {}, expected {}",
+                field.field, field.value, field.expected
+            )?;
+        }
+        write!(
+            f,
+            "Fix these values, or run `fxp_videoclipper init` to set them"
+        )
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
@@ -0,0 +1,60 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Name of the RIFE binary looked up on the `PATH` when no path is given.
+pub const DEFAULT_RIFE_BINARY: &str = "rife-ncnn-vulkan";
+
+/// What generates the intermediate frames.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// ffmpeg's motion-compensated `minterpolate` filter; no extra install, but slow and
+    /// prone to artifacts on fast motion.
+    #[default]
+    Minterpolate,
+    /// The RIFE neural network through its ncnn command-line binary; `None` runs
+    /// `DEFAULT_RIFE_BINARY` from the `PATH`.
+    Rife(Option<PathBuf>),
+}
+
+impl Engine {
+    /// Returns the RIFE binary to run, or `None` for ffmpeg's filter.
+    pub fn rife_binary(&self) -> Option<PathBuf> {
+        match self {
+            Engine::Minterpolate => None,
+            Engine::Rife(binary) => Some(
+                binary
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_RIFE_BINARY)),
+            ),
+        }
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minterpolate" => Ok(Engine::Minterpolate),
+            "rife" => Ok(Engine::Rife(None)),
+            _ => match s.strip_prefix("rife:") {
+                Some(path) if !path.is_empty() => Ok(Engine::Rife(Some(PathBuf::from(path)))),
+                _ => Err(format!(
+                    "Unknown interpolation engine '{}', expected minterpolate, rife or rife:PATH",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Engine::Minterpolate => write!(f, "minterpolate"),
+            Engine::Rife(None) => write!(f, "rife"),
+            Engine::Rife(Some(path)) => write!(f, "rife:{}", path.display()),
+        }
+    }
+}
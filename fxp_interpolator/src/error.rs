@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Interpolator that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum InterpolatorError {
+    /// A tool the Interpolator runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Interpolator runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The input holds no frames.
+    #[error("No frames found in {}", .0.display())]
+    NoFrames(PathBuf),
+    /// The input does not exist or is not what the Interpolator takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Interpolation interrupted by user")]
+    Interrupted,
+}
+
+impl InterpolatorError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            InterpolatorError::ToolMissing(_) => FailureKind::ToolMissing,
+            InterpolatorError::ToolFailed { .. } => FailureKind::ToolFailed,
+            InterpolatorError::NoFrames(_) => FailureKind::NoFrames,
+            InterpolatorError::InvalidInput(_) => FailureKind::InvalidInput,
+            InterpolatorError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for InterpolatorError {
+    fn missing(tool: &str) -> Self {
+        InterpolatorError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        InterpolatorError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
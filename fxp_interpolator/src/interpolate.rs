@@ -0,0 +1,204 @@
+use anyhow::{bail, Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use fxp_filenames::FramePadding;
+use fxp_output::FrameRate;
+use fxp_output::{kill_requested, ToolError};
+
+use crate::error::InterpolatorError;
+
+/// Copies the frames under consecutive `frame_%04d.<ext>` names, as ffmpeg and RIFE read them.
+///
+/// # Parameters
+/// - `frames`: The frames, ordered by frame number.
+/// - `staging_dir`: The directory receiving the copies.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The ffmpeg input pattern of the staged frames.
+///
+/// # Notes
+/// - All frames must share one extension, since ffmpeg reads them with a single pattern.
+/// - Numbers are padded to five digits or more for over 9999 frames.
+pub(crate) fn stage_frames(frames: &BTreeMap<u32, PathBuf>, staging_dir: &Path) -> Result<PathBuf> {
+    let extension_of = |path: &Path| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+    let extension = frames
+        .values()
+        .next()
+        .map(|first| extension_of(first))
+        .context("No frames to interpolate")?;
+    let padding = FramePadding::for_count(frames.len() as u64);
+
+    for (index, frame) in frames.values().enumerate() {
+        if extension_of(frame) != extension {
+            bail!(
+                "Frame {} is not a .{} image like the first frame; convert the frames to one format first",
+                frame.display(),
+                extension
+            );
+        }
+        let staged = staging_dir.join(padding.file_name("frame", index as u64 + 1, &extension));
+        fs::copy(frame, &staged)
+            .with_context(|| format!("Failed to copy {:?} to {:?}", frame, staged))?;
+    }
+    Ok(staging_dir.join(padding.pattern("frame", &extension)))
+}
+
+/// Extracts the frames of a video at a fixed rate.
+///
+/// # Parameters
+/// - `video_path`: The video to extract.
+/// - `output_dir`: The directory receiving the frames.
+/// - `frame_pattern`: Name pattern of the frames, e.g. `frame_%04d.png`.
+/// - `fps`: The rate the video is sampled at.
+/// - `running`: Cleared to interrupt the extraction.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or the extraction was interrupted.
+pub(crate) fn extract_frames(
+    video_path: &Path,
+    output_dir: &Path,
+    frame_pattern: &str,
+    fps: FrameRate,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span = info_span!("extract", video = %video_path.display(), fps = %fps).entered();
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-i"])
+        .arg(video_path)
+        .args(["-vf", &format!("fps={}", fps.ffmpeg_arg())])
+        .arg(output_dir.join(frame_pattern));
+    run_command(command, "ffmpeg", "extract the frames", running)
+}
+
+/// Interpolates frames to the target rate with ffmpeg's `minterpolate` filter.
+///
+/// # Parameters
+/// - `input`: The video, or the pattern of the staged frames.
+/// - `input_fps`: The rate of the staged frames; `None` for a video, whose own rate is used.
+/// - `output_dir`: The directory receiving the frames.
+/// - `frame_pattern`: Name pattern of the frames, e.g. `frame_%04d.png`.
+/// - `target_fps`: The frame rate of the interpolated frames.
+/// - `running`: Cleared to interrupt the interpolation.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or the interpolation was interrupted.
+///
+/// # Notes
+/// - Uses motion-compensated interpolation (`mi_mode=mci`), which blends along the
+///   estimated motion rather than cross-fading neighbouring frames.
+pub(crate) fn minterpolate(
+    input: &Path,
+    input_fps: Option<FrameRate>,
+    output_dir: &Path,
+    frame_pattern: &str,
+    target_fps: FrameRate,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span = info_span!("minterpolate", input = %input.display(), fps = %target_fps).entered();
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y");
+    if let Some(input_fps) = input_fps {
+        command.args(["-framerate", &input_fps.ffmpeg_arg()]);
+    }
+    command
+        .arg("-i")
+        .arg(input)
+        .args([
+            "-vf",
+            &format!("minterpolate=fps={}:mi_mode=mci", target_fps.ffmpeg_arg()),
+            "-start_number",
+            "1",
+        ])
+        .arg(output_dir.join(frame_pattern));
+    run_command(command, "ffmpeg", "interpolate the frames", running)
+}
+
+/// Interpolates a directory of frames with the RIFE ncnn binary.
+///
+/// # Parameters
+/// - `binary`: The RIFE binary, e.g. `rife-ncnn-vulkan`.
+/// - `input_dir`: The directory of frames, read in name order.
+/// - `output_dir`: The directory receiving the frames.
+/// - `frame_pattern`: Name pattern of the frames, e.g. `frame_%04d.png`.
+/// - `frame_count`: The number of frames to generate.
+/// - `running`: Cleared to interrupt the interpolation.
+///
+/// # Returns
+/// - `Result<()>`: An error naming the binary if it is missing or fails.
+pub(crate) fn rife(
+    binary: &Path,
+    input_dir: &Path,
+    output_dir: &Path,
+    frame_pattern: &str,
+    frame_count: u64,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span = info_span!("rife", input = %input_dir.display(), frames = %frame_count).entered();
+    let mut command = Command::new(binary);
+    command
+        .arg("-i")
+        .arg(input_dir)
+        .arg("-o")
+        .arg(output_dir)
+        .args(["-n", &frame_count.to_string(), "-f", frame_pattern]);
+    run_command(
+        command,
+        &binary.display().to_string(),
+        "interpolate the frames",
+        running,
+    )
+}
+
+/// Runs a command to completion, killing it if `running` is cleared.
+fn run_command(
+    mut command: Command,
+    program: &str,
+    action: &str,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(InterpolatorError::Interrupted.into());
+    }
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| InterpolatorError::spawn(program, e))
+        .with_context(|| format!("Failed to start {} to {}", program, action))?;
+
+    loop {
+        if kill_requested(&running) {
+            debug!("Interruption requested; terminating {}.", program);
+            child.kill().ok();
+            return Err(InterpolatorError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => {
+                debug!("{} failed with status: {:?}", program, status);
+                return Err(InterpolatorError::ToolFailed {
+                    tool: program.to_string(),
+                    reason: status.to_string(),
+                })
+                .with_context(|| format!("{} failed to {}", program, action));
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
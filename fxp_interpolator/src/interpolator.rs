@@ -0,0 +1,312 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::FrameRate;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::{FileOperations, FramePadding};
+
+use crate::engine::Engine;
+use crate::error::InterpolatorError;
+use crate::interpolate::{extract_frames, minterpolate, rife, stage_frames};
+
+/// Frame rate of a directory of frames, see `Interpolator::source_fps`.
+pub const DEFAULT_SOURCE_FPS: FrameRate = FrameRate::fps(15);
+
+/// Struct responsible for generating intermediate frames, for slow motion or a higher frame rate.
+pub struct Interpolator {
+    input: PathBuf,
+    /// The frames of a directory input, ordered by number; empty for a video input.
+    input_files: BTreeMap<u32, PathBuf>,
+    output_directory: PathBuf,
+    target_fps: FrameRate,
+    /// Frame rate of a directory of frames, and the rate a video is sampled at before RIFE
+    /// interpolates it; `new` sets `DEFAULT_SOURCE_FPS`. ffmpeg reads a video at its own rate.
+    pub source_fps: FrameRate,
+    /// What generates the intermediate frames; `new` sets `Engine::Minterpolate`.
+    pub engine: Engine,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Length of a video input in milliseconds, which sizes the padding of the frame
+    /// numbers past 9999 frames; `new` sets `None`, padding to four digits. A directory
+    /// input is sized by its frames.
+    pub duration_ms: Option<u64>,
+}
+
+impl Interpolator {
+    /// Creates a new `Interpolator` for a directory of frames or a video.
+    ///
+    /// # Parameters
+    /// - `input`: The directory of frames or the video to interpolate.
+    /// - `output_directory`: Optional path for the interpolated frames; defaults to
+    ///   `<input>_interpolated` next to the input.
+    /// - `target_fps`: The frame rate of the interpolated frames.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: New `Interpolator` instance on success, or an error if validation fails.
+    ///
+    /// # Notes
+    /// - The frames of a directory are ordered by the numbers in their filenames, see
+    ///   `FileOperations::load_files`.
+    pub fn new(
+        input: String,
+        output_directory: Option<String>,
+        target_fps: FrameRate,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        debug!("Initializing new Interpolator instance with:");
+        debug!("- Input: {}", input);
+        debug!("- Output directory: {:?}", output_directory);
+        debug!("- Target fps: {}", target_fps);
+
+        let input_path = canonical_input(&input)?;
+        let input_files = load_frames(&input_path)?;
+
+        let mode: Modes = Modes::Interpolator;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Interpolator(interpolator_output) => interpolator_output
+                .create_output((input_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Interpolator mode"),
+        };
+        debug!("Output directory created at: {:?}", output_directory_path);
+
+        Ok(Self {
+            input: input_path,
+            input_files,
+            output_directory: output_directory_path,
+            target_fps,
+            source_fps: DEFAULT_SOURCE_FPS,
+            engine: Engine::default(),
+            in_place: false,
+            duration_ms: None,
+        })
+    }
+
+    /// Resolves what `new` and `interpolate` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input`: The directory of frames or the video to interpolate.
+    /// - `output_directory`: Optional path for the interpolated frames.
+    /// - `target_fps`: The frame rate of the interpolated frames.
+    /// - `source_fps`: The frame rate of a directory of frames.
+    /// - `engine`: What generates the intermediate frames.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails.
+    pub fn plan(
+        input: String,
+        output_directory: Option<String>,
+        target_fps: FrameRate,
+        source_fps: FrameRate,
+        engine: &Engine,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_path = canonical_input(&input)?;
+        let input_files = load_frames(&input_path)?;
+
+        let mode: Modes = Modes::Interpolator;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Interpolator(interpolator_output) => interpolator_output
+                .plan_output((input_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Interpolator mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Interpolator);
+        if input_path.is_dir() {
+            plan = plan
+                .entry("input directory", input_path.display())
+                .entry("frames", input_files.len())
+                .entry("source fps", source_fps)
+                .entry(
+                    "interpolated frames",
+                    interpolated_count(input_files.len() as u64, source_fps, target_fps),
+                );
+        } else {
+            plan = plan.entry("input video", input_path.display());
+            if engine.rife_binary().is_some() {
+                plan = plan.entry("sampled at", format!("{} fps", source_fps));
+            }
+        }
+        Ok(plan
+            .entry("target fps", target_fps)
+            .entry("engine", engine)
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
+}
+
+/// Checks that the input exists and canonicalizes it.
+fn canonical_input(input: &str) -> Result<PathBuf> {
+    let input_path = PathBuf::from(input);
+    if !input_path.exists() {
+        return Err(InterpolatorError::InvalidInput(format!(
+            "Input '{}' is neither a directory nor a video file",
+            input_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_path)
+        .with_context(|| format!("Failed to resolve input '{}'", input_path.display()))
+}
+
+/// Maps the frames of a directory input by their frame number; a video has none.
+fn load_frames(input: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    if !input.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+    let input_images: Vec<PathBuf> = fs::read_dir(input)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    let frames = Modes::Interpolator.load_files(&input_images)?;
+    if frames.is_empty() {
+        return Err(InterpolatorError::NoFrames(input.to_path_buf()).into());
+    }
+    Ok(frames)
+}
+
+/// Returns how many frames `frames` frames at `source_fps` become at `target_fps`.
+fn interpolated_count(frames: u64, source_fps: FrameRate, target_fps: FrameRate) -> u64 {
+    (frames * target_fps.numerator() as u64 * source_fps.denominator() as u64)
+        .div_ceil(target_fps.denominator() as u64 * source_fps.numerator() as u64)
+}
+
+impl Interpolator {
+    /// Generates the intermediate frames and writes all frames at the target rate.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of frames written, or an error if the engine fails
+    ///   or the process was interrupted.
+    ///
+    /// # Notes
+    /// - The frames are written as `frame_0001.png`, `frame_0002.png`, ..., ready for the
+    ///   Clipper at the target fps; with a target rate below the source, frames are dropped.
+    ///   Past 9999 frames the numbers are padded to five digits or more.
+    /// - A directory is staged under consecutive names first, so gaps in its numbering
+    ///   are closed rather than interpolated across.
+    /// - RIFE reads directories only, so a video is first sampled at `source_fps`.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are written, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Handles Ctrl+C interruptions gracefully.
+    /// - Writes a `manifest.json` recording the rates, the engine and the input hashes.
+    pub fn interpolate(&self) -> Result<usize> {
+        let _span = info_span!(
+            "interpolator",
+            input = %self.input.display(),
+            output = %self.output_directory.display(),
+            fps = %self.target_fps
+        )
+        .entered();
+
+        let manifest = Manifest::new(Modes::Interpolator)
+            .parameter("input", self.input.display())
+            .parameter("fps", self.target_fps)
+            .parameter("source fps", self.source_fps)
+            .parameter("engine", &self.engine);
+        let manifest = if self.input_files.is_empty() {
+            manifest.inputs([&self.input])
+        } else {
+            manifest.inputs(self.input_files.values())
+        };
+
+        let running = running_flag()?;
+
+        let tmp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+        staged.keep_partial(&manifest);
+        let is_video = self.input_files.is_empty();
+
+        match self.engine.rife_binary() {
+            None if is_video => minterpolate(
+                &self.input,
+                None,
+                staged.path(),
+                &self.video_padding(self.target_fps).pattern("frame", "png"),
+                self.target_fps,
+                running.clone(),
+            )?,
+            None => {
+                let pattern = stage_frames(&self.input_files, tmp_dir.path())?;
+                let count = interpolated_count(
+                    self.input_files.len() as u64,
+                    self.source_fps,
+                    self.target_fps,
+                );
+                minterpolate(
+                    &pattern,
+                    Some(self.source_fps),
+                    staged.path(),
+                    &FramePadding::for_count(count).pattern("frame", "png"),
+                    self.target_fps,
+                    running.clone(),
+                )?
+            }
+            Some(binary) => {
+                if is_video {
+                    extract_frames(
+                        &self.input,
+                        tmp_dir.path(),
+                        &self.video_padding(self.source_fps).pattern("frame", "png"),
+                        self.source_fps,
+                        running.clone(),
+                    )?;
+                } else {
+                    stage_frames(&self.input_files, tmp_dir.path())?;
+                }
+                let frames = count_frames(tmp_dir.path())?;
+                let count = interpolated_count(frames as u64, self.source_fps, self.target_fps);
+                rife(
+                    &binary,
+                    tmp_dir.path(),
+                    staged.path(),
+                    &FramePadding::for_count(count).pattern("frame", "png"),
+                    count,
+                    running.clone(),
+                )?;
+            }
+        }
+
+        let written = count_frames(staged.path())?;
+        debug!("Interpolated {} frames into {:?}", written, staged.path());
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Interpolator, written)?;
+
+        Ok(written)
+    }
+}
+
+impl Interpolator {
+    /// Returns the padding of the frames a video input yields at `fps`, four digits if
+    /// its length is unknown.
+    fn video_padding(&self, fps: FrameRate) -> FramePadding {
+        self.duration_ms
+            .map(|duration_ms| FramePadding::for_count(fps.frames_in(duration_ms) + 1))
+            .unwrap_or_default()
+    }
+}
+
+/// Counts the image files of a directory, skipping hidden files and the manifest.
+fn count_frames(dir: &Path) -> Result<usize> {
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    Ok(Modes::Interpolator.load_files(&files)?.len())
+}
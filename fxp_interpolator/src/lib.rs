@@ -0,0 +1,8 @@
+mod engine;
+mod error;
+mod interpolate;
+mod interpolator;
+
+pub use engine::Engine;
+pub use error::InterpolatorError;
+pub use interpolator::Interpolator;
@@ -0,0 +1,554 @@
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use fxp_stream::is_deep_color;
+
+/// An RGBA image with 16 bits per channel.
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// How the overlay is mixed into the base image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Blending {
+    /// Composite using the alpha channels of both images instead of writing opaque blends.
+    pub respect_alpha: bool,
+    /// Mix linear light instead of sRGB-encoded values.
+    pub linear: bool,
+}
+
+/// How a layer's colors combine with the image below it, as the blend modes of the layers
+/// of an image editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The layer's own colors.
+    #[default]
+    Normal,
+    /// Darkens: the product of both colors.
+    Multiply,
+    /// Lightens: the inverse of the product of both inverted colors.
+    Screen,
+    /// Multiplies the dark colors below and screens the light ones, raising contrast.
+    Overlay,
+    /// The sum of both colors, clipped to white.
+    Add,
+    /// The absolute difference of both colors.
+    Difference,
+}
+
+impl FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(BlendMode::Normal),
+            "multiply" => Ok(BlendMode::Multiply),
+            "screen" => Ok(BlendMode::Screen),
+            "overlay" => Ok(BlendMode::Overlay),
+            "add" => Ok(BlendMode::Add),
+            "difference" => Ok(BlendMode::Difference),
+            other => Err(format!(
+                "Unknown blend mode '{}', expected normal, multiply, screen, overlay, add or difference",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Add => "add",
+            BlendMode::Difference => "difference",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl BlendMode {
+    /// Returns the colors of the layer as its blend mode combines them with the image
+    /// below, to be mixed into that image with the layer's opacity by `blend`.
+    ///
+    /// # Parameters
+    /// - `below`: The image the layer is blended onto.
+    /// - `layer`: The layer, of the same size as `below`.
+    ///
+    /// # Returns
+    /// - `Cow<DynamicImage>`: `layer` itself for `Normal`; otherwise an RGBA image of the
+    ///   combined colors, keeping the layer's alpha.
+    ///
+    /// # Notes
+    /// - The colors are combined as sRGB values, as image editors do by default, even
+    ///   when the opacity is mixed in linear light.
+    /// - When either image has more than 8 bits per channel, the colors are combined and
+    ///   returned with 16.
+    pub fn apply<'a>(self, below: &DynamicImage, layer: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        if self == BlendMode::Normal {
+            return Cow::Borrowed(layer);
+        }
+        if is_deep_color(below) || is_deep_color(layer) {
+            let below = below.to_rgba16();
+            let mut combined = layer.to_rgba16();
+            for (pixel, below) in combined.pixels_mut().zip(below.pixels()) {
+                for channel in 0..3 {
+                    let value = self.combine_deep(
+                        below[channel] as f32 / 65535.0,
+                        pixel[channel] as f32 / 65535.0,
+                    );
+                    pixel[channel] = (value * 65535.0).round() as u16;
+                }
+            }
+            return Cow::Owned(DynamicImage::ImageRgba16(combined));
+        }
+        let below = Pixels::of(below);
+        let mut combined = layer.to_rgba8();
+        for (pixel, below) in combined
+            .chunks_exact_mut(4)
+            .zip(below.data.chunks_exact(below.channels))
+        {
+            for channel in 0..3 {
+                pixel[channel] = self.combine(below[channel] as u32, pixel[channel] as u32);
+            }
+        }
+        Cow::Owned(DynamicImage::ImageRgba8(combined))
+    }
+
+    /// Combines one 8-bit channel of the image below with the layer's.
+    #[inline]
+    fn combine(self, below: u32, layer: u32) -> u8 {
+        let multiply = |a: u32, b: u32| (a * b + 127) / 255;
+        let screen = |a: u32, b: u32| 255 - multiply(255 - a, 255 - b);
+        let combined = match self {
+            BlendMode::Normal => layer,
+            BlendMode::Multiply => multiply(below, layer),
+            BlendMode::Screen => screen(below, layer),
+            BlendMode::Overlay if below < 128 => multiply(2 * below, layer),
+            BlendMode::Overlay => screen(2 * below - 255, layer),
+            BlendMode::Add => (below + layer).min(255),
+            BlendMode::Difference => below.abs_diff(layer),
+        };
+        combined as u8
+    }
+
+    /// Combines one channel of the image below with the layer's, both from 0 to 1.
+    #[inline]
+    fn combine_deep(self, below: f32, layer: f32) -> f32 {
+        let screen = |a: f32, b: f32| 1.0 - (1.0 - a) * (1.0 - b);
+        match self {
+            BlendMode::Normal => layer,
+            BlendMode::Multiply => below * layer,
+            BlendMode::Screen => screen(below, layer),
+            BlendMode::Overlay if below < 0.5 => 2.0 * below * layer,
+            BlendMode::Overlay => screen(2.0 * below - 1.0, layer),
+            BlendMode::Add => (below + layer).min(1.0),
+            BlendMode::Difference => (below - layer).abs(),
+        }
+    }
+}
+
+/// Mixes the overlay into the base image with the specified opacity.
+///
+/// # Arguments
+/// * `base` - The first image.
+/// * `overlay` - The second image, of the same size as the first.
+/// * `opacity` - The opacity of the overlay (between `0.0` and `1.0`).
+/// * `blending` - Whether to respect alpha and whether to mix in linear light.
+///
+/// # Returns
+/// The result as an `RgbaImage`.
+///
+/// # Notes
+/// - Works on the raw RGB or RGBA buffers with fixed-point weights, so the inner loops
+///   need no per-pixel conversions and can be vectorized by the compiler.
+/// - With the `parallel` feature, rows are mixed on all cores.
+pub fn blend(
+    base: &DynamicImage,
+    overlay: &DynamicImage,
+    opacity: f32,
+    blending: Blending,
+) -> RgbaImage {
+    let mut out = RgbaImage::new(base.width(), base.height());
+    if base.width() == 0 || base.height() == 0 {
+        return out;
+    }
+    let base = Pixels::of(base);
+    let overlay = Pixels::of(overlay);
+    let opacity = opacity.clamp(0.0, 1.0);
+
+    if blending.respect_alpha {
+        // Weight out of 255, the scale of the alpha channels.
+        let weight = (opacity * 255.0).round() as u32;
+        let row = if blending.linear {
+            composite_row_for::<true>(base.channels, overlay.channels)
+        } else {
+            composite_row_for::<false>(base.channels, overlay.channels)
+        };
+        for_each_row(&mut out, &base, &overlay, row, weight);
+    } else {
+        // Weight out of 256, so that opacity 1.0 keeps the overlay exactly.
+        let weight = (opacity * 256.0).round() as u32;
+        let row = if blending.linear {
+            blend_row_for::<true>(base.channels, overlay.channels)
+        } else {
+            blend_row_for::<false>(base.channels, overlay.channels)
+        };
+        for_each_row(&mut out, &base, &overlay, row, weight);
+    }
+    out
+}
+
+/// Mixes the overlay into the base image like `blend`, keeping 16 bits per channel.
+///
+/// # Parameters
+/// - `base`: The first image.
+/// - `overlay`: The second image, of the same size as the first.
+/// - `opacity`: The opacity of the overlay (between `0.0` and `1.0`).
+/// - `blending`: Whether to respect alpha and whether to mix in linear light.
+///
+/// # Returns
+/// - `Rgba16Image`: The result, with 16 bits per channel.
+///
+/// # Notes
+/// - For images with more than 8 bits per channel, such as the frames of a 10-bit video,
+///   whose precision `blend` would cut; `blend_image` picks between the two.
+/// - Mixes in floating point, so it is slower than `blend`.
+pub fn blend_deep(
+    base: &DynamicImage,
+    overlay: &DynamicImage,
+    opacity: f32,
+    blending: Blending,
+) -> Rgba16Image {
+    let mut out = Rgba16Image::new(base.width(), base.height());
+    if base.width() == 0 || base.height() == 0 {
+        return out;
+    }
+    let base = base.to_rgba16();
+    let overlay = overlay.to_rgba16();
+    let opacity = opacity.clamp(0.0, 1.0);
+    let row = base.width() as usize * 4;
+    let mix = |(out, (base, overlay)): (&mut [u16], (&[u16], &[u16]))| {
+        blend_deep_row(out, base, overlay, opacity, blending)
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        out.par_chunks_mut(row)
+            .zip(base.par_chunks(row).zip(overlay.par_chunks(row)))
+            .for_each(mix);
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        out.chunks_mut(row)
+            .zip(base.chunks(row).zip(overlay.chunks(row)))
+            .for_each(mix);
+    }
+    out
+}
+
+/// Mixes like `blend`, or like `blend_deep` when either image has more than 8 bits per
+/// channel.
+///
+/// # Returns
+/// - `DynamicImage`: An RGBA image with 8 or 16 bits per channel.
+pub fn blend_image(
+    base: &DynamicImage,
+    overlay: &DynamicImage,
+    opacity: f32,
+    blending: Blending,
+) -> DynamicImage {
+    if is_deep_color(base) || is_deep_color(overlay) {
+        DynamicImage::ImageRgba16(blend_deep(base, overlay, opacity, blending))
+    } else {
+        DynamicImage::ImageRgba8(blend(base, overlay, opacity, blending))
+    }
+}
+
+/// Mixes one row of 16-bit RGBA pixels, as `blend_row` or, respecting alpha,
+/// `composite_row` do.
+fn blend_deep_row(
+    out: &mut [u16],
+    base: &[u16],
+    overlay: &[u16],
+    opacity: f32,
+    blending: Blending,
+) {
+    let decode = |value: u16| {
+        let value = value as f32 / 65535.0;
+        if blending.linear {
+            srgb_to_linear(value)
+        } else {
+            value
+        }
+    };
+    let encode = |value: f32| {
+        let value = if blending.linear {
+            linear_to_srgb(value)
+        } else {
+            value
+        };
+        (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+    };
+    for ((out, base), overlay) in out
+        .chunks_exact_mut(4)
+        .zip(base.chunks_exact(4))
+        .zip(overlay.chunks_exact(4))
+    {
+        let (source_alpha, below) = if blending.respect_alpha {
+            let source_alpha = overlay[3] as f32 / 65535.0 * opacity;
+            (
+                source_alpha,
+                base[3] as f32 / 65535.0 * (1.0 - source_alpha),
+            )
+        } else {
+            (opacity, 1.0 - opacity)
+        };
+        let alpha = source_alpha + below;
+        if alpha <= 0.0 {
+            out.copy_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        for channel in 0..3 {
+            out[channel] = encode(
+                (decode(overlay[channel]) * source_alpha + decode(base[channel]) * below) / alpha,
+            );
+        }
+        out[3] = if blending.respect_alpha {
+            (alpha.min(1.0) * 65535.0).round() as u16
+        } else {
+            u16::MAX
+        };
+    }
+}
+
+/// Returns the linear light of an sRGB value, both from 0 to 1.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Returns the sRGB value of linear light, both from 0 to 1.
+fn linear_to_srgb(light: f32) -> f32 {
+    if light <= 0.0031308 {
+        light * 12.92
+    } else {
+        1.055 * light.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Mixes one row: output RGBA pixels, base pixels, overlay pixels and the weight.
+type RowFn = fn(&mut [u8], &[u8], &[u8], u32);
+
+/// Runs `row` over every row of the images, in parallel with the `parallel` feature.
+fn for_each_row(out: &mut RgbaImage, base: &Pixels, overlay: &Pixels, row: RowFn, weight: u32) {
+    let width = out.width() as usize;
+    let out_row = width * 4;
+    let base_row = width * base.channels;
+    let overlay_row = width * overlay.channels;
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        out.par_chunks_mut(out_row)
+            .zip(
+                base.data
+                    .par_chunks(base_row)
+                    .zip(overlay.data.par_chunks(overlay_row)),
+            )
+            .for_each(|(out, (base, overlay))| row(out, base, overlay, weight));
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        out.chunks_mut(out_row)
+            .zip(
+                base.data
+                    .chunks(base_row)
+                    .zip(overlay.data.chunks(overlay_row)),
+            )
+            .for_each(|(out, (base, overlay))| row(out, base, overlay, weight));
+    }
+}
+
+/// Picks the blending row function for the channel layouts, so its loop is monomorphized.
+fn blend_row_for<const LINEAR: bool>(base: usize, overlay: usize) -> RowFn {
+    match (base, overlay) {
+        (3, 3) => blend_row::<3, 3, LINEAR>,
+        (3, _) => blend_row::<3, 4, LINEAR>,
+        (_, 3) => blend_row::<4, 3, LINEAR>,
+        _ => blend_row::<4, 4, LINEAR>,
+    }
+}
+
+/// Picks the compositing row function for the channel layouts.
+fn composite_row_for<const LINEAR: bool>(base: usize, overlay: usize) -> RowFn {
+    match (base, overlay) {
+        (3, 3) => composite_row::<3, 3, LINEAR>,
+        (3, _) => composite_row::<3, 4, LINEAR>,
+        (_, 3) => composite_row::<4, 3, LINEAR>,
+        _ => composite_row::<4, 4, LINEAR>,
+    }
+}
+
+/// Blends one row of pixels with `BASE` and `OVERLAY` bytes per pixel into opaque RGBA;
+/// `overlay_weight` is the opacity out of 256.
+#[inline]
+fn blend_row<const BASE: usize, const OVERLAY: usize, const LINEAR: bool>(
+    out: &mut [u8],
+    base: &[u8],
+    overlay: &[u8],
+    overlay_weight: u32,
+) {
+    let base_weight = 256 - overlay_weight;
+    let tables = linear_tables();
+    for ((out, base), overlay) in out
+        .chunks_exact_mut(4)
+        .zip(base.chunks_exact(BASE))
+        .zip(overlay.chunks_exact(OVERLAY))
+    {
+        for channel in 0..3 {
+            out[channel] = if LINEAR {
+                tables.encode(
+                    (tables.decode(base[channel]) * base_weight
+                        + tables.decode(overlay[channel]) * overlay_weight)
+                        >> 8,
+                )
+            } else {
+                ((base[channel] as u32 * base_weight + overlay[channel] as u32 * overlay_weight)
+                    >> 8) as u8
+            };
+        }
+        out[3] = 255;
+    }
+}
+
+/// Composites one row of pixels with `BASE` and `OVERLAY` bytes per pixel into RGBA;
+/// `overlay_weight` is the opacity out of 255, and a missing alpha channel is opaque.
+///
+/// Uses source-over compositing on premultiplied colors: the overlay covers the base
+/// in proportion to its own alpha times the opacity, and the result is transparent only
+/// where both images are. The output alpha is straight, not premultiplied.
+#[inline]
+fn composite_row<const BASE: usize, const OVERLAY: usize, const LINEAR: bool>(
+    out: &mut [u8],
+    base: &[u8],
+    overlay: &[u8],
+    overlay_weight: u32,
+) {
+    let tables = linear_tables();
+    for ((out, base), overlay) in out
+        .chunks_exact_mut(4)
+        .zip(base.chunks_exact(BASE))
+        .zip(overlay.chunks_exact(OVERLAY))
+    {
+        let base_alpha = if BASE == 4 { base[3] as u32 } else { 255 };
+        let overlay_alpha = if OVERLAY == 4 { overlay[3] as u32 } else { 255 };
+        // Coverage of the overlay and the share of the base showing through, out of 255.
+        let source_alpha = (overlay_alpha * overlay_weight + 127) / 255;
+        let below = (base_alpha * (255 - source_alpha) + 127) / 255;
+        let alpha = source_alpha + below;
+        if alpha == 0 {
+            out.copy_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        for channel in 0..3 {
+            out[channel] = if LINEAR {
+                let premultiplied = tables.decode(overlay[channel]) * source_alpha
+                    + tables.decode(base[channel]) * below;
+                tables.encode((premultiplied + alpha / 2) / alpha)
+            } else {
+                let premultiplied =
+                    overlay[channel] as u32 * source_alpha + base[channel] as u32 * below;
+                ((premultiplied + alpha / 2) / alpha) as u8
+            };
+        }
+        out[3] = alpha as u8;
+    }
+}
+
+/// Lookup tables between 8-bit sRGB values and 16-bit linear light.
+struct LinearTables {
+    to_linear: [u16; 256],
+    /// sRGB values of linear light quantized to 12 bits.
+    to_srgb: [u8; 4096],
+}
+
+impl LinearTables {
+    /// Returns the linear light of an sRGB value, out of 65535.
+    #[inline]
+    fn decode(&self, srgb: u8) -> u32 {
+        self.to_linear[srgb as usize] as u32
+    }
+
+    /// Returns the sRGB value of linear light out of 65535.
+    ///
+    /// # Notes
+    /// - The light is rounded to the nearest of the 4096 steps, so every sRGB value
+    ///   decodes and encodes back to itself.
+    #[inline]
+    fn encode(&self, linear: u32) -> u8 {
+        self.to_srgb[((linear + 8) >> 4).min(4095) as usize]
+    }
+}
+
+/// Returns the sRGB tables, computing them on first use.
+fn linear_tables() -> &'static LinearTables {
+    static TABLES: OnceLock<LinearTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut to_linear = [0u16; 256];
+        for (srgb, linear) in to_linear.iter_mut().enumerate() {
+            let value = srgb as f64 / 255.0;
+            let light = if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            };
+            *linear = (light * 65535.0).round() as u16;
+        }
+        let mut to_srgb = [0u8; 4096];
+        for (linear, srgb) in to_srgb.iter_mut().enumerate() {
+            let light = linear as f64 / 4095.0;
+            let value = if light <= 0.0031308 {
+                light * 12.92
+            } else {
+                1.055 * light.powf(1.0 / 2.4) - 0.055
+            };
+            *srgb = (value * 255.0).round() as u8;
+        }
+        LinearTables { to_linear, to_srgb }
+    })
+}
+
+/// The 8-bit RGB or RGBA buffer of an image.
+struct Pixels<'a> {
+    data: Cow<'a, [u8]>,
+    /// 3 for RGB, 4 for RGBA.
+    channels: usize,
+}
+
+impl<'a> Pixels<'a> {
+    /// Borrows the buffer of an 8-bit RGB or RGBA image, and converts any other to RGBA.
+    fn of(image: &'a DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageRgb8(rgb) => Self {
+                data: Cow::Borrowed(rgb.as_raw()),
+                channels: 3,
+            },
+            DynamicImage::ImageRgba8(rgba) => Self {
+                data: Cow::Borrowed(rgba.as_raw()),
+                channels: 4,
+            },
+            other => Self {
+                data: Cow::Owned(other.to_rgba8().into_raw()),
+                channels: 4,
+            },
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use log::debug;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Memory budget of the decoded image cache, see `Merger::decode_cache_bytes`.
+pub const DEFAULT_DECODE_CACHE_BYTES: usize = 512 * 1024 * 1024;
+
+/// Decoded images, and overlays resized to their base, kept for reuse within a budget.
+///
+/// With the repeat-last and loop mismatch policies the same file is merged many times;
+/// the cache decodes and resizes it once. The least recently used images are evicted
+/// when the budget is exceeded.
+pub struct DecodeCache {
+    budget_bytes: usize,
+    used_bytes: usize,
+    /// Least recently used first.
+    entries: VecDeque<CachedImage>,
+}
+
+struct CachedImage {
+    path: PathBuf,
+    /// The size the image was resized to, `None` for the image as decoded.
+    size: Option<(u32, u32)>,
+    image: Arc<DynamicImage>,
+    bytes: usize,
+}
+
+impl DecodeCache {
+    /// Creates an empty cache holding at most `budget_bytes` of decoded pixels.
+    ///
+    /// A budget of 0 disables caching; every image is decoded when it is needed.
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes,
+            used_bytes: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Returns the decoded image at `path`, decoding it if it is not cached.
+    pub fn image(&mut self, path: &Path) -> Result<Arc<DynamicImage>> {
+        if let Some(image) = self.lookup(path, None) {
+            return Ok(image);
+        }
+        let image = image::open(path)
+            .with_context(|| format!("Failed to open image {}", path.display()))?;
+        Ok(self.insert(path, None, image))
+    }
+
+    /// Returns the image at `path` resized to `width` x `height`.
+    ///
+    /// # Notes
+    /// - An image that already has the requested size is returned as decoded.
+    pub fn resized(&mut self, path: &Path, width: u32, height: u32) -> Result<Arc<DynamicImage>> {
+        let size = Some((width, height));
+        if let Some(image) = self.lookup(path, size) {
+            return Ok(image);
+        }
+        let decoded = self.image(path)?;
+        if (decoded.width(), decoded.height()) == (width, height) {
+            return Ok(decoded);
+        }
+        let resized = decoded.resize(width, height, image::imageops::FilterType::Lanczos3);
+        Ok(self.insert(path, size, resized))
+    }
+
+    /// Finds a cached image and marks it as the most recently used.
+    fn lookup(&mut self, path: &Path, size: Option<(u32, u32)>) -> Option<Arc<DynamicImage>> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.path == path && entry.size == size)?;
+        let entry = self.entries.remove(position)?;
+        let image = entry.image.clone();
+        self.entries.push_back(entry);
+        Some(image)
+    }
+
+    /// Caches an image, evicting the least recently used ones to stay within the budget.
+    fn insert(
+        &mut self,
+        path: &Path,
+        size: Option<(u32, u32)>,
+        image: DynamicImage,
+    ) -> Arc<DynamicImage> {
+        let image = Arc::new(image);
+        let bytes = image.as_bytes().len();
+        if bytes > self.budget_bytes {
+            return image;
+        }
+        while self.used_bytes + bytes > self.budget_bytes {
+            let Some(evicted) = self.entries.pop_front() else {
+                break;
+            };
+            debug!("Evicting {:?} from the decode cache", evicted.path);
+            self.used_bytes -= evicted.bytes;
+        }
+        self.used_bytes += bytes;
+        self.entries.push_back(CachedImage {
+            path: path.to_path_buf(),
+            size,
+            image: image.clone(),
+            bytes,
+        });
+        image
+    }
+}
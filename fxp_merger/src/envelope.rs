@@ -0,0 +1,80 @@
+use anyhow::{bail, Result};
+use std::fmt;
+use std::path::PathBuf;
+
+use fxp_audio::Loudness;
+use fxp_output::FrameRate;
+
+/// Share of the level kept from one frame to the next as the sound fades, so the
+/// opacity falls off smoothly after a beat instead of flickering.
+const RELEASE: f32 = 0.8;
+
+/// An opacity that follows the loudness of an audio track, frame by frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioOpacity {
+    /// The audio whose loudness drives the opacity.
+    pub audio: PathBuf,
+    /// Frame rate the merged frames play at, which aligns each pair with the audio.
+    pub fps: FrameRate,
+    /// Opacity of the quietest frames.
+    pub min: f32,
+    /// Opacity of the loudest frames.
+    pub max: f32,
+}
+
+impl AudioOpacity {
+    /// Checks that the opacity range lies within 0 to 1 and is not reversed.
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.min) || !(0.0..=1.0).contains(&self.max) {
+            bail!(
+                "The opacity range {} to {} must lie within 0 to 1",
+                self.min,
+                self.max
+            );
+        }
+        if self.min > self.max {
+            bail!(
+                "The opacity range {} to {} is reversed, expected the lower opacity first",
+                self.min,
+                self.max
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the opacity of each of the first `frames` frames.
+    ///
+    /// # Returns
+    /// - `Result<Vec<f32>>`: One opacity per frame, or an error if ffmpeg cannot decode
+    ///   the audio.
+    ///
+    /// # Notes
+    /// - The loudness of each frame is the RMS of the audio it spans, relative to the
+    ///   loudest frame, and fades out at `RELEASE` per frame.
+    /// - Frames past the end of the audio are as quiet as silence.
+    pub fn opacities(&self, frames: usize) -> Result<Vec<f32>> {
+        let loudness = Loudness::analyze(&self.audio, self.fps)?;
+        let mut level = 0.0;
+        Ok(loudness
+            .levels(frames)
+            .into_iter()
+            .map(|value| {
+                level = value.max(level * RELEASE);
+                self.min + (self.max - self.min) * level
+            })
+            .collect())
+    }
+}
+
+impl fmt::Display for AudioOpacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} to {}, following the loudness of {} at {} fps",
+            self.min,
+            self.max,
+            self.audio.display(),
+            self.fps
+        )
+    }
+}
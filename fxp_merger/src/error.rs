@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+use fxp_output::FailureKind;
+
+/// Failures of the Merger that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum MergerError {
+    /// The directories hold a different number of images and the mismatch policy is
+    /// `MismatchPolicy::Error`.
+    #[error(
+        "Directories hold a different number of images ({0} and {1}); use --mismatch-policy truncate, repeat-last or loop to merge them anyway"
+    )]
+    LengthMismatch(usize, usize),
+    /// An output past the end of the first directory would be named like one of its
+    /// images and overwrite its merge.
+    #[error(
+        "The output {0} past the end of the first directory is named like one of its images; rename the images of the first directory to number them in order"
+    )]
+    NameCollision(String),
+}
+
+impl MergerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            MergerError::LengthMismatch(..) | MergerError::NameCollision(_) => {
+                FailureKind::InvalidInput
+            }
+        }
+    }
+}
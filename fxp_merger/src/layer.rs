@@ -0,0 +1,93 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use fxp_filenames::FrameSource;
+
+use crate::blend::BlendMode;
+
+/// A directory of images stacked over the first directory, with how it is blended.
+///
+/// The layers are blended in order, each onto the result of the ones before, like the
+/// layer stack of an image editor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub source: FrameSource,
+    /// Opacity of the layer in every output; `None` blends it with the output's opacity,
+    /// as given by `--opacity` or following the audio.
+    pub opacity: Option<f32>,
+    /// How the layer's colors combine with the images below it.
+    pub mode: BlendMode,
+}
+
+impl From<FrameSource> for Layer {
+    /// A layer blended normally with the output's opacity.
+    fn from(source: FrameSource) -> Self {
+        Self {
+            source,
+            opacity: None,
+            mode: BlendMode::default(),
+        }
+    }
+}
+
+impl FromStr for Layer {
+    type Err = String;
+
+    /// Parses a directory or glob pattern, optionally followed by `@` and its opacity, its
+    /// blend mode or both, e.g. `grain@0.3`, `glow@screen` or `glow@0.6,screen`.
+    ///
+    /// # Notes
+    /// - An existing directory whose name contains `@` is read as a directory as a whole.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut layer = Layer::from(FrameSource::new(s, false));
+        let Some((input, style)) = s.rsplit_once('@') else {
+            return Ok(layer);
+        };
+        if Path::new(s).exists() {
+            return Ok(layer);
+        }
+        layer.source = FrameSource::new(input, false);
+        for part in style.split(',') {
+            match part.parse::<f32>() {
+                Ok(_) if layer.opacity.is_some() => {
+                    return Err(format!("Layer '{}' has two opacities", s))
+                }
+                Ok(opacity) if (0.0..=1.0).contains(&opacity) => layer.opacity = Some(opacity),
+                Ok(opacity) => {
+                    return Err(format!(
+                        "The opacity {} of layer '{}' must lie within 0 to 1",
+                        opacity, s
+                    ))
+                }
+                Err(_) => layer.mode = part.parse()?,
+            }
+        }
+        Ok(layer)
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some(style) = self.style() {
+            write!(f, "@{}", style)?;
+        }
+        Ok(())
+    }
+}
+
+impl Layer {
+    /// Returns the opacity and blend mode as written after the `@`, e.g. `0.6,screen`;
+    /// `None` for a layer blended normally with the output's opacity.
+    pub fn style(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(opacity) = self.opacity {
+            parts.push(opacity.to_string());
+        }
+        if self.mode != BlendMode::Normal {
+            parts.push(self.mode.to_string());
+        }
+        (!parts.is_empty()).then(|| parts.join(","))
+    }
+}
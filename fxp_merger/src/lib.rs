@@ -1,4 +1,20 @@
+mod blend;
+mod decode;
+mod envelope;
+mod error;
+mod layer;
 mod merge;
 mod merger;
+mod mismatch;
+mod pip;
+mod region;
 
+pub use blend::{blend, blend_deep, blend_image, BlendMode, Blending, Rgba16Image};
+pub use decode::DEFAULT_DECODE_CACHE_BYTES;
+pub use envelope::AudioOpacity;
+pub use error::MergerError;
+pub use layer::Layer;
 pub use merger::Merger;
+pub use mismatch::MismatchPolicy;
+pub use pip::{PictureInPicture, PipBorder, PipPosition, DEFAULT_PIP_MARGIN, DEFAULT_PIP_SCALE};
+pub use region::{Region, DEFAULT_FEATHER};
@@ -1,42 +1,130 @@
-use anyhow::{anyhow, Context, Result};
-use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
-use indicatif::{ProgressBar, ProgressStyle};
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use indicatif::ProgressStyle;
 use log::debug;
-use std::collections::BTreeMap;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::info_span;
 
-/// Merges images from two directories into a single output directory.
+use fxp_cache::Cache;
+use fxp_modes::Modes;
+use fxp_output::progress_bar;
+use fxp_stream::{decoded_size, is_deep_color, FrameEncoder, IccProfile, MemoryLimit};
+
+use crate::blend::{blend_image, BlendMode, Blending};
+use crate::decode::DecodeCache;
+use crate::layer::Layer;
+use crate::mismatch::MergePair;
+use crate::pip::PictureInPicture;
+use crate::region::Region;
+
+/// How the overlays of every pair are stacked over its base.
+pub struct Stacking<'a> {
+    /// The layers, in stacking order, with their own opacities and blend modes.
+    pub layers: &'a [Layer],
+    /// The opacity of each pair, used instead of the outputs' opacities.
+    pub pair_opacities: Option<&'a [f32]>,
+    /// Whether to composite using alpha and whether to mix in linear light.
+    pub blending: Blending,
+    /// The region the layers are limited to, with the width of its feathered edge.
+    pub region: Option<(Region, u32)>,
+    /// Shrink the layers into a picture over the base instead of resizing them to it.
+    pub pip: Option<PictureInPicture>,
+}
+
+impl Stacking<'_> {
+    /// Returns the opacity of every layer of the pair at `position` in an output blended
+    /// with `opacity`.
+    fn opacities(&self, position: usize, opacity: f32) -> Vec<f32> {
+        let opacity = self
+            .pair_opacities
+            .map_or(opacity, |opacities| opacities[position]);
+        self.layers
+            .iter()
+            .map(|layer| layer.opacity.unwrap_or(opacity))
+            .collect()
+    }
+
+    /// Returns how the layers are mixed; a picture-in-picture is composited by its alpha,
+    /// which leaves the base showing around it.
+    fn blending(&self) -> Blending {
+        Blending {
+            respect_alpha: self.blending.respect_alpha || self.pip.is_some(),
+            ..self.blending
+        }
+    }
+}
+
+/// The outputs every pair is written to.
+struct Outputs<'a> {
+    /// The opacities to blend with, each with the directory its images are saved to.
+    directories: &'a [(f32, &'a Path)],
+    /// The cache of each directory.
+    caches: Mutex<Vec<Cache>>,
+    /// How the blends are encoded.
+    encoder: &'a FrameEncoder,
+}
+
+/// Merges images from two or more directories into one output directory per opacity.
 ///
-/// This function combines pairs of images from two directories, blending them with specified opacity.
-/// It ensures consistent output formatting and handles errors gracefully.
+/// This function stacks the overlays of each pair over its base, blending them with each
+/// of the given opacities. It ensures consistent output formatting and handles errors gracefully.
 ///
 /// # Parameters
-/// - `directory1_files`: BTreeMap of images from the first directory
-/// - `directory2_files`: BTreeMap of images from the second directory
-/// - `output_directory`: Path to save the merged images
-/// - `opacity`: Opacity level for blending images
-/// - `total_images`: Total number of images to process
+/// - `pairs`: The base/overlays pairs to blend, in order, with their output filenames
+/// - `outputs`: The opacities to blend with, each with the directory its images are saved to
+/// - `stacking`: The opacity and blend mode of every layer, the opacity of each pair
+///   used instead of the outputs' opacities, how the layers are mixed and the region
+///   they are limited to
+/// - `encoder`: The format the blends are written in
+/// - `decode_cache_bytes`: Memory budget for decoded images reused across pairs
+/// - `jobs`: Number of pairs merged at the same time
+/// - `memory`: Limit on the memory the pairs merged at the same time take
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the merge operation
 ///
 /// # Notes
-/// - Images are resized to match before blending
-/// - Output filenames match the first directory's files
-/// - Both directories must have matching image indices for merging
-pub fn merge_all_images<P: AsRef<Path>>(
-    directory1_files: &BTreeMap<u32, PathBuf>,
-    directory2_files: &BTreeMap<u32, PathBuf>,
-    output_directory: P,
-    opacity: f32,
-    total_images: usize,
+/// - Each pair is decoded, and its overlays resized to match the base, once for all opacities
+/// - Each overlay is blended onto the result of the ones before it; with a region, only
+///   the part of the result inside it is kept over the base
+/// - With a picture-in-picture, the overlays are shrunk into a picture composited over
+///   the base instead of being resized to it
+/// - Images used by several pairs, as with the repeat-last and loop mismatch policies, are
+///   kept decoded within the memory budget, see `DecodeCache`
+/// - Pairing, and therefore the number of outputs, is decided by the mismatch policy
+/// - With `pair_opacities`, as for an opacity following the audio, every pair is blended
+///   with its own opacity; layers with an opacity of their own keep it
+/// - Pairs whose inputs and opacity are unchanged since the last run into the same output
+///   directory are skipped, see `fxp_cache::Cache`
+/// - With an output format, every blend is written in it, its extension replaced by the
+///   format's, see `FrameEncoder::output_path`
+/// - Each of the `jobs` workers takes the next pair as it finishes one, and waits before
+///   decoding it while the other workers hold too much of `memory`; every worker keeps
+///   its own share of the decode cache, which is not counted against `memory`
+/// - The first failure stops all workers once their current pair is done
+pub fn merge_all_images(
+    pairs: &[MergePair],
+    outputs: &[(f32, &Path)],
+    stacking: &Stacking,
+    encoder: &FrameEncoder,
+    decode_cache_bytes: usize,
+    jobs: usize,
+    memory: &MemoryLimit,
 ) -> Result<()> {
-    let output_directory = output_directory.as_ref();
-    debug!("Starting image merge with opacity: {}", opacity);
-    debug!("Output directory: {:?}", output_directory);
-    debug!("Total images to process: {}", total_images);
+    debug!(
+        "Merging {} pairs with opacities {:?}",
+        pairs.len(),
+        outputs
+            .iter()
+            .map(|(opacity, _)| opacity)
+            .collect::<Vec<_>>()
+    );
 
-    let pb = ProgressBar::new(total_images as u64);
+    let pb = progress_bar(pairs.len() as u64);
     pb.set_style(
         ProgressStyle::default_bar()
             .template(
@@ -45,61 +133,56 @@ pub fn merge_all_images<P: AsRef<Path>>(
             .unwrap(),
     );
 
-    debug!("Beginning image processing loop...");
-    for (index, file1) in directory1_files.iter().take(total_images) {
-        debug!("Processing index: {}", index);
-        debug!("Directory1 file: {:?}", file1);
-
-        if let Some(file2) = directory2_files.get(index) {
-            debug!("Found matching file in directory2: {:?}", file2);
-
-            // Load images
-            debug!("Loading images...");
-            let img1 = image::open(file1)
-                .context("Failed to open image from directory1")
-                .map_err(|e| {
-                    debug!("Error opening {:?}: {}", file1, e);
-                    e
-                })?;
-
-            let img2 = image::open(file2)
-                .context("Failed to open image from directory2")
-                .map_err(|e| {
-                    debug!("Error opening {:?}: {}", file2, e);
-                    e
-                })?;
-
-            // Resize and blend
-            debug!("Resizing image2 to match image1 dimensions...");
-            let img2_resized = img2.resize(
-                img1.width(),
-                img1.height(),
-                image::imageops::FilterType::Lanczos3,
-            );
-
-            debug!("Blending images with opacity: {}", opacity);
-            let blended = blend_images(&img1, &img2_resized, opacity);
-
-            // Save result
-            let output_path = output_directory.join(file1.file_name().ok_or_else(|| {
-                debug!("Failed to get filename from {:?}", file1);
-                anyhow!("Failed to get file name from directory1")
-            })?);
-
-            debug!("Saving blended image to: {:?}", output_path);
-            blended
-                .save(&output_path)
-                .context("Failed to save blended image")
-                .map_err(|e| {
-                    debug!("Error saving to {:?}: {}", output_path, e);
-                    e
-                })?;
-
-            pb.inc(1);
-            debug!("Processed {} images", pb.position());
-        } else {
-            debug!("No matching file in directory2 for index {}", index);
+    let outputs = Outputs {
+        directories: outputs,
+        caches: Mutex::new(
+            outputs
+                .iter()
+                .map(|(_, directory)| Cache::open(directory, Modes::Merger))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        encoder,
+    };
+
+    let next = AtomicUsize::new(0);
+    let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    let workers = jobs.clamp(1, pairs.len().max(1));
+    debug!("Merging with {} workers", workers);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let mut decoded = DecodeCache::new(decode_cache_bytes / workers);
+                loop {
+                    if failure.lock().expect("a worker panicked").is_some() {
+                        break;
+                    }
+                    let position = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(pair) = pairs.get(position) else {
+                        break;
+                    };
+                    let result =
+                        merge_pair(pair, position, &outputs, stacking, &mut decoded, memory);
+                    if let Err(e) = result {
+                        failure.lock().expect("a worker panicked").get_or_insert(e);
+                        break;
+                    }
+                    pb.inc(1);
+                }
+            });
         }
+    });
+
+    // Keep what was merged so far even if a later pair failed.
+    for cache in outputs
+        .caches
+        .into_inner()
+        .expect("a worker panicked")
+        .iter()
+    {
+        cache.save()?;
+    }
+    if let Some(e) = failure.into_inner().expect("a worker panicked") {
+        return Err(e);
     }
 
     pb.finish_with_message("All images merged successfully!");
@@ -108,48 +191,166 @@ pub fn merge_all_images<P: AsRef<Path>>(
     Ok(())
 }
 
-/// Blends two images together with the specified opacity.
-///
-/// The opacity parameter controls the influence of the second image, where:
-/// - `0.0` means only the first image is visible.
-/// - `1.0` means only the second image is visible.
-/// - Values between `0.0` and `1.0` create a blend between the two images.
-///
-/// # Arguments
-/// * `img1` - The first image to blend.
-/// * `img2` - The second image to blend.
-/// * `opacity` - The opacity value (between `0.0` and `1.0`).
-///
-/// # Returns
-/// The blended image as an `RgbaImage`.
-fn blend_images(img1: &DynamicImage, img2: &DynamicImage, opacity: f32) -> RgbaImage {
-    // debug!("Starting blend_images function");
-    // debug!("Opacity: {:.2}", opacity);
-
-    let (width, height) = img1.dimensions();
-    // debug!("Image dimensions: {}x{}", width, height);
-
-    let mut blended = RgbaImage::new(width, height);
-    // debug!("Created a new RgbaImage for the blended result");
+/// Merges one pair, the one at `position`, with every opacity whose output is missing or
+/// out of date.
+fn merge_pair(
+    pair: &MergePair,
+    position: usize,
+    outputs: &Outputs,
+    stacking: &Stacking,
+    decoded: &mut DecodeCache,
+    memory: &MemoryLimit,
+) -> Result<()> {
+    let overlays = pair
+        .overlays
+        .iter()
+        .map(|overlay| overlay.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let _span = info_span!(
+        "merge",
+        base = %pair.base.display(),
+        overlays = %overlays,
+        output_name = %pair.output_name.to_string_lossy()
+    )
+    .entered();
+    debug!("Directory1 file: {:?}", pair.base);
+    debug!("Overlay files: {}", overlays);
+    let modes: Vec<BlendMode> = stacking.layers.iter().map(|layer| layer.mode).collect();
+    let inputs: Vec<&Path> = pair.inputs().map(|path| path.as_path()).collect();
 
-    for y in 0..height {
-        for x in 0..width {
-            let px1 = img1.get_pixel(x, y);
-            let px2 = img2.get_pixel(x, y);
+    // Find the opacities whose output is missing or out of date.
+    let mut stale = Vec::new();
+    for (index, ((opacity, directory), cache)) in outputs
+        .directories
+        .iter()
+        .zip(outputs.caches.lock().expect("a worker panicked").iter_mut())
+        .enumerate()
+    {
+        let opacities = stacking.opacities(position, *opacity);
+        let output_path = outputs
+            .encoder
+            .output_path(&directory.join(&pair.output_name));
+        let mut parameters = cache_parameters(&opacities, stacking);
+        parameters.extend(outputs.encoder.cache_parameter());
+        let key = cache.key(&inputs, &parameters)?;
+        if cache.is_fresh(&output_path, &key) {
+            debug!("{:?} is unchanged, skipping", output_path);
+        } else {
+            stale.push((index, opacities, output_path, key));
+        }
+    }
+    if stale.is_empty() {
+        return Ok(());
+    }
 
-            // debug!("Pixel at ({}, {}): img1={:?}, img2={:?}", x, y, px1, px2);
+    // The base, the overlays, the overlays resized to the base and the blend; a stack
+    // also holds the image below each layer and the colors its blend mode combines, and
+    // a region the blend limited to it.
+    let base_size = decoded_size(&pair.base);
+    let stacked = pair.overlays.len() > 1 || modes.iter().any(|&mode| mode != BlendMode::Normal);
+    let _permit = memory.acquire(
+        (2 + pair.overlays.len()
+            + 2 * usize::from(stacked)
+            + usize::from(stacking.region.is_some()))
+            * base_size
+            + pair
+                .overlays
+                .iter()
+                .map(|overlay| decoded_size(overlay))
+                .sum::<usize>(),
+    );
+    debug!("Loading images...");
+    let base = decoded.image(&pair.base)?;
+    let overlays = {
+        let _span = info_span!("resize", width = %base.width(), height = %base.height()).entered();
+        debug!("Resizing the overlays to match the base dimensions...");
+        pair.overlays
+            .iter()
+            .map(|overlay| match &stacking.pip {
+                Some(pip) => {
+                    let image = decoded.image(overlay)?;
+                    let (width, height) = pip.picture_size(
+                        (base.width(), base.height()),
+                        (image.width(), image.height()),
+                    );
+                    let picture = decoded.resized(overlay, width, height)?;
+                    Ok(Arc::new(pip.layer((base.width(), base.height()), &picture)))
+                }
+                None => decoded.resized(overlay, base.width(), base.height()),
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
 
-            let r = ((px1[0] as f32) * (1.0 - opacity) + (px2[0] as f32) * opacity) as u8;
-            let g = ((px1[1] as f32) * (1.0 - opacity) + (px2[1] as f32) * opacity) as u8;
-            let b = ((px1[2] as f32) * (1.0 - opacity) + (px2[2] as f32) * opacity) as u8;
-            let a = 255;
+    // The blends keep the colors of the base, so they carry its profile.
+    let profile = IccProfile::read(&pair.base);
+    for (index, opacities, output_path, key) in stale {
+        debug!("Blending images with opacities: {:?}", opacities);
+        let mut blended = stack(&base, &overlays, &modes, &opacities, stacking.blending());
+        if let Some((region, feather)) = stacking.region {
+            blended = region.limit(&base, &blended, feather);
+        }
+        debug!("Saving blended image to: {:?}", output_path);
+        outputs
+            .encoder
+            .save(&blended, profile.as_ref(), &output_path)
+            .with_context(|| format!("Failed to save blended image {:?}", output_path))?;
+        outputs.caches.lock().expect("a worker panicked")[index].record(&output_path, key)?;
+    }
+    Ok(())
+}
 
-            // debug!("Blended pixel at ({}, {}): R={}, G={}, B={}, A={}", x, y, r, g, b, a);
+/// Blends each overlay onto the result of the ones before it, starting from the base.
+///
+/// # Notes
+/// - The result has 16 bits per channel once an image with more than 8 is involved.
+fn stack(
+    base: &DynamicImage,
+    overlays: &[impl AsRef<DynamicImage>],
+    modes: &[BlendMode],
+    opacities: &[f32],
+    blending: Blending,
+) -> DynamicImage {
+    let mut merged: Option<DynamicImage> = None;
+    for ((overlay, mode), opacity) in overlays.iter().zip(modes).zip(opacities) {
+        let below = match merged.take() {
+            Some(image) => Cow::Owned(image),
+            None => Cow::Borrowed(base),
+        };
+        let colors = mode.apply(&below, overlay.as_ref());
+        merged = Some(blend_image(&below, &colors, *opacity, blending));
+    }
+    merged.unwrap_or_else(|| {
+        if is_deep_color(base) {
+            DynamicImage::ImageRgba16(base.to_rgba16())
+        } else {
+            DynamicImage::ImageRgba8(base.to_rgba8())
+        }
+    })
+}
 
-            blended.put_pixel(x, y, Rgba([r, g, b, a]));
+/// Returns the parameters an output depends on besides its inputs.
+///
+/// Blending options, blend modes and the region are only recorded when enabled, so
+/// outputs of earlier runs stay fresh.
+fn cache_parameters(opacities: &[f32], stacking: &Stacking) -> Vec<String> {
+    let mut parameters: Vec<String> = opacities.iter().map(f32::to_string).collect();
+    if stacking.blending().respect_alpha {
+        parameters.push("respect-alpha".to_string());
+    }
+    if stacking.blending.linear {
+        parameters.push("linear-blend".to_string());
+    }
+    for (index, layer) in stacking.layers.iter().enumerate() {
+        if layer.mode != BlendMode::Normal {
+            parameters.push(format!("layer {} {}", index + 1, layer.mode));
         }
     }
-
-    // debug!("Finished blending images");
-    blended
+    if let Some((region, feather)) = stacking.region {
+        parameters.push(format!("region {} feather {}", region.arg(), feather));
+    }
+    if let Some(pip) = &stacking.pip {
+        parameters.push(format!("pip {:?}", pip));
+    }
+    parameters
 }
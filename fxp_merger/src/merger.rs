@@ -1,23 +1,73 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use log::debug;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
 
-use crate::merge::merge_all_images;
+use crate::blend::Blending;
+use crate::decode::DEFAULT_DECODE_CACHE_BYTES;
+use crate::envelope::AudioOpacity;
+use crate::layer::Layer;
+use crate::merge::{merge_all_images, Stacking};
+use crate::mismatch::{pair_images, MergePair, MismatchPolicy};
+use crate::pip::PictureInPicture;
+use crate::region::{Region, DEFAULT_FEATHER};
 
 use fxp_modes::Modes;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
 
-use fxp_filenames::FileOperations;
+use fxp_filenames::FrameGroup;
+use fxp_filenames::FrameSelection;
+use fxp_filenames::FrameSource;
+
+use fxp_stream::{default_max_memory, FrameEncoder, MemoryLimit};
 
 pub struct Merger {
-    opacity: f32,
-    directory1_files: BTreeMap<u32, PathBuf>,
-    directory2_files: BTreeMap<u32, PathBuf>,
-    output_directory: PathBuf,
-    total_images: usize,
+    directory1: FrameSource,
+    /// The directories stacked over `directory1`, in order.
+    layers: Vec<Layer>,
+    mismatch_policy: MismatchPolicy,
+    /// Every opacity to merge with and its output directory.
+    outputs: Vec<(f32, PathBuf)>,
+    pairs: Vec<MergePair>,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Memory budget for decoded images reused across pairs; `new` sets
+    /// `DEFAULT_DECODE_CACHE_BYTES`, 0 disables the cache.
+    pub decode_cache_bytes: usize,
+    /// Composite using the alpha channels of both images instead of writing opaque
+    /// blends; `new` sets `false`.
+    pub respect_alpha: bool,
+    /// Mix in linear light instead of sRGB-encoded values; `new` sets `false`.
+    pub linear_blend: bool,
+    /// Merge only these pairs, numbered from 1 in pairing order; `new` selects all of them.
+    pub selection: FrameSelection,
+    /// Blend each pair with an opacity following the loudness of an audio track instead
+    /// of a fixed one; `new` sets `None`.
+    pub audio_opacity: Option<AudioOpacity>,
+    /// Blend the layers only within this part of the frames, keeping the first
+    /// directory's images elsewhere; `new` sets `None`, blending the whole frames.
+    pub region: Option<Region>,
+    /// Width in pixels over which the blend fades in inside the edge of `region`; `new`
+    /// sets `DEFAULT_FEATHER`.
+    pub feather: u32,
+    /// Shrink the second directory's images into a picture over the first's instead of
+    /// blending them over the whole frame; `new` sets `None`. Takes a single layer.
+    pub pip: Option<PictureInPicture>,
+    /// The format the merged images are written in; `new` keeps the format of the first
+    /// directory's images.
+    pub encoder: FrameEncoder,
+    /// Number of pairs merged at the same time; `new` sets 1.
+    pub jobs: usize,
+    /// Memory the pairs merged at the same time may take, in bytes; `new` sets
+    /// `fxp_stream::default_max_memory()`, half of the system memory.
+    pub max_memory: usize,
 }
 
 impl Merger {
@@ -30,6 +80,8 @@ impl Merger {
     /// - `directory2`: The second directory containing images to process.
     /// - `opacity`: The opacity value used for image merging (0.0 to 1.0).
     /// - `output_directory`: Optional output directory for the merged images.
+    /// - `mismatch_policy`: How to pair directories holding a different number of images.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: A new `Merger` instance or an error if initialization fails.
@@ -43,127 +95,522 @@ impl Merger {
         directory2: String,
         opacity: f32,
         output_directory: Option<String>,
+        mismatch_policy: MismatchPolicy,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
-        // Convert directory strings into PathBufs.
-        let directory1_path = PathBuf::from(&directory1);
-        let directory2_path = PathBuf::from(&directory2);
+        Self::with_opacities(
+            directory1.into(),
+            vec![FrameSource::from(directory2).into()],
+            &[opacity],
+            output_directory,
+            mismatch_policy,
+            collision,
+        )
+    }
+
+    /// Creates a `Merger` that blends the same pairs with several opacities in one pass.
+    ///
+    /// # Parameters
+    /// - `directory1`: The first directory, optionally with its subdirectories, or glob
+    ///   pattern of the images to process.
+    /// - `layers`: The directories or glob patterns stacked over the first, in order; a
+    ///   single one for a two-way merge.
+    /// - `opacities`: The opacity values (0.0 to 1.0), each merged into its own directory.
+    /// - `output_directory`: Optional output directory for the merged images.
+    /// - `mismatch_policy`: How to pair directories holding a different number of images.
+    /// - `collision`: What to do if an output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: A new `Merger` instance or an error if initialization fails.
+    ///
+    /// # Notes
+    /// - With several opacities, an explicit output directory `out` becomes `out_<opacity>`
+    ///   for each of them; the default directories already hold the opacity.
+    /// - Every image is decoded once for all opacities, see `merge_images`.
+    /// - The images of each subdirectory of `directory1` are paired with the same
+    ///   subdirectory of every layer and merged into the same subdirectory of the output;
+    ///   a layer holding images in its root only is paired with every subdirectory.
+    /// - Each layer is blended onto the result of the ones before it, with its own opacity
+    ///   and blend mode, see `Layer`.
+    pub fn with_opacities(
+        directory1: FrameSource,
+        layers: Vec<Layer>,
+        opacities: &[f32],
+        output_directory: Option<String>,
+        mismatch_policy: MismatchPolicy,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        let directory1_path = directory1.root();
+        let opacities = distinct_opacities(opacities)?;
+        check_layers(&layers)?;
 
         let mode: Modes = Modes::Merger;
         let output: Output = mode.into();
 
-        let output_directory_path = match output {
-            Output::Merger(merger_output) => {
-                merger_output.create_output((
-                    directory1_path.clone(), // using directory1 as base
-                    output_directory,
-                    opacity,
-                ))?
-            }
-            _ => unreachable!("Expected Merger mode"),
-        };
+        let mut outputs = Vec::with_capacity(opacities.len());
+        for &opacity in &opacities {
+            let output_directory_path = match &output {
+                Output::Merger(merger_output) => merger_output.create_output(
+                    (
+                        directory1_path.clone(), // using directory1 as base
+                        output_for_opacity(&output_directory, opacity, opacities.len()),
+                        opacity,
+                    ),
+                    collision,
+                )?,
+                _ => unreachable!("Expected Merger mode"),
+            };
+            outputs.push((opacity, output_directory_path));
+        }
 
         // Set up image processing (assuming this no longer returns an output directory).
-        let (directory1_files, directory2_files, total_images) =
-            setup_image_processing(directory1_path.clone(), directory2_path.clone())?;
+        let pairs = setup_image_processing(&directory1, &layers, mismatch_policy)?;
 
         Ok(Self {
-            opacity,
-            directory1_files,
-            directory2_files,
-            output_directory: output_directory_path,
-            total_images,
+            directory1,
+            layers,
+            mismatch_policy,
+            outputs,
+            pairs,
+            in_place: false,
+            decode_cache_bytes: DEFAULT_DECODE_CACHE_BYTES,
+            respect_alpha: false,
+            linear_blend: false,
+            selection: FrameSelection::default(),
+            audio_opacity: None,
+            region: None,
+            feather: DEFAULT_FEATHER,
+            pip: None,
+            encoder: FrameEncoder::default(),
+            jobs: 1,
+            max_memory: default_max_memory(),
         })
     }
+
+    /// Resolves what `new` and `merge_images` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `directory1`: The first directory containing images to process.
+    /// - `directory2`: The second directory containing images to process.
+    /// - `opacity`: The opacity value used for image merging (0.0 to 1.0).
+    /// - `output_directory`: Optional output directory for the merged images.
+    /// - `mismatch_policy`: How to pair directories holding a different number of images.
+    /// - `selection`: The pairs to merge.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if image validation fails.
+    pub fn plan(
+        directory1: String,
+        directory2: String,
+        opacity: f32,
+        output_directory: Option<String>,
+        mismatch_policy: MismatchPolicy,
+        selection: &FrameSelection,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        Self::plan_with_opacities(
+            directory1.into(),
+            &[FrameSource::from(directory2).into()],
+            &[opacity],
+            output_directory,
+            mismatch_policy,
+            selection,
+            collision,
+        )
+    }
+
+    /// Resolves what `with_opacities` and `merge_images` would do, without touching the
+    /// filesystem.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, listing one output directory per opacity.
+    pub fn plan_with_opacities(
+        directory1: FrameSource,
+        layers: &[Layer],
+        opacities: &[f32],
+        output_directory: Option<String>,
+        mismatch_policy: MismatchPolicy,
+        selection: &FrameSelection,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let directory1_path = directory1.root();
+        let opacities = distinct_opacities(opacities)?;
+        check_layers(layers)?;
+        let pairs = setup_image_processing(&directory1, layers, mismatch_policy)?;
+
+        let mut plan = Plan::new(Modes::Merger).entry("first directory", &directory1);
+        match layers {
+            [layer] => plan = plan.entry("second directory", layer),
+            _ => {
+                for layer in layers {
+                    plan = plan.entry("layer", layer);
+                }
+            }
+        }
+        if directory1.is_nested() {
+            let directories: BTreeSet<&Path> = pairs
+                .iter()
+                .map(|pair| {
+                    Path::new(&pair.output_name)
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                })
+                .collect();
+            plan = plan.entry("directories", directories.len());
+        }
+        plan = plan.entry("mismatch policy", mismatch_policy);
+        if !selection.is_all() {
+            plan = plan.entry("selection", selection);
+        }
+        plan = plan
+            .entry("images to merge", selection.select_positions(pairs).len())
+            .entry("on existing output", collision);
+
+        let mode: Modes = Modes::Merger;
+        let output: Output = mode.into();
+        for &opacity in &opacities {
+            let output_directory_path = match &output {
+                Output::Merger(merger_output) => merger_output.plan_output(
+                    (
+                        directory1_path.clone(),
+                        output_for_opacity(&output_directory, opacity, opacities.len()),
+                        opacity,
+                    ),
+                    collision,
+                )?,
+                _ => unreachable!("Expected Merger mode"),
+            };
+            plan = plan
+                .entry("opacity", opacity)
+                .entry("output directory", output_directory_path.display());
+        }
+        Ok(plan)
+    }
 }
 
 impl Merger {
-    /// Merges images from two directories using specified opacity and returns the output directory or an error.
+    /// Merges images from two or more directories using the specified opacities and returns the output directories or an error.
     ///
-    /// This function combines images from two directories, applies the given opacity, and saves the merged results to the output directory.
-    ///
-    /// # Parameters
-    /// - `directory1_files`: The first directory containing image files to merge.
-    /// - `directory2_files`: The second directory containing image files to merge.
-    /// - `output_directory`: The directory where merged images will be saved.
-    /// - `opacity`: The opacity level applied during the merging process.
-    /// - `total_images`: The total number of images to be merged.
+    /// This function stacks the images of every layer over the first directory's, applies each opacity, and saves the merged results to its output directory.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The path to the output directory on success, or an error if merging fails.
+    /// - `Result<Vec<PathBuf>>`: The output directory of every opacity, in order, on success,
+    ///   or an error if merging fails.
     ///
     /// # Notes
     /// - The function provides contextual error information if the merging process fails.
-    pub fn merge_images(&self) -> Result<PathBuf> {
+    /// - Each pair is decoded and resized once for all opacities; decoded images are kept
+    ///   for later pairs within `decode_cache_bytes`.
+    /// - Images are staged and moved into the output directories only once all of them are
+    ///   merged, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Writes a `manifest.json` into every output directory recording its opacity and
+    ///   the input hashes.
+    /// - Only the pairs of `selection` are merged; the pairs are numbered from 1 in
+    ///   pairing order, which is the frame order when both directories start at frame 1.
+    /// - With `audio_opacity`, pair N is blended with the opacity of frame N of the audio
+    ///   envelope, so the overlay pulses with the music; this needs a single opacity.
+    /// - Layers with an opacity of their own are blended with it in every output.
+    /// - With `region`, the blend is kept only inside it, fading in over `feather` pixels,
+    ///   as for a spot effect.
+    /// - With `pip`, the second directory's images are shrunk into a picture, framed by
+    ///   its border and shadow, and composited over the first's with the opacity.
+    /// - With a format in `encoder`, the merged images are written in it, named with its
+    ///   extension.
+    /// - `jobs` pairs are merged at the same time, as long as their decoded images fit in
+    ///   `max_memory`; workers wait for each other otherwise.
+    pub fn merge_images(&self) -> Result<Vec<PathBuf>> {
+        let _span = info_span!(
+            "merger",
+            first_directory = %self.directory1,
+            layers = self.layers.len(),
+            outputs = self.outputs.len()
+        )
+        .entered();
+        if self.pip.is_some() && self.layers.len() > 1 {
+            bail!("A picture-in-picture takes a single second directory, not a stack of layers");
+        }
+        self.encoder.validate().map_err(anyhow::Error::msg)?;
+        let (pairs, pair_opacities) = match &self.audio_opacity {
+            Some(audio_opacity) => {
+                if self.outputs.len() > 1 {
+                    bail!("An opacity following the audio replaces the opacities, so only one output can be merged");
+                }
+                audio_opacity.validate()?;
+                let opacities = audio_opacity.opacities(self.pairs.len())?;
+                let selected: Vec<(MergePair, f32)> = self
+                    .selection
+                    .select_positions(self.pairs.iter().cloned().zip(opacities).collect());
+                let (pairs, opacities): (Vec<MergePair>, Vec<f32>) = selected.into_iter().unzip();
+                (pairs, Some(opacities))
+            }
+            None => (self.selection.select_positions(self.pairs.clone()), None),
+        };
+        let manifests: Vec<Manifest> = self
+            .outputs
+            .iter()
+            .map(|(opacity, _)| {
+                let mut manifest = Manifest::new(Modes::Merger)
+                    .parameter("first directory", &self.directory1)
+                    .parameter("second directory", &self.layers[0].source);
+                if self.layers.len() > 1 {
+                    let more_directories: Vec<String> = self.layers[1..]
+                        .iter()
+                        .map(|layer| layer.source.to_string())
+                        .collect();
+                    manifest = manifest.parameter_list("more directories", &more_directories);
+                }
+                if self.layers.iter().any(|layer| layer.style().is_some()) {
+                    let styles: Vec<String> = self
+                        .layers
+                        .iter()
+                        .map(|layer| layer.style().unwrap_or_default())
+                        .collect();
+                    manifest = manifest.parameter_list("layer styles", &styles);
+                }
+                manifest = manifest
+                    .parameter("mismatch policy", self.mismatch_policy)
+                    .parameter("opacity", opacity)
+                    .parameter("respect alpha", self.respect_alpha)
+                    .parameter("linear blend", self.linear_blend);
+                if self.directory1.recursive
+                    || self.layers.iter().any(|layer| layer.source.recursive)
+                {
+                    manifest = manifest.parameter("recursive", true);
+                }
+                if let Some(region) = &self.region {
+                    let name = match region {
+                        Region::Rectangle { .. } => "region",
+                        Region::Circle { .. } => "region circle",
+                    };
+                    manifest = manifest
+                        .parameter(name, region.arg())
+                        .parameter("region feather", self.feather);
+                }
+                if let Some(pip) = &self.pip {
+                    manifest = manifest
+                        .parameter("pip", pip.scale)
+                        .parameter("pip position", pip.position)
+                        .parameter("pip margin", pip.margin)
+                        .parameter("pip shadow", pip.shadow);
+                    if let Some(border) = pip.border {
+                        manifest = manifest.parameter("pip border", border);
+                    }
+                }
+                if let Some(format) = self.encoder.format {
+                    manifest = manifest
+                        .parameter("output format", format)
+                        .parameter("quality", self.encoder.quality)
+                        .parameter("lossless", self.encoder.lossless);
+                }
+                if let Some(range) = self.selection.range {
+                    manifest = manifest.parameter("frames", range);
+                }
+                if self.selection.every > 1 {
+                    manifest = manifest.parameter("every", self.selection.every);
+                }
+                if let Some(audio_opacity) = &self.audio_opacity {
+                    manifest = manifest
+                        .parameter("audio opacity", audio_opacity.audio.display())
+                        .parameter("audio fps", audio_opacity.fps)
+                        .parameter(
+                            "opacity range",
+                            format!("{},{}", audio_opacity.min, audio_opacity.max),
+                        )
+                        .inputs([&audio_opacity.audio]);
+                }
+                manifest.inputs(pairs.iter().flat_map(MergePair::inputs))
+            })
+            .collect();
+
+        let staged = self
+            .outputs
+            .iter()
+            .map(|(_, directory)| StagedDirectory::begin(directory, self.in_place))
+            .collect::<Result<Vec<_>>>()?;
+        for subdirectory in output_subdirectories(&pairs) {
+            for staged in &staged {
+                let directory = staged.path().join(&subdirectory);
+                fs::create_dir_all(&directory).with_context(|| {
+                    format!("Failed to create output directory {:?}", directory)
+                })?;
+            }
+        }
+        let targets: Vec<(f32, &Path)> = self
+            .outputs
+            .iter()
+            .zip(&staged)
+            .map(|((opacity, _), staged)| (*opacity, staged.path()))
+            .collect();
         merge_all_images(
-            &self.directory1_files,
-            &self.directory2_files,
-            &self.output_directory,
-            self.opacity,
-            self.total_images,
+            &pairs,
+            &targets,
+            &Stacking {
+                layers: &self.layers,
+                pair_opacities: pair_opacities.as_deref(),
+                blending: Blending {
+                    respect_alpha: self.respect_alpha,
+                    linear: self.linear_blend,
+                },
+                region: self.region.map(|region| (region, self.feather)),
+                pip: self.pip,
+            },
+            &self.encoder,
+            self.decode_cache_bytes,
+            self.jobs,
+            &MemoryLimit::new(self.max_memory),
         )
         .with_context(|| "Error merging images")?;
 
-        Ok(self.output_directory.clone())
+        let mut finished = Vec::with_capacity(staged.len());
+        for (staged, manifest) in staged.into_iter().zip(&manifests) {
+            manifest.write(staged.path())?;
+            finished.push(staged.finish(Modes::Merger, pairs.len())?);
+        }
+        Ok(finished)
     }
 }
 
-/// Sets up image processing by reading, validating, and preparing images from two directories.
+/// Checks that there is a layer to merge and that every layer's opacity lies within 0 to 1.
+fn check_layers(layers: &[Layer]) -> Result<()> {
+    if layers.is_empty() {
+        bail!("At least a second directory is needed to merge images");
+    }
+    if let Some(layer) = layers.iter().find(|layer| {
+        layer
+            .opacity
+            .is_some_and(|opacity| !(0.0..=1.0).contains(&opacity))
+    }) {
+        bail!("The opacity of layer {} must lie within 0 to 1", layer);
+    }
+    Ok(())
+}
+
+/// Removes repeated opacities, keeping their order, and checks that there is at least one.
+fn distinct_opacities(opacities: &[f32]) -> Result<Vec<f32>> {
+    let mut distinct: Vec<f32> = Vec::with_capacity(opacities.len());
+    for &opacity in opacities {
+        if !distinct.contains(&opacity) {
+            distinct.push(opacity);
+        }
+    }
+    if distinct.is_empty() {
+        bail!("At least one opacity is needed to merge images");
+    }
+    Ok(distinct)
+}
+
+/// Returns the explicit output directory of one opacity, `<output>_<opacity>` when
+/// several opacities are merged.
+fn output_for_opacity(
+    output_directory: &Option<String>,
+    opacity: f32,
+    opacities: usize,
+) -> Option<String> {
+    output_directory.as_ref().map(|output| {
+        if opacities > 1 {
+            format!("{}_{}", output.trim_end_matches(['/', '\\']), opacity)
+        } else {
+            output.clone()
+        }
+    })
+}
+
+/// Returns the subdirectories of the output the pairs are written to, besides its root.
+fn output_subdirectories(pairs: &[MergePair]) -> BTreeSet<PathBuf> {
+    pairs
+        .iter()
+        .filter_map(|pair| Path::new(&pair.output_name).parent())
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Sets up image processing by reading, validating, and preparing images from the directories.
 ///
-/// This function reads image files from two specified directories, validates them,
-/// and prepares them for further processing.
+/// This function reads image files from the first directory and every layer, validates
+/// them, and prepares them for further processing.
 ///
 /// # Parameters
-/// - `directory1`: Path to the first directory containing images to process.
-/// - `directory2`: Path to the second directory containing images to process.
+/// - `directory1`: The first directory or glob pattern of the images to process.
+/// - `layers`: The directories or glob patterns stacked over the first, in order.
+/// - `mismatch_policy`: How to pair directories holding a different number of images.
 ///
 /// # Returns
-/// - `Result<(BTreeMap<u32, PathBuf>, BTreeMap<u32, PathBuf>, usize)>`:
-///   - A tuple containing two maps of validated image paths (one for each directory)
-///     and the total number of images to be processed.
+/// - `Result<Vec<MergePair>>`: The image pairs to merge, in order.
 ///
 /// # Notes
-/// - Only processes images present in both directories.
+/// - Images are paired by frame number or by position, and as many as the mismatch
+///   policy decides, see `pair_images`.
+/// - Each directory of `directory1` is paired on its own with the directory of the same
+///   relative path in every layer, or with the root of a layer if that is the only
+///   directory of the layer holding images; the outputs keep the relative path.
 /// - Uses the `FileOperations` trait for loading and validating image files.
 /// - Logs debug information about the processing steps and image counts.
 fn setup_image_processing(
-    directory1: PathBuf,
-    directory2: PathBuf,
-) -> Result<(BTreeMap<u32, PathBuf>, BTreeMap<u32, PathBuf>, usize)> {
-    debug!("Reading images from directory1: {:?}", directory1);
-    debug!("Reading images from directory2: {:?}", directory2);
-
-    let dir1_images: Vec<PathBuf> = fs::read_dir(&directory1)?
-        .filter_map(|entry| entry.ok().map(|e| e.path()))
-        .collect();
-    let dir2_images: Vec<PathBuf> = fs::read_dir(&directory2)?
-        .filter_map(|entry| entry.ok().map(|e| e.path()))
-        .collect();
-
-    // Debug: Print the number of images found in each directory
-    debug!("Found {} images in directory1", dir1_images.len());
-    debug!("Found {} images in directory2", dir2_images.len());
+    directory1: &FrameSource,
+    layers: &[Layer],
+    mismatch_policy: MismatchPolicy,
+) -> Result<Vec<MergePair>> {
+    debug!("Reading images from directory1: {}", directory1);
 
     let mode = Modes::Merger;
 
-    // Debug: Load and validate files using FileOperations trait.
+    // Load and validate files using FileOperations trait.
     debug!("Loading files for directory1 using FileOperations");
-    let validated_dir1_images = mode.load_files(&dir1_images)?;
-    debug!("Loading files for directory2 using FileOperations");
-    let validated_dir2_images = mode.load_files(&dir2_images)?;
+    let groups1 = directory1.load_groups(mode)?;
+    let mut layer_groups = Vec::with_capacity(layers.len());
+    for layer in layers {
+        debug!("Loading files for layer {} using FileOperations", layer);
+        layer_groups.push(layer.source.load_groups(mode)?);
+    }
 
-    // Debug: Print the number of validated images in each directory.
     debug!(
-        "Validated {} images in directory1",
-        validated_dir1_images.len()
-    );
-    debug!(
-        "Validated {} images in directory2",
-        validated_dir2_images.len()
+        "Found {} directories in directory1 and {:?} in the layers",
+        groups1.len(),
+        layer_groups.iter().map(Vec::len).collect::<Vec<_>>()
     );
 
-    // Calculate the total images to be processed.
-    let total_images = std::cmp::min(validated_dir1_images.len(), validated_dir2_images.len());
-    debug!("Total images to be processed: {}", total_images);
+    let mut pairs = Vec::new();
+    for group1 in &groups1 {
+        let mut matched: Vec<&FrameGroup> = Vec::with_capacity(layers.len());
+        for (layer, groups) in layers.iter().zip(&layer_groups) {
+            let group = match groups.as_slice() {
+                [only] if only.relative.as_os_str().is_empty() => only,
+                _ => groups
+                    .iter()
+                    .find(|group| group.relative == group1.relative)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "{} has no directory {} to merge with {}",
+                            layer.source,
+                            group1.relative.display(),
+                            directory1.root().join(&group1.relative).display()
+                        )
+                    })?,
+            };
+            matched.push(group);
+        }
+        debug!(
+            "Pairing {} images with {:?} images in {:?}",
+            group1.frames.len(),
+            matched
+                .iter()
+                .map(|group| group.frames.len())
+                .collect::<Vec<_>>(),
+            group1.relative
+        );
+
+        // Pair the images according to the mismatch policy.
+        let layer_files: Vec<&BTreeMap<u32, PathBuf>> =
+            matched.iter().map(|group| &group.frames).collect();
+        let group_pairs = pair_images(&group1.frames, &layer_files, mismatch_policy)?;
+        pairs.extend(group_pairs.into_iter().map(|mut pair| {
+            pair.output_name = group1.relative.join(&pair.output_name).into_os_string();
+            pair
+        }));
+    }
+    debug!("Total images to be processed: {}", pairs.len());
 
-    Ok((validated_dir1_images, validated_dir2_images, total_images))
+    Ok(pairs)
 }
@@ -0,0 +1,212 @@
+use anyhow::Result;
+use log::debug;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use fxp_filenames::FramePadding;
+
+use crate::error::MergerError;
+
+/// How the Merger pairs directories holding a different number of images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MismatchPolicy {
+    /// Merge only the images numbered alike in every directory, at most as many as the
+    /// shortest directory holds.
+    #[default]
+    Truncate,
+    /// Keep going to the end of the longest directory, repeating the last image of the shorter ones.
+    RepeatLast,
+    /// Keep going to the end of the longest directory, starting the shorter ones over.
+    Loop,
+    /// Refuse to merge directories of different lengths.
+    Error,
+}
+
+impl FromStr for MismatchPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "truncate" => Ok(MismatchPolicy::Truncate),
+            "repeat-last" => Ok(MismatchPolicy::RepeatLast),
+            "loop" => Ok(MismatchPolicy::Loop),
+            "error" => Ok(MismatchPolicy::Error),
+            other => Err(format!(
+                "Unknown mismatch policy '{}', expected truncate, repeat-last, loop or error",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MismatchPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MismatchPolicy::Truncate => "truncate",
+            MismatchPolicy::RepeatLast => "repeat-last",
+            MismatchPolicy::Loop => "loop",
+            MismatchPolicy::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One output image of the Merger: the base image, the images of each layer stacked
+/// over it, in order, and the output filename.
+#[derive(Debug, Clone)]
+pub struct MergePair {
+    pub base: PathBuf,
+    pub overlays: Vec<PathBuf>,
+    pub output_name: OsString,
+}
+
+impl MergePair {
+    /// Returns the base and the overlays, every input of the output.
+    pub fn inputs(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.base).chain(&self.overlays)
+    }
+}
+
+/// Pairs the images of the first directory with those of every layer according to the
+/// mismatch policy.
+///
+/// # Parameters
+/// - `directory1_files`: Images of the first (base) directory, mapped by frame number.
+/// - `layer_files`: Images of each layer directory, in stacking order, mapped by frame
+///   number; a single one for the second directory of a two-way merge.
+/// - `policy`: How to handle directories of different lengths.
+///
+/// # Returns
+/// - `Result<Vec<MergePair>>`: The pairs to merge in order, or an error if the lengths differ
+///   and the policy is `Error`, or a synthesized output name is taken by an image of the
+///   first directory.
+///
+/// # Notes
+/// - `Truncate` pairs images by frame number, as the Merger always has: an image of the
+///   first directory is merged if every layer holds an image of the same number.
+/// - The other policies pair images by position, the n-th image of every directory
+///   together, whatever their numbers; the lengths are those of the shortest and the
+///   longest of all directories, and each shorter directory is repeated or looped on its
+///   own.
+/// - Outputs keep the first directory's filenames. Positions past the end of the first
+///   directory are named `frame_{number}.{extension}`, numbered on from its last frame
+///   number and padded as wide as the first directory's numbers or as the numbers need.
+pub fn pair_images(
+    directory1_files: &BTreeMap<u32, PathBuf>,
+    layer_files: &[&BTreeMap<u32, PathBuf>],
+    policy: MismatchPolicy,
+) -> Result<Vec<MergePair>> {
+    if policy == MismatchPolicy::Truncate {
+        return Ok(pair_by_number(directory1_files, layer_files));
+    }
+
+    let base: Vec<&PathBuf> = directory1_files.values().collect();
+    let layers: Vec<Vec<&PathBuf>> = layer_files
+        .iter()
+        .map(|files| files.values().collect())
+        .collect();
+    let lengths = || std::iter::once(base.len()).chain(layers.iter().map(Vec::len));
+    let shorter = lengths().min().unwrap_or(0);
+    let longer = lengths().max().unwrap_or(0);
+
+    let total = match policy {
+        _ if shorter == longer => shorter,
+        // Nothing to repeat or loop when one side is empty.
+        MismatchPolicy::RepeatLast | MismatchPolicy::Loop if shorter == 0 => 0,
+        MismatchPolicy::RepeatLast | MismatchPolicy::Loop => longer,
+        MismatchPolicy::Truncate => unreachable!("Truncate pairs by frame number"),
+        MismatchPolicy::Error => {
+            let other = lengths()
+                .find(|&length| length != base.len())
+                .unwrap_or(base.len());
+            return Err(MergerError::LengthMismatch(base.len(), other).into());
+        }
+    };
+    debug!(
+        "Pairing {} images with {:?} images with policy {}: {} pairs",
+        base.len(),
+        layers.iter().map(Vec::len).collect::<Vec<_>>(),
+        policy,
+        total
+    );
+
+    let pick = |images: &[&PathBuf], position: usize| -> PathBuf {
+        let index = match policy {
+            MismatchPolicy::Loop => position % images.len(),
+            _ => position.min(images.len() - 1),
+        };
+        images[index].clone()
+    };
+
+    let last_number = directory1_files.keys().next_back().map_or(0, |&n| n as u64);
+    let last_synthesized = last_number + total.saturating_sub(base.len()) as u64;
+    let padding = FramePadding::detect(base.iter().map(|path| path.as_path()))
+        .max(FramePadding::for_count(last_synthesized));
+    let base_names: HashSet<&OsStr> = base.iter().filter_map(|path| path.file_name()).collect();
+    (0..total)
+        .map(|position| {
+            let base_image = pick(&base, position);
+            let output_name = if position < base.len() {
+                base_image.file_name().map(OsString::from)
+            } else {
+                let extension = base_image
+                    .extension()
+                    .map(|e| e.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "png".to_string());
+                let number = last_number + 1 + (position - base.len()) as u64;
+                let name = OsString::from(padding.file_name("frame", number, &extension));
+                if base_names.contains(name.as_os_str()) {
+                    return Err(
+                        MergerError::NameCollision(name.to_string_lossy().into_owned()).into(),
+                    );
+                }
+                Some(name)
+            };
+            Ok(MergePair {
+                base: base_image,
+                overlays: layers.iter().map(|layer| pick(layer, position)).collect(),
+                output_name: output_name.unwrap_or_else(|| OsString::from("merged.png")),
+            })
+        })
+        .collect()
+}
+
+/// Pairs every image of the first directory with the images of the same frame number in
+/// the layers, skipping those missing from any layer, for `MismatchPolicy::Truncate`.
+fn pair_by_number(
+    directory1_files: &BTreeMap<u32, PathBuf>,
+    layer_files: &[&BTreeMap<u32, PathBuf>],
+) -> Vec<MergePair> {
+    let pairs: Vec<MergePair> = directory1_files
+        .iter()
+        .filter_map(|(number, base)| {
+            let overlays = layer_files
+                .iter()
+                .map(|files| files.get(number).cloned())
+                .collect::<Option<Vec<_>>>();
+            if overlays.is_none() {
+                debug!("No image numbered {} in every layer, skipping it", number);
+            }
+            Some(MergePair {
+                base: base.clone(),
+                overlays: overlays?,
+                output_name: base
+                    .file_name()
+                    .map_or_else(|| OsString::from("merged.png"), OsString::from),
+            })
+        })
+        .collect();
+    debug!(
+        "Pairing {} images with {:?} images by frame number: {} pairs",
+        directory1_files.len(),
+        layer_files
+            .iter()
+            .map(|files| files.len())
+            .collect::<Vec<_>>(),
+        pairs.len()
+    );
+    pairs
+}
@@ -0,0 +1,257 @@
+use image::{imageops, DynamicImage, ImageBuffer, Pixel, Rgba};
+use std::fmt;
+use std::str::FromStr;
+
+use fxp_stream::is_deep_color;
+
+/// Width of the picture, in percent of the first directory's images, see `--pip`.
+pub const DEFAULT_PIP_SCALE: f32 = 30.0;
+
+/// Distance in pixels between a corner picture and the edges of the frame.
+pub const DEFAULT_PIP_MARGIN: u32 = 20;
+
+/// Opacity of the drop shadow, out of 255.
+const SHADOW_ALPHA: u8 = 112;
+
+/// Where the picture sits on the frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PipPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+    /// The top left corner of the picture, in pixels of the frame; the margin is ignored.
+    At(u32, u32),
+}
+
+impl FromStr for PipPosition {
+    type Err = String;
+
+    /// Parses a corner, `center`, or the top left corner of the picture as `X,Y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "top-left" => return Ok(PipPosition::TopLeft),
+            "top-right" => return Ok(PipPosition::TopRight),
+            "bottom-left" => return Ok(PipPosition::BottomLeft),
+            "bottom-right" => return Ok(PipPosition::BottomRight),
+            "center" => return Ok(PipPosition::Center),
+            _ => {}
+        }
+        let coordinates = s
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+        match coordinates {
+            Some((x, y)) => Ok(PipPosition::At(x, y)),
+            None => Err(format!(
+                "Unknown position '{}', expected top-left, top-right, bottom-left, bottom-right, center or X,Y",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PipPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipPosition::TopLeft => write!(f, "top-left"),
+            PipPosition::TopRight => write!(f, "top-right"),
+            PipPosition::BottomLeft => write!(f, "bottom-left"),
+            PipPosition::BottomRight => write!(f, "bottom-right"),
+            PipPosition::Center => write!(f, "center"),
+            PipPosition::At(x, y) => write!(f, "{},{}", x, y),
+        }
+    }
+}
+
+/// A frame drawn around the picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipBorder {
+    /// Width in pixels.
+    pub width: u32,
+    pub color: Rgba<u8>,
+}
+
+impl FromStr for PipBorder {
+    type Err = String;
+
+    /// Parses a width, optionally followed by a color, e.g. `4` or `4,#ffcc00`; the color
+    /// is `white`, `black` or hex `#rrggbb`, and white if left out.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, color) = s.split_once(',').unwrap_or((s, "white"));
+        let width = width
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid border '{}', expected WIDTH[,COLOR]", s))?;
+        let color = match color.trim().to_ascii_lowercase().as_str() {
+            "white" => Rgba([255, 255, 255, 255]),
+            "black" => Rgba([0, 0, 0, 255]),
+            hex => {
+                let hex = hex.trim_start_matches('#');
+                let channel = |index: usize| {
+                    hex.get(index * 2..index * 2 + 2)
+                        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                };
+                match (hex.len(), channel(0), channel(1), channel(2)) {
+                    (6, Some(r), Some(g), Some(b)) => Rgba([r, g, b, 255]),
+                    _ => {
+                        return Err(format!(
+                            "Invalid border color '{}', expected white, black or #rrggbb",
+                            color
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(PipBorder { width, color })
+    }
+}
+
+impl fmt::Display for PipBorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rgba([r, g, b, _]) = self.color;
+        write!(f, "{},#{:02x}{:02x}{:02x}", self.width, r, g, b)
+    }
+}
+
+/// The second directory's images shrunk into a picture over the first's, instead of
+/// blended over the whole frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PictureInPicture {
+    /// Width of the picture in percent of the frame's; the height keeps its aspect ratio.
+    pub scale: f32,
+    pub position: PipPosition,
+    /// Distance in pixels between a corner picture, with its border, and the frame's edges.
+    pub margin: u32,
+    pub border: Option<PipBorder>,
+    /// Draw a drop shadow below and to the right of the picture.
+    pub shadow: bool,
+}
+
+impl Default for PictureInPicture {
+    /// A 30% picture in the bottom right corner, without border or shadow.
+    fn default() -> Self {
+        Self {
+            scale: DEFAULT_PIP_SCALE,
+            position: PipPosition::default(),
+            margin: DEFAULT_PIP_MARGIN,
+            border: None,
+            shadow: false,
+        }
+    }
+}
+
+impl fmt::Display for PictureInPicture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}% at {}", self.scale, self.position)?;
+        if !matches!(self.position, PipPosition::At(..)) {
+            write!(f, ", {} px margin", self.margin)?;
+        }
+        if let Some(border) = self.border {
+            write!(f, ", {} px border", border.width)?;
+        }
+        if self.shadow {
+            write!(f, ", shadow")?;
+        }
+        Ok(())
+    }
+}
+
+impl PictureInPicture {
+    /// Returns the size of the picture of an image on a frame, keeping the image's
+    /// aspect ratio; at least 1x1.
+    pub fn picture_size(&self, frame: (u32, u32), image: (u32, u32)) -> (u32, u32) {
+        let width = (frame.0 as f32 * self.scale / 100.0).round().max(1.0);
+        let height = (width * image.1 as f32 / image.0.max(1) as f32)
+            .round()
+            .max(1.0);
+        (width as u32, height as u32)
+    }
+
+    /// Draws the picture onto a transparent image the size of the frame, to be
+    /// composited over the frame.
+    ///
+    /// # Parameters
+    /// - `frame`: Width and height of the frame.
+    /// - `picture`: The image already shrunk to `picture_size`.
+    ///
+    /// # Returns
+    /// - `DynamicImage`: The frame-sized layer holding the shadow, border and picture;
+    ///   parts falling outside the frame are cut off. It has 16 bits per channel if the
+    ///   picture has more than 8.
+    pub fn layer(&self, frame: (u32, u32), picture: &DynamicImage) -> DynamicImage {
+        if is_deep_color(picture) {
+            DynamicImage::ImageRgba16(self.draw(frame, &picture.to_rgba16(), |color| {
+                Rgba(color.0.map(|channel| channel as u16 * 257))
+            }))
+        } else {
+            DynamicImage::ImageRgba8(self.draw(frame, &picture.to_rgba8(), |color| color))
+        }
+    }
+
+    /// Draws the layer `layer` returns, in the picture's sample type; `sample` turns the shadow
+    /// and border colors into them.
+    fn draw<S>(
+        &self,
+        frame: (u32, u32),
+        picture: &ImageBuffer<Rgba<S>, Vec<S>>,
+        sample: impl Fn(Rgba<u8>) -> Rgba<S>,
+    ) -> ImageBuffer<Rgba<S>, Vec<S>>
+    where
+        Rgba<S>: Pixel<Subpixel = S>,
+    {
+        let mut layer = ImageBuffer::new(frame.0, frame.1);
+        let border = self.border.map_or(0, |border| border.width) as i64;
+        let (x, y) = self.origin(frame, (picture.width(), picture.height()));
+        let outer = (
+            picture.width() as i64 + 2 * border,
+            picture.height() as i64 + 2 * border,
+        );
+        if self.shadow {
+            let offset = (frame.0 as i64 / 200).max(3);
+            fill(
+                &mut layer,
+                (x - border + offset, y - border + offset),
+                outer,
+                sample(Rgba([0, 0, 0, SHADOW_ALPHA])),
+            );
+        }
+        if let Some(PipBorder { color, .. }) = self.border {
+            fill(&mut layer, (x - border, y - border), outer, sample(color));
+        }
+        imageops::overlay(&mut layer, picture, x, y);
+        layer
+    }
+
+    /// Returns the top left corner of the picture itself, inside its border.
+    fn origin(&self, frame: (u32, u32), picture: (u32, u32)) -> (i64, i64) {
+        let border = self.border.map_or(0, |border| border.width) as i64;
+        let inset = self.margin as i64 + border;
+        let (frame_width, frame_height) = (frame.0 as i64, frame.1 as i64);
+        let (width, height) = (picture.0 as i64, picture.1 as i64);
+        match self.position {
+            PipPosition::TopLeft => (inset, inset),
+            PipPosition::TopRight => (frame_width - width - inset, inset),
+            PipPosition::BottomLeft => (inset, frame_height - height - inset),
+            PipPosition::BottomRight => {
+                (frame_width - width - inset, frame_height - height - inset)
+            }
+            PipPosition::Center => ((frame_width - width) / 2, (frame_height - height) / 2),
+            PipPosition::At(x, y) => (x as i64, y as i64),
+        }
+    }
+}
+
+/// Fills a rectangle of the layer, blending the color over what is already there.
+fn fill<S>(
+    layer: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    (x, y): (i64, i64),
+    (width, height): (i64, i64),
+    color: Rgba<S>,
+) where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    let patch = ImageBuffer::from_pixel(width.max(0) as u32, height.max(0) as u32, color);
+    imageops::overlay(layer, &patch, x, y);
+}
@@ -0,0 +1,203 @@
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba};
+use std::fmt;
+
+/// Width in pixels over which a region fades in from its edge, see `Merger::feather`.
+pub const DEFAULT_FEATHER: u32 = 8;
+
+/// The part of the frames the layers are blended into; outside it the first directory's
+/// images are kept as they are.
+///
+/// Coordinates are pixels of the first directory's images, from their top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Rectangle {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Circle {
+        x: u32,
+        y: u32,
+        radius: u32,
+    },
+}
+
+impl Region {
+    /// Parses a rectangle given as `X,Y,WIDTH,HEIGHT`, e.g. `40,40,320,180`.
+    pub fn parse_rectangle(s: &str) -> Result<Self, String> {
+        match parse_numbers(s, "X,Y,WIDTH,HEIGHT")?[..] {
+            [_, _, 0, _] | [_, _, _, 0] => Err(format!("The rectangle '{}' is empty", s)),
+            [x, y, width, height] => Ok(Region::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!(
+                "Invalid rectangle '{}', expected X,Y,WIDTH,HEIGHT",
+                s
+            )),
+        }
+    }
+
+    /// Parses a circle given as `CENTER_X,CENTER_Y,RADIUS`, e.g. `640,360,120`.
+    pub fn parse_circle(s: &str) -> Result<Self, String> {
+        match parse_numbers(s, "CENTER_X,CENTER_Y,RADIUS")?[..] {
+            [_, _, 0] => Err(format!("The circle '{}' is empty", s)),
+            [x, y, radius] => Ok(Region::Circle { x, y, radius }),
+            _ => Err(format!(
+                "Invalid circle '{}', expected CENTER_X,CENTER_Y,RADIUS",
+                s
+            )),
+        }
+    }
+
+    /// Returns the region as given on the command line, e.g. `40,40,320,180`.
+    pub fn arg(&self) -> String {
+        match self {
+            Region::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            } => format!("{},{},{},{}", x, y, width, height),
+            Region::Circle { x, y, radius } => format!("{},{},{}", x, y, radius),
+        }
+    }
+
+    /// Keeps the blend only within the region, fading it in over `feather` pixels inside
+    /// the region's edge.
+    ///
+    /// # Parameters
+    /// - `below`: The image the layers were blended onto, kept outside the region.
+    /// - `blended`: The blend, of the same size as `below`.
+    /// - `feather`: Width of the fade in pixels; 0 gives a hard edge.
+    ///
+    /// # Returns
+    /// - `DynamicImage`: `below` with the blend inside the region, as RGBA with the bits
+    ///   per channel of the blend.
+    ///
+    /// # Notes
+    /// - Parts of the region outside the image are ignored.
+    pub fn limit(
+        &self,
+        below: &DynamicImage,
+        blended: &DynamicImage,
+        feather: u32,
+    ) -> DynamicImage {
+        match blended {
+            DynamicImage::ImageRgba16(blended) => {
+                DynamicImage::ImageRgba16(self.mix(below.to_rgba16(), blended, feather, |value| {
+                    value.round() as u16
+                }))
+            }
+            blended => DynamicImage::ImageRgba8(self.mix(
+                below.to_rgba8(),
+                &blended.to_rgba8(),
+                feather,
+                |value| value.round() as u8,
+            )),
+        }
+    }
+
+    /// Mixes the blend into `out` within the region, see `limit`; `round` turns a mixed
+    /// channel back into a sample.
+    fn mix<S>(
+        &self,
+        mut out: ImageBuffer<Rgba<S>, Vec<S>>,
+        blended: &ImageBuffer<Rgba<S>, Vec<S>>,
+        feather: u32,
+        round: impl Fn(f32) -> S,
+    ) -> ImageBuffer<Rgba<S>, Vec<S>>
+    where
+        S: Copy + Into<f32>,
+        Rgba<S>: Pixel<Subpixel = S>,
+    {
+        let (left, top, right, bottom) = self.bounds();
+        for y in top.min(out.height())..bottom.min(out.height()) {
+            for x in left.min(out.width())..right.min(out.width()) {
+                let weight = self.weight(x as f32 + 0.5, y as f32 + 0.5, feather);
+                if weight <= 0.0 {
+                    continue;
+                }
+                let blend = blended.get_pixel(x, y);
+                let pixel = out.get_pixel_mut(x, y);
+                for channel in 0..4 {
+                    let below: f32 = pixel[channel].into();
+                    let above: f32 = blend[channel].into();
+                    pixel[channel] = round(below + (above - below) * weight);
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns the left, top, right and bottom edges of the region, right and bottom
+    /// exclusive.
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        match *self {
+            Region::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, x.saturating_add(width), y.saturating_add(height)),
+            Region::Circle { x, y, radius } => (
+                x.saturating_sub(radius),
+                y.saturating_sub(radius),
+                x.saturating_add(radius),
+                y.saturating_add(radius),
+            ),
+        }
+    }
+
+    /// Returns how much of the blend shows at a point, from 0 outside the region to 1
+    /// at least `feather` pixels inside its edge.
+    fn weight(&self, px: f32, py: f32, feather: u32) -> f32 {
+        let inside = match *self {
+            Region::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            } => (px - x as f32)
+                .min(x as f32 + width as f32 - px)
+                .min(py - y as f32)
+                .min(y as f32 + height as f32 - py),
+            Region::Circle { x, y, radius } => radius as f32 - (px - x as f32).hypot(py - y as f32),
+        };
+        match feather {
+            0 if inside >= 0.0 => 1.0,
+            0 => 0.0,
+            feather => (inside / feather as f32).clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            } => write!(f, "{}x{} rectangle at {},{}", width, height, x, y),
+            Region::Circle { x, y, radius } => {
+                write!(f, "circle of radius {} around {},{}", radius, x, y)
+            }
+        }
+    }
+}
+
+/// Parses comma separated pixel coordinates, naming `expected` if one is not a number.
+fn parse_numbers(s: &str, expected: &str) -> Result<Vec<u32>, String> {
+    s.split(',')
+        .map(|part| {
+            part.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid region '{}', expected {} in pixels", s, expected))
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+use crate::modes::Modes;
+
+/// What a mode consumes and produces, so shared code can ask instead of matching on modes.
+pub trait Capabilities {
+    /// Name of the mode's subcommand, e.g. `"merger"`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the mode reads a directory of images.
+    fn supports_directory_input(&self) -> bool;
+
+    /// Whether the mode reads a video file.
+    ///
+    /// A mode supporting both, such as the Interpolator, reads either one.
+    fn supports_video_input(&self) -> bool;
+
+    /// Whether the mode can take an audio file.
+    fn accepts_audio(&self) -> bool;
+
+    /// Whether the mode cannot run without an audio file.
+    fn requires_audio(&self) -> bool;
+
+    /// Whether the mode writes a directory of images by default, rather than a single file.
+    fn writes_directory(&self) -> bool;
+
+    /// The fixed suffix appended to the input name for the auto-generated output.
+    ///
+    /// `None` when the generated name is not the input name plus a fixed suffix, as with
+    /// the Gmicer's name taken from its arguments, the Sampler's `sample_frames`, the
+    /// Clipper's video named after the audio or the Zoopraxiscope's `_sheet.png` and
+    /// `_frames`, which depend on the direction.
+    fn default_output_suffix(&self) -> Option<&'static str>;
+}
+
+impl Capabilities for Modes {
+    fn name(&self) -> &'static str {
+        match self {
+            Modes::Exporter => "exporter",
+            Modes::Merger => "merger",
+            Modes::Sampler => "sampler",
+            Modes::Clutter => "clutter",
+            Modes::Clipper => "clipper",
+            Modes::Gmicer => "gmicer",
+            Modes::Dedup => "dedup",
+            Modes::Grader => "grader",
+            Modes::Captioner => "captioner",
+            Modes::Stabilizer => "stabilizer",
+            Modes::Interpolator => "interpolator",
+            Modes::Visualizer => "visualizer",
+            Modes::Processor => "process",
+            Modes::Zoopraxiscope => "zoopraxiscope",
+        }
+    }
+
+    fn supports_directory_input(&self) -> bool {
+        match self {
+            Modes::Merger
+            | Modes::Clutter
+            | Modes::Clipper
+            | Modes::Gmicer
+            | Modes::Dedup
+            | Modes::Grader
+            | Modes::Captioner
+            | Modes::Processor
+            | Modes::Interpolator
+            | Modes::Zoopraxiscope => true,
+            Modes::Exporter | Modes::Sampler | Modes::Stabilizer | Modes::Visualizer => false,
+        }
+    }
+
+    fn supports_video_input(&self) -> bool {
+        match self {
+            Modes::Exporter | Modes::Sampler | Modes::Stabilizer | Modes::Interpolator => true,
+            Modes::Merger
+            | Modes::Clutter
+            | Modes::Clipper
+            | Modes::Gmicer
+            | Modes::Dedup
+            | Modes::Grader
+            | Modes::Captioner
+            | Modes::Processor
+            | Modes::Visualizer
+            | Modes::Zoopraxiscope => false,
+        }
+    }
+
+    fn accepts_audio(&self) -> bool {
+        match self {
+            Modes::Exporter | Modes::Sampler | Modes::Clipper | Modes::Visualizer => true,
+            Modes::Merger
+            | Modes::Clutter
+            | Modes::Gmicer
+            | Modes::Dedup
+            | Modes::Grader
+            | Modes::Captioner
+            | Modes::Stabilizer
+            | Modes::Processor
+            | Modes::Interpolator
+            | Modes::Zoopraxiscope => false,
+        }
+    }
+
+    /// Only the Visualizer, whose input is the audio: without it the Exporter and Sampler
+    /// take a duration and the Clipper encodes the frames silently.
+    fn requires_audio(&self) -> bool {
+        match self {
+            Modes::Visualizer => true,
+            Modes::Exporter
+            | Modes::Sampler
+            | Modes::Clipper
+            | Modes::Merger
+            | Modes::Clutter
+            | Modes::Gmicer
+            | Modes::Dedup
+            | Modes::Grader
+            | Modes::Captioner
+            | Modes::Stabilizer
+            | Modes::Processor
+            | Modes::Interpolator
+            | Modes::Zoopraxiscope => false,
+        }
+    }
+
+    /// The Zoopraxiscope writes a sprite sheet, or a directory when it explodes one.
+    fn writes_directory(&self) -> bool {
+        match self {
+            Modes::Clipper | Modes::Stabilizer | Modes::Zoopraxiscope => false,
+            Modes::Exporter
+            | Modes::Merger
+            | Modes::Sampler
+            | Modes::Clutter
+            | Modes::Gmicer
+            | Modes::Dedup
+            | Modes::Grader
+            | Modes::Captioner
+            | Modes::Interpolator
+            | Modes::Processor
+            | Modes::Visualizer => true,
+        }
+    }
+
+    fn default_output_suffix(&self) -> Option<&'static str> {
+        match self {
+            Modes::Exporter => Some("_original_frames"),
+            Modes::Merger => Some("_merged"),
+            Modes::Clutter => Some("_clutted"),
+            Modes::Dedup => Some("_dedup"),
+            Modes::Grader => Some("_graded"),
+            Modes::Captioner => Some("_captioned"),
+            Modes::Stabilizer => Some("_stabilized"),
+            Modes::Interpolator => Some("_interpolated"),
+            Modes::Visualizer => Some("_visualized"),
+            Modes::Processor => Some("_processed"),
+            Modes::Sampler | Modes::Gmicer | Modes::Clipper | Modes::Zoopraxiscope => None,
+        }
+    }
+}
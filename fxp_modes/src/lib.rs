@@ -1,3 +1,5 @@
+mod capabilities;
 mod modes;
 
+pub use capabilities::Capabilities;
 pub use modes::Modes;
@@ -1,5 +1,5 @@
 // An enum for all possible modes
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Modes {
     Exporter,
     Merger,
@@ -7,4 +7,32 @@ pub enum Modes {
     Clutter,
     Clipper,
     Gmicer,
+    Dedup,
+    Grader,
+    Captioner,
+    Stabilizer,
+    Interpolator,
+    Visualizer,
+    Processor,
+    Zoopraxiscope,
+}
+
+impl Modes {
+    /// Every mode, in the order the subcommands are listed.
+    pub const ALL: [Modes; 14] = [
+        Modes::Exporter,
+        Modes::Sampler,
+        Modes::Merger,
+        Modes::Gmicer,
+        Modes::Clutter,
+        Modes::Grader,
+        Modes::Captioner,
+        Modes::Dedup,
+        Modes::Processor,
+        Modes::Interpolator,
+        Modes::Visualizer,
+        Modes::Zoopraxiscope,
+        Modes::Clipper,
+        Modes::Stabilizer,
+    ];
 }
@@ -0,0 +1,329 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::debug;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::exit::OutputError;
+use crate::lock;
+
+/// What to do when an output target already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Leave the existing output alone and write next to it under a `_N` suffixed name.
+    #[default]
+    Unique,
+    /// Write into the existing directory, keeping what is already there. A file cannot be
+    /// appended to, so a file output gets a `_N` suffixed name as with `Unique`.
+    Append,
+    /// Delete the existing output and replace it.
+    Overwrite,
+    /// Refuse to run.
+    ErrorIfExists,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unique" => Ok(CollisionPolicy::Unique),
+            "append" => Ok(CollisionPolicy::Append),
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            "error-if-exists" => Ok(CollisionPolicy::ErrorIfExists),
+            other => Err(format!(
+                "Unknown collision policy '{}', expected unique, append, overwrite or error-if-exists",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CollisionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CollisionPolicy::Unique => "unique",
+            CollisionPolicy::Append => "append",
+            CollisionPolicy::Overwrite => "overwrite",
+            CollisionPolicy::ErrorIfExists => "error-if-exists",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Whether an output target is a single file or a directory of files.
+pub(crate) enum OutputType {
+    File,
+    Directory,
+}
+
+/// Resolves the path an output will be written to, without touching the filesystem.
+///
+/// # Parameters
+/// - `target`: The preferred output path.
+/// - `output_type`: Whether the output is a file or a directory.
+/// - `policy`: What to do if `target` already exists.
+///
+/// # Returns
+/// - `Result<PathBuf>`: `target` itself, a free `_N` suffixed sibling of it, or an error
+///   if the policy forbids using it.
+///
+/// # Notes
+/// - Under `CollisionPolicy::Unique`, a path another live run holds the lock of is taken
+///   even before that run created it. The other policies leave a locked path to
+///   `claim_output`.
+pub(crate) fn resolve_output(
+    target: &Path,
+    output_type: &OutputType,
+    policy: CollisionPolicy,
+) -> Result<PathBuf> {
+    let taken = match policy {
+        CollisionPolicy::Unique => is_taken(target),
+        _ => target.exists(),
+    };
+    if !taken {
+        return Ok(target.to_path_buf());
+    }
+    debug!("Output {:?} exists, applying policy {}", target, policy);
+
+    match (policy, output_type) {
+        (CollisionPolicy::Unique, OutputType::Directory) => {
+            let (parent, base_name) = split_dir_target(target)?;
+            Ok(unique_dir_path(parent, &base_name))
+        }
+        (CollisionPolicy::Unique | CollisionPolicy::Append, OutputType::File) => {
+            Ok(unique_file_path(target))
+        }
+        (CollisionPolicy::Append, OutputType::Directory) if target.is_dir() => {
+            Ok(target.to_path_buf())
+        }
+        (CollisionPolicy::Append, OutputType::Directory) => Err(anyhow!(
+            "Cannot append to {}: it exists and is not a directory",
+            target.display()
+        )),
+        (CollisionPolicy::Overwrite, _) => Ok(target.to_path_buf()),
+        (CollisionPolicy::ErrorIfExists, _) => {
+            Err(OutputError::Exists(target.display().to_string()).into())
+        }
+    }
+}
+
+/// Resolves an output path under the collision policy and prepares it for writing.
+///
+/// # Parameters
+/// - `target`: The preferred output path.
+/// - `output_type`: Whether the output is a file or a directory.
+/// - `policy`: What to do if `target` already exists.
+/// - `input_path`: The mode's input, which an overwrite must never delete.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The output path; a directory output exists afterwards, a file
+///   output has its parent directories created.
+///
+/// # Notes
+/// - Only `CollisionPolicy::Overwrite` deletes anything, and never a directory that
+///   holds the input. An existing file output is not deleted up front; the mode replaces it.
+/// - Falling back to a suffixed name is reported on stderr, so it never happens silently.
+/// - The output is locked until `release_output_locks`, so two runs never write into it
+///   at once. Under `CollisionPolicy::Unique` a locked output is skipped like an existing
+///   one; otherwise `lock_policy` decides whether to fail, wait or take the lock over.
+pub(crate) fn claim_output(
+    target: &Path,
+    output_type: OutputType,
+    policy: CollisionPolicy,
+    input_path: &Path,
+) -> Result<PathBuf> {
+    let path = loop {
+        let path = match (policy, &output_type) {
+            // Created right away, so two runs cannot both settle on the same free name.
+            (CollisionPolicy::Unique, OutputType::Directory) => {
+                let (parent, base_name) = split_dir_target(target)?;
+                create_unique_dir(parent, &base_name)?
+            }
+            _ => resolve_output(target, &output_type, policy)?,
+        };
+        match lock::try_lock(&path)? {
+            None => break path,
+            // Another run locked it since it was resolved; the next resolve skips it.
+            Some(_) if policy == CollisionPolicy::Unique => {
+                // The directory created for it is removed, unless the other run has
+                // already written into it.
+                if matches!(output_type, OutputType::Directory) {
+                    let _ = fs::remove_dir(&path);
+                }
+                continue;
+            }
+            // Resolved again once locked, as the other run may have created it meanwhile.
+            Some(pid) => lock::take_lock(&path, pid)?,
+        }
+    };
+    if path != target {
+        eprintln!(
+            "Output {} already exists, writing to {} instead",
+            target.display(),
+            path.display()
+        );
+    }
+
+    // An existing file is left for the writer to replace once the new output is complete.
+    let replaced_by_writer = matches!(output_type, OutputType::File) && path.is_file();
+    if policy == CollisionPolicy::Overwrite && path.exists() && !replaced_by_writer {
+        remove_existing_output(&path, input_path)?;
+    }
+
+    match output_type {
+        OutputType::Directory => {
+            debug!("Creating output directory: {:?}", path);
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create output directory {:?}", path))?;
+        }
+        OutputType::File => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create parent directories for {:?}", path)
+                })?;
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Deletes an existing output file or directory for `CollisionPolicy::Overwrite`.
+fn remove_existing_output(path: &Path, input_path: &Path) -> Result<()> {
+    if path.is_dir() {
+        // Refuse to delete the input along with the old output, e.g. for `-o .`.
+        let output = fs::canonicalize(path)
+            .with_context(|| format!("Failed to resolve output directory {:?}", path))?;
+        if let Ok(input) = fs::canonicalize(input_path) {
+            if input.starts_with(&output) {
+                bail!(
+                    "Refusing to overwrite {}: it contains the input {}",
+                    path.display(),
+                    input_path.display()
+                );
+            }
+        }
+        debug!("Removing existing output directory: {:?}", path);
+        fs::remove_dir_all(path)
+            .with_context(|| format!("Failed to remove existing output directory {:?}", path))?;
+    } else {
+        debug!("Removing existing output file: {:?}", path);
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove existing output file {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// Splits a directory output into its parent and its name, for `unique_dir_path`.
+fn split_dir_target(target: &Path) -> Result<(&Path, String)> {
+    let parent = target
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let base_name = target
+        .file_name()
+        .ok_or_else(|| anyhow!("Output path {:?} has no directory name", target))?
+        .to_string_lossy()
+        .into_owned();
+    Ok((parent, base_name))
+}
+
+/// Creates the first free directory under `parent` for `base_name`.
+///
+/// # Parameters
+/// - `parent`: The directory to create it in, created first if missing.
+/// - `base_name`: The preferred name of the directory.
+///
+/// # Returns
+/// - `Result<PathBuf>`: `parent/base_name`, or the first free `parent/base_name_N` with
+///   `N` counting up from 1, newly created; or an error if a directory cannot be created.
+///
+/// # Notes
+/// - Each name is claimed with a single `fs::create_dir`, which fails if the name
+///   exists, so concurrent runs, even in other processes, never get the same directory.
+///   Checking for the name first and creating it after would leave a window between the two.
+/// - A name another live run holds the lock of is skipped too.
+pub fn create_unique_dir(parent: &Path, base_name: &str) -> Result<PathBuf> {
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create parent directories {:?}", parent))?;
+
+    let mut counter = 0;
+    loop {
+        let candidate = match counter {
+            0 => parent.join(base_name),
+            _ => parent.join(format!("{}_{counter}", base_name)),
+        };
+        counter += 1;
+        if lock::holder(&candidate).is_some() {
+            continue;
+        }
+        match fs::create_dir(&candidate) {
+            Ok(()) => {
+                debug!("Created output directory: {:?}", candidate);
+                return Ok(candidate);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to create output directory {:?}", candidate))
+            }
+        }
+    }
+}
+
+/// Resolves the first free directory name under `parent` for `base_name`.
+///
+/// Returns `parent/base_name` if it is not taken yet, otherwise the first free
+/// `parent/base_name_N` with `N` counting up from 1. Nothing is created, so this only
+/// predicts the name for a plan; `create_unique_dir` claims one.
+pub(crate) fn unique_dir_path(parent: &Path, base_name: &str) -> PathBuf {
+    // Check if the directory with the base name already exists.
+    let base_path = parent.join(base_name);
+    if !is_taken(&base_path) {
+        return base_path;
+    }
+
+    // Otherwise, append an incrementing number until a free directory is found.
+    let mut counter = 1;
+    loop {
+        let candidate_name = format!("{}_{counter}", base_name);
+        let candidate_path = parent.join(&candidate_name);
+        if !is_taken(&candidate_path) {
+            return candidate_path;
+        }
+        counter += 1;
+    }
+}
+
+/// Resolves the first free file name for `path`, appending `_N` to its stem if needed.
+///
+/// `out.mp4` becomes `out_1.mp4`, then `out_2.mp4`, and so on. Nothing is created.
+fn unique_file_path(path: &Path) -> PathBuf {
+    if !is_taken(path) {
+        return path.to_path_buf();
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    let extension = path.extension().map(|e| e.to_string_lossy().into_owned());
+    let mut counter = 1;
+    loop {
+        let mut candidate = path.with_file_name(format!("{}_{}", stem, counter));
+        if let Some(extension) = &extension {
+            candidate.set_extension(extension);
+        }
+        if !is_taken(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Returns whether an output path exists or another live run holds its lock.
+fn is_taken(path: &Path) -> bool {
+    path.exists() || lock::holder(path).is_some()
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io;
+use thiserror::Error;
+
+/// What kind of failure ended a run, each with its own process exit code so wrappers
+/// can tell them apart without parsing messages.
+///
+/// # Notes
+/// - The codes are stable; a new kind gets a new code rather than reusing one.
+/// - The error of each crate has a `kind` method returning the kind of each of its
+///   variants, which decides the exit code.
+/// - 2 is shared with the usage errors clap exits with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum FailureKind {
+    /// Any failure not listed below.
+    Other = 1,
+    /// `compare` found a frame below its thresholds.
+    BelowThreshold = 2,
+    /// A tool the mode runs, such as ffmpeg or gmic, is not installed or not on `PATH`.
+    ToolMissing = 3,
+    /// A tool the mode runs failed.
+    ToolFailed = 4,
+    /// The input holds no frames to work on.
+    NoFrames = 5,
+    /// The input does not exist or is not what the mode takes.
+    InvalidInput = 6,
+    /// The output exists and the collision policy forbids touching it.
+    OutputExists = 7,
+    /// The output would not fit on the disk.
+    InsufficientSpace = 8,
+    /// Another run holds the lock of the output.
+    OutputLocked = 9,
+    /// The run was stopped by Ctrl+C, SIGTERM or SIGHUP, as a shell reports a process
+    /// killed by SIGINT.
+    Interrupted = 130,
+}
+
+impl FailureKind {
+    /// Returns the process exit code of the kind.
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FailureKind::Other => "other failure",
+            FailureKind::BelowThreshold => "below threshold",
+            FailureKind::ToolMissing => "tool missing",
+            FailureKind::ToolFailed => "tool failed",
+            FailureKind::NoFrames => "no frames",
+            FailureKind::InvalidInput => "invalid input",
+            FailureKind::OutputExists => "output exists",
+            FailureKind::InsufficientSpace => "insufficient space",
+            FailureKind::OutputLocked => "output locked",
+            FailureKind::Interrupted => "interrupted",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Failures of resolving an output that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum OutputError {
+    /// The output exists and the collision policy is `CollisionPolicy::ErrorIfExists`.
+    #[error("Output {0} already exists; pass --overwrite to replace it or --append to add to it")]
+    Exists(String),
+    /// Another live run holds the lock of the output, see `LockPolicy`.
+    #[error("Output {0} is in use by run {1}; pass --wait to wait for it or --force-lock to take it over")]
+    Locked(String, u32),
+}
+
+impl OutputError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            OutputError::Exists(_) => FailureKind::OutputExists,
+            OutputError::Locked(..) => FailureKind::OutputLocked,
+        }
+    }
+}
+
+/// Returns whether spawning a tool failed because it is not installed.
+pub fn is_tool_missing(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::NotFound
+}
+
+/// The error of a crate that runs tools such as ffmpeg or gmic, built from the failure to
+/// run one.
+///
+/// # Notes
+/// - Each crate implements the constructors for its `ToolMissing` and `ToolFailed`
+///   variants, whose kinds are `FailureKind::ToolMissing` and `FailureKind::ToolFailed`.
+pub trait ToolError: Sized {
+    /// The tool is not installed or not on `PATH`.
+    fn missing(tool: &str) -> Self;
+
+    /// The tool could not be started or exited unsuccessfully.
+    fn failed(tool: &str, reason: String) -> Self;
+
+    /// Maps the error of starting `tool` to `missing` if it is not installed, and to
+    /// `failed` otherwise.
+    fn spawn(tool: &str, error: io::Error) -> Self {
+        if is_tool_missing(&error) {
+            Self::missing(tool)
+        } else {
+            Self::failed(tool, error.to_string())
+        }
+    }
+}
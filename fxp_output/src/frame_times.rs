@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::rate::FrameRate;
+
+/// Name of the sidecar the Exporter writes next to the frames with `--frames-json`.
+pub const FRAMES_FILE_NAME: &str = "frames.json";
+
+/// Where each exported frame comes from in its source video, the layout of `frames.json`.
+///
+/// Lets later stages time the frames precisely without probing the video again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameTimes {
+    /// The rate the frames were exported at.
+    pub fps: FrameRate,
+    /// The frames, in order.
+    pub frames: Vec<FrameTime>,
+}
+
+/// One exported frame and its place in the source video.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameTime {
+    /// File name of the frame, e.g. `frame_0001.png`.
+    pub file: String,
+    /// The video the frame was extracted from.
+    pub video: String,
+    /// Time of the frame in its source video, in milliseconds.
+    pub source_ms: u64,
+    /// Index of the frame in its source video, counted from 0 at the video's own frame
+    /// rate; absent if ffprobe could not tell that rate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_frame: Option<u64>,
+}
+
+impl FrameTimes {
+    /// Writes `frames.json` into a directory of frames.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The path of the written file.
+    pub fn write(&self, directory: &Path) -> Result<PathBuf> {
+        let path = directory.join(FRAMES_FILE_NAME);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize frame times")?;
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write frame times {}", path.display()))?;
+        debug!(
+            "Frame times of {} frames written to {:?}",
+            self.frames.len(),
+            path
+        );
+        Ok(path)
+    }
+
+    /// Reads a `frames.json`.
+    ///
+    /// # Parameters
+    /// - `path`: Path to the file, usually `frames.json` in a directory of exported frames.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The frame times, or an error if the file is missing or malformed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read frame times {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Malformed frame times {}", path.display()))
+    }
+
+    /// Returns the times of a frame by its number, counted from 1 as in `frame_0001.png`.
+    pub fn frame(&self, number: u32) -> Option<&FrameTime> {
+        let index = (number as usize).checked_sub(1)?;
+        self.frames.get(index)
+    }
+}
@@ -1,6 +1,40 @@
+mod collision;
+mod exit;
+mod frame_times;
+mod lock;
+mod manifest;
 mod output;
+mod plan;
+mod progress;
+mod rate;
+mod retry;
+mod seed;
+mod signal;
+mod staging;
+mod temp;
+mod trace;
 
+pub use collision::{create_unique_dir, CollisionPolicy};
+pub use exit::{is_tool_missing, FailureKind, OutputError, ToolError};
+pub use frame_times::{FrameTime, FrameTimes, FRAMES_FILE_NAME};
+pub use lock::{lock_policy, release_output_locks, set_lock_policy, LockPolicy};
+pub use manifest::{
+    manifest_output, written_manifests, InputRecord, Manifest, RecordedRun, MANIFEST_FILE_NAME,
+};
 pub use output::{
-    ClipperOutput, ClutterOutput, ExporterOutput, GmicerOutput, MergerOutput, ModeOutput, Output,
-    SamplerOutput,
+    CaptionerOutput, ClipperOutput, ClutterOutput, DedupOutput, ExporterOutput, GmicerOutput,
+    GraderOutput, InterpolatorOutput, MergerOutput, ModeOutput, Output, ProcessorOutput,
+    SampleKind, SamplerOutput, StabilizerOutput, VisualizerOutput, ZoopraxiscopeOutput,
+};
+pub use plan::Plan;
+pub use progress::{progress_bar, progress_mode, set_progress_mode, ProgressMode};
+pub use rate::FrameRate;
+pub use retry::RetryPolicy;
+pub use seed::{generate_seed, seed, set_seed, Seed};
+pub use signal::{graceful, kill_requested, running_flag, set_graceful, stop_requested};
+pub use staging::{StagedDirectory, COMPLETE_MARKER, PARTIAL_MARKER};
+pub use temp::{default_keep_temp_dir, keep_temp_files};
+pub use trace::{
+    init_tracing, timing_summary, trace_file, write_timing_summary, StageTiming, TimingSummary,
+    TraceOutput,
 };
@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::exit::OutputError;
+
+/// How often a waiting run checks whether the lock it waits for was released.
+const WAIT_POLL: Duration = Duration::from_millis(500);
+/// How long a lock file may stay without a PID before it is taken as abandoned by a run
+/// that died while writing it.
+const WRITE_GRACE: Duration = Duration::from_secs(5);
+/// How often a lock file without a PID yet is read again.
+const WRITE_POLL: Duration = Duration::from_millis(50);
+
+static LOCK_POLICY: OnceLock<LockPolicy> = OnceLock::new();
+/// The locks this process holds, released by `release_output_locks`.
+static HELD: Mutex<Vec<OutputLock>> = Mutex::new(Vec::new());
+
+/// What to do when another run holds the lock of an output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Refuse to run.
+    #[default]
+    Fail,
+    /// Wait until the other run releases the lock.
+    Wait,
+    /// Take the lock over, for a lock left behind by a run that is known to be gone.
+    Force,
+}
+
+impl fmt::Display for LockPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LockPolicy::Fail => "fail",
+            LockPolicy::Wait => "wait",
+            LockPolicy::Force => "force",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Sets what to do when another run holds the lock of an output.
+///
+/// # Parameters
+/// - `policy`: Fail, wait for the lock or take it over.
+///
+/// # Notes
+/// - Only the first call has an effect, like `set_progress_mode`.
+pub fn set_lock_policy(policy: LockPolicy) {
+    debug!("Output lock policy: {}", policy);
+    let _ = LOCK_POLICY.set(policy);
+}
+
+/// Returns what to do when another run holds the lock of an output, see `set_lock_policy`.
+pub fn lock_policy() -> LockPolicy {
+    LOCK_POLICY.get().copied().unwrap_or_default()
+}
+
+/// Releases the locks of all outputs this process claimed.
+///
+/// # Notes
+/// - Called once the run is over, whether it succeeded or not. A lock left behind by a
+///   process that was killed names a PID that is gone, and is taken over by the next run.
+pub fn release_output_locks() {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    held.clear();
+}
+
+/// An advisory lock on an output, held as a `.<name>.lock` file next to it that holds
+/// the PID of the run writing it. Removed on drop.
+#[derive(Debug)]
+struct OutputLock {
+    output: PathBuf,
+    lock_file: PathBuf,
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        debug!("Releasing the lock of {:?}", self.output);
+        if let Err(e) = fs::remove_file(&self.lock_file) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Failed to remove lock file {:?}: {}", self.lock_file, e);
+            }
+        }
+    }
+}
+
+/// Returns the lock file of an output, a hidden sibling so it never ends up in the output.
+fn lock_path(output: &Path) -> PathBuf {
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    output.with_file_name(format!(".{}.lock", name))
+}
+
+/// Returns the PID of the live run holding the lock of an output, if any.
+pub(crate) fn holder(output: &Path) -> Option<u32> {
+    read_pid(&lock_path(output)).filter(|pid| is_alive(*pid))
+}
+
+/// Locks an output for this process, or hands back the PID of the run holding it.
+///
+/// # Parameters
+/// - `output`: The output to lock.
+///
+/// # Returns
+/// - `Result<Option<u32>>`: `None` once this process holds the lock, including when it
+///   already did, or the PID of the live run holding it.
+///
+/// # Notes
+/// - A lock whose PID is gone is stale, and is taken over.
+pub(crate) fn try_lock(output: &Path) -> Result<Option<u32>> {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    if held.iter().any(|lock| lock.output == output) {
+        return Ok(None);
+    }
+
+    let lock_file = lock_path(output);
+    if let Some(parent) = lock_file.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create parent directories for {:?}", output))?;
+    }
+    loop {
+        if create_lock_file(&lock_file)? {
+            debug!("Locked {:?} with {:?}", output, lock_file);
+            held.push(OutputLock {
+                output: output.to_path_buf(),
+                lock_file,
+            });
+            return Ok(None);
+        }
+        match read_pid(&lock_file) {
+            Some(pid) if is_alive(pid) => return Ok(Some(pid)),
+            // The run that created it is still writing its PID.
+            None if being_written(&lock_file) => thread::sleep(WRITE_POLL),
+            _ => {
+                warn!("Removing stale lock file {}", lock_file.display());
+                remove_lock_file(&lock_file)?;
+            }
+        }
+    }
+}
+
+/// Takes the lock of an output held by another run, as `lock_policy` says.
+///
+/// # Parameters
+/// - `output`: The output to lock.
+/// - `pid`: The PID of the run holding the lock.
+///
+/// # Returns
+/// - `Result<()>`: `Ok` once this process holds the lock, or `OutputError::Locked` under
+///   `LockPolicy::Fail`.
+pub(crate) fn take_lock(output: &Path, pid: u32) -> Result<()> {
+    match lock_policy() {
+        LockPolicy::Fail => Err(OutputError::Locked(output.display().to_string(), pid).into()),
+        LockPolicy::Force => {
+            warn!(
+                "Taking over the lock of {} from run {}",
+                output.display(),
+                pid
+            );
+            remove_lock_file(&lock_path(output))?;
+            match try_lock(output)? {
+                None => Ok(()),
+                // Another run took it in between.
+                Some(pid) => Err(OutputError::Locked(output.display().to_string(), pid).into()),
+            }
+        }
+        LockPolicy::Wait => {
+            eprintln!(
+                "Output {} is in use by run {}, waiting for it to finish...",
+                output.display(),
+                pid
+            );
+            while try_lock(output)?.is_some() {
+                thread::sleep(WAIT_POLL);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Creates the lock file holding the PID of this process.
+///
+/// # Returns
+/// - `Result<bool>`: `true` if the lock file was created, `false` if it already exists.
+///
+/// # Notes
+/// - The file is created exclusively, which works on every filesystem, and the PID is
+///   written into it afterwards; until then other runs see it as `being_written`.
+fn create_lock_file(lock_file: &Path) -> Result<bool> {
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(lock_file)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to create lock file {:?}", lock_file))
+        }
+    };
+    if let Err(e) = file.write_all(std::process::id().to_string().as_bytes()) {
+        let _ = fs::remove_file(lock_file);
+        return Err(e).with_context(|| format!("Failed to write lock file {:?}", lock_file));
+    }
+    Ok(true)
+}
+
+/// Returns whether a lock file without a PID was created recently enough that its run
+/// may still be writing it, see `WRITE_GRACE`.
+fn being_written(lock_file: &Path) -> bool {
+    fs::metadata(lock_file)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age < WRITE_GRACE)
+        })
+        // A lock file removed in between is taken again by the caller.
+        .unwrap_or(false)
+}
+
+fn remove_lock_file(lock_file: &Path) -> Result<()> {
+    match fs::remove_file(lock_file) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove lock file {:?}", lock_file))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn read_pid(lock_file: &Path) -> Option<u32> {
+    fs::read_to_string(lock_file).ok()?.trim().parse().ok()
+}
+
+/// Returns whether a process is running.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 sends nothing, it only checks that the process exists.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // A process of another user cannot be signalled but is running.
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Returns whether a process is running.
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    // SAFETY: the handle is checked before use and closed once the exit code is read.
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let queried = GetExitCodeProcess(process, &mut code) != 0;
+        CloseHandle(process);
+        !queried || code == STILL_ACTIVE as u32
+    }
+}
+
+/// Returns whether a process is running; without a way to tell, every lock is live.
+#[cfg(not(any(unix, windows)))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fxp_modes::Modes;
+
+use crate::seed::seed;
+
+/// Name of the run manifest written into every output directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// The manifests this process wrote, listed by `written_manifests`.
+static WRITTEN: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A record of how an output was produced, written next to it once the mode finishes.
+///
+/// Holds the tool version, the mode, its resolved parameters, a hash of every input
+/// and the timing of the run, so that an output can be traced back and reproduced.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    mode: Modes,
+    parameters: BTreeMap<String, Value>,
+    inputs: Vec<PathBuf>,
+    started: SystemTime,
+    partial: bool,
+}
+
+/// A run as recorded in `manifest.json`, the JSON layout of the file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedRun {
+    pub tool: String,
+    pub version: String,
+    pub mode: String,
+    /// Directory the run was started from; relative path parameters are relative to it.
+    pub working_directory: String,
+    /// Resolved parameters; strings, or arrays of strings for argument lists.
+    pub parameters: BTreeMap<String, Value>,
+    pub inputs: Vec<InputRecord>,
+    pub started: u64,
+    pub finished: u64,
+    pub elapsed_seconds: f64,
+    /// Whether the run was stopped before it finished, with `--graceful`; the output
+    /// holds only what was done by then.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+/// One input file of the run and its content hash.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InputRecord {
+    pub path: String,
+    pub size: u64,
+    pub blake3: String,
+}
+
+impl Manifest {
+    /// Starts the manifest of a run of `mode`; the run is timed from now.
+    ///
+    /// # Notes
+    /// - The seed of the run, chosen with `set_seed` or generated with `generate_seed`, is
+    ///   recorded as the `seed` parameter of every mode.
+    pub fn new(mode: Modes) -> Self {
+        let mut parameters = BTreeMap::new();
+        if let Some(seed) = seed() {
+            parameters.insert("seed".to_string(), Value::String(seed.to_string()));
+        }
+        Self {
+            mode,
+            parameters,
+            inputs: Vec::new(),
+            started: SystemTime::now(),
+            partial: false,
+        }
+    }
+
+    /// Returns the mode of the run.
+    pub fn mode(&self) -> Modes {
+        self.mode
+    }
+
+    /// Marks the run as stopped before it finished.
+    pub fn partial(mut self) -> Self {
+        self.partial = true;
+        self
+    }
+
+    /// Records a resolved parameter of the run.
+    ///
+    /// # Parameters
+    /// - `name`: Name of the parameter, e.g. `"fps"`.
+    /// - `value`: Anything printable; paths should be passed through `display()`.
+    ///
+    /// # Returns
+    /// - `Self`: The manifest with the parameter recorded, for chaining.
+    pub fn parameter(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.parameters
+            .insert(name.to_string(), Value::String(value.to_string()));
+        self
+    }
+
+    /// Records a parameter made of several arguments, such as the GMIC arguments.
+    pub fn parameter_list(mut self, name: &str, values: &[String]) -> Self {
+        let values = values.iter().cloned().map(Value::String).collect();
+        self.parameters
+            .insert(name.to_string(), Value::Array(values));
+        self
+    }
+
+    /// Records input files of the run; they are hashed when the manifest is written.
+    pub fn inputs<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        for path in paths {
+            let path = path.as_ref().to_path_buf();
+            if !self.inputs.contains(&path) {
+                self.inputs.push(path);
+            }
+        }
+        self
+    }
+
+    /// Hashes the inputs and writes the manifest for `output`.
+    ///
+    /// # Parameters
+    /// - `output`: The output directory or file the run produced.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The path of the written manifest.
+    ///
+    /// # Notes
+    /// - A directory output gets `manifest.json` inside it; a file output, such as the
+    ///   Clipper's video, gets `<file name>.manifest.json` next to it.
+    /// - The run is considered finished when this is called.
+    pub fn write(&self, output: &Path) -> Result<PathBuf> {
+        let finished = SystemTime::now();
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|path| hash_input(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let working_directory = std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        let file = RecordedRun {
+            tool: "fxp_videoclipper".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            mode: format!("{:?}", self.mode),
+            working_directory,
+            parameters: self.parameters.clone(),
+            inputs,
+            started: unix_seconds(self.started),
+            finished: unix_seconds(finished),
+            elapsed_seconds: finished
+                .duration_since(self.started)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            partial: self.partial,
+        };
+
+        let manifest_path = manifest_path(output);
+        let json =
+            serde_json::to_string_pretty(&file).context("Failed to serialize run manifest")?;
+        fs::write(&manifest_path, json)
+            .with_context(|| format!("Failed to write run manifest {}", manifest_path.display()))?;
+        debug!("Run manifest written to {:?}", manifest_path);
+        WRITTEN
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(manifest_path.clone());
+
+        Ok(manifest_path)
+    }
+}
+
+impl RecordedRun {
+    /// Reads a run manifest.
+    ///
+    /// # Parameters
+    /// - `manifest_path`: Path to a `manifest.json` or `<file name>.manifest.json`.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The recorded run, or an error if the file is missing or malformed.
+    pub fn load(manifest_path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(manifest_path)
+            .with_context(|| format!("Failed to read run manifest {}", manifest_path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Malformed run manifest {}", manifest_path.display()))
+    }
+
+    /// Returns a recorded string parameter.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.get(name).and_then(Value::as_str)
+    }
+
+    /// Returns a recorded string parameter, or an error naming it if it is missing.
+    pub fn required_parameter(&self, name: &str) -> Result<&str> {
+        self.parameter(name)
+            .ok_or_else(|| anyhow!("Run manifest has no '{}' parameter", name))
+    }
+
+    /// Returns a recorded path parameter, resolved against the recorded working directory.
+    pub fn path_parameter(&self, name: &str) -> Result<PathBuf> {
+        Ok(Path::new(&self.working_directory).join(self.required_parameter(name)?))
+    }
+
+    /// Returns a recorded list parameter, such as the GMIC arguments.
+    pub fn parameter_list(&self, name: &str) -> Result<Vec<String>> {
+        self.parameters
+            .get(name)
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(String::from))
+                    .collect()
+            })
+            .ok_or_else(|| anyhow!("Run manifest has no '{}' list", name))
+    }
+
+    /// Hashes the recorded inputs again and describes every one that changed.
+    ///
+    /// # Returns
+    /// - `Vec<String>`: One line per missing or modified input; empty if all match.
+    pub fn changed_inputs(&self) -> Vec<String> {
+        self.inputs
+            .iter()
+            .filter_map(|recorded| match hash_input(Path::new(&recorded.path)) {
+                Ok(current) if current.blake3 == recorded.blake3 => None,
+                Ok(_) => Some(format!("{} has changed", recorded.path)),
+                Err(_) => Some(format!("{} is missing or unreadable", recorded.path)),
+            })
+            .collect()
+    }
+}
+
+/// Returns the output a manifest was written for.
+///
+/// `dir/manifest.json` belongs to `dir`, and `<file name>.manifest.json` to the file next to it.
+pub fn manifest_output(manifest_path: &Path) -> PathBuf {
+    let file_name = manifest_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let parent = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    match file_name.strip_suffix(&format!(".{}", MANIFEST_FILE_NAME)) {
+        Some(output_name) if !output_name.is_empty() => parent.join(output_name),
+        _ => parent.to_path_buf(),
+    }
+}
+
+/// Returns the manifests this process wrote so far, in order, one per output.
+pub fn written_manifests() -> Vec<PathBuf> {
+    WRITTEN.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Updates the manifests listed by `written_manifests` that were written into a
+/// directory since moved, such as a staging directory moved into place.
+pub(crate) fn manifests_moved(from: &Path, to: &Path) {
+    let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    for manifest in written.iter_mut() {
+        if let Ok(relative) = manifest.strip_prefix(from) {
+            *manifest = to.join(relative);
+        }
+    }
+}
+
+/// Returns where the manifest of `output` is written.
+fn manifest_path(output: &Path) -> PathBuf {
+    if output.is_dir() {
+        return output.join(MANIFEST_FILE_NAME);
+    }
+    let mut file_name = output
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".");
+    file_name.push(MANIFEST_FILE_NAME);
+    output.with_file_name(file_name)
+}
+
+/// Reads an input file and returns its size and blake3 hash.
+fn hash_input(path: &Path) -> Result<InputRecord> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {:?} for hashing", path))?;
+    let mut hasher = blake3::Hasher::new();
+    let size =
+        io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to hash {:?}", path))?;
+    Ok(InputRecord {
+        path: fs::canonicalize(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+            .display()
+            .to_string(),
+        size,
+        blake3: hasher.finalize().to_hex().to_string(),
+    })
+}
+
+/// Returns the seconds since the Unix epoch, or 0 for a clock set before it.
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,20 +1,20 @@
 use anyhow::{anyhow, Context, Result};
 use log::debug;
 use std::ffi::OsStr;
-use std::fs;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
+use fxp_modes::Capabilities;
 pub use fxp_modes::Modes;
 
+use crate::collision::{claim_output, resolve_output, CollisionPolicy, OutputType};
+
 pub trait ModeOutput {
     type Parameters;
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf>;
-}
-
-enum OutputType {
-    File,
-    Directory,
+    /// Resolves the output path and prepares it for writing, following `policy` if it exists.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf>;
+    /// Resolves the path `create_output` would produce without touching the filesystem.
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf>;
 }
 
 // Enum to hold all the possible outputs.
@@ -24,7 +24,15 @@ pub enum Output {
     Merger(MergerOutput),
     Clutter(ClutterOutput),
     Gmicer(GmicerOutput),
+    Dedup(DedupOutput),
+    Grader(GraderOutput),
+    Captioner(CaptionerOutput),
     Clipper(ClipperOutput),
+    Stabilizer(StabilizerOutput),
+    Interpolator(InterpolatorOutput),
+    Visualizer(VisualizerOutput),
+    Processor(ProcessorOutput),
+    Zoopraxiscope(ZoopraxiscopeOutput),
 }
 
 // Implement conversion from Modes to Output.
@@ -37,6 +45,14 @@ impl From<Modes> for Output {
             Modes::Clutter => Output::Clutter(ClutterOutput),
             Modes::Clipper => Output::Clipper(ClipperOutput),
             Modes::Gmicer => Output::Gmicer(GmicerOutput),
+            Modes::Dedup => Output::Dedup(DedupOutput),
+            Modes::Grader => Output::Grader(GraderOutput),
+            Modes::Captioner => Output::Captioner(CaptionerOutput),
+            Modes::Stabilizer => Output::Stabilizer(StabilizerOutput),
+            Modes::Interpolator => Output::Interpolator(InterpolatorOutput),
+            Modes::Visualizer => Output::Visualizer(VisualizerOutput),
+            Modes::Processor => Output::Processor(ProcessorOutput),
+            Modes::Zoopraxiscope => Output::Zoopraxiscope(ZoopraxiscopeOutput),
         }
     }
 }
@@ -46,29 +62,95 @@ impl ModeOutput for ExporterOutput {
     // Parameters is a tuple of the input path and an optional explicit output directory string.
     type Parameters = (PathBuf, Option<String>);
 
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
         let (input_path, output_directory) = input;
-        match output_directory.as_deref() {
-            Some(dir) => create_explicit_output_directory(dir),
-            None => self.output_directory_auto_generated(&input_path),
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+/// What the Sampler takes at each sampling point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleKind {
+    /// A still frame, saved as PNG.
+    #[default]
+    Frames,
+    /// A short video snippet, saved as MP4.
+    Clips,
+}
+
+impl SampleKind {
+    /// Returns the name of a single sample written into an existing directory.
+    pub fn single_name(&self) -> &'static str {
+        match self {
+            SampleKind::Frames => "sample_frame.png",
+            SampleKind::Clips => "sample_clip_1.mp4",
+        }
+    }
+
+    /// Returns the name of the auto-generated output directory.
+    fn directory_name(&self) -> &'static str {
+        match self {
+            SampleKind::Frames => "sample_frames",
+            SampleKind::Clips => "sample_clips",
         }
     }
 }
 
 pub struct SamplerOutput;
 impl ModeOutput for SamplerOutput {
-    // Extend the Parameters tuple to include sample_number (e.g., u32)
-    type Parameters = (PathBuf, Option<String>, usize);
+    // Extend the Parameters tuple to include sample_number (e.g., u32) and the kind of sample
+    type Parameters = (PathBuf, Option<String>, usize, SampleKind);
 
     /// Creates the output directory either explicitly (if provided) or auto-generates one.
-    /// The auto-generated directory will now take `sample_number` into account.
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
-        // Destructure the tuple into `input_path`, `output_directory`, and `sample_number`
-        let (input_path, output_directory, sample_number) = input;
+    ///
+    /// # Notes
+    /// - An explicit target is a file when a single sample is taken, and a directory otherwise.
+    /// - A single sample inside an existing directory becomes `sample_frame.png` in it, or
+    ///   `sample_clip_1.mp4` for a clip.
+    /// - A file target is created empty, reserving its name.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        // Destructure the tuple into `input_path`, `output_directory`, `sample_number` and `kind`
+        let (input_path, output_directory, sample_number, kind) = input;
+
+        match output_directory {
+            Some(dir) => {
+                let (target, output_type) = self.explicit_output_target(&dir, sample_number, kind);
+                let is_file = matches!(output_type, OutputType::File);
+                let output_path = claim_output(&target, output_type, policy, &input_path)?;
+                if is_file {
+                    debug!("Creating output file: {:?}", output_path);
+                    File::create(&output_path).context("Failed to create output file")?;
+                }
+                Ok(output_path)
+            }
+            None => claim_output(
+                &self.auto_generated_target(&input_path, kind),
+                OutputType::Directory,
+                policy,
+                &input_path,
+            ),
+        }
+    }
 
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory, sample_number, kind) = input;
         match output_directory {
-            Some(dir) => self.create_explicit_output_directory(&dir, sample_number),
-            None => self.output_directory_auto_generated(&input_path),
+            Some(dir) => {
+                let (target, output_type) = self.explicit_output_target(&dir, sample_number, kind);
+                resolve_output(&target, &output_type, policy)
+            }
+            None => resolve_output(
+                &self.auto_generated_target(&input_path, kind),
+                &OutputType::Directory,
+                policy,
+            ),
         }
     }
 }
@@ -84,6 +166,7 @@ impl ModeOutput for ClutterOutput {
     /// # Parameters
     /// - `input_path`: The source path used for generating the output directory if no explicit directory is provided.
     /// - `output_directory`: An optional directory path to use for output; if `None`, the directory is generated automatically from `input_path`.
+    /// - `policy`: What to do if the output directory already exists.
     ///
     /// # Returns
     /// - `Result<PathBuf>`: The path to the created or specified output directory.
@@ -91,12 +174,173 @@ impl ModeOutput for ClutterOutput {
     /// # Notes
     /// - If an explicit output directory is provided, it is used directly.
     /// - If no output directory is provided, one is automatically generated from the input path.
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
         let (input_path, output_directory) = input;
-        match output_directory.as_deref() {
-            Some(dir) => create_explicit_output_directory(dir),
-            None => self.output_directory_auto_generated(&input_path),
-        }
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct DedupOutput;
+impl ModeOutput for DedupOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the kept frames, explicitly or as `<input>_dedup`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct GraderOutput;
+impl ModeOutput for GraderOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the graded frames, explicitly or as `<input>_graded`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct CaptionerOutput;
+impl ModeOutput for CaptionerOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the captioned frames, explicitly or as `<input>_captioned`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct ProcessorOutput;
+impl ModeOutput for ProcessorOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the processed frames, explicitly or as
+    /// `<input>_processed`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct InterpolatorOutput;
+impl ModeOutput for InterpolatorOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the interpolated frames, explicitly or as
+    /// `<input>_interpolated`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct VisualizerOutput;
+impl ModeOutput for VisualizerOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Creates the output directory of the rendered frames, explicitly or as
+    /// `<audio_stem>_visualized`.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory) = input;
+        let target = explicit_or(output_directory, || self.auto_generated_target(&input_path));
+        resolve_output(&target, &OutputType::Directory, policy)
+    }
+}
+
+pub struct ZoopraxiscopeOutput;
+impl ModeOutput for ZoopraxiscopeOutput {
+    // Parameters: (input path, optional explicit output, whether a sheet is exploded)
+    type Parameters = (PathBuf, Option<String>, bool);
+
+    /// Creates the output of the Zoopraxiscope: the sprite sheet file of a directory of
+    /// frames, or the directory of frames exploded from a sprite sheet.
+    ///
+    /// # Notes
+    /// - An existing directory given for a sheet receives `<input_name>_sheet.png`.
+    /// - Nothing is created for a sheet; it is written next to its output and renamed
+    ///   into place once complete.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output, explode) = input;
+        let (target, output_type) = self.target(&input_path, output, explode);
+        claim_output(&target, output_type, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output, explode) = input;
+        let (target, output_type) = self.target(&input_path, output, explode);
+        resolve_output(&target, &output_type, policy)
+    }
+}
+
+pub struct StabilizerOutput;
+impl ModeOutput for StabilizerOutput {
+    type Parameters = (PathBuf, Option<String>);
+
+    /// Resolves the stabilized video, explicitly or as `<input_stem>_stabilized.<ext>`.
+    ///
+    /// # Notes
+    /// - An explicit existing directory receives the auto-generated file name.
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_file) = input;
+        let target = self.target(&input_path, output_file);
+        claim_output(&target, OutputType::File, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_file) = input;
+        let target = self.target(&input_path, output_file);
+        resolve_output(&target, &OutputType::File, policy)
     }
 }
 
@@ -114,6 +358,7 @@ impl ModeOutput for MergerOutput {
     /// - `input_path`: The path to the input file.
     /// - `output_directory`: An optional directory to use for output.
     /// - `merge_value`: A floating-point value used in auto-generating the output directory.
+    /// - `policy`: What to do if the output directory already exists.
     ///
     /// # Returns
     /// - `Result<PathBuf>`: The resulting output path, or an error if it fails.
@@ -121,12 +366,20 @@ impl ModeOutput for MergerOutput {
     /// # Notes
     /// - If `output_directory` is provided, it is used explicitly.
     /// - If `output_directory` is not provided, the directory is auto-generated based on `input_path` and `merge_value`.
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
         let (input_path, output_directory, merge_value) = input;
-        match output_directory.as_deref() {
-            Some(dir) => create_explicit_output_directory(&dir),
-            None => self.output_directory_auto_generated(&input_path, merge_value),
-        }
+        let target = explicit_or(output_directory, || {
+            self.auto_generated_target(&input_path, merge_value)
+        });
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, output_directory, merge_value) = input;
+        let target = explicit_or(output_directory, || {
+            self.auto_generated_target(&input_path, merge_value)
+        });
+        resolve_output(&target, &OutputType::Directory, policy)
     }
 }
 
@@ -146,25 +399,36 @@ impl ModeOutput for GmicerOutput {
     /// - `input_path`: Path to the input file.
     /// - `gmic_args`: Vector of arguments for GMIC.
     /// - `output_directory`: Optional output directory.
+    /// - `policy`: What to do if the output directory already exists.
     ///
     /// # Returns
     /// - `Result<PathBuf>`: The determined output path, or an error if creation fails.
     ///
     /// # Notes
     /// - If `output_directory` is `None`, it is automatically generated from `input_path` and `gmic_args`.
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
         let (input_path, gmic_args, output_directory) = input;
-        match output_directory.as_deref() {
-            Some(dir) => create_explicit_output_directory(dir),
-            None => self.output_directory_auto_generated(&input_path, &gmic_args),
-        }
+        let target = match output_directory {
+            Some(dir) => PathBuf::from(dir),
+            None => self.auto_generated_target(&input_path, &gmic_args)?,
+        };
+        claim_output(&target, OutputType::Directory, policy, &input_path)
+    }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, gmic_args, output_directory) = input;
+        let target = match output_directory {
+            Some(dir) => PathBuf::from(dir),
+            None => self.auto_generated_target(&input_path, &gmic_args)?,
+        };
+        resolve_output(&target, &OutputType::Directory, policy)
     }
 }
 
 pub struct ClipperOutput;
 impl ModeOutput for ClipperOutput {
-    // Parameters: (input_path, optional custom directory as PathBuf, optional string parameter)
-    type Parameters = (PathBuf, Option<PathBuf>, Option<String>);
+    // Parameters: (input_path, optional MP3 path, optional explicit output, file extension)
+    type Parameters = (PathBuf, Option<PathBuf>, Option<String>, &'static str);
 
     /// Creates output files based on specified parameters.
     ///
@@ -174,6 +438,7 @@ impl ModeOutput for ClipperOutput {
     /// - `input_path`: The input file path as a `PathBuf`.
     /// - `mp3_path`: An optional path to an MP3 file.
     /// - `output_path`: An optional output directory as a `String`.
+    /// - `policy`: What to do if the output file already exists.
     ///
     /// # Returns
     /// - `Result<PathBuf>`: The path to the created output file on success.
@@ -181,38 +446,54 @@ impl ModeOutput for ClipperOutput {
     /// # Notes
     /// - If an explicit `output_path` is provided, the function will create the output file in that directory.
     /// - If no `output_path` is provided, the function will auto-generate the output directory based on the `input_path` and `mp3_path`.
-    fn create_output(&self, input: Self::Parameters) -> Result<PathBuf> {
-        let (input_path, mp3_path, output_path) = input;
+    fn create_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, mp3_path, output_path, extension) = input;
         match output_path.as_deref() {
             // If an explicit output directory is provided, use it.
-            Some(output_path) => {
-                self.create_explicit_output_file(output_path, mp3_path, &input_path)
-            }
+            Some(output_path) => self.create_explicit_output_file(
+                output_path,
+                mp3_path,
+                &input_path,
+                extension,
+                policy,
+            ),
             // Otherwise, auto-generate the output directory, passing the optional mp3_path.
-            None => self.output_file_auto_generated(&input_path, mp3_path.as_deref()),
+            None => claim_output(
+                &self.auto_generated_target(&input_path, mp3_path.as_deref(), extension),
+                OutputType::File,
+                policy,
+                &input_path,
+            ),
         }
     }
+
+    fn plan_output(&self, input: Self::Parameters, policy: CollisionPolicy) -> Result<PathBuf> {
+        let (input_path, mp3_path, output_path, extension) = input;
+        let target = match output_path.as_deref() {
+            Some(output_path) => {
+                self.explicit_output_file(output_path, mp3_path, &input_path, extension)?
+            }
+            None => self.auto_generated_target(&input_path, mp3_path.as_deref(), extension),
+        };
+        resolve_output(&target, &OutputType::File, policy)
+    }
 }
 
 impl GmicerOutput {
-    /// Automatically generates an output directory name based on the input path and GMIC arguments.
+    /// Builds the auto-generated output directory from the input path and GMIC arguments.
     ///
     /// # Parameters
     /// - `input_path`: The path to the input file.
     /// - `gmic_args`: The arguments provided to GMIC.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The path to the generated output directory, or an error if creation fails.
+    /// - `Result<PathBuf>`: The preferred output directory, or an error if there are no GMIC arguments.
     ///
     /// # Notes
     /// - The directory name is created by combining the input filename and the first GMIC argument.
     /// - If the input filename is unavailable, it defaults to "input".
-    /// - If the directory already exists, a unique suffix is appended to ensure uniqueness.
-    fn output_directory_auto_generated(
-        &self,
-        input_path: &Path,
-        gmic_args: &[String],
-    ) -> Result<PathBuf> {
+    /// - The directory is placed next to the input.
+    fn auto_generated_target(&self, input_path: &Path, gmic_args: &[String]) -> Result<PathBuf> {
         let first_arg = gmic_args
             .first()
             .ok_or_else(|| anyhow!("GMIC arguments should not be empty"))?;
@@ -227,269 +508,372 @@ impl GmicerOutput {
                 .to_string_lossy(),
             first_arg
         );
-
-        // Determine the parent directory for the new directory.
         let parent_dir = input_path.parent().unwrap_or_else(|| Path::new("."));
-
-        // Use the helper function to create a unique directory.
-        let output_path = create_unique_dir(parent_dir, &base_directory_name)
-            .with_context(|| format!("Failed to create output directory under {:?}", parent_dir))?;
-
-        debug!("Output directory created successfully: {:?}", output_path);
-        Ok(output_path)
+        Ok(parent_dir.join(base_directory_name))
     }
 }
 impl MergerOutput {
-    /// Automatically generates a unique output directory name by appending a suffix.
-    /// Creates a directory in the parent of the given input path with a base name
-    /// formatted as `input_filename_merged_{merge_value}`.
+    /// Builds the auto-generated output directory `input_filename_merged_{merge_value}`.
     ///
     /// # Parameters
     /// - `input_path`: The input file path used to derive the output directory name.
     /// - `merge_value`: A float value incorporated into the directory name.
     ///
     /// # Returns
-    /// - `PathBuf`: The path to the newly created directory.
-    ///
-    /// # Notes
-    /// - The directory is created in the parent directory of `input_path`.
-    /// - Ensures uniqueness by appending a random suffix if necessary.
-    fn output_directory_auto_generated(
-        &self,
-        input_path: &Path,
-        merge_value: f32,
-    ) -> Result<PathBuf> {
+    /// - `PathBuf`: The preferred output directory, in the parent directory of `input_path`.
+    fn auto_generated_target(&self, input_path: &Path, merge_value: f32) -> PathBuf {
         let base_directory_name = format!(
-            "{}_merged_{}",
+            "{}{}_{}",
             input_path
                 .file_name()
                 .unwrap_or_else(|| OsStr::new("input"))
                 .to_string_lossy(),
+            Modes::Merger.default_output_suffix().unwrap_or_default(),
             merge_value
         );
-
         let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
-        // Use the refactored function instead of duplicating the loop.
-        create_unique_dir(parent, &base_directory_name)
+        parent.join(base_directory_name)
     }
 }
 impl SamplerOutput {
-    /// Auto-generates an output directory based on the input path, ensuring a unique name.
-    ///
-    /// This function creates an output directory by first attempting to use a base name.
-    /// If that directory exists, it appends a numerical suffix to find an available name.
+    /// Builds the auto-generated output directory `sample_frames`, or `sample_clips` for
+    /// clips, next to the input.
     ///
     /// # Parameters
     /// - `input_path`: The path used as the foundation for the output directory.
+    /// - `kind`: What is sampled.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The path to the created directory, or an error if creation fails.
-    ///
-    /// # Notes
-    /// - The base directory name is "sample_frames".
-    /// - If the base name is taken, it appends a counter (e.g., "sample_frames_1", "sample_frames_2").
-    fn output_directory_auto_generated(&self, input_path: &Path) -> Result<PathBuf> {
-        let base_directory_name = "sample_frames";
+    /// - `PathBuf`: The preferred output directory.
+    fn auto_generated_target(&self, input_path: &Path, kind: SampleKind) -> PathBuf {
+        let base_directory_name = kind.directory_name();
         debug!("Base directory name: {}", base_directory_name);
 
         let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
         debug!("Parent directory: {:?}", parent);
 
-        let candidate_path = parent.join(base_directory_name);
-        debug!("Initial candidate path: {:?}", candidate_path);
-
-        let output_path = if candidate_path.exists() {
-            debug!("Candidate path exists, looking for alternative");
-            let mut counter = 1;
-            loop {
-                let candidate_name = format!("{}_{}", base_directory_name, counter);
-                let candidate_path = parent.join(&candidate_name);
-                debug!("Checking alternative path: {:?}", candidate_path);
-
-                if !candidate_path.exists() {
-                    debug!("Found available path: {:?}", candidate_path);
-                    break candidate_path;
-                }
-                counter += 1;
-            }
-        } else {
-            debug!("Candidate path is available");
-            candidate_path
-        };
-
-        debug!("Creating directory at: {:?}", output_path);
-        fs::create_dir_all(&output_path)
-            .with_context(|| format!("Failed to create output directory {:?}", output_path))?;
-
-        debug!("Successfully created output directory: {:?}", output_path);
-        Ok(output_path)
+        parent.join(base_directory_name)
     }
 
-    /// Creates an explicit output target.
-    /// - When sampling_number is 1, the target is treated as a file.
-    ///   * If the provided path exists as a file, it is removed.
-    ///   * If it exists as a directory, a file named "output_file" is created inside that directory.
-    ///   * If it doesn't exist, an output file is created at that path (ensuring parent dirs exist).
-    /// - When sampling_number is greater than 1, the target is treated as a directory.
-    ///   * Any existing file or directory at that path is removed and a directory is created.
-    fn create_explicit_output_directory(
+    /// Resolves the explicit output target without touching the filesystem.
+    ///
+    /// When a single sample is taken the target is a file, and a single sample inside an
+    /// existing directory is named by `SampleKind::single_name` in it. Otherwise the target
+    /// is a directory.
+    fn explicit_output_target(
         &self,
         output_dir: &str,
         sampling_number: usize,
-    ) -> Result<PathBuf> {
-        debug!("Output path provided: {:?}", output_dir);
+        kind: SampleKind,
+    ) -> (PathBuf, OutputType) {
         let output_path = Path::new(output_dir);
-
-        // Map sampling number to output type.
-        let output_type = match sampling_number {
-            1 => OutputType::File,
-            _ => OutputType::Directory,
-        };
-
-        match output_type {
-            OutputType::File => {
-                // Check if the path exists.
-                if output_path.exists() {
-                    if output_path.is_dir() {
-                        // Instead of removing the directory, append a file name.
-                        let file_path = output_path.join("sample_frame.png");
-                        debug!(
-                            "Output path is a directory; creating file inside: {:?}",
-                            file_path
-                        );
-                        // If the file already exists in the directory, remove it.
-                        if file_path.exists() {
-                            if file_path.is_file() {
-                                debug!(
-                                    "Existing file inside directory found, removing it: {:?}",
-                                    file_path
-                                );
-                                fs::remove_file(&file_path).context(
-                                    "Failed to remove existing file in directory target",
-                                )?;
-                            }
-                        }
-                        File::create(&file_path)
-                            .context("Failed to create output file inside directory")?;
-                        return Ok(file_path);
-                    } else if output_path.is_file() {
-                        debug!("Existing file found, removing it: {:?}", output_path);
-                        fs::remove_file(output_path).context("Failed to remove existing file")?;
-                    }
-                } else {
-                    // If the file doesn't exist, ensure its parent directories exist.
-                    if let Some(parent) = output_path.parent() {
-                        fs::create_dir_all(parent)
-                            .context("Failed to create parent directories for output file")?;
-                    }
-                }
-                debug!("Creating output file: {:?}", output_path);
-                File::create(output_path).context("Failed to create output file")?;
-                Ok(output_path.to_path_buf())
-            }
-            OutputType::Directory => {
-                if output_path.exists() {
-                    if output_path.is_file() {
-                        debug!(
-                            "Existing file found at directory target, removing it: {:?}",
-                            output_path
-                        );
-                        fs::remove_file(output_path)
-                            .context("Failed to remove existing file at directory target")?;
-                    }
-                }
-                debug!("Creating output directory: {:?}", output_path);
-                fs::create_dir_all(output_path).context("Failed to create output directory")?;
-                Ok(output_path.to_path_buf())
-            }
+        match sampling_number {
+            1 if output_path.is_dir() => (output_path.join(kind.single_name()), OutputType::File),
+            1 => (output_path.to_path_buf(), OutputType::File),
+            _ => (output_path.to_path_buf(), OutputType::Directory),
         }
     }
 }
 impl ExporterOutput {
-    /// Auto-generates an output directory based on the input file's path.
-    ///
-    /// This method creates a uniquely named directory for output in the parent
-    /// directory of the input path, using the input filename without extension.
+    /// Builds the auto-generated output directory `<input_name>_original_frames`.
     ///
     /// # Parameters
     /// - `input_path`: The path to the input file used to determine the output directory.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The path to the created output directory.
-    ///
-    /// # Notes
-    /// - The directory name is formatted as `<input_name>_original_frames`.
-    /// - If the directory exists, a unique name is created by appending a number.
-    /// - The directory is created in the parent directory of `input_path`.
-    fn output_directory_auto_generated(&self, input_path: &Path) -> Result<PathBuf> {
+    /// - `PathBuf`: The preferred output directory, in the parent directory of `input_path`.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
         let base_directory_name = format!(
-            "{}_original_frames",
+            "{}{}",
             input_path
                 .file_stem() // Strip the extension.
                 .unwrap_or_else(|| OsStr::new("input"))
-                .to_string_lossy()
+                .to_string_lossy(),
+            Modes::Exporter.default_output_suffix().unwrap_or_default()
         );
-
-        // Determine the parent directory of the input path.
         let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
-        // Delegate the unique directory creation to the helper function.
-        create_unique_dir(parent, &base_directory_name)
+        parent.join(base_directory_name)
     }
 }
 impl ClutterOutput {
-    /// Generates a unique output directory name based on the input file's name and location.
-    ///
-    /// This function constructs a directory name by appending `_clutted` to the input file's name.
-    /// If no file name is present, it defaults to `input_clutted`.
-    /// The directory is created in the same location as the input file.
+    /// Builds the auto-generated output directory `<input_name>_clutted`.
     ///
     /// # Parameters
     /// - `input_path`: The path to the input file used to generate the output directory name.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The path to the generated directory, or an error if creation fails.
+    /// - `PathBuf`: The preferred output directory, in the same location as the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let base_directory_name = format!(
+            "{}{}",
+            input_path
+                .file_name()
+                .unwrap_or_else(|| OsStr::new("input"))
+                .to_string_lossy(),
+            Modes::Clutter.default_output_suffix().unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl DedupOutput {
+    /// Builds the auto-generated output directory `<input_name>_dedup`.
     ///
-    /// # Notes
-    /// - If the directory already exists, a unique name is created by appending a numerical suffix.
-    fn output_directory_auto_generated(&self, input_path: &Path) -> Result<PathBuf> {
+    /// # Parameters
+    /// - `input_path`: The input directory the output directory is named after.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
         let base_directory_name = format!(
-            "{}_clutted",
+            "{}{}",
             input_path
                 .file_name()
                 .unwrap_or_else(|| OsStr::new("input"))
-                .to_string_lossy()
+                .to_string_lossy(),
+            Modes::Dedup.default_output_suffix().unwrap_or_default()
         );
-
         let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
-        create_unique_dir(parent, &base_directory_name)
+        parent.join(base_directory_name)
+    }
+}
+impl GraderOutput {
+    /// Builds the auto-generated output directory `<input_name>_graded`.
+    ///
+    /// # Parameters
+    /// - `input_path`: The input directory the output directory is named after.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let base_directory_name = format!(
+            "{}{}",
+            input_path
+                .file_name()
+                .unwrap_or_else(|| OsStr::new("input"))
+                .to_string_lossy(),
+            Modes::Grader.default_output_suffix().unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl CaptionerOutput {
+    /// Builds the auto-generated output directory `<input_name>_captioned`.
+    ///
+    /// # Parameters
+    /// - `input_path`: The input directory the output directory is named after.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let base_directory_name = format!(
+            "{}{}",
+            input_path
+                .file_name()
+                .unwrap_or_else(|| OsStr::new("input"))
+                .to_string_lossy(),
+            Modes::Captioner.default_output_suffix().unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl ProcessorOutput {
+    /// Builds the auto-generated output directory `<input_name>_processed`.
+    ///
+    /// # Parameters
+    /// - `input_path`: The input directory the output directory is named after.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let base_directory_name = format!(
+            "{}{}",
+            input_path
+                .file_name()
+                .unwrap_or_else(|| OsStr::new("input"))
+                .to_string_lossy(),
+            Modes::Processor.default_output_suffix().unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl InterpolatorOutput {
+    /// Builds the auto-generated output directory `<input_name>_interpolated`.
+    ///
+    /// # Parameters
+    /// - `input_path`: The input directory, or video whose stem names the output.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the input.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let name = if input_path.is_file() {
+            input_path.file_stem()
+        } else {
+            input_path.file_name()
+        };
+        let base_directory_name = format!(
+            "{}{}",
+            name.unwrap_or_else(|| OsStr::new("input"))
+                .to_string_lossy(),
+            Modes::Interpolator
+                .default_output_suffix()
+                .unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl VisualizerOutput {
+    /// Builds the auto-generated output directory `<audio_stem>_visualized`.
+    ///
+    /// # Parameters
+    /// - `input_path`: The audio file whose stem names the output.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output directory, next to the audio.
+    fn auto_generated_target(&self, input_path: &Path) -> PathBuf {
+        let base_directory_name = format!(
+            "{}{}",
+            input_path
+                .file_stem()
+                .unwrap_or_else(|| OsStr::new("audio"))
+                .to_string_lossy(),
+            Modes::Visualizer
+                .default_output_suffix()
+                .unwrap_or_default()
+        );
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        parent.join(base_directory_name)
+    }
+}
+impl ZoopraxiscopeOutput {
+    /// Builds the preferred output: `<input_name>_sheet.png` for a sheet, or
+    /// `<sheet_stem>_frames` for the frames exploded from one.
+    ///
+    /// # Parameters
+    /// - `input_path`: The directory of frames, or the sprite sheet to explode.
+    /// - `output`: The explicit output, if any.
+    /// - `explode`: Whether the input is a sheet to explode into frames.
+    ///
+    /// # Returns
+    /// - `(PathBuf, OutputType)`: The preferred output, next to the input by default, and
+    ///   whether it is a file or a directory.
+    fn target(
+        &self,
+        input_path: &Path,
+        output: Option<String>,
+        explode: bool,
+    ) -> (PathBuf, OutputType) {
+        let parent = input_path.parent().unwrap_or_else(|| Path::new("."));
+        if explode {
+            let directory_name = format!(
+                "{}_frames",
+                input_path
+                    .file_stem()
+                    .unwrap_or_else(|| OsStr::new("sheet"))
+                    .to_string_lossy()
+            );
+            let target = explicit_or(output, || parent.join(directory_name));
+            return (target, OutputType::Directory);
+        }
+        let file_name = format!(
+            "{}_sheet.png",
+            input_path
+                .file_name()
+                .unwrap_or_else(|| OsStr::new("frames"))
+                .to_string_lossy()
+        );
+        let target = match output.map(PathBuf::from) {
+            Some(directory) if directory.is_dir() => directory.join(file_name),
+            Some(file) => file,
+            None => parent.join(file_name),
+        };
+        (target, OutputType::File)
+    }
+}
+impl StabilizerOutput {
+    /// Builds the preferred output file from the input video and the explicit output, if any.
+    ///
+    /// # Parameters
+    /// - `input_path`: The video being stabilized.
+    /// - `output_file`: The explicit output file or directory, if any.
+    ///
+    /// # Returns
+    /// - `PathBuf`: The preferred output file, next to the input by default.
+    fn target(&self, input_path: &Path, output_file: Option<String>) -> PathBuf {
+        let file_name = format!(
+            "{}{}.{}",
+            input_path
+                .file_stem()
+                .unwrap_or_else(|| OsStr::new("video"))
+                .to_string_lossy(),
+            Modes::Stabilizer
+                .default_output_suffix()
+                .unwrap_or_default(),
+            input_path
+                .extension()
+                .unwrap_or_else(|| OsStr::new("mp4"))
+                .to_string_lossy()
+        );
+        match output_file.map(PathBuf::from) {
+            Some(directory) if directory.is_dir() => directory.join(file_name),
+            Some(file) => file,
+            None => input_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join(file_name),
+        }
     }
 }
 impl ClipperOutput {
     /// Creates an explicit output file path, handling both file and directory cases.
     ///
     /// This function determines the appropriate output path based on whether the provided
-    /// path points to a file or directory. If the output path exists and is a file, it will
-    /// be removed before creating the new output file.
+    /// path points to a file or directory, then applies the collision policy to it.
     ///
     /// # Parameters
     /// - `output_file_or_dir`: The desired output path, which can be a file or directory.
     /// - `mp3_path`: An optional MP3 file path used to derive the output filename.
     /// - `input_dir`: The input directory path used as a fallback when `mp3_path` is not provided.
+    /// - `extension`: The extension of a generated file name, e.g. `mp4` or `gif`.
+    /// - `policy`: What to do if the output file already exists.
     ///
     /// # Returns
     /// - `Result<PathBuf>`: The final output file path as a `PathBuf` on success.
     ///
     /// # Notes
     /// - If `output_file_or_dir` is a directory and `mp3_path` is provided, the output filename
-    ///   will be derived from the MP3 file's stem with an `.mp4` extension.
+    ///   will be derived from the MP3 file's stem with the given extension.
     /// - If `output_file_or_dir` is a directory and `mp3_path` is not provided, the output filename
-    ///   will be derived from the `input_dir`'s name with an `.mp4` extension.
-    /// - Existing files at the output path will be removed before creating the new file.
+    ///   will be derived from the `input_dir`'s name with the given extension.
+    /// - Nothing is created at the output path; the Clipper writes a `.part` file next to it
+    ///   and renames it into place once the video is complete.
     fn create_explicit_output_file(
         &self,
         output_file_or_dir: &str,
         mp3_path: Option<PathBuf>,
         input_dir: &Path,
+        extension: &str,
+        policy: CollisionPolicy,
+    ) -> Result<PathBuf> {
+        let target =
+            self.explicit_output_file(output_file_or_dir, mp3_path, input_dir, extension)?;
+        claim_output(&target, OutputType::File, policy, input_dir)
+    }
+
+    /// Resolves the final output file for an explicit output path without touching the filesystem.
+    ///
+    /// An existing directory receives a file named after the MP3 stem (or the input directory)
+    /// with the given extension; any other path is used as the output file itself.
+    fn explicit_output_file(
+        &self,
+        output_file_or_dir: &str,
+        mp3_path: Option<PathBuf>,
+        input_dir: &Path,
+        extension: &str,
     ) -> Result<PathBuf> {
         debug!("Output file provided: {:?}", output_file_or_dir);
         let output_path = std::path::Path::new(output_file_or_dir);
@@ -515,9 +899,10 @@ impl ClipperOutput {
                     let stem = mp3
                         .file_stem()
                         .ok_or_else(|| anyhow!("MP3 path does not have a valid file stem"))?;
-                    // Create a new filename with .mp4 extension.
+                    // Create a new filename with the extension of the format.
                     let mut new_filename = std::ffi::OsString::from(stem);
-                    new_filename.push(".mp4");
+                    new_filename.push(".");
+                    new_filename.push(extension);
                     output_path.join(new_filename)
                 } else {
                     // Use the input directory's filename.
@@ -525,186 +910,63 @@ impl ClipperOutput {
                         anyhow!("Input directory does not have a valid file name")
                     })?;
                     let mut new_filename = std::ffi::OsString::from(input_dir_filename);
-                    new_filename.push(".mp4");
+                    new_filename.push(".");
+                    new_filename.push(extension);
                     output_path.join(new_filename)
                 }
             }
         };
 
-        // If the final output path already exists and it's a file, remove it.
-        if final_output_path.exists() {
-            if final_output_path.is_file() {
-                debug!(
-                    "Output file exists as file, removing it: {:?}",
-                    final_output_path
-                );
-                std::fs::remove_file(&final_output_path)
-                    .with_context(|| "Failed to remove existing output file")?;
-            } else {
-                debug!(
-                    "Output path is a directory, not removing it: {:?}",
-                    final_output_path
-                );
-            }
-        }
-
-        debug!("Creating output file: {:?}", final_output_path);
-        std::fs::File::create(&final_output_path)
-            .with_context(|| "Failed to create output file")?;
-
         Ok(final_output_path)
     }
 
-    /// Generates an output file path based on the provided MP3 file or input directory.
-    ///
-    /// This function constructs the output file path by using either the MP3 file's parent
-    /// directory and file stem, or the input directory's details if no MP3 path is provided.
+    /// Builds the auto-generated output file from the MP3 file or input directory.
     ///
     /// # Parameters
     /// - `input_dir`: The input directory path used as a fallback when no MP3 path is provided.
     /// - `mp3_path`: An optional MP3 file path that determines the output directory and filename.
+    /// - `extension`: The extension of the file, e.g. `mp4` or `gif`.
     ///
     /// # Returns
-    /// - `Result<PathBuf>`: The constructed output file path.
+    /// - `PathBuf`: The preferred output file, `<stem>.<extension>`.
     ///
     /// # Notes
     /// - If an MP3 path is provided, the function uses its parent directory and file stem.
     /// - If no MP3 path is provided, the function uses the input directory's parent and name.
-    fn output_file_auto_generated(
+    fn auto_generated_target(
         &self,
         input_dir: &Path,
         mp3_path: Option<&Path>,
-    ) -> Result<PathBuf> {
-        if let Some(mp3) = mp3_path {
-            debug!("MP3 path provided: {:?}", mp3);
-            let parent = mp3.parent().unwrap_or_else(|| Path::new("."));
-            debug!("Using parent directory: {:?}", parent);
-            let stem = mp3.file_stem().unwrap_or_else(|| OsStr::new("input"));
-            debug!("Using file stem: {:?}", stem);
-            Ok(self.build_output_file(parent, stem))
-        } else {
-            debug!(
-                "No MP3 path provided, using input directory: {:?}",
-                input_dir
-            );
-            let parent = input_dir.parent().unwrap_or_else(|| Path::new("."));
-            debug!("Using parent directory: {:?}", parent);
-            let stem = input_dir.file_name().unwrap_or_else(|| OsStr::new("input"));
-            debug!("Using file stem: {:?}", stem);
-            Ok(self.build_output_file(parent, stem))
-        }
-    }
-
-    /// Constructs an output file path with a unique name for an MP4 file.
-    ///
-    /// This function generates a file path by combining the provided directory and stem.
-    /// If the desired file already exists, it appends an incrementing counter to the stem to ensure uniqueness.
-    ///
-    /// # Parameters
-    /// - `dir`: The directory path where the output file will be created.
-    /// - `stem`: The base name of the file without the extension.
-    ///
-    /// # Returns
-    /// - The unique `PathBuf` representing the output file path.
-    ///
-    /// # Behavior
-    /// 1. Creates the initial candidate path with ".mp4" extension
-    /// 2. Checks if the candidate file exists:
-    ///    - If it doesn't exist, returns the candidate path
-    ///    - If it does exist, appends an incrementing counter to the stem until a unique name is found
-    fn build_output_file(&self, dir: &Path, stem: &OsStr) -> PathBuf {
-        debug!("Starting build_output_file function");
-        debug!("Directory: {:?}, Stem: {:?}", dir, stem);
-
-        // Create the initial candidate path by joining the directory and stem, then setting its extension.
-        let mut candidate = dir.join(stem);
-        candidate.set_extension("mp4");
-        debug!("Initial candidate path: {:?}", candidate);
-
-        // If the candidate already exists, generate a new stem by appending an incrementing counter.
-        if candidate.exists() {
-            debug!("Candidate path already exists: {:?}", candidate);
-            let stem_str = stem.to_string_lossy();
-            let mut counter = 1;
-            loop {
-                // Create a new candidate name by appending the counter
-                let new_stem = format!("{}_{}", stem_str, counter);
-                let mut new_candidate = dir.join(new_stem);
-                new_candidate.set_extension("mp4");
-                if !new_candidate.exists() {
-                    candidate = new_candidate;
-                    break;
-                }
-                counter += 1;
+        extension: &str,
+    ) -> PathBuf {
+        let (parent, stem) = match mp3_path {
+            Some(mp3) => {
+                debug!("MP3 path provided: {:?}", mp3);
+                (
+                    mp3.parent().unwrap_or_else(|| Path::new(".")),
+                    mp3.file_stem().unwrap_or_else(|| OsStr::new("input")),
+                )
             }
-            debug!("Updated candidate path: {:?}", candidate);
-        } else {
-            debug!("Candidate path does not exist, using: {:?}", candidate);
-        }
+            None => {
+                debug!(
+                    "No MP3 path provided, using input directory: {:?}",
+                    input_dir
+                );
+                (
+                    input_dir.parent().unwrap_or_else(|| Path::new(".")),
+                    input_dir.file_name().unwrap_or_else(|| OsStr::new("input")),
+                )
+            }
+        };
+        debug!("Using parent directory: {:?}, stem: {:?}", parent, stem);
 
-        debug!("Final output path: {:?}", candidate);
+        let mut candidate = parent.join(stem);
+        candidate.set_extension(extension);
         candidate
     }
 }
 
-/// Creates a uniquely named directory, ensuring no existing directory with the same name.
-///
-/// This function attempts to create a directory with the given base name. If the directory
-/// already exists, it appends an incrementing counter to the base name until a unique
-/// directory is found.
-///
-/// # Parameters
-/// - `parent`: The parent directory path where the new directory should be created.
-/// - `base_name`: The base name of the directory to create.
-///
-/// # Returns
-/// - `Result<PathBuf>`: The path to the newly created directory on success.
-///
-/// # Notes
-/// - If the directory with `base_name` already exists, a numeric suffix is added
-///   (e.g., `name_1`, `name_2`, etc.) until a unique name is found.
-fn create_unique_dir(parent: &Path, base_name: &str) -> Result<PathBuf> {
-    // Check if the directory with the base name already exists.
-    let base_path = parent.join(base_name);
-    if !base_path.exists() {
-        fs::create_dir_all(&base_path)
-            .with_context(|| format!("Failed to create output directory {:?}", base_path))?;
-        return Ok(base_path);
-    }
-
-    // Otherwise, append an incrementing number until a free directory is found.
-    let mut counter = 1;
-    let output_path = loop {
-        let candidate_name = format!("{}_{counter}", base_name);
-        let candidate_path = parent.join(&candidate_name);
-        if !candidate_path.exists() {
-            break candidate_path;
-        }
-        counter += 1;
-    };
-
-    fs::create_dir_all(&output_path)
-        .with_context(|| format!("Failed to create output directory {:?}", output_path))?;
-    Ok(output_path)
-}
-
-/// Creates an explicit output directory, ensuring all necessary parent directories exist.
-///
-/// This function validates and creates the specified output directory structure.
-///
-/// # Parameters
-/// - `output_dir`: The path to the output directory to be created.
-///
-/// # Returns
-/// - `Result<PathBuf>`: The created directory path on success, or an error if creation fails.
-///
-/// # Notes
-/// - Creates parent directories if they do not already exist.
-fn create_explicit_output_directory(output_dir: &str) -> Result<PathBuf> {
-    debug!("Output directory provided: {:?}", output_dir);
-    let output_path = Path::new(output_dir);
-
-    debug!("Creating output directory: {:?}", output_path);
-    fs::create_dir_all(output_path).context("Failed to create output directory")?;
-    Ok(output_path.to_path_buf())
+/// Returns the explicit output path if one was given, otherwise the auto-generated one.
+fn explicit_or(explicit: Option<String>, auto_generated: impl FnOnce() -> PathBuf) -> PathBuf {
+    explicit.map(PathBuf::from).unwrap_or_else(auto_generated)
 }
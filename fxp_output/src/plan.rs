@@ -0,0 +1,53 @@
+use std::fmt;
+
+use fxp_modes::Modes;
+
+/// The resolved parameters of a mode, printed instead of running it under `--dry-run`.
+///
+/// Entries keep their insertion order so the printed plan is deterministic.
+#[derive(Debug)]
+pub struct Plan {
+    mode: Modes,
+    entries: Vec<(String, String)>,
+}
+
+impl Plan {
+    /// Creates an empty plan for the given mode.
+    pub fn new(mode: Modes) -> Self {
+        Self {
+            mode,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Appends a labelled value to the plan.
+    ///
+    /// # Parameters
+    /// - `label`: Short description of the value, e.g. `"output"`.
+    /// - `value`: Anything printable; paths should be passed through `display()`.
+    ///
+    /// # Returns
+    /// - `Self`: The plan with the entry appended, for chaining.
+    pub fn entry(mut self, label: &str, value: impl fmt::Display) -> Self {
+        self.entries.push((label.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Dry run: {:?} (nothing will be written)", self.mode)?;
+
+        // Align all values on the longest label.
+        let width = self
+            .entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in &self.entries {
+            writeln!(f, "  {:<width$} : {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use console::Term;
+use indicatif::{ProgressBar, ProgressDrawTarget, TermLike};
+use log::debug;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How long the plain progress output waits between two lines.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+static PROGRESS_MODE: OnceLock<ProgressMode> = OnceLock::new();
+
+/// How the modes report their progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// An animated progress bar, redrawn in place.
+    Bar,
+    /// A single line of plain text every few seconds, for logs of CI or cron jobs.
+    Plain,
+    /// No progress output.
+    None,
+}
+
+impl ProgressMode {
+    /// Picks the bar when stderr is a terminal, and plain lines otherwise.
+    pub fn detect() -> Self {
+        if Term::stderr().is_term() {
+            ProgressMode::Bar
+        } else {
+            ProgressMode::Plain
+        }
+    }
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bar" => Ok(ProgressMode::Bar),
+            "plain" => Ok(ProgressMode::Plain),
+            "none" => Ok(ProgressMode::None),
+            other => Err(format!(
+                "Unknown progress mode '{}', expected one of: bar, plain, none",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProgressMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProgressMode::Bar => "bar",
+            ProgressMode::Plain => "plain",
+            ProgressMode::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Sets how progress is reported for the rest of the process.
+///
+/// # Parameters
+/// - `mode`: The progress mode, or `None` to detect it from stderr.
+///
+/// # Notes
+/// - Only the first call has an effect; it must happen before any progress bar is created.
+pub fn set_progress_mode(mode: Option<ProgressMode>) {
+    let mode = mode.unwrap_or_else(ProgressMode::detect);
+    debug!("Progress mode: {}", mode);
+    let _ = PROGRESS_MODE.set(mode);
+}
+
+/// Returns the progress mode set by `set_progress_mode`, or the detected one.
+pub fn progress_mode() -> ProgressMode {
+    *PROGRESS_MODE.get_or_init(ProgressMode::detect)
+}
+
+/// Creates a progress bar of `len` steps drawn according to the progress mode.
+///
+/// # Parameters
+/// - `len`: The number of steps of the bar.
+///
+/// # Returns
+/// - `ProgressBar`: A bar to style and advance like any indicatif bar.
+///
+/// # Notes
+/// - In plain mode the rendered bar is printed as one line without control characters
+///   at most every few seconds, plus its final state when the bar is dropped.
+pub fn progress_bar(len: u64) -> ProgressBar {
+    let target = match progress_mode() {
+        ProgressMode::Bar => ProgressDrawTarget::stderr(),
+        ProgressMode::Plain => ProgressDrawTarget::term_like(Box::new(PlainLines::default())),
+        ProgressMode::None => ProgressDrawTarget::hidden(),
+    };
+    ProgressBar::with_draw_target(Some(len), target)
+}
+
+/// A draw target that turns the redraws of a bar into occasional lines on stderr.
+#[derive(Debug, Default)]
+struct PlainLines {
+    state: Mutex<PlainState>,
+}
+
+#[derive(Debug, Default)]
+struct PlainState {
+    /// Text of the redraw in progress.
+    drawing: String,
+    /// Last complete redraw that has not been printed yet.
+    unprinted: Option<String>,
+    printed_at: Option<Instant>,
+}
+
+impl PlainLines {
+    fn state(&self) -> std::sync::MutexGuard<'_, PlainState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl TermLike for PlainLines {
+    fn width(&self) -> u16 {
+        120
+    }
+
+    fn move_cursor_up(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_down(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_right(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_left(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        let mut state = self.state();
+        state.drawing.push_str(s);
+        state.drawing.push(' ');
+        Ok(())
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        if s != "\r" {
+            self.state().drawing.push_str(s);
+        }
+        Ok(())
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Ends a redraw, printing it if the last line is old enough.
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.state();
+        let drawn = std::mem::take(&mut state.drawing);
+        let line = console::strip_ansi_codes(&drawn).trim().to_string();
+        // Clearing the bar draws nothing; keep its last state for the final line.
+        if line.is_empty() {
+            return Ok(());
+        }
+        let due = state
+            .printed_at
+            .is_none_or(|printed_at| printed_at.elapsed() >= PLAIN_INTERVAL);
+        if due {
+            state.printed_at = Some(Instant::now());
+            state.unprinted = None;
+            writeln!(io::stderr(), "{}", line)?;
+        } else {
+            state.unprinted = Some(line);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PlainLines {
+    /// Prints the final state of the bar if it was drawn after the last printed line.
+    fn drop(&mut self) {
+        if let Some(line) = self.state().unprinted.take() {
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Rates given with three decimals or fewer that stand for an NTSC rate of `N * 1000/1001`.
+const NTSC_RATES: [(&str, u32); 6] = [
+    ("23.976", 24),
+    ("29.97", 30),
+    ("47.952", 48),
+    ("59.94", 60),
+    ("119.88", 120),
+    ("239.76", 240),
+];
+
+/// A frame rate as an exact fraction of frames per second, e.g. 30000/1001 for 29.97.
+///
+/// Frame counts and timestamps are computed from the fraction, so NTSC rates do not drift
+/// against the audio over long clips the way a rounded rate would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameRate {
+    numerator: u32,
+    denominator: u32,
+}
+
+impl FrameRate {
+    /// Creates the rate `numerator / denominator`, reduced to lowest terms.
+    ///
+    /// # Returns
+    /// - `Option<Self>`: The rate, or `None` if either part is zero.
+    pub fn new(numerator: u32, denominator: u32) -> Option<Self> {
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        Some(Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    /// Creates a whole number of frames per second.
+    ///
+    /// # Notes
+    /// - A rate of 0 is raised to 1, as a frame rate is never zero.
+    pub const fn fps(fps: u32) -> Self {
+        Self {
+            numerator: if fps == 0 { 1 } else { fps },
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(&self) -> u32 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> u32 {
+        self.denominator
+    }
+
+    /// Returns the rate in frames per second.
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Returns the rate as ffmpeg takes it for `-r`, `-framerate` and the `fps` filter,
+    /// e.g. `30` or `30000/1001`.
+    pub fn ffmpeg_arg(&self) -> String {
+        if self.denominator == 1 {
+            self.numerator.to_string()
+        } else {
+            format!("{}/{}", self.numerator, self.denominator)
+        }
+    }
+
+    /// Returns the number of whole frames in `duration_ms`.
+    pub fn frames_in(&self, duration_ms: u64) -> u64 {
+        (duration_ms as u128 * self.numerator as u128 / (self.denominator as u128 * 1000)) as u64
+    }
+
+    /// Returns the frames in `duration_ms`, rounded to the nearest frame.
+    pub fn frames_in_rounded(&self, duration_ms: u64) -> u64 {
+        let scale = self.denominator as u128 * 1000;
+        ((duration_ms as u128 * self.numerator as u128 + scale / 2) / scale) as u64
+    }
+
+    /// Returns the milliseconds at which frame `index`, counted from 0, starts.
+    pub fn timestamp_ms(&self, index: u64) -> u64 {
+        (index as u128 * 1000 * self.denominator as u128 / self.numerator as u128) as u64
+    }
+
+    /// Returns the duration of `frames` frames in milliseconds, rounded to the nearest one.
+    pub fn duration_ms(&self, frames: u64) -> u64 {
+        let numerator = self.numerator as u128;
+        ((frames as u128 * 1000 * self.denominator as u128 + numerator / 2) / numerator) as u64
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        Self::fps(60)
+    }
+}
+
+impl From<u32> for FrameRate {
+    fn from(fps: u32) -> Self {
+        Self::fps(fps)
+    }
+}
+
+impl FromStr for FrameRate {
+    type Err = String;
+
+    /// Parses `30`, `29.97` or `30000/1001`.
+    ///
+    /// # Notes
+    /// - The usual NTSC decimals, such as `23.976`, `29.97` and `59.94`, are read as
+    ///   the exact `N * 1000/1001` rates they stand for.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || {
+            format!(
+                "Invalid frame rate '{}', expected e.g. 30, 29.97 or 30000/1001",
+                s
+            )
+        };
+        if let Some((numerator, denominator)) = s.split_once('/') {
+            let numerator = numerator.trim().parse().map_err(|_| invalid())?;
+            let denominator = denominator.trim().parse().map_err(|_| invalid())?;
+            return Self::new(numerator, denominator).ok_or_else(invalid);
+        }
+        if let Some((_, nominal)) = NTSC_RATES.iter().find(|(decimal, _)| *decimal == s) {
+            return Ok(Self {
+                numerator: nominal * 1000,
+                denominator: 1001,
+            });
+        }
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        if whole.is_empty() && fraction.is_empty() || fraction.len() > 6 {
+            return Err(invalid());
+        }
+        let digits = format!("{}{}", whole, fraction);
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let numerator = digits.parse().map_err(|_| invalid())?;
+        Self::new(numerator, 10u32.pow(fraction.len() as u32)).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for FrameRate {
+    /// Formats the rate as `30`, `29.97` or `30000/1001`, whichever parses back exactly.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator == 1 {
+            return write!(f, "{}", self.numerator);
+        }
+        if self.denominator == 1001 {
+            if let Some((decimal, _)) = NTSC_RATES
+                .iter()
+                .find(|(_, nominal)| nominal * 1000 == self.numerator)
+            {
+                return write!(f, "{}", decimal);
+            }
+        }
+        let decimal = format!("{}", self.as_f64());
+        match decimal.parse::<FrameRate>() {
+            Ok(rate) if rate == *self => write!(f, "{}", decimal),
+            _ => write!(f, "{}/{}", self.numerator, self.denominator),
+        }
+    }
+}
+
+impl Serialize for FrameRate {
+    /// Writes whole rates as integers, so configuration files stay readable by older
+    /// versions, and others as strings such as `"29.97"`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.denominator == 1 {
+            serializer.serialize_u32(self.numerator)
+        } else {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FrameRate {
+    /// Reads a rate written by `serialize`, or any form `from_str` parses.
+    ///
+    /// # Notes
+    /// - A rate of 0 is an error, so every rate read has a nonzero numerator.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Whole(u32),
+            Decimal(f64),
+            Text(String),
+        }
+        let text = match Stored::deserialize(deserializer)? {
+            Stored::Whole(0) => {
+                return Err(serde::de::Error::custom(
+                    "Invalid frame rate '0', expected above 0, e.g. 30 or 29.97",
+                ))
+            }
+            Stored::Whole(fps) => return Ok(Self::fps(fps)),
+            Stored::Decimal(fps) => fps.to_string(),
+            Stored::Text(text) => text,
+        };
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
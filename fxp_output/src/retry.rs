@@ -0,0 +1,109 @@
+use anyhow::Result;
+use log::warn;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// How often a per-frame call of an external tool is retried before the run fails, so a
+/// spurious failure, such as an I/O error on a network filesystem, does not abort a
+/// long batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts of each call in total; 1 does not retry.
+    pub attempts: u32,
+    /// Milliseconds to wait before the first retry, doubled before each further one.
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    /// Does not retry.
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff_ms: 500,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy of `retries` retries after the first attempt.
+    pub fn new(retries: u32, backoff_ms: u64) -> Self {
+        Self {
+            attempts: retries.saturating_add(1),
+            backoff_ms,
+        }
+    }
+
+    /// Returns whether a failed call is attempted again.
+    pub fn retries(&self) -> bool {
+        self.attempts > 1
+    }
+
+    /// Runs `operation` until it succeeds, it fails permanently or the attempts run out.
+    ///
+    /// # Parameters
+    /// - `what`: What the operation does, for the warning logged before each retry.
+    /// - `operation`: The call to attempt.
+    /// - `is_permanent`: Returns whether an error would recur on every attempt, such as
+    ///   a tool that is not installed or an interruption, which is returned at once.
+    ///
+    /// # Returns
+    /// - `Result<T>`: The result of the first successful attempt, or the error of the
+    ///   last one.
+    ///
+    /// # Notes
+    /// - The wait before the Nth retry is `backoff_ms * 2^(N-1)`.
+    pub fn run<T>(
+        &self,
+        what: &str,
+        mut operation: impl FnMut() -> Result<T>,
+        is_permanent: impl Fn(&anyhow::Error) -> bool,
+    ) -> Result<T> {
+        let first = operation();
+        self.resume(what, first, operation, is_permanent)
+    }
+
+    /// Like `run`, but with the first attempt already made, such as one image of a batch
+    /// processed together.
+    pub fn resume<T>(
+        &self,
+        what: &str,
+        first: Result<T>,
+        mut operation: impl FnMut() -> Result<T>,
+        is_permanent: impl Fn(&anyhow::Error) -> bool,
+    ) -> Result<T> {
+        let mut result = first;
+        let mut attempt = 1;
+        loop {
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt >= self.attempts || is_permanent(&e) => return Err(e),
+                Err(e) => {
+                    let wait = self.backoff_ms.saturating_mul(1 << (attempt - 1).min(16));
+                    warn!(
+                        "{} failed on attempt {} of {}, retrying in {} ms: {:#}",
+                        what, attempt, self.attempts, wait, e
+                    );
+                    thread::sleep(Duration::from_millis(wait));
+                    attempt += 1;
+                    result = operation();
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.retries() {
+            write!(
+                f,
+                "{} retries, {} ms backoff doubling",
+                self.attempts - 1,
+                self.backoff_ms
+            )
+        } else {
+            write!(f, "no retries")
+        }
+    }
+}
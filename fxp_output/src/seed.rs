@@ -0,0 +1,94 @@
+use log::debug;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+static SEED: OnceLock<Seed> = OnceLock::new();
+
+/// The seed of everything random in a run, so a run with the same seed gives the same
+/// output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(u64);
+
+impl Seed {
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// Returns a seed drawn from the system's randomness.
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Returns the seed of one item of the run, such as a frame.
+    ///
+    /// # Parameters
+    /// - `item`: The item, e.g. the frame number.
+    ///
+    /// # Notes
+    /// - Each item gets its own numbers whatever order, batches or threads the items are
+    ///   processed in; the seeds are mixed with SplitMix64 so neighbouring items do not
+    ///   get related numbers.
+    pub fn derive(self, item: u64) -> Seed {
+        let mut z = self
+            .0
+            .wrapping_add(item.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Seed(z ^ (z >> 31))
+    }
+}
+
+impl FromStr for Seed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .parse()
+            .map(Seed)
+            .map_err(|_| format!("Invalid seed '{}', expected a whole number", s))
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Sets the seed of the run's randomness.
+///
+/// # Parameters
+/// - `seed`: The seed, recorded in the manifest of every output of the run.
+///
+/// # Notes
+/// - Only the first call has an effect, like `set_progress_mode`.
+pub fn set_seed(seed: Seed) {
+    debug!("Random seed: {}", seed);
+    let _ = SEED.set(seed);
+}
+
+/// Sets a random seed for the run's randomness, when none was chosen with `set_seed`.
+///
+/// # Returns
+/// - `Seed`: The seed of the run, recorded in its manifests so it can be reproduced.
+///
+/// # Notes
+/// - Only the first call of this or `set_seed` has an effect.
+pub fn generate_seed() -> Seed {
+    *SEED.get_or_init(|| {
+        let seed = Seed::random();
+        debug!("Generated random seed: {}", seed);
+        seed
+    })
+}
+
+/// Returns the seed of the run, see `set_seed` and `generate_seed`; `None` leaves the
+/// randomness of the tools unseeded.
+pub fn seed() -> Option<Seed> {
+    SEED.get().copied()
+}
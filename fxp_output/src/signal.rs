@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::lock::release_output_locks;
+
+/// Exit code of a process stopped a second time while finishing gracefully.
+const FORCED_EXIT_CODE: i32 = 130;
+
+static GRACEFUL: OnceLock<bool> = OnceLock::new();
+static HANDLER: Mutex<bool> = Mutex::new(false);
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+/// The flags handed out by `running_flag`, all cleared by the first signal.
+static FLAGS: Mutex<Vec<Weak<AtomicBool>>> = Mutex::new(Vec::new());
+
+/// Sets whether a stopped run finishes the frame or stage in flight before exiting.
+///
+/// # Parameters
+/// - `graceful`: Let the tools already running finish, and keep the partial output.
+///
+/// # Notes
+/// - Only the first call has an effect, like `set_progress_mode`.
+pub fn set_graceful(graceful: bool) {
+    debug!("Graceful stop: {}", graceful);
+    let _ = GRACEFUL.set(graceful);
+}
+
+/// Returns whether a stopped run finishes gracefully, see `set_graceful`.
+pub fn graceful() -> bool {
+    GRACEFUL.get().copied().unwrap_or(false)
+}
+
+/// Returns a flag that is cleared when the process is asked to stop.
+///
+/// # Returns
+/// - `Result<Arc<AtomicBool>>`: The flag, `true` until SIGINT (Ctrl+C), SIGTERM or
+///   SIGHUP is received, or an error if the handler cannot be installed.
+///
+/// # Notes
+/// - One handler serves the whole process, so several modes run one after the other
+///   each get their own flag; a flag handed out after a signal starts cleared.
+/// - The modes check the flag between frames. The tools they run are killed at once,
+///   unless the run is graceful, see `kill_requested`.
+/// - A second signal while finishing gracefully exits at once.
+pub fn running_flag() -> Result<Arc<AtomicBool>> {
+    install_handler()?;
+    let running = Arc::new(AtomicBool::new(!stop_requested()));
+    let mut flags = FLAGS.lock().unwrap_or_else(|e| e.into_inner());
+    flags.retain(|flag| flag.strong_count() > 0);
+    flags.push(Arc::downgrade(&running));
+    Ok(running)
+}
+
+/// Returns whether the process was asked to stop.
+pub fn stop_requested() -> bool {
+    SIGNALS.load(Ordering::SeqCst) > 0
+}
+
+/// Returns whether a tool in flight should be killed rather than waited for: the run is
+/// stopping, and not gracefully.
+pub fn kill_requested(running: &AtomicBool) -> bool {
+    !running.load(Ordering::SeqCst) && !graceful()
+}
+
+fn install_handler() -> Result<()> {
+    let mut installed = HANDLER.lock().unwrap_or_else(|e| e.into_inner());
+    if *installed {
+        return Ok(());
+    }
+    ctrlc::set_handler(|| {
+        let received = SIGNALS.fetch_add(1, Ordering::SeqCst) + 1;
+        if received > 1 && graceful() {
+            warn!("Stopped again, exiting without finishing");
+            release_output_locks();
+            std::process::exit(FORCED_EXIT_CODE);
+        }
+        if graceful() {
+            eprintln!("\nReceived a stop signal, finishing the current frame...");
+        } else {
+            eprintln!("\nReceived a stop signal, terminating...");
+        }
+        let flags = FLAGS.lock().unwrap_or_else(|e| e.into_inner());
+        for flag in flags.iter().filter_map(Weak::upgrade) {
+            flag.store(false, Ordering::SeqCst);
+        }
+    })
+    .context("Error setting the stop signal handler")?;
+    *installed = true;
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fxp_modes::Modes;
+
+use crate::manifest::{manifests_moved, Manifest};
+use crate::signal::{graceful, stop_requested};
+
+/// Name of the marker file written into an output directory once all of its images are complete.
+pub const COMPLETE_MARKER: &str = ".fxp_complete";
+
+/// Name of the marker file written into an output directory kept after a graceful stop,
+/// holding only the images done by then.
+pub const PARTIAL_MARKER: &str = ".fxp_partial";
+
+/// An output directory whose images are written to a hidden staging directory first.
+///
+/// The staging directory is a sibling of the output, `.<name>.staging`, and replaces the
+/// output only once every image has been written. An output directory holding the
+/// `COMPLETE_MARKER` file is therefore always complete.
+pub struct StagedDirectory {
+    output_dir: PathBuf,
+    /// `None` when writing in place.
+    staging_dir: Option<PathBuf>,
+    /// The manifest written with the partial output of a graceful stop, see `keep_partial`.
+    partial_manifest: Option<Manifest>,
+}
+
+impl StagedDirectory {
+    /// Starts writing images for `output_dir`.
+    ///
+    /// # Parameters
+    /// - `output_dir`: The final output directory, as claimed by `ModeOutput::create_output`.
+    /// - `in_place`: Write straight into `output_dir` instead of staging the images.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The staged directory; write the images to `path()`.
+    ///
+    /// # Notes
+    /// - Files already in `output_dir`, as with `--append`, are copied into the staging
+    ///   directory so they are kept and can be reused. They are copied rather than hard
+    ///   linked because the writers truncate existing files, which would alter the output.
+    /// - A staging directory left behind by an interrupted run is discarded first.
+    /// - An existing output holding subdirectories is written in place, since replacing
+    ///   it would drop them.
+    /// - When writing in place, the completion marker is removed until `finish` is called.
+    pub fn begin(output_dir: &Path, in_place: bool) -> Result<Self> {
+        let marker = output_dir.join(COMPLETE_MARKER);
+        let has_subdirectories = !in_place && contains_subdirectories(output_dir)?;
+        if has_subdirectories {
+            warn!(
+                "{} contains subdirectories, writing into it in place",
+                output_dir.display()
+            );
+        }
+        if in_place || has_subdirectories {
+            if marker.exists() {
+                fs::remove_file(&marker)
+                    .with_context(|| format!("Failed to remove {}", marker.display()))?;
+            }
+            return Ok(Self {
+                output_dir: output_dir.to_path_buf(),
+                staging_dir: None,
+                partial_manifest: None,
+            });
+        }
+
+        let staging_dir = sibling_path(output_dir, "staging");
+        if staging_dir.exists() {
+            debug!("Discarding stale staging directory: {:?}", staging_dir);
+            fs::remove_dir_all(&staging_dir).with_context(|| {
+                format!(
+                    "Failed to remove stale staging directory {}",
+                    staging_dir.display()
+                )
+            })?;
+        }
+        fs::create_dir_all(&staging_dir).with_context(|| {
+            format!(
+                "Failed to create staging directory {}",
+                staging_dir.display()
+            )
+        })?;
+        debug!("Staging output of {:?} in {:?}", output_dir, staging_dir);
+
+        let staged = Self {
+            output_dir: output_dir.to_path_buf(),
+            staging_dir: Some(staging_dir),
+            partial_manifest: None,
+        };
+        if output_dir.is_dir() {
+            staged.carry_over_existing_files()?;
+        }
+        Ok(staged)
+    }
+
+    /// Returns the directory the images must be written to.
+    pub fn path(&self) -> &Path {
+        self.staging_dir.as_deref().unwrap_or(&self.output_dir)
+    }
+
+    /// Keeps the images written so far if the run is stopped gracefully before `finish`.
+    ///
+    /// # Parameters
+    /// - `manifest`: The manifest of the run, written marked as partial with the images.
+    ///
+    /// # Notes
+    /// - Only applies with `--graceful`, see `fxp_output::set_graceful`; otherwise an
+    ///   unfinished staging directory is removed as before.
+    /// - The kept output holds `PARTIAL_MARKER` instead of `COMPLETE_MARKER`, and is
+    ///   moved into place like a finished one.
+    pub fn keep_partial(&mut self, manifest: &Manifest) {
+        self.partial_manifest = Some(manifest.clone());
+    }
+
+    /// Marks the output complete and moves the staged images into place.
+    ///
+    /// # Parameters
+    /// - `mode`: The mode that wrote the images, recorded in the marker.
+    /// - `images`: The number of images written, recorded in the marker.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The final output directory.
+    ///
+    /// # Notes
+    /// - See `move_into_place`.
+    pub fn finish(mut self, mode: Modes, images: usize) -> Result<PathBuf> {
+        self.partial_manifest = None;
+        let completed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let marker = self.path().join(COMPLETE_MARKER);
+        fs::write(
+            &marker,
+            format!(
+                "mode: {:?}\nimages: {}\ncompleted: {}\n",
+                mode, images, completed
+            ),
+        )
+        .with_context(|| format!("Failed to write completion marker {}", marker.display()))?;
+
+        self.move_into_place()
+    }
+
+    /// Writes the manifest marked as partial and the partial marker, and moves the
+    /// staged images into place.
+    fn finish_partial(&mut self, manifest: Manifest) -> Result<PathBuf> {
+        let mode = manifest.mode();
+        manifest.partial().write(self.path())?;
+        let stopped = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let marker = self.path().join(PARTIAL_MARKER);
+        fs::write(&marker, format!("mode: {:?}\nstopped: {}\n", mode, stopped))
+            .with_context(|| format!("Failed to write partial marker {}", marker.display()))?;
+        self.move_into_place()
+    }
+
+    /// Replaces the output directory with the staging directory, if staging.
+    ///
+    /// # Notes
+    /// - The previous output directory is renamed aside, replaced by the staging directory
+    ///   and only then deleted, so the output name never points at a half-replaced
+    ///   directory.
+    fn move_into_place(&mut self) -> Result<PathBuf> {
+        let Some(staging_dir) = self.staging_dir.take() else {
+            return Ok(self.output_dir.clone());
+        };
+
+        let replaced_dir = sibling_path(&self.output_dir, "replaced");
+        if replaced_dir.exists() {
+            fs::remove_dir_all(&replaced_dir)
+                .with_context(|| format!("Failed to remove {}", replaced_dir.display()))?;
+        }
+        let had_output = self.output_dir.exists();
+        if had_output {
+            fs::rename(&self.output_dir, &replaced_dir)
+                .with_context(|| format!("Failed to move {} aside", self.output_dir.display()))?;
+        }
+        if let Err(e) = fs::rename(&staging_dir, &self.output_dir) {
+            if had_output {
+                let _ = fs::rename(&replaced_dir, &self.output_dir);
+            }
+            // Keep the staging directory so the finished images are not lost.
+            return Err(e).with_context(|| {
+                format!(
+                    "Failed to move {} into place at {}",
+                    staging_dir.display(),
+                    self.output_dir.display()
+                )
+            });
+        }
+        manifests_moved(&staging_dir, &self.output_dir);
+        if had_output {
+            fs::remove_dir_all(&replaced_dir)
+                .with_context(|| format!("Failed to remove {}", replaced_dir.display()))?;
+        }
+        debug!("Moved staged output into place: {:?}", self.output_dir);
+
+        Ok(self.output_dir.clone())
+    }
+
+    /// Copies the files of the output directory into the staging directory.
+    fn carry_over_existing_files(&self) -> Result<()> {
+        let staging_dir = self.path();
+        for entry in fs::read_dir(&self.output_dir)
+            .with_context(|| format!("Failed to read {}", self.output_dir.display()))?
+        {
+            let path = entry?.path();
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            if !path.is_file() || name == COMPLETE_MARKER {
+                continue;
+            }
+            fs::copy(&path, staging_dir.join(name))
+                .with_context(|| format!("Failed to copy {} into staging", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagedDirectory {
+    /// Removes the staging directory of a run that did not finish, and the output
+    /// directory too if it was only claimed for this run and is still empty.
+    ///
+    /// After a graceful stop, the images written so far are kept instead, see
+    /// `keep_partial`.
+    fn drop(&mut self) {
+        if graceful() && stop_requested() {
+            if let Some(manifest) = self.partial_manifest.take() {
+                match self.finish_partial(manifest) {
+                    Ok(output_dir) => {
+                        warn!(
+                            "Stopped before finishing, kept the partial output in {}",
+                            output_dir.display()
+                        );
+                        return;
+                    }
+                    Err(e) => warn!("Failed to keep the partial output: {:#}", e),
+                }
+            }
+        }
+        if let Some(staging_dir) = &self.staging_dir {
+            debug!("Removing unfinished staging directory: {:?}", staging_dir);
+            if let Err(e) = fs::remove_dir_all(staging_dir) {
+                debug!(
+                    "Failed to remove staging directory {:?}: {}",
+                    staging_dir, e
+                );
+            }
+            // Only succeeds on an empty directory.
+            let _ = fs::remove_dir(&self.output_dir);
+        }
+    }
+}
+
+/// Returns whether `dir` exists and holds at least one directory.
+fn contains_subdirectories(dir: &Path) -> Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        if entry?.path().is_dir() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the hidden sibling `.<name>.<suffix>` of `output_dir`.
+fn sibling_path(output_dir: &Path, suffix: &str) -> PathBuf {
+    let name = output_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    output_dir.with_file_name(format!(".{}.{}", name, suffix))
+}
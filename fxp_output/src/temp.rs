@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory intermediate files are kept in by default.
+const KEEP_TEMP_DIR_NAME: &str = "fxp_videoclipper";
+
+/// Returns where intermediate files are kept when no directory is given:
+/// `fxp_videoclipper` in the temporary directory of the system, e.g. `/tmp` or `%TEMP%`.
+pub fn default_keep_temp_dir() -> PathBuf {
+    std::env::temp_dir().join(KEEP_TEMP_DIR_NAME)
+}
+
+/// Copies the intermediate files of a run out of its temporary directory before it is
+/// removed.
+///
+/// # Parameters
+/// - `tmp_dir`: The temporary directory holding the intermediate files.
+/// - `keep_dir`: The directory to copy them to, created if it does not exist.
+///
+/// # Returns
+/// - `Result<Vec<PathBuf>>`: The kept files, or an error if the directory cannot be
+///   created or a file cannot be copied.
+///
+/// # Notes
+/// - Files of the same name from an earlier run are overwritten.
+/// - Only the files directly inside `tmp_dir` are copied, not its subdirectories.
+/// - The kept files are listed on stderr, so a run never leaves them behind silently.
+pub fn keep_temp_files(tmp_dir: &Path, keep_dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(keep_dir).with_context(|| {
+        format!(
+            "Failed to create directory for intermediate files: {}",
+            keep_dir.display()
+        )
+    })?;
+
+    let mut kept = Vec::new();
+    for entry in fs::read_dir(tmp_dir).context("Failed to read temporary directory")? {
+        let source = entry?.path();
+        if !source.is_file() {
+            continue;
+        }
+        if let Some(file_name) = source.file_name() {
+            let destination = keep_dir.join(file_name);
+            fs::copy(&source, &destination)
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, destination))?;
+            kept.push(destination);
+        }
+    }
+    kept.sort();
+
+    debug!("Kept intermediate files in {}", keep_dir.display());
+    eprintln!(
+        "Kept {} intermediate files in {}",
+        kept.len(),
+        keep_dir.display()
+    );
+    for path in &kept {
+        eprintln!("  {}", path.display());
+    }
+    Ok(kept)
+}
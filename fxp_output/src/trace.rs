@@ -0,0 +1,318 @@
+use anyhow::{Context, Result};
+use log::{debug, trace};
+use serde::Serialize;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Context as LayerContext, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+static TRACE_FILE: OnceLock<(PathBuf, Arc<File>)> = OnceLock::new();
+static STAGE_TIMINGS: Mutex<Vec<StageTiming>> = Mutex::new(Vec::new());
+
+/// Prefix of the targets of the tool's own crates, whose spans are the stages traced;
+/// the spans of dependencies are left out.
+const TARGET_PREFIX: &str = "fxp_";
+
+/// Where finished spans are reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceOutput {
+    /// As debug records of the log.
+    Log,
+    /// As JSON lines appended to a file; `None` leaves the file to the caller's default.
+    Json(Option<PathBuf>),
+}
+
+impl FromStr for TraceOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log" => Ok(TraceOutput::Log),
+            "json" => Ok(TraceOutput::Json(None)),
+            _ => match s.strip_prefix("json:") {
+                Some(path) if !path.is_empty() => Ok(TraceOutput::Json(Some(PathBuf::from(path)))),
+                _ => Err(format!(
+                    "Unknown trace output '{}', expected log, json or json:PATH",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TraceOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceOutput::Log => write!(f, "log"),
+            TraceOutput::Json(None) => write!(f, "json"),
+            TraceOutput::Json(Some(path)) => write!(f, "json:{}", path.display()),
+        }
+    }
+}
+
+/// Installs the tracing subscriber of the run, timing the spans of the modes' stages.
+///
+/// # Parameters
+/// - `trace_file`: Where to append every finished span as a JSON line, for
+///   `--trace-output json`; the file and its directory are created if missing. `None`
+///   only logs the spans.
+///
+/// # Returns
+/// - `Result<()>`: An error if the trace file cannot be opened.
+///
+/// # Notes
+/// - Only the first call has an effect, like `set_progress_mode`.
+/// - Only the spans of the tool's own crates are traced, see `TARGET_PREFIX`.
+/// - Every finished span is logged at debug level with its fields and duration and added
+///   to the `timing_summary`.
+/// - The JSON lines are written by `tracing-subscriber`'s JSON formatter, one per closed
+///   span, holding its `span` with its name and fields, the `spans` it ran within, the
+///   `threadId`, and its `time.busy` and `time.idle`.
+pub fn init_tracing(trace_file: Option<&Path>) -> Result<()> {
+    let json_layer = match trace_file {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create trace directory {}", parent.display())
+                })?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open trace file {}", path.display()))?;
+            let file = Arc::new(file);
+            if TRACE_FILE.set((path.to_path_buf(), file.clone())).is_err() {
+                return Ok(());
+            }
+            debug!("Writing trace spans to {:?}", path);
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_span_list(true)
+                    .with_thread_ids(true)
+                    .with_writer(file),
+            )
+        }
+        None => None,
+    };
+    let subscriber = tracing_subscriber::registry()
+        .with(filter_fn(|metadata| {
+            metadata.target().starts_with(TARGET_PREFIX)
+        }))
+        .with(StageLayer)
+        .with(json_layer);
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        debug!("Tracing is already set up");
+    }
+    Ok(())
+}
+
+/// Returns the file spans are written to, if `init_tracing` was given one.
+pub fn trace_file() -> Option<&'static Path> {
+    TRACE_FILE.get().map(|(path, _)| path.as_path())
+}
+
+/// The wall time spent in one kind of span over the whole run.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    /// How many spans of this stage finished.
+    pub count: u64,
+    pub total_ms: f64,
+}
+
+/// The wall time per stage of the run, in the order the stages were first entered.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimingSummary {
+    pub stages: Vec<StageTiming>,
+}
+
+/// Returns the time spent per stage so far, or `None` if no span has finished.
+///
+/// # Notes
+/// - Nested stages are included in the time of the stages around them, so the first
+///   stage, the mode itself, accounts for the whole run.
+pub fn timing_summary() -> Option<TimingSummary> {
+    let stages: Vec<StageTiming> = lock_timings()
+        .iter()
+        .filter(|timing| timing.count > 0)
+        .cloned()
+        .collect();
+    if stages.is_empty() {
+        None
+    } else {
+        Some(TimingSummary { stages })
+    }
+}
+
+/// Appends the timing summary to the trace file as a `{"summary": ...}` record.
+///
+/// # Parameters
+/// - `summary`: The summary returned by `timing_summary`.
+///
+/// # Notes
+/// - Does nothing unless `init_tracing` was given a trace file.
+pub fn write_timing_summary(summary: &TimingSummary) {
+    let Some((_, file)) = TRACE_FILE.get() else {
+        return;
+    };
+    let record = format!("{}\n", serde_json::json!({ "summary": summary.stages }));
+    if let Err(e) = (&**file).write_all(record.as_bytes()) {
+        debug!("Failed to write timing summary: {}", e);
+    }
+}
+
+impl fmt::Display for TimingSummary {
+    /// Formats the summary as a table of stages with their count, total and mean time.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .stages
+            .iter()
+            .map(|timing| timing.stage.len())
+            .max()
+            .unwrap_or(0)
+            .max("Stage".len());
+        writeln!(
+            f,
+            "{:<width$}  {:>7}  {:>10}  {:>10}",
+            "Stage", "Count", "Total", "Mean"
+        )?;
+        for timing in &self.stages {
+            writeln!(
+                f,
+                "{:<width$}  {:>7}  {:>10}  {:>10}",
+                timing.stage,
+                timing.count,
+                format_ms(timing.total_ms),
+                format_ms(timing.total_ms / timing.count as f64),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats milliseconds as `850 ms`, `12.3 s` or `4m 05s`.
+fn format_ms(ms: f64) -> String {
+    if ms < 1000.0 {
+        format!("{:.0} ms", ms)
+    } else if ms < 60_000.0 {
+        format!("{:.1} s", ms / 1000.0)
+    } else {
+        let seconds = (ms / 1000.0).round() as u64;
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+fn lock_timings() -> std::sync::MutexGuard<'static, Vec<StageTiming>> {
+    STAGE_TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// When a span started and its fields, kept in the span's extensions by `StageLayer`.
+struct SpanTiming {
+    started: Instant,
+    fields: String,
+}
+
+/// Formats the fields of a span as `key=value, ...`.
+#[derive(Default)]
+struct FieldList(String);
+
+impl FieldList {
+    fn push(&mut self, field: &Field, value: &dyn fmt::Display) {
+        if !self.0.is_empty() {
+            self.0.push_str(", ");
+        }
+        self.0.push_str(&format!("{}={}", field.name(), value));
+    }
+}
+
+impl Visit for FieldList {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, &value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, &format_args!("{:?}", value));
+    }
+}
+
+/// A timed stage of a mode, such as cutting the video or merging one frame, is a
+/// `tracing` span; this layer logs each finished span with its duration and adds the
+/// duration to the timing summary of its stage, the span's name.
+struct StageLayer;
+
+impl<S> Layer<S> for StageLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let name = attrs.metadata().name();
+        {
+            let mut timings = lock_timings();
+            if !timings.iter().any(|timing| timing.stage == name) {
+                timings.push(StageTiming {
+                    stage: name,
+                    count: 0,
+                    total_ms: 0.0,
+                });
+            }
+        }
+        let mut fields = FieldList::default();
+        attrs.record(&mut fields);
+        trace!("{}{{{}}} started", name, fields.0);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                started: Instant::now(),
+                fields: fields.0,
+            });
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            let mut fields = FieldList(std::mem::take(&mut timing.fields));
+            values.record(&mut fields);
+            timing.fields = fields.0;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+        let duration_ms = timing.started.elapsed().as_secs_f64() * 1000.0;
+        debug!(
+            "{}{{{}}} finished in {:.1} ms",
+            span.name(),
+            timing.fields,
+            duration_ms
+        );
+        if let Some(stage) = lock_timings()
+            .iter_mut()
+            .find(|stage| stage.stage == span.name())
+        {
+            stage.count += 1;
+            stage.total_ms += duration_ms;
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::debug;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use fxp_output::ToolError;
+
+use crate::error::ProcessorError;
+use crate::frame_processor::{FrameContext, FrameProcessor};
+
+/// Placeholders of a command template, in the order of the values `process` fills in.
+const PLACEHOLDERS: [&str; 4] = ["{input}", "{output}", "{index}", "{total}"];
+
+/// Runs an external command once per frame.
+///
+/// The command is a template such as `magick {input} -negate {output}`, whose
+/// placeholders are replaced for every frame: `{input}` and `{output}` by the frame's
+/// paths, `{index}` by its frame number and `{total}` by the number of frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandProcessor {
+    name: String,
+    template: String,
+    words: Vec<String>,
+}
+
+impl CommandProcessor {
+    /// Creates a processor running `template`.
+    ///
+    /// # Parameters
+    /// - `name`: Name recorded in the manifest, e.g. the configured name of the command.
+    /// - `template`: The command line, split into words like a shell does, without
+    ///   running one: words are separated by whitespace, and single or double quotes
+    ///   keep a word with spaces together.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The processor, or an error if the template is empty, has an
+    ///   unclosed quote, or writes no `{output}`.
+    pub fn new(name: &str, template: &str) -> Result<Self> {
+        let words = split_words(template)?;
+        if words.is_empty() {
+            bail!("The command of processor '{}' is empty", name);
+        }
+        if !words.iter().any(|word| word.contains("{output}")) {
+            bail!(
+                "The command of processor '{}' has no {{output}} to write the frame to: {}",
+                name,
+                template
+            );
+        }
+        Ok(Self {
+            name: name.to_string(),
+            template: template.to_string(),
+            words,
+        })
+    }
+
+    /// Returns the command line template.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+}
+
+impl FrameProcessor for CommandProcessor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the command, failing with its error output if it fails or writes no frame.
+    fn process(
+        &self,
+        input: &Path,
+        output: &Path,
+        index: u32,
+        context: &FrameContext,
+    ) -> Result<()> {
+        let values = [
+            input.display().to_string(),
+            output.display().to_string(),
+            index.to_string(),
+            context.total.to_string(),
+        ];
+        let words: Vec<String> = self
+            .words
+            .iter()
+            .map(|word| {
+                PLACEHOLDERS
+                    .iter()
+                    .zip(&values)
+                    .fold(word.clone(), |word, (placeholder, value)| {
+                        word.replace(placeholder, value)
+                    })
+            })
+            .collect();
+        debug!("Running {:?}", words);
+
+        let result = Command::new(&words[0])
+            .args(&words[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .output()
+            .map_err(|e| ProcessorError::spawn(&words[0], e))
+            .with_context(|| format!("Failed to run '{}'", words[0]))?;
+        if !result.status.success() {
+            return Err(ProcessorError::ToolFailed {
+                tool: words[0].clone(),
+                reason: format!(
+                    "{}: {}",
+                    result.status,
+                    String::from_utf8_lossy(&result.stderr).trim()
+                ),
+            })
+            .with_context(|| format!("'{}' failed on frame {}", self.template, index));
+        }
+        if !output.is_file() {
+            bail!(
+                "'{}' wrote nothing to {} for frame {}",
+                self.template,
+                output.display(),
+                index
+            );
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        self.template.clone()
+    }
+}
+
+/// Splits a command line into words, honoring single and double quotes.
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if let Some(open) = quote {
+        return Err(anyhow!("Unclosed {} in command: {}", open, line));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
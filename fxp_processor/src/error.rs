@@ -0,0 +1,44 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Processor that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum ProcessorError {
+    /// A tool the Processor runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Processor runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The input does not exist or is not what the Processor takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Processing interrupted by user")]
+    Interrupted,
+}
+
+impl ProcessorError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ProcessorError::ToolMissing(_) => FailureKind::ToolMissing,
+            ProcessorError::ToolFailed { .. } => FailureKind::ToolFailed,
+            ProcessorError::InvalidInput(_) => FailureKind::InvalidInput,
+            ProcessorError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for ProcessorError {
+    fn missing(tool: &str) -> Self {
+        ProcessorError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        ProcessorError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
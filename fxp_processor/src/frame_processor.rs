@@ -0,0 +1,45 @@
+use anyhow::Result;
+use std::path::Path;
+
+/// What a processor knows about the run a frame belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameContext<'a> {
+    /// Number of frames the run processes.
+    pub total: usize,
+    /// Directory the frames are read from.
+    pub input_directory: &'a Path,
+    /// Directory the processed frames are written to, which may be a staging directory.
+    pub output_directory: &'a Path,
+}
+
+/// A stage turning one frame into another, run by the Processor on every frame.
+///
+/// Implement it to add a filter of your own next to the Gmicer, Clutter and Merger, and
+/// add it to a `Registry` to make it selectable by name.
+pub trait FrameProcessor: Send + Sync {
+    /// Name of the processor, as recorded in the manifest.
+    fn name(&self) -> &str;
+
+    /// Processes one frame.
+    ///
+    /// # Parameters
+    /// - `input`: The frame to read.
+    /// - `output`: Where to write the processed frame, with the input's file name.
+    /// - `index`: Frame number of the input, from its file name.
+    /// - `context`: The run the frame belongs to.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the frame could not be processed, which stops the run.
+    fn process(
+        &self,
+        input: &Path,
+        output: &Path,
+        index: u32,
+        context: &FrameContext,
+    ) -> Result<()>;
+
+    /// Describes the processor for the manifest and the plan, e.g. its command line.
+    fn describe(&self) -> String {
+        self.name().to_string()
+    }
+}
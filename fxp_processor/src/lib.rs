@@ -0,0 +1,11 @@
+mod command;
+mod error;
+mod frame_processor;
+mod processor;
+mod registry;
+
+pub use command::CommandProcessor;
+pub use error::ProcessorError;
+pub use frame_processor::{FrameContext, FrameProcessor};
+pub use processor::Processor;
+pub use registry::Registry;
@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use indicatif::ProgressStyle;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+use std::thread;
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::progress_bar;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::FileOperations;
+use fxp_filenames::FrameSelection;
+
+use crate::error::ProcessorError;
+use crate::frame_processor::{FrameContext, FrameProcessor};
+
+/// Struct running a `FrameProcessor` on every frame of a directory.
+pub struct Processor {
+    input_directory: PathBuf,
+    input_files: BTreeMap<u32, PathBuf>,
+    output_directory: PathBuf,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// The frames to process; `new` selects all of them.
+    pub selection: FrameSelection,
+    /// Number of frames processed at the same time; `new` sets 1.
+    pub jobs: usize,
+}
+
+impl Processor {
+    /// Creates a new `Processor` instance for a directory of frames.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the processed frames; defaults to
+    ///   `<input>_processed`.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: New `Processor` instance on success, or an error if validation fails.
+    ///
+    /// # Notes
+    /// - Creates the output directory if it does not exist.
+    pub fn new(
+        input_directory: String,
+        output_directory: Option<String>,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        debug!("Initializing new Processor instance with:");
+        debug!("- Input directory: {}", input_directory);
+        debug!("- Output directory: {:?}", output_directory);
+
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+        debug!("Found {} input files for processing", input_files.len());
+
+        let mode: Modes = Modes::Processor;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Processor(processor_output) => processor_output
+                .create_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Processor mode"),
+        };
+        debug!("Output directory created at: {:?}", output_directory_path);
+
+        Ok(Self {
+            input_directory: input_directory_path,
+            input_files,
+            output_directory: output_directory_path,
+            in_place: false,
+            selection: FrameSelection::default(),
+            jobs: 1,
+        })
+    }
+
+    /// Resolves what `new` and `process` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input_directory`: Path to the directory containing the frames.
+    /// - `output_directory`: Optional path for the processed frames.
+    /// - `processor`: The processor that would run on every frame.
+    /// - `selection`: The frames to process.
+    /// - `jobs`: Number of frames processed at the same time.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if validation fails.
+    pub fn plan(
+        input_directory: String,
+        output_directory: Option<String>,
+        processor: &dyn FrameProcessor,
+        selection: &FrameSelection,
+        jobs: usize,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_directory_path = canonical_input_directory(&input_directory)?;
+        let input_files = load_frames(&input_directory_path)?;
+
+        let mode: Modes = Modes::Processor;
+        let output: Output = mode.into();
+        let output_directory_path = match output {
+            Output::Processor(processor_output) => processor_output
+                .plan_output((input_directory_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Processor mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Processor)
+            .entry("input directory", input_directory_path.display())
+            .entry("frames", input_files.len());
+        if !selection.is_all() {
+            plan = plan
+                .entry("selection", selection)
+                .entry("selected frames", selection.select(&input_files).len());
+        }
+        Ok(plan
+            .entry("processor", processor.name())
+            .entry("runs", processor.describe())
+            .entry("jobs", jobs.max(1))
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory_path.display()))
+    }
+
+    /// Runs `processor` on every frame, writing each under its own name into the output.
+    ///
+    /// # Parameters
+    /// - `processor`: The stage to run, e.g. a `CommandProcessor` or one from a `Registry`.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of processed frames, or an error if the processor
+    ///   fails on a frame or processing was interrupted.
+    ///
+    /// # Notes
+    /// - Only the frames of `selection` are processed, `jobs` of them at a time; each
+    ///   worker takes the next frame in frame number order as it finishes one.
+    /// - The first failure stops all workers once their current frame is done.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are processed, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Writes a `manifest.json` recording the processor and the input hashes.
+    pub fn process(&self, processor: &dyn FrameProcessor) -> Result<usize> {
+        let _span = info_span!(
+            "process",
+            input = %self.input_directory.display(),
+            output = %self.output_directory.display(),
+            processor = %processor.name()
+        )
+        .entered();
+
+        let selected = self.selection.select(&self.input_files);
+        let mut manifest = Manifest::new(Modes::Processor)
+            .parameter("input", self.input_directory.display())
+            .parameter("processor", processor.name())
+            .parameter("command", processor.describe());
+        if let Some(range) = self.selection.range {
+            manifest = manifest.parameter("frames", range);
+        }
+        if self.selection.every > 1 {
+            manifest = manifest.parameter("every", self.selection.every);
+        }
+        let manifest = manifest.inputs(selected.values());
+
+        let running = running_flag()?;
+
+        let pb = progress_bar(selected.len() as u64);
+        pb.set_style(ProgressStyle::default_bar().template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+        )?);
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let context = FrameContext {
+            total: selected.len(),
+            input_directory: &self.input_directory,
+            output_directory: staged.path(),
+        };
+
+        let frames: Vec<(u32, &PathBuf)> = selected
+            .iter()
+            .map(|(&number, frame)| (number, frame))
+            .collect();
+        let next = AtomicUsize::new(0);
+        let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+        let workers = self.jobs.clamp(1, frames.len().max(1));
+        debug!(
+            "Processing {} frames with {} workers",
+            frames.len(),
+            workers
+        );
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let stopped = failure.lock().expect("a worker panicked").is_some();
+                    if stopped || !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Some(&(number, frame)) = frames.get(next.fetch_add(1, Ordering::SeqCst))
+                    else {
+                        break;
+                    };
+                    if let Err(e) = process_frame(processor, frame, number, &context) {
+                        failure.lock().expect("a worker panicked").get_or_insert(e);
+                        break;
+                    }
+                    pb.inc(1);
+                });
+            }
+        });
+        if let Some(e) = failure.into_inner().expect("a worker panicked") {
+            pb.abandon();
+            return Err(e);
+        }
+        if !running.load(Ordering::SeqCst) {
+            pb.abandon();
+            return Err(ProcessorError::Interrupted.into());
+        }
+        pb.finish_with_message("Done");
+        debug!(
+            "Processed {} frames with {}",
+            frames.len(),
+            processor.name()
+        );
+
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Processor, frames.len())?;
+
+        Ok(frames.len())
+    }
+}
+
+/// Runs the processor on one frame, writing it under its own name into the output.
+fn process_frame(
+    processor: &dyn FrameProcessor,
+    frame: &Path,
+    number: u32,
+    context: &FrameContext,
+) -> Result<()> {
+    let file_name = frame
+        .file_name()
+        .with_context(|| format!("Frame {:?} has no filename", frame))?;
+    let target = context.output_directory.join(file_name);
+
+    let _frame_span = info_span!("process frame", frame = number).entered();
+    processor
+        .process(frame, &target, number, context)
+        .with_context(|| {
+            format!(
+                "Processor '{}' failed on {}",
+                processor.name(),
+                frame.display()
+            )
+        })
+}
+
+/// Checks that the input is a directory and canonicalizes it.
+fn canonical_input_directory(input_directory: &str) -> Result<PathBuf> {
+    let input_directory_path = PathBuf::from(input_directory);
+    if !input_directory_path.is_dir() {
+        return Err(ProcessorError::InvalidInput(format!(
+            "Input directory '{}' does not exist or is not a directory",
+            input_directory_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_directory_path).with_context(|| {
+        format!(
+            "Failed to resolve input directory '{}'",
+            input_directory_path.display()
+        )
+    })
+}
+
+/// Maps the frames of the input directory by their frame number.
+fn load_frames(input_directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let input_images: Vec<PathBuf> = fs::read_dir(input_directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    Ok(Modes::Processor.load_files(&input_images)?)
+}
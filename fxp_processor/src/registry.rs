@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::command::CommandProcessor;
+use crate::frame_processor::FrameProcessor;
+
+/// Frame processors selectable by name with `process --processor`.
+#[derive(Default, Clone)]
+pub struct Registry {
+    processors: BTreeMap<String, Arc<dyn FrameProcessor>>,
+}
+
+impl Registry {
+    /// Creates a registry holding a `CommandProcessor` per configured command.
+    ///
+    /// # Parameters
+    /// - `commands`: Command templates by name, e.g. `processors` of the configuration.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The registry, or an error naming a command that is not valid.
+    pub fn with_commands(commands: &BTreeMap<String, String>) -> Result<Self> {
+        let mut registry = Self::default();
+        for (name, template) in commands {
+            registry.register(Arc::new(CommandProcessor::new(name, template)?));
+        }
+        Ok(registry)
+    }
+
+    /// Adds a processor under its name, replacing one registered under the same name.
+    pub fn register(&mut self, processor: Arc<dyn FrameProcessor>) {
+        self.processors
+            .insert(processor.name().to_string(), processor);
+    }
+
+    /// Returns the processor registered as `name`.
+    ///
+    /// # Returns
+    /// - `Result<Arc<dyn FrameProcessor>>`: The processor, or an error listing the names
+    ///   registered.
+    pub fn get(&self, name: &str) -> Result<Arc<dyn FrameProcessor>> {
+        self.processors.get(name).cloned().ok_or_else(|| {
+            if self.processors.is_empty() {
+                anyhow!(
+                    "Unknown processor '{}'; none are registered, add commands to processors in the configuration",
+                    name
+                )
+            } else {
+                anyhow!(
+                    "Unknown processor '{}', expected one of: {}",
+                    name,
+                    self.names().collect::<Vec<_>>().join(", ")
+                )
+            }
+        })
+    }
+
+    /// Returns the names of the registered processors, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.processors.keys().map(String::as_str)
+    }
+}
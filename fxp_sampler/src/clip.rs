@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Context, Result};
+use indicatif::ProgressStyle;
+use log::debug;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use fxp_output::{progress_bar, RetryPolicy};
+
+use crate::error::SamplerError;
+use crate::ffmpeg::extract_clip;
+
+/// Length of the clips the Sampler takes instead of stills, e.g. `2s` or `500ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipLength {
+    millis: u64,
+}
+
+impl ClipLength {
+    /// Returns the length in milliseconds.
+    pub fn millis(&self) -> u64 {
+        self.millis
+    }
+}
+
+impl FromStr for ClipLength {
+    type Err = String;
+
+    /// Parses seconds, as `2`, `2s` or `1.5s`, or milliseconds, as `500ms`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let millis = if let Some(millis) = s.strip_suffix("ms") {
+            millis.trim().parse::<f64>().ok()
+        } else {
+            s.strip_suffix('s')
+                .unwrap_or(s)
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|seconds| seconds * 1000.0)
+        };
+        match millis {
+            Some(millis) if millis.is_finite() && millis >= 1.0 => Ok(ClipLength {
+                millis: millis.round() as u64,
+            }),
+            _ => Err(format!(
+                "Invalid clip length '{}', expected a length such as 2s, 1.5s or 500ms",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClipLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.millis.is_multiple_of(1000) {
+            write!(f, "{}s", self.millis / 1000)
+        } else {
+            write!(f, "{}ms", self.millis)
+        }
+    }
+}
+
+/// Cuts a clip around each sampling point of a video.
+///
+/// # Parameters
+/// - `video`: Path to the video file to cut the clips from.
+/// - `duration_ms`: Total duration of the video in milliseconds.
+/// - `num_clips`: Number of clips to cut.
+/// - `length`: Length of each clip.
+/// - `output`: The directory the clips are written to as `sample_clip_N.mp4`, or the
+///   file of a single clip.
+/// - `retry`: How often a failed clip is cut again.
+/// - `running`: Flag indicating whether the extraction process should continue.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` once every clip is written, or an error if one fails
+///   or the extraction was interrupted.
+///
+/// # Notes
+/// - The sampling points are the ones of the stills: the middle of the video for one
+///   clip, the video divided into `num_clips + 1` equal parts otherwise.
+/// - Each clip is centered on its sampling point and moved to stay within the video; a
+///   clip longer than the video is cut to its length.
+pub fn extract_clips(
+    video: &Path,
+    duration_ms: u64,
+    num_clips: usize,
+    length: ClipLength,
+    output: &Path,
+    retry: &RetryPolicy,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    if duration_ms == 0 {
+        return Err(anyhow!("Failed to determine video length."));
+    }
+    let video_str = video
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid video path"))?;
+    let length_ms = length.millis().min(duration_ms);
+    let interval_ms = duration_ms / (num_clips as u64 + 1);
+
+    let pb = progress_bar(num_clips as u64);
+    let style = ProgressStyle::default_bar()
+        .template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
+        )
+        .context("Failed to set progress bar template")?;
+    pb.set_style(style);
+
+    for i in 0..num_clips {
+        if !running.load(Ordering::SeqCst) {
+            pb.finish_and_clear();
+            return Err(SamplerError::Interrupted.into());
+        }
+
+        let point_ms = interval_ms * (i as u64 + 1);
+        let start_ms = point_ms
+            .saturating_sub(length_ms / 2)
+            .min(duration_ms - length_ms);
+        let clip_path = if output.is_dir() {
+            output.join(format!("sample_clip_{}.mp4", i + 1))
+        } else {
+            output.to_path_buf()
+        };
+        debug!(
+            "Cutting clip {} of {} ms at {} ms into {:?}",
+            i + 1,
+            length_ms,
+            start_ms,
+            clip_path
+        );
+
+        let clip_str = clip_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid output file path"))?;
+        retry
+            .run(
+                &format!("Cutting clip {}", i + 1),
+                || {
+                    extract_clip(
+                        video_str,
+                        start_ms as f64 / 1000.0,
+                        length_ms as f64 / 1000.0,
+                        clip_str,
+                        running.clone(),
+                    )
+                },
+                SamplerError::is_permanent,
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to cut the clip at {:.3} seconds from the video.",
+                    start_ms as f64 / 1000.0
+                )
+            })?;
+        pb.inc(1);
+    }
+
+    pb.finish();
+    debug!("Successfully cut {} clips.", num_clips);
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+use fxp_output::ToolError;
+
+use crate::error::SamplerError;
+
+/// Name of the collage written next to the sampled frames.
+pub const COLLAGE_FILE_NAME: &str = "collage.png";
+
+/// A single image of the sampled frames laid out in a grid, as a quick visual summary of
+/// the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collage {
+    /// Frames per row; `None` makes the grid as square as possible.
+    pub columns: Option<u32>,
+    /// Draw the timestamp of each frame onto it.
+    pub labels: bool,
+}
+
+impl Default for Collage {
+    /// A square grid with timestamps.
+    fn default() -> Self {
+        Self {
+            columns: None,
+            labels: true,
+        }
+    }
+}
+
+impl fmt::Display for Collage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.columns {
+            Some(columns) => write!(f, "{} columns", columns)?,
+            None => write!(f, "square grid")?,
+        }
+        if self.labels {
+            write!(f, ", timestamps")?;
+        }
+        Ok(())
+    }
+}
+
+impl Collage {
+    /// Returns the columns and rows of the grid for `frames` frames.
+    pub fn grid(&self, frames: usize) -> (u32, u32) {
+        let frames = frames.max(1) as u32;
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (frames as f64).sqrt().ceil() as u32)
+            .clamp(1, frames);
+        (columns, frames.div_ceil(columns))
+    }
+
+    /// Writes the collage of the sampled frames.
+    ///
+    /// # Parameters
+    /// - `frames`: Each sampled frame with its timestamp in milliseconds, in order.
+    /// - `output_dir`: The directory the frames were sampled into.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The path of the collage, `collage.png` in `output_dir`, or an
+    ///   error if ffmpeg fails.
+    ///
+    /// # Notes
+    /// - The frames are tiled by ffmpeg's `tile` filter, with a 4 pixel black border;
+    ///   cells past the last frame stay black.
+    /// - Timestamps need ffmpeg's `drawtext` filter; without it the collage is written
+    ///   without them.
+    /// - Written under a temporary name and renamed into place once complete.
+    pub fn write(&self, frames: &[(PathBuf, u64)], output_dir: &Path) -> Result<PathBuf> {
+        if frames.is_empty() {
+            return Err(anyhow!("No sampled frames to make a collage of"));
+        }
+        let labels = self.labels && has_drawtext()?;
+        let (columns, rows) = self.grid(frames.len());
+        debug!(
+            "Tiling {} frames into a {}x{} collage",
+            frames.len(),
+            columns,
+            rows
+        );
+
+        let mut filter = String::new();
+        for (index, (_, timestamp_ms)) in frames.iter().enumerate() {
+            if labels {
+                filter.push_str(&format!(
+                    "[{index}:v]drawtext=text='{}':x=(w-tw)/2:y=h-th-10:fontsize=max(16\\,h/16):fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=6[f{index}];",
+                    timestamp_label(*timestamp_ms)
+                ));
+            } else {
+                filter.push_str(&format!("[{index}:v]null[f{index}];"));
+            }
+        }
+        for index in 0..frames.len() {
+            filter.push_str(&format!("[f{index}]"));
+        }
+        filter.push_str(&format!(
+            "concat=n={}:v=1:a=0,tile={}x{}:padding=4:margin=4[collage]",
+            frames.len(),
+            columns,
+            rows
+        ));
+
+        let collage = output_dir.join(COLLAGE_FILE_NAME);
+        let part = output_dir.join(format!("part.{}", COLLAGE_FILE_NAME));
+        let mut command = StdCommand::new("ffmpeg");
+        command.args(["-hide_banner", "-loglevel", "error", "-y"]);
+        for (frame, _) in frames {
+            command.arg("-i").arg(frame);
+        }
+        command
+            .args(["-filter_complex", &filter])
+            .args(["-map", "[collage]", "-frames:v", "1"])
+            .arg(&part);
+        debug!("Running ffmpeg for the collage: {:?}", command);
+
+        let output = command
+            .output()
+            .map_err(|e| SamplerError::spawn("ffmpeg", e))
+            .context("Failed to run ffmpeg for the collage")?;
+        if !output.status.success() {
+            let _ = fs::remove_file(&part);
+            return Err(SamplerError::ToolFailed {
+                tool: "ffmpeg".to_string(),
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+            .context("Failed to write the collage");
+        }
+        fs::rename(&part, &collage)
+            .with_context(|| format!("Failed to move the collage into place at {:?}", collage))?;
+        Ok(collage)
+    }
+}
+
+/// Formats a timestamp as `MM:SS.mmm`, escaped for a `drawtext` text.
+fn timestamp_label(timestamp_ms: u64) -> String {
+    let minutes = timestamp_ms / 60_000;
+    let seconds = (timestamp_ms % 60_000) / 1000;
+    let millis = timestamp_ms % 1000;
+    format!("{:02}\\:{:02}.{:03}", minutes, seconds, millis)
+}
+
+/// Returns whether ffmpeg has the `drawtext` filter, warning if it does not.
+fn has_drawtext() -> Result<bool> {
+    let output = StdCommand::new("ffmpeg")
+        .args(["-hide_banner", "-filters"])
+        .output()
+        .map_err(|e| SamplerError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to list its filters")?;
+    let found = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some("drawtext"));
+    if !found {
+        warn!("ffmpeg lacks the drawtext filter (it needs libfreetype), writing the collage without timestamps");
+    }
+    Ok(found)
+}
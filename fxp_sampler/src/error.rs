@@ -0,0 +1,49 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Sampler that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum SamplerError {
+    /// A tool the Sampler runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Sampler runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The run was stopped by Ctrl+C.
+    #[error("Sampling interrupted by user")]
+    Interrupted,
+}
+
+impl SamplerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            SamplerError::ToolMissing(_) => FailureKind::ToolMissing,
+            SamplerError::ToolFailed { .. } => FailureKind::ToolFailed,
+            SamplerError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+
+    /// Returns whether an error would recur on every attempt, so it is not retried, see
+    /// `fxp_output::RetryPolicy`.
+    pub(crate) fn is_permanent(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<SamplerError>(),
+            Some(SamplerError::ToolMissing(_) | SamplerError::Interrupted)
+        )
+    }
+}
+
+impl ToolError for SamplerError {
+    fn missing(tool: &str) -> Self {
+        SamplerError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        SamplerError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
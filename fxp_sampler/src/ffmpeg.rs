@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, error};
+use std::process::Command as ShellCommand;
+use std::process::{Child, Stdio};
+use std::sync::{atomic::AtomicBool, Arc};
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use fxp_output::{kill_requested, ToolError};
+
+use crate::error::SamplerError;
+
+/// Extracts a single frame from a video at the specified timestamp.
+///
+/// This function uses FFmpeg to capture a frame at a given time and saves it as an image file.
+///
+/// # Parameters
+/// - `video`: Path to the input video file.
+/// - `timestamp_seconds`: Time in seconds (with millisecond precision) to extract the frame.
+/// - `output`: Path where the extracted frame image will be saved.
+/// - `count`: Number of consecutive frames to extract; more than one needs an image2
+///   pattern such as `frame_%02d.png` as `output`.
+/// - `running`: A flag to control the extraction process, allowing it to be interrupted.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` on successful frame extraction, or an error if extraction fails.
+///
+/// # Notes
+/// - The function uses FFmpeg under the hood for frame extraction.
+/// - If the `running` flag becomes false, the process will be interrupted.
+/// - The extraction process can be interrupted by setting the `running` flag to false.
+/// - With the `native-decoding` feature, `native::extract_frame` decodes the frames instead.
+#[cfg(not(feature = "native-decoding"))]
+pub fn extract_frame(
+    video: &str,
+    timestamp_seconds: f64,
+    output: &str,
+    count: usize,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span = info_span!("export", seconds = %timestamp_seconds, path = %output).entered();
+    debug!(
+        "Attempting to extract frame at {:.3} seconds from video '{}' to '{}'",
+        timestamp_seconds, video, output
+    );
+
+    // Construct the ffmpeg command as a string for debugging purposes
+    let ffmpeg_command = format!(
+        "ffmpeg -i {} -ss {:.3} -frames:v {} {} -y",
+        video, timestamp_seconds, count, output
+    );
+    // Log the final ffmpeg command
+    debug!("Final ffmpeg command: {}", ffmpeg_command);
+
+    // Spawn a child process for ffmpeg with the working directory set to output_dir.
+    let child = ShellCommand::new("ffmpeg")
+        .arg("-i")
+        .arg(video)
+        .arg("-ss")
+        .arg(format!("{:.3}", timestamp_seconds)) // Timestamp with millisecond precision
+        .arg("-frames:v")
+        .arg(count.to_string()) // Extract this many frames
+        .arg(output) // Pass only the file name now
+        .arg("-y") // Pass only the file name now
+        .stdout(Stdio::null()) // Suppress stdout
+        .stderr(Stdio::null()) // Suppress stderr
+        .spawn()
+        .map_err(|e| SamplerError::spawn("ffmpeg", e))
+        .with_context(|| {
+            format!(
+                "Failed to start ffmpeg process for frame extraction at {:.3} seconds",
+                timestamp_seconds
+            )
+        })?;
+
+    debug!("FFmpeg process spawned with PID: {:?}", child.id());
+    wait_for_ffmpeg(child, output, running)
+}
+
+/// Cuts a clip out of a video and encodes it as H.264 MP4, with the video's audio.
+///
+/// # Parameters
+/// - `video`: Path to the input video file.
+/// - `start_seconds`: Time in seconds the clip starts at.
+/// - `length_seconds`: Length of the clip in seconds.
+/// - `output`: Path of the clip to write.
+/// - `running`: A flag to control the extraction process, allowing it to be interrupted.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` once the clip is written, or an error if ffmpeg fails.
+///
+/// # Notes
+/// - The clip is re-encoded, so it starts at the exact time rather than the keyframe
+///   before it; encoding a few seconds takes little longer than copying them.
+/// - Clips are always cut by the ffmpeg binary, also with the `native-decoding` feature.
+pub fn extract_clip(
+    video: &str,
+    start_seconds: f64,
+    length_seconds: f64,
+    output: &str,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span =
+        info_span!("clip", seconds = %start_seconds, length = %length_seconds, path = %output)
+            .entered();
+
+    let child = ShellCommand::new("ffmpeg")
+        .arg("-y")
+        .arg("-ss")
+        .arg(format!("{:.3}", start_seconds))
+        .arg("-i")
+        .arg(video)
+        .arg("-t")
+        .arg(format!("{:.3}", length_seconds))
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac"])
+        .args(["-movflags", "+faststart"])
+        .arg(output)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| SamplerError::spawn("ffmpeg", e))
+        .with_context(|| {
+            format!(
+                "Failed to start ffmpeg process for the clip at {:.3} seconds",
+                start_seconds
+            )
+        })?;
+
+    debug!("FFmpeg process spawned with PID: {:?}", child.id());
+    wait_for_ffmpeg(child, output, running)
+}
+
+/// Waits for ffmpeg to finish, killing it if `running` turns false.
+fn wait_for_ffmpeg(mut child: Child, output: &str, running: Arc<AtomicBool>) -> Result<()> {
+    // Periodically check the `running` flag.
+    while !kill_requested(&running) {
+        if let Ok(Some(status)) = child.try_wait() {
+            // Process finished, check its status.
+            if status.success() {
+                debug!("Extracted successfully to {}", output);
+                return Ok(());
+            } else {
+                return Err(SamplerError::ToolFailed {
+                    tool: "ffmpeg".to_string(),
+                    reason: status.to_string(),
+                })
+                .with_context(|| format!("Failed to extract {}", output));
+            }
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    // If we exit the loop, it means `running` is false, so terminate ffmpeg.
+    error!("Interrupt signal received, terminating FFmpeg process...");
+    if let Err(e) = child.kill() {
+        return Err(anyhow!("Failed to kill FFmpeg process: {}", e));
+    }
+    if let Err(e) = child.wait() {
+        return Err(anyhow!(
+            "Failed to wait for FFmpeg process to terminate: {}",
+            e
+        ));
+    }
+
+    Err(SamplerError::Interrupted.into())
+}
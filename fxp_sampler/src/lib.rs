@@ -1,4 +1,14 @@
+mod clip;
+mod collage;
+mod error;
+mod ffmpeg;
+#[cfg(feature = "native-decoding")]
+mod native;
 mod sample;
 mod sampler;
+mod sharpness;
 
+pub use clip::ClipLength;
+pub use collage::{Collage, COLLAGE_FILE_NAME};
+pub use error::SamplerError;
 pub use sampler::Sampler;
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use log::debug;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tracing::info_span;
+
+use fxp_decoder::VideoDecoder;
+
+use crate::error::SamplerError;
+
+/// Extracts frames from a video at the specified timestamp by decoding it in-process.
+///
+/// This is the `native-decoding` counterpart of the ffmpeg-spawning `extract_frame`,
+/// taking the same parameters.
+///
+/// # Parameters
+/// - `video`: Path to the input video file.
+/// - `timestamp_seconds`: Time in seconds of the first frame to extract.
+/// - `output`: Path where the extracted frame image will be saved.
+/// - `count`: Number of consecutive frames to extract; more than one needs an image2
+///   pattern such as `frame_%02d.png` as `output`.
+/// - `running`: A flag to control the extraction process, allowing it to be interrupted.
+///
+/// # Returns
+/// - `Result<()>`: Returns `Ok(())` on successful frame extraction, or an error if
+///   decoding fails or the video ends before the timestamp.
+///
+/// # Notes
+/// - The seek is exact: the first frame is the first one shown at or after the timestamp.
+/// - `%02d` and `%04d` in `output` are numbered from 1, as ffmpeg numbers them.
+pub fn extract_frame(
+    video: &str,
+    timestamp_seconds: f64,
+    output: &str,
+    count: usize,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span = info_span!("decode", seconds = %timestamp_seconds, path = %output).entered();
+
+    let mut decoder = VideoDecoder::open(Path::new(video))?;
+    decoder.seek((timestamp_seconds * 1000.0).round() as u64)?;
+    for number in 1..=count {
+        if !running.load(Ordering::SeqCst) {
+            return Err(SamplerError::Interrupted.into());
+        }
+        let Some(frame) = decoder.next_frame()? else {
+            if number == 1 {
+                bail!("The video ends before {:.3} seconds", timestamp_seconds);
+            }
+            break;
+        };
+        let path = output
+            .replace("%04d", &format!("{:04}", number))
+            .replace("%02d", &format!("{:02}", number));
+        frame
+            .image
+            .save(&path)
+            .with_context(|| format!("Failed to write frame {}", path))?;
+        debug!("Frame at {} ms decoded to {}", frame.timestamp_ms, path);
+    }
+    Ok(())
+}
@@ -1,17 +1,22 @@
 use anyhow::{anyhow, Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
-use log::{debug, error};
+use indicatif::ProgressStyle;
+use log::debug;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command as ShellCommand;
-use std::process::Stdio;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::thread;
-use std::time::Duration;
+
+use fxp_output::{progress_bar, RetryPolicy};
+
+use crate::error::SamplerError;
+#[cfg(not(feature = "native-decoding"))]
+use crate::ffmpeg::extract_frame;
+#[cfg(feature = "native-decoding")]
+use crate::native::extract_frame;
+use crate::sharpness::sharpest;
 
 /// Extracts a single frame from the middle of a video.
 ///
@@ -22,6 +27,8 @@ use std::time::Duration;
 /// - `video`: Path to the video file.
 /// - `duration_ms`: Video duration in milliseconds.
 /// - `output_path`: Destination path for the extracted frame.
+/// - `best_of`: Number of nearby frames to pick the sharpest from; 1 keeps the first.
+/// - `retry`: How often a failed extraction is attempted again.
 /// - `running`: Flag indicating whether the operation should continue.
 ///
 /// # Returns
@@ -35,10 +42,12 @@ pub fn extract_single_frame<P: AsRef<Path>>(
     video: P,
     duration_ms: u64,
     output_path: PathBuf,
+    best_of: usize,
+    retry: &RetryPolicy,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
     // Initialize the progress bar with a total of 1 step (since only one frame is being extracted)
-    let pb = ProgressBar::new(1);
+    let pb = progress_bar(1);
     let style = ProgressStyle::default_bar()
         .template(
             "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
@@ -50,7 +59,7 @@ pub fn extract_single_frame<P: AsRef<Path>>(
 
     if !running.load(Ordering::SeqCst) {
         pb.finish_and_clear();
-        return Err(anyhow!("Extraction interrupted before starting."));
+        return Err(SamplerError::Interrupted.into());
     }
 
     if duration_ms == 0 {
@@ -63,9 +72,7 @@ pub fn extract_single_frame<P: AsRef<Path>>(
 
     if !running.load(Ordering::SeqCst) {
         pb.finish_and_clear();
-        return Err(anyhow!(
-            "Extraction interrupted before extracting the frame."
-        ));
+        return Err(SamplerError::Interrupted.into());
     }
 
     let middle_timestamp_seconds = middle_timestamp_ms as f64 / 1000.0;
@@ -99,18 +106,26 @@ pub fn extract_single_frame<P: AsRef<Path>>(
         .ok_or_else(|| anyhow!("Invalid output file path"))?;
 
     // Set a progress message and perform the frame extraction
-    extract_frame(
-        video_str,
-        middle_timestamp_seconds,
-        temp_output_str,
-        running.clone(),
-    )
-    .with_context(|| {
-        format!(
-            "Failed to extract frame at {:.3} seconds from the video.",
-            middle_timestamp_seconds
+    retry
+        .run(
+            "Extracting the middle frame",
+            || {
+                extract_best_frame(
+                    video_str,
+                    middle_timestamp_seconds,
+                    temp_output_str,
+                    best_of,
+                    running.clone(),
+                )
+            },
+            SamplerError::is_permanent,
         )
-    })?;
+        .with_context(|| {
+            format!(
+                "Failed to extract frame at {:.3} seconds from the video.",
+                middle_timestamp_seconds
+            )
+        })?;
 
     // Mark progress complete
     pb.inc(1);
@@ -136,7 +151,7 @@ pub fn extract_single_frame<P: AsRef<Path>>(
         );
     } else {
         pb.finish_and_clear();
-        return Err(anyhow!("Extraction was interrupted midway."));
+        return Err(SamplerError::Interrupted.into());
     }
 
     pb.finish();
@@ -153,6 +168,8 @@ pub fn extract_single_frame<P: AsRef<Path>>(
 /// - `duration_ms`: Total duration of the video in milliseconds.
 /// - `num_frames`: Number of frames to extract from the video.
 /// - `output_dir`: Directory path where the extracted frames will be saved.
+/// - `best_of`: Number of nearby frames to pick the sharpest from at each point.
+/// - `retry`: How often a failed extraction is attempted again.
 /// - `running`: Flag indicating whether the extraction process should continue.
 ///
 /// # Returns
@@ -169,6 +186,8 @@ pub fn extract_multiple_frames(
     duration_ms: u64,
     num_frames: usize,
     output_dir: &Path,
+    best_of: usize,
+    retry: &RetryPolicy,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
     log::debug!("Starting to extract multiple frames from the video...");
@@ -180,23 +199,20 @@ pub fn extract_multiple_frames(
     }
 
     if !running.load(Ordering::SeqCst) {
-        return Err(anyhow!("Extraction interrupted before starting."));
+        return Err(SamplerError::Interrupted.into());
     }
 
     if duration_ms == 0 {
         return Err(anyhow!("Failed to determine video length."));
     }
 
-    // Calculate frame interval by dividing the duration into (num_frames + 1) parts.
-    let frame_interval_ms = duration_ms / (num_frames as u64 + 1);
-
     // Convert the video path to a &str for extract_frame.
     let video_str = video
         .to_str()
         .ok_or_else(|| anyhow!("Invalid video path"))?;
 
     // Set up a progress bar for the total number of frames.
-    let pb = ProgressBar::new(num_frames as u64);
+    let pb = progress_bar(num_frames as u64);
     let style = ProgressStyle::default_bar()
         .template(
             "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
@@ -204,39 +220,47 @@ pub fn extract_multiple_frames(
         .context("Failed to set progress bar template")?;
     pb.set_style(style);
 
-    for i in 0..num_frames {
+    for (i, (output_file_path, timestamp_ms)) in sample_points(output_dir, duration_ms, num_frames)
+        .into_iter()
+        .enumerate()
+    {
         if !running.load(Ordering::SeqCst) {
             pb.finish_and_clear();
-            return Err(anyhow!("Extraction interrupted during frame extraction."));
+            return Err(SamplerError::Interrupted.into());
         }
 
-        // Calculate timestamp for each frame.
-        let timestamp_ms = frame_interval_ms * (i as u64 + 1);
         debug!("Extracting frame {} at {} ms", i + 1, timestamp_ms);
         // pb.set_message(format!("Extracting frame {} at {} ms", i + 1, timestamp_ms));
 
-        // Build output file path by joining directory with a generated filename.
-        let output_file_path = output_dir.join(format!("sample_frame_{}.png", i + 1));
         debug!("Output file set to: {:?}", output_file_path);
 
         // Convert timestamp to seconds.
         let timestamp_seconds = timestamp_ms as f64 / 1000.0;
 
         // Call the frame extraction function.
-        extract_frame(
-            video_str,
-            timestamp_seconds,
-            output_file_path
-                .to_str()
-                .ok_or_else(|| anyhow!("Invalid output file path"))?,
-            running.clone(),
-        )
-        .with_context(|| {
-            format!(
-                "Failed to extract frame at {:.3} seconds from the video.",
-                timestamp_seconds
+        let output_file = output_file_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid output file path"))?;
+        retry
+            .run(
+                &format!("Extracting frame {}", i + 1),
+                || {
+                    extract_best_frame(
+                        video_str,
+                        timestamp_seconds,
+                        output_file,
+                        best_of,
+                        running.clone(),
+                    )
+                },
+                SamplerError::is_permanent,
             )
-        })?;
+            .with_context(|| {
+                format!(
+                    "Failed to extract frame at {:.3} seconds from the video.",
+                    timestamp_seconds
+                )
+            })?;
 
         // Update the progress bar.
         pb.inc(1);
@@ -247,96 +271,84 @@ pub fn extract_multiple_frames(
     if running.load(Ordering::SeqCst) {
         debug!("Successfully extracted {} frames.", num_frames);
     } else {
-        return Err(anyhow!("Extraction was interrupted midway."));
+        return Err(SamplerError::Interrupted.into());
     }
 
     Ok(())
 }
 
-/// Extracts a single frame from a video at the specified timestamp.
+/// Returns the file and timestamp in milliseconds of each of `num_frames` frames sampled
+/// evenly from a video, as `extract_multiple_frames` writes them.
 ///
-/// This function uses FFmpeg to capture a frame at a given time and saves it as an image file.
+/// # Notes
+/// - The duration is divided into `num_frames + 1` parts, so neither the first nor the
+///   last frame of the video is sampled.
+pub(crate) fn sample_points(
+    output_dir: &Path,
+    duration_ms: u64,
+    num_frames: usize,
+) -> Vec<(PathBuf, u64)> {
+    let frame_interval_ms = duration_ms / (num_frames as u64 + 1);
+    (1..=num_frames)
+        .map(|n| {
+            (
+                output_dir.join(format!("sample_frame_{}.png", n)),
+                frame_interval_ms * n as u64,
+            )
+        })
+        .collect()
+}
+
+/// Extracts the sharpest of `best_of` consecutive frames from the specified timestamp.
 ///
 /// # Parameters
 /// - `video`: Path to the input video file.
-/// - `timestamp_seconds`: Time in seconds (with millisecond precision) to extract the frame.
-/// - `output`: Path where the extracted frame image will be saved.
+/// - `timestamp_seconds`: Time in seconds of the first candidate frame.
+/// - `output`: Path where the chosen frame will be saved.
+/// - `best_of`: Number of candidate frames; 1 extracts the frame at the timestamp directly.
 /// - `running`: A flag to control the extraction process, allowing it to be interrupted.
 ///
 /// # Returns
-/// - `Result<()>`: Returns `Ok(())` on successful frame extraction, or an error if extraction fails.
+/// - `Result<()>`: Returns `Ok(())` once the chosen frame is saved.
 ///
 /// # Notes
-/// - The function uses FFmpeg under the hood for frame extraction.
-/// - If the `running` flag becomes false, the process will be interrupted.
-/// - The extraction process can be interrupted by setting the `running` flag to false.
-fn extract_frame(
+/// - The candidates are extracted into a temporary directory and scored by the variance
+///   of their Laplacian, which is low for blurry frames.
+/// - An image2 pattern output such as `frame%04d.png` receives the frame as number 1,
+///   the name ffmpeg gives a single frame.
+fn extract_best_frame(
     video: &str,
     timestamp_seconds: f64,
     output: &str,
+    best_of: usize,
     running: Arc<AtomicBool>,
 ) -> Result<()> {
-    debug!(
-        "Attempting to extract frame at {:.3} seconds from video '{}' to '{}'",
-        timestamp_seconds, video, output
-    );
-
-    // Construct the ffmpeg command as a string for debugging purposes
-    let ffmpeg_command = format!(
-        "ffmpeg -i {} -ss {:.3} -frames:v 1 {} -y",
-        video, timestamp_seconds, output
-    );
-    // Log the final ffmpeg command
-    debug!("Final ffmpeg command: {}", ffmpeg_command);
-
-    // Spawn a child process for ffmpeg with the working directory set to output_dir.
-    let mut child = ShellCommand::new("ffmpeg")
-        .arg("-i")
-        .arg(video)
-        .arg("-ss")
-        .arg(format!("{:.3}", timestamp_seconds)) // Timestamp with millisecond precision
-        .arg("-frames:v")
-        .arg("1") // Extract a single frame
-        .arg(output) // Pass only the file name now
-        .arg("-y") // Pass only the file name now
-        .stdout(Stdio::null()) // Suppress stdout
-        .stderr(Stdio::null()) // Suppress stderr
-        .spawn()
-        .with_context(|| {
-            format!(
-                "Failed to start ffmpeg process for frame extraction at {:.3} seconds",
-                timestamp_seconds
-            )
-        })?;
-
-    debug!("FFmpeg process spawned with PID: {:?}", child.id());
-
-    // Periodically check the `running` flag.
-    while running.load(Ordering::SeqCst) {
-        if let Ok(Some(status)) = child.try_wait() {
-            // Process finished, check its status.
-            if status.success() {
-                debug!("Frame extracted successfully to {}", output);
-                return Ok(());
-            } else {
-                return Err(anyhow!("FFmpeg command failed with status: {}", status));
-            }
-        }
-
-        thread::sleep(Duration::from_millis(100));
+    if best_of <= 1 {
+        return extract_frame(video, timestamp_seconds, output, 1, running);
     }
 
-    // If we exit the loop, it means `running` is false, so terminate ffmpeg.
-    error!("Interrupt signal received, terminating FFmpeg process...");
-    if let Err(e) = child.kill() {
-        return Err(anyhow!("Failed to kill FFmpeg process: {}", e));
-    }
-    if let Err(e) = child.wait() {
-        return Err(anyhow!(
-            "Failed to wait for FFmpeg process to terminate: {}",
-            e
-        ));
-    }
-
-    Err(anyhow!("Extraction interrupted before completion"))
+    let candidates_dir =
+        tempfile::tempdir().context("Failed to create a directory for candidate frames")?;
+    let pattern = candidates_dir.path().join("candidate_%02d.png");
+    extract_frame(
+        video,
+        timestamp_seconds,
+        pattern
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid temporary directory path"))?,
+        best_of,
+        running,
+    )?;
+
+    let mut candidates: Vec<PathBuf> = fs::read_dir(candidates_dir.path())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    candidates.sort();
+    let best = sharpest(&candidates)?;
+    debug!("Sharpest of {} candidates: {:?}", candidates.len(), best);
+
+    let output = output.replace("%04d", "0001");
+    fs::copy(&best, &output)
+        .with_context(|| format!("Failed to save the chosen frame to {}", output))?;
+    Ok(())
 }
@@ -1,17 +1,25 @@
 use anyhow::{anyhow, Context, Result};
-// use ctrlc;
 use log::debug;
 use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use tracing::info_span;
 
 use fxp_modes::Modes;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
 use fxp_output::ModeOutput;
 use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::RetryPolicy;
+use fxp_output::SampleKind;
 
-use crate::sample::{extract_multiple_frames, extract_single_frame};
+use crate::clip::{extract_clips, ClipLength};
+use crate::collage::Collage;
+use crate::error::SamplerError;
+use crate::sample::{extract_multiple_frames, extract_single_frame, sample_points};
 
 /// A collection of arguments for video sampling operations.
 ///
@@ -36,6 +44,16 @@ pub struct Sampler {
     pub output_path: PathBuf,
     pub duration: u64,
     pub sampling_number: usize,
+    /// Candidate frames per sampling point, of which the sharpest is kept; `new` sets 1.
+    pub best_of: usize,
+    /// Cut a clip of this length at each sampling point instead of a still.
+    pub clip_length: Option<ClipLength>,
+    /// How often the extraction at a sampling point is retried after a failure; `new`
+    /// sets no retries.
+    pub retry: RetryPolicy,
+    /// Also tile the sampled frames into `collage.png`; `new` sets `None`. Only applies
+    /// when several stills are sampled.
+    pub collage: Option<Collage>,
 }
 
 impl Sampler {
@@ -48,6 +66,8 @@ impl Sampler {
     /// - `output_path`: An optional path for the output directory; if not provided, a default will be used.
     /// - `duration`: The duration of the video in seconds.
     /// - `sampling_number`: The number of samples to take from the video.
+    /// - `clip_length`: Cut clips of this length instead of stills, if given.
+    /// - `collision`: What to do if the output already exists.
     ///
     /// # Returns
     /// - `Result<Self>`: Returns `Ok` if the Sampler was created successfully, `Err` if there was an issue creating the output directory.
@@ -60,6 +80,8 @@ impl Sampler {
         output_path: Option<String>,
         duration: u64,
         sampling_number: usize,
+        clip_length: Option<ClipLength>,
+        collision: CollisionPolicy,
     ) -> Result<Self> {
         let video_path = PathBuf::from(&video_path);
 
@@ -69,19 +91,102 @@ impl Sampler {
 
         // Use the trait method to create the output directory.
         let output_path = match output {
-            Output::Sampler(sampler_output) => {
-                sampler_output.create_output((video_path.clone(), output_path, sampling_number))?
-            }
+            Output::Sampler(sampler_output) => sampler_output.create_output(
+                (
+                    video_path.clone(),
+                    output_path,
+                    sampling_number,
+                    sample_kind(clip_length),
+                ),
+                collision,
+            )?,
             _ => unreachable!("Expected Sampler mode"),
         };
 
         Ok(Self {
             video_path,
-            output_path: output_path,
+            output_path,
             duration,
             sampling_number,
+            best_of: 1,
+            clip_length,
+            retry: RetryPolicy::default(),
+            collage: None,
         })
     }
+
+    /// Resolves what `new` and `sample_images` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `video_path`: The path to the video file to process.
+    /// - `output_path`: An optional path for the output file or directory.
+    /// - `duration`: The duration of the video in milliseconds.
+    /// - `sampling_number`: The number of samples to take from the video.
+    /// - `best_of`: Candidate frames per sampling point.
+    /// - `clip_length`: Cut clips of this length instead of stills, if given.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the output cannot be resolved.
+    pub fn plan(
+        video_path: String,
+        output_path: Option<String>,
+        duration: u64,
+        sampling_number: usize,
+        best_of: usize,
+        clip_length: Option<ClipLength>,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let video_path = PathBuf::from(&video_path);
+
+        let mode: Modes = Modes::Sampler;
+        let output: Output = mode.into();
+        let output_path = match output {
+            Output::Sampler(sampler_output) => sampler_output.plan_output(
+                (
+                    video_path.clone(),
+                    output_path,
+                    sampling_number,
+                    sample_kind(clip_length),
+                ),
+                collision,
+            )?,
+            _ => unreachable!("Expected Sampler mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Sampler)
+            .entry("input video", video_path.display())
+            .entry("duration", format!("{} ms", duration))
+            .entry("samples", sampling_number);
+        plan = match clip_length {
+            Some(clip_length) => plan
+                .entry(
+                    "sample",
+                    format!("{} clip around each sampling point", clip_length),
+                )
+                .entry("decoding", "ffmpeg binary, encoding H.264 MP4"),
+            None => plan
+                .entry(
+                    "frame choice",
+                    if best_of > 1 {
+                        format!("sharpest of {} consecutive frames", best_of)
+                    } else {
+                        "frame at each sampling point".to_string()
+                    },
+                )
+                .entry(
+                    "decoding",
+                    if cfg!(feature = "native-decoding") {
+                        "ffmpeg libraries, in-process"
+                    } else {
+                        "ffmpeg binary"
+                    },
+                ),
+        };
+        Ok(plan
+            .entry("on existing output", collision)
+            .entry("output", output_path.display()))
+    }
 }
 
 impl Sampler {
@@ -103,12 +208,27 @@ impl Sampler {
     /// - If `running` is false, the function exits early.
     /// - If `duration` is 0, returns an error as it's an invalid value.
     /// - Based on `sampling_number`, the function will either extract a single frame or multiple frames.
+    /// - With `best_of` above 1, the sharpest of that many consecutive frames is kept at
+    ///   each sampling point.
+    /// - With `clip_length`, a clip centered on each sampling point is written as
+    ///   `sample_clip_N.mp4` instead, see `extract_clips`; `best_of` does not apply.
+    /// - A sampling point whose extraction fails is extracted again as `retry` allows.
+    /// - With `collage` and several stills, the stills are also tiled into `collage.png`,
+    ///   see `Collage::write`.
+    /// - Writes a run manifest next to the output, see `fxp_output::Manifest`.
     pub fn sample_images(&self, running: Arc<AtomicBool>) -> Result<()> {
         debug!("Starting sample processing with arguments: {:?}", self);
+        let _span = info_span!(
+            "sampler",
+            video = %self.video_path.display(),
+            output = %self.output_path.display(),
+            sampling_number = %self.sampling_number
+        )
+        .entered();
 
         // Check if the running flag is true; if false, exit early.
         if !running.load(Ordering::SeqCst) {
-            return Err(anyhow!("Processing interrupted before starting."));
+            return Err(SamplerError::Interrupted.into());
         }
 
         if self.duration == 0 {
@@ -116,6 +236,34 @@ impl Sampler {
         }
 
         let output_path = &self.output_path;
+        let mut manifest = Manifest::new(Modes::Sampler)
+            .parameter("video", self.video_path.display())
+            .parameter("duration", self.duration)
+            .parameter("sampling number", self.sampling_number)
+            .inputs([&self.video_path]);
+        if self.best_of > 1 {
+            manifest = manifest.parameter("best of", self.best_of);
+        }
+
+        if let Some(clip_length) = self.clip_length {
+            if self.sampling_number == 0 {
+                return Err(anyhow!("Invalid sampling number: 0"));
+            }
+            extract_clips(
+                &self.video_path,
+                self.duration,
+                self.sampling_number,
+                clip_length,
+                output_path,
+                &self.retry,
+                running,
+            )
+            .context("Failed to extract clips")?;
+            manifest
+                .parameter("clip length", clip_length)
+                .write(output_path)?;
+            return Ok(());
+        }
 
         match self.sampling_number {
             1 => {
@@ -123,6 +271,8 @@ impl Sampler {
                     &self.video_path,
                     self.duration,
                     output_path.clone(), // Convert &Path to PathBuf
+                    self.best_of,
+                    &self.retry,
                     running.clone(),
                 )
                 .context("Failed to extract single frame")?;
@@ -133,10 +283,25 @@ impl Sampler {
                     &self.video_path,
                     self.duration,
                     num_frames,
-                    &output_path, // Provide the output directory
+                    output_path, // Provide the output directory
+                    self.best_of,
+                    &self.retry,
                     running.clone(),
                 )
                 .context("Failed to extract multiple frames")?;
+
+                if let Some(collage) = &self.collage {
+                    let frames = sample_points(output_path, self.duration, num_frames);
+                    let collage_path = collage.write(&frames, output_path)?;
+                    debug!("Wrote collage {:?}", collage_path);
+                    manifest = manifest.parameter("collage", true);
+                    if let Some(columns) = collage.columns {
+                        manifest = manifest.parameter("collage columns", columns);
+                    }
+                    if !collage.labels {
+                        manifest = manifest.parameter("collage labels", false);
+                    }
+                }
             }
             _ => {
                 return Err(anyhow!(
@@ -145,7 +310,16 @@ impl Sampler {
                 ));
             }
         }
+        manifest.write(output_path)?;
 
         Ok(())
     }
 }
+
+/// Returns what the Sampler takes at each sampling point.
+fn sample_kind(clip_length: Option<ClipLength>) -> SampleKind {
+    match clip_length {
+        Some(_) => SampleKind::Clips,
+        None => SampleKind::Frames,
+    }
+}
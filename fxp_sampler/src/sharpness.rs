@@ -0,0 +1,61 @@
+use anyhow::{anyhow, Context, Result};
+use image::GrayImage;
+use log::debug;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
+
+/// Scores the sharpness of an image as the variance of its Laplacian.
+///
+/// # Parameters
+/// - `image`: The luma of the image.
+///
+/// # Returns
+/// - `f64`: The variance; blurry images have few strong edges and score low.
+///
+/// # Notes
+/// - Uses the 4-neighbour kernel `[0 1 0; 1 -4 1; 0 1 0]` over the interior pixels, so
+///   an image narrower or shorter than 3 pixels scores 0.
+pub(crate) fn laplacian_variance(image: &GrayImage) -> f64 {
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let pixel = |x: u32, y: u32| image.get_pixel(x, y)[0] as i32;
+    let mut sum = 0.0;
+    let mut sum_of_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = (pixel(x, y - 1) + pixel(x - 1, y) + pixel(x + 1, y) + pixel(x, y + 1)
+                - 4 * pixel(x, y)) as f64;
+            sum += laplacian;
+            sum_of_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_of_squares / count - mean * mean
+}
+
+/// Returns the sharpest of the candidate frames.
+///
+/// # Parameters
+/// - `candidates`: Paths of the extracted frames to choose from.
+///
+/// # Returns
+/// - `Result<PathBuf>`: The candidate with the highest Laplacian variance, or an error if
+///   there is none or one cannot be decoded.
+pub(crate) fn sharpest(candidates: &[PathBuf]) -> Result<PathBuf> {
+    let mut best: Option<(f64, &Path)> = None;
+    for candidate in candidates {
+        let _span = info_span!("score", path = %candidate.display()).entered();
+        let image = image::open(candidate)
+            .with_context(|| format!("Failed to open candidate frame {}", candidate.display()))?;
+        let score = laplacian_variance(&image.to_luma8());
+        debug!("Sharpness of {:?}: {:.1}", candidate, score);
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, candidate));
+        }
+    }
+    best.map(|(_, path)| path.to_path_buf())
+        .ok_or_else(|| anyhow!("No candidate frames were extracted"))
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Stabilizer that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum StabilizerError {
+    /// A tool the Stabilizer runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Stabilizer runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The run was stopped by Ctrl+C.
+    #[error("Stabilization interrupted by user")]
+    Interrupted,
+}
+
+impl StabilizerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            StabilizerError::ToolMissing(_) => FailureKind::ToolMissing,
+            StabilizerError::ToolFailed { .. } => FailureKind::ToolFailed,
+            StabilizerError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for StabilizerError {
+    fn missing(tool: &str) -> Self {
+        StabilizerError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        StabilizerError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
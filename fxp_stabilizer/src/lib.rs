@@ -0,0 +1,6 @@
+mod error;
+mod stabilize;
+mod stabilizer;
+
+pub use error::StabilizerError;
+pub use stabilizer::Stabilizer;
@@ -0,0 +1,149 @@
+use anyhow::{bail, Context, Result};
+use log::debug;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use fxp_output::{kill_requested, ToolError};
+
+use crate::error::StabilizerError;
+
+/// Name of the transforms file written by the detection pass, relative to the work directory.
+const TRANSFORMS_FILE: &str = "transforms.trf";
+
+/// Checks that ffmpeg is installed and built with the vidstab filters.
+///
+/// # Returns
+/// - `Result<()>`: An error naming what is missing, before any work is done.
+///
+/// # Notes
+/// - The filters come from libvidstab, which ffmpeg only includes when configured with
+///   `--enable-libvidstab`; many distribution builds leave it out.
+pub(crate) fn check_vidstab() -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-filters"])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| StabilizerError::spawn("ffmpeg", e))
+        .context("Failed to execute ffmpeg to list its filters")?;
+    let filters = String::from_utf8_lossy(&output.stdout);
+    let available = |name: &str| {
+        filters
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(name))
+    };
+    if !available("vidstabdetect") || !available("vidstabtransform") {
+        bail!(
+            "ffmpeg has no vidstabdetect and vidstabtransform filters; \
+             install an ffmpeg built with --enable-libvidstab to stabilize videos"
+        );
+    }
+    Ok(())
+}
+
+/// Runs the first pass, measuring the camera motion of the video.
+///
+/// # Parameters
+/// - `video_path`: The video to analyze, as an absolute path.
+/// - `work_dir`: Directory receiving the transforms file.
+/// - `shakiness`: How shaky the video is, from 1 (little) to 10 (a lot).
+/// - `running`: Cleared to interrupt the pass.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or the pass was interrupted.
+pub(crate) fn detect_motion(
+    video_path: &Path,
+    work_dir: &Path,
+    shakiness: u8,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span =
+        info_span!("detect", video = %video_path.display(), shakiness = %shakiness).entered();
+    let filter = format!(
+        "vidstabdetect=shakiness={}:result={}",
+        shakiness, TRANSFORMS_FILE
+    );
+    let mut command = Command::new("ffmpeg");
+    command
+        .current_dir(work_dir)
+        .args(["-y", "-i"])
+        .arg(video_path)
+        .args(["-vf", &filter, "-f", "null", "-"]);
+    run_ffmpeg(command, "detect the camera motion", running)
+}
+
+/// Runs the second pass, smoothing the measured motion and writing the stabilized video.
+///
+/// # Parameters
+/// - `video_path`: The video to stabilize, as an absolute path.
+/// - `work_dir`: Directory holding the transforms file of `detect_motion`.
+/// - `output_path`: The stabilized video, as an absolute path.
+/// - `smoothing`: Number of frames before and after each frame the motion is averaged over.
+/// - `running`: Cleared to interrupt the pass.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or the pass was interrupted.
+///
+/// # Notes
+/// - The audio stream, if any, is copied unchanged.
+pub(crate) fn transform_video(
+    video_path: &Path,
+    work_dir: &Path,
+    output_path: &Path,
+    smoothing: u32,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span =
+        info_span!("transform", video = %video_path.display(), smoothing = %smoothing).entered();
+    let filter = format!(
+        "vidstabtransform=input={}:smoothing={}",
+        TRANSFORMS_FILE, smoothing
+    );
+    let mut command = Command::new("ffmpeg");
+    command
+        .current_dir(work_dir)
+        .args(["-y", "-i"])
+        .arg(video_path)
+        .args(["-vf", &filter, "-c:a", "copy"])
+        .arg(output_path);
+    run_ffmpeg(command, "stabilize the video", running)
+}
+
+/// Runs an ffmpeg command to completion, killing it if `running` is cleared.
+fn run_ffmpeg(mut command: Command, action: &str, running: Arc<AtomicBool>) -> Result<()> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(StabilizerError::Interrupted.into());
+    }
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| StabilizerError::spawn("ffmpeg", e))
+        .with_context(|| format!("Failed to start ffmpeg to {}", action))?;
+
+    loop {
+        if kill_requested(&running) {
+            debug!("Interruption requested; terminating ffmpeg process.");
+            child.kill().ok();
+            return Err(StabilizerError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => {
+                debug!("FFmpeg command failed with status: {:?}", status);
+                return Err(StabilizerError::ToolFailed {
+                    tool: "ffmpeg".to_string(),
+                    reason: status.to_string(),
+                })
+                .with_context(|| format!("ffmpeg failed to {}", action));
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+
+use crate::stabilize::{check_vidstab, detect_motion, transform_video};
+
+/// Shakiness of the video, see `Stabilizer::shakiness`.
+pub const DEFAULT_SHAKINESS: u8 = 5;
+
+/// Smoothing window of the camera motion, see `Stabilizer::smoothing`.
+pub const DEFAULT_SMOOTHING: u32 = 10;
+
+/// Struct responsible for stabilizing a video with ffmpeg's vidstab filters.
+///
+/// Stabilize the source video before running the Exporter on it, or the video written
+/// by the Clipper; either way the input is a video file and the output another one.
+#[derive(Debug, Clone)]
+pub struct Stabilizer {
+    video_path: PathBuf,
+    output_path: PathBuf,
+    /// How shaky the video is, from 1 (little) to 10 (a lot); `new` sets `DEFAULT_SHAKINESS`.
+    pub shakiness: u8,
+    /// Number of frames before and after each frame the camera motion is averaged over;
+    /// `new` sets `DEFAULT_SMOOTHING`. Larger values give a steadier, slower camera.
+    pub smoothing: u32,
+}
+
+impl Stabilizer {
+    /// Creates a new `Stabilizer` for a video file.
+    ///
+    /// # Parameters
+    /// - `video_path`: The video to stabilize.
+    /// - `output`: An optional output file or directory; defaults to
+    ///   `<video_stem>_stabilized.<ext>` next to the video.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The configured `Stabilizer`, or an error if the video does not exist.
+    pub fn new(
+        video_path: String,
+        output: Option<String>,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        let video_path = PathBuf::from(video_path);
+        let video_path = fs::canonicalize(&video_path)
+            .with_context(|| format!("Failed to resolve video '{}'", video_path.display()))?;
+
+        let mode: Modes = Modes::Stabilizer;
+        let output_enum: Output = mode.into();
+        let output_path = match output_enum {
+            Output::Stabilizer(stabilizer_output) => {
+                stabilizer_output.create_output((video_path.clone(), output), collision)?
+            }
+            _ => unreachable!("Expected Stabilizer mode"),
+        };
+        debug!("Stabilizing {:?} into {:?}", video_path, output_path);
+
+        Ok(Self {
+            video_path,
+            output_path,
+            shakiness: DEFAULT_SHAKINESS,
+            smoothing: DEFAULT_SMOOTHING,
+        })
+    }
+
+    /// Resolves what `new` and `stabilize` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `video_path`: The video to stabilize.
+    /// - `output`: An optional output file or directory.
+    /// - `shakiness`: How shaky the video is, from 1 to 10.
+    /// - `smoothing`: The smoothing window in frames.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the output cannot be resolved.
+    ///
+    /// # Notes
+    /// - Whether ffmpeg has the vidstab filters is only checked when running.
+    pub fn plan(
+        video_path: String,
+        output: Option<String>,
+        shakiness: u8,
+        smoothing: u32,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let video_path = PathBuf::from(video_path);
+
+        let mode: Modes = Modes::Stabilizer;
+        let output_enum: Output = mode.into();
+        let output_path = match output_enum {
+            Output::Stabilizer(stabilizer_output) => {
+                stabilizer_output.plan_output((video_path.clone(), output), collision)?
+            }
+            _ => unreachable!("Expected Stabilizer mode"),
+        };
+
+        Ok(Plan::new(Modes::Stabilizer)
+            .entry("input video", video_path.display())
+            .entry("shakiness", shakiness)
+            .entry("smoothing", format!("{} frames", smoothing))
+            .entry("passes", "vidstabdetect, then vidstabtransform")
+            .entry("on existing output", collision)
+            .entry("output file", output_path.display()))
+    }
+}
+
+impl Stabilizer {
+    /// Stabilizes the video in two passes and writes it to the output file.
+    ///
+    /// # Returns
+    /// - `Result<PathBuf>`: The stabilized video, or an error if ffmpeg lacks the vidstab
+    ///   filters, a pass fails or the process was interrupted.
+    ///
+    /// # Notes
+    /// - The first pass measures the camera motion into a transforms file, the second
+    ///   smooths it and writes the compensated frames.
+    /// - Both passes work in a temporary directory; the output is only replaced once the
+    ///   stabilized video is complete.
+    /// - Handles Ctrl+C interruptions gracefully.
+    /// - Writes `<video>.manifest.json` next to the output, see `fxp_output::Manifest`.
+    pub fn stabilize(&self) -> Result<PathBuf> {
+        let _span = info_span!(
+            "stabilizer",
+            video = %self.video_path.display(),
+            output = %self.output_path.display()
+        )
+        .entered();
+        check_vidstab()?;
+
+        let manifest = Manifest::new(Modes::Stabilizer)
+            .parameter("video", self.video_path.display())
+            .parameter("shakiness", self.shakiness)
+            .parameter("smoothing", self.smoothing)
+            .inputs([&self.video_path]);
+
+        let running = running_flag()?;
+
+        let tmp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+        let extension = self
+            .output_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "mp4".to_string());
+        let stabilized_path = tmp_dir.path().join(format!("stabilized.{}", extension));
+
+        detect_motion(
+            &self.video_path,
+            tmp_dir.path(),
+            self.shakiness,
+            running.clone(),
+        )?;
+        transform_video(
+            &self.video_path,
+            tmp_dir.path(),
+            &stabilized_path,
+            self.smoothing,
+            running,
+        )?;
+
+        fs::copy(&stabilized_path, &self.output_path).with_context(|| {
+            format!(
+                "Failed to move the stabilized video to {}",
+                self.output_path.display()
+            )
+        })?;
+        manifest.write(&self.output_path)?;
+        debug!("Stabilized video saved as {:?}", self.output_path);
+
+        Ok(self.output_path.clone())
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Context, Result};
+use image::RgbaImage;
+use log::debug;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+
+/// Frames a channel holds before its sender waits for the receiver.
+pub const CHANNEL_CAPACITY: usize = 4;
+
+/// Memory the channels of a chain may hold frames in before spilling them, 1 GiB.
+pub const DEFAULT_STREAM_MEMORY_BYTES: usize = 1024 * 1024 * 1024;
+
+/// A decoded frame passed from one stage to the next.
+#[derive(Debug, Clone)]
+pub struct StreamFrame {
+    /// Frame number of the input the frame comes from.
+    pub number: u32,
+    pub image: RgbaImage,
+}
+
+/// Error of a stage whose next stage stopped receiving, because it failed or finished.
+///
+/// The stage that stopped reports the reason; this error only ends the stages before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageStopped;
+
+impl fmt::Display for StageStopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The next stage stopped receiving frames")
+    }
+}
+
+impl std::error::Error for StageStopped {}
+
+/// The memory the channels of a chain share, and where frames go once it is used up.
+///
+/// Only frames waiting in a channel are counted; each stage also holds the frame it
+/// is working on.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: Arc<AtomicUsize>,
+    spill_dir: PathBuf,
+    spilled: Arc<AtomicUsize>,
+}
+
+impl MemoryBudget {
+    /// Creates a budget of `limit` bytes, spilling frames beyond it into `spill_dir`.
+    ///
+    /// # Notes
+    /// - A limit of 0 spills every frame, as the stages did by writing PNGs.
+    pub fn new(limit: usize, spill_dir: &Path) -> Self {
+        Self {
+            limit,
+            used: Arc::new(AtomicUsize::new(0)),
+            spill_dir: spill_dir.to_path_buf(),
+            spilled: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the number of frames spilled to disk so far.
+    pub fn spilled(&self) -> usize {
+        self.spilled.load(Ordering::SeqCst)
+    }
+
+    /// Claims `bytes` of the budget, if they are left.
+    fn reserve(&self, bytes: usize) -> bool {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used + bytes <= self.limit).then_some(used + bytes)
+            })
+            .is_ok()
+    }
+
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    /// Writes the raw pixels of a frame into the spill directory.
+    fn spill(&self, frame: StreamFrame) -> Result<Message> {
+        let index = self.spilled.fetch_add(1, Ordering::SeqCst);
+        let path = self.spill_dir.join(format!("spill_{:06}.rgba", index));
+        fs::write(&path, frame.image.as_raw())
+            .with_context(|| format!("Failed to spill frame {} to disk", frame.number))?;
+        Ok(Message::Spilled {
+            number: frame.number,
+            path,
+            width: frame.image.width(),
+            height: frame.image.height(),
+        })
+    }
+}
+
+enum Message {
+    Memory(StreamFrame),
+    Spilled {
+        number: u32,
+        path: PathBuf,
+        width: u32,
+        height: u32,
+    },
+}
+
+/// Creates a bounded channel of frames between two stages, holding at most
+/// `CHANNEL_CAPACITY` frames.
+///
+/// # Parameters
+/// - `budget`: The memory shared by the channels of the chain.
+///
+/// # Returns
+/// - `(FrameSender, FrameReceiver)`: The ends of the channel; dropping the sender ends
+///   the frames of the receiver.
+pub fn frame_channel(budget: &MemoryBudget) -> (FrameSender, FrameReceiver) {
+    let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    (
+        FrameSender {
+            sender,
+            budget: budget.clone(),
+        },
+        FrameReceiver {
+            receiver,
+            budget: budget.clone(),
+        },
+    )
+}
+
+/// The sending end of a frame channel.
+pub struct FrameSender {
+    sender: SyncSender<Message>,
+    budget: MemoryBudget,
+}
+
+impl FrameSender {
+    /// Sends a frame, waiting while the channel is full.
+    ///
+    /// # Returns
+    /// - `Result<()>`: `StageStopped` if the receiver is gone, or an error if the frame
+    ///   had to be spilled and could not be written.
+    ///
+    /// # Notes
+    /// - The frame is kept in memory if the budget has room for it, and spilled to disk
+    ///   otherwise.
+    pub fn send(&self, frame: StreamFrame) -> Result<()> {
+        let bytes = frame.image.as_raw().len();
+        let message = if self.budget.reserve(bytes) {
+            Message::Memory(frame)
+        } else {
+            debug!("Memory budget used up, spilling frame {}", frame.number);
+            self.budget.spill(frame)?
+        };
+        self.sender.send(message).map_err(|error| {
+            // The frame will never be received, so give back what it held.
+            match error.0 {
+                Message::Memory(_) => self.budget.release(bytes),
+                Message::Spilled { path, .. } => {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            anyhow!(StageStopped)
+        })
+    }
+}
+
+/// The receiving end of a frame channel, yielding the frames in the order they were sent.
+pub struct FrameReceiver {
+    receiver: Receiver<Message>,
+    budget: MemoryBudget,
+}
+
+impl Iterator for FrameReceiver {
+    type Item = Result<StreamFrame>;
+
+    /// Receives the next frame, reading it back if it was spilled.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv().ok()? {
+            Message::Memory(frame) => {
+                self.budget.release(frame.image.as_raw().len());
+                Some(Ok(frame))
+            }
+            Message::Spilled {
+                number,
+                path,
+                width,
+                height,
+            } => Some(read_spilled(&path, width, height).map(|image| {
+                let _ = fs::remove_file(&path);
+                StreamFrame { number, image }
+            })),
+        }
+    }
+}
+
+impl Drop for FrameReceiver {
+    /// Removes the frames spilled into the channel that were never received.
+    fn drop(&mut self) {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                Message::Memory(frame) => self.budget.release(frame.image.as_raw().len()),
+                Message::Spilled { path, .. } => {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// Reads back the raw pixels of a spilled frame.
+fn read_spilled(path: &Path, width: u32, height: u32) -> Result<RgbaImage> {
+    let pixels =
+        fs::read(path).with_context(|| format!("Failed to read spilled frame {:?}", path))?;
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("Spilled frame {:?} is not {}x{}", path, width, height))
+}
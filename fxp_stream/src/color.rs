@@ -0,0 +1,167 @@
+use image::{ColorType, DynamicImage, ImageDecoder, ImageReader};
+use log::{debug, warn};
+use std::fmt;
+use std::path::Path;
+
+/// Largest difference between a colorant of a profile and a known one, per XYZ component,
+/// for the profile to count as having those primaries.
+const COLORANT_TOLERANCE: f64 = 0.01;
+
+/// The red and green colorants, D50 adapted as ICC profiles store them, of the primaries
+/// recognized in a profile.
+const KNOWN_COLORANTS: [(ColorPrimaries, [f64; 3], [f64; 3]); 3] = [
+    (
+        ColorPrimaries::Bt709,
+        [0.4361, 0.2225, 0.0139],
+        [0.3851, 0.7169, 0.0971],
+    ),
+    (
+        ColorPrimaries::DisplayP3,
+        [0.5151, 0.2412, -0.0011],
+        [0.2920, 0.6922, 0.0419],
+    ),
+    (
+        ColorPrimaries::Bt2020,
+        [0.6734, 0.2790, -0.0019],
+        [0.1656, 0.6757, 0.0300],
+    ),
+];
+
+/// Returns whether an image has more than 8 bits per channel, as the 16-bit frames of a
+/// 10-bit video do.
+pub fn is_deep_color(image: &DynamicImage) -> bool {
+    is_deep(image.color())
+}
+
+/// Returns whether an image file has more than 8 bits per channel, decoding only its
+/// header; `false` if it cannot be read.
+pub fn has_deep_color(path: &Path) -> bool {
+    ImageReader::open(path)
+        .ok()
+        .and_then(|reader| reader.with_guessed_format().ok())
+        .and_then(|reader| reader.into_decoder().ok())
+        .is_some_and(|decoder| is_deep(decoder.color_type()))
+}
+
+/// Returns whether a color type has more than a byte per channel.
+fn is_deep(color: ColorType) -> bool {
+    color.bytes_per_pixel() > color.channel_count()
+}
+
+/// An ICC color profile embedded in an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    bytes: Vec<u8>,
+}
+
+impl IccProfile {
+    /// Reads the profile embedded in an image file, decoding only its header.
+    ///
+    /// # Parameters
+    /// - `path`: The image, in any format the image crate reads.
+    ///
+    /// # Returns
+    /// - `Option<Self>`: The profile, or `None` if the image has none or cannot be read.
+    pub fn read(path: &Path) -> Option<Self> {
+        let mut decoder = ImageReader::open(path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .into_decoder()
+            .ok()?;
+        let bytes = decoder.icc_profile().ok()??;
+        debug!(
+            "{} carries a {} byte color profile",
+            path.display(),
+            bytes.len()
+        );
+        (!bytes.is_empty()).then_some(Self { bytes })
+    }
+
+    /// Returns the profile as embedded in a file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the primaries of the profile, if they are ones `ColorPrimaries` knows.
+    ///
+    /// # Notes
+    /// - The primaries are told by the red and green colorants, the `rXYZ` and `gXYZ`
+    ///   tags; profiles without them, such as gray ones, give `None`.
+    pub fn primaries(&self) -> Option<ColorPrimaries> {
+        let red = self.colorant(b"rXYZ")?;
+        let green = self.colorant(b"gXYZ")?;
+        let near = |a: [f64; 3], b: [f64; 3]| {
+            a.iter()
+                .zip(b)
+                .all(|(a, b)| (a - b).abs() <= COLORANT_TOLERANCE)
+        };
+        KNOWN_COLORANTS
+            .iter()
+            .find(|(_, known_red, known_green)| near(red, *known_red) && near(green, *known_green))
+            .map(|(primaries, ..)| *primaries)
+    }
+
+    /// Returns the XYZ value of an `XYZ ` type tag, looked up in the tag table.
+    fn colorant(&self, signature: &[u8; 4]) -> Option<[f64; 3]> {
+        let u32_at = |offset: usize| {
+            self.bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let count = u32_at(128)? as usize;
+        let entry = (0..count)
+            .map(|index| 132 + index * 12)
+            .find(|&entry| self.bytes.get(entry..entry + 4) == Some(signature))?;
+        let offset = u32_at(entry + 4)? as usize;
+        if self.bytes.get(offset..offset + 4)? != b"XYZ " {
+            return None;
+        }
+        let component = |index: usize| {
+            u32_at(offset + 8 + index * 4).map(|value| value as i32 as f64 / 65536.0)
+        };
+        Some([component(0)?, component(1)?, component(2)?])
+    }
+}
+
+/// The primaries of a frame's colors, as video is tagged with them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorPrimaries {
+    /// The primaries of sRGB and HD video, assumed for frames without a profile.
+    #[default]
+    Bt709,
+    /// The wider gamut of Apple displays and many phone cameras.
+    DisplayP3,
+    Bt2020,
+}
+
+impl ColorPrimaries {
+    /// Returns the primaries of an image file's profile.
+    ///
+    /// # Returns
+    /// - `Self`: The primaries, `Bt709` if the image has no profile or one with
+    ///   primaries not known, which is logged.
+    pub fn of_image(path: &Path) -> Self {
+        let Some(profile) = IccProfile::read(path) else {
+            return Self::default();
+        };
+        profile.primaries().unwrap_or_else(|| {
+            warn!(
+                "The color profile of {} has primaries other than bt709, Display P3 or \
+                 bt2020; treating them as bt709",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+}
+
+impl fmt::Display for ColorPrimaries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorPrimaries::Bt709 => write!(f, "bt709"),
+            ColorPrimaries::DisplayP3 => write!(f, "display-p3"),
+            ColorPrimaries::Bt2020 => write!(f, "bt2020"),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::{bail, Context, Result};
+use image::codecs::avif::AvifEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, ImageFormat};
+use log::debug;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::color::{is_deep_color, IccProfile};
+
+/// Quality of lossy frames, from 1 to 100, see `FrameEncoder::quality`.
+pub const DEFAULT_QUALITY: u8 = 90;
+
+/// Speed of the AVIF encoder, from 1 (smallest files) to 10 (fastest).
+const AVIF_SPEED: u8 = 6;
+
+/// Quality of a JPEG written in the format of its extension, as `DynamicImage::save`
+/// writes it.
+const EXTENSION_JPEG_QUALITY: u8 = 75;
+
+/// The image format frames are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Png,
+    /// Progressive JPEG; without alpha, so transparent pixels turn to their color.
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl FrameFormat {
+    /// Returns the file extension of the format, e.g. `webp`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Jpeg => "jpg",
+            FrameFormat::Webp => "webp",
+            FrameFormat::Avif => "avif",
+        }
+    }
+}
+
+impl FromStr for FrameFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(FrameFormat::Png),
+            "jpeg" | "jpg" => Ok(FrameFormat::Jpeg),
+            "webp" => Ok(FrameFormat::Webp),
+            "avif" => Ok(FrameFormat::Avif),
+            _ => Err(format!(
+                "Unknown output format '{}', expected png, jpeg, webp or avif",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FrameFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameFormat::Png => write!(f, "png"),
+            FrameFormat::Jpeg => write!(f, "jpeg"),
+            FrameFormat::Webp => write!(f, "webp"),
+            FrameFormat::Avif => write!(f, "avif"),
+        }
+    }
+}
+
+/// How the images a stage writes are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameEncoder {
+    /// The format of every image; `None` keeps the format of each output's file
+    /// extension, which is the input's.
+    pub format: Option<FrameFormat>,
+    /// Quality of JPEG, lossy WebP and AVIF images, from 1 to 100.
+    pub quality: u8,
+    /// Write WebP images losslessly, ignoring `quality`.
+    pub lossless: bool,
+}
+
+impl Default for FrameEncoder {
+    /// Each image in the format of its extension, at quality 90.
+    fn default() -> Self {
+        Self {
+            format: None,
+            quality: DEFAULT_QUALITY,
+            lossless: false,
+        }
+    }
+}
+
+impl fmt::Display for FrameEncoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            None => write!(f, "as the input"),
+            Some(FrameFormat::Png) => write!(f, "png"),
+            Some(FrameFormat::Webp) if self.lossless => write!(f, "webp, lossless"),
+            Some(format) => write!(f, "{}, quality {}", format, self.quality),
+        }
+    }
+}
+
+impl FrameEncoder {
+    /// Checks that the quality is within range and that lossless is only asked of WebP.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.quality) {
+            return Err(format!(
+                "The quality {} must lie within 1 to 100",
+                self.quality
+            ));
+        }
+        match self.format {
+            Some(FrameFormat::Jpeg | FrameFormat::Avif) | None if self.lossless => Err(format!(
+                "Lossless output needs the webp or png format, not {}",
+                self.format
+                    .map_or_else(|| "the input's".to_string(), |format| format.to_string())
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether images are written as before the format could be chosen, each in
+    /// the format of its extension.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the path an image meant for `path` is written to, its extension replaced
+    /// by the format's.
+    pub fn output_path(&self, path: &Path) -> PathBuf {
+        match self.format {
+            Some(format) => path.with_extension(format.extension()),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Returns the encoding as a parameter of the cache keys; `None` for the default, so
+    /// outputs of earlier runs stay fresh.
+    pub fn cache_parameter(&self) -> Option<String> {
+        (!self.is_default()).then(|| self.to_string())
+    }
+
+    /// Encodes an image and writes it.
+    ///
+    /// # Parameters
+    /// - `image`: The image to write.
+    /// - `profile`: The color profile to embed, usually the one of the stage's input, so
+    ///   the colors keep their meaning.
+    /// - `path`: Where to write it, usually as given by `output_path`.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the image cannot be encoded, as a JPEG wider or higher
+    ///   than 65535 pixels, or the file cannot be written.
+    ///
+    /// # Notes
+    /// - PNG and JPEG images carry the profile; WebP and AVIF images, and images in other
+    ///   formats of their extension, are written without it.
+    pub fn save(
+        &self,
+        image: &DynamicImage,
+        profile: Option<&IccProfile>,
+        path: &Path,
+    ) -> Result<()> {
+        let format = match self.format {
+            Some(format) => Some(format),
+            // A PNG or JPEG of its extension is only written by hand to embed a profile.
+            None => match (ImageFormat::from_path(path), profile) {
+                (Ok(ImageFormat::Png), Some(_)) => Some(FrameFormat::Png),
+                (Ok(ImageFormat::Jpeg), Some(_)) => {
+                    return save_jpeg(image, (EXTENSION_JPEG_QUALITY, false), profile, path)
+                }
+                _ => None,
+            },
+        };
+        match format {
+            None => {
+                if profile.is_some() {
+                    debug!("Writing {} without its color profile", path.display());
+                }
+                image.save(path)?
+            }
+            Some(FrameFormat::Png) => match profile {
+                Some(profile) => save_png_with_profile(image, profile, path)?,
+                None => image.save_with_format(path, ImageFormat::Png)?,
+            },
+            Some(FrameFormat::Jpeg) => save_jpeg(image, (self.quality, true), profile, path)?,
+            Some(FrameFormat::Webp) => {
+                let rgba = image.to_rgba8();
+                let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                    .encode_simple(self.lossless, self.quality as f32)
+                    .map_err(|e| anyhow::anyhow!("Failed to encode WebP: {:?}", e))?;
+                fs::write(path, &*encoded)?;
+            }
+            Some(FrameFormat::Avif) => {
+                let rgba = image.to_rgba8();
+                AvifEncoder::new_with_speed_quality(create(path)?, AVIF_SPEED, self.quality)
+                    .write_image(&rgba, rgba.width(), rgba.height(), ExtendedColorType::Rgba8)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a PNG with a color profile, through the png crate since the image crate's
+/// encoder cannot embed one.
+///
+/// # Notes
+/// - The image is written as 8 or 16 bit RGB, with alpha if it has any.
+fn save_png_with_profile(image: &DynamicImage, profile: &IccProfile, path: &Path) -> Result<()> {
+    let alpha = image.color().has_alpha();
+    let sixteen_bit = is_deep_color(image);
+    let data = match (alpha, sixteen_bit) {
+        (true, false) => image.to_rgba8().into_raw(),
+        (false, false) => image.to_rgb8().into_raw(),
+        (true, true) => big_endian(image.to_rgba16().into_raw()),
+        (false, true) => big_endian(image.to_rgb16().into_raw()),
+    };
+    let mut info = png::Info::with_size(image.width(), image.height());
+    info.color_type = if alpha {
+        png::ColorType::Rgba
+    } else {
+        png::ColorType::Rgb
+    };
+    info.bit_depth = if sixteen_bit {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    };
+    info.icc_profile = Some(Cow::Borrowed(profile.bytes()));
+    let mut writer = png::Encoder::with_info(create(path)?, info)?.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Returns 16 bit samples as the big endian bytes PNG stores.
+fn big_endian(samples: Vec<u16>) -> Vec<u8> {
+    samples.into_iter().flat_map(u16::to_be_bytes).collect()
+}
+
+/// Writes a JPEG, dropping any alpha.
+///
+/// # Parameters
+/// - `(quality, progressive)`: The quality, from 1 to 100, and whether the JPEG is
+///   progressive rather than baseline.
+/// - `profile`: The color profile to embed, if any.
+fn save_jpeg(
+    image: &DynamicImage,
+    (quality, progressive): (u8, bool),
+    profile: Option<&IccProfile>,
+    path: &Path,
+) -> Result<()> {
+    let rgb = image.to_rgb8();
+    let (Ok(width), Ok(height)) = (u16::try_from(rgb.width()), u16::try_from(rgb.height())) else {
+        bail!(
+            "A JPEG cannot hold a {}x{} image",
+            rgb.width(),
+            rgb.height()
+        );
+    };
+    let mut encoder = jpeg_encoder::Encoder::new_file(path, quality)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    encoder.set_progressive(progressive);
+    if let Some(profile) = profile {
+        encoder.add_icc_profile(profile.bytes())?;
+    }
+    encoder.encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)?;
+    Ok(())
+}
+
+/// Creates the file an image is written to.
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
@@ -0,0 +1,15 @@
+mod channel;
+mod color;
+mod format;
+mod memory;
+
+pub use channel::{
+    frame_channel, FrameReceiver, FrameSender, MemoryBudget, StageStopped, StreamFrame,
+    CHANNEL_CAPACITY, DEFAULT_STREAM_MEMORY_BYTES,
+};
+pub use color::{has_deep_color, is_deep_color, ColorPrimaries, IccProfile};
+pub use format::{FrameEncoder, FrameFormat, DEFAULT_QUALITY};
+pub use memory::{
+    decoded_size, default_max_memory, system_memory, MemoryLimit, MemoryPermit,
+    FALLBACK_MAX_MEMORY_BYTES,
+};
@@ -0,0 +1,102 @@
+use log::debug;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+
+/// Memory the images decoded at the same time may take when the size of the system
+/// memory is unknown, 2 GiB.
+pub const FALLBACK_MAX_MEMORY_BYTES: usize = 2 * 1024 * 1024 * 1024;
+
+/// Limits the memory the images decoded at the same time take, by making the workers
+/// decoding them wait for each other.
+///
+/// Each worker asks for the bytes its images take once decoded before decoding them,
+/// and waits while the other workers hold too much of the limit.
+#[derive(Debug)]
+pub struct MemoryLimit {
+    limit: usize,
+    used: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Memory held from a `MemoryLimit` until it is dropped.
+#[derive(Debug)]
+pub struct MemoryPermit<'a> {
+    limit: &'a MemoryLimit,
+    bytes: usize,
+}
+
+impl MemoryLimit {
+    /// Creates a limit of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Returns the limit in bytes.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Holds `bytes` of the limit, waiting until the other permits leave room for them.
+    ///
+    /// # Notes
+    /// - A request larger than the whole limit waits until no other permit is held and
+    ///   then runs alone, so a single large image is slow but never refused.
+    pub fn acquire(&self, bytes: usize) -> MemoryPermit<'_> {
+        let mut used = self.used.lock().expect("a worker panicked");
+        while *used > 0 && *used + bytes > self.limit {
+            debug!(
+                "Waiting for {} bytes, {} of {} in use",
+                bytes, *used, self.limit
+            );
+            used = self.released.wait(used).expect("a worker panicked");
+        }
+        *used += bytes;
+        MemoryPermit { limit: self, bytes }
+    }
+}
+
+impl Drop for MemoryPermit<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut used) = self.limit.used.lock() {
+            *used -= self.bytes;
+        }
+        self.limit.released.notify_all();
+    }
+}
+
+/// Returns the size of the memory of the system, if it can be read.
+///
+/// # Notes
+/// - Read from `/proc/meminfo`, so only known on Linux.
+pub fn system_memory() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kilobytes: usize = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Returns the default memory limit of the decoding modes: half of the system memory,
+/// or `FALLBACK_MAX_MEMORY_BYTES` if its size is unknown.
+pub fn default_max_memory() -> usize {
+    system_memory().map_or(FALLBACK_MAX_MEMORY_BYTES, |bytes| bytes / 2)
+}
+
+/// Returns the bytes an image takes once decoded to RGBA, from its header.
+///
+/// # Notes
+/// - An image whose header cannot be read counts as 0 bytes; decoding it fails anyway.
+pub fn decoded_size(path: &Path) -> usize {
+    image::image_dimensions(path)
+        .map(|(width, height)| width as usize * height as usize * 4)
+        .unwrap_or(0)
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use fxp_output::{FailureKind, ToolError};
+
+/// Failures of the Visualizer that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum VisualizerError {
+    /// A tool the Visualizer runs is not installed or not on `PATH`.
+    #[error("{0} is not installed or not on PATH")]
+    ToolMissing(String),
+    /// A tool the Visualizer runs could not be started or exited unsuccessfully.
+    #[error("{tool} failed: {reason}")]
+    ToolFailed { tool: String, reason: String },
+    /// The run was stopped by Ctrl+C.
+    #[error("Rendering interrupted by user")]
+    Interrupted,
+}
+
+impl VisualizerError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            VisualizerError::ToolMissing(_) => FailureKind::ToolMissing,
+            VisualizerError::ToolFailed { .. } => FailureKind::ToolFailed,
+            VisualizerError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
+
+impl ToolError for VisualizerError {
+    fn missing(tool: &str) -> Self {
+        VisualizerError::ToolMissing(tool.to_string())
+    }
+
+    fn failed(tool: &str, reason: String) -> Self {
+        VisualizerError::ToolFailed {
+            tool: tool.to_string(),
+            reason,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod error;
+mod render;
+mod style;
+mod visualizer;
+
+pub use error::VisualizerError;
+pub use style::{FrameSize, Visualization};
+pub use visualizer::Visualizer;
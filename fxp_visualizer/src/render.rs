@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{atomic::AtomicBool, Arc};
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use fxp_output::FrameRate;
+use fxp_output::{kill_requested, ToolError};
+
+use crate::error::VisualizerError;
+use crate::style::{FrameSize, Visualization};
+
+/// Returns the ffmpeg filter graph drawing the audio input as video.
+///
+/// # Parameters
+/// - `visualization`: What to draw.
+/// - `size`: The size of the frames.
+/// - `fps`: The frame rate of the frames.
+/// - `color`: The color of the waveform; the spectrogram uses its own color scheme.
+///
+/// # Notes
+/// - `showwaves` draws on a transparent background, kept by the `rgba` format, so the
+///   Merger can composite the waves over frames with `--respect-alpha`.
+pub(crate) fn filter_graph(
+    visualization: Visualization,
+    size: FrameSize,
+    fps: FrameRate,
+    color: &str,
+) -> String {
+    match visualization {
+        Visualization::Waves => format!(
+            "[0:a]showwaves=s={}:mode=cline:rate={}:colors={},format=rgba[v]",
+            size,
+            fps.ffmpeg_arg(),
+            color
+        ),
+        Visualization::Spectrum => format!(
+            "[0:a]showspectrum=s={}:slide=scroll:color=intensity,fps={},format=rgb24[v]",
+            size,
+            fps.ffmpeg_arg()
+        ),
+    }
+}
+
+/// Renders the audio into numbered frames with ffmpeg.
+///
+/// # Parameters
+/// - `audio_path`: The audio file to visualize.
+/// - `output_dir`: The directory receiving the frames.
+/// - `frame_pattern`: Name pattern of the frames, e.g. `frame_%04d.png`; they are
+///   numbered from 1 like the Exporter's.
+/// - `filter_graph`: The graph returned by `filter_graph`.
+/// - `running`: Cleared to interrupt the rendering.
+///
+/// # Returns
+/// - `Result<()>`: An error if ffmpeg fails or the rendering was interrupted.
+pub(crate) fn render_frames(
+    audio_path: &Path,
+    output_dir: &Path,
+    frame_pattern: &str,
+    filter_graph: &str,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let _span =
+        info_span!("render", audio = %audio_path.display(), filter = %filter_graph).entered();
+    let mut child = Command::new("ffmpeg")
+        .args(["-y", "-i"])
+        .arg(audio_path)
+        .args([
+            "-filter_complex",
+            filter_graph,
+            "-map",
+            "[v]",
+            "-start_number",
+            "1",
+        ])
+        .arg(output_dir.join(frame_pattern))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| VisualizerError::spawn("ffmpeg", e))
+        .context("Failed to start ffmpeg to render the visualization")?;
+
+    loop {
+        if kill_requested(&running) {
+            debug!("Interruption requested; terminating ffmpeg process.");
+            child.kill().ok();
+            return Err(VisualizerError::Interrupted.into());
+        }
+        match child.try_wait()? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => {
+                debug!("FFmpeg command failed with status: {:?}", status);
+                return Err(VisualizerError::ToolFailed {
+                    tool: "ffmpeg".to_string(),
+                    reason: status.to_string(),
+                })
+                .context("ffmpeg failed to render the visualization");
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// What the Visualizer draws of the audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visualization {
+    /// The waveform, drawn on a transparent background so it can be laid over frames.
+    #[default]
+    Waves,
+    /// A scrolling spectrogram on an opaque background.
+    Spectrum,
+}
+
+impl FromStr for Visualization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "waves" => Ok(Visualization::Waves),
+            "spectrum" => Ok(Visualization::Spectrum),
+            _ => Err(format!(
+                "Unknown visualization '{}', expected waves or spectrum",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Visualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visualization::Waves => write!(f, "waves"),
+            Visualization::Spectrum => write!(f, "spectrum"),
+        }
+    }
+}
+
+/// Width and height of the rendered frames, written as `WIDTHxHEIGHT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for FrameSize {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+        }
+    }
+}
+
+impl FromStr for FrameSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid size '{}', expected WIDTHxHEIGHT, e.g. 1280x720", s);
+        let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+        let width: u32 = width.parse().map_err(|_| invalid())?;
+        let height: u32 = height.parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { width, height })
+    }
+}
+
+impl fmt::Display for FrameSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::FrameRate;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::{FileOperations, FramePadding};
+
+use crate::render::{filter_graph, render_frames};
+use crate::style::{FrameSize, Visualization};
+
+/// Color of the waveform, see `Visualizer::color`.
+pub const DEFAULT_COLOR: &str = "white";
+
+/// Struct responsible for rendering the audio as a sequence of frames.
+///
+/// The frames have the Exporter's names and rate, so the Merger can composite them over
+/// the exported frames, or the Clipper can encode them on their own.
+#[derive(Debug, Clone)]
+pub struct Visualizer {
+    audio_path: PathBuf,
+    output_directory: PathBuf,
+    fps: FrameRate,
+    /// What is drawn of the audio; `new` sets `Visualization::Waves`.
+    pub visualization: Visualization,
+    /// Size of the frames; `new` sets 1280x720. The Merger resizes them to its first
+    /// directory's frames anyway.
+    pub size: FrameSize,
+    /// An ffmpeg color for the waveform, e.g. `white` or `0x00ff88`; `new` sets
+    /// `DEFAULT_COLOR`.
+    pub color: String,
+    /// Write straight into the output directory instead of staging it; `new` sets `false`.
+    pub in_place: bool,
+    /// Length of the audio in milliseconds, which sizes the padding of the frame numbers
+    /// past 9999 frames; `new` sets `None`, padding to four digits.
+    pub duration_ms: Option<u64>,
+}
+
+impl Visualizer {
+    /// Creates a new `Visualizer` for an audio file.
+    ///
+    /// # Parameters
+    /// - `audio_path`: The audio file to visualize.
+    /// - `output_directory`: Optional path for the frames; defaults to `<audio_stem>_visualized`
+    ///   next to the audio.
+    /// - `fps`: The frame rate of the frames, usually the rate the video was exported at.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The configured `Visualizer`, or an error if the audio does not exist.
+    pub fn new(
+        audio_path: String,
+        output_directory: Option<String>,
+        fps: FrameRate,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        let audio_path = PathBuf::from(audio_path);
+        let audio_path = fs::canonicalize(&audio_path)
+            .with_context(|| format!("Failed to resolve audio '{}'", audio_path.display()))?;
+
+        let mode: Modes = Modes::Visualizer;
+        let output: Output = mode.into();
+        let output_directory = match output {
+            Output::Visualizer(visualizer_output) => visualizer_output
+                .create_output((audio_path.clone(), output_directory), collision)?,
+            _ => unreachable!("Expected Visualizer mode"),
+        };
+        debug!("Visualizing {:?} into {:?}", audio_path, output_directory);
+
+        Ok(Self {
+            audio_path,
+            output_directory,
+            fps,
+            visualization: Visualization::default(),
+            size: FrameSize::default(),
+            color: DEFAULT_COLOR.to_string(),
+            in_place: false,
+            duration_ms: None,
+        })
+    }
+
+    /// Resolves what `new` and `visualize` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `audio_path`: The audio file to visualize.
+    /// - `output_directory`: Optional path for the frames.
+    /// - `fps`: The frame rate of the frames.
+    /// - `visualization`: What to draw of the audio.
+    /// - `size`: The size of the frames.
+    /// - `color`: The color of the waveform.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the output cannot be resolved.
+    pub fn plan(
+        audio_path: String,
+        output_directory: Option<String>,
+        fps: FrameRate,
+        visualization: Visualization,
+        size: FrameSize,
+        color: &str,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let audio_path = PathBuf::from(audio_path);
+
+        let mode: Modes = Modes::Visualizer;
+        let output: Output = mode.into();
+        let output_directory = match output {
+            Output::Visualizer(visualizer_output) => {
+                visualizer_output.plan_output((audio_path.clone(), output_directory), collision)?
+            }
+            _ => unreachable!("Expected Visualizer mode"),
+        };
+
+        let mut plan = Plan::new(Modes::Visualizer)
+            .entry("input audio", audio_path.display())
+            .entry("visualization", visualization)
+            .entry("size", size)
+            .entry("fps", fps);
+        if visualization == Visualization::Waves {
+            plan = plan.entry("color", color);
+        }
+        Ok(plan
+            .entry("filter", filter_graph(visualization, size, fps, color))
+            .entry("on existing output", collision)
+            .entry("output directory", output_directory.display()))
+    }
+}
+
+impl Visualizer {
+    /// Renders the audio into `frame_0001.png`, `frame_0002.png`, ... at the frame rate,
+    /// padded to five digits or more if `duration_ms` holds over 9999 frames.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of frames written, or an error if ffmpeg fails or
+    ///   the process was interrupted.
+    ///
+    /// # Notes
+    /// - The waveform keeps a transparent background; merge it over the exported frames
+    ///   with the Merger's `--respect-alpha` to draw only the waves.
+    /// - Frames are staged and moved into the output directory only once all of them
+    ///   are written, unless `in_place` is set; see `fxp_output::StagedDirectory`.
+    /// - Handles Ctrl+C interruptions gracefully.
+    /// - Writes a `manifest.json` recording the settings and the audio hash.
+    pub fn visualize(&self) -> Result<usize> {
+        let _span = info_span!(
+            "visualizer",
+            audio = %self.audio_path.display(),
+            output = %self.output_directory.display(),
+            fps = %self.fps
+        )
+        .entered();
+        let mut manifest = Manifest::new(Modes::Visualizer)
+            .parameter("audio", self.audio_path.display())
+            .parameter("fps", self.fps)
+            .parameter("visualization", self.visualization)
+            .parameter("size", self.size)
+            .inputs([&self.audio_path]);
+        if self.visualization == Visualization::Waves {
+            manifest = manifest.parameter("color", &self.color);
+        }
+
+        let running = running_flag()?;
+
+        let mut staged = StagedDirectory::begin(&self.output_directory, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let graph = filter_graph(self.visualization, self.size, self.fps, &self.color);
+        let padding = self
+            .duration_ms
+            .map(|duration_ms| FramePadding::for_count(self.fps.frames_in(duration_ms) + 1))
+            .unwrap_or_default();
+        let pattern = padding.pattern("frame", "png");
+        render_frames(&self.audio_path, staged.path(), &pattern, &graph, running)?;
+
+        let written = count_frames(staged.path())?;
+        debug!("Rendered {} frames into {:?}", written, staged.path());
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Visualizer, written)?;
+
+        Ok(written)
+    }
+}
+
+/// Counts the image files of a directory, skipping hidden files and the manifest.
+fn count_frames(dir: &Path) -> Result<usize> {
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    Ok(Modes::Visualizer.load_files(&files)?.len())
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use fxp_output::FailureKind;
+
+/// Failures of the Zoopraxiscope that callers may tell apart, see `kind`.
+#[derive(Debug, Error)]
+pub enum ZoopraxiscopeError {
+    /// The input holds no frames.
+    #[error("No frames found in {}", .0.display())]
+    NoFrames(PathBuf),
+    /// The input does not exist or is not what the Zoopraxiscope takes.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The run was stopped by Ctrl+C.
+    #[error("Zoopraxiscope interrupted by user")]
+    Interrupted,
+}
+
+impl ZoopraxiscopeError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ZoopraxiscopeError::NoFrames(_) => FailureKind::NoFrames,
+            ZoopraxiscopeError::InvalidInput(_) => FailureKind::InvalidInput,
+            ZoopraxiscopeError::Interrupted => FailureKind::Interrupted,
+        }
+    }
+}
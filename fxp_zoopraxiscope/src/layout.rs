@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Color of the padding and of the cells no frame fills.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Background {
+    /// Fully transparent, for web animations and texture atlases.
+    #[default]
+    Transparent,
+    /// An opaque or translucent color.
+    Color(Rgba<u8>),
+}
+
+impl Background {
+    /// Returns the pixel the background is filled with.
+    pub fn pixel(&self) -> Rgba<u8> {
+        match self {
+            Background::Transparent => Rgba([0, 0, 0, 0]),
+            Background::Color(color) => *color,
+        }
+    }
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    /// Parses `transparent`, `black`, `white` or a hex color, `#rrggbb` or `#rrggbbaa`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid background '{}', expected transparent, black, white or a color such as #202020 or #20202080",
+                s
+            )
+        };
+        match s.to_ascii_lowercase().as_str() {
+            "transparent" => return Ok(Background::Transparent),
+            "black" => return Ok(Background::Color(Rgba([0, 0, 0, 255]))),
+            "white" => return Ok(Background::Color(Rgba([255, 255, 255, 255]))),
+            _ => {}
+        }
+        let hex = s.trim_start_matches('#');
+        if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+            return Err(invalid());
+        }
+        let channel = |index: usize| {
+            hex.get(index * 2..index * 2 + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        };
+        let alpha = if hex.len() == 8 {
+            channel(3)
+        } else {
+            Some(255)
+        };
+        match (channel(0), channel(1), channel(2), alpha) {
+            (Some(r), Some(g), Some(b), Some(a)) => Ok(Background::Color(Rgba([r, g, b, a]))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Background::Transparent => write!(f, "transparent"),
+            Background::Color(Rgba([r, g, b, 255])) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Background::Color(Rgba([r, g, b, a])) => {
+                write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+            }
+        }
+    }
+}
+
+/// How the frames are laid out on a sprite sheet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SheetLayout {
+    /// Frames per row; `None` lays the frames out as a square, or reads the columns from
+    /// the atlas when exploding. 1 row of all frames is a filmstrip.
+    pub columns: Option<u32>,
+    /// Rows of a sheet exploded without an atlas; unused when packing.
+    pub rows: Option<u32>,
+    /// Pixels around and between the frames.
+    pub padding: u32,
+    /// Color of the padding and of the empty cells.
+    pub background: Background,
+}
+
+impl SheetLayout {
+    /// Returns the columns and rows of a sheet of `frames` frames.
+    pub fn grid(&self, frames: u32) -> (u32, u32) {
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (frames as f64).sqrt().ceil() as u32)
+            .clamp(1, frames.max(1));
+        (columns, frames.div_ceil(columns).max(1))
+    }
+}
+
+/// Where each frame is on a sprite sheet, written next to it as `<sheet_stem>.json` for
+/// web animations and texture atlases, and read back to explode the sheet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Atlas {
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub padding: u32,
+    pub frames: Vec<AtlasFrame>,
+}
+
+/// One frame of an `Atlas`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasFrame {
+    /// Frame number, from 1 in playing order.
+    pub frame: u32,
+    /// Top-left corner of the frame on the sheet.
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Atlas {
+    /// Lays out `frames` frames of `frame_width` by `frame_height` row by row.
+    pub fn new(frame_width: u32, frame_height: u32, frames: u32, layout: &SheetLayout) -> Self {
+        let (columns, rows) = layout.grid(frames);
+        let padding = layout.padding;
+        let frames = (0..frames)
+            .map(|index| AtlasFrame {
+                frame: index + 1,
+                x: padding + (index % columns) * (frame_width + padding),
+                y: padding + (index / columns) * (frame_height + padding),
+            })
+            .collect();
+        Self {
+            frame_width,
+            frame_height,
+            columns,
+            rows,
+            padding,
+            frames,
+        }
+    }
+
+    /// Returns the size of the sheet.
+    pub fn sheet_size(&self) -> (u32, u32) {
+        (
+            self.padding + self.columns * (self.frame_width + self.padding),
+            self.padding + self.rows * (self.frame_height + self.padding),
+        )
+    }
+
+    /// Reads the atlas written next to a sheet.
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read atlas {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Malformed atlas {}", path.display()))
+    }
+
+    /// Writes the atlas as pretty JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize atlas")?;
+        fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write atlas {}", path.display()))
+    }
+}
+
+/// Returns where the atlas of a sheet is written: `<sheet_stem>.json` next to it.
+pub fn atlas_path(sheet: &Path) -> PathBuf {
+    sheet.with_extension("json")
+}
@@ -0,0 +1,8 @@
+mod error;
+mod layout;
+mod sheet;
+mod zoopraxiscope;
+
+pub use error::ZoopraxiscopeError;
+pub use layout::{atlas_path, Atlas, Background, SheetLayout};
+pub use zoopraxiscope::Zoopraxiscope;
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, bail, Context, Result};
+use image::imageops::{self, FilterType};
+use image::{GenericImageView, RgbaImage};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use fxp_filenames::FramePadding;
+use fxp_output::progress_bar;
+
+use crate::error::ZoopraxiscopeError;
+use crate::layout::{Atlas, SheetLayout};
+
+/// Tiles the frames, in frame number order, into a sprite sheet.
+///
+/// # Parameters
+/// - `frames`: The frames mapped by frame number.
+/// - `layout`: The columns, padding and background of the sheet.
+/// - `running`: Flag cleared by Ctrl+C to stop between frames.
+///
+/// # Returns
+/// - `Result<(RgbaImage, Atlas)>`: The sheet and where each frame is on it, or an error
+///   if a frame cannot be decoded or the run was interrupted.
+///
+/// # Notes
+/// - Every cell has the size of the first frame; frames of another size are scaled to it.
+pub fn pack(
+    frames: &BTreeMap<u32, PathBuf>,
+    layout: &SheetLayout,
+    running: &AtomicBool,
+) -> Result<(RgbaImage, Atlas)> {
+    let first = frames
+        .values()
+        .next()
+        .ok_or_else(|| anyhow!("No frames to tile into a sprite sheet"))?;
+    let (frame_width, frame_height) = image::image_dimensions(first)
+        .with_context(|| format!("Failed to read frame {}", first.display()))?;
+    let atlas = Atlas::new(frame_width, frame_height, frames.len() as u32, layout);
+    let (width, height) = atlas.sheet_size();
+    debug!(
+        "Tiling {} frames of {}x{} into a {}x{} sheet of {} columns",
+        frames.len(),
+        frame_width,
+        frame_height,
+        width,
+        height,
+        atlas.columns
+    );
+
+    let mut sheet = RgbaImage::from_pixel(width, height, layout.background.pixel());
+    let pb = styled_progress_bar(frames.len())?;
+    for (path, cell) in frames.values().zip(&atlas.frames) {
+        if !running.load(Ordering::SeqCst) {
+            return Err(ZoopraxiscopeError::Interrupted.into());
+        }
+        let mut frame = image::open(path)
+            .with_context(|| format!("Failed to decode frame {}", path.display()))?
+            .into_rgba8();
+        if frame.dimensions() != (frame_width, frame_height) {
+            frame = imageops::resize(&frame, frame_width, frame_height, FilterType::Lanczos3);
+        }
+        imageops::replace(&mut sheet, &frame, cell.x as i64, cell.y as i64);
+        pb.inc(1);
+    }
+    pb.finish();
+    Ok((sheet, atlas))
+}
+
+/// Cuts the frames out of a sprite sheet and writes them as `frame_0001.png` and on.
+///
+/// # Parameters
+/// - `sheet`: The sprite sheet.
+/// - `atlas`: Where each frame is on the sheet.
+/// - `output_dir`: Directory the frames are written to.
+/// - `running`: Flag cleared by Ctrl+C to stop between frames.
+///
+/// # Returns
+/// - `Result<usize>`: The number of frames written, or an error if a frame lies outside
+///   the sheet, cannot be written or the run was interrupted.
+pub fn explode(
+    sheet: &RgbaImage,
+    atlas: &Atlas,
+    output_dir: &Path,
+    running: &AtomicBool,
+) -> Result<usize> {
+    let pb = styled_progress_bar(atlas.frames.len())?;
+    let last = atlas
+        .frames
+        .iter()
+        .map(|cell| cell.frame)
+        .max()
+        .unwrap_or(0);
+    let padding = FramePadding::for_count(u64::from(last));
+    for cell in &atlas.frames {
+        if !running.load(Ordering::SeqCst) {
+            return Err(ZoopraxiscopeError::Interrupted.into());
+        }
+        if cell.x + atlas.frame_width > sheet.width()
+            || cell.y + atlas.frame_height > sheet.height()
+        {
+            bail!(
+                "Frame {} at {},{} lies outside the {}x{} sheet",
+                cell.frame,
+                cell.x,
+                cell.y,
+                sheet.width(),
+                sheet.height()
+            );
+        }
+        let path = output_dir.join(padding.file_name("frame", u64::from(cell.frame), "png"));
+        sheet
+            .view(cell.x, cell.y, atlas.frame_width, atlas.frame_height)
+            .to_image()
+            .save(&path)
+            .with_context(|| format!("Failed to write frame {}", path.display()))?;
+        pb.inc(1);
+    }
+    pb.finish();
+    Ok(atlas.frames.len())
+}
+
+/// Lays out the cells of a sheet that has no atlas from its columns and rows.
+///
+/// # Parameters
+/// - `sheet`: The sprite sheet.
+/// - `layout`: The columns, rows and padding the sheet was tiled with.
+///
+/// # Returns
+/// - `Result<Atlas>`: The cells, or an error if the columns or rows are missing or do
+///   not divide the sheet into cells of equal size.
+///
+/// # Notes
+/// - Trailing cells filled with nothing but the background, the empty cells of the last
+///   row, are left out.
+pub fn grid_atlas(sheet: &RgbaImage, layout: &SheetLayout) -> Result<Atlas> {
+    let (Some(columns), Some(rows)) = (layout.columns, layout.rows) else {
+        bail!("A sprite sheet without an atlas needs --columns and --rows");
+    };
+    let cell_size = |length: u32, cells: u32| -> Option<u32> {
+        let frames_length = length.checked_sub(layout.padding * (cells + 1))?;
+        (frames_length > 0 && frames_length.is_multiple_of(cells)).then(|| frames_length / cells)
+    };
+    let (frame_width, frame_height) = match (
+        cell_size(sheet.width(), columns),
+        cell_size(sheet.height(), rows),
+    ) {
+        (Some(width), Some(height)) => (width, height),
+        _ => bail!(
+            "A {}x{} sheet does not divide into {} columns and {} rows with {} pixels of padding",
+            sheet.width(),
+            sheet.height(),
+            columns,
+            rows,
+            layout.padding
+        ),
+    };
+
+    let mut atlas = Atlas::new(
+        frame_width,
+        frame_height,
+        columns * rows,
+        &SheetLayout {
+            columns: Some(columns),
+            ..*layout
+        },
+    );
+    let background = layout.background.pixel();
+    while let Some(cell) = atlas.frames.last() {
+        let empty = sheet
+            .view(cell.x, cell.y, frame_width, frame_height)
+            .pixels()
+            .all(|(_, _, pixel)| pixel == background);
+        if !empty {
+            break;
+        }
+        atlas.frames.pop();
+    }
+    debug!(
+        "Found {} frames of {}x{} on the sheet",
+        atlas.frames.len(),
+        frame_width,
+        frame_height
+    );
+    Ok(atlas)
+}
+
+fn styled_progress_bar(total: usize) -> Result<ProgressBar> {
+    let pb = progress_bar(total as u64);
+    pb.set_style(ProgressStyle::default_bar().template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+    )?);
+    Ok(pb)
+}
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use image::ImageFormat;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use tracing::info_span;
+
+use fxp_modes::Modes;
+use fxp_output::running_flag;
+use fxp_output::CollisionPolicy;
+use fxp_output::Manifest;
+use fxp_output::ModeOutput;
+use fxp_output::Output;
+use fxp_output::Plan;
+use fxp_output::StagedDirectory;
+
+use fxp_filenames::FileOperations;
+
+use crate::error::ZoopraxiscopeError;
+use crate::layout::{atlas_path, Atlas, SheetLayout};
+use crate::sheet::{explode, grid_atlas, pack};
+
+/// Struct responsible for tiling a directory of frames into a sprite sheet, or for
+/// exploding a sprite sheet back into frames.
+#[derive(Debug, Clone)]
+pub struct Zoopraxiscope {
+    input_path: PathBuf,
+    output_path: PathBuf,
+    explode: bool,
+    /// Columns, rows, padding and background of the sheet; `new` sets a square grid
+    /// without padding on a transparent background.
+    pub layout: SheetLayout,
+    /// Write the exploded frames straight into the output directory instead of staging
+    /// it; `new` sets `false`.
+    pub in_place: bool,
+}
+
+impl Zoopraxiscope {
+    /// Creates a new `Zoopraxiscope`.
+    ///
+    /// # Parameters
+    /// - `input`: The directory of frames to tile, or the sprite sheet to explode.
+    /// - `output`: Optional output; defaults to `<input_name>_sheet.png`, or to
+    ///   `<sheet_stem>_frames` when exploding, next to the input.
+    /// - `explode`: Whether to explode a sprite sheet back into frames.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Self>`: The configured `Zoopraxiscope`, or an error if the input is not
+    ///   a directory, or not a file when exploding.
+    pub fn new(
+        input: String,
+        output: Option<String>,
+        explode: bool,
+        collision: CollisionPolicy,
+    ) -> Result<Self> {
+        let input_path = canonical_input(&input, explode)?;
+
+        let mode: Modes = Modes::Zoopraxiscope;
+        let output_enum: Output = mode.into();
+        let output_path = match output_enum {
+            Output::Zoopraxiscope(zoopraxiscope_output) => zoopraxiscope_output
+                .create_output((input_path.clone(), output, explode), collision)?,
+            _ => unreachable!("Expected Zoopraxiscope mode"),
+        };
+        debug!("Zoopraxiscope from {:?} to {:?}", input_path, output_path);
+
+        Ok(Self {
+            input_path,
+            output_path,
+            explode,
+            layout: SheetLayout::default(),
+            in_place: false,
+        })
+    }
+
+    /// Resolves what `new` and `run` would do, without touching the filesystem.
+    ///
+    /// # Parameters
+    /// - `input`: The directory of frames to tile, or the sprite sheet to explode.
+    /// - `output`: Optional output.
+    /// - `explode`: Whether to explode a sprite sheet back into frames.
+    /// - `layout`: The layout of the sheet.
+    /// - `collision`: What to do if the output already exists.
+    ///
+    /// # Returns
+    /// - `Result<Plan>`: The resolved plan, or an error if the input is missing or its
+    ///   frames or atlas cannot be read.
+    pub fn plan(
+        input: String,
+        output: Option<String>,
+        explode: bool,
+        layout: &SheetLayout,
+        collision: CollisionPolicy,
+    ) -> Result<Plan> {
+        let input_path = canonical_input(&input, explode)?;
+
+        let mode: Modes = Modes::Zoopraxiscope;
+        let output_enum: Output = mode.into();
+        let output_path = match output_enum {
+            Output::Zoopraxiscope(zoopraxiscope_output) => zoopraxiscope_output
+                .plan_output((input_path.clone(), output, explode), collision)?,
+            _ => unreachable!("Expected Zoopraxiscope mode"),
+        };
+
+        if explode {
+            let atlas = atlas_path(&input_path);
+            let plan = Plan::new(Modes::Zoopraxiscope)
+                .entry("input sheet", input_path.display())
+                .entry("direction", "explode into frames");
+            let plan = if atlas.is_file() {
+                let recorded = Atlas::read(&atlas)?;
+                plan.entry("atlas", atlas.display())
+                    .entry("frames", recorded.frames.len())
+                    .entry(
+                        "frame size",
+                        format!("{}x{}", recorded.frame_width, recorded.frame_height),
+                    )
+            } else {
+                let (columns, rows) = (layout.columns.unwrap_or(0), layout.rows.unwrap_or(0));
+                plan.entry("atlas", "none, cells from --columns and --rows")
+                    .entry("grid", format!("{} columns, {} rows", columns, rows))
+                    .entry("frames", format!("up to {}", columns * rows))
+                    .entry("padding", format!("{} px", layout.padding))
+            };
+            return Ok(plan
+                .entry("on existing output", collision)
+                .entry("output directory", output_path.display()));
+        }
+
+        let frames = load_frames(&input_path)?;
+        let (columns, rows) = layout.grid(frames.len() as u32);
+        let mut plan = Plan::new(Modes::Zoopraxiscope)
+            .entry("input directory", input_path.display())
+            .entry("direction", "tile into a sprite sheet")
+            .entry("frames", frames.len())
+            .entry("grid", format!("{} columns, {} rows", columns, rows))
+            .entry("padding", format!("{} px", layout.padding))
+            .entry("background", layout.background);
+        if let Some(first) = frames.values().next() {
+            let (width, height) = image::image_dimensions(first)
+                .with_context(|| format!("Failed to read frame {}", first.display()))?;
+            let atlas = Atlas::new(width, height, frames.len() as u32, layout);
+            let (sheet_width, sheet_height) = atlas.sheet_size();
+            plan = plan
+                .entry("frame size", format!("{}x{}", width, height))
+                .entry("sheet size", format!("{}x{}", sheet_width, sheet_height));
+        }
+        Ok(plan
+            .entry("atlas", atlas_path(&output_path).display())
+            .entry("on existing output", collision)
+            .entry("output sheet", output_path.display()))
+    }
+}
+
+impl Zoopraxiscope {
+    /// Tiles the frames into a sprite sheet, or explodes the sheet into frames.
+    ///
+    /// # Returns
+    /// - `Result<usize>`: The number of frames tiled or exploded, or an error if a frame
+    ///   cannot be read or written or the run was interrupted.
+    ///
+    /// # Notes
+    /// - A sheet is written as PNG with its atlas next to it, `<sheet_stem>.json`, holding
+    ///   the size and position of every frame for web animations and texture atlases.
+    /// - A sheet is exploded by its atlas if it has one, otherwise by `layout.columns`
+    ///   and `layout.rows`; the frames are written as `frame_0001.png` and on.
+    /// - The sheet is written under a temporary name and renamed into place once complete;
+    ///   the exploded frames are staged, unless `in_place` is set, see
+    ///   `fxp_output::StagedDirectory`.
+    /// - Handles Ctrl+C interruptions gracefully.
+    /// - Writes a manifest recording the layout and the input hashes.
+    pub fn run(&self) -> Result<usize> {
+        let _span = info_span!(
+            "zoopraxiscope",
+            input = %self.input_path.display(),
+            output = %self.output_path.display()
+        )
+        .entered();
+        let running = running_flag()?;
+
+        let mut manifest = Manifest::new(Modes::Zoopraxiscope)
+            .parameter("input", self.input_path.display())
+            .parameter("padding", self.layout.padding)
+            .parameter("background", self.layout.background);
+        if let Some(columns) = self.layout.columns {
+            manifest = manifest.parameter("columns", columns);
+        }
+
+        if self.explode {
+            self.explode_sheet(manifest, &running)
+        } else {
+            self.tile_frames(manifest, &running)
+        }
+    }
+
+    fn tile_frames(&self, manifest: Manifest, running: &AtomicBool) -> Result<usize> {
+        let frames = load_frames(&self.input_path)?;
+        let manifest = manifest.inputs(frames.values());
+        let (sheet, atlas) = pack(&frames, &self.layout, running)?;
+
+        // Renamed into place once complete, so the output is never a partial sheet.
+        let mut part_name = self.output_path.as_os_str().to_os_string();
+        part_name.push(".part");
+        let part = PathBuf::from(part_name);
+        sheet
+            .save_with_format(&part, ImageFormat::Png)
+            .with_context(|| format!("Failed to write sprite sheet {}", part.display()))?;
+        fs::rename(&part, &self.output_path).with_context(|| {
+            format!(
+                "Failed to move sprite sheet into place at {}",
+                self.output_path.display()
+            )
+        })?;
+        atlas.write(&atlas_path(&self.output_path))?;
+        manifest.write(&self.output_path)?;
+        debug!(
+            "Tiled {} frames into {:?}",
+            atlas.frames.len(),
+            self.output_path
+        );
+        Ok(atlas.frames.len())
+    }
+
+    fn explode_sheet(&self, manifest: Manifest, running: &AtomicBool) -> Result<usize> {
+        let mut manifest = manifest
+            .parameter("explode", true)
+            .inputs([&self.input_path]);
+        if let Some(rows) = self.layout.rows {
+            manifest = manifest.parameter("rows", rows);
+        }
+        let sheet = image::open(&self.input_path)
+            .with_context(|| format!("Failed to decode sheet {}", self.input_path.display()))?
+            .into_rgba8();
+        let atlas_file = atlas_path(&self.input_path);
+        let atlas = if atlas_file.is_file() {
+            debug!("Exploding by the atlas {:?}", atlas_file);
+            Atlas::read(&atlas_file)?
+        } else {
+            grid_atlas(&sheet, &self.layout)?
+        };
+
+        let mut staged = StagedDirectory::begin(&self.output_path, self.in_place)?;
+
+        staged.keep_partial(&manifest);
+        let written = explode(&sheet, &atlas, staged.path(), running)?;
+        manifest.write(staged.path())?;
+        staged.finish(Modes::Zoopraxiscope, written)?;
+        debug!("Exploded {} frames into {:?}", written, self.output_path);
+        Ok(written)
+    }
+}
+
+/// Checks that the input is a directory, or a file when exploding, and canonicalizes it.
+fn canonical_input(input: &str, explode: bool) -> Result<PathBuf> {
+    let input_path = PathBuf::from(input);
+    if explode && !input_path.is_file() {
+        return Err(ZoopraxiscopeError::InvalidInput(format!(
+            "Sprite sheet '{}' does not exist",
+            input_path.display()
+        ))
+        .into());
+    }
+    if !explode && !input_path.is_dir() {
+        return Err(ZoopraxiscopeError::InvalidInput(format!(
+            "Input directory '{}' does not exist or is not a directory",
+            input_path.display()
+        ))
+        .into());
+    }
+    fs::canonicalize(&input_path)
+        .with_context(|| format!("Failed to resolve input '{}'", input_path.display()))
+}
+
+/// Maps the frames of a directory by their frame number.
+fn load_frames(input_directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let input_images: Vec<PathBuf> = fs::read_dir(input_directory)
+        .with_context(|| format!("Failed to read {}", input_directory.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    let frames = Modes::Zoopraxiscope.load_files(&input_images)?;
+    if frames.is_empty() {
+        return Err(ZoopraxiscopeError::NoFrames(input_directory.to_path_buf()).into());
+    }
+    Ok(frames)
+}
@@ -0,0 +1,347 @@
+use anyhow::{bail, Context, Result};
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fxp_filenames::FileOperations;
+use fxp_modes::Modes;
+
+use crate::probe::ProbeFormat;
+
+/// Rec. 709 weights of red, green and blue in the luma.
+const LUMA: [f64; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Size of the plots written with `--plots`.
+const PLOT_WIDTH: u32 = 512;
+const PLOT_HEIGHT: u32 = 200;
+
+/// Levels of one channel, summed over every frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelStats {
+    /// Pixels at each of the 256 levels.
+    pub histogram: Vec<u64>,
+    /// Share of pixels at or below the clip level, in percent.
+    pub clipped_low: f64,
+    /// Share of pixels at or above 255 minus the clip level, in percent.
+    pub clipped_high: f64,
+}
+
+/// Luminance and clipping of one frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameStats {
+    pub index: u32,
+    pub path: PathBuf,
+    /// Mean luma of the pixels, from 0 to 255.
+    pub average_luminance: f64,
+    /// Share of pixels with any channel at or below the clip level, in percent.
+    pub clipped_low: f64,
+    /// Share of pixels with any channel at or above 255 minus the clip level, in percent.
+    pub clipped_high: f64,
+}
+
+/// The report of `analyze` for a directory of frames.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisReport {
+    pub path: PathBuf,
+    /// Levels this close to 0 or 255 count as clipped.
+    pub clip_level: u8,
+    pub pixels: u64,
+    /// Mean luma of all pixels, from 0 to 255.
+    pub average_luminance: f64,
+    /// Share of all pixels with any channel clipped low, in percent.
+    pub clipped_low: f64,
+    /// Share of all pixels with any channel clipped high, in percent.
+    pub clipped_high: f64,
+    pub red: ChannelStats,
+    pub green: ChannelStats,
+    pub blue: ChannelStats,
+    pub frames: Vec<FrameStats>,
+    /// Frames that could not be decoded, e.g. files that are not images.
+    pub unreadable: Vec<PathBuf>,
+    /// The plots written, if any.
+    pub plots: Vec<PathBuf>,
+}
+
+/// Running sums of the pixels read so far.
+#[derive(Default)]
+struct Totals {
+    histograms: [Vec<u64>; 3],
+    pixels: u64,
+    luma: f64,
+    clipped_low: u64,
+    clipped_high: u64,
+}
+
+/// Computes the histograms, luminance and clipping of a directory of frames.
+///
+/// # Parameters
+/// - `directory`: The directory of frames to analyze.
+/// - `clip_level`: Levels up to this far from 0 or 255 count as clipped; 0 counts only
+///   the extremes themselves.
+/// - `plots`: A directory to write `histogram.png` and `luminance.png` into, if any.
+///
+/// # Returns
+/// - `Result<AnalysisReport>`: The report, or an error if the directory cannot be read,
+///   holds no frames, or a plot cannot be written.
+///
+/// # Notes
+/// - Frames are numbered as the modes number them, see `fxp_filenames::FileOperations`.
+/// - Every pixel of every frame is read; frames that cannot be decoded are listed and
+///   left out of the figures.
+pub fn analyze(directory: &Path, clip_level: u8, plots: Option<&Path>) -> Result<AnalysisReport> {
+    if !directory.is_dir() {
+        bail!(
+            "Nothing to analyze at {}, expected a directory of frames",
+            directory.display()
+        );
+    }
+    let files: Vec<PathBuf> = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    let frames: BTreeMap<u32, PathBuf> = Modes::Clipper
+        .load_files(&files)
+        .with_context(|| format!("Failed to number the frames in {}", directory.display()))?;
+    if frames.is_empty() {
+        bail!("No frames found in {}", directory.display());
+    }
+
+    let low = clip_level;
+    let high = 255 - clip_level;
+    let mut totals = Totals {
+        histograms: [vec![0; 256], vec![0; 256], vec![0; 256]],
+        ..Totals::default()
+    };
+    let mut frame_stats = Vec::with_capacity(frames.len());
+    let mut unreadable = Vec::new();
+    for (&index, path) in &frames {
+        let image = match image::open(path) {
+            Ok(image) => image.to_rgb8(),
+            Err(_) => {
+                unreadable.push(path.clone());
+                continue;
+            }
+        };
+        let (mut luma, mut clipped_low, mut clipped_high) = (0.0, 0, 0);
+        for pixel in image.pixels() {
+            for (histogram, level) in totals.histograms.iter_mut().zip(pixel.0) {
+                histogram[level as usize] += 1;
+            }
+            luma += pixel
+                .0
+                .iter()
+                .zip(LUMA)
+                .map(|(level, weight)| *level as f64 * weight)
+                .sum::<f64>();
+            clipped_low += pixel.0.iter().any(|level| *level <= low) as u64;
+            clipped_high += pixel.0.iter().any(|level| *level >= high) as u64;
+        }
+        let pixels = image.pixels().len() as u64;
+        totals.pixels += pixels;
+        totals.luma += luma;
+        totals.clipped_low += clipped_low;
+        totals.clipped_high += clipped_high;
+        frame_stats.push(FrameStats {
+            index,
+            path: path.clone(),
+            average_luminance: luma / pixels.max(1) as f64,
+            clipped_low: percent(clipped_low, pixels),
+            clipped_high: percent(clipped_high, pixels),
+        });
+    }
+    if frame_stats.is_empty() {
+        bail!(
+            "None of the frames in {} could be decoded",
+            directory.display()
+        );
+    }
+
+    let channel = |histogram: &Vec<u64>| ChannelStats {
+        histogram: histogram.clone(),
+        clipped_low: percent(histogram[..=low as usize].iter().sum(), totals.pixels),
+        clipped_high: percent(histogram[high as usize..].iter().sum(), totals.pixels),
+    };
+    let mut report = AnalysisReport {
+        path: directory.to_path_buf(),
+        clip_level,
+        pixels: totals.pixels,
+        average_luminance: totals.luma / totals.pixels.max(1) as f64,
+        clipped_low: percent(totals.clipped_low, totals.pixels),
+        clipped_high: percent(totals.clipped_high, totals.pixels),
+        red: channel(&totals.histograms[0]),
+        green: channel(&totals.histograms[1]),
+        blue: channel(&totals.histograms[2]),
+        frames: frame_stats,
+        unreadable,
+        plots: Vec::new(),
+    };
+    if let Some(plots) = plots {
+        report.plots = report.write_plots(plots)?;
+    }
+    Ok(report)
+}
+
+/// Returns `part` as a percentage of `whole`.
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+impl AnalysisReport {
+    /// Renders the report in the requested format.
+    pub fn render(&self, format: ProbeFormat) -> Result<String> {
+        match format {
+            ProbeFormat::Text => Ok(self.to_string()),
+            ProbeFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .context("Failed to serialize analysis report"),
+        }
+    }
+
+    /// Writes the histogram of the three channels and the luminance of each frame as PNG.
+    ///
+    /// # Returns
+    /// - `Result<Vec<PathBuf>>`: The plots written, or an error if the directory cannot
+    ///   be created or a plot cannot be saved.
+    ///
+    /// # Notes
+    /// - The histogram is scaled to its highest count; channels overlap additively, so
+    ///   levels all three share show white.
+    /// - The luminance plot spans the frames from left to right, 0 at the bottom and 255
+    ///   at the top.
+    fn write_plots(&self, directory: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create plot directory {}", directory.display()))?;
+
+        let mut histogram = RgbImage::new(PLOT_WIDTH, PLOT_HEIGHT);
+        let channels = [&self.red, &self.green, &self.blue];
+        let peak = channels
+            .iter()
+            .flat_map(|channel| channel.histogram.iter())
+            .max()
+            .copied()
+            .unwrap_or(0)
+            .max(1);
+        for x in 0..PLOT_WIDTH {
+            let level = (x * 256 / PLOT_WIDTH) as usize;
+            for (channel, stats) in channels.iter().enumerate() {
+                let height = (stats.histogram[level] * PLOT_HEIGHT as u64 / peak) as u32;
+                for y in PLOT_HEIGHT - height..PLOT_HEIGHT {
+                    histogram.get_pixel_mut(x, y).0[channel] = 255;
+                }
+            }
+        }
+
+        let mut luminance = RgbImage::from_pixel(PLOT_WIDTH, PLOT_HEIGHT, Rgb([24, 24, 24]));
+        for x in 0..PLOT_WIDTH {
+            let frame = &self.frames[x as usize * self.frames.len() / PLOT_WIDTH as usize];
+            let height = (frame.average_luminance / 255.0 * PLOT_HEIGHT as f64).round() as u32;
+            for y in PLOT_HEIGHT - height.min(PLOT_HEIGHT)..PLOT_HEIGHT {
+                luminance.put_pixel(x, y, Rgb([200, 200, 200]));
+            }
+        }
+
+        let mut written = Vec::new();
+        for (name, plot) in [("histogram.png", histogram), ("luminance.png", luminance)] {
+            let path = directory.join(name);
+            plot.save(&path)
+                .with_context(|| format!("Failed to write plot {}", path.display()))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Returns the labelled lines of the text report.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![("frames", self.frames.len().to_string())];
+        let by_luminance =
+            |a: &&FrameStats, b: &&FrameStats| a.average_luminance.total_cmp(&b.average_luminance);
+        let darkest = self.frames.iter().min_by(by_luminance);
+        let brightest = self.frames.iter().max_by(by_luminance);
+        let (darkest, brightest) = darkest.zip(brightest).expect("the report has frames");
+        entries.push((
+            "average luminance",
+            format!(
+                "{:.1} of 255 (frame {} darkest at {:.1}, frame {} brightest at {:.1})",
+                self.average_luminance,
+                darkest.index,
+                darkest.average_luminance,
+                brightest.index,
+                brightest.average_luminance
+            ),
+        ));
+        let level = |level: u8| {
+            if self.clip_level == 0 {
+                level.to_string()
+            } else if level == 0 {
+                format!("0-{}", self.clip_level)
+            } else {
+                format!("{}-255", 255 - self.clip_level)
+            }
+        };
+        entries.push((
+            "clipped blacks",
+            format!(
+                "{:.2}% of pixels at {} (red {:.2}%, green {:.2}%, blue {:.2}%)",
+                self.clipped_low,
+                level(0),
+                self.red.clipped_low,
+                self.green.clipped_low,
+                self.blue.clipped_low
+            ),
+        ));
+        entries.push((
+            "clipped whites",
+            format!(
+                "{:.2}% of pixels at {} (red {:.2}%, green {:.2}%, blue {:.2}%)",
+                self.clipped_high,
+                level(255),
+                self.red.clipped_high,
+                self.green.clipped_high,
+                self.blue.clipped_high
+            ),
+        ));
+        let most = |clipped: fn(&FrameStats) -> f64| {
+            let frame = self
+                .frames
+                .iter()
+                .max_by(|a, b| clipped(a).total_cmp(&clipped(b)))
+                .expect("the report has frames");
+            format!("frame {} ({:.2}%)", frame.index, clipped(frame))
+        };
+        entries.push(("most crushed", most(|frame| frame.clipped_low)));
+        entries.push(("most blown out", most(|frame| frame.clipped_high)));
+        if !self.unreadable.is_empty() {
+            entries.push(("unreadable", self.unreadable.len().to_string()));
+        }
+        for plot in &self.plots {
+            entries.push(("plot", plot.display().to_string()));
+        }
+        entries
+    }
+}
+
+impl fmt::Display for AnalysisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+
+        // Align all values on the longest label, as `probe` does.
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "  {:<width$} : {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
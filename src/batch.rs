@@ -0,0 +1,316 @@
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fxp_output::{running_flag, FailureKind};
+
+use crate::exit::{Interrupted, InvalidInput};
+
+/// How often running inputs are checked for having finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Extensions of the files `--batch` takes for videos, compared without regard to case.
+const VIDEO_EXTENSIONS: [&str; 11] = [
+    "mp4", "mkv", "mov", "avi", "webm", "m4v", "mpg", "mpeg", "wmv", "flv", "ts",
+];
+
+/// The input that stands for a list of inputs read from stdin.
+pub const STDIN_INPUT: &str = "-";
+
+/// Where the value of an option sits in a command line.
+pub struct OptionValue {
+    /// The index of the argument holding the value.
+    index: usize,
+    /// What precedes the value in that argument, e.g. `--input=` or `-i` for the joined
+    /// forms; empty for a value given as an argument of its own.
+    prefix: String,
+}
+
+impl OptionValue {
+    /// Finds the first value of an option given as `-s VALUE`, `-sVALUE`, `--long VALUE`
+    /// or `--long=VALUE`.
+    ///
+    /// # Parameters
+    /// - `args`: The command line, starting with the program name.
+    /// - `short`: The short form of the option, e.g. `-i`.
+    /// - `long`: The long form of the option, e.g. `--input`.
+    pub fn find(args: &[String], short: &str, long: &str) -> Option<Self> {
+        let long_joined = format!("{}=", long);
+        args.iter().enumerate().skip(1).find_map(|(index, arg)| {
+            if (arg == short || arg == long) && index + 1 < args.len() {
+                Some(OptionValue {
+                    index: index + 1,
+                    prefix: String::new(),
+                })
+            } else if arg.starts_with(&long_joined) {
+                Some(OptionValue {
+                    index,
+                    prefix: long_joined.clone(),
+                })
+            } else if arg.starts_with(short) && arg.len() > short.len() && !arg.starts_with("--") {
+                Some(OptionValue {
+                    index,
+                    prefix: short.to_string(),
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the value of the option.
+    pub fn value<'a>(&self, args: &'a [String]) -> &'a str {
+        &args[self.index][self.prefix.len()..]
+    }
+
+    /// Returns `args` with the value of the option replaced, keeping its form.
+    pub fn replace(&self, args: &[String], value: &str) -> Vec<String> {
+        let mut replaced = args.to_vec();
+        replaced[self.index] = format!("{}{}", self.prefix, value);
+        replaced
+    }
+}
+
+/// Finds the `-i -` of a command line, which reads the inputs from stdin.
+///
+/// # Parameters
+/// - `args`: The command line, starting with the program name.
+///
+/// # Returns
+/// - `Option<OptionValue>`: Where the `-` is, if the input is read from stdin.
+pub fn stdin_input(args: &[String]) -> Option<OptionValue> {
+    OptionValue::find(args, "-i", "--input").filter(|input| input.value(args) == STDIN_INPUT)
+}
+
+/// Reads a newline separated list of inputs from stdin.
+///
+/// # Returns
+/// - `Result<Vec<String>>`: The inputs, without blank lines and `#` comments, or an
+///   error if stdin cannot be read or lists nothing.
+pub fn read_stdin_inputs() -> Result<Vec<String>> {
+    let mut inputs = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line.context("Failed to read the list of inputs from stdin")?;
+        let input = line.trim();
+        if !input.is_empty() && !input.starts_with('#') {
+            inputs.push(input.to_string());
+        }
+    }
+    if inputs.is_empty() {
+        bail!("No inputs were listed on stdin");
+    }
+    debug!("Read {} inputs from stdin", inputs.len());
+    Ok(inputs)
+}
+
+/// The outcome of running the mode for one input.
+pub struct InputRun {
+    pub input: String,
+    pub status: ExitStatus,
+    /// How long the run took.
+    pub elapsed: Duration,
+}
+
+impl InputRun {
+    /// Returns whether the mode succeeded for the input.
+    pub fn succeeded(&self) -> bool {
+        self.status.success()
+    }
+}
+
+/// Runs the mode once per input, each as its own process.
+///
+/// # Parameters
+/// - `runs`: Each input with the command line to run it, starting with the program name.
+/// - `jobs`: How many inputs run at the same time.
+///
+/// # Returns
+/// - `Result<Vec<InputRun>>`: The outcome of every input started, in the order of
+///   `runs`, or an error if the executable cannot be started.
+///
+/// # Notes
+/// - Each input runs in a process of its own, so a failing input does not stop the
+///   others, and each claims, locks and records its output as a single run would.
+/// - Once stopped by Ctrl+C, no further input is started; the running ones are stopped
+///   by the same signal and waited for.
+pub fn run_each(runs: &[(String, Vec<String>)], jobs: usize) -> Result<Vec<InputRun>> {
+    let executable =
+        env::current_exe().context("Failed to locate the fxp_videoclipper executable")?;
+    let running = running_flag()?;
+    let jobs = jobs.max(1);
+
+    let mut started: Vec<(usize, Instant, Child)> = Vec::new();
+    let mut finished: Vec<(usize, ExitStatus, Duration)> = Vec::new();
+    let mut next = 0;
+    while next < runs.len() || !started.is_empty() {
+        if next < runs.len() && started.len() < jobs && running.load(Ordering::SeqCst) {
+            let (input, args) = &runs[next];
+            info!("Running {} ({} of {})", input, next + 1, runs.len());
+            debug!("Running {:?} {:?}", executable, &args[1..]);
+            let child = Command::new(&executable)
+                .args(&args[1..])
+                .spawn()
+                .with_context(|| format!("Failed to start the run of {}", input))?;
+            started.push((next, Instant::now(), child));
+            next += 1;
+            continue;
+        }
+        if !running.load(Ordering::SeqCst) {
+            // Nothing more is started, so only the running inputs are left.
+            next = runs.len();
+        }
+
+        let mut index = 0;
+        while index < started.len() {
+            match started[index].2.try_wait()? {
+                Some(status) => {
+                    let (input, start, _) = started.swap_remove(index);
+                    if !status.success() {
+                        warn!("{} failed ({})", runs[input].0, status);
+                    }
+                    finished.push((input, status, start.elapsed()));
+                }
+                None => index += 1,
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    finished.sort_by_key(|(input, _, _)| *input);
+    Ok(finished
+        .into_iter()
+        .map(|(input, status, elapsed)| InputRun {
+            input: runs[input].0.clone(),
+            status,
+            elapsed,
+        })
+        .collect())
+}
+
+/// Fails if any input failed, naming them.
+///
+/// # Returns
+/// - `Result<()>`: `Ok` if every input succeeded; otherwise an error listing the failed
+///   inputs, or the interruption if the run was stopped before all inputs ran.
+pub fn check_runs(runs: &[InputRun], total: usize) -> Result<()> {
+    let failed: Vec<&InputRun> = runs.iter().filter(|run| !run.succeeded()).collect();
+    let interrupted = runs.len() < total
+        || failed
+            .iter()
+            .any(|run| run.status.code() == Some(FailureKind::Interrupted.exit_code()));
+    if interrupted {
+        return Err(Interrupted(runs.len(), total).into());
+    }
+    if !failed.is_empty() {
+        let names: Vec<&str> = failed.iter().map(|run| run.input.as_str()).collect();
+        bail!(
+            "{} of {} inputs failed:\n  {}",
+            failed.len(),
+            total,
+            names.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+/// Lists the videos of a directory for `--batch`.
+///
+/// # Returns
+/// - `Result<Vec<PathBuf>>`: The files with a video extension, sorted by name, or an
+///   error if `directory` is not a directory or holds no videos.
+pub fn list_videos(directory: &Path) -> Result<Vec<PathBuf>> {
+    if !directory.is_dir() {
+        return Err(InvalidInput(format!(
+            "With --batch, the input must be a directory of videos: {}",
+            directory.display()
+        ))
+        .into());
+    }
+    let mut videos: Vec<PathBuf> = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+        })
+        .collect();
+    if videos.is_empty() {
+        return Err(InvalidInput(format!("No videos found in {}", directory.display())).into());
+    }
+    videos.sort();
+    Ok(videos)
+}
+
+/// Builds the command line of each video of a `--batch` run.
+///
+/// # Parameters
+/// - `args`: The command line of the batch, starting with the program name.
+/// - `videos`: The videos to run the mode for.
+///
+/// # Returns
+/// - `Result<Vec<(String, Vec<String>)>>`: Each video with its command line: `--batch`
+///   dropped, `-i` naming the video and `-o`, if given, naming `<output>/<video_stem>`.
+pub fn batch_runs(args: &[String], videos: &[PathBuf]) -> Result<Vec<(String, Vec<String>)>> {
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--batch")
+        .cloned()
+        .collect();
+    let input = OptionValue::find(&args, "-i", "--input")
+        .context("--batch needs the directory of videos as -i")?;
+    let output = OptionValue::find(&args, "-o", "--output");
+    let output_dir = output
+        .as_ref()
+        .map(|output| PathBuf::from(output.value(&args)));
+
+    videos
+        .iter()
+        .map(|video| {
+            let mut video_args = input.replace(&args, &video.display().to_string());
+            if let (Some(output), Some(output_dir)) = (&output, &output_dir) {
+                let stem = video
+                    .file_stem()
+                    .with_context(|| format!("Video {} has no file name", video.display()))?;
+                let video_output = output_dir.join(stem);
+                video_args = output.replace(&video_args, &video_output.display().to_string());
+            }
+            Ok((video.display().to_string(), video_args))
+        })
+        .collect()
+}
+
+/// Prints a table of the inputs that succeeded and failed.
+pub fn print_summary(runs: &[InputRun], total: usize) {
+    let width = runs
+        .iter()
+        .map(|run| run.input.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Input".len());
+    println!("\n{:<width$}  {:<12}  {:>8}", "Input", "Result", "Time");
+    for run in runs {
+        let result = match run.status.code() {
+            Some(0) => "ok".to_string(),
+            Some(code) => format!("failed ({})", code),
+            None => "killed".to_string(),
+        };
+        println!(
+            "{:<width$}  {:<12}  {:>6.1} s",
+            run.input,
+            result,
+            run.elapsed.as_secs_f64()
+        );
+    }
+    let succeeded = runs.iter().filter(|run| run.succeeded()).count();
+    println!("{} of {} succeeded", succeeded, total);
+}
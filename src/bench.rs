@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageFormat, RgbImage};
+use serde::Serialize;
+use std::fmt;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use fxp_merger::{blend, Blending};
+
+use crate::probe::ProbeFormat;
+
+/// Distinct frames generated for each side of a blend; the workload cycles through them.
+const DISTINCT_FRAMES: usize = 4;
+
+/// Share of the best throughput a thread count must reach to be recommended.
+const RECOMMEND_SHARE: f64 = 0.95;
+
+/// Throughput of the workload with one number of threads.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchRun {
+    pub jobs: usize,
+    pub seconds: f64,
+    pub frames_per_second: f64,
+}
+
+/// The report of `bench`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub width: u32,
+    pub height: u32,
+    /// Frames decoded, blended and encoded per run.
+    pub frames: usize,
+    pub runs: Vec<BenchRun>,
+    /// The fewest jobs reaching 95% of the best throughput.
+    pub recommended_jobs: usize,
+}
+
+/// Measures the throughput of a decode, blend and encode workload for each thread count.
+///
+/// # Parameters
+/// - `width`, `height`: Size of the generated frames.
+/// - `frames`: Number of frames each run processes.
+/// - `max_jobs`: The most threads tried; runs use 1, 2, 4 and on up to it.
+///
+/// # Returns
+/// - `Result<BenchReport>`: The throughput of each run and the recommended `--jobs`, or
+///   an error if a frame cannot be encoded or decoded.
+///
+/// # Notes
+/// - Each frame decodes two PNGs, blends them as the Merger does and encodes the blend
+///   as PNG in memory; nothing is written to disk, so the disk does not skew the result.
+/// - The blend itself runs on all cores, as in the Merger, so more jobs pay off less than
+///   for the `process` mode's external commands.
+/// - More jobs than the fewest that reach 95% of the best throughput only add memory.
+pub fn bench(width: u32, height: u32, frames: usize, max_jobs: usize) -> Result<BenchReport> {
+    let bases = generate_frames(width, height, 1)?;
+    let overlays = generate_frames(width, height, 2)?;
+
+    // Warm up, e.g. the lookup tables of the blend, outside the measured runs.
+    process_frame(&bases[0], &overlays[0])?;
+
+    let mut runs = Vec::new();
+    for jobs in job_counts(max_jobs) {
+        let start = Instant::now();
+        run_workload(&bases, &overlays, frames, jobs)?;
+        let seconds = start.elapsed().as_secs_f64();
+        runs.push(BenchRun {
+            jobs,
+            seconds,
+            frames_per_second: frames as f64 / seconds.max(f64::EPSILON),
+        });
+    }
+
+    let best = runs
+        .iter()
+        .map(|run| run.frames_per_second)
+        .fold(0.0, f64::max);
+    let recommended_jobs = runs
+        .iter()
+        .find(|run| run.frames_per_second >= best * RECOMMEND_SHARE)
+        .map_or(1, |run| run.jobs);
+
+    Ok(BenchReport {
+        width,
+        height,
+        frames,
+        runs,
+        recommended_jobs,
+    })
+}
+
+impl BenchReport {
+    /// Renders the report as aligned text or JSON.
+    pub fn render(&self, format: ProbeFormat) -> Result<String> {
+        match format {
+            ProbeFormat::Text => Ok(self.to_string()),
+            ProbeFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .context("Failed to serialize benchmark report"),
+        }
+    }
+
+    /// Returns the label and value of each line of the text report.
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![(
+            "workload".to_string(),
+            format!(
+                "{} frames of {}x{}, decoded, blended and encoded",
+                self.frames, self.width, self.height
+            ),
+        )];
+        for run in &self.runs {
+            entries.push((
+                format!("jobs {}", run.jobs),
+                format!(
+                    "{:.1} frames/s ({:.2} s)",
+                    run.frames_per_second, run.seconds
+                ),
+            ));
+        }
+        entries.push((
+            "recommended".to_string(),
+            format!("--jobs {}", self.recommended_jobs),
+        ));
+        entries
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Align all values on the longest label, as `probe` does.
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "  {:<width$} : {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the thread counts to try: powers of two below `max_jobs`, then `max_jobs`.
+fn job_counts(max_jobs: usize) -> Vec<usize> {
+    let max_jobs = max_jobs.max(1);
+    let mut counts: Vec<usize> = std::iter::successors(Some(1usize), |jobs| Some(jobs * 2))
+        .take_while(|&jobs| jobs < max_jobs)
+        .collect();
+    counts.push(max_jobs);
+    counts
+}
+
+/// Processes `frames` frames with `jobs` workers, each taking the next frame as it
+/// finishes one, as the Processor, Merger and Clutter do.
+fn run_workload(bases: &[Vec<u8>], overlays: &[Vec<u8>], frames: usize, jobs: usize) -> Result<()> {
+    let next = AtomicUsize::new(0);
+    let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let frame = next.fetch_add(1, Ordering::SeqCst);
+                if frame >= frames {
+                    break;
+                }
+                let which = frame % DISTINCT_FRAMES;
+                if let Err(e) = process_frame(&bases[which], &overlays[which]) {
+                    failure.lock().expect("a worker panicked").get_or_insert(e);
+                    break;
+                }
+            });
+        }
+    });
+    match failure.into_inner().expect("a worker panicked") {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Decodes a pair of PNGs, blends them and encodes the blend.
+fn process_frame(base: &[u8], overlay: &[u8]) -> Result<Vec<u8>> {
+    let base = image::load_from_memory(base).context("Failed to decode a benchmark frame")?;
+    let overlay = image::load_from_memory(overlay).context("Failed to decode a benchmark frame")?;
+    let blended = blend(&base, &overlay, 0.5, Blending::default());
+    encode(&DynamicImage::ImageRgba8(blended))
+}
+
+/// Generates PNG frames of gradients with noise, so they compress like real frames.
+fn generate_frames(width: u32, height: u32, seed: u32) -> Result<Vec<Vec<u8>>> {
+    (0..DISTINCT_FRAMES as u32)
+        .map(|index| {
+            let mut state = seed.wrapping_mul(0x9E37_79B9) ^ (index + 1);
+            let frame = RgbImage::from_fn(width, height, |x, y| {
+                // xorshift32, enough for noise that defeats run-length compression.
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let noise = (state % 32) as u8;
+                image::Rgb([
+                    ((x * 255 / width.max(1)) as u8).saturating_add(noise),
+                    ((y * 255 / height.max(1)) as u8).saturating_add(noise),
+                    ((index * 60) as u8).saturating_add(noise),
+                ])
+            });
+            encode(&DynamicImage::ImageRgb8(frame))
+        })
+        .collect()
+}
+
+/// Encodes an image as PNG in memory.
+fn encode(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .context("Failed to encode a benchmark frame")?;
+    Ok(bytes)
+}
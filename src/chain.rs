@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Context, Result};
+use image::DynamicImage;
+use indicatif::ProgressStyle;
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use tracing::{info_span, Span};
+
+use fxp_clutter::ColorTransfer;
+use fxp_filenames::FramePadding;
+use fxp_merger::{blend, Blending};
+use fxp_output::progress_bar;
+use fxp_stream::{
+    frame_channel, FrameReceiver, FrameSender, MemoryBudget, StageStopped, StreamFrame,
+};
+
+/// The native stages a chain runs on every frame, in this order, before the Clipper.
+#[derive(Debug, Default)]
+pub struct ChainStages {
+    /// Transfer the colors of a reference image, as the Clutter's `--reference` does.
+    pub transfer: Option<ColorTransfer>,
+    /// Blend these frames over the frames with the opacity, as the Merger does; the
+    /// last one is repeated if there are fewer of them.
+    pub overlay: Option<(Vec<PathBuf>, f32)>,
+}
+
+impl ChainStages {
+    /// Names the stages for the plan, e.g. `color transfer -> merge`.
+    pub fn describe(&self) -> String {
+        let mut stages = vec!["decode"];
+        if self.transfer.is_some() {
+            stages.push("color transfer");
+        }
+        if self.overlay.is_some() {
+            stages.push("merge");
+        }
+        stages.push("clip");
+        stages.join(" -> ")
+    }
+}
+
+/// Runs the frames through the stages, passing them between stages in memory.
+///
+/// # Parameters
+/// - `frames`: The input frames mapped by frame number.
+/// - `stages`: The stages to run.
+/// - `output_dir`: Directory the last stage writes the frames to, for the Clipper.
+/// - `budget`: Memory the frames waiting between stages may take.
+///
+/// # Returns
+/// - `Result<usize>`: The number of frames written, or the error of the first stage
+///   that failed.
+///
+/// # Notes
+/// - Every stage runs on its own thread, connected to the next by a bounded channel of
+///   decoded frames, see `fxp_stream::frame_channel`; a frame is decoded once and
+///   encoded once, however many stages there are.
+/// - Frames beyond the budget are spilled to disk as raw pixels and read back by the
+///   next stage, so memory stays bounded for large frames.
+/// - The frames are written as `frame_0001.png` and on, by their input frame number,
+///   padded to five digits or more past frame 9999.
+pub fn run_chain(
+    frames: &BTreeMap<u32, PathBuf>,
+    stages: &ChainStages,
+    output_dir: &Path,
+    budget: &MemoryBudget,
+) -> Result<usize> {
+    let _span = info_span!("chain", frames = frames.len(), stages = %stages.describe()).entered();
+
+    thread::scope(|scope| {
+        let (sender, mut receiver) = frame_channel(budget);
+        let mut workers = vec![scope.spawn(move || decode_frames(frames, sender))];
+
+        if let Some(transfer) = &stages.transfer {
+            let (next_sender, next_receiver) = frame_channel(budget);
+            let input = std::mem::replace(&mut receiver, next_receiver);
+            workers.push(scope.spawn(move || {
+                map_frames(
+                    input,
+                    next_sender,
+                    |frame| info_span!("color transfer", frame),
+                    |frame| {
+                        let image = transfer.apply(&DynamicImage::ImageRgba8(frame.image));
+                        Ok(StreamFrame {
+                            number: frame.number,
+                            image: image.into_rgba8(),
+                        })
+                    },
+                )
+            }));
+        }
+
+        if let Some((overlay, opacity)) = &stages.overlay {
+            let (next_sender, next_receiver) = frame_channel(budget);
+            let input = std::mem::replace(&mut receiver, next_receiver);
+            workers.push(scope.spawn(move || {
+                let mut position = 0;
+                map_frames(
+                    input,
+                    next_sender,
+                    |frame| info_span!("merge", frame),
+                    |frame| {
+                        let overlay_path = &overlay[position.min(overlay.len() - 1)];
+                        position += 1;
+                        merge_frame(frame, overlay_path, *opacity)
+                    },
+                )
+            }));
+        }
+
+        let last = frames.keys().next_back().copied().unwrap_or(0);
+        let padding = FramePadding::for_count(u64::from(last));
+        let written = write_frames(receiver, output_dir, frames.len(), padding);
+        for worker in workers {
+            let result = worker
+                .join()
+                .unwrap_or_else(|_| Err(anyhow!("A stage of the chain panicked")));
+            if let Err(e) = result {
+                // A stage stopped by the failure of a later one is not the cause.
+                if e.downcast_ref::<StageStopped>().is_none() {
+                    return Err(e);
+                }
+            }
+        }
+        let written = written?;
+        debug!(
+            "Chained {} frames, {} of them spilled to disk between stages",
+            written,
+            budget.spilled()
+        );
+        Ok(written)
+    })
+}
+
+/// First stage: decodes the input frames in order.
+fn decode_frames(frames: &BTreeMap<u32, PathBuf>, sender: FrameSender) -> Result<()> {
+    for (&number, path) in frames {
+        let _span = info_span!("decode", frame = number).entered();
+        let image = image::open(path)
+            .with_context(|| format!("Failed to decode frame {}", path.display()))?
+            .into_rgba8();
+        sender.send(StreamFrame { number, image })?;
+    }
+    Ok(())
+}
+
+/// A stage between two others, passing each frame through `stage` within the span
+/// `span` returns for its number.
+fn map_frames(
+    input: FrameReceiver,
+    output: FrameSender,
+    span: fn(u32) -> Span,
+    mut stage: impl FnMut(StreamFrame) -> Result<StreamFrame>,
+) -> Result<()> {
+    for frame in input {
+        let frame = frame?;
+        let _span = span(frame.number).entered();
+        output.send(stage(frame)?)?;
+    }
+    Ok(())
+}
+
+/// Blends an overlay frame over a frame, resizing the overlay to the frame's size.
+fn merge_frame(frame: StreamFrame, overlay_path: &Path, opacity: f32) -> Result<StreamFrame> {
+    let mut overlay = image::open(overlay_path)
+        .with_context(|| format!("Failed to decode overlay {}", overlay_path.display()))?;
+    let (width, height) = frame.image.dimensions();
+    if overlay.width() != width || overlay.height() != height {
+        overlay = overlay.resize_exact(width, height, image::imageops::FilterType::Lanczos3);
+    }
+    let image = blend(
+        &DynamicImage::ImageRgba8(frame.image),
+        &overlay,
+        opacity,
+        Blending::default(),
+    );
+    Ok(StreamFrame {
+        number: frame.number,
+        image,
+    })
+}
+
+/// Last stage: writes the frames for the Clipper.
+fn write_frames(
+    input: FrameReceiver,
+    output_dir: &Path,
+    total: usize,
+    padding: FramePadding,
+) -> Result<usize> {
+    let pb = progress_bar(total as u64);
+    pb.set_style(ProgressStyle::default_bar().template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta_precise})",
+    )?);
+
+    let mut written = 0;
+    for frame in input {
+        let frame = frame?;
+        let _span = info_span!("encode", frame = frame.number).entered();
+        let path = output_dir.join(padding.file_name("frame", u64::from(frame.number), "png"));
+        frame
+            .image
+            .save(&path)
+            .with_context(|| format!("Failed to write frame {}", path.display()))?;
+        written += 1;
+        pb.inc(1);
+    }
+    pb.finish();
+    Ok(written)
+}
@@ -0,0 +1,335 @@
+use anyhow::{bail, Context, Result};
+use image::{GrayImage, RgbImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fxp_filenames::FileOperations;
+use fxp_modes::Modes;
+
+use crate::probe::ProbeFormat;
+
+/// Side of the windows SSIM is computed over, and the step between them.
+const SSIM_WINDOW: u32 = 8;
+const SSIM_STEP: u32 = 4;
+
+/// Stabilizing constants of SSIM for 8-bit levels.
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// How a frame of the first directory differs from the frame of the same index in the second.
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameComparison {
+    pub index: u32,
+    /// Mean absolute difference of the red, green and blue levels, from 0 to 255.
+    pub mean_difference: f64,
+    /// Peak signal-to-noise ratio in dB; `None` for identical frames.
+    pub psnr: Option<f64>,
+    /// Structural similarity of the luma, from -1 to 1; 1 for identical frames.
+    pub ssim: f64,
+    /// Whether the frame falls below `--min-psnr` or `--min-ssim`.
+    pub below_threshold: bool,
+}
+
+/// The minimum PSNR and SSIM each compared frame must reach.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Thresholds {
+    pub min_psnr: Option<f64>,
+    pub min_ssim: Option<f64>,
+}
+
+impl Thresholds {
+    fn is_set(&self) -> bool {
+        self.min_psnr.is_some() || self.min_ssim.is_some()
+    }
+
+    /// Returns whether the frame falls below either threshold; identical frames never do.
+    fn fails(&self, psnr: Option<f64>, ssim: f64) -> bool {
+        let psnr_fails = matches!((self.min_psnr, psnr), (Some(min), Some(psnr)) if psnr < min);
+        let ssim_fails = self.min_ssim.is_some_and(|min| ssim < min);
+        psnr_fails || ssim_fails
+    }
+}
+
+/// The report of `compare` for two directories of frames.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    pub thresholds: Thresholds,
+    pub frames: Vec<FrameComparison>,
+    /// Indices found in only one of the directories.
+    pub unmatched: Vec<u32>,
+}
+
+/// Compares the frames of two directories by matching index.
+///
+/// # Parameters
+/// - `first`: The directory of reference frames.
+/// - `second`: The directory of frames to compare with them.
+/// - `thresholds`: The minimum PSNR and SSIM every frame must reach.
+///
+/// # Returns
+/// - `Result<ComparisonReport>`: The metrics of every pair, or an error if a directory
+///   cannot be read, no index is in both, or a pair cannot be decoded or differs in size.
+///
+/// # Notes
+/// - Frames are numbered as the modes number them, see `fxp_filenames::FileOperations`,
+///   so `frame_0007.png` pairs with `image_0007.png`.
+/// - PSNR is computed over the red, green and blue levels; SSIM over the luma, in 8x8
+///   windows 4 pixels apart, averaged over the frame.
+pub fn compare(first: &Path, second: &Path, thresholds: Thresholds) -> Result<ComparisonReport> {
+    let first_frames = load_frames(first)?;
+    let second_frames = load_frames(second)?;
+
+    let unmatched: Vec<u32> = first_frames
+        .keys()
+        .filter(|index| !second_frames.contains_key(index))
+        .chain(
+            second_frames
+                .keys()
+                .filter(|index| !first_frames.contains_key(index)),
+        )
+        .copied()
+        .collect();
+
+    let mut frames = Vec::new();
+    for (&index, first_path) in &first_frames {
+        let Some(second_path) = second_frames.get(&index) else {
+            continue;
+        };
+        let a = open(first_path)?;
+        let b = open(second_path)?;
+        if a.dimensions() != b.dimensions() {
+            bail!(
+                "Frame {} differs in size: {}x{} in {}, {}x{} in {}",
+                index,
+                a.width(),
+                a.height(),
+                first_path.display(),
+                b.width(),
+                b.height(),
+                second_path.display()
+            );
+        }
+        let (mean_difference, psnr) = difference_and_psnr(&a, &b);
+        let ssim = ssim(&luma(&a), &luma(&b));
+        frames.push(FrameComparison {
+            index,
+            mean_difference,
+            psnr,
+            ssim,
+            below_threshold: thresholds.fails(psnr, ssim),
+        });
+    }
+    if frames.is_empty() {
+        bail!(
+            "No frame index is in both {} and {}",
+            first.display(),
+            second.display()
+        );
+    }
+
+    Ok(ComparisonReport {
+        first: first.to_path_buf(),
+        second: second.to_path_buf(),
+        thresholds,
+        frames,
+        unmatched,
+    })
+}
+
+/// Maps the frames of a directory by their index.
+fn load_frames(directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    if !directory.is_dir() {
+        bail!(
+            "Nothing to compare at {}, expected a directory of frames",
+            directory.display()
+        );
+    }
+    let files: Vec<PathBuf> = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    Modes::Clipper
+        .load_files(&files)
+        .with_context(|| format!("Failed to number the frames in {}", directory.display()))
+}
+
+fn open(path: &Path) -> Result<RgbImage> {
+    Ok(image::open(path)
+        .with_context(|| format!("Failed to decode frame {}", path.display()))?
+        .to_rgb8())
+}
+
+/// Returns the Rec. 709 luma of an image.
+fn luma(image: &RgbImage) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        let luma = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+        image::Luma([luma.round() as u8])
+    })
+}
+
+/// Returns the mean absolute difference and the PSNR of two images of the same size.
+fn difference_and_psnr(a: &RgbImage, b: &RgbImage) -> (f64, Option<f64>) {
+    let (mut absolute, mut squared) = (0u64, 0u64);
+    for (x, y) in a.as_raw().iter().zip(b.as_raw()) {
+        let difference = (*x as i64 - *y as i64).unsigned_abs();
+        absolute += difference;
+        squared += difference * difference;
+    }
+    let samples = a.as_raw().len().max(1) as f64;
+    let mse = squared as f64 / samples;
+    let psnr = (squared > 0).then(|| 10.0 * (255.0 * 255.0 / mse).log10());
+    (absolute as f64 / samples, psnr)
+}
+
+/// Returns the mean structural similarity of two gray images of the same size.
+fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    let window = SSIM_WINDOW.min(a.width()).min(a.height());
+    if window == 0 {
+        return 1.0;
+    }
+    let starts = |length: u32| {
+        let mut starts: Vec<u32> = (0..=length - window).step_by(SSIM_STEP as usize).collect();
+        if starts.last() != Some(&(length - window)) {
+            starts.push(length - window);
+        }
+        starts
+    };
+
+    let (mut total, mut windows) = (0.0, 0);
+    for top in starts(a.height()) {
+        for left in starts(a.width()) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in top..top + window {
+                for x in left..left + window {
+                    let va = a.get_pixel(x, y).0[0] as f64;
+                    let vb = b.get_pixel(x, y).0[0] as f64;
+                    sum_a += va;
+                    sum_b += vb;
+                    sum_aa += va * va;
+                    sum_bb += vb * vb;
+                    sum_ab += va * vb;
+                }
+            }
+            let n = (window * window) as f64;
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let variance_a = sum_aa / n - mean_a * mean_a;
+            let variance_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1)
+                    * (variance_a + variance_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+impl ComparisonReport {
+    /// Returns whether a threshold is set and a frame falls below it or has no counterpart.
+    pub fn failed(&self) -> bool {
+        self.thresholds.is_set()
+            && (!self.unmatched.is_empty() || self.frames.iter().any(|f| f.below_threshold))
+    }
+
+    /// Renders the report in the requested format.
+    pub fn render(&self, format: ProbeFormat) -> Result<String> {
+        match format {
+            ProbeFormat::Text => Ok(self.to_string()),
+            ProbeFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .context("Failed to serialize comparison report"),
+        }
+    }
+
+    /// Returns the labelled lines of the text report.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let count = self.frames.len() as f64;
+        let mut entries = vec![("compared frames", self.frames.len().to_string())];
+        if !self.unmatched.is_empty() {
+            entries.push(("unmatched", format!("{} indices", self.unmatched.len())));
+        }
+
+        let mean_difference = self.frames.iter().map(|f| f.mean_difference).sum::<f64>() / count;
+        entries.push(("mean difference", format!("{:.2} of 255", mean_difference)));
+
+        let psnrs: Vec<(u32, f64)> = self
+            .frames
+            .iter()
+            .filter_map(|f| f.psnr.map(|psnr| (f.index, psnr)))
+            .collect();
+        let identical = self.frames.len() - psnrs.len();
+        let psnr = match psnrs.iter().min_by(|a, b| a.1.total_cmp(&b.1)) {
+            Some((index, min)) => format!(
+                "{:.2} dB mean, {:.2} dB lowest at frame {}, {} identical",
+                psnrs.iter().map(|(_, psnr)| psnr).sum::<f64>() / psnrs.len() as f64,
+                min,
+                index,
+                identical
+            ),
+            None => "all frames identical".to_string(),
+        };
+        entries.push(("psnr", psnr));
+
+        let lowest_ssim = self
+            .frames
+            .iter()
+            .min_by(|a, b| a.ssim.total_cmp(&b.ssim))
+            .expect("the report has frames");
+        entries.push((
+            "ssim",
+            format!(
+                "{:.4} mean, {:.4} lowest at frame {}",
+                self.frames.iter().map(|f| f.ssim).sum::<f64>() / count,
+                lowest_ssim.ssim,
+                lowest_ssim.index
+            ),
+        ));
+
+        if self.thresholds.is_set() {
+            let below: Vec<String> = self
+                .frames
+                .iter()
+                .filter(|f| f.below_threshold)
+                .map(|f| f.index.to_string())
+                .collect();
+            let verdict = if self.failed() {
+                format!(
+                    "failed, {} frames below the threshold{}{}",
+                    below.len(),
+                    if below.is_empty() { "" } else { ": " },
+                    below.join(", ")
+                )
+            } else {
+                "passed".to_string()
+            };
+            entries.push(("result", verdict));
+        }
+        entries
+    }
+}
+
+impl fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} vs {}", self.first.display(), self.second.display())?;
+
+        // Align all values on the longest label, as `probe` does.
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "  {:<width$} : {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::error::Error;
+use thiserror::Error;
+
+use fxp_output::{FailureKind, OutputError};
+
+/// Exit codes, listed at the end of `--help`.
+pub const EXIT_CODES: &str = "Exit codes:
+  0    success
+  1    any other failure
+  2    usage error, or compare below --min-ssim
+  3    a tool such as ffmpeg, gmic or convert is not installed
+  4    a tool failed
+  5    the input holds no frames
+  6    the input does not exist or is not what the mode takes
+  7    the output exists and --error-if-exists is given
+  8    not enough disk space for the output
+  9    another run is writing the output, see --wait and --force-lock
+  130  interrupted by Ctrl+C, SIGTERM or SIGHUP";
+
+/// An input that does not exist or is not what the mode takes, see `validate_input`.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct InvalidInput(pub String);
+
+impl InvalidInput {
+    pub fn kind(&self) -> FailureKind {
+        FailureKind::InvalidInput
+    }
+}
+
+/// Frames of `compare` that fell below its thresholds, see `ComparisonReport::failed`.
+#[derive(Debug, Error)]
+#[error("Frames compared fall below the thresholds or have no counterpart")]
+pub struct BelowThreshold;
+
+impl BelowThreshold {
+    pub fn kind(&self) -> FailureKind {
+        FailureKind::BelowThreshold
+    }
+}
+
+/// Inputs of a list that were stopped by Ctrl+C, SIGTERM or SIGHUP, see `batch::run_each`.
+#[derive(Debug, Error)]
+#[error("Stopped after {0} of {1} inputs")]
+pub struct Interrupted(pub usize, pub usize);
+
+impl Interrupted {
+    pub fn kind(&self) -> FailureKind {
+        FailureKind::Interrupted
+    }
+}
+
+/// Returns the kind of failure of an error, and so the process exit code.
+///
+/// # Parameters
+/// - `error`: The error a mode failed with.
+///
+/// # Returns
+/// - `FailureKind`: The kind of the first typed error in the chain of causes, or
+///   `FailureKind::Other` if there is none.
+pub fn failure_kind(error: &anyhow::Error) -> FailureKind {
+    error
+        .chain()
+        .find_map(typed_kind)
+        .unwrap_or(FailureKind::Other)
+}
+
+/// Returns the kind of a cause that is one of `$error`, tried in order.
+macro_rules! kind_of {
+    ($cause:expr, $($error:ty),+ $(,)?) => {
+        None$(.or_else(|| $cause.downcast_ref::<$error>().map(<$error>::kind)))+
+    };
+}
+
+/// Returns the kind of a single cause, if it is one of the typed errors of the modes.
+fn typed_kind(cause: &(dyn Error + 'static)) -> Option<FailureKind> {
+    kind_of!(
+        cause,
+        InvalidInput,
+        BelowThreshold,
+        Interrupted,
+        OutputError,
+        fxp_init::InitError,
+        fxp_audio::AudioError,
+        fxp_exporter::ExporterError,
+        fxp_sampler::SamplerError,
+        fxp_merger::MergerError,
+        fxp_gmicer::GmicerError,
+        fxp_clutter::ClutterError,
+        fxp_clipper::ClipperError,
+        fxp_dedup::DedupError,
+        fxp_grader::GraderError,
+        fxp_captioner::CaptionerError,
+        fxp_stabilizer::StabilizerError,
+        fxp_interpolator::InterpolatorError,
+        fxp_visualizer::VisualizerError,
+        fxp_processor::ProcessorError,
+        fxp_zoopraxiscope::ZoopraxiscopeError,
+    )
+}
@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use fxp_output::{manifest_output, written_manifests};
+
+/// Environment variable holding the outputs of the run, one per line, for `--on-success`.
+const OUTPUTS_VARIABLE: &str = "FXP_OUTPUTS";
+
+/// How long to wait for the webhook to answer, so an unreachable one does not hold up
+/// the end of the run.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An output of the run and the manifest describing it.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookOutput {
+    pub output: PathBuf,
+    pub manifest: PathBuf,
+}
+
+/// The JSON summary of a run given to the hooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub tool: &'static str,
+    pub version: &'static str,
+    /// The subcommand, e.g. `clipper`.
+    pub mode: String,
+    /// `succeeded` or `failed`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_seconds: f64,
+    /// Every output the run wrote a manifest for, in order.
+    pub outputs: Vec<HookOutput>,
+}
+
+impl RunSummary {
+    /// Summarizes a finished run.
+    ///
+    /// # Parameters
+    /// - `mode`: The subcommand that ran.
+    /// - `result`: What the mode returned.
+    /// - `elapsed`: How long the run took.
+    pub fn new(mode: &str, result: &Result<()>, elapsed: Duration) -> Self {
+        let outputs = written_manifests()
+            .into_iter()
+            .map(|manifest| HookOutput {
+                output: manifest_output(&manifest),
+                manifest,
+            })
+            .collect();
+        Self {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            mode: mode.to_string(),
+            status: if result.is_ok() {
+                "succeeded"
+            } else {
+                "failed"
+            },
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            elapsed_seconds: elapsed.as_secs_f64(),
+            outputs,
+        }
+    }
+}
+
+/// The commands run once a mode finishes, from `--on-success` and `--webhook` or the
+/// configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    /// Shell command run after a successful run.
+    pub on_success: Option<String>,
+    /// URL the summary of every run is posted to.
+    pub webhook: Option<String>,
+}
+
+impl Hooks {
+    /// Runs the hooks of a finished run.
+    ///
+    /// # Parameters
+    /// - `summary`: The summary of the run.
+    ///
+    /// # Notes
+    /// - `on_success` only runs if the run succeeded; the webhook is posted either way.
+    /// - A failing hook is reported as a warning and does not fail the run, whose outputs
+    ///   are already written.
+    pub fn fire(&self, summary: &RunSummary) {
+        if self.on_success.is_none() && self.webhook.is_none() {
+            return;
+        }
+        let json = match serde_json::to_string_pretty(summary) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to summarize the run for its hooks: {}", e);
+                return;
+            }
+        };
+        if let (Some(command), None) = (&self.on_success, &summary.error) {
+            if let Err(e) = run_on_success(command, summary, &json) {
+                warn!("The --on-success command failed: {:#}", e);
+            }
+        }
+        if let Some(url) = &self.webhook {
+            if let Err(e) = post_webhook(url, &json) {
+                warn!("Failed to post the run summary to {}: {:#}", url, e);
+            }
+        }
+    }
+}
+
+/// Runs the `--on-success` command through the shell, with the outputs in
+/// `FXP_OUTPUTS` and the summary on stdin.
+fn run_on_success(command: &str, summary: &RunSummary, json: &str) -> Result<()> {
+    let outputs: Vec<String> = summary
+        .outputs
+        .iter()
+        .map(|output| output.output.to_string_lossy().into_owned())
+        .collect();
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command).env(OUTPUTS_VARIABLE, outputs.join("\n"));
+    debug!("Running --on-success: {}", command);
+    run_with_input(shell, json).with_context(|| format!("Failed to run '{}'", command))
+}
+
+/// Posts the summary as JSON to the webhook, failing on an error status.
+fn post_webhook(url: &str, json: &str) -> Result<()> {
+    debug!("Posting the run summary to {}", url);
+    ureq::post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(json)?;
+    Ok(())
+}
+
+/// Runs a command with `input` on its stdin, failing if it exits unsuccessfully.
+fn run_with_input(mut command: Command, input: &str) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start {:?}", command.get_program()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that does not read its input closes the pipe early, which is fine.
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{:?} exited with {}", command.get_program(), status);
+    }
+    Ok(())
+}
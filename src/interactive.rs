@@ -0,0 +1,493 @@
+use anyhow::{bail, Context, Result};
+use console::style;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, Select};
+use log::debug;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fxp_init::{get_duration, Config};
+use fxp_output::{progress_mode, trace_file};
+
+use crate::GlobalOptions;
+
+/// The filter applied to the exported frames.
+enum Filter {
+    None,
+    Clut(String),
+    Gmic(Vec<String>),
+    Command(String),
+}
+
+/// Guides the user through exporting, sampling, filtering, blending and clipping a video.
+///
+/// # Parameters
+/// - `global`: Options shared by every mode; `--dry-run` is not supported here.
+/// - `config`: Application configuration, used to measure the clip length for chunks.
+///
+/// # Returns
+/// - `Result<()>`: Indicates whether the clip was rendered, or why a step failed.
+///
+/// # Notes
+/// - Every step runs the matching subcommand of this executable, so it behaves exactly
+///   like the command line that is printed before it, and can be repeated by hand.
+/// - All outputs are written into one project directory, replacing earlier outputs in it.
+/// - The filter is tried on the sampled preview frames first, so it can be changed before
+///   it is applied to every frame.
+/// - In chunks, each part of the video is exported, filtered, blended and appended to the
+///   clip before the next one, and its frames are deleted, so the disk only ever holds
+///   the frames of one chunk.
+pub fn run_interactive(global: &GlobalOptions, config: &Config) -> Result<()> {
+    if global.dry_run {
+        bail!("The interactive mode runs every step for real and does not support --dry-run");
+    }
+    let theme = ColorfulTheme::default();
+
+    let video: String = Input::with_theme(&theme)
+        .with_prompt("Video to clip")
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if Path::new(input).is_file() {
+                Ok(())
+            } else {
+                Err("Not a file")
+            }
+        })
+        .interact_text()?;
+    let audio: String = Input::with_theme(&theme)
+        .with_prompt("Audio file (empty for none)")
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.is_empty() || Path::new(input).is_file() {
+                Ok(())
+            } else {
+                Err("Not a file")
+            }
+        })
+        .interact_text()?;
+    // Without audio the length of the clip has to be given.
+    let duration: Option<u64> = if audio.is_empty() {
+        Some(
+            Input::with_theme(&theme)
+                .with_prompt("Duration to export, in milliseconds")
+                .interact_text()?,
+        )
+    } else {
+        None
+    };
+    let length_args: Vec<String> = match duration {
+        Some(duration) => vec!["-d".into(), duration.to_string()],
+        None => vec!["-a".into(), audio.clone()],
+    };
+    let fps: String = Input::with_theme(&theme)
+        .with_prompt("Frames per second (empty for the configured default)")
+        .allow_empty(true)
+        .interact_text()?;
+    let fps_args: Vec<String> = if fps.is_empty() {
+        Vec::new()
+    } else {
+        vec!["-f".into(), fps]
+    };
+
+    let video_path = Path::new(&video);
+    let stem = video_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "clip".to_string());
+    let default_project = video_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(format!("{}_project", stem));
+    let project: String = Input::with_theme(&theme)
+        .with_prompt("Project directory for all outputs")
+        .default(default_project.display().to_string())
+        .interact_text()?;
+    let project = PathBuf::from(project);
+    if project.is_dir()
+        && !Confirm::with_theme(&theme)
+            .with_prompt(format!(
+                "{} exists; replace the outputs inside it?",
+                project.display()
+            ))
+            .default(false)
+            .interact()?
+    {
+        bail!("Choose another project directory");
+    }
+    let path_in_project = |name: &str| project.join(name).display().to_string();
+    let chunk_seconds: u64 = Input::with_theme(&theme)
+        .with_prompt("Chunk length in seconds, to bound the disk space (0 for no chunks)")
+        .default(0)
+        .interact_text()?;
+
+    // Step 1: export the frames, unless they are exported chunk by chunk later.
+    let frames = path_in_project("frames");
+    if chunk_seconds == 0 {
+        let mut args = vec![
+            "exporter".into(),
+            "-i".into(),
+            video.clone(),
+            "-o".into(),
+            frames.clone(),
+        ];
+        args.extend(length_args.iter().cloned());
+        args.extend(fps_args.iter().cloned());
+        run_step(global, "Exporting frames", &args)?;
+    }
+
+    // Step 2: sample a few frames to judge the filters on.
+    let samples = if Confirm::with_theme(&theme)
+        .with_prompt("Sample preview frames to try filters on?")
+        .default(true)
+        .interact()?
+    {
+        let number: usize = Input::with_theme(&theme)
+            .with_prompt("Number of preview frames")
+            .default(6)
+            .interact_text()?;
+        let samples = path_in_project("samples");
+        let mut args = vec![
+            "sampler".into(),
+            "-i".into(),
+            video.clone(),
+            "-o".into(),
+            samples.clone(),
+            "-u".into(),
+            "-n".into(),
+            number.to_string(),
+        ];
+        args.extend(length_args.iter().cloned());
+        run_step(global, "Sampling preview frames", &args)?;
+        println!("Preview frames are in {}", style(&samples).green());
+        Some(samples)
+    } else {
+        None
+    };
+
+    // Step 3: choose a filter, trying it on the preview frames until it is kept.
+    let filter = loop {
+        let filter = choose_filter(&theme)?;
+        let Some(samples) = &samples else {
+            break filter;
+        };
+        if matches!(filter, Filter::None) {
+            break filter;
+        }
+        let preview = path_in_project("samples_filtered");
+        run_step(
+            global,
+            "Filtering the preview frames",
+            &filter_args(&filter, samples, &preview),
+        )?;
+        println!("Filtered preview frames are in {}", style(&preview).green());
+        if Confirm::with_theme(&theme)
+            .with_prompt("Keep this filter?")
+            .default(true)
+            .interact()?
+        {
+            break filter;
+        }
+    };
+
+    let opacity = if matches!(filter, Filter::None) {
+        1.0
+    } else {
+        ask_opacity(&theme)?
+    };
+    let clip = path_in_project(&format!("{}.mp4", stem));
+
+    if chunk_seconds > 0 {
+        // Steps 4 and 5 for one chunk at a time, appending each to the clip.
+        let duration = match duration {
+            Some(duration) => duration,
+            None => get_duration(&video, Some(audio.clone()), None, config)
+                .context("Failed to measure the length of the clip")?,
+        };
+        let chunk_ms = chunk_seconds * 1000;
+        let chunks = duration.div_ceil(chunk_ms);
+        let chunk_dir = project.join("chunk");
+        let path_in_chunk = |name: &str| chunk_dir.join(name).display().to_string();
+        for chunk in 0..chunks {
+            let start = chunk * chunk_ms;
+            let length = chunk_ms.min(duration - start);
+            println!("{} Chunk {}/{}", style("==>").cyan(), chunk + 1, chunks);
+            let frames = path_in_chunk("frames");
+            let mut args = vec![
+                "exporter".into(),
+                "-i".into(),
+                video.clone(),
+                "-o".into(),
+                frames.clone(),
+                "--start".into(),
+                start.to_string(),
+                "-d".into(),
+                length.to_string(),
+            ];
+            args.extend(fps_args.iter().cloned());
+            run_step(global, "Exporting the frames of the chunk", &args)?;
+
+            let clip_frames = filter_and_blend(
+                global,
+                &filter,
+                opacity,
+                &frames,
+                &path_in_chunk("filtered"),
+                &path_in_chunk("blended"),
+            )?;
+
+            let mut args = vec![
+                "clipper".into(),
+                "-i".into(),
+                clip_frames,
+                "-o".into(),
+                clip.clone(),
+            ];
+            if chunk > 0 {
+                args.push("--append-clip".into());
+            }
+            // The audio is laid over the whole clip once its last chunk is appended.
+            if chunk + 1 == chunks && !audio.is_empty() {
+                args.extend(["-a".into(), audio.clone()]);
+            }
+            args.extend(fps_args.iter().cloned());
+            run_step(global, "Appending the chunk to the clip", &args)?;
+
+            fs::remove_dir_all(&chunk_dir).with_context(|| {
+                format!(
+                    "Failed to remove the chunk frames in {}",
+                    chunk_dir.display()
+                )
+            })?;
+        }
+        println!("Clip written to {}", style(&clip).green());
+        return Ok(());
+    }
+
+    // Step 4: filter every frame and blend it over the original.
+    let clip_frames = filter_and_blend(
+        global,
+        &filter,
+        opacity,
+        &frames,
+        &path_in_project("filtered"),
+        &path_in_project("blended"),
+    )?;
+
+    // Step 5: render the clip.
+    if !Confirm::with_theme(&theme)
+        .with_prompt("Render the clip now?")
+        .default(true)
+        .interact()?
+    {
+        println!(
+            "Frames ready to clip are in {}",
+            style(&clip_frames).green()
+        );
+        return Ok(());
+    }
+    let mut args = vec![
+        "clipper".into(),
+        "-i".into(),
+        clip_frames,
+        "-o".into(),
+        clip.clone(),
+    ];
+    if !audio.is_empty() {
+        args.extend(["-a".into(), audio]);
+    }
+    args.extend(fps_args);
+    run_step(global, "Rendering the clip", &args)?;
+    println!("Clip written to {}", style(&clip).green());
+
+    Ok(())
+}
+
+/// Asks for the opacity of the filtered frames over the originals.
+fn ask_opacity(theme: &ColorfulTheme) -> Result<f32> {
+    let opacity = Input::with_theme(theme)
+        .with_prompt("Opacity of the filtered frames over the originals (1 keeps only them)")
+        .default(1.0)
+        .validate_with(|value: &f32| -> Result<(), &str> {
+            if (0.0..=1.0).contains(value) {
+                Ok(())
+            } else {
+                Err("Opacity must be between 0 and 1")
+            }
+        })
+        .interact_text()?;
+    Ok(opacity)
+}
+
+/// Filters the frames in `frames` and blends them over the originals.
+///
+/// # Returns
+/// - `Result<String>`: The directory of the frames to clip: `frames` itself without a
+///   filter, `filtered` at full opacity, and `blended` otherwise.
+fn filter_and_blend(
+    global: &GlobalOptions,
+    filter: &Filter,
+    opacity: f32,
+    frames: &str,
+    filtered: &str,
+    blended: &str,
+) -> Result<String> {
+    if matches!(filter, Filter::None) {
+        return Ok(frames.to_string());
+    }
+    let mut args = filter_args(filter, frames, filtered);
+    // The Clutter blends in the same pass; other filters are blended by the Merger.
+    let blended_by_clutter = opacity < 1.0 && matches!(filter, Filter::Clut(_));
+    if blended_by_clutter {
+        args.extend(["--clut-opacity".into(), opacity.to_string()]);
+    }
+    run_step(global, "Filtering the frames", &args)?;
+    if opacity >= 1.0 || blended_by_clutter {
+        return Ok(filtered.to_string());
+    }
+
+    run_step(
+        global,
+        "Blending the filtered frames over the originals",
+        &[
+            "merger".into(),
+            "-i".into(),
+            frames.into(),
+            "-r".into(),
+            filtered.into(),
+            "-t".into(),
+            opacity.to_string(),
+            "-o".into(),
+            blended.into(),
+        ],
+    )?;
+    Ok(blended.to_string())
+}
+
+/// Asks which filter to apply and its CLUT image, GMIC arguments or command.
+fn choose_filter(theme: &ColorfulTheme) -> Result<Filter> {
+    let choice = Select::with_theme(theme)
+        .with_prompt("Filter")
+        .items(&[
+            "No filter",
+            "CLUT image",
+            "GMIC command",
+            "External command",
+        ])
+        .default(0)
+        .interact()?;
+    let filter = match choice {
+        1 => Filter::Clut(
+            Input::with_theme(theme)
+                .with_prompt("CLUT image")
+                .validate_with(|input: &String| -> Result<(), &str> {
+                    if Path::new(input).is_file() {
+                        Ok(())
+                    } else {
+                        Err("Not a file")
+                    }
+                })
+                .interact_text()?,
+        ),
+        2 => {
+            let command: String = Input::with_theme(theme)
+                .with_prompt("GMIC arguments, e.g. -blur 3")
+                .interact_text()?;
+            Filter::Gmic(command.split_whitespace().map(String::from).collect())
+        }
+        3 => Filter::Command(
+            Input::with_theme(theme)
+                .with_prompt("Command per frame, e.g. magick {input} -negate {output}")
+                .validate_with(|input: &String| -> Result<(), &str> {
+                    if input.contains("{output}") {
+                        Ok(())
+                    } else {
+                        Err("The command must write to {output}")
+                    }
+                })
+                .interact_text()?,
+        ),
+        _ => Filter::None,
+    };
+    Ok(filter)
+}
+
+/// Builds the arguments that apply `filter` to the frames in `input`, writing to `output`.
+fn filter_args(filter: &Filter, input: &str, output: &str) -> Vec<String> {
+    match filter {
+        Filter::None => Vec::new(),
+        Filter::Clut(clut) => vec![
+            "clutter".into(),
+            "-i".into(),
+            input.into(),
+            "-o".into(),
+            output.into(),
+            "-l".into(),
+            clut.clone(),
+        ],
+        Filter::Gmic(gmic_args) => {
+            let mut args = vec![
+                "gmicer".into(),
+                "-i".into(),
+                input.into(),
+                "-o".into(),
+                output.into(),
+                "--".into(),
+            ];
+            args.extend(gmic_args.iter().cloned());
+            args
+        }
+        Filter::Command(command) => vec![
+            "process".into(),
+            "-i".into(),
+            input.into(),
+            "-o".into(),
+            output.into(),
+            "--cmd".into(),
+            command.clone(),
+        ],
+    }
+}
+
+/// Runs one step as a subcommand of this executable, replacing its earlier output.
+///
+/// The step reports progress, logs and traces the same way as this process.
+fn run_step(global: &GlobalOptions, description: &str, args: &[String]) -> Result<()> {
+    println!(
+        "{} {}\n  fxp_videoclipper --overwrite {}",
+        style("==>").cyan(),
+        style(description).bold(),
+        args.join(" ")
+    );
+    let mut forwarded = vec![
+        "--overwrite".to_string(),
+        "--progress".into(),
+        progress_mode().to_string(),
+        "--log-format".into(),
+        global.log_format.to_string(),
+    ];
+    if let Some(trace_file) = trace_file() {
+        forwarded.extend([
+            "--trace-output".into(),
+            format!("json:{}", trace_file.display()),
+        ]);
+    }
+    if global.no_log_file {
+        forwarded.push("--no-log-file".into());
+    } else if let Some(log_file) = &global.log_file {
+        forwarded.extend(["--log-file".into(), log_file.display().to_string()]);
+    }
+
+    let executable =
+        env::current_exe().context("Failed to locate the fxp_videoclipper executable")?;
+    debug!("Running {:?} {:?} {:?}", executable, forwarded, args);
+    let status = Command::new(executable)
+        .args(&forwarded)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to start step: {}", description))?;
+    if !status.success() {
+        bail!("{} failed ({})", description, status);
+    }
+    Ok(())
+}
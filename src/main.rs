@@ -1,19 +1,46 @@
 use anyhow::{Context, Result};
-use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::{ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use clap_verbosity_flag::log::LevelFilter;
 use console::style;
-use log::debug;
-use std::path::Path;
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use fxp_clutter::ClutSource;
+use fxp_filenames::{FileOperations, FrameSource};
 use fxp_init::get_audio_file;
-use fxp_init::{get_audio_dir, get_audio_duration};
-use fxp_init::{get_duration, get_fps, get_opacity, get_pixel_upper_limit, get_sampling_number};
-use fxp_init::{initialize_configuration, initialize_logger, load_default_configuration, Config};
-
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use fxp_init::{
+    default_log_dir, initialize_configuration, initialize_logger, load_default_configuration,
+    save_configuration, Config, LogFile, LogFormat,
+};
+use fxp_init::{
+    get_audio_dir, get_audio_duration, media_duration, resolve_video_url, AudioSelection,
+};
+use fxp_init::{
+    get_duration, get_fps, get_jobs, get_multiple_opacities, get_opacity, get_pixel_upper_limit,
+    get_preview_pixel_limit, get_retry_policy, get_sampling_number, get_sequence_duration,
 };
+use fxp_modes::{Capabilities, Modes};
+use fxp_output::{
+    generate_seed, init_tracing, release_output_locks, running_flag, set_graceful, set_lock_policy,
+    set_progress_mode, set_seed, timing_summary, write_timing_summary, CollisionPolicy, FrameRate,
+    LockPolicy, ModeOutput, ProgressMode, Seed, TraceOutput,
+};
+
+use std::sync::Arc;
+
+mod analyze;
+mod batch;
+mod bench;
+mod chain;
+mod compare;
+mod exit;
+mod hooks;
+mod interactive;
+mod notify;
+mod probe;
+mod reproduce;
 
 #[derive(clap::Args, Debug)]
 pub struct Verbosity {
@@ -37,72 +64,1230 @@ impl Verbosity {
     }
 }
 
+/// Options shared by every mode.
+#[derive(Args, Debug)]
+struct GlobalOptions {
+    /// Print the resolved plan instead of running the mode
+    #[arg(
+        long = "dry-run",
+        global = true,
+        help = "Print the resolved plan without creating files or running ffmpeg",
+        display_order = 98
+    )]
+    dry_run: bool,
+    /// Replace an existing output
+    #[arg(
+        long = "overwrite",
+        global = true,
+        group = "collision",
+        help = "Delete and replace an output that already exists",
+        display_order = 99
+    )]
+    overwrite: bool,
+    /// Add to an existing output directory
+    #[arg(
+        long = "append",
+        global = true,
+        group = "collision",
+        help = "Write into an existing output directory, keeping its files",
+        display_order = 99
+    )]
+    append: bool,
+    /// Fail if the output exists
+    #[arg(
+        long = "error-if-exists",
+        global = true,
+        group = "collision",
+        help = "Fail instead of writing next to an output that already exists",
+        display_order = 99
+    )]
+    error_if_exists: bool,
+    /// Skip the staging directory
+    #[arg(
+        long = "in-place",
+        global = true,
+        help = "Write images straight into the output directory instead of staging them until all succeed",
+        display_order = 99
+    )]
+    in_place: bool,
+    /// Finish the frame in flight when stopped
+    #[arg(
+        long = "graceful",
+        global = true,
+        help = "On Ctrl+C or SIGTERM, let the frame or stage in flight finish and keep the partial output with its manifest; a second signal exits at once",
+        display_order = 99
+    )]
+    graceful: bool,
+    /// Wait for a run writing the same output
+    #[arg(
+        long = "wait",
+        global = true,
+        group = "lock",
+        help = "Wait for another run writing the same output to finish instead of failing",
+        display_order = 99
+    )]
+    wait: bool,
+    /// Take over the lock of an output
+    #[arg(
+        long = "force-lock",
+        global = true,
+        group = "lock",
+        help = "Take over the lock of an output another run holds, e.g. one left by a run on another machine",
+        display_order = 99
+    )]
+    force_lock: bool,
+    /// Which audio file to use from a directory
+    #[arg(
+        long = "audio-select",
+        global = true,
+        value_name = "STRATEGY",
+        help = "Which audio file to use when the audio path is a directory holding several: alphabetical, newest, longest or prompt; defaults to audio_selection of the configuration",
+        display_order = 99
+    )]
+    audio_select: Option<AudioSelection>,
+    /// Refuse to download URL inputs
+    #[arg(
+        long = "no-download",
+        global = true,
+        help = "Fail on an http:// or https:// input of the Exporter or Sampler that is not downloaded yet, instead of downloading it",
+        display_order = 99
+    )]
+    no_download: bool,
+    /// Inputs of a `-i -` list or `--batch` run at the same time
+    #[arg(
+        long = "list-jobs",
+        global = true,
+        value_name = "N",
+        default_value_t = 1,
+        help = "With -i - or --batch, run the mode for this many of the inputs at the same time; --jobs of a mode still sets the frames each of them processes at once",
+        display_order = 99
+    )]
+    list_jobs: usize,
+    /// Seed of the randomness of effects
+    #[arg(
+        long = "seed",
+        global = true,
+        value_name = "N",
+        help = "Seed the randomness of effects, such as the noise of G'MIC presets, so a run gives the same output again; without it a random seed is generated, and either is recorded in the manifest",
+        display_order = 99
+    )]
+    seed: Option<Seed>,
+    /// Command run after the mode succeeds
+    #[arg(
+        long = "on-success",
+        global = true,
+        value_name = "CMD",
+        help = "Shell command run after the mode succeeds, getting the outputs in FXP_OUTPUTS, one per line, and the JSON summary of the run on stdin; defaults to on_success of the configuration",
+        display_order = 99
+    )]
+    on_success: Option<String>,
+    /// URL the summary of the run is posted to
+    #[arg(
+        long = "webhook",
+        global = true,
+        value_name = "URL",
+        help = "Post the JSON summary of the run, with its outputs, to URL once the mode succeeds or fails; defaults to webhook of the configuration",
+        display_order = 99
+    )]
+    webhook: Option<String>,
+    /// Announce the end of a long run on the desktop
+    #[arg(
+        long = "notify",
+        global = true,
+        help = "Send a desktop notification when the run finishes or fails, if it took longer than --notify-after",
+        display_order = 99
+    )]
+    notify: bool,
+    /// Seconds a run must take to be announced by --notify
+    #[arg(
+        long = "notify-after",
+        global = true,
+        value_name = "SECONDS",
+        requires = "notify",
+        help = "Only notify of runs taking at least SECONDS; defaults to notify_after_seconds of the configuration",
+        display_order = 99
+    )]
+    notify_after: Option<u64>,
+    /// How to report progress
+    #[arg(
+        long = "progress",
+        global = true,
+        help = "How to report progress: bar, plain (a line every few seconds) or none; defaults to bar on a terminal and plain otherwise",
+        display_order = 99
+    )]
+    progress: Option<ProgressMode>,
+    /// Write the log to this file
+    #[arg(
+        long = "log-file",
+        global = true,
+        help = "Write the log to this file instead of app.log in the default log directory",
+        display_order = 100
+    )]
+    log_file: Option<PathBuf>,
+    /// Do not write a log file
+    #[arg(
+        long = "no-log-file",
+        global = true,
+        conflicts_with = "log_file",
+        help = "Log to the console only",
+        display_order = 100
+    )]
+    no_log_file: bool,
+    /// Format of the log records
+    #[arg(
+        long = "log-format",
+        global = true,
+        help = "Format of the log records: plain or json",
+        default_value = "plain",
+        display_order = 100
+    )]
+    log_format: LogFormat,
+    /// Where to report the timed stages of a run
+    #[arg(
+        long = "trace-output",
+        global = true,
+        help = "Where to report timed stages: log (debug records), json (trace.jsonl in the log directory) or json:PATH",
+        default_value = "log",
+        display_order = 100
+    )]
+    trace_output: TraceOutput,
+    /// Keep the intermediate files of the Exporter and Clipper
+    #[arg(
+        long = "keep-temp",
+        global = true,
+        value_name = "DIR",
+        num_args = 0..=1,
+        require_equals = true,
+        help = "Keep and list the intermediate videos of the Exporter and Clipper, in DIR or in fxp_videoclipper in the system temporary directory; debug builds always keep them",
+        display_order = 100
+    )]
+    keep_temp: Option<Option<PathBuf>>,
+}
+
+impl GlobalOptions {
+    /// Returns what to do with an existing output; by default a new `_N` suffixed one is made.
+    fn collision_policy(&self) -> CollisionPolicy {
+        if self.overwrite {
+            CollisionPolicy::Overwrite
+        } else if self.append {
+            CollisionPolicy::Append
+        } else if self.error_if_exists {
+            CollisionPolicy::ErrorIfExists
+        } else {
+            CollisionPolicy::Unique
+        }
+    }
+
+    /// Returns whether a URL input that is not cached yet may be downloaded; a dry run
+    /// never downloads.
+    fn downloads(&self) -> bool {
+        !self.no_download && !self.dry_run
+    }
+
+    /// Returns what to do when another run holds the lock of an output; by default fail.
+    fn lock_policy(&self) -> LockPolicy {
+        if self.wait {
+            LockPolicy::Wait
+        } else if self.force_lock {
+            LockPolicy::Force
+        } else {
+            LockPolicy::Fail
+        }
+    }
+
+    /// Returns where the intermediate files of a run are kept, if anywhere.
+    ///
+    /// `--keep-temp` without a directory, and debug builds without the option, keep them
+    /// in `fxp_output::default_keep_temp_dir`.
+    fn keep_temp(&self) -> Option<PathBuf> {
+        match &self.keep_temp {
+            Some(Some(directory)) => Some(directory.clone()),
+            Some(None) => Some(fxp_output::default_keep_temp_dir()),
+            None => cfg!(debug_assertions).then(fxp_output::default_keep_temp_dir),
+        }
+    }
+
+    /// Returns where the log file is written.
+    fn log_file(&self) -> LogFile {
+        if self.no_log_file {
+            LogFile::Disabled
+        } else if let Some(path) = &self.log_file {
+            LogFile::Path(path.clone())
+        } else {
+            LogFile::Default
+        }
+    }
+}
+
 #[derive(Args, Debug)]
 struct ClipperCommonOptions {
-    /// Optional path to the MP3 file (Exporter, Sampler)
-    #[arg(short = 'a', long = "audio", help = "Optional path to the MP3 file ")]
+    /// Optional path to the audio file (Exporter, Sampler)
+    #[arg(
+        short = 'a',
+        long = "audio",
+        value_name = "AUDIO",
+        help = "Optional audio file (mp3, wav, flac, ogg, m4a, aac, opus or any other ffprobe finds audio in), or a directory holding one"
+    )]
     mp3: Option<String>,
     /// Frames per second to extract (Exporter)
-    #[arg(short, long, help = "Frames per second to extract \n")]
-    fps: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Frames per second to extract, e.g. 30, 29.97 or 30000/1001 \n"
+    )]
+    fps: Option<FrameRate>,
 }
 
 #[derive(Args, Debug)]
 struct CommonOptions {
-    /// Optional path to the MP3 file (Exporter, Sampler)
-    #[arg(short = 'a', long = "audio", help = "Optional path to the MP3 file ")]
+    /// Optional path to the audio file (Exporter, Sampler)
+    #[arg(
+        short = 'a',
+        long = "audio",
+        value_name = "AUDIO",
+        help = "Optional audio file (mp3, wav, flac, ogg, m4a, aac, opus or any other ffprobe finds audio in), or a directory holding one"
+    )]
     mp3: Option<String>,
     /// Duration in milliseconds to cut the video (Exporter, Sampler)
     #[arg(short, long, help = "Duration in milliseconds to cut the video ")]
     duration: Option<String>,
     /// Frames per second to extract (Exporter)
-    #[arg(short, long, help = "Frames per second to extract \n")]
-    fps: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Frames per second to extract, e.g. 30, 29.97 or 30000/1001 \n"
+    )]
+    fps: Option<FrameRate>,
 }
 
 #[derive(Args, Debug)]
 struct SamplerCommonOptions {
-    /// Optional path to the MP3 file (Exporter, Sampler)
-    #[arg(short = 'a', long = "audio", help = "Optional path to the MP3 file ")]
+    /// Optional path to the audio file (Exporter, Sampler)
+    #[arg(
+        short = 'a',
+        long = "audio",
+        value_name = "AUDIO",
+        help = "Optional audio file (mp3, wav, flac, ogg, m4a, aac, opus or any other ffprobe finds audio in), or a directory holding one"
+    )]
     mp3: Option<String>,
     /// Duration in milliseconds to cut the video (Exporter, Sampler)
     #[arg(short, long, help = "Duration in milliseconds to cut the video \n")]
     duration: Option<String>,
 }
 
+#[derive(Args, Debug)]
+struct SelectionOptions {
+    /// Range of frame numbers to process (Clipper, Gmicer, Merger)
+    #[arg(
+        long = "frames",
+        help = "Process only the frames numbered FIRST..LAST, both included; either end may be left open"
+    )]
+    frames: Option<fxp_filenames::FrameRange>,
+    /// Stride through the frames (Clipper, Gmicer, Merger)
+    #[arg(
+        long = "every",
+        help = "Process only every Nth frame",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    every: u32,
+}
+
+impl SelectionOptions {
+    /// Returns the frames the options select.
+    fn selection(&self) -> fxp_filenames::FrameSelection {
+        fxp_filenames::FrameSelection {
+            range: self.frames,
+            every: self.every as usize,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct WorkerOptions {
+    /// Number of images processed at once (Clutter, Merger)
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        help = "Process this many images at the same time, as long as they fit in --max-memory; defaults to jobs of the configuration",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    jobs: Option<u16>,
+    /// Memory the images processed at once may take (Clutter, Merger)
+    #[arg(
+        long = "max-memory",
+        value_name = "MB",
+        help = "Memory the images processed at the same time may take; jobs wait for each other beyond it. Defaults to half of the system memory",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_memory: Option<u64>,
+}
+
+impl WorkerOptions {
+    /// Returns the memory limit in bytes, half of the system memory if not given.
+    fn max_memory_bytes(&self) -> usize {
+        self.max_memory
+            .map_or_else(fxp_stream::default_max_memory, |megabytes| {
+                megabytes as usize * 1024 * 1024
+            })
+    }
+
+    /// Adds the workers and their memory limit to a plan.
+    fn plan(&self, plan: fxp_output::Plan, config: &Config) -> fxp_output::Plan {
+        plan.entry("jobs", get_jobs(self.jobs, config)).entry(
+            "max memory",
+            format!("{} MB", self.max_memory_bytes() / (1024 * 1024)),
+        )
+    }
+}
+
+#[derive(Args, Debug)]
+struct EncodingOptions {
+    /// Image format of the output frames (Merger, Clutter, Grader, Captioner)
+    #[arg(
+        long = "output-format",
+        value_name = "FORMAT",
+        help = "Write the frames as png, jpeg (progressive), webp or avif, named with its extension; defaults to the format of the input frames"
+    )]
+    output_format: Option<fxp_stream::FrameFormat>,
+    /// Quality of lossy output frames (Merger, Clutter, Grader, Captioner)
+    #[arg(
+        long = "quality",
+        value_name = "QUALITY",
+        help = "Quality of jpeg, webp and avif frames, 1 to 100",
+        default_value_t = fxp_stream::DEFAULT_QUALITY,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        requires = "output_format"
+    )]
+    quality: u8,
+    /// Lossless WebP output (Merger, Clutter, Grader, Captioner)
+    #[arg(
+        long = "lossless",
+        help = "Write webp frames losslessly, ignoring --quality",
+        requires = "output_format"
+    )]
+    lossless: bool,
+}
+
+impl EncodingOptions {
+    /// Returns how the frames are encoded, checking that lossless is asked of WebP only.
+    fn encoder(&self) -> Result<fxp_stream::FrameEncoder> {
+        let encoder = fxp_stream::FrameEncoder {
+            format: self.output_format,
+            quality: self.quality,
+            lossless: self.lossless,
+        };
+        encoder.validate().map_err(exit::InvalidInput)?;
+        Ok(encoder)
+    }
+
+    /// Adds the output format to a plan, if one was given.
+    fn plan(&self, plan: fxp_output::Plan) -> Result<fxp_output::Plan> {
+        let encoder = self.encoder()?;
+        Ok(match encoder.format {
+            Some(_) => plan.entry("output format", encoder),
+            None => plan,
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+struct RetryOptions {
+    /// Retries of a failed per-frame ffmpeg or gmic call (Exporter, Sampler, Gmicer)
+    #[arg(
+        long = "retries",
+        value_name = "N",
+        help = "Retry a frame whose ffmpeg or gmic call fails up to N times before the run fails, for flaky network filesystems; defaults to retries of the configuration"
+    )]
+    retries: Option<u32>,
+    /// Wait before the first retry (Exporter, Sampler, Gmicer)
+    #[arg(
+        long = "retry-backoff",
+        value_name = "MS",
+        help = "Milliseconds to wait before the first retry, doubled before each further one; defaults to retry_backoff_ms of the configuration"
+    )]
+    retry_backoff: Option<u64>,
+}
+
+impl RetryOptions {
+    /// Returns the retry policy, falling back to the configuration.
+    fn policy(&self, config: &Config) -> fxp_output::RetryPolicy {
+        get_retry_policy(self.retries, self.retry_backoff, config)
+    }
+
+    /// Adds the retry policy to a plan.
+    fn plan(&self, plan: fxp_output::Plan, config: &Config) -> fxp_output::Plan {
+        plan.entry("failed frames", self.policy(config))
+    }
+}
+
+#[derive(Args, Debug)]
+struct CollageOptions {
+    /// Tile the samples into one image (Sampler)
+    #[arg(
+        long = "collage",
+        help = "Also tile the sampled frames into collage.png, a grid with the timestamp of each frame",
+        conflicts_with = "clip_length"
+    )]
+    collage: bool,
+    /// Frames per row of the collage (Sampler)
+    #[arg(
+        long = "collage-columns",
+        value_name = "N",
+        help = "Frames per row of the collage; defaults to a grid as square as possible",
+        requires = "collage",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    collage_columns: Option<u32>,
+    /// Leave the timestamps off the collage (Sampler)
+    #[arg(
+        long = "no-collage-labels",
+        help = "Leave the timestamps off the collage",
+        requires = "collage"
+    )]
+    no_collage_labels: bool,
+}
+
+impl CollageOptions {
+    /// Returns the collage to write, if `--collage` was given.
+    fn collage(&self) -> Option<fxp_sampler::Collage> {
+        self.collage.then_some(fxp_sampler::Collage {
+            columns: self.collage_columns,
+            labels: !self.no_collage_labels,
+        })
+    }
+
+    /// Adds the collage to a plan.
+    fn plan(&self, plan: fxp_output::Plan) -> fxp_output::Plan {
+        match self.collage() {
+            Some(collage) => plan.entry("collage", collage),
+            None => plan,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct PipOptions {
+    /// Shrink the second directory into a picture-in-picture (Merger)
+    #[arg(
+        long = "pip",
+        value_name = "PERCENT",
+        num_args = 0..=1,
+        default_missing_value = "30",
+        help = "Shrink the second directory's images to this percent of the frame's width and place them over the first's as a picture-in-picture, blended with --opacity (use -t 1 for an opaque picture); 30 if no percent is given",
+        value_parser = parse_pip_scale,
+        conflicts_with_all = ["region", "region_circle"]
+    )]
+    pip: Option<f32>,
+    /// Where the picture sits (Merger)
+    #[arg(
+        long = "pip-position",
+        help = "Where the picture sits: top-left, top-right, bottom-left, bottom-right, center, or the picture's top left corner as X,Y",
+        default_value = "bottom-right",
+        requires = "pip"
+    )]
+    pip_position: fxp_merger::PipPosition,
+    /// Distance between the picture and the frame's edges (Merger)
+    #[arg(
+        long = "pip-margin",
+        value_name = "PIXELS",
+        help = "Pixels between a corner picture, with its border, and the frame's edges",
+        default_value_t = fxp_merger::DEFAULT_PIP_MARGIN,
+        requires = "pip"
+    )]
+    pip_margin: u32,
+    /// Frame the picture with a border (Merger)
+    #[arg(
+        long = "pip-border",
+        value_name = "WIDTH[,COLOR]",
+        help = "Frame the picture with a border this many pixels wide, white or of a color such as black or #ffcc00",
+        requires = "pip"
+    )]
+    pip_border: Option<fxp_merger::PipBorder>,
+    /// Draw a drop shadow under the picture (Merger)
+    #[arg(
+        long = "pip-shadow",
+        help = "Draw a drop shadow below and to the right of the picture",
+        requires = "pip"
+    )]
+    pip_shadow: bool,
+}
+
+impl PipOptions {
+    /// Returns the picture-in-picture, if `--pip` was given.
+    fn pip(&self) -> Option<fxp_merger::PictureInPicture> {
+        self.pip.map(|scale| fxp_merger::PictureInPicture {
+            scale,
+            position: self.pip_position,
+            margin: self.pip_margin,
+            border: self.pip_border,
+            shadow: self.pip_shadow,
+        })
+    }
+
+    /// Adds the picture-in-picture to a plan.
+    fn plan(&self, plan: fxp_output::Plan) -> fxp_output::Plan {
+        match self.pip() {
+            Some(pip) => plan.entry("picture-in-picture", pip),
+            None => plan,
+        }
+    }
+}
+
+/// Parses the width of a picture-in-picture, in percent of the frame's.
+fn parse_pip_scale(s: &str) -> Result<f32, String> {
+    match s.trim_end_matches('%').parse::<f32>() {
+        Ok(scale) if scale > 0.0 && scale <= 100.0 => Ok(scale),
+        _ => Err(format!(
+            "Invalid picture size '{}', expected a percent above 0 and up to 100",
+            s
+        )),
+    }
+}
+
 #[derive(Args, Debug)]
 struct ClipperOptions {
     #[command(flatten)]
     io: ClipperInputOutput,
     #[command(flatten)]
     common_options: ClipperCommonOptions,
+    #[command(flatten)]
+    selection: SelectionOptions,
+    /// How to handle missing frame numbers (Clipper)
+    #[arg(
+        long = "gaps",
+        help = "How to handle missing frame numbers: resequence, fill or error",
+        default_value = "error"
+    )]
+    gaps: fxp_clipper::GapPolicy,
+    /// Stream a preview instead of writing the video (Clipper)
+    #[arg(
+        long = "preview",
+        help = "Stream a preview instead of writing the video: ffplay (default), http or http:PORT",
+        num_args = 0..=1,
+        default_missing_value = "ffplay"
+    )]
+    preview: Option<fxp_clipper::PreviewTarget>,
+    /// Render only the first N seconds at a reduced resolution (Clipper)
+    #[arg(
+        long = "preview-seconds",
+        help = "Render only the first N seconds at half the pixel limit, for quick iteration",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    preview_seconds: Option<u32>,
+    /// Append to an existing output video (Clipper)
+    #[arg(
+        long = "append-clip",
+        help = "Append the frames to the end of the output video if it exists, instead of replacing it"
+    )]
+    append_clip: bool,
+    /// Play the frames backwards (Clipper)
+    #[arg(long = "reverse", help = "Play the frames from the last to the first")]
+    reverse: bool,
+    /// Follow the frames with themselves backwards (Clipper)
+    #[arg(
+        long = "pingpong",
+        help = "Follow the frames with the same frames backwards, for a boomerang clip"
+    )]
+    pingpong: bool,
+    /// Stretch the frames to the audio length (Clipper)
+    #[arg(
+        long = "fit-audio",
+        help = "Repeat or drop frames evenly so the frames last exactly as long as the audio"
+    )]
+    fit_audio: bool,
+    /// End the clip with the frames (Clipper)
+    #[arg(
+        long = "trim-audio",
+        conflicts_with_all = ["fit_audio", "pad_frames"],
+        help = "When the frames end before the audio, end the clip with them and cut the audio"
+    )]
+    trim_audio: bool,
+    /// Hold the last frame until the audio ends (Clipper)
+    #[arg(
+        long = "pad-frames",
+        conflicts_with = "fit_audio",
+        help = "When the frames end before the audio, repeat the last frame until the audio ends"
+    )]
+    pad_frames: bool,
+    /// Shift the audio against the frames (Clipper)
+    #[arg(
+        long = "audio-offset",
+        value_name = "MS",
+        default_value_t = 0,
+        allow_negative_numbers = true,
+        help = "Milliseconds to delay the audio by, or to advance it when negative"
+    )]
+    audio_offset: i64,
+    /// Skip the silence the audio starts with (Clipper)
+    #[arg(
+        long = "skip-audio-silence",
+        help = "Detect the silence the audio starts with and cut it, so the sound starts with the first frame; --audio-offset then shifts from there; frames exported with --align-to-audio-onset already start on the music, whose onset is used instead"
+    )]
+    skip_audio_silence: bool,
+    /// Normalize the loudness of the audio (Clipper)
+    #[arg(
+        long = "normalize-audio",
+        value_name = "LUFS",
+        num_args = 0..=1,
+        default_missing_value = "-23",
+        allow_negative_numbers = true,
+        value_parser = clap::value_parser!(i32).range(-70..=-5),
+        help = "Normalize the audio to this loudness with EBU R128 loudnorm, -23 LUFS if no value is given"
+    )]
+    normalize_audio: Option<i32>,
+    /// Container to write (Clipper)
+    #[arg(
+        long = "format",
+        help = "Write an mp4 video (default), or a silent looping gif or apng",
+        default_value = "mp4"
+    )]
+    format: fxp_clipper::ClipFormat,
+    /// Segmented output for streaming (Clipper)
+    #[arg(
+        long = "segment",
+        value_name = "FORMAT",
+        conflicts_with_all = ["format", "append_clip"],
+        value_parser = ["hls", "dash"],
+        help = "Cut the video into segments for streaming, hls (an m3u8 playlist and ts segments) or dash (an mpd manifest and m4s segments), instead of one mp4"
+    )]
+    segment: Option<String>,
+    /// Largest width of the clip (Clipper)
+    #[arg(
+        long = "max-width",
+        help = "Scale the frames down to at most this width, keeping the aspect ratio",
+        value_parser = clap::value_parser!(u32).range(2..)
+    )]
+    max_width: Option<u32>,
+    /// Number of plays of an animation (Clipper)
+    #[arg(
+        long = "loop-count",
+        help = "How many times a gif or apng plays; 0 loops forever",
+        default_value = "0"
+    )]
+    loop_count: u32,
+    /// Video codec (Clipper)
+    #[arg(
+        long = "codec",
+        help = "Video codec of an mp4: h264 (default) or h265",
+        default_value = "h264"
+    )]
+    codec: fxp_clipper::VideoCodec,
+    /// Constant rate factor (Clipper)
+    #[arg(
+        long = "crf",
+        help = "Constant rate factor from 0 (lossless) to 51; lower is better and larger",
+        conflicts_with = "bitrate",
+        value_parser = clap::value_parser!(u8).range(0..=51)
+    )]
+    crf: Option<u8>,
+    /// Encoder speed preset (Clipper)
+    #[arg(
+        long = "preset",
+        help = "Encoder speed preset, from ultrafast to placebo; slower presets compress better",
+        value_parser = clap::builder::PossibleValuesParser::new(fxp_clipper::PRESETS)
+    )]
+    preset: Option<String>,
+    /// Average bitrate (Clipper)
+    #[arg(
+        long = "bitrate",
+        help = "Average video bitrate, e.g. 4M or 2500k, instead of a constant quality"
+    )]
+    bitrate: Option<String>,
+    /// Two-pass encoding (Clipper)
+    #[arg(
+        long = "two-pass",
+        requires = "bitrate",
+        help = "Encode twice to meet the bitrate precisely"
+    )]
+    two_pass: bool,
+    /// 10-bit encoding (Clipper)
+    #[arg(
+        long = "ten-bit",
+        help = "Encode 10 bits per channel with h265, keeping the gradients of 16-bit frames such as those of --high-bit-depth"
+    )]
+    ten_bit: bool,
+    /// Resize frames of a differing size (Clipper)
+    #[arg(
+        long = "auto-fix",
+        help = "Resize frames whose size differs from most frames' instead of refusing to encode"
+    )]
+    auto_fix: bool,
+    /// Chapter markers (Clipper)
+    #[arg(
+        long = "chapters",
+        value_name = "FILE",
+        help = "Embed chapters into the mp4 from a markers file, one frame number and title per line"
+    )]
+    chapters: Option<String>,
+    /// WebVTT chapters track (Clipper)
+    #[arg(
+        long = "webvtt",
+        requires = "chapters",
+        help = "Also write the chapters as a WebVTT track next to the video"
+    )]
+    webvtt: bool,
+    /// Keep the timing of the source video (Clipper)
+    #[arg(
+        long = "source-timing",
+        value_name = "FRAMES_JSON",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["reverse", "pingpong", "fit_audio"],
+        help = "Show each frame as long as it lasted in the source video, from the frames.json the Exporter writes with --frames-json; defaults to frames.json in the input directory"
+    )]
+    source_timing: Option<Option<PathBuf>>,
+    /// HDR metadata of the source video (Clipper)
+    #[arg(
+        long = "hdr-from",
+        value_name = "VIDEO",
+        requires = "ten_bit",
+        help = "Copy the HDR color tags and mastering metadata of the video the frames were exported from into the clip"
+    )]
+    hdr_from: Option<PathBuf>,
+    /// How the frames of a still image input move (Clipper)
+    #[arg(
+        long = "still-motion",
+        value_name = "MOTION",
+        help = "When the input is a single image: static, or ken-burns for a slow zoom",
+        default_value = "static"
+    )]
+    still_motion: fxp_clipper::StillMotion,
+}
+
+#[derive(Args, Debug)]
+struct GmicerOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    #[command(flatten)]
+    selection: SelectionOptions,
+    /// Read the subdirectories of the input too (Gmicer)
+    #[arg(
+        long = "recursive",
+        help = "Process the subdirectories of the input too, keeping their structure in the output; -i also takes a glob pattern such as 'shots/**/frame_*.png'"
+    )]
+    recursive: bool,
+    /// Start the GMIC arguments with a named preset (Gmicer)
+    #[arg(
+        long = "preset",
+        value_name = "NAME",
+        help = "Apply a named look: cartoon, glow, noir, oldfilm, sepia, sketch, vhs, vignette, or one of gmic_presets in the configuration; GMIC arguments given too are applied after it"
+    )]
+    preset: Option<String>,
+
+    /// Arguments for GMIC command
+    #[arg(
+        help = "Arguments for GMIC command; {frame}, {t} (0 at the first image to 1 at the last) and {total} are replaced per image, e.g. 'blur {10*{t}}'",
+        num_args = 0..,
+        allow_hyphen_values = true
+    )]
+    gmic_args: Option<Vec<String>>,
+    #[command(flatten)]
+    retry: RetryOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct ClutterOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    /// Path to the source image used for CLUT (Clutter mode)
+    #[arg(
+        short = 'l',
+        long = "clut",
+        help = "Path to the source image used for CLUT",
+        required_unless_present = "reference"
+    )]
+    pub clut_image: Option<String>,
+    /// Reference image to transfer the colors of (Clutter mode)
+    #[arg(
+        long = "reference",
+        value_name = "IMAGE",
+        help = "Match the colors of the frames to this image instead of applying a CLUT",
+        conflicts_with = "clut_image"
+    )]
+    pub reference: Option<String>,
+    /// Opacity of the clutted images over the originals (Clutter mode)
+    #[arg(
+        long = "clut-opacity",
+        help = "Blend the clutted images over the originals with this opacity, in one pass per image"
+    )]
+    pub clut_opacity: Option<f32>,
+    /// Opacities to clut with, one output directory each (Clutter mode)
+    #[arg(
+        long = "clut-multiple",
+        help = "Clut once per opacity, into <output>_<opacity> each; without values, uses multiple_opacities from the configuration",
+        num_args = 0..,
+        value_delimiter = ',',
+        conflicts_with = "clut_opacity"
+    )]
+    pub clut_multiple: Option<Vec<f32>>,
+    #[command(flatten)]
+    encoding: EncodingOptions,
+    #[command(flatten)]
+    workers: WorkerOptions,
+}
+
+#[derive(Args, Debug)]
+struct GraderOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    /// Brightness change in stops (Grader mode)
+    #[arg(
+        long = "exposure",
+        value_name = "VALUE",
+        help = "Brightness change in stops, -10 to 10; a number, or keyframes FRAME:VALUE,... interpolated across the frames, e.g. 1:0,120:1.5",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    exposure: fxp_grader::Keyframes,
+    /// Contrast change (Grader mode)
+    #[arg(
+        long = "contrast",
+        value_name = "VALUE",
+        help = "Contrast change around middle grey, -1 (flat) to 1 (twice as steep); a number or keyframes",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    contrast: fxp_grader::Keyframes,
+    /// Saturation change (Grader mode)
+    #[arg(
+        long = "saturation",
+        value_name = "VALUE",
+        help = "Saturation change, -1 (black and white) to 1 (twice as saturated); a number or keyframes",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    saturation: fxp_grader::Keyframes,
+    /// Color temperature change (Grader mode)
+    #[arg(
+        long = "temperature",
+        value_name = "VALUE",
+        help = "Color temperature change, -1 (cooler) to 1 (warmer); a number or keyframes",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    temperature: fxp_grader::Keyframes,
+    #[command(flatten)]
+    encoding: EncodingOptions,
+}
+
+impl GraderOptions {
+    /// Returns the color controls the options set.
+    fn grading(&self) -> fxp_grader::Grading {
+        fxp_grader::Grading {
+            exposure: self.exposure.clone(),
+            contrast: self.contrast.clone(),
+            saturation: self.saturation.clone(),
+            temperature: self.temperature.clone(),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct CaptionerOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    /// Captions file (Captioner mode)
+    #[arg(
+        long = "captions",
+        value_name = "FILE",
+        help = "Captions file, one caption per line as FRAME or FIRST-LAST followed by the text, e.g. 1-120 First line; \\n breaks a line and # starts a comment"
+    )]
+    captions: String,
+    /// Font file (Captioner mode)
+    #[arg(
+        long = "font",
+        value_name = "FILE",
+        help = "TrueType or OpenType font to draw with; defaults to a bold sans serif font installed in a usual place, such as DejaVu Sans Bold"
+    )]
+    font: Option<PathBuf>,
+    /// Height of the text (Captioner mode)
+    #[arg(
+        long = "font-size",
+        value_name = "PIXELS",
+        help = "Height of the text in pixels",
+        default_value_t = fxp_captioner::DEFAULT_FONT_SIZE
+    )]
+    font_size: f32,
+    /// Color of the text (Captioner mode)
+    #[arg(
+        long = "text-color",
+        value_name = "COLOR",
+        help = "Color of the text: white, black or #rrggbb[aa]",
+        default_value = "white",
+        value_parser = fxp_captioner::parse_color
+    )]
+    text_color: image::Rgba<u8>,
+    /// Outline around the letters (Captioner mode)
+    #[arg(
+        long = "outline",
+        value_name = "WIDTH[,COLOR]",
+        help = "Outline around the letters, its width in pixels and optionally its color, e.g. 3,#202020; 0 draws none",
+        default_value = "2,black"
+    )]
+    outline: fxp_captioner::Outline,
+    /// Where the text sits (Captioner mode)
+    #[arg(
+        long = "text-position",
+        value_name = "POSITION",
+        help = "Where the text sits: top, center, bottom, or X,Y for its top left corner in pixels",
+        default_value = "bottom"
+    )]
+    text_position: fxp_captioner::TextPosition,
+    /// Distance of the text from the edge (Captioner mode)
+    #[arg(
+        long = "text-margin",
+        value_name = "PIXELS",
+        help = "Distance in pixels between text at the top or bottom and the edge of the frame",
+        default_value_t = fxp_captioner::DEFAULT_TEXT_MARGIN
+    )]
+    text_margin: u32,
+    #[command(flatten)]
+    encoding: EncodingOptions,
+}
+
+impl CaptionerOptions {
+    /// Returns how the options draw the text.
+    fn style(&self) -> fxp_captioner::CaptionStyle {
+        fxp_captioner::CaptionStyle {
+            font_size: self.font_size,
+            color: self.text_color,
+            outline: Some(self.outline).filter(|outline| outline.width > 0),
+            position: self.text_position,
+            margin: self.text_margin,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct DedupOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    /// Hash distance below which a frame counts as a duplicate (Dedup mode)
+    #[arg(
+        long = "threshold",
+        help = "Drop frames whose perceptual hash differs from the last kept frame in fewer than this many of 64 bits",
+        default_value = "5",
+        value_parser = clap::value_parser!(u32).range(0..=64)
+    )]
+    threshold: u32,
+    /// Directory to copy the dropped frames into (Dedup mode)
+    #[arg(
+        long = "duplicates",
+        help = "Copy the dropped frames into this directory for review"
+    )]
+    duplicates: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ProcessOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    #[command(flatten)]
+    selection: SelectionOptions,
+    /// Command to run on every frame (Process mode)
+    #[arg(
+        long = "cmd",
+        value_name = "COMMAND",
+        help = "Run this command once per frame, with {input}, {output}, {index} and {total} replaced, e.g. 'magick {input} -negate {output}'",
+        required_unless_present = "processor",
+        conflicts_with = "processor"
+    )]
+    cmd: Option<String>,
+    /// Named processor to run on every frame (Process mode)
+    #[arg(
+        long = "processor",
+        value_name = "NAME",
+        help = "Run the processor of this name, one of processors in the configuration"
+    )]
+    processor: Option<String>,
+    /// Number of frames processed at once (Process mode)
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        help = "Process this many frames at the same time, each with its own command; defaults to jobs of the configuration",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    jobs: Option<u16>,
+}
+
+#[derive(Args, Debug)]
+struct ChainOptions {
+    /// Directory of frames to run through the chain (Chain mode)
+    #[arg(short = 'i', long, help = "Input directory ")]
+    input: String,
+    /// Video to write (Chain mode)
+    #[arg(short = 'o', long, help = "Output video \n")]
+    output: Option<String>,
+    #[command(flatten)]
+    common_options: ClipperCommonOptions,
+    /// Reference image whose colors are transferred to the frames (Chain mode)
+    #[arg(
+        long = "reference",
+        value_name = "IMAGE",
+        help = "Transfer the colors of this image to the frames, as the Clutter's --reference does",
+        required_unless_present = "overlay"
+    )]
+    reference: Option<String>,
+    /// Frames blended over the frames (Chain mode)
+    #[arg(
+        long = "overlay",
+        value_name = "DIR",
+        help = "Blend the frames of this directory over the frames, as the Merger does"
+    )]
+    overlay: Option<String>,
+    /// Opacity of the overlay (Chain mode)
+    #[arg(
+        short = 't',
+        long = "opacity",
+        help = "Opacity of the overlay, the configured opacity if not given",
+        requires = "overlay"
+    )]
+    opacity: Option<f32>,
+    /// Memory the frames between stages may take (Chain mode)
+    #[arg(
+        long = "stream-memory",
+        value_name = "MB",
+        help = "Memory the frames waiting between stages may take before they are spilled to disk",
+        default_value = "1024"
+    )]
+    stream_memory: u64,
+}
+
+#[derive(Args, Debug)]
+struct InterpolatorOptions {
+    #[command(flatten)]
+    io: InputOutput,
+    /// Frame rate of the interpolated frames (Interpolator mode)
+    #[arg(
+        short = 'f',
+        long = "fps",
+        help = "Frame rate of the interpolated frames, e.g. 30, 60 or 59.94"
+    )]
+    fps: FrameRate,
+    /// Frame rate of the input frames (Interpolator mode)
+    #[arg(
+        long = "source-fps",
+        help = "Frame rate of a directory of frames; with RIFE, also the rate a video is sampled at",
+        default_value = "15"
+    )]
+    source_fps: FrameRate,
+    /// Engine generating the intermediate frames (Interpolator mode)
+    #[arg(
+        long = "engine",
+        help = "Engine generating the intermediate frames: minterpolate (default), rife or rife:PATH",
+        default_value = "minterpolate"
+    )]
+    engine: fxp_interpolator::Engine,
+}
+
+#[derive(Args, Debug)]
+struct ZoopraxiscopeOptions {
+    /// Directory of frames, or sprite sheet with --explode (Zoopraxiscope mode)
+    #[arg(
+        short = 'i',
+        long,
+        help = "Directory of frames to tile, or the sprite sheet to explode with --explode"
+    )]
+    input: String,
+    /// Output sheet, or directory with --explode (Zoopraxiscope mode)
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output sprite sheet, or directory of frames with --explode \n"
+    )]
+    output: Option<String>,
+    /// Explode a sprite sheet back into frames (Zoopraxiscope mode)
+    #[arg(
+        long = "explode",
+        help = "Cut a sprite sheet back into frames, by its atlas or by --columns and --rows"
+    )]
+    explode: bool,
+    /// Frames per row (Zoopraxiscope mode)
+    #[arg(
+        long = "columns",
+        help = "Frames per row; a square grid by default, all frames for a filmstrip",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    columns: Option<u32>,
+    /// Rows of a sheet without an atlas (Zoopraxiscope mode)
+    #[arg(
+        long = "rows",
+        requires = "explode",
+        help = "Rows of a sprite sheet to explode that has no atlas",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    rows: Option<u32>,
+    /// Pixels around and between the frames (Zoopraxiscope mode)
+    #[arg(
+        long = "padding",
+        value_name = "PX",
+        help = "Pixels around and between the frames",
+        default_value_t = 0
+    )]
+    padding: u32,
+    /// Color of the padding and empty cells (Zoopraxiscope mode)
+    #[arg(
+        long = "background",
+        help = "Color of the padding and empty cells: transparent, black, white or #rrggbb[aa]",
+        default_value = "transparent"
+    )]
+    background: fxp_zoopraxiscope::Background,
 }
 
 #[derive(Args, Debug)]
-struct GmicerOptions {
+struct VisualizerOptions {
     #[command(flatten)]
-    io: InputOutput,
-
-    /// Arguments for GMIC command
+    io: AudioInputOutput,
+    /// Frame rate of the rendered frames (Visualizer mode)
     #[arg(
-        help = "Arguments for GMIC command ",
-        num_args = 0..,
-        allow_hyphen_values = true
+        short = 'f',
+        long = "fps",
+        help = "Frame rate of the rendered frames; use the rate the video was exported at to merge them"
     )]
-    gmic_args: Option<Vec<String>>,
+    fps: FrameRate,
+    /// What to draw of the audio (Visualizer mode)
+    #[arg(
+        long = "style",
+        help = "What to draw of the audio: waves (transparent background) or spectrum",
+        default_value = "waves"
+    )]
+    style: fxp_visualizer::Visualization,
+    /// Size of the rendered frames (Visualizer mode)
+    #[arg(
+        long = "size",
+        help = "Size of the rendered frames, as WIDTHxHEIGHT",
+        default_value = "1280x720"
+    )]
+    size: fxp_visualizer::FrameSize,
+    /// Color of the waveform (Visualizer mode)
+    #[arg(
+        long = "color",
+        help = "Color of the waveform, as an ffmpeg color such as white or 0x00ff88",
+        default_value = "white"
+    )]
+    color: String,
 }
 
 #[derive(Args, Debug)]
-pub struct ClutterOptions {
+struct StabilizerOptions {
     #[command(flatten)]
-    io: InputOutput,
-    /// Path to the source image used for CLUT (Clutter mode)
+    io: ExporterInputOutput,
+    /// How shaky the video is (Stabilizer mode)
     #[arg(
-        short = 'l',
-        long = "clut",
-        help = "Path to the source image used for CLUT"
+        long = "shakiness",
+        help = "How shaky the video is, from 1 (little) to 10 (a lot)",
+        default_value = "5",
+        value_parser = clap::value_parser!(u8).range(1..=10)
+    )]
+    shakiness: u8,
+    /// Smoothing window in frames (Stabilizer mode)
+    #[arg(
+        long = "smoothing",
+        help = "Frames before and after each frame the camera motion is averaged over",
+        default_value = "10"
     )]
-    pub clut_image: String,
+    smoothing: u32,
 }
 
 #[derive(Args, Debug)]
@@ -110,6 +1295,13 @@ struct SamplerOptions {
     #[command(flatten)]
     io: ExporterInputOutput,
 
+    /// Run for every video of a directory (Sampler)
+    #[arg(
+        long = "batch",
+        help = "Take -i as a directory and run the mode for every video in it, into OUTPUT/<video name> with -o, then print which videos succeeded and failed"
+    )]
+    batch: bool,
+
     /// Extract multiple frames (Sampler)
     #[arg(short = 'u', long = "multiple", help = "Extract multiple frames", action = ArgAction::SetTrue)]
     multiple: bool,
@@ -118,6 +1310,30 @@ struct SamplerOptions {
     #[arg(short = 'n', long = "number", help = "Number of frames to extract", value_parser = clap::value_parser!(usize))]
     number: Option<usize>,
 
+    /// Keep the sharpest of K frames at each sampling point (Sampler)
+    #[arg(
+        long = "best-of",
+        help = "Extract K consecutive frames at each sampling point and keep the sharpest",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..100)
+    )]
+    best_of: u32,
+
+    /// Cut a clip of this length at each sampling point (Sampler)
+    #[arg(
+        long = "clip-length",
+        value_name = "LENGTH",
+        help = "Cut a clip of this length around each sampling point instead of a frame, as sample_clip_N.mp4, e.g. 2s or 500ms",
+        conflicts_with = "best_of"
+    )]
+    clip_length: Option<fxp_sampler::ClipLength>,
+
+    #[command(flatten)]
+    collage: CollageOptions,
+
+    #[command(flatten)]
+    retry: RetryOptions,
+
     #[command(flatten)]
     common_options: SamplerCommonOptions,
 }
@@ -126,41 +1342,379 @@ struct SamplerOptions {
 struct MergerOptions {
     #[command(flatten)]
     io: InputOutput,
-    /// Path to the second image directory (Merger)
+    #[command(flatten)]
+    selection: SelectionOptions,
+    /// Paths to the image directories stacked over the first (Merger)
     #[arg(
         short = 'r',
         long = "second-directory",
-        help = "Path to the second image directory (Merger)"
+        value_name = "DIRECTORY[@OPACITY,MODE]",
+        num_args = 1..,
+        required = true,
+        help = "Path to the second image directory (Merger); several, as -r a b or -r a -r b, are stacked in order like the layers of an image editor, each optionally with @ and its own opacity, blend mode or both, e.g. glow@0.6,screen; modes are normal, multiply, screen, overlay, add and difference"
+    )]
+    layers: Vec<fxp_merger::Layer>,
+    /// Read the subdirectories of both directories too (Merger)
+    #[arg(
+        long = "recursive",
+        help = "Merge the subdirectories of both directories too, pairing them by relative path and keeping their structure in the output; -i and -r also take glob patterns"
     )]
-    directory2: String,
-    /// Opacity level for merging (Merger)
+    recursive: bool,
+    /// Opacity levels for merging (Merger)
     #[arg(
         short = 't',
         long,
-        help = "Opacity level for merging \n",
+        help = "Opacity level for merging; several, as -t 0.2,0.5, merge into one directory each \n",
+        value_delimiter = ',',
         default_value = "0.5"
     )]
-    opacity: f32,
+    opacity: Vec<f32>,
+    /// Merge with the configured list of opacities (Merger)
+    #[arg(
+        long = "multiple",
+        help = "Merge once per opacity in multiple_opacities from the configuration",
+        conflicts_with = "opacity"
+    )]
+    multiple: bool,
+    /// How to pair directories of different lengths (Merger)
+    #[arg(
+        long = "mismatch-policy",
+        help = "How to pair directories of different lengths: truncate merges the images numbered alike; repeat-last, loop and error pair images by position",
+        default_value = "truncate"
+    )]
+    mismatch_policy: fxp_merger::MismatchPolicy,
+    /// Composite using the alpha channels (Merger)
+    #[arg(
+        long = "respect-alpha",
+        help = "Composite the second image over the first using both alpha channels, instead of writing opaque blends"
+    )]
+    respect_alpha: bool,
+    /// Blend in linear light (Merger)
+    #[arg(
+        long = "linear-blend",
+        help = "Blend in linear light instead of sRGB values, for cleaner crossfades and light overlays"
+    )]
+    linear_blend: bool,
+    /// Follow the loudness of an audio track with the opacity (Merger)
+    #[arg(
+        long = "audio-opacity",
+        value_name = "AUDIO",
+        help = "Pulse the opacity of each pair with the loudness of this audio, within --opacity-range",
+        conflicts_with = "multiple"
+    )]
+    audio_opacity: Option<String>,
+    /// Opacities of the quietest and loudest frames (Merger)
+    #[arg(
+        long = "opacity-range",
+        value_name = "MIN,MAX",
+        help = "Opacities of the quietest and loudest frames with --audio-opacity",
+        value_delimiter = ',',
+        default_value = "0,1",
+        requires = "audio_opacity"
+    )]
+    opacity_range: Vec<f32>,
+    /// Blend only within a rectangle (Merger)
+    #[arg(
+        long = "region",
+        value_name = "X,Y,WIDTH,HEIGHT",
+        value_parser = fxp_merger::Region::parse_rectangle,
+        conflicts_with = "region_circle",
+        help = "Blend the second directory only within this rectangle, in pixels of the first directory's images, keeping them as they are elsewhere"
+    )]
+    region: Option<fxp_merger::Region>,
+    /// Blend only within a circle (Merger)
+    #[arg(
+        long = "region-circle",
+        value_name = "CENTER_X,CENTER_Y,RADIUS",
+        value_parser = fxp_merger::Region::parse_circle,
+        help = "Blend the second directory only within this circle, in pixels of the first directory's images"
+    )]
+    region_circle: Option<fxp_merger::Region>,
+    /// Width of the fade at the edge of the region (Merger)
+    #[arg(
+        long = "region-feather",
+        value_name = "PIXELS",
+        default_value_t = fxp_merger::DEFAULT_FEATHER,
+        help = "Pixels over which the blend fades in inside the edge of --region or --region-circle; 0 for a hard edge"
+    )]
+    region_feather: u32,
+    #[command(flatten)]
+    pip: PipOptions,
+    /// Frame rate the merged frames play at (Merger)
+    #[arg(
+        short = 'f',
+        long = "fps",
+        help = "Frame rate the merged frames play at, which aligns them with --audio-opacity",
+        requires = "audio_opacity"
+    )]
+    fps: Option<FrameRate>,
+    #[command(flatten)]
+    encoding: EncodingOptions,
+    #[command(flatten)]
+    workers: WorkerOptions,
 }
 
 #[derive(Args, Debug)]
 struct ExporterOptions {
     #[command(flatten)]
-    io: ExporterInputOutput,
+    io: VideosInputOutput,
+
+    /// Run for every video of a directory (Exporter only)
+    #[arg(
+        long = "batch",
+        help = "Take -i as a directory and run the mode for every video in it, into OUTPUT/<video name> with -o, then print which videos succeeded and failed"
+    )]
+    batch: bool,
 
     /// Maximum upper limit for pixel resolution (Exporter only)
     #[arg(short, long = "pixel-limit", help = "Maximum upper limit for pixel resolution", value_parser = clap::value_parser!(u32))]
     pixel_upper_limit: Option<u32>,
 
+    /// Export only the first N seconds at a reduced resolution (Exporter only)
+    #[arg(
+        long = "preview-seconds",
+        help = "Export only the first N seconds at half the pixel limit, for quick iteration",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    preview_seconds: Option<u32>,
+
+    /// Export even if the frames would not fit on the disk (Exporter only)
+    #[arg(
+        long = "force",
+        help = "Export even if the frames are projected to exceed the free disk space"
+    )]
+    force: bool,
+
+    /// Start the export this far into the video (Exporter only)
+    #[arg(
+        long = "start",
+        help = "Milliseconds of the video to skip before exporting",
+        default_value = "0"
+    )]
+    start: u64,
+
+    /// Draw the frame number or timecode into each frame (Exporter only)
+    #[arg(
+        long = "burn-in",
+        value_name = "TEXT",
+        help = "Draw framenumber or timecode into the bottom-left corner of each frame"
+    )]
+    burn_in: Option<fxp_exporter::BurnIn>,
+
+    /// Number the frames from the first strong onset of the audio (Exporter only)
+    #[arg(
+        long = "align-to-audio-onset",
+        requires = "mp3",
+        help = "Find where the music starts in --audio and export only the frames playing from there; the Clipper skips the audio before it"
+    )]
+    align_to_audio_onset: bool,
+
+    /// Record the source time of each frame (Exporter only)
+    #[arg(
+        long = "frames-json",
+        help = "Write frames.json next to the frames, mapping each frame to its timestamp and frame index in the source video"
+    )]
+    frames_json: bool,
+
+    /// 16-bit frames (Exporter only)
+    #[arg(
+        long = "high-bit-depth",
+        help = "Write 16-bit PNG frames, keeping the precision of 10-bit and HDR videos; the Merger and Clutter keep 16 bits for them"
+    )]
+    high_bit_depth: bool,
+
+    #[command(flatten)]
+    retry: RetryOptions,
+
     #[command(flatten)]
     common: CommonOptions,
 }
 
 #[derive(Args, Debug)]
-struct ClipperInputOutput {
-    /// Input for video or directory. Applies to all modes.
-    #[arg(short = 'i', long, help = "Input directory ")]
+struct ReproduceOptions {
+    /// Manifest written by the run to reproduce
+    #[arg(help = "Path to the manifest.json written next to an output")]
+    manifest: String,
+
+    /// Reproduce even if inputs changed since the run
+    #[arg(
+        long = "allow-changed",
+        help = "Reproduce even if recorded inputs changed or disappeared since the run"
+    )]
+    allow_changed: bool,
+}
+
+#[derive(Args, Debug)]
+struct AnalyzeOptions {
+    /// Directory of frames to analyze
+    #[arg(help = "Directory of frames")]
+    path: String,
+
+    /// Levels counted as clipped
+    #[arg(
+        long = "clip-level",
+        value_name = "LEVEL",
+        help = "Count levels up to this far from 0 or 255 as clipped blacks or whites",
+        default_value = "0",
+        value_parser = clap::value_parser!(u8).range(0..=127)
+    )]
+    clip_level: u8,
+
+    /// Directory to write histogram.png and luminance.png into
+    #[arg(
+        long = "plots",
+        value_name = "DIR",
+        help = "Write the histogram and the luminance per frame as PNG plots into this directory"
+    )]
+    plots: Option<PathBuf>,
+
+    /// Format of the report
+    #[arg(
+        long = "format",
+        help = "Print the report as text or json",
+        default_value = "text"
+    )]
+    format: probe::ProbeFormat,
+}
+
+#[derive(Args, Debug)]
+struct CompareOptions {
+    /// Directory of reference frames
+    #[arg(help = "Directory of reference frames")]
+    first: String,
+
+    /// Directory of frames to compare with the reference
+    #[arg(help = "Directory of frames to compare, matched by frame index")]
+    second: String,
+
+    /// Lowest PSNR a frame may have
+    #[arg(
+        long = "min-psnr",
+        value_name = "DB",
+        help = "Fail with exit code 2 if a frame's PSNR is below this many dB"
+    )]
+    min_psnr: Option<f64>,
+
+    /// Lowest SSIM a frame may have
+    #[arg(
+        long = "min-ssim",
+        value_name = "VALUE",
+        help = "Fail with exit code 2 if a frame's SSIM is below this value, from -1 to 1",
+        allow_hyphen_values = true
+    )]
+    min_ssim: Option<f64>,
+
+    /// Format of the report
+    #[arg(
+        long = "format",
+        help = "Print the report as text or json",
+        default_value = "text"
+    )]
+    format: probe::ProbeFormat,
+}
+
+#[derive(Args, Debug)]
+struct AudioAnalyzeOptions {
+    /// Audio to analyze
+    #[arg(help = "Audio file, or video with sound, to analyze")]
     input: String,
+
+    /// Frame rate to divide the audio at
+    #[arg(
+        short = 'f',
+        long = "fps",
+        help = "Frame rate to divide the audio at, the configured one if not given"
+    )]
+    fps: Option<FrameRate>,
+
+    /// File to write the JSON to
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "FILE",
+        help = "Write the JSON to this file instead of printing it"
+    )]
+    output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ProbeOptions {
+    /// Media file or directory of frames to inspect
+    #[arg(help = "Video or audio file, or a directory of frames")]
+    path: String,
+
+    /// Format of the report
+    #[arg(
+        long = "format",
+        help = "Print the report as text or json",
+        default_value = "text"
+    )]
+    format: probe::ProbeFormat,
+}
+
+#[derive(Args, Debug)]
+struct BenchOptions {
+    /// Width of the generated frames
+    #[arg(
+        long = "width",
+        help = "Width of the generated frames",
+        default_value = "1280",
+        value_parser = clap::value_parser!(u32).range(16..)
+    )]
+    width: u32,
+    /// Height of the generated frames
+    #[arg(
+        long = "height",
+        help = "Height of the generated frames",
+        default_value = "720",
+        value_parser = clap::value_parser!(u32).range(16..)
+    )]
+    height: u32,
+
+    /// Frames processed by each run
+    #[arg(
+        long = "frames",
+        help = "Frames each thread count decodes, blends and encodes",
+        default_value = "48",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    frames: u32,
+
+    /// Most threads to try
+    #[arg(
+        long = "max-jobs",
+        help = "Try up to this many threads; defaults to the number of cores",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    max_jobs: Option<u16>,
+
+    /// Store the recommended number of jobs in the configuration
+    #[arg(
+        long = "save",
+        help = "Store the recommended --jobs as jobs in the configuration, the default of process, merger and clutter"
+    )]
+    save: bool,
+
+    /// Format of the report
+    #[arg(
+        long = "format",
+        help = "Print the report as text or json",
+        default_value = "text"
+    )]
+    format: probe::ProbeFormat,
+}
+
+#[derive(Args, Debug)]
+struct ClipperInputOutput {
+    /// Input directories, clipped one after the other (Clipper mode)
+    #[arg(
+        short = 'i',
+        long,
+        help = "Input directory, or a single image; repeat to clip several directories in order, each optionally as DIR@FPS",
+        required = true,
+        action = ArgAction::Append
+    )]
+    input: Vec<fxp_clipper::Segment>,
     /// Output for directory or video. Applies to all modes.
     #[arg(short = 'o', long, help = "Output video \n")]
     output: Option<String>,
@@ -186,16 +1740,45 @@ struct ExporterInputOutput {
     output: Option<String>,
 }
 
+#[derive(Args, Debug)]
+struct VideosInputOutput {
+    /// Input videos, exported one after the other (Exporter mode)
+    #[arg(
+        short = 'i',
+        long,
+        help = "Input video; repeat to export several videos as one sequence, or pass a .txt or .m3u playlist",
+        required = true,
+        action = ArgAction::Append
+    )]
+    input: Vec<String>,
+    /// Output directory (Exporter mode)
+    #[arg(short = 'o', long, help = "Output directory \n")]
+    output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct AudioInputOutput {
+    /// Input audio file (Visualizer mode)
+    #[arg(short = 'i', long, help = "Input audio file")]
+    input: String,
+    /// Output directory (Visualizer mode)
+    #[arg(short = 'o', long, help = "Output directory \n")]
+    output: Option<String>,
+}
+
 #[derive(Parser, Debug)]
 #[command(
     author = "emporas",
     version = "0.5",
     about = "Easy videoclip creation with optional GMIC mode",
-    long_about = None
+    long_about = None,
+    after_help = exit::EXIT_CODES
 )]
 struct Cli {
     #[command(flatten)]
     verbose: Verbosity,
+    #[command(flatten)]
+    global: GlobalOptions,
     #[command(subcommand)]
     mode: Mode,
 }
@@ -214,77 +1797,445 @@ enum Mode {
     Gmicer(GmicerOptions),
     /// Transfer colors using a CLUT file
     Clutter(ClutterOptions),
+    /// Adjust exposure, contrast, saturation and temperature, optionally keyframed
+    Grader(GraderOptions),
+    /// Draw per-frame captions from a file onto the frames, e.g. for lyric videos
+    Captioner(CaptionerOptions),
+    /// Remove near-duplicate frames and renumber the rest
+    Dedup(DedupOptions),
+    /// Run an external command, or a processor of the configuration, on every frame
+    #[command(alias = "exec")]
+    Process(ProcessOptions),
+    /// Transfer colors and blend an overlay in memory, then clip, without intermediate PNGs
+    Chain(ChainOptions),
     /// Create the videoclip
     Clipper(ClipperOptions),
+    /// Generate intermediate frames for slow motion or a higher frame rate
+    Interpolator(InterpolatorOptions),
+    /// Stabilize a video with ffmpeg's vidstab filters
+    Stabilizer(StabilizerOptions),
+    /// Render the audio as a waveform or spectrogram frame sequence
+    Visualizer(VisualizerOptions),
+    /// Tile frames into a looping sprite sheet, or explode a sheet back into frames
+    Zoopraxiscope(ZoopraxiscopeOptions),
+    /// Re-run the mode recorded in an output's manifest.json
+    Reproduce(ReproduceOptions),
+    /// Report duration, resolution, fps and codecs of a file, or the frames of a directory
+    Probe(ProbeOptions),
+    /// Report histograms, average luminance and clipped pixels of a directory of frames
+    Analyze(AnalyzeOptions),
+    /// Report the difference, PSNR and SSIM between the frames of two directories
+    Compare(CompareOptions),
+    /// Write the RMS, peak and beats of each frame of an audio track as JSON
+    AudioAnalyze(AudioAnalyzeOptions),
+    /// Measure the throughput of decoding, blending and encoding frames to pick --jobs
+    Bench(BenchOptions),
+    /// Build a clip step by step: export, sample, filter, blend and render
+    Interactive,
 }
 
-/// Main entry point for the application, handling command-line argument parsing and dispatching.
+/// Main entry point for the application, exiting with the code of the failure if the
+/// run fails.
 ///
-/// This function initializes the application, sets up logging, loads configuration, and dispatches execution
+/// # Notes
+/// - Each kind of failure has its own exit code, see `fxp_output::FailureKind`, so
+///   wrappers can tell a missing ffmpeg from an input without frames or an interruption.
+fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let result = run(cli, matches.subcommand_name().unwrap_or_default());
+    release_output_locks();
+    if let Err(e) = result {
+        eprintln!("Error: {:?}", e);
+        let kind = exit::failure_kind(&e);
+        debug!("Exiting with code {} ({})", kind.exit_code(), kind);
+        std::process::exit(kind.exit_code());
+    }
+}
+
+/// Runs the application, handling dispatching of the parsed command line.
+///
+/// This function sets up logging, loads configuration, and dispatches execution
 /// based on the specified command-line mode.
 ///
 /// # Parameters
-/// - None
+/// - `cli`: The parsed command line.
+/// - `mode_name`: The subcommand, named in the summary given to the hooks.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the application execution
 ///
 /// # Notes
-/// - This is the primary entry point of the application
 /// - Dispatches to different runtime modes based on the command-line arguments provided
 /// - Upon successful execution, returns `Ok(())`
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-
+/// - The `--on-success` and `--webhook` hooks, and the `--notify` notification, run once
+///   the mode finishes, except for a dry run; inputs listed on stdin each run in a process
+///   of their own, which runs them.
+fn run(cli: Cli, mode_name: &str) -> Result<()> {
     let verbosity_level = cli.verbose.log_level_filter();
-    initialize_logger(verbosity_level).context("Failed to initialize logger")?;
+    initialize_logger(
+        verbosity_level,
+        cli.global.log_file(),
+        cli.global.log_format,
+    )
+    .context("Failed to initialize logger")?;
     debug!(
         "{} {:?}",
         style("Logger initialized with verbosity:").cyan(),
         verbosity_level
     );
 
-    let config = load_default_configuration().context("Failed to load default configuration")?;
+    let mut config = match load_default_configuration() {
+        Ok(config) => config,
+        // `init` is how an invalid configuration gets fixed, so it must not be refused.
+        Err(e) if matches!(cli.mode, Mode::Init) => {
+            warn!("{:#}", e);
+            Config::default()
+        }
+        Err(e) => return Err(e.context("Failed to load default configuration")),
+    };
     debug!("{}", style("Default configuration loaded").green());
+    if let Some(selection) = cli.global.audio_select {
+        config.audio_selection = selection;
+    }
 
-    // Dispatch based on the subcommand variant
-    match &cli.mode {
+    set_progress_mode(cli.global.progress);
+    set_graceful(cli.global.graceful);
+    set_lock_policy(cli.global.lock_policy());
+    match cli.global.seed {
+        Some(seed) => set_seed(seed),
+        // A reproduced run takes the seed recorded in its manifest.
+        None if !matches!(cli.mode, Mode::Reproduce(_)) => {
+            generate_seed();
+        }
+        None => {}
+    }
+    let trace_file = match &cli.global.trace_output {
+        TraceOutput::Json(path) => Some(
+            path.clone()
+                .unwrap_or_else(|| default_log_dir().join("trace.jsonl")),
+        ),
+        TraceOutput::Log => None,
+    };
+    init_tracing(trace_file.as_deref())?;
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(input) = batch::stdin_input(&args) {
+        return run_stdin_list(&args, &input, &cli.global);
+    }
+
+    let hooks = hooks::Hooks {
+        on_success: cli.global.on_success.clone().or(config.on_success.clone()),
+        webhook: cli.global.webhook.clone().or(config.webhook.clone()),
+    };
+    let started = Instant::now();
+    let result = run_mode(&cli.mode, &config, &cli.global);
+    if !cli.global.dry_run {
+        let summary = hooks::RunSummary::new(mode_name, &result, started.elapsed());
+        hooks.fire(&summary);
+        if cli.global.notify {
+            let threshold = cli
+                .global
+                .notify_after
+                .unwrap_or(config.notify_after_seconds);
+            notify::notify_finished(&summary, Duration::from_secs(threshold));
+        }
+    }
+    result?;
+
+    if let Some(summary) = timing_summary() {
+        write_timing_summary(&summary);
+        if cli.verbose.quiet == 0 {
+            eprint!("\n{}", summary);
+        }
+    }
+
+    debug!(
+        "{}",
+        style("Main function execution completed successfully").green()
+    );
+    Ok(())
+}
+
+/// Runs the mode for every input listed on stdin, for `-i -`.
+///
+/// # Parameters
+/// - `args`: The command line, starting with the program name.
+/// - `input`: Where the `-` is in `args`, see `batch::stdin_input`.
+/// - `global`: Options shared by every mode, of which `--list-jobs` is used here.
+///
+/// # Returns
+/// - `Result<()>`: `Ok` if the mode succeeded for every input, or an error naming the
+///   inputs it failed for.
+///
+/// # Notes
+/// - Each input runs the same command line with `-` replaced by the input, in a process
+///   of its own, so shell pipelines like `find . -name '*.mp4' | fxp_videoclipper sampler
+///   -i -` work.
+fn run_stdin_list(
+    args: &[String],
+    input: &batch::OptionValue,
+    global: &GlobalOptions,
+) -> Result<()> {
+    let inputs = batch::read_stdin_inputs()?;
+    let runs: Vec<(String, Vec<String>)> = inputs
+        .into_iter()
+        .map(|listed| {
+            let input_args = input.replace(args, &listed);
+            (listed, input_args)
+        })
+        .collect();
+    let results = batch::run_each(&runs, global.list_jobs)?;
+    batch::check_runs(&results, runs.len())
+}
+
+/// Runs the Exporter or Sampler for every video of a directory, for `--batch`.
+///
+/// # Parameters
+/// - `directory`: The directory of videos given as `-i`.
+/// - `global`: Options shared by every mode, of which `--list-jobs` is used here.
+///
+/// # Returns
+/// - `Result<()>`: `Ok` if the mode succeeded for every video, or an error naming the
+///   videos it failed for.
+///
+/// # Notes
+/// - Each video runs the same command line in a process of its own, see `batch::run_each`,
+///   so one failing video does not stop the others. A table of the results is printed
+///   once all have run.
+fn run_batch(directory: &str, global: &GlobalOptions) -> Result<()> {
+    let videos = batch::list_videos(Path::new(directory))?;
+    let args: Vec<String> = std::env::args().collect();
+    let runs = batch::batch_runs(&args, &videos)?;
+    let results = batch::run_each(&runs, global.list_jobs)?;
+    batch::print_summary(&results, runs.len());
+    batch::check_runs(&results, runs.len())
+}
+
+/// Runs the selected mode.
+///
+/// # Parameters
+/// - `mode`: The parsed subcommand.
+/// - `config`: Configuration containing default settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the mode.
+///
+/// # Notes
+/// - `reproduce` translates a run manifest back into the recorded mode's command line
+///   and runs that, with the global options of the current invocation.
+fn run_mode(mode: &Mode, config: &Config, global: &GlobalOptions) -> Result<()> {
+    match mode {
         Mode::Init => {
             debug!("{}", style("Initializing configuration...").yellow());
             initialize_configuration().context("Failed to initialize configuration")?;
-            return Ok(());
         }
 
         Mode::Gmicer(options) => {
             debug!("{}", style("Running in GMIC mode").blue());
-            run_gmicer(options, &config)?;
+            run_gmicer(options, config, global)?;
         }
         Mode::Clipper(options) => {
             debug!("{}", style("Running in clipper mode").blue());
-            run_clipper(options, &config)?;
+            run_clipper(options, config, global)?;
         }
         Mode::Clutter(options) => {
             debug!("{}", style("Running in clutter mode").blue());
-            run_clutter(options, &config)?;
+            run_clutter(options, config, global)?;
+        }
+        Mode::Grader(options) => {
+            debug!("{}", style("Running in grader mode").blue());
+            run_grader(options, global)?;
+        }
+        Mode::Captioner(options) => {
+            debug!("{}", style("Running in captioner mode").blue());
+            run_captioner(options, global)?;
+        }
+        Mode::Dedup(options) => {
+            debug!("{}", style("Running in dedup mode").blue());
+            run_dedup(options, global)?;
+        }
+        Mode::Process(options) => {
+            debug!("{}", style("Running in process mode").blue());
+            run_process(options, config, global)?;
+        }
+        Mode::Chain(options) => {
+            debug!("{}", style("Running in chain mode").blue());
+            run_chain(options, config, global)?;
+        }
+        Mode::Interpolator(options) => {
+            debug!("{}", style("Running in interpolator mode").blue());
+            run_interpolator(options, global)?;
+        }
+        Mode::Stabilizer(options) => {
+            debug!("{}", style("Running in stabilizer mode").blue());
+            run_stabilizer(options, global)?;
+        }
+        Mode::Visualizer(options) => {
+            debug!("{}", style("Running in visualizer mode").blue());
+            run_visualizer(options, global)?;
+        }
+        Mode::Zoopraxiscope(options) => {
+            debug!("{}", style("Running in zoopraxiscope mode").blue());
+            run_zoopraxiscope(options, global)?;
         }
         Mode::Sampler(options) => {
             debug!("{}", style("Running in sampler mode").blue());
-            run_sampler(options, &config)?;
+            run_sampler(options, config, global)?;
+        }
+        Mode::Exporter(options) => {
+            debug!("{}", style("Running in exporter mode").blue());
+            run_exporter(options, config, global)?;
+        }
+        Mode::Merger(options) => {
+            debug!("{}", style("Running in merger mode").blue());
+            run_merger(options, config, global)?;
+        }
+        Mode::Reproduce(options) => {
+            debug!("{}", style("Reproducing a recorded run").blue());
+            let args =
+                reproduce::reproduce_args(Path::new(&options.manifest), options.allow_changed)?;
+            println!("Reproducing: {}", args[1..].join(" "));
+            let replay = Cli::try_parse_from(&args)
+                .context("Run manifest does not translate into a valid command line")?;
+            // A --seed given to reproduce wins over the recorded one.
+            match replay.global.seed {
+                Some(seed) => set_seed(seed),
+                None => {
+                    generate_seed();
+                }
+            }
+            run_mode(&replay.mode, config, global)?;
+        }
+        Mode::Probe(options) => {
+            let report = probe::probe(Path::new(&options.path))?;
+            print!("{}", report.render(options.format)?);
+        }
+        Mode::Analyze(options) => {
+            let report = analyze::analyze(
+                Path::new(&options.path),
+                options.clip_level,
+                options.plots.as_deref(),
+            )?;
+            print!("{}", report.render(options.format)?);
+        }
+        Mode::Compare(options) => {
+            if options
+                .min_ssim
+                .is_some_and(|min| !(-1.0..=1.0).contains(&min))
+            {
+                return Err(anyhow::anyhow!("--min-ssim must lie within -1 to 1"));
+            }
+            let thresholds = compare::Thresholds {
+                min_psnr: options.min_psnr,
+                min_ssim: options.min_ssim,
+            };
+            let report = compare::compare(
+                Path::new(&options.first),
+                Path::new(&options.second),
+                thresholds,
+            )?;
+            print!("{}", report.render(options.format)?);
+            if report.failed() {
+                return Err(exit::BelowThreshold.into());
+            }
+        }
+        Mode::AudioAnalyze(options) => {
+            if !Path::new(&options.input).is_file() {
+                return Err(anyhow::anyhow!(
+                    "Audio to analyze must be a file: {}",
+                    options.input
+                ));
+            }
+            let fps = get_fps(options.fps, config).context("Failed to resolve FPS")?;
+            let loudness = fxp_audio::Loudness::analyze(Path::new(&options.input), fps)?;
+            match &options.output {
+                Some(output) => {
+                    loudness.write(Path::new(output))?;
+                    info!(
+                        "Wrote the loudness of {} frames with {} beats to {}",
+                        loudness.frames.len(),
+                        loudness.beats(),
+                        output
+                    );
+                }
+                None => println!("{}", loudness.to_json()?),
+            }
         }
-        Mode::Exporter(options) => {
-            debug!("{}", style("Running in exporter mode").blue());
-            run_exporter(options, &config)?;
+        Mode::Bench(options) => {
+            let max_jobs = options.max_jobs.map_or_else(
+                || std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+                usize::from,
+            );
+            let report = bench::bench(
+                options.width,
+                options.height,
+                options.frames as usize,
+                max_jobs,
+            )?;
+            print!("{}", report.render(options.format)?);
+            if options.save {
+                let mut config = config.clone();
+                config.jobs = report.recommended_jobs;
+                save_configuration(&config)?;
+                info!("Stored jobs = {} in the configuration", config.jobs);
+            }
         }
-        Mode::Merger(options) => {
-            debug!("{}", style("Running in merger mode").blue());
-            run_merger(options, &config)?;
+        Mode::Interactive => {
+            debug!("{}", style("Running in interactive mode").blue());
+            interactive::run_interactive(global, config)?;
         }
     }
 
-    debug!(
-        "{}",
-        style("Main function execution completed successfully").green()
-    );
+    Ok(())
+}
+
+/// Checks that the input is of the kind the mode reads.
+///
+/// # Parameters
+/// - `mode`: The mode about to run.
+/// - `input`: The `-i` argument.
+///
+/// # Returns
+/// - `Result<()>`: An error naming the mode if the input is missing or of the wrong kind.
+fn validate_input(mode: Modes, input: &str) -> Result<()> {
+    let input_path = Path::new(input);
+    if mode.supports_directory_input() && mode.supports_video_input() {
+        if !input_path.is_dir() && !input_path.is_file() {
+            return Err(exit::InvalidInput(format!(
+                "For {} mode, the input must be a directory or a video file: {}",
+                mode.name(),
+                input
+            ))
+            .into());
+        }
+        return Ok(());
+    }
+    if mode.supports_directory_input() && !input_path.is_dir() {
+        return Err(exit::InvalidInput(format!(
+            "For {} mode, the input must be a directory: {}",
+            mode.name(),
+            input
+        ))
+        .into());
+    }
+    if mode.supports_video_input() && !input_path.is_file() {
+        return Err(exit::InvalidInput(format!(
+            "For {} mode, the input must be a video file: {}",
+            mode.name(),
+            input
+        ))
+        .into());
+    }
+    if mode.requires_audio() && !input_path.is_file() {
+        return Err(exit::InvalidInput(format!(
+            "For {} mode, the input must be an audio file: {}",
+            mode.name(),
+            input
+        ))
+        .into());
+    }
     Ok(())
 }
 
@@ -296,33 +2247,36 @@ fn main() -> Result<()> {
 /// # Parameters
 /// - `options`: Contains input, output, and GMIC arguments.
 /// - `config`: Configuration settings for the application.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of image processing.
 ///
 /// # Notes
-/// - The input must be a directory.
-/// - At least one GMIC argument is required.
+/// - The input must be a directory or a glob pattern matching images.
+/// - At least one GMIC argument or a `--preset` is required.
+/// - The arguments of the preset, built in or from `gmic_presets` of the configuration,
+///   come before the GMIC arguments given.
 /// - Handles the `-o` flag for explicit output directories.
-fn run_gmicer(options: &GmicerOptions, _config: &Config) -> Result<()> {
+fn run_gmicer(options: &GmicerOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
     debug!("Running in GMIC mode");
 
-    // Validate that the input is provided and is a directory.
-    let input = &options.io.input;
-    let input_path = Path::new(input);
-    if !input_path.is_dir() {
-        return Err(anyhow::anyhow!(
-            "For GMIC mode, the input must be a directory: {}",
-            input
-        ));
+    // Validate that the input is provided and is a directory, unless it is a pattern.
+    let input = FrameSource::new(options.io.input.as_str(), options.recursive);
+    if !input.is_glob() {
+        validate_input(Modes::Gmicer, input.input())?;
     }
-    debug!("GMIC input directory: {:?}", input_path);
-
-    // Ensure that at least one GMIC argument is provided.
-    let args = options.gmic_args.clone().unwrap_or_default();
+    debug!("GMIC input: {}", input);
+
+    // Start with the arguments of the preset, then ensure that at least one is provided.
+    let mut args = match &options.preset {
+        Some(name) => fxp_gmicer::find_preset(name, &config.gmic_presets)?.args,
+        None => Vec::new(),
+    };
+    args.extend(options.gmic_args.clone().unwrap_or_default());
     if args.is_empty() {
         return Err(anyhow::anyhow!(
-            "GMIC mode requires at least one GMIC argument."
+            "GMIC mode requires at least one GMIC argument or a --preset."
         ));
     }
     debug!("GMIC arguments before filtering: {:?}", args);
@@ -337,9 +2291,31 @@ fn run_gmicer(options: &GmicerOptions, _config: &Config) -> Result<()> {
     let output = explicit_output.or_else(|| options.io.output.clone());
     debug!("Final GMIC output directory: {:?}", output);
 
+    if global.dry_run {
+        let plan = fxp_gmicer::Gmicer::plan(
+            &input,
+            output.as_deref(),
+            filtered_args,
+            options.preset.as_deref(),
+            &options.selection.selection(),
+            global.collision_policy(),
+        )?;
+        print!("{}", options.retry.plan(plan, config));
+        return Ok(());
+    }
+
     // Create the GMIC processor instance using the input, output, and filtered GMIC args.
-    let gmicer = fxp_gmicer::Gmicer::new(input, output.as_deref(), filtered_args)
-        .context("Failed to initialize GMIC processor")?;
+    let mut gmicer = fxp_gmicer::Gmicer::new(
+        input,
+        output.as_deref(),
+        filtered_args,
+        global.collision_policy(),
+    )
+    .context("Failed to initialize GMIC processor")?;
+    gmicer.in_place = global.in_place;
+    gmicer.selection = options.selection.selection();
+    gmicer.preset = options.preset.clone();
+    gmicer.retry = options.retry.policy(config);
     gmicer
         .gmic_images()
         .context("Failed to process images using GMIC")?;
@@ -349,33 +2325,128 @@ fn run_gmicer(options: &GmicerOptions, _config: &Config) -> Result<()> {
 
 /// Merges images from two directories based on the provided options and configuration.
 ///
-/// This function takes two directories of images, applies the specified opacity,
-/// and merges them into the output directory.
+/// This function takes two directories of images, applies the specified opacities,
+/// and merges them into one output directory per opacity.
 ///
 /// # Parameters
-/// - `options`: A struct containing input/output paths and opacity value.
+/// - `options`: A struct containing input/output paths and opacity values.
 /// - `config`: Configuration containing default settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the merge operation.
 ///
 /// # Notes
 /// - Extracts directories from the provided options and uses them for merging.
+/// - With `--multiple`, the opacities come from `get_multiple_opacities` instead of `-t`.
 /// - Returns an error if opacity resolution or image merging fails.
-fn run_merger(options: &MergerOptions, config: &Config) -> Result<()> {
-    // Resolve the opacity using the value provided in the merger options.
-    let opacity =
-        get_opacity(Some(options.opacity), config).context("Failed to resolve opacity")?;
-    debug!("Resolved opacity: {}", opacity);
-
-    // Use the embedded InputOutput field for directories.
-    let directory1 = options.io.input.clone();
-    let directory2 = options.directory2.clone();
+fn run_merger(options: &MergerOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
+    // Resolve the opacities using the values provided in the merger options.
+    let opacities = if options.multiple {
+        get_multiple_opacities(None, config).context("Failed to resolve multiple opacities")?
+    } else {
+        options
+            .opacity
+            .iter()
+            .map(|opacity| get_opacity(Some(*opacity), config))
+            .collect::<Result<Vec<f32>>>()
+            .context("Failed to resolve opacity")?
+    };
+    debug!("Resolved opacities: {:?}", opacities);
+
+    // Use the embedded InputOutput field for directories; patterns are not validated.
+    let directory1 = FrameSource::new(options.io.input.as_str(), options.recursive);
+    let mut layers = options.layers.clone();
+    for layer in &mut layers {
+        layer.source.recursive = options.recursive;
+    }
+    for directory in std::iter::once(&directory1).chain(layers.iter().map(|layer| &layer.source)) {
+        if !directory.is_glob() {
+            validate_input(Modes::Merger, directory.input())?;
+        }
+    }
     let output = options.io.output.clone();
 
-    // Initialize the merger with the provided directories, opacity, and output.
-    let merger = fxp_merger::Merger::new(directory1, directory2, opacity, output);
-    merger?.merge_images().context("Failed to merge images")?;
+    let audio_opacity = match &options.audio_opacity {
+        Some(audio) => {
+            let [min, max] = options.opacity_range[..] else {
+                return Err(anyhow::anyhow!(
+                    "--opacity-range takes two opacities, e.g. 0.2,0.8"
+                ));
+            };
+            if !Path::new(audio).is_file() {
+                return Err(anyhow::anyhow!(
+                    "--audio-opacity must be an audio file: {}",
+                    audio
+                ));
+            }
+            let audio_opacity = fxp_merger::AudioOpacity {
+                audio: PathBuf::from(audio),
+                fps: get_fps(options.fps, config).context("Failed to resolve FPS")?,
+                min,
+                max,
+            };
+            audio_opacity.validate()?;
+            Some(audio_opacity)
+        }
+        None => None,
+    };
+
+    let region = options.region.or(options.region_circle);
+    let pip = options.pip.pip();
+    if pip.is_some() && layers.len() > 1 {
+        return Err(exit::InvalidInput(
+            "--pip takes a single second directory, not a stack of layers".to_string(),
+        )
+        .into());
+    }
+
+    if global.dry_run {
+        let mut plan = fxp_merger::Merger::plan_with_opacities(
+            directory1,
+            &layers,
+            &opacities,
+            output,
+            options.mismatch_policy,
+            &options.selection.selection(),
+            global.collision_policy(),
+        )?;
+        if let Some(audio_opacity) = &audio_opacity {
+            plan = plan.entry("opacity follows audio", audio_opacity);
+        }
+        if let Some(region) = region {
+            plan = plan.entry(
+                "region",
+                format!("{}, feathered {} px", region, options.region_feather),
+            );
+        }
+        plan = options.pip.plan(plan);
+        plan = options.encoding.plan(plan)?;
+        print!("{}", options.workers.plan(plan, config));
+        return Ok(());
+    }
+
+    // Initialize the merger with the provided directories, opacities, and output.
+    let mut merger = fxp_merger::Merger::with_opacities(
+        directory1,
+        layers,
+        &opacities,
+        output,
+        options.mismatch_policy,
+        global.collision_policy(),
+    )?;
+    merger.in_place = global.in_place;
+    merger.respect_alpha = options.respect_alpha;
+    merger.linear_blend = options.linear_blend;
+    merger.selection = options.selection.selection();
+    merger.audio_opacity = audio_opacity;
+    merger.region = region;
+    merger.feather = options.region_feather;
+    merger.pip = pip;
+    merger.encoder = options.encoding.encoder()?;
+    merger.jobs = get_jobs(options.workers.jobs, config);
+    merger.max_memory = options.workers.max_memory_bytes();
+    merger.merge_images().context("Failed to merge images")?;
     Ok(())
 }
 
@@ -387,13 +2458,32 @@ fn run_merger(options: &MergerOptions, config: &Config) -> Result<()> {
 /// # Parameters
 /// - `options`: Struct containing clipper-specific options, including input/output paths and FPS.
 /// - `config`: Configuration struct providing default values and settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the clipping process.
-fn run_clipper(options: &ClipperOptions, config: &Config) -> Result<()> {
+fn run_clipper(options: &ClipperOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
     // Get input and output from the embedded I/O field.
-    let input_dir = &options.io.input;
-    debug!("Input directory: {}", input_dir);
+    let segments = &options.io.input;
+    // A single image is turned into frames covering the audio, then clipped like them.
+    let still = match segments.as_slice() {
+        [segment] if fxp_clipper::is_still_image(&segment.directory) => {
+            if segment.fps.is_some() {
+                return Err(anyhow::anyhow!(
+                    "A still image is clipped at --fps, not at its own frame rate"
+                ));
+            }
+            Some(segment.directory.clone())
+        }
+        _ => None,
+    };
+    if still.is_none() {
+        for segment in segments {
+            validate_input(Modes::Clipper, &segment.directory.to_string_lossy())?;
+        }
+    }
+    let input_dir = &segments[0].directory.to_string_lossy().into_owned();
+    debug!("Input segments: {:?}", segments);
 
     let output_path = options.io.output.clone();
     debug!("Output path: {:?}", output_path);
@@ -406,12 +2496,7 @@ fn run_clipper(options: &ClipperOptions, config: &Config) -> Result<()> {
     let mp3_path_str = mp3_path.as_ref().map(|p| p.to_string_lossy().into_owned());
 
     // Resolve the FPS value using the common options.
-    let cli_fps = options
-        .common_options
-        .fps
-        .clone()
-        .map(|s| s.parse::<u32>().context("Invalid FPS value"))
-        .transpose()?;
+    let cli_fps = options.common_options.fps;
     let fps_val = get_fps(cli_fps, config).context("Failed to resolve FPS")?;
     debug!("Resolved FPS value: {}", fps_val);
 
@@ -423,19 +2508,178 @@ fn run_clipper(options: &ClipperOptions, config: &Config) -> Result<()> {
         None => debug!("Final duration to use: None"),
     }
 
+    // Frames exported with --align-to-audio-onset start on the music, so the audio
+    // before it is skipped unless an offset is given. Otherwise --skip-audio-silence
+    // advances the audio past its leading silence; either is resolved into the offset,
+    // which the manifest records.
+    let mut skipped_silence = 0;
+    let audio_offset = match (exported_audio_onset(&segments[0].directory), &mp3_path) {
+        (Some(onset), Some(_)) if options.audio_offset == 0 => {
+            if options.skip_audio_silence {
+                warn!(
+                    "Ignoring --skip-audio-silence, the frames were exported from the audio onset at {} ms, which the audio is advanced to instead",
+                    onset
+                );
+            }
+            debug!("Advancing the audio by the exported onset of {} ms", onset);
+            -(onset as i64)
+        }
+        (_, Some(mp3)) if options.skip_audio_silence => {
+            skipped_silence = fxp_audio::leading_silence(mp3)
+                .context("Failed to detect the silence the audio starts with")?;
+            if skipped_silence > 0 {
+                println!(
+                    "Skipping {} ms of silence at the start of the audio",
+                    skipped_silence
+                );
+            }
+            options.audio_offset - skipped_silence as i64
+        }
+        _ => options.audio_offset,
+    };
+
+    // A preview render keeps the frames' size unless a reduced one is asked for.
+    let pixel_upper_limit = match options.preview_seconds {
+        Some(_) => Some(get_preview_pixel_limit(get_pixel_upper_limit(
+            None, config,
+        )?)),
+        None => None,
+    };
+
+    let clip_options = fxp_clipper::ClipOptions {
+        gap_policy: options.gaps,
+        preview: options.preview.clone(),
+        preview_seconds: options.preview_seconds,
+        pixel_upper_limit,
+        append: options.append_clip,
+        reverse: options.reverse,
+        pingpong: options.pingpong,
+        selection: options.selection.selection(),
+        fit_audio: options.fit_audio,
+        trim_audio: options.trim_audio,
+        pad_frames: options.pad_frames,
+        audio_offset_ms: audio_offset,
+        loudness: options.normalize_audio,
+        format: match &options.segment {
+            Some(segment) => segment.parse().map_err(anyhow::Error::msg)?,
+            None => options.format,
+        },
+        max_width: options.max_width,
+        loop_count: options.loop_count,
+        quality: fxp_clipper::VideoQuality {
+            // 10-bit video is encoded with x265 only.
+            codec: if options.ten_bit {
+                fxp_clipper::VideoCodec::H265
+            } else {
+                options.codec
+            },
+            crf: options.crf,
+            preset: options.preset.clone(),
+            bitrate: options.bitrate.clone(),
+            two_pass: options.two_pass,
+            ten_bit: options.ten_bit,
+        },
+        auto_fix: options.auto_fix,
+        keep_temp: global.keep_temp(),
+        // A single directory at the clip's frame rate is clipped as it is.
+        segments: if segments.len() > 1 || segments[0].fps.is_some() {
+            segments.clone()
+        } else {
+            Vec::new()
+        },
+        chapters: options.chapters.as_ref().map(PathBuf::from),
+        webvtt: options.webvtt,
+        source_timing: options.source_timing.as_ref().map(|path| {
+            path.clone()
+                .unwrap_or_else(|| segments[0].directory.join(fxp_output::FRAMES_FILE_NAME))
+        }),
+        hdr_source: options.hdr_from.clone(),
+    };
+    debug!("Clip options: {:?}", clip_options);
+
+    // An existing video is extended rather than replaced, so its path must be kept as is.
+    let collision = if options.append_clip {
+        CollisionPolicy::Overwrite
+    } else {
+        global.collision_policy()
+    };
+
+    // The frames built from a still image are kept until the Clipper is done with them.
+    let (input_dir, output_path, _still_frames_dir) = match &still {
+        Some(image) => {
+            // The frames last as long as the audio left once its silence is skipped.
+            let audible = duration.map(|duration| duration.saturating_sub(skipped_silence));
+            let frames = fxp_clipper::still_frames(audible, fps_val)?;
+            let output = fxp_output::ClipperOutput.plan_output(
+                (
+                    image.clone(),
+                    mp3_path.clone(),
+                    output_path,
+                    clip_options.format.extension(),
+                ),
+                collision,
+            )?;
+            if global.dry_run {
+                let plan = fxp_output::Plan::new(Modes::Clipper)
+                    .entry("input image", image.display())
+                    .entry("still motion", options.still_motion)
+                    .entry("frames", frames)
+                    .entry("fps", fps_val)
+                    .entry("audio", mp3_path_str.as_deref().unwrap_or("none"))
+                    .entry("output", output.display());
+                print!("{}", plan);
+                return Ok(());
+            }
+            let frames_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+            fxp_clipper::synthesize_frames(image, frames, options.still_motion, frames_dir.path())
+                .context("Failed to build frames from the still image")?;
+            (
+                frames_dir.path().to_string_lossy().into_owned(),
+                Some(output.to_string_lossy().into_owned()),
+                Some(frames_dir),
+            )
+        }
+        None => (input_dir.clone(), output_path, None),
+    };
+
+    if global.dry_run {
+        let plan = fxp_clipper::Clipper::plan(
+            input_dir.clone(),
+            mp3_path_str,
+            output_path,
+            fps_val,
+            duration,
+            &clip_options,
+            collision,
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
     // Initialize the Clipper with the resolved parameters.
-    let clipper = fxp_clipper::Clipper::new(
+    let mut clipper = fxp_clipper::Clipper::new(
         input_dir.clone(),
         mp3_path_str,
         output_path,
         fps_val,
         duration,
+        clip_options.format,
+        collision,
     )?;
+    clipper.options = clip_options;
     debug!("Initialized Clipper: {:?}", clipper);
 
-    // Run the clip process.
-    clipper.clip()?;
-    debug!("Clip process completed successfully");
+    // Run the clip process, or stream a preview of it.
+    match &clipper.options.preview {
+        Some(target) => {
+            clipper.preview(target)?;
+            debug!("Preview completed successfully");
+        }
+        None => {
+            clipper.clip()?;
+            debug!("Clip process completed successfully");
+        }
+    }
 
     Ok(())
 }
@@ -448,29 +2692,110 @@ fn run_clipper(options: &ClipperOptions, config: &Config) -> Result<()> {
 /// # Parameters
 /// - `options`: Configuration options for the CLUT process.
 /// - `config`: Application configuration containing additional settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the CLUT operation.
-fn run_clutter(options: &ClutterOptions, _config: &Config) -> Result<()> {
+///
+/// # Notes
+/// - With `--clut-multiple`, the images are clutted once per opacity, into
+///   `<output>_<opacity>` or `<input>_clutted_<opacity>`.
+/// - With `--reference`, the colors of the reference image are transferred to the images
+///   instead of applying a CLUT.
+fn run_clutter(options: &ClutterOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
     // Access input and output from the flattened InputOutput field
     let input_dir = &options.io.input;
     let output = options.io.output.clone();
+    validate_input(Modes::Clutter, input_dir)?;
 
     debug!("Input directory: {:?}", input_dir);
 
-    // Ensure the CLUT image is provided.
-    let clut_image = &options.clut_image;
-    debug!("CLUT image: {:?}", clut_image);
+    // Ensure the CLUT or reference image is provided.
+    let source = match (&options.clut_image, &options.reference) {
+        (_, Some(reference)) => ClutSource::Reference(reference.into()),
+        (Some(clut_image), None) => ClutSource::Clut(clut_image.into()),
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "Clutter mode requires --clut or --reference"
+            ))
+        }
+    };
+    debug!("Colors from: {:?}", source);
+    let encoder = options.encoding.encoder()?;
+
+    if let Some(cli_opacities) = options.clut_multiple.clone() {
+        let opacities = get_multiple_opacities(Some(cli_opacities), config)
+            .context("Failed to resolve multiple CLUT opacities")?;
+        let base = output.unwrap_or_else(|| {
+            format!(
+                "{}{}",
+                input_dir.trim_end_matches(['/', '\\']),
+                Modes::Clutter.default_output_suffix().unwrap_or_default()
+            )
+        });
+        for opacity in opacities {
+            let output = format!("{}_{}", base.trim_end_matches(['/', '\\']), opacity);
+            if global.dry_run {
+                let plan = fxp_clutter::Clutter::plan(
+                    input_dir.clone(),
+                    source.clone(),
+                    Some(output),
+                    Some(opacity),
+                    global.collision_policy(),
+                )?;
+                let plan = options.encoding.plan(plan)?;
+                print!("{}", options.workers.plan(plan, config));
+                continue;
+            }
+            let mut clutter = fxp_clutter::Clutter::new(
+                input_dir.clone(),
+                source.clone(),
+                Some(output),
+                global.collision_policy(),
+            )?;
+            clutter.in_place = global.in_place;
+            clutter.opacity = Some(opacity);
+            clutter.encoder = encoder;
+            clutter.jobs = get_jobs(options.workers.jobs, config);
+            clutter.max_memory = options.workers.max_memory_bytes();
+            clutter
+                .create_clut_images()
+                .with_context(|| format!("Failed to create CLUT images at opacity {}", opacity))?;
+        }
+        return Ok(());
+    }
+
+    let opacity = options
+        .clut_opacity
+        .map(|opacity| get_opacity(Some(opacity), config))
+        .transpose()
+        .context("Failed to resolve CLUT opacity")?;
+
+    if global.dry_run {
+        let plan = fxp_clutter::Clutter::plan(
+            input_dir.clone(),
+            source,
+            output,
+            opacity,
+            global.collision_policy(),
+        )?;
+        let plan = options.encoding.plan(plan)?;
+        print!("{}", options.workers.plan(plan, config));
+        return Ok(());
+    }
 
     // Create a Clutter instance using the input directory, CLUT image, and output.
-    let clutter = fxp_clutter::Clutter::new(input_dir.clone(), clut_image.clone(), output);
-    debug!(
-        "Clutter instance created with input_dir: {:?} and clut_image: {:?}",
-        input_dir, clut_image
-    );
+    let mut clutter =
+        fxp_clutter::Clutter::new(input_dir.clone(), source, output, global.collision_policy())?;
+    clutter.in_place = global.in_place;
+    clutter.opacity = opacity;
+    clutter.encoder = encoder;
+    clutter.jobs = get_jobs(options.workers.jobs, config);
+    clutter.max_memory = options.workers.max_memory_bytes();
+    debug!("Clutter instance created with input_dir: {:?}", input_dir);
 
     // Generate CLUT images.
-    let clut_dir = clutter?
+    let clut_dir = clutter
         .create_clut_images()
         .context("Failed to create CLUT images")?;
     debug!(
@@ -482,6 +2807,519 @@ fn run_clutter(options: &ClutterOptions, _config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Color grades a directory of images natively.
+///
+/// # Parameters
+/// - `options`: The input and output directories and the color controls.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the grading.
+///
+/// # Notes
+/// - Keyframes refer to the frame numbers in the filenames; between them, the controls
+///   are interpolated linearly.
+fn run_grader(options: &GraderOptions, global: &GlobalOptions) -> Result<()> {
+    let input_dir = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Grader, input_dir)?;
+    let grading = options.grading();
+
+    if global.dry_run {
+        let plan = fxp_grader::Grader::plan(
+            input_dir.clone(),
+            output,
+            &grading,
+            global.collision_policy(),
+        )?;
+        print!("{}", options.encoding.plan(plan)?);
+        return Ok(());
+    }
+
+    grading.validate()?;
+    let mut grader = fxp_grader::Grader::new(input_dir.clone(), output, global.collision_policy())?;
+    grader.in_place = global.in_place;
+    grader.grading = grading;
+    grader.encoder = options.encoding.encoder()?;
+
+    let graded = grader.grade().context("Failed to grade frames")?;
+    debug!(
+        "Grader run completed successfully, graded {} frames",
+        graded
+    );
+    Ok(())
+}
+
+/// Draws captions read from a file onto a directory of images natively.
+///
+/// # Parameters
+/// - `options`: The input and output directories, the captions file and the text style.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the captioning.
+///
+/// # Notes
+/// - The captions refer to the frame numbers in the filenames; frames without a caption
+///   are copied unchanged.
+fn run_captioner(options: &CaptionerOptions, global: &GlobalOptions) -> Result<()> {
+    let input_dir = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Captioner, input_dir)?;
+    let style = options.style();
+
+    if global.dry_run {
+        let plan = fxp_captioner::Captioner::plan(
+            input_dir.clone(),
+            options.captions.clone(),
+            output,
+            &style,
+            options.font.as_deref(),
+            global.collision_policy(),
+        )?;
+        print!("{}", options.encoding.plan(plan)?);
+        return Ok(());
+    }
+
+    style.validate().map_err(exit::InvalidInput)?;
+    let mut captioner = fxp_captioner::Captioner::new(
+        input_dir.clone(),
+        options.captions.clone(),
+        output,
+        global.collision_policy(),
+    )?;
+    captioner.in_place = global.in_place;
+    captioner.style = style;
+    captioner.font = options.font.clone();
+    captioner.encoder = options.encoding.encoder()?;
+
+    let captioned = captioner.caption().context("Failed to caption frames")?;
+    debug!(
+        "Captioner run completed successfully, captioned {} frames",
+        captioned
+    );
+    Ok(())
+}
+
+/// Runs a frame processor on every frame of a directory.
+///
+/// # Parameters
+/// - `options`: The input and output directories, and the command or processor to run.
+/// - `config`: Configuration holding the named processors.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the processing.
+fn run_process(options: &ProcessOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
+    let input_dir = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Processor, input_dir)?;
+    let processor: Arc<dyn fxp_processor::FrameProcessor> = match (&options.cmd, &options.processor)
+    {
+        (Some(cmd), _) => Arc::new(fxp_processor::CommandProcessor::new("cmd", cmd)?),
+        (None, Some(name)) => {
+            fxp_processor::Registry::with_commands(&config.processors)?.get(name)?
+        }
+        (None, None) => unreachable!("clap requires --cmd or --processor"),
+    };
+
+    if global.dry_run {
+        let plan = fxp_processor::Processor::plan(
+            input_dir.clone(),
+            output,
+            processor.as_ref(),
+            &options.selection.selection(),
+            get_jobs(options.jobs, config),
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut runner =
+        fxp_processor::Processor::new(input_dir.clone(), output, global.collision_policy())?;
+    runner.in_place = global.in_place;
+    runner.selection = options.selection.selection();
+    runner.jobs = get_jobs(options.jobs, config);
+    let processed = runner
+        .process(processor.as_ref())
+        .context("Failed to process frames")?;
+    debug!(
+        "Process run completed successfully, processed {} frames",
+        processed
+    );
+    Ok(())
+}
+
+/// Runs frames through the color transfer and the Merger in memory, then clips them.
+///
+/// # Parameters
+/// - `options`: The input frames, the stages to run, the audio and the output video.
+/// - `config`: Configuration providing the audio, frame rate and opacity defaults.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the chain.
+///
+/// # Notes
+/// - The frames go from stage to stage as decoded images instead of PNGs in a
+///   directory per stage; only the Clipper's input is written, into a temporary
+///   directory, see `chain::run_chain`.
+/// - The clip's manifest records that temporary directory as its input.
+fn run_chain(options: &ChainOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
+    validate_input(Modes::Clipper, &options.input)?;
+    let input_dir = PathBuf::from(&options.input);
+    let frames = load_frames(&input_dir)?;
+    let overlay = match &options.overlay {
+        Some(directory) => {
+            validate_input(Modes::Merger, directory)?;
+            let overlay_frames: Vec<PathBuf> =
+                load_frames(Path::new(directory))?.into_values().collect();
+            if overlay_frames.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Overlay directory {} has no frames",
+                    directory
+                ));
+            }
+            let opacity =
+                get_opacity(options.opacity, config).context("Failed to resolve opacity")?;
+            Some((overlay_frames, opacity))
+        }
+        None => None,
+    };
+
+    let mp3_path = get_audio_file(options.common_options.mp3.clone(), config)
+        .context("Failed to get audio file")?;
+    let mp3_path_str = mp3_path.as_ref().map(|p| p.to_string_lossy().into_owned());
+    let fps = get_fps(options.common_options.fps, config).context("Failed to resolve FPS")?;
+    let duration =
+        get_audio_duration(mp3_path_str.clone(), config).context("Failed to resolve duration")?;
+    // The Clipper would name its output after the temporary directory it reads.
+    let output = match &options.output {
+        Some(output) => PathBuf::from(output),
+        None => fxp_output::ClipperOutput.plan_output(
+            (input_dir.clone(), mp3_path, None, "mp4"),
+            global.collision_policy(),
+        )?,
+    };
+    let memory_bytes = options.stream_memory as usize * 1024 * 1024;
+
+    let mut stages = chain::ChainStages {
+        transfer: None,
+        overlay,
+    };
+    if global.dry_run {
+        let mut plan = fxp_output::Plan::new(Modes::Clipper)
+            .entry("input directory", input_dir.display())
+            .entry("frames", frames.len());
+        if let Some(reference) = &options.reference {
+            plan = plan.entry("color transfer from", reference);
+        }
+        if let Some((overlay_frames, opacity)) = &stages.overlay {
+            plan = plan
+                .entry("overlay frames", overlay_frames.len())
+                .entry("opacity", opacity);
+        }
+        plan = plan
+            .entry("stream memory", format!("{} MB", options.stream_memory))
+            .entry("fps", fps)
+            .entry("audio", mp3_path_str.as_deref().unwrap_or("none"))
+            .entry("output", output.display());
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    if let Some(reference) = &options.reference {
+        stages.transfer = Some(fxp_clutter::ColorTransfer::new(
+            Path::new(reference),
+            &frames,
+        )?);
+    }
+    let frames_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let spill_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let budget = fxp_stream::MemoryBudget::new(memory_bytes, spill_dir.path());
+    let chained = chain::run_chain(&frames, &stages, frames_dir.path(), &budget)
+        .context("Failed to run the chain")?;
+    debug!("Chained {} frames through {}", chained, stages.describe());
+
+    let mut clipper = fxp_clipper::Clipper::new(
+        frames_dir.path().to_string_lossy().into_owned(),
+        mp3_path_str,
+        Some(output.to_string_lossy().into_owned()),
+        fps,
+        duration,
+        fxp_clipper::ClipFormat::default(),
+        global.collision_policy(),
+    )?;
+    clipper.options.keep_temp = global.keep_temp();
+    clipper.clip()?;
+    Ok(())
+}
+
+/// Returns the audio onset the Exporter aligned a directory of frames to, if it did.
+fn exported_audio_onset(directory: &Path) -> Option<u64> {
+    let manifest = directory.join(fxp_output::MANIFEST_FILE_NAME);
+    if !manifest.is_file() {
+        return None;
+    }
+    fxp_output::RecordedRun::load(&manifest)
+        .ok()?
+        .parameter("audio onset")?
+        .parse()
+        .ok()
+}
+
+/// Maps the frames of a directory by their frame number.
+fn load_frames(directory: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let files: Vec<PathBuf> = std::fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    Ok(Modes::Clipper.load_files(&files)?)
+}
+
+/// Removes near-duplicate frames from a directory of images.
+///
+/// # Parameters
+/// - `options`: The input and output directories, the threshold and the duplicates directory.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the deduplication.
+///
+/// # Notes
+/// - The kept frames are renumbered without gaps, ready for the Clipper.
+fn run_dedup(options: &DedupOptions, global: &GlobalOptions) -> Result<()> {
+    let input_dir = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Dedup, input_dir)?;
+    let duplicates = options.duplicates.as_ref().map(PathBuf::from);
+
+    if global.dry_run {
+        let plan = fxp_dedup::Dedup::plan(
+            input_dir.clone(),
+            output,
+            options.threshold,
+            duplicates.as_deref(),
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut dedup = fxp_dedup::Dedup::new(input_dir.clone(), output, global.collision_policy())?;
+    dedup.in_place = global.in_place;
+    dedup.threshold = options.threshold;
+    dedup.duplicates_directory = duplicates;
+
+    let kept = dedup
+        .remove_duplicates()
+        .context("Failed to remove duplicate frames")?;
+    debug!("Dedup run completed successfully, kept {} frames", kept);
+    Ok(())
+}
+
+/// Interpolates a directory of frames or a video to a higher frame rate.
+///
+/// # Parameters
+/// - `options`: The input, the output, the frame rates and the engine.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the interpolation.
+///
+/// # Notes
+/// - The input may be a directory of frames, such as a Gmicer output, or a video.
+fn run_interpolator(options: &InterpolatorOptions, global: &GlobalOptions) -> Result<()> {
+    let input = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Interpolator, input)?;
+
+    if global.dry_run {
+        let plan = fxp_interpolator::Interpolator::plan(
+            input.clone(),
+            output,
+            options.fps,
+            options.source_fps,
+            &options.engine,
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut interpolator = fxp_interpolator::Interpolator::new(
+        input.clone(),
+        output,
+        options.fps,
+        global.collision_policy(),
+    )?;
+    interpolator.source_fps = options.source_fps;
+    interpolator.engine = options.engine.clone();
+    interpolator.in_place = global.in_place;
+    if Path::new(input).is_file() {
+        interpolator.duration_ms = media_duration(input)
+            .map_err(|e| debug!("Could not probe the length of {}: {:#}", input, e))
+            .ok();
+    }
+
+    let written = interpolator
+        .interpolate()
+        .context("Failed to interpolate the frames")?;
+    debug!(
+        "Interpolator run completed successfully, wrote {} frames",
+        written
+    );
+    Ok(())
+}
+
+/// Stabilizes a video, such as the source before exporting or the rendered clip.
+///
+/// # Parameters
+/// - `options`: The input video, the output and the stabilization settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the stabilization.
+///
+/// # Notes
+/// - Requires an ffmpeg built with libvidstab.
+fn run_stabilizer(options: &StabilizerOptions, global: &GlobalOptions) -> Result<()> {
+    let video = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Stabilizer, video)?;
+
+    if global.dry_run {
+        let plan = fxp_stabilizer::Stabilizer::plan(
+            video.clone(),
+            output,
+            options.shakiness,
+            options.smoothing,
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut stabilizer =
+        fxp_stabilizer::Stabilizer::new(video.clone(), output, global.collision_policy())?;
+    stabilizer.shakiness = options.shakiness;
+    stabilizer.smoothing = options.smoothing;
+
+    let stabilized = stabilizer
+        .stabilize()
+        .context("Failed to stabilize the video")?;
+    debug!("Stabilized video written to {:?}", stabilized);
+    Ok(())
+}
+
+/// Renders the audio as frames, to merge over the exported frames or clip on their own.
+///
+/// # Parameters
+/// - `options`: The input audio, the output and the rendering settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the rendering.
+///
+/// # Notes
+/// - Render at the fps the video was exported at, then run the Merger with the frames as
+///   its second directory; `--respect-alpha` keeps only the waves.
+fn run_visualizer(options: &VisualizerOptions, global: &GlobalOptions) -> Result<()> {
+    let audio = &options.io.input;
+    let output = options.io.output.clone();
+    validate_input(Modes::Visualizer, audio)?;
+
+    if global.dry_run {
+        let plan = fxp_visualizer::Visualizer::plan(
+            audio.clone(),
+            output,
+            options.fps,
+            options.style,
+            options.size,
+            &options.color,
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut visualizer = fxp_visualizer::Visualizer::new(
+        audio.clone(),
+        output,
+        options.fps,
+        global.collision_policy(),
+    )?;
+    visualizer.visualization = options.style;
+    visualizer.size = options.size;
+    visualizer.color = options.color.clone();
+    visualizer.in_place = global.in_place;
+    visualizer.duration_ms = media_duration(audio)
+        .map_err(|e| debug!("Could not probe the length of {}: {:#}", audio, e))
+        .ok();
+
+    let written = visualizer
+        .visualize()
+        .context("Failed to render the visualization")?;
+    debug!(
+        "Visualizer run completed successfully, wrote {} frames",
+        written
+    );
+    Ok(())
+}
+
+/// Tiles a directory of frames into a sprite sheet, or explodes a sheet into frames.
+///
+/// # Parameters
+/// - `options`: The input, the output, the direction and the layout of the sheet.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
+///
+/// # Returns
+/// - `Result<()>`: Indicates success or failure of the tiling or exploding.
+///
+/// # Notes
+/// - A sheet gets an atlas, `<sheet_stem>.json`, next to it; `--explode` reads it back,
+///   so a sheet this mode wrote explodes without `--columns` and `--rows`.
+fn run_zoopraxiscope(options: &ZoopraxiscopeOptions, global: &GlobalOptions) -> Result<()> {
+    let layout = fxp_zoopraxiscope::SheetLayout {
+        columns: options.columns,
+        rows: options.rows,
+        padding: options.padding,
+        background: options.background,
+    };
+
+    if global.dry_run {
+        let plan = fxp_zoopraxiscope::Zoopraxiscope::plan(
+            options.input.clone(),
+            options.output.clone(),
+            options.explode,
+            &layout,
+            global.collision_policy(),
+        )?;
+        print!("{}", plan);
+        return Ok(());
+    }
+
+    let mut zoopraxiscope = fxp_zoopraxiscope::Zoopraxiscope::new(
+        options.input.clone(),
+        options.output.clone(),
+        options.explode,
+        global.collision_policy(),
+    )?;
+    zoopraxiscope.layout = layout;
+    zoopraxiscope.in_place = global.in_place;
+
+    let frames = zoopraxiscope
+        .run()
+        .context("Failed to run the zoopraxiscope")?;
+    debug!(
+        "Zoopraxiscope run completed successfully, {} frames",
+        frames
+    );
+    Ok(())
+}
+
 /// Processes video and audio to generate samples according to specified parameters.
 ///
 /// This function manages the sampling process, including input validation, duration calculation,
@@ -490,6 +3328,7 @@ fn run_clutter(options: &ClutterOptions, _config: &Config) -> Result<()> {
 /// # Parameters
 /// - `options`: Contains input/output paths, duration, and sampling configuration.
 /// - `config`: Application-level settings that may override or extend options.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the sampling process.
@@ -498,12 +3337,16 @@ fn run_clutter(options: &ClutterOptions, _config: &Config) -> Result<()> {
 /// - Requires a valid video input path to proceed with sampling.
 /// - Supports interruptible operation through Ctrl+C handler.
 /// - Calculates appropriate duration and sampling number based on inputs.
-fn run_sampler(options: &SamplerOptions, config: &Config) -> Result<()> {
+fn run_sampler(options: &SamplerOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
     // Ensure an input path is provided.
-    let video_path = options.io.input.clone();
-    if video_path.is_empty() {
+    if options.io.input.is_empty() {
         return Err(anyhow::anyhow!("Video path must be provided."));
     }
+    if options.batch {
+        return run_batch(&options.io.input, global);
+    }
+    let video_path = resolve_video_url(&options.io.input, global.downloads())?;
+    validate_input(Modes::Sampler, &video_path)?;
 
     let output_dir = get_audio_dir(options.io.output.clone(), config)
         .context("Failed to resolve audio directory for sampler mode")?;
@@ -522,24 +3365,40 @@ fn run_sampler(options: &SamplerOptions, config: &Config) -> Result<()> {
     let sampling_number = get_sampling_number(options.multiple, options.number, config);
     debug!("Using resolved sampling number: {}", sampling_number);
 
+    if global.dry_run {
+        let plan = fxp_sampler::Sampler::plan(
+            video_path,
+            output_path,
+            duration,
+            sampling_number,
+            options.best_of as usize,
+            options.clip_length,
+            global.collision_policy(),
+        )?;
+        let plan = options.collage.plan(plan);
+        print!("{}", options.retry.plan(plan, config));
+        return Ok(());
+    }
+
     // Create sampler arguments.
-    let sampler_args =
-        fxp_sampler::Sampler::new(video_path, output_path, duration, sampling_number);
+    let mut sampler_args = fxp_sampler::Sampler::new(
+        video_path,
+        output_path,
+        duration,
+        sampling_number,
+        options.clip_length,
+        global.collision_policy(),
+    )?;
+    sampler_args.best_of = options.best_of as usize;
+    sampler_args.retry = options.retry.policy(config);
+    sampler_args.collage = options.collage.collage();
     debug!("Sampler CLI Arguments: {:?}", sampler_args);
 
-    // Set up a Ctrl+C handler.
-    let running = Arc::new(AtomicBool::new(true));
-    {
-        let running_clone = running.clone();
-        ctrlc::set_handler(move || {
-            eprintln!("\nReceived Ctrl+C, terminating...");
-            running_clone.store(false, Ordering::SeqCst);
-        })
-        .context("Error setting Ctrl+C handler")?;
-    }
+    // Cleared by Ctrl+C or SIGTERM.
+    let running = running_flag()?;
 
     // Execute the sampling process.
-    sampler_args?
+    sampler_args
         .sample_images(running)
         .context("An error occurred during sample image processing")?;
 
@@ -554,6 +3413,7 @@ fn run_sampler(options: &SamplerOptions, config: &Config) -> Result<()> {
 /// # Parameters
 /// - `options`: An `ExporterOptions` instance containing exporter-specific settings.
 /// - `config`: A `Config` instance providing global configuration settings.
+/// - `global`: Options shared by every mode, such as `--dry-run`.
 ///
 /// # Returns
 /// - `Result<()>`: Indicates success or failure of the export operation.
@@ -561,26 +3421,65 @@ fn run_sampler(options: &SamplerOptions, config: &Config) -> Result<()> {
 /// # Notes
 /// - Manages input/output paths, video duration, FPS calculation, and pixel limits.
 /// - Creates and executes the exporter instance with calculated parameters.
-fn run_exporter(options: &ExporterOptions, config: &Config) -> Result<()> {
-    // Use the new IO field for input/output
-    let video_path = &options.io.input;
+fn run_exporter(options: &ExporterOptions, config: &Config, global: &GlobalOptions) -> Result<()> {
+    if options.batch {
+        let [directory] = options.io.input.as_slice() else {
+            anyhow::bail!("--batch takes a single directory of videos as -i");
+        };
+        return run_batch(directory, global);
+    }
+
+    // Playlists stand for the videos they list, in place.
+    let mut videos = Vec::new();
+    for input in &options.io.input {
+        if fxp_exporter::is_playlist(Path::new(input)) {
+            let listed = fxp_exporter::read_playlist(Path::new(input))?;
+            videos.extend(listed.iter().map(|video| video.display().to_string()));
+        } else {
+            videos.push(resolve_video_url(input, global.downloads())?);
+        }
+    }
+    for video in &videos {
+        validate_input(Modes::Exporter, video)?;
+    }
+    let video_path = &videos[0];
     let output_path = &options.io.output;
-    debug!("Video path: {}", video_path);
+    debug!("Video paths: {:?}", videos);
     debug!("Output path: {:?}", output_path);
 
     let mp3_path = options.common.mp3.clone();
     let duration_arg = options.common.duration.clone();
 
-    let duration = get_duration(video_path, mp3_path, duration_arg, config)
+    let duration_from_audio = mp3_path.is_some() && duration_arg.is_none();
+    let duration = get_sequence_duration(&videos, mp3_path.clone(), duration_arg, config)
         .context("Failed to resolve duration")?;
     debug!("Final duration to use: {} milliseconds", duration);
 
-    let cli_fps = options
-        .common
-        .fps
-        .clone()
-        .map(|s| s.parse::<u32>().context("Invalid FPS value"))
-        .transpose()?;
+    // Frame 1 plays on the musical start once the Clipper skips the audio before it, so
+    // only the audio after the onset needs frames.
+    let audio_onset_ms = match (&mp3_path, options.align_to_audio_onset) {
+        (Some(mp3), true) => {
+            let onset = fxp_audio::find_onset(Path::new(mp3))?
+                .ok_or_else(|| anyhow::anyhow!("No audio onset found, {} is silent", mp3))?;
+            debug!("Aligning frame 1 to the audio onset at {} ms", onset);
+            Some(onset)
+        }
+        _ => None,
+    };
+    let duration = match audio_onset_ms {
+        Some(onset) if duration_from_audio => {
+            if onset >= duration {
+                return Err(anyhow::anyhow!(
+                    "The audio onset at {} ms leaves no audio to export",
+                    onset
+                ));
+            }
+            duration - onset
+        }
+        _ => duration,
+    };
+
+    let cli_fps = options.common.fps;
     let fps = get_fps(cli_fps, config).context("Failed to resolve FPS")?;
     debug!("Resolved FPS value: {}", fps);
 
@@ -592,13 +3491,56 @@ fn run_exporter(options: &ExporterOptions, config: &Config) -> Result<()> {
     });
     debug!("Resolved pixel upper limit: {}", pixel_upper_limit);
 
-    let exporter = fxp_exporter::Exporter::new(
+    // A preview export goes through the same path, only shorter and smaller.
+    let (duration, pixel_upper_limit) = match options.preview_seconds {
+        Some(seconds) => (
+            duration.min(seconds as u64 * 1000),
+            get_preview_pixel_limit(pixel_upper_limit),
+        ),
+        None => (duration, pixel_upper_limit),
+    };
+    debug!(
+        "Exporting {} milliseconds at pixel limit {}",
+        duration, pixel_upper_limit
+    );
+
+    let export_options = fxp_exporter::ExportOptions {
+        force: options.force,
+        in_place: global.in_place,
+        start_ms: options.start,
+        keep_temp: global.keep_temp(),
+        burn_in: options.burn_in,
+        more_videos: videos[1..].iter().map(PathBuf::from).collect(),
+        audio_onset_ms,
+        retry: options.retry.policy(config),
+        frames_json: options.frames_json,
+        high_bit_depth: options.high_bit_depth,
+    };
+    debug!("Export options: {:?}", export_options);
+
+    if global.dry_run {
+        let plan = fxp_exporter::Exporter::plan(
+            video_path.to_string(),
+            output_path.clone(),
+            duration,
+            fps,
+            pixel_upper_limit,
+            &export_options,
+            global.collision_policy(),
+        )?;
+        print!("{}", options.retry.plan(plan, config));
+        return Ok(());
+    }
+
+    let mut exporter = fxp_exporter::Exporter::new(
         video_path.to_string(),
         output_path.clone(),
         duration,
         fps,
         pixel_upper_limit,
+        global.collision_policy(),
     )?;
+    exporter.options = export_options;
     exporter.export_images()?;
     debug!("Finished running exporter: {:?}", exporter);
 
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use log::{debug, warn};
+use notify_rust::Notification;
+use std::time::Duration;
+
+use crate::hooks::RunSummary;
+
+/// Title of every notification.
+const TITLE: &str = "fxp_videoclipper";
+
+/// Announces the end of a run on the desktop, for `--notify`.
+///
+/// # Parameters
+/// - `summary`: The summary of the run, also given to the hooks.
+/// - `threshold`: How long the run must have taken to be announced, so quick runs,
+///   finished before the terminal was left, stay quiet.
+///
+/// # Notes
+/// - The notification is sent through the desktop's notification service, see
+///   `notify_rust`.
+/// - Failing to notify is reported as a warning and does not fail the run.
+pub fn notify_finished(summary: &RunSummary, threshold: Duration) {
+    let elapsed = Duration::from_secs_f64(summary.elapsed_seconds);
+    if elapsed < threshold {
+        debug!(
+            "Not notifying, the run took {:.1}s, less than {}s",
+            elapsed.as_secs_f64(),
+            threshold.as_secs()
+        );
+        return;
+    }
+    let message = match &summary.error {
+        None => {
+            let mut message = format!("{} finished in {}", summary.mode, clock(elapsed));
+            if let Some(output) = summary.outputs.last() {
+                message.push_str(&format!(": {}", output.output.display()));
+            }
+            message
+        }
+        Some(error) => format!(
+            "{} failed after {}: {}",
+            summary.mode,
+            clock(elapsed),
+            error.lines().next().unwrap_or_default()
+        ),
+    };
+    if let Err(e) = send(&message) {
+        warn!("Failed to send the desktop notification: {:#}", e);
+    }
+}
+
+/// Sends a notification to the desktop.
+fn send(message: &str) -> Result<()> {
+    debug!("Notifying: {}", message);
+    Notification::new()
+        .appname(TITLE)
+        .summary(TITLE)
+        .body(message)
+        .show()?;
+    Ok(())
+}
+
+/// Formats a duration as `1h 02m 03s`, `2m 03s` or `3s`.
+fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use fxp_filenames::FileOperations;
+use fxp_init::{media_info, MediaInfo};
+use fxp_modes::Modes;
+
+/// How `probe` prints its report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProbeFormat {
+    /// Aligned `label : value` lines.
+    #[default]
+    Text,
+    /// One JSON object.
+    Json,
+}
+
+impl FromStr for ProbeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(ProbeFormat::Text),
+            "json" => Ok(ProbeFormat::Json),
+            other => Err(format!(
+                "Unknown probe format '{}', expected one of: text, json",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProbeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProbeFormat::Text => "text",
+            ProbeFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What `probe` found in a directory of frames.
+#[derive(Debug, Clone, Serialize)]
+pub struct FramesInfo {
+    /// Number of frames, as the modes number them.
+    pub count: usize,
+    pub first_index: Option<u32>,
+    pub last_index: Option<u32>,
+    /// Runs of indices missing between the first and last frame, as `[first, last]`.
+    pub gaps: Vec<[u32; 2]>,
+    /// Each resolution found, with the number of frames that have it, most common first.
+    pub resolutions: Vec<Resolution>,
+    /// Frames whose size could not be read, e.g. files that are not images.
+    pub unreadable: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+    pub frames: usize,
+}
+
+/// The report of `probe` for a file or a directory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ProbeReport {
+    Media { path: PathBuf, info: MediaInfo },
+    Frames { path: PathBuf, info: FramesInfo },
+}
+
+/// Reports the media information of a file, or the frames of a directory.
+///
+/// # Parameters
+/// - `path`: A video or audio file, or a directory of frames.
+///
+/// # Returns
+/// - `Result<ProbeReport>`: The report, or an error if the path does not exist or
+///   ffprobe cannot read the file.
+///
+/// # Notes
+/// - Files are read with ffprobe, see `fxp_init::media_info`.
+/// - Directory frames are numbered as the modes number them, see
+///   `fxp_filenames::FileOperations`; only the image headers are read for their size.
+pub fn probe(path: &Path) -> Result<ProbeReport> {
+    if path.is_dir() {
+        Ok(ProbeReport::Frames {
+            path: path.to_path_buf(),
+            info: probe_frames(path)?,
+        })
+    } else if path.is_file() {
+        let info = media_info(&path.to_string_lossy())?;
+        Ok(ProbeReport::Media {
+            path: path.to_path_buf(),
+            info,
+        })
+    } else {
+        bail!("Nothing to probe at {}", path.display());
+    }
+}
+
+/// Numbers the frames of a directory and checks their continuity and sizes.
+fn probe_frames(directory: &Path) -> Result<FramesInfo> {
+    let files: Vec<PathBuf> = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    let frames: BTreeMap<u32, PathBuf> = Modes::Clipper
+        .load_files(&files)
+        .with_context(|| format!("Failed to number the frames in {}", directory.display()))?;
+
+    let mut gaps = Vec::new();
+    let mut previous: Option<u32> = None;
+    for &index in frames.keys() {
+        if let Some(previous) = previous {
+            if index > previous + 1 {
+                gaps.push([previous + 1, index - 1]);
+            }
+        }
+        previous = Some(index);
+    }
+
+    let mut sizes: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+    let mut unreadable = Vec::new();
+    for path in frames.values() {
+        match image::image_dimensions(path) {
+            Ok(size) => *sizes.entry(size).or_default() += 1,
+            Err(_) => unreadable.push(path.clone()),
+        }
+    }
+    let mut resolutions: Vec<Resolution> = sizes
+        .into_iter()
+        .map(|((width, height), frames)| Resolution {
+            width,
+            height,
+            frames,
+        })
+        .collect();
+    resolutions.sort_by_key(|resolution| std::cmp::Reverse(resolution.frames));
+
+    Ok(FramesInfo {
+        count: frames.len(),
+        first_index: frames.keys().next().copied(),
+        last_index: frames.keys().next_back().copied(),
+        gaps,
+        resolutions,
+        unreadable,
+    })
+}
+
+impl ProbeReport {
+    /// Renders the report in the requested format.
+    pub fn render(&self, format: ProbeFormat) -> Result<String> {
+        match format {
+            ProbeFormat::Text => Ok(self.to_string()),
+            ProbeFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .context("Failed to serialize probe report"),
+        }
+    }
+
+    /// Returns the labelled lines of the text report.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = Vec::new();
+        match self {
+            ProbeReport::Media { info, .. } => {
+                if let Some(format) = &info.format {
+                    entries.push(("format", format.clone()));
+                }
+                if let Some(duration_ms) = info.duration_ms {
+                    entries.push(("duration", format!("{} ms", duration_ms)));
+                }
+                match &info.video {
+                    Some(video) => {
+                        entries.push(("video codec", video.codec.clone()));
+                        entries.push(("resolution", format!("{}x{}", video.width, video.height)));
+                        if let Some(fps) = video.fps {
+                            entries.push(("fps", fps.to_string()));
+                        }
+                        if let Some(frames) = video.frames {
+                            entries.push(("frames", frames.to_string()));
+                        }
+                    }
+                    None => entries.push(("video", "none".to_string())),
+                }
+                match &info.audio {
+                    Some(audio) => {
+                        let mut description = audio.codec.clone();
+                        if let Some(rate) = audio.sample_rate {
+                            description.push_str(&format!(", {} Hz", rate));
+                        }
+                        if let Some(channels) = audio.channels {
+                            description.push_str(&format!(", {} channels", channels));
+                        }
+                        entries.push(("audio", description));
+                    }
+                    None => entries.push(("audio", "none".to_string())),
+                }
+            }
+            ProbeReport::Frames { info, .. } => {
+                entries.push(("frames", info.count.to_string()));
+                if let (Some(first), Some(last)) = (info.first_index, info.last_index) {
+                    entries.push(("indices", format!("{} to {}", first, last)));
+                }
+                let continuity = if info.gaps.is_empty() {
+                    "continuous".to_string()
+                } else {
+                    let missing: u32 = info.gaps.iter().map(|[a, b]| b - a + 1).sum();
+                    let runs: Vec<String> = info
+                        .gaps
+                        .iter()
+                        .map(|[a, b]| {
+                            if a == b {
+                                a.to_string()
+                            } else {
+                                format!("{}-{}", a, b)
+                            }
+                        })
+                        .collect();
+                    format!("{} missing: {}", missing, runs.join(", "))
+                };
+                entries.push(("numbering", continuity));
+                let resolutions = match info.resolutions.as_slice() {
+                    [] => "unknown".to_string(),
+                    [only] => format!("{}x{} (all frames)", only.width, only.height),
+                    several => several
+                        .iter()
+                        .map(|r| format!("{}x{} ({} frames)", r.width, r.height, r.frames))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+                entries.push(("resolution", resolutions));
+                if !info.unreadable.is_empty() {
+                    entries.push(("unreadable", info.unreadable.len().to_string()));
+                }
+            }
+        }
+        entries
+    }
+}
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self {
+            ProbeReport::Media { path, .. } | ProbeReport::Frames { path, .. } => path,
+        };
+        writeln!(f, "{}", path.display())?;
+
+        // Align all values on the longest label, as a dry-run plan does.
+        let entries = self.entries();
+        let width = entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "  {:<width$} : {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
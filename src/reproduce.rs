@@ -0,0 +1,436 @@
+use anyhow::{anyhow, bail, Result};
+use log::{debug, warn};
+use std::path::Path;
+
+use fxp_modes::{Capabilities, Modes};
+use fxp_output::{manifest_output, RecordedRun};
+
+/// Rebuilds the command line of the run recorded in a manifest.
+///
+/// # Parameters
+/// - `manifest_path`: Path to the `manifest.json` written by the run.
+/// - `allow_changed`: Reproduce even if some recorded inputs changed or disappeared.
+///
+/// # Returns
+/// - `Result<Vec<String>>`: The arguments of the recorded mode, starting with the program
+///   name, writing to the recorded output; or an error if an input changed and
+///   `allow_changed` is not set.
+///
+/// # Notes
+/// - Relative paths are resolved against the directory the run was started from.
+/// - Parameters derived from the audio, such as the Exporter's duration, are passed as
+///   the resolved values so the reproduction does not depend on the audio again.
+/// - A recorded seed is passed with `--seed`, so random effects come out the same.
+pub fn reproduce_args(manifest_path: &Path, allow_changed: bool) -> Result<Vec<String>> {
+    let run = RecordedRun::load(manifest_path)?;
+    debug!(
+        "Reproducing {} run recorded by version {}",
+        run.mode, run.version
+    );
+
+    let changed = run.changed_inputs();
+    if !changed.is_empty() {
+        let report = changed.join("\n  ");
+        if !allow_changed {
+            bail!(
+                "{} of {} recorded inputs changed since the run:\n  {}\nUse --allow-changed to reproduce anyway",
+                changed.len(),
+                run.inputs.len(),
+                report
+            );
+        }
+        warn!("Reproducing with changed inputs:\n  {}", report);
+    }
+
+    let output = manifest_output(manifest_path);
+    let path =
+        |name: &str| -> Result<String> { Ok(run.path_parameter(name)?.display().to_string()) };
+    let value = |name: &str| -> Result<String> { Ok(run.required_parameter(name)?.to_string()) };
+
+    let mode = Modes::ALL
+        .into_iter()
+        .find(|mode| format!("{:?}", mode) == run.mode)
+        .ok_or_else(|| anyhow!("Run manifest records an unknown mode '{}'", run.mode))?;
+
+    let mut args: Vec<String> = vec!["fxp_videoclipper".into(), mode.name().into()];
+    match mode {
+        Modes::Exporter => {
+            args.extend([
+                "-i".into(),
+                path("video")?,
+                "-d".into(),
+                value("duration")?,
+                "-f".into(),
+                value("fps")?,
+                "-p".into(),
+                value("pixel upper limit")?,
+            ]);
+            if let Ok(more_videos) = run.parameter_list("more videos") {
+                for video in more_videos {
+                    let video = Path::new(&run.working_directory).join(video);
+                    args.extend(["-i".into(), video.display().to_string()]);
+                }
+            }
+            if let Some(start) = run.parameter("start") {
+                args.extend(["--start".into(), start.to_string()]);
+            }
+            if let Some(burn_in) = run.parameter("burn-in") {
+                args.extend(["--burn-in".into(), burn_in.to_string()]);
+            }
+            if run.parameter("frames json") == Some("true") {
+                args.push("--frames-json".into());
+            }
+            if run.parameter("high bit depth") == Some("true") {
+                args.push("--high-bit-depth".into());
+            }
+        }
+        Modes::Sampler => {
+            args.extend([
+                "-i".into(),
+                path("video")?,
+                "-d".into(),
+                value("duration")?,
+                "-n".into(),
+                value("sampling number")?,
+            ]);
+            if let Some(best_of) = run.parameter("best of") {
+                args.extend(["--best-of".into(), best_of.to_string()]);
+            }
+            if let Some(clip_length) = run.parameter("clip length") {
+                args.extend(["--clip-length".into(), clip_length.to_string()]);
+            }
+            if run.parameter("collage") == Some("true") {
+                args.push("--collage".into());
+            }
+            if let Some(columns) = run.parameter("collage columns") {
+                args.extend(["--collage-columns".into(), columns.to_string()]);
+            }
+            if run.parameter("collage labels") == Some("false") {
+                args.push("--no-collage-labels".into());
+            }
+        }
+        Modes::Merger => {
+            let mut layers = vec![path("second directory")?];
+            if let Ok(more_directories) = run.parameter_list("more directories") {
+                for directory in more_directories {
+                    let directory = Path::new(&run.working_directory).join(directory);
+                    layers.push(directory.display().to_string());
+                }
+            }
+            if let Ok(styles) = run.parameter_list("layer styles") {
+                for (layer, style) in layers.iter_mut().zip(styles) {
+                    if !style.is_empty() {
+                        *layer = format!("{}@{}", layer, style);
+                    }
+                }
+            }
+            args.extend(["-i".into(), path("first directory")?]);
+            for layer in layers {
+                args.extend(["-r".into(), layer]);
+            }
+            args.extend([
+                "-t".into(),
+                value("opacity")?,
+                "--mismatch-policy".into(),
+                value("mismatch policy")?,
+            ]);
+            if run.parameter("recursive") == Some("true") {
+                args.push("--recursive".into());
+            }
+            if run.parameter("respect alpha") == Some("true") {
+                args.push("--respect-alpha".into());
+            }
+            if run.parameter("linear blend") == Some("true") {
+                args.push("--linear-blend".into());
+            }
+            if let Some(region) = run.parameter("region") {
+                args.extend(["--region".into(), region.to_string()]);
+            }
+            if let Some(region) = run.parameter("region circle") {
+                args.extend(["--region-circle".into(), region.to_string()]);
+            }
+            if let Some(feather) = run.parameter("region feather") {
+                args.extend(["--region-feather".into(), feather.to_string()]);
+            }
+            if let Some(scale) = run.parameter("pip") {
+                args.extend([
+                    "--pip".into(),
+                    scale.to_string(),
+                    "--pip-position".into(),
+                    value("pip position")?,
+                    "--pip-margin".into(),
+                    value("pip margin")?,
+                ]);
+                if let Some(border) = run.parameter("pip border") {
+                    args.extend(["--pip-border".into(), border.to_string()]);
+                }
+                if run.parameter("pip shadow") == Some("true") {
+                    args.push("--pip-shadow".into());
+                }
+            }
+            if run.parameter("audio opacity").is_some() {
+                args.extend([
+                    "--audio-opacity".into(),
+                    path("audio opacity")?,
+                    "--opacity-range".into(),
+                    value("opacity range")?,
+                    "-f".into(),
+                    value("audio fps")?,
+                ]);
+            }
+            push_selection(&mut args, &run);
+            push_encoding(&mut args, &run);
+        }
+        Modes::Clutter => {
+            args.extend(["-i".into(), path("input")?]);
+            if run.parameter("reference image").is_some() {
+                args.extend(["--reference".into(), path("reference image")?]);
+            } else {
+                args.extend(["-l".into(), path("clut image")?]);
+            }
+            if let Some(opacity) = run.parameter("opacity") {
+                args.extend(["--clut-opacity".into(), opacity.to_string()]);
+            }
+            push_encoding(&mut args, &run);
+        }
+        Modes::Grader => {
+            args.extend(["-i".into(), path("input")?]);
+            for control in ["exposure", "contrast", "saturation", "temperature"] {
+                if let Some(keyframes) = run.parameter(control) {
+                    args.push(format!("--{}={}", control, keyframes));
+                }
+            }
+            push_encoding(&mut args, &run);
+        }
+        Modes::Captioner => {
+            args.extend([
+                "-i".into(),
+                path("input")?,
+                "--captions".into(),
+                path("captions")?,
+            ]);
+            if run.parameter("font").is_some() {
+                args.extend(["--font".into(), path("font")?]);
+            }
+            for (name, flag) in [
+                ("font size", "--font-size"),
+                ("text color", "--text-color"),
+                ("outline", "--outline"),
+                ("text position", "--text-position"),
+                ("text margin", "--text-margin"),
+            ] {
+                if let Some(value) = run.parameter(name) {
+                    args.extend([flag.into(), value.to_string()]);
+                }
+            }
+            push_encoding(&mut args, &run);
+        }
+        // A processor of the configuration is run by name, as the configuration holds it.
+        Modes::Processor => {
+            args.extend(["-i".into(), path("input")?]);
+            match value("processor")?.as_str() {
+                "cmd" => args.extend(["--cmd".into(), value("command")?]),
+                name => args.extend(["--processor".into(), name.to_string()]),
+            }
+            push_selection(&mut args, &run);
+        }
+        Modes::Dedup => {
+            args.extend([
+                "-i".into(),
+                path("input")?,
+                "--threshold".into(),
+                value("threshold")?,
+            ]);
+            if run.parameter("duplicates").is_some() {
+                args.extend(["--duplicates".into(), path("duplicates")?]);
+            }
+        }
+        Modes::Interpolator => args.extend([
+            "-i".into(),
+            path("input")?,
+            "-f".into(),
+            value("fps")?,
+            "--source-fps".into(),
+            value("source fps")?,
+            "--engine".into(),
+            value("engine")?,
+        ]),
+        Modes::Stabilizer => args.extend([
+            "-i".into(),
+            path("video")?,
+            "--shakiness".into(),
+            value("shakiness")?,
+            "--smoothing".into(),
+            value("smoothing")?,
+        ]),
+        Modes::Visualizer => {
+            args.extend([
+                "-i".into(),
+                path("audio")?,
+                "-f".into(),
+                value("fps")?,
+                "--style".into(),
+                value("visualization")?,
+                "--size".into(),
+                value("size")?,
+            ]);
+            if run.parameter("color").is_some() {
+                args.extend(["--color".into(), value("color")?]);
+            }
+        }
+        Modes::Zoopraxiscope => {
+            args.extend([
+                "-i".into(),
+                path("input")?,
+                "--padding".into(),
+                value("padding")?,
+                "--background".into(),
+                value("background")?,
+            ]);
+            if run.parameter("explode") == Some("true") {
+                args.push("--explode".into());
+            }
+            if let Some(columns) = run.parameter("columns") {
+                args.extend(["--columns".into(), columns.to_string()]);
+            }
+            if let Some(rows) = run.parameter("rows") {
+                args.extend(["--rows".into(), rows.to_string()]);
+            }
+        }
+        Modes::Clipper => {
+            match run.parameter_list("segments") {
+                Ok(segments) => {
+                    for segment in segments {
+                        let segment = Path::new(&run.working_directory).join(segment);
+                        args.extend(["-i".into(), segment.display().to_string()]);
+                    }
+                }
+                Err(_) => args.extend(["-i".into(), path("input")?]),
+            }
+            args.extend([
+                "-f".into(),
+                value("fps")?,
+                "--gaps".into(),
+                value("gap policy")?,
+            ]);
+            if run.parameter("mp3").is_some() {
+                args.extend(["-a".into(), path("mp3")?]);
+            }
+            if let Some(seconds) = run.parameter("preview seconds") {
+                args.extend(["--preview-seconds".into(), seconds.to_string()]);
+            }
+            if run.parameter("reverse") == Some("true") {
+                args.push("--reverse".into());
+            }
+            if run.parameter("pingpong") == Some("true") {
+                args.push("--pingpong".into());
+            }
+            if run.parameter("fit audio") == Some("true") {
+                args.push("--fit-audio".into());
+            }
+            if run.parameter("trim audio") == Some("true") {
+                args.push("--trim-audio".into());
+            }
+            if run.parameter("pad frames") == Some("true") {
+                args.push("--pad-frames".into());
+            }
+            if let Some(offset) = run.parameter("audio offset") {
+                args.push(format!("--audio-offset={}", offset));
+            }
+            if let Some(loudness) = run.parameter("loudness") {
+                args.push(format!("--normalize-audio={}", loudness));
+            }
+            if let Some(width) = run.parameter("max width") {
+                args.extend(["--max-width".into(), width.to_string()]);
+            }
+            if let Some(codec) = run.parameter("codec") {
+                args.extend(["--codec".into(), codec.to_string()]);
+            }
+            for (parameter, flag) in [
+                ("crf", "--crf"),
+                ("preset", "--preset"),
+                ("bitrate", "--bitrate"),
+            ] {
+                if let Some(value) = run.parameter(parameter) {
+                    args.extend([flag.into(), value.to_string()]);
+                }
+            }
+            if run.parameter("two pass") == Some("true") {
+                args.push("--two-pass".into());
+            }
+            if run.parameter("ten bit") == Some("true") {
+                args.push("--ten-bit".into());
+            }
+            if run.parameter("hdr source").is_some() {
+                args.extend(["--hdr-from".into(), path("hdr source")?]);
+            }
+            if run.parameter("auto fix") == Some("true") {
+                args.push("--auto-fix".into());
+            }
+            if run.parameter("chapters").is_some() {
+                args.extend(["--chapters".into(), path("chapters")?]);
+            }
+            if run.parameter("webvtt") == Some("true") {
+                args.push("--webvtt".into());
+            }
+            if run.parameter("source timing").is_some() {
+                args.push(format!("--source-timing={}", path("source timing")?));
+            }
+            if let Some(segment) = run.parameter("segment") {
+                args.extend(["--segment".into(), segment.to_string()]);
+            }
+            if let Some(format) = run.parameter("format") {
+                args.extend([
+                    "--format".into(),
+                    format.to_string(),
+                    "--loop-count".into(),
+                    value("loop count")?,
+                ]);
+            }
+            push_selection(&mut args, &run);
+        }
+        // The GMIC arguments are positional, so they go last, after `--`.
+        Modes::Gmicer => {
+            args.extend(["-i".into(), path("input")?]);
+            if run.parameter("recursive") == Some("true") {
+                args.push("--recursive".into());
+            }
+            push_selection(&mut args, &run);
+        }
+    }
+    args.extend(["-o".into(), output.display().to_string()]);
+    if let Some(seed) = run.parameter("seed") {
+        args.extend(["--seed".into(), seed.to_string()]);
+    }
+    if mode == Modes::Gmicer {
+        args.push("--".into());
+        args.extend(run.parameter_list("gmic arguments")?);
+    }
+
+    debug!("Reproduction arguments: {:?}", args);
+    Ok(args)
+}
+
+/// Adds the `--frames` and `--every` arguments of a run that processed a subset of frames.
+fn push_selection(args: &mut Vec<String>, run: &RecordedRun) {
+    if let Some(frames) = run.parameter("frames") {
+        args.extend(["--frames".into(), frames.to_string()]);
+    }
+    if let Some(every) = run.parameter("every") {
+        args.extend(["--every".into(), every.to_string()]);
+    }
+}
+
+/// Adds the `--output-format`, `--quality` and `--lossless` arguments of a run that wrote
+/// its frames in a chosen format.
+fn push_encoding(args: &mut Vec<String>, run: &RecordedRun) {
+    let Some(format) = run.parameter("output format") else {
+        return;
+    };
+    args.extend(["--output-format".into(), format.to_string()]);
+    if run.parameter("lossless") == Some("true") {
+        args.push("--lossless".into());
+    } else if let Some(quality) = run.parameter("quality") {
+        args.extend(["--quality".into(), quality.to_string()]);
+    }
+}